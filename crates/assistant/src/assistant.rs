@@ -3,6 +3,7 @@
 pub mod assistant_panel;
 pub mod assistant_settings;
 mod context;
+mod context_server_sampling;
 pub mod context_store;
 mod inline_assistant;
 mod model_selector;
@@ -13,17 +14,18 @@ pub(crate) mod slash_command_picker;
 pub mod slash_command_settings;
 mod streaming_diff;
 mod terminal_inline_assistant;
+mod token_usage;
 mod tools;
 mod workflow;
 
 pub use assistant_panel::{AssistantPanel, AssistantPanelEvent};
 use assistant_settings::AssistantSettings;
 use assistant_slash_command::SlashCommandRegistry;
-use assistant_tool::ToolRegistry;
+use assistant_tool::{Tool as _, ToolRegistry};
 use client::{proto, Client};
 use command_palette_hooks::CommandPaletteFilter;
 pub use context::*;
-use context_servers::ContextServerRegistry;
+use context_servers::{manager::ContextServer, protocol::ServerCapability, ContextServerRegistry};
 pub use context_store::*;
 use feature_flags::FeatureFlagAppExt;
 use fs::Fs;
@@ -42,8 +44,8 @@ use serde::{Deserialize, Serialize};
 use settings::{update_settings_file, Settings, SettingsStore};
 use slash_command::{
     auto_command, context_server_command, default_command, diagnostics_command, docs_command,
-    fetch_command, file_command, now_command, project_command, prompt_command, search_command,
-    symbols_command, tab_command, terminal_command, workflow_command,
+    fetch_command, file_command, now_command, project_command, prompt_command, resource_command,
+    search_command, symbols_command, tab_command, terminal_command, workflow_command,
 };
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -59,6 +61,8 @@ actions!(
         Assist,
         Split,
         CycleMessageRole,
+        ForkConversation,
+        RegenerateResponse,
         QuoteSelection,
         InsertIntoEditor,
         ToggleFocus,
@@ -68,6 +72,10 @@ actions!(
         ConfirmCommand,
         NewContext,
         ToggleModelSelector,
+        ShowTokenUsage,
+        ExportContextAsMarkdown,
+        ExportContextAsJson,
+        ImportContext,
     ]
 );
 
@@ -224,6 +232,7 @@ pub fn init(
 
     context_store::init(&client.clone().into());
     prompt_library::init(cx);
+    token_usage::init(cx);
     init_language_model_settings(cx);
     assistant_slash_command::init(cx);
     assistant_tool::init(cx);
@@ -278,6 +287,9 @@ pub fn init(
 }
 
 fn register_context_server_handlers(cx: &mut AppContext) {
+    context_servers::manager::ContextServerManager::global(cx).update(cx, |manager, _| {
+        manager.set_sampling_handler(Arc::new(context_server_sampling::create_message));
+    });
     cx.subscribe(
         &context_servers::manager::ContextServerManager::global(cx),
         |manager, event, cx| match event {
@@ -288,6 +300,16 @@ fn register_context_server_handlers(cx: &mut AppContext) {
                         let slash_command_registry = SlashCommandRegistry::global(cx);
                         let context_server_registry = ContextServerRegistry::global(cx);
                         if let Some(server) = manager.get_server(server_id) {
+                            if let Some(protocol) = server.client.read().clone() {
+                                if protocol.capable(ServerCapability::Resources) {
+                                    slash_command_registry.register_command(
+                                        resource_command::ResourceSlashCommand,
+                                        true,
+                                    );
+                                }
+                            }
+
+                            register_context_server_tools(server.clone(), cx);
                             cx.spawn(|_, _| async move {
                                 let Some(protocol) = server.client.read().clone() else {
                                     return;
@@ -329,12 +351,67 @@ fn register_context_server_handlers(cx: &mut AppContext) {
                         context_server_registry.unregister_command(&server_id, &command_name);
                     }
                 }
+                unregister_context_server_tools(server_id, cx);
+                if !resource_command::ResourceSlashCommand::is_available(cx) {
+                    slash_command_registry
+                        .unregister_command(resource_command::ResourceSlashCommand);
+                }
+            }
+            context_servers::manager::Event::ToolsChanged { server_id } => {
+                unregister_context_server_tools(server_id, cx);
+                if let Some(server) = manager.read(cx).get_server(server_id) {
+                    register_context_server_tools(server, cx);
+                }
             }
+            context_servers::manager::Event::ResourceUpdated { .. } => {}
         },
     )
     .detach();
 }
 
+/// Registers the tools of a context server, so the model can use them.
+fn register_context_server_tools(server: Arc<ContextServer>, cx: &mut AppContext) {
+    let tool_registry = ToolRegistry::global(cx);
+    let context_server_registry = ContextServerRegistry::global(cx);
+    cx.spawn(|_| async move {
+        let Some(protocol) = server.client.read().clone() else {
+            return;
+        };
+        if !protocol.capable(ServerCapability::Tools) {
+            return;
+        }
+
+        if let Some(tools) = protocol.list_tools().await.log_err() {
+            for tool in tools {
+                let tool =
+                    tools::context_server_tool::ContextServerTool::new(server.id.clone(), tool);
+                // Tools are namespaced by their server, so they can't replace built-in tools
+                // or inherit their approval policy, but a tool can still clash with another.
+                let tool_name = tool.name();
+                if tool_registry.tool(&tool_name).is_some() {
+                    log::warn!("skipping context server tool {tool_name:?}: name already in use");
+                    continue;
+                }
+                log::info!("registering context server tool: {:?}", tool_name);
+                context_server_registry.register_tool(server.id.clone(), &tool_name);
+                tool_registry.register_tool(tool);
+            }
+        }
+    })
+    .detach();
+}
+
+fn unregister_context_server_tools(server_id: &str, cx: &mut AppContext) {
+    let tool_registry = ToolRegistry::global(cx);
+    let context_server_registry = ContextServerRegistry::global(cx);
+    if let Some(tool_names) = context_server_registry.get_tools(server_id) {
+        for tool_name in tool_names {
+            tool_registry.unregister_tool_by_name(&tool_name);
+            context_server_registry.unregister_tool(server_id, &tool_name);
+        }
+    }
+}
+
 fn init_language_model_settings(cx: &mut AppContext) {
     update_active_language_model_from_settings(cx);
 
@@ -430,6 +507,12 @@ fn update_slash_commands_from_settings(cx: &mut AppContext) {
 fn register_tools(cx: &mut AppContext) {
     let tool_registry = ToolRegistry::global(cx);
     tool_registry.register_tool(tools::now_tool::NowTool);
+    tool_registry.register_tool(tools::read_file_tool::ReadFileTool);
+    tool_registry.register_tool(tools::list_directory_tool::ListDirectoryTool);
+    tool_registry.register_tool(tools::search_project_tool::SearchProjectTool);
+    tool_registry.register_tool(tools::diagnostics_tool::DiagnosticsTool);
+    tool_registry.register_tool(tools::edit_file_tool::EditFileTool);
+    tool_registry.register_tool(tools::run_task_tool::RunTaskTool);
 }
 
 pub fn humanize_token_count(count: usize) -> String {
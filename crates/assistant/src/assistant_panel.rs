@@ -7,22 +7,26 @@ use crate::{
         default_command::DefaultSlashCommand,
         docs_command::{DocsSlashCommand, DocsSlashCommandArgs},
         file_command::{self, codeblock_fence_for_path},
-        SlashCommandCompletionProvider, SlashCommandRegistry,
+        resource_command, SlashCommandCompletionProvider, SlashCommandRegistry,
     },
     slash_command_picker,
     terminal_inline_assistant::TerminalInlineAssistant,
-    Assist, CacheStatus, ConfirmCommand, Content, Context, ContextEvent, ContextId, ContextStore,
-    ContextStoreEvent, CycleMessageRole, DeployHistory, DeployPromptLibrary, InlineAssistId,
-    InlineAssistant, InsertDraggedFiles, InsertIntoEditor, Message, MessageId, MessageMetadata,
-    MessageStatus, ModelPickerDelegate, ModelSelector, NewContext, PendingSlashCommand,
-    PendingSlashCommandStatus, QuoteSelection, RemoteContextMetadata, SavedContextMetadata, Split,
-    ToggleFocus, ToggleModelSelector, WorkflowStepResolution,
+    token_usage::{self, BudgetWarning, TokenUsageStore},
+    tools, Assist, CacheStatus, ConfirmCommand, Content, Context, ContextEvent, ContextId,
+    ContextStore, ContextStoreEvent, ContextTranscript, CycleMessageRole, DeployHistory,
+    DeployPromptLibrary, ExportContextAsJson, ExportContextAsMarkdown, ForkConversation,
+    ImportContext, InlineAssistId, InlineAssistant, InsertDraggedFiles, InsertIntoEditor, Message,
+    MessageId, MessageMetadata, MessageStatus, ModelPickerDelegate, ModelSelector, NewContext,
+    PendingSlashCommand, PendingSlashCommandStatus, QuoteSelection, RegenerateResponse,
+    RemoteContextMetadata, SavedContextMatch, ShowTokenUsage, Split, ToggleFocus,
+    ToggleModelSelector, WorkflowStepResolution,
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context as _, Result};
 use assistant_slash_command::{SlashCommand, SlashCommandOutputSection};
-use assistant_tool::ToolRegistry;
+use assistant_tool::{Tool, ToolRegistry};
 use client::{proto, Client, Status};
 use collections::{BTreeSet, HashMap, HashSet};
+use context_servers::manager::ContextServerManager;
 use editor::{
     actions::{FoldAt, MoveToEndOfLine, Newline, ShowCompletions, UnfoldAt},
     display_map::{
@@ -34,14 +38,14 @@ use editor::{
 };
 use editor::{display_map::CreaseId, FoldPlaceholder};
 use fs::Fs;
-use futures::FutureExt;
+use futures::{future::Shared, FutureExt};
 use gpui::{
     canvas, div, img, percentage, point, pulsating_between, size, Action, Animation, AnimationExt,
     AnyElement, AnyView, AppContext, AsyncWindowContext, ClipboardEntry, ClipboardItem,
     Context as _, Empty, Entity, EntityId, EventEmitter, ExternalPaths, FocusHandle, FocusableView,
-    FontWeight, InteractiveElement, IntoElement, Model, ParentElement, Pixels, ReadGlobal, Render,
-    RenderImage, SharedString, Size, StatefulInteractiveElement, Styled, Subscription, Task,
-    Transformation, UpdateGlobal, View, VisualContext, WeakView, WindowContext,
+    FontWeight, InteractiveElement, IntoElement, Model, ParentElement, PathPromptOptions, Pixels,
+    ReadGlobal, Render, RenderImage, SharedString, Size, StatefulInteractiveElement, Styled,
+    Subscription, Task, Transformation, UpdateGlobal, View, VisualContext, WeakView, WindowContext,
 };
 use indexed_docs::IndexedDocsStore;
 use language::{
@@ -77,13 +81,15 @@ use ui::{
     Avatar, AvatarShape, ButtonLike, ContextMenu, Disclosure, ElevationIndex, KeyBinding, ListItem,
     ListItemSpacing, PopoverMenu, PopoverMenuHandle, Tooltip,
 };
+use url::Url;
 use util::{maybe, ResultExt};
 use workspace::{
     dock::{DockPosition, Panel, PanelEvent},
     item::{self, FollowableItem, Item, ItemHandle},
+    notifications::NotificationId,
     pane::{self, SaveIntent},
     searchable::{SearchEvent, SearchableItem},
-    DraggedSelection, Pane, Save, ShowConfiguration, ToggleZoom, ToolbarItemEvent,
+    DraggedSelection, Pane, Save, ShowConfiguration, Toast, ToggleZoom, ToolbarItemEvent,
     ToolbarItemLocation, ToolbarItemView, Workspace,
 };
 use workspace::{searchable::SearchableItemHandle, DraggedTab};
@@ -107,7 +113,8 @@ pub fn init(cx: &mut AppContext) {
                 .register_action(ContextEditor::insert_selection)
                 .register_action(ContextEditor::insert_dragged_files)
                 .register_action(AssistantPanel::show_configuration)
-                .register_action(AssistantPanel::create_new_context);
+                .register_action(AssistantPanel::create_new_context)
+                .register_action(token_usage::show_token_usage);
         },
     )
     .detach();
@@ -147,7 +154,7 @@ pub struct AssistantPanel {
 #[derive(Clone)]
 enum ContextMetadata {
     Remote(RemoteContextMetadata),
-    Saved(SavedContextMetadata),
+    Saved(SavedContextMatch),
 }
 
 struct SavedContextPickerDelegate {
@@ -279,14 +286,21 @@ impl PickerDelegate for SavedContextPickerDelegate {
                 .justify_between()
                 .gap_2()
                 .child(
-                    h_flex()
+                    v_flex()
                         .flex_1()
-                        .child(Label::new(context.title.clone()).size(LabelSize::Small))
-                        .overflow_x_hidden(),
+                        .overflow_x_hidden()
+                        .child(Label::new(context.metadata.title.clone()).size(LabelSize::Small))
+                        .when_some(context.excerpt.clone(), |this, excerpt| {
+                            this.child(
+                                Label::new(excerpt)
+                                    .color(Color::Muted)
+                                    .size(LabelSize::XSmall),
+                            )
+                        }),
                 )
                 .child(
                     Label::new(format_distance_from_now(
-                        DateTimeType::Local(context.mtime),
+                        DateTimeType::Local(context.metadata.mtime),
                         false,
                         true,
                         true,
@@ -333,11 +347,12 @@ impl AssistantPanel {
     ) -> Self {
         let model_selector_menu_handle = PopoverMenuHandle::default();
         let model_summary_editor = cx.new_view(Editor::single_line);
-        let context_editor_toolbar = cx.new_view(|_| {
+        let context_editor_toolbar = cx.new_view(|cx| {
             ContextEditorToolbarItem::new(
                 workspace,
                 model_selector_menu_handle.clone(),
                 model_summary_editor.clone(),
+                cx,
             )
         });
 
@@ -471,7 +486,9 @@ impl AssistantPanel {
                                     menu.context(focus_handle.clone())
                                         .action("New Context", Box::new(NewContext))
                                         .action("History", Box::new(DeployHistory))
+                                        .action("Import Context…", Box::new(ImportContext))
                                         .action("Prompt Library", Box::new(DeployPromptLibrary))
+                                        .action("Token Usage", Box::new(ShowTokenUsage))
                                         .action("Configure", Box::new(ShowConfiguration))
                                         .action(zoom_label, Box::new(ToggleZoom))
                                 }))
@@ -1162,6 +1179,52 @@ impl AssistantPanel {
         }
     }
 
+    fn import_context(&mut self, _: &ImportContext, cx: &mut ViewContext<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+        });
+        let fs = self.fs.clone();
+        cx.spawn(|this, mut cx| async move {
+            let Some(path) = paths.await??.and_then(|paths| paths.into_iter().next()) else {
+                return Ok(());
+            };
+            let json = fs.load(&path).await?;
+            let mut transcript = ContextTranscript::from_json(&json)
+                .with_context(|| format!("failed to import context from {:?}", path))?;
+            // Contexts are only saved once they have a title.
+            if transcript.title.is_none() {
+                transcript.title = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned());
+            }
+
+            this.update(&mut cx, |this, cx| {
+                let context = this
+                    .context_store
+                    .update(cx, |store, cx| store.import_transcript(transcript, cx))?;
+                let lsp_adapter_delegate = make_lsp_adapter_delegate(&this.project, cx).log_err();
+                let assistant_panel = cx.view().downgrade();
+                let editor = cx.new_view(|cx| {
+                    ContextEditor::for_context(
+                        context.clone(),
+                        this.fs.clone(),
+                        this.workspace.clone(),
+                        this.project.clone(),
+                        lsp_adapter_delegate,
+                        assistant_panel,
+                        cx,
+                    )
+                });
+                this.show_context(editor, cx);
+                context.update(cx, |context, cx| context.save(None, this.fs.clone(), cx));
+                anyhow::Ok(())
+            })?
+        })
+        .detach_and_log_err(cx);
+    }
+
     fn deploy_prompt_library(&mut self, _: &DeployPromptLibrary, cx: &mut ViewContext<Self>) {
         open_prompt_library(self.languages.clone(), cx).detach_and_log_err(cx);
     }
@@ -1312,6 +1375,7 @@ impl Render for AssistantPanel {
                 cx.listener(|this, _: &ShowConfiguration, cx| this.show_configuration_tab(cx)),
             )
             .on_action(cx.listener(AssistantPanel::deploy_history))
+            .on_action(cx.listener(AssistantPanel::import_context))
             .on_action(cx.listener(AssistantPanel::deploy_prompt_library))
             .on_action(cx.listener(AssistantPanel::toggle_model_selector))
             .child(registrar.size_full().child(self.pane.clone()))
@@ -1512,6 +1576,10 @@ pub struct ContextEditor {
     assistant_panel: WeakView<AssistantPanel>,
     error_message: Option<SharedString>,
     show_accept_terms: bool,
+    /// A warning that the provider's monthly budget is nearly used up, shown before sending.
+    budget_warning: Option<BudgetWarning>,
+    /// The last budget warning the user chose to send anyway, which isn't shown again.
+    acknowledged_budget_warning: Option<BudgetWarning>,
     pub(crate) slash_menu_handle:
         PopoverMenuHandle<Picker<slash_command_picker::SlashCommandDelegate>>,
     // dragged_file_worktrees is used to keep references to worktrees that were added
@@ -1520,6 +1588,11 @@ pub struct ContextEditor {
     // the file is opened. In order to keep the worktree alive for the duration of the
     // context editor, we keep a reference here.
     dragged_file_worktrees: Vec<Model<Worktree>>,
+    /// The last prompt asking the user to approve a tool use, which the next one waits for.
+    tool_approval_prompt: Option<Shared<Task<Option<usize>>>>,
+    /// The sections resources were inserted in, whose updates are subscribed to until the
+    /// section is removed or the editor is closed.
+    resource_sections: Vec<(Range<language::Anchor>, Url)>,
 }
 
 const DEFAULT_TAB_TITLE: &str = "New Context";
@@ -1559,6 +1632,15 @@ impl ContextEditor {
             cx.subscribe(&context, Self::handle_context_event),
             cx.subscribe(&editor, Self::handle_editor_event),
             cx.subscribe(&editor, Self::handle_editor_search_event),
+            cx.subscribe(
+                &ContextServerManager::global(cx),
+                Self::handle_context_server_event,
+            ),
+            cx.on_release(|this: &mut Self, _, cx| {
+                for (_, uri) in this.resource_sections.drain(..) {
+                    resource_command::unsubscribe_from_resource(&uri, cx);
+                }
+            }),
         ];
 
         let sections = context.read(cx).slash_command_output_sections().to_vec();
@@ -1583,8 +1665,12 @@ impl ContextEditor {
             assistant_panel,
             error_message: None,
             show_accept_terms: false,
+            budget_warning: None,
+            acknowledged_budget_warning: None,
             slash_menu_handle: Default::default(),
             dragged_file_worktrees: Vec::new(),
+            tool_approval_prompt: None,
+            resource_sections: Vec::new(),
         };
         this.update_message_headers(cx);
         this.update_image_blocks(cx);
@@ -1624,6 +1710,16 @@ impl ContextEditor {
             return;
         }
 
+        if let Some(budget_warning) = token_usage::unacknowledged_budget_warning(
+            self.acknowledged_budget_warning.as_ref(),
+            cx,
+        ) {
+            self.budget_warning = Some(budget_warning);
+            cx.notify();
+            return;
+        }
+        self.budget_warning = None;
+
         if !self.apply_active_workflow_step(cx) {
             self.error_message = None;
             self.send_to_model(cx);
@@ -1812,6 +1908,119 @@ impl ContextEditor {
         });
     }
 
+    fn fork_conversation(&mut self, _: &ForkConversation, cx: &mut ViewContext<Self>) {
+        if let Some(message) = self.newest_cursor_message(cx) {
+            self.fork_message(message.id, cx);
+        }
+    }
+
+    fn regenerate_response(&mut self, _: &RegenerateResponse, cx: &mut ViewContext<Self>) {
+        if let Some(message) = self.newest_cursor_message(cx) {
+            if message.role == Role::Assistant {
+                self.regenerate_message(message.id, cx);
+            }
+        }
+    }
+
+    fn export_as_markdown(&mut self, _: &ExportContextAsMarkdown, cx: &mut ViewContext<Self>) {
+        let markdown = self.context.read(cx).to_markdown(cx);
+        self.export(markdown, cx);
+    }
+
+    fn export_as_json(&mut self, _: &ExportContextAsJson, cx: &mut ViewContext<Self>) {
+        let transcript = self.context.read(cx).to_transcript(cx);
+        if let Some(json) = serde_json::to_string_pretty(&transcript).log_err() {
+            self.export(json, cx);
+        }
+    }
+
+    fn export(&mut self, contents: String, cx: &mut ViewContext<Self>) {
+        let directory = self
+            .project
+            .read(cx)
+            .visible_worktrees(cx)
+            .next()
+            .and_then(|worktree| Some(worktree.read(cx).as_local()?.abs_path().to_path_buf()))
+            .unwrap_or_default();
+        let path = cx.prompt_for_new_path(&directory);
+        let fs = self.fs.clone();
+        cx.spawn(|_, _| async move {
+            if let Some(path) = path.await?? {
+                fs.atomic_write(path, contents).await?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn fork_message(&mut self, message_id: MessageId, cx: &mut ViewContext<Self>) {
+        let forked_message = self
+            .context
+            .update(cx, |context, cx| context.fork_message(message_id, cx));
+        if forked_message.is_some() {
+            // The forked message is the last one, so the cursor is placed at its end for editing.
+            let cursor = self.context.read(cx).buffer().read(cx).len();
+            self.editor.update(cx, |editor, cx| {
+                editor.change_selections(
+                    Some(Autoscroll::Strategy(AutoscrollStrategy::Fit)),
+                    cx,
+                    |selections| selections.select_ranges([cursor..cursor]),
+                );
+            });
+        }
+    }
+
+    fn regenerate_message(&mut self, message_id: MessageId, cx: &mut ViewContext<Self>) {
+        self.error_message = None;
+        if let Some(user_message) = self
+            .context
+            .update(cx, |context, cx| context.regenerate_message(message_id, cx))
+        {
+            let cursor = user_message
+                .start
+                .to_offset(self.context.read(cx).buffer().read(cx));
+            self.editor.update(cx, |editor, cx| {
+                editor.change_selections(
+                    Some(Autoscroll::Strategy(AutoscrollStrategy::Fit)),
+                    cx,
+                    |selections| selections.select_ranges([cursor..cursor]),
+                );
+            });
+            cx.defer(|this, _| this.scroll_position = None);
+        }
+        cx.notify();
+    }
+
+    fn select_branch(
+        &mut self,
+        message_id: MessageId,
+        branch_ix: usize,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if let Some(first_message) = self.context.update(cx, |context, cx| {
+            context.select_branch(message_id, branch_ix, cx)
+        }) {
+            let cursor = first_message
+                .start
+                .to_offset(self.context.read(cx).buffer().read(cx));
+            self.editor.update(cx, |editor, cx| {
+                editor.change_selections(
+                    Some(Autoscroll::Strategy(AutoscrollStrategy::Fit)),
+                    cx,
+                    |selections| selections.select_ranges([cursor..cursor]),
+                );
+            });
+        }
+    }
+
+    fn newest_cursor_message(&self, cx: &AppContext) -> Option<Message> {
+        let cursor = self.editor.read(cx).selections.newest::<usize>(cx).head();
+        self.context
+            .read(cx)
+            .messages_for_offsets([cursor], cx)
+            .pop()
+    }
+
     fn cursors(&self, cx: &AppContext) -> Vec<usize> {
         let selections = self.editor.read(cx).selections.all::<usize>(cx);
         selections
@@ -1931,6 +2140,7 @@ impl ContextEditor {
             ContextEvent::MessagesEdited => {
                 self.update_message_headers(cx);
                 self.update_image_blocks(cx);
+                self.release_removed_resources(cx);
                 self.context.update(cx, |context, cx| {
                     context.save(Some(Duration::from_millis(500)), self.fs.clone(), cx);
                 });
@@ -2101,6 +2311,7 @@ impl ContextEditor {
                             };
                             let render_trailer = {
                                 let command = command.clone();
+                                let workspace = self.workspace.clone();
                                 move |row, _unfold, cx: &mut WindowContext| {
                                     // TODO: In the future we should investigate how we can expose
                                     // this as a hook on the `SlashCommand` trait so that we don't
@@ -2109,6 +2320,7 @@ impl ContextEditor {
                                         return render_docs_slash_command_trailer(
                                             row,
                                             command.clone(),
+                                            &workspace,
                                             cx,
                                         );
                                     }
@@ -2214,7 +2426,7 @@ impl ContextEditor {
                 for tool_use in pending_tool_uses {
                     let tool_registry = ToolRegistry::global(cx);
                     if let Some(tool) = tool_registry.tool(&tool_use.name) {
-                        let task = tool.run(tool_use.input, self.workspace.clone(), cx);
+                        let task = self.run_tool_with_approval(tool, tool_use.input, cx);
 
                         self.context.update(cx, |context, cx| {
                             context.insert_tool_output(tool_use.id.clone(), task, cx);
@@ -2501,6 +2713,14 @@ impl ContextEditor {
         expand_result: bool,
         cx: &mut ViewContext<Self>,
     ) {
+        let sections = sections.into_iter().collect::<Vec<_>>();
+        for section in &sections {
+            if let Some(uri) = resource_command::section_resource(section) {
+                resource_command::subscribe_to_resource(uri.clone(), cx);
+                self.resource_sections.push((section.range.clone(), uri));
+            }
+        }
+
         self.editor.update(cx, |editor, cx| {
             let buffer = editor.buffer().read(cx).snapshot(cx);
             let excerpt_id = *buffer.as_singleton().unwrap().0;
@@ -2548,6 +2768,22 @@ impl ContextEditor {
         });
     }
 
+    /// Unsubscribes from the updates of resources whose sections have been removed.
+    fn release_removed_resources(&mut self, cx: &mut ViewContext<Self>) {
+        let buffer = self.context.read(cx).buffer().read(cx);
+        let mut removed = Vec::new();
+        self.resource_sections.retain(|(range, uri)| {
+            let is_removed = range.start.to_offset(buffer) == range.end.to_offset(buffer);
+            if is_removed {
+                removed.push(uri.clone());
+            }
+            !is_removed
+        });
+        for uri in removed {
+            resource_command::unsubscribe_from_resource(&uri, cx);
+        }
+    }
+
     fn handle_editor_event(
         &mut self,
         _: View<Editor>,
@@ -2577,6 +2813,25 @@ impl ContextEditor {
         Some((step.range.clone(), self.workflow_steps.get(&step.range)?))
     }
 
+    /// Runs a tool the model asked to use, if the user's approval policy for it allows it.
+    fn run_tool_with_approval(
+        &mut self,
+        tool: Arc<dyn Tool>,
+        input: serde_json::Value,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<String>> {
+        let (task, prompt) = tools::run_tool_with_approval(
+            tool,
+            input,
+            self.workspace.clone(),
+            self.fs.clone(),
+            self.tool_approval_prompt.take(),
+            cx,
+        );
+        self.tool_approval_prompt = prompt;
+        task
+    }
+
     fn update_active_workflow_step(&mut self, cx: &mut ViewContext<Self>) {
         let newest_cursor = self.editor.read(cx).selections.newest::<usize>(cx).head();
         let context = self.context.read(cx);
@@ -2842,6 +3097,44 @@ impl ContextEditor {
         })
     }
 
+    fn handle_context_server_event(
+        &mut self,
+        _: Model<ContextServerManager>,
+        event: &context_servers::manager::Event,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let context_servers::manager::Event::ResourceUpdated { server_id, uri } = event else {
+            return;
+        };
+        let label = resource_command::resource_label(uri);
+        let is_attached = self
+            .context
+            .read(cx)
+            .slash_command_output_sections()
+            .iter()
+            .any(|section| section.label.as_ref() == label);
+        if !is_attached {
+            return;
+        }
+
+        if let Some(workspace) = self.workspace.upgrade() {
+            struct ContextServerResourceUpdated;
+
+            let message = format!(
+                "{uri} was updated by the {server_id} context server since it was inserted. Run `/resource {uri}` again to insert its new contents."
+            );
+            workspace.update(cx, |workspace, cx| {
+                workspace.show_toast(
+                    Toast::new(
+                        NotificationId::unique::<ContextServerResourceUpdated>(),
+                        message,
+                    ),
+                    cx,
+                );
+            });
+        }
+    }
+
     fn handle_editor_search_event(
         &mut self,
         _: View<Editor>,
@@ -2877,6 +3170,7 @@ impl ContextEditor {
     }
 
     fn update_message_headers(&mut self, cx: &mut ViewContext<Self>) {
+        let context_editor = cx.view().downgrade();
         self.editor.update(cx, |editor, cx| {
             let buffer = editor.buffer().read(cx).snapshot(cx);
 
@@ -2891,8 +3185,10 @@ impl ContextEditor {
             let render_block = |message: MessageMetadata| -> RenderBlock {
                 Box::new({
                     let context = self.context.clone();
+                    let context_editor = context_editor.clone();
                     move |cx| {
                         let message_id = MessageId(message.timestamp);
+                        let branch = context.read(cx).branch_for_message(message_id, cx);
                         let show_spinner = message.role == Role::Assistant
                             && message.status == MessageStatus::Pending;
 
@@ -2953,6 +3249,99 @@ impl ContextEditor {
                             .relative()
                             .gap_1()
                             .child(sender)
+                            .children(branch.map(|(branch_ix, branch_count)| {
+                                h_flex()
+                                    .child(
+                                        IconButton::new("previous-branch", IconName::ChevronLeft)
+                                            .icon_size(IconSize::XSmall)
+                                            .disabled(branch_ix == 0)
+                                            .tooltip(|cx| Tooltip::text("Previous Branch", cx))
+                                            .on_click({
+                                                let context_editor = context_editor.clone();
+                                                move |_, cx| {
+                                                    context_editor
+                                                        .update(cx, |this, cx| {
+                                                            this.select_branch(
+                                                                message_id,
+                                                                branch_ix.saturating_sub(1),
+                                                                cx,
+                                                            )
+                                                        })
+                                                        .ok();
+                                                }
+                                            }),
+                                    )
+                                    .child(
+                                        Label::new(format!("{}/{}", branch_ix + 1, branch_count))
+                                            .size(LabelSize::Small)
+                                            .color(Color::Muted),
+                                    )
+                                    .child(
+                                        IconButton::new("next-branch", IconName::ChevronRight)
+                                            .icon_size(IconSize::XSmall)
+                                            .disabled(branch_ix + 1 == branch_count)
+                                            .tooltip(|cx| Tooltip::text("Next Branch", cx))
+                                            .on_click({
+                                                let context_editor = context_editor.clone();
+                                                move |_, cx| {
+                                                    context_editor
+                                                        .update(cx, |this, cx| {
+                                                            this.select_branch(
+                                                                message_id,
+                                                                branch_ix + 1,
+                                                                cx,
+                                                            )
+                                                        })
+                                                        .ok();
+                                                }
+                                            }),
+                                    )
+                            }))
+                            .child(match message.role {
+                                Role::Assistant => {
+                                    IconButton::new("regenerate", IconName::RotateCw)
+                                        .icon_size(IconSize::XSmall)
+                                        .disabled(message.status == MessageStatus::Pending)
+                                        .tooltip(|cx| {
+                                            Tooltip::for_action(
+                                                "Regenerate Response",
+                                                &RegenerateResponse,
+                                                cx,
+                                            )
+                                        })
+                                        .on_click({
+                                            let context_editor = context_editor.clone();
+                                            move |_, cx| {
+                                                context_editor
+                                                    .update(cx, |this, cx| {
+                                                        this.regenerate_message(message_id, cx)
+                                                    })
+                                                    .ok();
+                                            }
+                                        })
+                                }
+                                Role::User | Role::System => {
+                                    IconButton::new("fork", IconName::Pencil)
+                                        .icon_size(IconSize::XSmall)
+                                        .tooltip(|cx| {
+                                            Tooltip::for_action(
+                                                "Edit in a New Branch",
+                                                &ForkConversation,
+                                                cx,
+                                            )
+                                        })
+                                        .on_click({
+                                            let context_editor = context_editor.clone();
+                                            move |_, cx| {
+                                                context_editor
+                                                    .update(cx, |this, cx| {
+                                                        this.fork_message(message_id, cx)
+                                                    })
+                                                    .ok();
+                                            }
+                                        })
+                                }
+                            })
                             .children(match &message.cache {
                                 Some(cache) if cache.is_final_anchor => match cache.status {
                                     CacheStatus::Cached => Some(
@@ -4267,6 +4656,10 @@ impl Render for ContextEditor {
             .capture_action(cx.listener(ContextEditor::confirm_command))
             .on_action(cx.listener(ContextEditor::assist))
             .on_action(cx.listener(ContextEditor::split))
+            .on_action(cx.listener(ContextEditor::fork_conversation))
+            .on_action(cx.listener(ContextEditor::regenerate_response))
+            .on_action(cx.listener(ContextEditor::export_as_markdown))
+            .on_action(cx.listener(ContextEditor::export_as_json))
             .size_full()
             .children(self.render_notice(cx))
             .child(
@@ -4332,6 +4725,30 @@ impl Render for ContextEditor {
                         ),
                 )
             })
+            .when_some(self.budget_warning.clone(), |this, budget_warning| {
+                this.child(
+                    div()
+                        .absolute()
+                        .right_3()
+                        .bottom_12()
+                        .max_w_96()
+                        .py_2()
+                        .px_3()
+                        .elevation_2(cx)
+                        .occlude()
+                        .child(token_usage::render_budget_warning(
+                            &budget_warning,
+                            cx.listener(|this, _, cx| {
+                                this.budget_warning = None;
+                                cx.notify();
+                            }),
+                            cx.listener(|this, _, cx| {
+                                this.acknowledged_budget_warning = this.budget_warning.take();
+                                this.assist(&Assist, cx);
+                            }),
+                        )),
+                )
+            })
             .child(
                 h_flex().w_full().relative().child(
                     h_flex()
@@ -4617,6 +5034,7 @@ pub struct ContextEditorToolbarItem {
     active_context_editor: Option<WeakView<ContextEditor>>,
     model_summary_editor: View<Editor>,
     model_selector_menu_handle: PopoverMenuHandle<Picker<ModelPickerDelegate>>,
+    _token_usage_subscription: Option<Subscription>,
 }
 
 fn active_editor_focus_handle(
@@ -4655,6 +5073,7 @@ impl ContextEditorToolbarItem {
         workspace: &Workspace,
         model_selector_menu_handle: PopoverMenuHandle<Picker<ModelPickerDelegate>>,
         model_summary_editor: View<Editor>,
+        cx: &mut ViewContext<Self>,
     ) -> Self {
         Self {
             fs: workspace.app_state().fs.clone(),
@@ -4662,7 +5081,44 @@ impl ContextEditorToolbarItem {
             active_context_editor: None,
             model_summary_editor,
             model_selector_menu_handle,
+            _token_usage_subscription: TokenUsageStore::try_global(cx)
+                .map(|store| cx.observe(&store, |_, _, cx| cx.notify())),
+        }
+    }
+
+    fn render_token_usage(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let context_id = self
+            .active_context_editor
+            .as_ref()?
+            .upgrade()?
+            .read(cx)
+            .context
+            .read(cx)
+            .id()
+            .clone();
+        let totals = TokenUsageStore::try_global(cx)?
+            .read(cx)
+            .usage_for_context(&context_id, cx);
+        if totals.requests == 0 {
+            return None;
         }
+
+        let summary = totals.summary();
+        Some(
+            div()
+                .id("token-usage")
+                .child(
+                    Label::new(format!(
+                        "{} used",
+                        humanize_token_count(totals.usage.total_tokens() as usize)
+                    ))
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+                )
+                .tooltip(move |cx| {
+                    Tooltip::with_meta("Tokens used by this context", None, summary.clone(), cx)
+                }),
+        )
     }
 
     fn render_remaining_tokens(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
@@ -4783,6 +5239,7 @@ impl Render for ContextEditorToolbarItem {
                 )
                 .with_handle(self.model_selector_menu_handle.clone()),
             )
+            .children(self.render_token_usage(cx))
             .children(self.render_remaining_tokens(cx))
             .child(
                 PopoverMenu::new("context-editor-popover")
@@ -4844,6 +5301,49 @@ impl Render for ContextEditorToolbarItem {
                                         },
                                     )
                                     .action("Insert Selection", QuoteSelection.boxed_clone())
+                                    .separator()
+                                    .entry("Export as Markdown…", None, {
+                                        let weak_self = weak_self.clone();
+                                        move |cx| {
+                                            weak_self
+                                                .update(cx, |this, cx| {
+                                                    if let Some(editor) =
+                                                        &this.active_context_editor
+                                                    {
+                                                        editor
+                                                            .update(cx, |editor, cx| {
+                                                                editor.export_as_markdown(
+                                                                    &ExportContextAsMarkdown,
+                                                                    cx,
+                                                                )
+                                                            })
+                                                            .ok();
+                                                    }
+                                                })
+                                                .ok();
+                                        }
+                                    })
+                                    .entry("Export as JSON…", None, {
+                                        let weak_self = weak_self.clone();
+                                        move |cx| {
+                                            weak_self
+                                                .update(cx, |this, cx| {
+                                                    if let Some(editor) =
+                                                        &this.active_context_editor
+                                                    {
+                                                        editor
+                                                            .update(cx, |editor, cx| {
+                                                                editor.export_as_json(
+                                                                    &ExportContextAsJson,
+                                                                    cx,
+                                                                )
+                                                            })
+                                                            .ok();
+                                                    }
+                                                })
+                                                .ok();
+                                        }
+                                    })
                             }))
                         }
                     }),
@@ -4937,9 +5437,9 @@ impl ContextHistory {
                         .open_remote_context(metadata.id.clone(), cx)
                         .detach_and_log_err(cx);
                 }
-                ContextMetadata::Saved(metadata) => {
+                ContextMetadata::Saved(context) => {
                     assistant_panel
-                        .open_saved_context(metadata.path.clone(), cx)
+                        .open_saved_context(context.metadata.path.clone(), cx)
                         .detach_and_log_err(cx);
                 }
             })
@@ -5297,6 +5797,7 @@ fn render_pending_slash_command_gutter_decoration(
 fn render_docs_slash_command_trailer(
     row: MultiBufferRow,
     command: PendingSlashCommand,
+    workspace: &WeakView<Workspace>,
     cx: &mut WindowContext,
 ) -> AnyElement {
     if command.arguments.is_empty() {
@@ -5304,10 +5805,10 @@ fn render_docs_slash_command_trailer(
     }
     let args = DocsSlashCommandArgs::parse(&command.arguments);
 
-    let Some(store) = args
-        .provider()
-        .and_then(|provider| IndexedDocsStore::try_global(provider, cx).ok())
-    else {
+    let project_root = DocsSlashCommand::project_root(Some(workspace), cx);
+    let Some(store) = args.provider().and_then(|provider| {
+        IndexedDocsStore::try_for_project(project_root.as_deref(), provider, cx).ok()
+    }) else {
         return Empty.into_any();
     };
 
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use ::open_ai::Model as OpenAiModel;
 use anthropic::Model as AnthropicModel;
+use collections::HashMap;
 use fs::Fs;
 use gpui::{AppContext, Pixels};
 use language_model::provider::open_ai;
@@ -25,6 +26,60 @@ pub enum AssistantDockPosition {
     Bottom,
 }
 
+/// Whether the assistant may use a tool without asking the user first.
+#[derive(Copy, Clone, Default, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolApproval {
+    /// Use the tool without asking.
+    Allow,
+    /// Ask the user every time the tool is used.
+    #[default]
+    Ask,
+    /// Never use the tool.
+    Deny,
+}
+
+/// The price of a model's tokens, in dollars per million tokens.
+#[derive(Copy, Clone, Default, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ModelPrice {
+    /// The price of input tokens.
+    pub input: f64,
+    /// The price of output tokens.
+    pub output: f64,
+    /// The price of input tokens written to the prompt cache.
+    ///
+    /// Default: the price of input tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    /// The price of input tokens read from the prompt cache.
+    ///
+    /// Default: the price of input tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+}
+
+/// A monthly limit on the tokens used with a provider, which the assistant
+/// warns about before sending requests.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct UsageBudget {
+    /// The number of tokens that may be used each month.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_tokens: Option<u64>,
+    /// The amount, in dollars, that may be spent each month. Only the
+    /// models listed in `model_prices` count towards it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_cost: Option<f64>,
+    /// The fraction of the budget after which the assistant starts warning.
+    ///
+    /// Default: 0.8
+    #[serde(default = "default_warning_threshold")]
+    pub warning_threshold: f64,
+}
+
+fn default_warning_threshold() -> f64 {
+    0.8
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(tag = "name", rename_all = "snake_case")]
 pub enum AssistantProviderContentV1 {
@@ -59,9 +114,22 @@ pub struct AssistantSettings {
     pub default_width: Pixels,
     pub default_height: Pixels,
     pub default_model: LanguageModelSelection,
+    pub tool_approval: HashMap<String, ToolApproval>,
+    pub model_prices: HashMap<String, ModelPrice>,
+    pub usage_budgets: HashMap<String, UsageBudget>,
     pub using_outdated_settings_version: bool,
 }
 
+impl AssistantSettings {
+    /// Returns whether the tool with the given name may be used without asking the user.
+    pub fn tool_approval(&self, tool_name: &str) -> ToolApproval {
+        self.tool_approval
+            .get(tool_name)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
 /// Assistant panel settings
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(untagged)]
@@ -236,6 +304,9 @@ impl AssistantSettingsContent {
                                 })
                             }
                         }),
+                    tool_approval: None,
+                    model_prices: None,
+                    usage_budgets: None,
                 },
                 VersionedAssistantSettingsContent::V2(settings) => settings.clone(),
             },
@@ -254,6 +325,9 @@ impl AssistantSettingsContent {
                         .id()
                         .to_string(),
                 }),
+                tool_approval: None,
+                model_prices: None,
+                usage_budgets: None,
             },
         }
     }
@@ -349,6 +423,20 @@ impl AssistantSettingsContent {
             }
         }
     }
+
+    pub fn set_tool_approval(&mut self, tool_name: &str, approval: ToolApproval) {
+        match self {
+            AssistantSettingsContent::Versioned(VersionedAssistantSettingsContent::V2(
+                settings,
+            )) => {
+                settings
+                    .tool_approval
+                    .get_or_insert_with(HashMap::default)
+                    .insert(tool_name.to_string(), approval);
+            }
+            _ => log::warn!("attempted to set a tool approval on outdated settings"),
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, JsonSchema, Debug)]
@@ -369,6 +457,9 @@ impl Default for VersionedAssistantSettingsContent {
             default_width: None,
             default_height: None,
             default_model: None,
+            tool_approval: None,
+            model_prices: None,
+            usage_budgets: None,
         })
     }
 }
@@ -397,6 +488,21 @@ pub struct AssistantSettingsContentV2 {
     default_height: Option<f32>,
     /// The default model to use when creating new contexts.
     default_model: Option<LanguageModelSelection>,
+    /// Whether the assistant may use each tool, by name, without asking first.
+    /// Tools that aren't listed are always asked about.
+    ///
+    /// Default: tools that only read the project are allowed, `edit_file` and `run_task` ask
+    tool_approval: Option<HashMap<String, ToolApproval>>,
+    /// The prices of models, by model id, used to estimate what the assistant's
+    /// requests cost.
+    ///
+    /// Default: {}
+    model_prices: Option<HashMap<String, ModelPrice>>,
+    /// Monthly budgets for providers, by provider id, which the assistant warns
+    /// about before sending requests once they're nearly used up.
+    ///
+    /// Default: {}
+    usage_budgets: Option<HashMap<String, UsageBudget>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
@@ -407,7 +513,7 @@ pub struct LanguageModelSelection {
 }
 
 fn providers_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
-    schemars::schema::SchemaObject {
+    let built_in_providers = schemars::schema::SchemaObject {
         enum_values: Some(vec![
             "anthropic".into(),
             "google".into(),
@@ -417,6 +523,25 @@ fn providers_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema:
             "copilot_chat".into(),
         ]),
         ..Default::default()
+    };
+    // OpenAI-compatible providers are identified by the name they're configured with.
+    let open_ai_compatible_providers = schemars::schema::SchemaObject {
+        instance_type: Some(schemars::schema::InstanceType::String.into()),
+        string: Some(Box::new(schemars::schema::StringValidation {
+            pattern: Some("^openai_compatible:".into()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    schemars::schema::SchemaObject {
+        subschemas: Some(Box::new(schemars::schema::SubschemaValidation {
+            any_of: Some(vec![
+                built_in_providers.into(),
+                open_ai_compatible_providers.into(),
+            ]),
+            ..Default::default()
+        })),
+        ..Default::default()
     }
     .into()
 }
@@ -521,6 +646,15 @@ impl Settings for AssistantSettings {
                 &mut settings.default_model,
                 value.default_model.map(Into::into),
             );
+            if let Some(tool_approval) = value.tool_approval {
+                settings.tool_approval.extend(tool_approval);
+            }
+            if let Some(model_prices) = value.model_prices {
+                settings.model_prices.extend(model_prices);
+            }
+            if let Some(usage_budgets) = value.usage_budgets {
+                settings.usage_budgets.extend(usage_budgets);
+            }
             // merge(&mut settings.infer_context, value.infer_context); TODO re-enable this once we ship context inference
         }
 
@@ -579,6 +713,9 @@ mod tests {
                             dock: None,
                             default_width: None,
                             default_height: None,
+                            tool_approval: None,
+                            model_prices: None,
+                            usage_budgets: None,
                         }),
                     )
                 },
@@ -2,8 +2,12 @@
 mod context_tests;
 
 use crate::{
-    prompts::PromptBuilder, slash_command::SlashCommandLine, MessageId, MessageStatus,
-    WorkflowStep, WorkflowStepEdit, WorkflowStepResolution, WorkflowSuggestionGroup,
+    assistant_settings::{AssistantSettings, ToolApproval},
+    prompts::PromptBuilder,
+    slash_command::SlashCommandLine,
+    token_usage::{self, CompletionUsage, TokenUsageSource},
+    MessageId, MessageStatus, WorkflowStep, WorkflowStepEdit, WorkflowStepResolution,
+    WorkflowSuggestionGroup,
 };
 use anyhow::{anyhow, Context as _, Result};
 use assistant_slash_command::{
@@ -35,10 +39,11 @@ use open_ai::Model as OpenAiModel;
 use paths::contexts_dir;
 use project::Project;
 use serde::{Deserialize, Serialize};
+use settings::Settings;
 use smallvec::SmallVec;
 use std::{
     cmp::{self, max, Ordering},
-    fmt::Debug,
+    fmt::{Debug, Write as _},
     iter, mem,
     ops::Range,
     path::{Path, PathBuf},
@@ -51,7 +56,7 @@ use text::BufferSnapshot;
 use util::{post_inc, TryFutureExt};
 use uuid::Uuid;
 
-#[derive(Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ContextId(String);
 
 impl ContextId {
@@ -90,6 +95,11 @@ pub enum ContextOperation {
         sections: Vec<SlashCommandOutputSection<language::Anchor>>,
         version: clock::Global,
     },
+    UpdateBranchPoints {
+        branch_points: Vec<SavedBranchPoint>,
+        timestamp: clock::Lamport,
+        version: clock::Global,
+    },
     BufferOperation(language::Operation),
 }
 
@@ -168,6 +178,19 @@ impl ContextOperation {
                     version: language::proto::deserialize_version(&finished.version),
                 })
             }
+            proto::context_operation::Variant::UpdateBranchPoints(update) => {
+                Ok(Self::UpdateBranchPoints {
+                    branch_points: update
+                        .branch_points
+                        .into_iter()
+                        .map(SavedBranchPoint::from_proto)
+                        .collect::<Result<Vec<_>>>()?,
+                    timestamp: language::proto::deserialize_timestamp(
+                        update.timestamp.context("invalid timestamp")?,
+                    ),
+                    version: language::proto::deserialize_version(&update.version),
+                })
+            }
             proto::context_operation::Variant::BufferOperation(op) => Ok(Self::BufferOperation(
                 language::proto::deserialize_operation(
                     op.operation.context("invalid buffer operation")?,
@@ -249,6 +272,22 @@ impl ContextOperation {
                     },
                 )),
             },
+            Self::UpdateBranchPoints {
+                branch_points,
+                timestamp,
+                version,
+            } => proto::ContextOperation {
+                variant: Some(proto::context_operation::Variant::UpdateBranchPoints(
+                    proto::context_operation::UpdateBranchPoints {
+                        branch_points: branch_points
+                            .iter()
+                            .map(SavedBranchPoint::to_proto)
+                            .collect(),
+                        timestamp: Some(language::proto::serialize_timestamp(*timestamp)),
+                        version: language::proto::serialize_version(version),
+                    },
+                )),
+            },
             Self::BufferOperation(operation) => proto::ContextOperation {
                 variant: Some(proto::context_operation::Variant::BufferOperation(
                     proto::context_operation::BufferOperation {
@@ -265,6 +304,7 @@ impl ContextOperation {
             Self::UpdateMessage { metadata, .. } => metadata.timestamp,
             Self::UpdateSummary { summary, .. } => summary.timestamp,
             Self::SlashCommandFinished { id, .. } => id.0,
+            Self::UpdateBranchPoints { timestamp, .. } => *timestamp,
             Self::BufferOperation(_) => {
                 panic!("reading the timestamp of a buffer operation is not supported")
             }
@@ -277,7 +317,8 @@ impl ContextOperation {
             Self::InsertMessage { version, .. }
             | Self::UpdateMessage { version, .. }
             | Self::UpdateSummary { version, .. }
-            | Self::SlashCommandFinished { version, .. } => version,
+            | Self::SlashCommandFinished { version, .. }
+            | Self::UpdateBranchPoints { version, .. } => version,
             Self::BufferOperation(_) => {
                 panic!("reading the version of a buffer operation is not supported")
             }
@@ -413,6 +454,16 @@ impl Content {
         }
     }
 
+    /// Returns a copy of the content attached to the given range instead.
+    fn with_range(&self, new_range: Range<language::Anchor>) -> Self {
+        let mut content = self.clone();
+        match &mut content {
+            Self::Image { anchor, .. } => *anchor = new_range.end,
+            Self::ToolUse { range, .. } | Self::ToolResult { range, .. } => *range = new_range,
+        }
+        content
+    }
+
     fn cmp(&self, other: &Self, buffer: &BufferSnapshot) -> Ordering {
         let self_range = self.range();
         let other_range = other.range();
@@ -469,6 +520,8 @@ pub struct Context {
     message_anchors: Vec<MessageAnchor>,
     contents: Vec<Content>,
     messages_metadata: HashMap<MessageId, MessageMetadata>,
+    branch_points: Vec<SavedBranchPoint>,
+    branch_points_timestamp: clock::Lamport,
     summary: Option<ContextSummary>,
     pending_summary: Task<Option<()>>,
     completion_count: usize,
@@ -563,6 +616,8 @@ impl Context {
             message_anchors: Default::default(),
             contents: Default::default(),
             messages_metadata: Default::default(),
+            branch_points: Vec::new(),
+            branch_points_timestamp: clock::Lamport::MIN,
             pending_slash_commands: Vec::new(),
             finished_slash_commands: HashSet::default(),
             pending_tool_uses_by_id: HashMap::default(),
@@ -626,6 +681,7 @@ impl Context {
                     metadata: self.messages_metadata[&message.id].clone(),
                 })
                 .collect(),
+            branch_points: self.branch_points.clone(),
             summary: self
                 .summary
                 .as_ref()
@@ -767,6 +823,7 @@ impl Context {
     fn flush_ops(&mut self, cx: &mut ModelContext<Context>) {
         let mut changed_messages = HashSet::default();
         let mut summary_changed = false;
+        let mut branch_points_changed = false;
 
         self.pending_ops.sort_unstable_by_key(|op| op.timestamp());
         for op in mem::take(&mut self.pending_ops) {
@@ -831,6 +888,17 @@ impl Context {
                         });
                     }
                 }
+                ContextOperation::UpdateBranchPoints {
+                    branch_points,
+                    timestamp,
+                    ..
+                } => {
+                    if timestamp > self.branch_points_timestamp {
+                        self.branch_points = branch_points;
+                        self.branch_points_timestamp = timestamp;
+                        branch_points_changed = true;
+                    }
+                }
                 ContextOperation::BufferOperation(_) => unreachable!(),
             }
 
@@ -843,6 +911,9 @@ impl Context {
             self.message_roles_updated(changed_messages, cx);
             cx.emit(ContextEvent::MessagesEdited);
             cx.notify();
+        } else if branch_points_changed {
+            cx.emit(ContextEvent::MessagesEdited);
+            cx.notify();
         }
 
         if summary_changed {
@@ -886,6 +957,13 @@ impl Context {
                         observed_start && observed_end
                     })
             }
+            ContextOperation::UpdateBranchPoints { branch_points, .. } => {
+                branch_points.iter().all(|branch_point| {
+                    branch_point
+                        .parent
+                        .map_or(true, |parent| self.messages_metadata.contains_key(&parent))
+                })
+            }
             ContextOperation::BufferOperation(_) => {
                 panic!("buffer operations should always be applied")
             }
@@ -1176,11 +1254,24 @@ impl Context {
         };
 
         let model = Arc::clone(model);
+        let usage_source = self.token_usage_source();
+        let project_root = self
+            .project
+            .as_ref()
+            .and_then(|project| token_usage::project_root(project, cx));
         self.pending_cache_warming_task = cx.spawn(|this, mut cx| {
             async move {
-                match model.stream_completion(request, &cx).await {
+                let mut completion_usage = CompletionUsage::default();
+                match model.stream_completion(request.clone(), &cx).await {
                     Ok(mut stream) => {
-                        stream.next().await;
+                        // Providers report the tokens written to the cache before the response,
+                        // which isn't waited for.
+                        while let Some(Ok(event)) = stream.next().await {
+                            completion_usage.observe(&event);
+                            if !matches!(event, LanguageModelCompletionEvent::UsageUpdate(_)) {
+                                break;
+                            }
+                        }
                         log::info!("Cache warming completed successfully");
                     }
                     Err(e) => {
@@ -1191,12 +1282,26 @@ impl Context {
                     this.update_cache_status_for_completion(cx);
                 })
                 .ok();
-                anyhow::Ok(())
+                token_usage::record_completion_usage(
+                    model,
+                    request,
+                    completion_usage,
+                    usage_source,
+                    project_root,
+                    &mut cx,
+                )
+                .await
             }
             .log_err()
         });
     }
 
+    fn token_usage_source(&self) -> TokenUsageSource {
+        TokenUsageSource::AssistantPanel {
+            context_id: self.id.clone(),
+        }
+    }
+
     pub fn update_cache_status_for_completion(&mut self, cx: &mut ModelContext<Self>) {
         let cached_message_ids: Vec<MessageId> = self
             .messages_metadata
@@ -1976,9 +2081,12 @@ impl Context {
 
         if cx.has_flag::<ToolUseFeatureFlag>() {
             let tool_registry = ToolRegistry::global(cx);
+            let settings = AssistantSettings::get_global(cx);
+            // Tools the user doesn't allow aren't offered to the model at all.
             request.tools = tool_registry
                 .tools()
                 .into_iter()
+                .filter(|tool| settings.tool_approval(&tool.name()) != ToolApproval::Deny)
                 .map(|tool| LanguageModelRequestTool {
                     name: tool.name(),
                     description: tool.description(),
@@ -1997,12 +2105,18 @@ impl Context {
             .unwrap();
 
         let pending_completion_id = post_inc(&mut self.completion_count);
+        let usage_source = self.token_usage_source();
+        let project_root = self
+            .project
+            .as_ref()
+            .and_then(|project| token_usage::project_root(project, cx));
 
         let task = cx.spawn({
             |this, mut cx| async move {
-                let stream = model.stream_completion(request, &cx);
+                let stream = model.stream_completion(request.clone(), &cx);
                 let assistant_message_id = assistant_message.id;
                 let mut response_latency = None;
+                let mut completion_usage = CompletionUsage::default();
                 let stream_completion = async {
                     let request_start = Instant::now();
                     let mut events = stream.await?;
@@ -2013,6 +2127,7 @@ impl Context {
                             response_latency = Some(request_start.elapsed());
                         }
                         let event = event?;
+                        completion_usage.observe(&event);
 
                         this.update(&mut cx, |this, cx| {
                             let message_ix = this
@@ -2080,6 +2195,7 @@ impl Context {
                                             },
                                         );
                                     }
+                                    LanguageModelCompletionEvent::UsageUpdate(_) => {}
                                 }
                             });
 
@@ -2143,6 +2259,17 @@ impl Context {
                     }
                 })
                 .ok();
+
+                token_usage::record_completion_usage(
+                    model,
+                    request,
+                    completion_usage,
+                    usage_source,
+                    project_root,
+                    &mut cx,
+                )
+                .log_err()
+                .await;
             }
         });
 
@@ -2523,6 +2650,423 @@ impl Context {
         }
     }
 
+    /// Returns the index of the branch the message starts and the number of branches, if the
+    /// conversation branches right before the message.
+    pub fn branch_for_message(
+        &self,
+        message_id: MessageId,
+        cx: &AppContext,
+    ) -> Option<(usize, usize)> {
+        let parent = self.previous_message_id(message_id, cx)?;
+        let branch_point = self
+            .branch_points
+            .iter()
+            .find(|branch_point| branch_point.parent == parent)?;
+        Some((branch_point.active_ix, branch_point.branches.len() + 1))
+    }
+
+    /// Starts a new branch of the conversation at the given message, containing a copy of the
+    /// message that can be edited. The message and the ones following it are kept as an
+    /// alternative branch.
+    pub fn fork_message(
+        &mut self,
+        message_id: MessageId,
+        cx: &mut ModelContext<Self>,
+    ) -> Option<MessageAnchor> {
+        let parent = self.previous_message_id(message_id, cx)?;
+        let branch = self.take_branch(parent, cx);
+        let message = branch.messages.first()?.clone();
+        let message_end = branch
+            .messages
+            .get(1)
+            .map_or(branch.text.len(), |next_message| next_message.start - 1);
+        let fork = SavedBranch {
+            text: branch.text[..message_end].to_string(),
+            messages: vec![message],
+            slash_command_output_sections: branch
+                .slash_command_output_sections
+                .iter()
+                .filter(|section| section.range.end <= message_end)
+                .cloned()
+                .collect(),
+            contents: branch
+                .contents
+                .iter()
+                .filter(|content| content.range.end <= message_end)
+                .cloned()
+                .collect(),
+            branch_points: Vec::new(),
+        };
+        self.push_inactive_branch(parent, branch);
+        let first_message = self.restore_branch(fork, cx);
+        self.push_branch_points_op(cx);
+        first_message
+    }
+
+    /// Requests a new response in place of the given assistant message, keeping the previous
+    /// response and the messages following it as an alternative branch.
+    pub fn regenerate_message(
+        &mut self,
+        message_id: MessageId,
+        cx: &mut ModelContext<Self>,
+    ) -> Option<MessageAnchor> {
+        let provider = LanguageModelRegistry::read_global(cx).active_provider()?;
+        LanguageModelRegistry::read_global(cx).active_model()?;
+        if !provider.is_authenticated(cx) {
+            return None;
+        }
+        if self.messages_metadata.get(&message_id)?.role != Role::Assistant {
+            return None;
+        }
+
+        // The response has to follow a message it can be regenerated from.
+        let parent = self.previous_message_id(message_id, cx)??;
+        let branch = self.take_branch(Some(parent), cx);
+        self.push_inactive_branch(Some(parent), branch);
+        self.push_branch_points_op(cx);
+        self.assist(cx)
+    }
+
+    /// Replaces the branch the given message starts with the one at the given index, keeping
+    /// the current branch as an alternative.
+    pub fn select_branch(
+        &mut self,
+        message_id: MessageId,
+        branch_ix: usize,
+        cx: &mut ModelContext<Self>,
+    ) -> Option<MessageAnchor> {
+        let parent = self.previous_message_id(message_id, cx)?;
+        let (active_ix, branch_count) = self.branch_for_message(message_id, cx)?;
+        if branch_ix == active_ix || branch_ix >= branch_count {
+            return None;
+        }
+
+        let current_branch = self.take_branch(parent, cx);
+        let branch_point = self
+            .branch_points
+            .iter_mut()
+            .find(|branch_point| branch_point.parent == parent)?;
+        branch_point
+            .branches
+            .insert(branch_point.active_ix, current_branch);
+        let branch = branch_point.branches.remove(branch_ix);
+        branch_point.active_ix = branch_ix;
+        let first_message = self.restore_branch(branch, cx);
+        self.push_branch_points_op(cx);
+        first_message
+    }
+
+    /// Returns the ID of the message before the given one, which is `None` for the first message.
+    fn previous_message_id(
+        &self,
+        message_id: MessageId,
+        cx: &AppContext,
+    ) -> Option<Option<MessageId>> {
+        let mut previous_message_id = None;
+        for message in self.messages(cx) {
+            if message.id == message_id {
+                return Some(previous_message_id);
+            }
+            previous_message_id = Some(message.id);
+        }
+        None
+    }
+
+    /// Removes the messages following the given one from the buffer, returning them along with
+    /// the branch points among them.
+    fn take_branch(
+        &mut self,
+        parent: Option<MessageId>,
+        cx: &mut ModelContext<Self>,
+    ) -> SavedBranch {
+        let messages = self.messages(cx).collect::<Vec<_>>();
+        let first_message_ix = match parent {
+            Some(parent) => messages
+                .iter()
+                .position(|message| message.id == parent)
+                .map_or(messages.len(), |ix| ix + 1),
+            None => 0,
+        };
+        let Some(first_message) = messages.get(first_message_ix) else {
+            return SavedBranch::default();
+        };
+
+        // Messages are separated by newlines, so the branch starts with the newline ending the
+        // parent message.
+        let start = if parent.is_some() {
+            first_message.offset_range.start - 1
+        } else {
+            0
+        };
+        let buffer = self.buffer.read(cx);
+        let text = buffer
+            .text_for_range(start..buffer.len())
+            .collect::<String>();
+        let branch_messages = messages[first_message_ix..]
+            .iter()
+            .map(|message| {
+                let mut metadata = self.messages_metadata[&message.id].clone();
+                if metadata.status == MessageStatus::Pending {
+                    metadata.status = MessageStatus::Canceled;
+                }
+                metadata.cache = None;
+                SavedMessage {
+                    id: message.id,
+                    start: message.offset_range.start - start,
+                    metadata,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let message_ids = branch_messages
+            .iter()
+            .map(|message| message.id)
+            .collect::<HashSet<_>>();
+        let (branch_points, other_branch_points): (Vec<_>, Vec<_>) =
+            mem::take(&mut self.branch_points)
+                .into_iter()
+                .partition(|branch_point| {
+                    branch_point
+                        .parent
+                        .map_or(false, |parent| message_ids.contains(&parent))
+                });
+        self.branch_points = other_branch_points;
+        self.pending_completions
+            .retain(|completion| !message_ids.contains(&completion.assistant_message_id));
+
+        // Sections and contents are taken along with the branch when their text is removed, which
+        // is decided by their anchors rather than their offsets.
+        let buffer = self.buffer.read(cx);
+        let section_ranges = self
+            .slash_command_output_sections
+            .iter()
+            .map(|section| {
+                section
+                    .range
+                    .start
+                    .is_valid(buffer)
+                    .then(|| section.range.to_offset(buffer))
+            })
+            .collect::<Vec<_>>();
+        let content_ranges = self
+            .contents
+            .iter()
+            .map(|content| {
+                let range = content.range();
+                range
+                    .start
+                    .is_valid(buffer)
+                    .then(|| range.to_offset(buffer))
+            })
+            .collect::<Vec<_>>();
+        let end = buffer.len();
+        self.edit_branch(start..end, "", cx);
+
+        let buffer = self.buffer.read(cx);
+        let mut slash_command_output_sections = Vec::new();
+        let mut section_ranges = section_ranges.into_iter();
+        self.slash_command_output_sections.retain(|section| {
+            let Some(range) = section_ranges.next().flatten() else {
+                return true;
+            };
+            if section.range.start.is_valid(buffer) || range.start < start {
+                return true;
+            }
+            slash_command_output_sections.push(SlashCommandOutputSection {
+                range: range.start - start..range.end - start,
+                icon: section.icon,
+                label: section.label.clone(),
+            });
+            false
+        });
+        let mut contents = Vec::new();
+        let mut content_ranges = content_ranges.into_iter();
+        self.contents.retain(|content| {
+            let Some(range) = content_ranges.next().flatten() else {
+                return true;
+            };
+            if content.range().start.is_valid(buffer) || range.start < start {
+                return true;
+            }
+            contents.push(SavedContent {
+                range: range.start - start..range.end - start,
+                content: content.clone(),
+            });
+            false
+        });
+
+        SavedBranch {
+            text,
+            messages: branch_messages,
+            slash_command_output_sections,
+            contents,
+            branch_points,
+        }
+    }
+
+    /// Appends a branch taken with [`Self::take_branch`] to the buffer, returning its first
+    /// message. The messages are given new IDs.
+    fn restore_branch(
+        &mut self,
+        branch: SavedBranch,
+        cx: &mut ModelContext<Self>,
+    ) -> Option<MessageAnchor> {
+        let start = self.buffer.read(cx).len();
+        self.edit_branch(start..start, &branch.text, cx);
+
+        let mut first_message = None;
+        let mut message_ids = HashMap::default();
+        for message in branch.messages {
+            let anchor = if start + message.start == 0 {
+                // The first message of the conversation is never removed from the buffer.
+                let anchor = self.message_anchors.first()?.clone();
+                let metadata = message.metadata;
+                self.update_metadata(anchor.id, cx, |first_metadata| {
+                    first_metadata.role = metadata.role;
+                    first_metadata.status = metadata.status;
+                });
+                anchor
+            } else {
+                let version = self.version.clone();
+                let anchor = MessageAnchor {
+                    id: MessageId(self.next_timestamp()),
+                    start: self.buffer.read(cx).anchor_before(start + message.start),
+                };
+                let metadata = MessageMetadata {
+                    role: message.metadata.role,
+                    status: message.metadata.status,
+                    timestamp: anchor.id.0,
+                    cache: None,
+                };
+                self.insert_message(anchor.clone(), metadata.clone(), cx);
+                self.push_op(
+                    ContextOperation::InsertMessage {
+                        anchor: anchor.clone(),
+                        metadata,
+                        version,
+                    },
+                    cx,
+                );
+                anchor
+            };
+            message_ids.insert(message.id, anchor.id);
+            first_message.get_or_insert(anchor);
+        }
+
+        for mut branch_point in branch.branch_points {
+            branch_point.parent = branch_point
+                .parent
+                .and_then(|parent| message_ids.get(&parent).copied());
+            self.branch_points.push(branch_point);
+        }
+
+        let buffer = self.buffer.read(cx);
+        let contents = branch
+            .contents
+            .into_iter()
+            .map(|content| {
+                content.content.with_range(
+                    buffer.anchor_after(start + content.range.start)
+                        ..buffer.anchor_before(start + content.range.end),
+                )
+            })
+            .collect::<Vec<_>>();
+        for content in contents {
+            self.insert_content(content, cx);
+        }
+
+        if !branch.slash_command_output_sections.is_empty() {
+            let version = self.version.clone();
+            let command_id = SlashCommandId(self.next_timestamp());
+            let buffer = self.buffer.read(cx);
+            let sections = branch
+                .slash_command_output_sections
+                .into_iter()
+                .map(|section| SlashCommandOutputSection {
+                    range: buffer.anchor_after(start + section.range.start)
+                        ..buffer.anchor_before(start + section.range.end),
+                    icon: section.icon,
+                    label: section.label,
+                })
+                .collect::<Vec<_>>();
+            let output_range = buffer.anchor_after(start)..buffer.anchor_before(buffer.len());
+            self.slash_command_output_sections
+                .extend(sections.iter().cloned());
+            self.slash_command_output_sections
+                .sort_by(|a, b| a.range.cmp(&b.range, buffer));
+            self.finished_slash_commands.insert(command_id);
+
+            self.push_op(
+                ContextOperation::SlashCommandFinished {
+                    id: command_id,
+                    output_range: output_range.clone(),
+                    sections: sections.clone(),
+                    version,
+                },
+                cx,
+            );
+            cx.emit(ContextEvent::SlashCommandFinished {
+                output_range,
+                sections,
+                run_commands_in_output: false,
+                expand_result: false,
+            });
+        }
+
+        self.count_remaining_tokens(cx);
+        cx.emit(ContextEvent::MessagesEdited);
+        cx.notify();
+        first_message
+    }
+
+    /// Edits the buffer outside of its undo history, so that undoing doesn't bring back the text
+    /// of a branch that was switched away from.
+    fn edit_branch(&mut self, range: Range<usize>, new_text: &str, cx: &mut ModelContext<Self>) {
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.finalize_last_transaction();
+            buffer.start_transaction();
+            buffer.edit([(range, new_text)], None, cx);
+            if let Some(transaction_id) = buffer.end_transaction(cx) {
+                buffer.forget_transaction(transaction_id);
+            }
+        });
+    }
+
+    /// Sends the branch points to the other replicas after they changed.
+    fn push_branch_points_op(&mut self, cx: &mut ModelContext<Self>) {
+        let version = self.version.clone();
+        let timestamp = self.next_timestamp();
+        self.branch_points_timestamp = timestamp;
+        self.push_op(
+            ContextOperation::UpdateBranchPoints {
+                branch_points: self.branch_points.clone(),
+                timestamp,
+                version,
+            },
+            cx,
+        );
+    }
+
+    /// Keeps a branch taken with [`Self::take_branch`] as an alternative to the one that's about
+    /// to replace it, which becomes the last branch.
+    fn push_inactive_branch(&mut self, parent: Option<MessageId>, branch: SavedBranch) {
+        let branch_point_ix = self
+            .branch_points
+            .iter()
+            .position(|branch_point| branch_point.parent == parent)
+            .unwrap_or_else(|| {
+                self.branch_points.push(SavedBranchPoint {
+                    parent,
+                    branches: Vec::new(),
+                    active_ix: 0,
+                });
+                self.branch_points.len() - 1
+            });
+        let branch_point = &mut self.branch_points[branch_point_ix];
+        branch_point.branches.insert(branch_point.active_ix, branch);
+        branch_point.active_ix = branch_point.branches.len();
+    }
+
     fn insert_message(
         &mut self,
         new_anchor: MessageAnchor,
@@ -2567,9 +3111,20 @@ impl Context {
                 cache: false,
             });
 
+            let usage_source = self.token_usage_source();
+            let project_root = self
+                .project
+                .as_ref()
+                .and_then(|project| token_usage::project_root(project, cx));
             self.pending_summary = cx.spawn(|this, mut cx| {
                 async move {
-                    let stream = model.stream_completion_text(request, &cx);
+                    let stream = token_usage::stream_completion_text(
+                        model,
+                        request,
+                        usage_source,
+                        project_root,
+                        &cx,
+                    );
                     let mut messages = stream.await?;
 
                     let mut replaced = !replace_old;
@@ -2783,6 +3338,96 @@ impl Context {
         });
     }
 
+    /// Renders the messages of the active branch as a Markdown document, with a heading per
+    /// message naming its role.
+    pub fn to_markdown(&self, cx: &AppContext) -> String {
+        let transcript = self.to_transcript(cx);
+        let mut markdown = String::new();
+        if let Some(title) = transcript.title.as_ref() {
+            writeln!(markdown, "# {}\n", title).unwrap();
+        }
+        for message in &transcript.messages {
+            let role = match message.role {
+                Role::User => "User",
+                Role::Assistant => "Assistant",
+                Role::System => "System",
+            };
+            writeln!(markdown, "## {}\n", role).unwrap();
+            if !message.content.is_empty() {
+                writeln!(markdown, "{}\n", message.content).unwrap();
+            }
+        }
+        markdown
+    }
+
+    /// Returns the messages of the active branch in a format that doesn't depend on this
+    /// context's operations, so it can be shared and imported elsewhere.
+    pub fn to_transcript(&self, cx: &AppContext) -> ContextTranscript {
+        let buffer = self.buffer.read(cx);
+        ContextTranscript {
+            zed: ContextTranscript::KIND.into(),
+            version: ContextTranscript::VERSION.into(),
+            title: self
+                .summary
+                .as_ref()
+                .filter(|summary| summary.done)
+                .map(|summary| summary.text.clone()),
+            messages: self
+                .messages(cx)
+                .map(|message| TranscriptMessage {
+                    role: message.role,
+                    content: buffer
+                        .text_for_range(message.offset_range)
+                        .collect::<String>()
+                        .trim_end_matches('\n')
+                        .to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Replaces the contents of a new context with the messages of the given transcript.
+    pub fn import_transcript(
+        &mut self,
+        transcript: ContextTranscript,
+        cx: &mut ModelContext<Self>,
+    ) -> Result<()> {
+        let mut messages = transcript.messages.into_iter();
+        let first_message = messages
+            .next()
+            .ok_or_else(|| anyhow!("transcript has no messages"))?;
+        let first_message_id = self
+            .message_anchors
+            .first()
+            .map(|message| message.id)
+            .ok_or_else(|| anyhow!("context has no messages"))?;
+
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.edit([(0..buffer.len(), first_message.content)], None, cx)
+        });
+        self.update_metadata(first_message_id, cx, |metadata| {
+            metadata.role = first_message.role;
+            metadata.status = MessageStatus::Done;
+        });
+
+        let mut last_message_id = first_message_id;
+        for message in messages {
+            let anchor = self
+                .insert_message_after(last_message_id, message.role, MessageStatus::Done, cx)
+                .ok_or_else(|| anyhow!("failed to insert message"))?;
+            self.buffer.update(cx, |buffer, cx| {
+                let offset = anchor.start.to_offset(buffer);
+                buffer.edit([(offset..offset, message.content)], None, cx)
+            });
+            last_message_id = anchor.id;
+        }
+
+        if let Some(title) = transcript.title {
+            self.custom_summary(title, cx);
+        }
+        Ok(())
+    }
+
     pub(crate) fn custom_summary(&mut self, custom_summary: String, cx: &mut ModelContext<Self>) {
         let timestamp = self.next_timestamp();
         let summary = self.summary.get_or_insert(ContextSummary::default());
@@ -2863,7 +3508,7 @@ impl PendingToolUseStatus {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SavedMessage {
     pub id: MessageId,
     pub start: usize,
@@ -2880,10 +3525,159 @@ pub struct SavedContext {
     pub summary: String,
     pub slash_command_output_sections:
         Vec<assistant_slash_command::SlashCommandOutputSection<usize>>,
+    pub branch_points: Vec<SavedBranchPoint>,
+}
+
+/// A message after which the conversation continues in several ways. The active branch is the
+/// one in the buffer, and the others are kept aside.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedBranchPoint {
+    /// The message the branches follow, or `None` if they start the conversation.
+    pub parent: Option<MessageId>,
+    /// The inactive branches, in order.
+    pub branches: Vec<SavedBranch>,
+    /// The index of the active branch among all of them.
+    pub active_ix: usize,
+}
+
+impl SavedBranchPoint {
+    fn from_proto(branch_point: proto::ContextBranchPoint) -> Result<Self> {
+        Ok(Self {
+            parent: branch_point
+                .parent
+                .map(|parent| MessageId(language::proto::deserialize_timestamp(parent))),
+            branches: branch_point
+                .branches
+                .into_iter()
+                .map(SavedBranch::from_proto)
+                .collect::<Result<Vec<_>>>()?,
+            active_ix: branch_point.active_ix as usize,
+        })
+    }
+
+    fn to_proto(&self) -> proto::ContextBranchPoint {
+        proto::ContextBranchPoint {
+            parent: self
+                .parent
+                .map(|parent| language::proto::serialize_timestamp(parent.0)),
+            branches: self.branches.iter().map(SavedBranch::to_proto).collect(),
+            active_ix: self.active_ix as u64,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SavedBranch {
+    pub text: String,
+    /// The branch's messages, whose starts are relative to the branch's text.
+    pub messages: Vec<SavedMessage>,
+    /// The slash command output sections in the branch, relative to the branch's text.
+    #[serde(default)]
+    pub slash_command_output_sections: Vec<SlashCommandOutputSection<usize>>,
+    /// The contents of the branch's messages, relative to the branch's text. Like the contents
+    /// of the active branch, they aren't saved or replicated.
+    #[serde(skip)]
+    pub contents: Vec<SavedContent>,
+    /// The branch points following the branch's messages.
+    pub branch_points: Vec<SavedBranchPoint>,
+}
+
+impl SavedBranch {
+    fn from_proto(branch: proto::ContextBranch) -> Result<Self> {
+        Ok(Self {
+            text: branch.text,
+            messages: branch
+                .messages
+                .into_iter()
+                .map(|message| {
+                    Ok(SavedMessage {
+                        id: MessageId(language::proto::deserialize_timestamp(
+                            message.id.context("invalid id")?,
+                        )),
+                        start: message.start as usize,
+                        metadata: MessageMetadata {
+                            role: Role::from_proto(message.role),
+                            status: MessageStatus::from_proto(
+                                message.status.context("invalid status")?,
+                            ),
+                            timestamp: language::proto::deserialize_timestamp(
+                                message.timestamp.context("invalid timestamp")?,
+                            ),
+                            cache: None,
+                        },
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            slash_command_output_sections: branch
+                .sections
+                .into_iter()
+                .map(|section| {
+                    let range = section.range.context("invalid range")?;
+                    Ok(SlashCommandOutputSection {
+                        range: range.start as usize..range.end as usize,
+                        icon: section.icon_name.parse()?,
+                        label: section.label.into(),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            contents: Vec::new(),
+            branch_points: branch
+                .branch_points
+                .into_iter()
+                .map(SavedBranchPoint::from_proto)
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+
+    fn to_proto(&self) -> proto::ContextBranch {
+        proto::ContextBranch {
+            text: self.text.clone(),
+            messages: self
+                .messages
+                .iter()
+                .map(|message| proto::ContextBranchMessage {
+                    id: Some(language::proto::serialize_timestamp(message.id.0)),
+                    start: message.start as u64,
+                    role: message.metadata.role.to_proto() as i32,
+                    status: Some(message.metadata.status.to_proto()),
+                    timestamp: Some(language::proto::serialize_timestamp(
+                        message.metadata.timestamp,
+                    )),
+                })
+                .collect(),
+            sections: self
+                .slash_command_output_sections
+                .iter()
+                .map(|section| {
+                    let icon_name: &'static str = section.icon.into();
+                    proto::ContextBranchSection {
+                        range: Some(proto::Range {
+                            start: section.range.start as u64,
+                            end: section.range.end as u64,
+                        }),
+                        icon_name: icon_name.to_string(),
+                        label: section.label.to_string(),
+                    }
+                })
+                .collect(),
+            branch_points: self
+                .branch_points
+                .iter()
+                .map(SavedBranchPoint::to_proto)
+                .collect(),
+        }
+    }
+}
+
+/// A [`Content`] taken along with a branch, whose range is relative to the branch's text.
+#[derive(Clone, Debug)]
+pub struct SavedContent {
+    pub range: Range<usize>,
+    pub content: Content,
 }
 
 impl SavedContext {
-    pub const VERSION: &'static str = "0.4.0";
+    pub const VERSION: &'static str = "0.5.0";
 
     pub fn from_json(json: &str) -> Result<Self> {
         let saved_context_json = serde_json::from_str::<serde_json::Value>(json)?;
@@ -2895,6 +3689,11 @@ impl SavedContext {
                 SavedContext::VERSION => {
                     Ok(serde_json::from_value::<SavedContext>(saved_context_json)?)
                 }
+                SavedContextV0_4_0::VERSION => {
+                    let saved_context =
+                        serde_json::from_value::<SavedContextV0_4_0>(saved_context_json)?;
+                    Ok(saved_context.upgrade())
+                }
                 SavedContextV0_3_0::VERSION => {
                     let saved_context =
                         serde_json::from_value::<SavedContextV0_3_0>(saved_context_json)?;
@@ -2984,6 +3783,14 @@ impl SavedContext {
         });
         version.observe(timestamp);
 
+        let timestamp = next_timestamp.tick();
+        operations.push(ContextOperation::UpdateBranchPoints {
+            branch_points: self.branch_points,
+            timestamp,
+            version: version.clone(),
+        });
+        version.observe(timestamp);
+
         let timestamp = next_timestamp.tick();
         operations.push(ContextOperation::UpdateSummary {
             summary: ContextSummary {
@@ -2999,6 +3806,34 @@ impl SavedContext {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+struct SavedContextV0_4_0 {
+    id: Option<ContextId>,
+    zed: String,
+    version: String,
+    text: String,
+    messages: Vec<SavedMessage>,
+    summary: String,
+    slash_command_output_sections: Vec<assistant_slash_command::SlashCommandOutputSection<usize>>,
+}
+
+impl SavedContextV0_4_0 {
+    const VERSION: &'static str = "0.4.0";
+
+    fn upgrade(self) -> SavedContext {
+        SavedContext {
+            id: self.id,
+            zed: self.zed,
+            version: SavedContext::VERSION.into(),
+            text: self.text,
+            messages: self.messages,
+            summary: self.summary,
+            slash_command_output_sections: self.slash_command_output_sections,
+            branch_points: Vec::new(),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 struct SavedMessageIdPreV0_4_0(usize);
 
@@ -3030,10 +3865,10 @@ impl SavedContextV0_3_0 {
     const VERSION: &'static str = "0.3.0";
 
     fn upgrade(self) -> SavedContext {
-        SavedContext {
+        SavedContextV0_4_0 {
             id: self.id,
             zed: self.zed,
-            version: SavedContext::VERSION.into(),
+            version: SavedContextV0_4_0::VERSION.into(),
             text: self.text,
             messages: self
                 .messages
@@ -3059,6 +3894,7 @@ impl SavedContextV0_3_0 {
             summary: self.summary,
             slash_command_output_sections: self.slash_command_output_sections,
         }
+        .upgrade()
     }
 }
 
@@ -3127,3 +3963,40 @@ pub struct SavedContextMetadata {
     pub path: PathBuf,
     pub mtime: chrono::DateTime<chrono::Local>,
 }
+
+/// A portable copy of a context's messages, used to share conversations outside of the
+/// contexts directory. Unlike [`SavedContext`], it doesn't record operations, branches or
+/// slash command sections, so it stays readable and stable across versions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContextTranscript {
+    pub zed: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+impl ContextTranscript {
+    pub const KIND: &'static str = "context-transcript";
+    pub const VERSION: &'static str = "1.0.0";
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let transcript = serde_json::from_str::<ContextTranscript>(json)?;
+        if transcript.zed != Self::KIND {
+            return Err(anyhow!("not a context transcript"));
+        }
+        if transcript.version != Self::VERSION {
+            return Err(anyhow!(
+                "unrecognized context transcript version: {}",
+                transcript.version
+            ));
+        }
+        Ok(transcript)
+    }
+}
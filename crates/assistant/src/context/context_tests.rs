@@ -1,8 +1,8 @@
-use super::{MessageCacheMetadata, WorkflowStepEdit};
+use super::{Content, MessageCacheMetadata, WorkflowStepEdit};
 use crate::{
     assistant_panel, prompt_library, slash_command::file_command, CacheStatus, Context,
-    ContextEvent, ContextId, ContextOperation, MessageId, MessageStatus, PromptBuilder,
-    WorkflowStepEditKind,
+    ContextEvent, ContextId, ContextOperation, ContextTranscript, MessageId, MessageStatus,
+    PromptBuilder, TranscriptMessage, WorkflowStepEditKind,
 };
 use anyhow::Result;
 use assistant_slash_command::{
@@ -949,6 +949,280 @@ async fn test_serialization(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+fn test_branching(cx: &mut AppContext) {
+    let settings_store = SettingsStore::test(cx);
+    LanguageModelRegistry::test(cx);
+    cx.set_global(settings_store);
+    assistant_panel::init(cx);
+    let registry = Arc::new(LanguageRegistry::test(cx.background_executor().clone()));
+    let prompt_builder = Arc::new(PromptBuilder::new(None).unwrap());
+    let context =
+        cx.new_model(|cx| Context::local(registry.clone(), None, None, prompt_builder.clone(), cx));
+    let buffer = context.read(cx).buffer.clone();
+
+    let message_1 = context.read(cx).message_anchors[0].clone();
+    let message_2 = context.update(cx, |context, cx| {
+        context
+            .insert_message_after(message_1.id, Role::Assistant, MessageStatus::Done, cx)
+            .unwrap()
+    });
+    let message_3 = context.update(cx, |context, cx| {
+        context
+            .insert_message_after(message_2.id, Role::User, MessageStatus::Done, cx)
+            .unwrap()
+    });
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..0, "a"), (1..1, "b"), (2..2, "c")], None, cx)
+    });
+    assert_eq!(buffer.read(cx).text(), "a\nb\nc");
+    assert_eq!(context.read(cx).branch_for_message(message_2.id, cx), None);
+
+    // Forking a message keeps a copy of it, without the messages following it.
+    let fork = context
+        .update(cx, |context, cx| context.fork_message(message_2.id, cx))
+        .unwrap();
+    assert_eq!(buffer.read(cx).text(), "a\nb");
+    assert_eq!(
+        messages(&context, cx),
+        vec![
+            (message_1.id, Role::User, 0..2),
+            (fork.id, Role::Assistant, 2..3),
+        ]
+    );
+    assert_eq!(
+        context.read(cx).branch_for_message(fork.id, cx),
+        Some((1, 2))
+    );
+    buffer.update(cx, |buffer, cx| buffer.edit([(3..3, "!")], None, cx));
+
+    // Selecting the first branch restores the original messages, keeping the fork.
+    let restored = context
+        .update(cx, |context, cx| context.select_branch(fork.id, 0, cx))
+        .unwrap();
+    assert_eq!(buffer.read(cx).text(), "a\nb\nc");
+    let restored_messages = messages(&context, cx);
+    assert_eq!(
+        restored_messages
+            .iter()
+            .map(|(_, role, range)| (*role, range.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (Role::User, 0..2),
+            (Role::Assistant, 2..4),
+            (Role::User, 4..5)
+        ]
+    );
+    assert_eq!(restored_messages[1].0, restored.id);
+    assert_ne!(restored.id, message_2.id);
+    assert_ne!(restored_messages[2].0, message_3.id);
+    assert_eq!(
+        context.read(cx).branch_for_message(restored.id, cx),
+        Some((0, 2))
+    );
+
+    // Branches are saved with the context.
+    let deserialized_context = cx.new_model(|cx| {
+        Context::deserialize(
+            context.read(cx).serialize(cx),
+            Default::default(),
+            registry.clone(),
+            prompt_builder.clone(),
+            None,
+            None,
+            cx,
+        )
+    });
+    assert_eq!(
+        deserialized_context
+            .read(cx)
+            .branch_for_message(restored.id, cx),
+        Some((0, 2))
+    );
+    deserialized_context.update(cx, |context, cx| context.select_branch(restored.id, 1, cx));
+    assert_eq!(
+        deserialized_context.read(cx).buffer.read(cx).text(),
+        "a\nb!"
+    );
+
+    // Regenerating a response keeps the previous one as a branch.
+    context.update(cx, |context, cx| {
+        context.regenerate_message(restored.id, cx)
+    });
+    assert_eq!(buffer.read(cx).text(), "a\n\n");
+    let regenerated_messages = messages(&context, cx);
+    assert_eq!(
+        regenerated_messages
+            .iter()
+            .map(|(_, role, range)| (*role, range.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (Role::User, 0..2),
+            (Role::Assistant, 2..3),
+            (Role::User, 3..3)
+        ]
+    );
+    assert_eq!(
+        context
+            .read(cx)
+            .branch_for_message(regenerated_messages[1].0, cx),
+        Some((2, 3))
+    );
+}
+
+#[gpui::test]
+fn test_branching_sections_contents_and_replication(cx: &mut AppContext) {
+    let settings_store = SettingsStore::test(cx);
+    LanguageModelRegistry::test(cx);
+    cx.set_global(settings_store);
+    assistant_panel::init(cx);
+    let registry = Arc::new(LanguageRegistry::test(cx.background_executor().clone()));
+    let prompt_builder = Arc::new(PromptBuilder::new(None).unwrap());
+    let context_id = ContextId::new();
+    let mut contexts = Vec::new();
+    let mut operations = Vec::new();
+    for replica_id in 0..2 {
+        let context = cx.new_model(|cx| {
+            Context::new(
+                context_id.clone(),
+                replica_id,
+                language::Capability::ReadWrite,
+                registry.clone(),
+                prompt_builder.clone(),
+                None,
+                None,
+                cx,
+            )
+        });
+        let context_operations = Rc::new(RefCell::new(Vec::new()));
+        cx.subscribe(&context, {
+            let context_operations = context_operations.clone();
+            move |_, event, _| {
+                if let ContextEvent::Operation(op) = event {
+                    context_operations.borrow_mut().push(op.to_proto());
+                }
+            }
+        })
+        .detach();
+        contexts.push(context);
+        operations.push(context_operations);
+    }
+    let sync = |from: usize, to: usize, cx: &mut AppContext| {
+        let ops = operations[from]
+            .borrow_mut()
+            .drain(..)
+            .map(ContextOperation::from_proto)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        contexts[to]
+            .update(cx, |context, cx| context.apply_ops(ops, cx))
+            .unwrap();
+    };
+    let host = contexts[0].clone();
+    let guest = contexts[1].clone();
+    let buffer = host.read(cx).buffer.clone();
+
+    let message_1 = host.read(cx).message_anchors[0].clone();
+    let message_2 = host.update(cx, |context, cx| {
+        context
+            .insert_message_after(message_1.id, Role::Assistant, MessageStatus::Done, cx)
+            .unwrap()
+    });
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..0, "a"), (1..1, "bcd")], None, cx)
+    });
+    assert_eq!(buffer.read(cx).text(), "a\nbcd");
+    host.update(cx, |context, cx| {
+        let buffer = context.buffer.read(cx);
+        let range = buffer.anchor_after(3)..buffer.anchor_before(5);
+        context
+            .slash_command_output_sections
+            .push(SlashCommandOutputSection {
+                range,
+                icon: ui::IconName::Ai,
+                label: "section".into(),
+            });
+        let range = buffer.anchor_after(2)..buffer.anchor_before(5);
+        context.insert_content(
+            Content::ToolResult {
+                range,
+                tool_use_id: "tool".into(),
+            },
+            cx,
+        );
+    });
+
+    // Forking a message copies its sections and contents.
+    let fork = host
+        .update(cx, |context, cx| context.fork_message(message_2.id, cx))
+        .unwrap();
+    assert_eq!(buffer.read(cx).text(), "a\nbcd");
+    assert_eq!(
+        sections(&host, cx),
+        vec![(3..5, SharedString::from("section"))]
+    );
+    assert_eq!(contents(&host, cx), vec![2..5]);
+    buffer.update(cx, |buffer, cx| buffer.edit([(5..5, "!")], None, cx));
+
+    // Switching branches takes the sections and contents along with their text.
+    let restored = host
+        .update(cx, |context, cx| context.select_branch(fork.id, 0, cx))
+        .unwrap();
+    assert_eq!(buffer.read(cx).text(), "a\nbcd");
+    assert_eq!(
+        sections(&host, cx),
+        vec![(3..5, SharedString::from("section"))]
+    );
+    assert_eq!(contents(&host, cx), vec![2..5]);
+    assert_eq!(
+        host.read(cx).branch_for_message(restored.id, cx),
+        Some((0, 2))
+    );
+
+    // Undoing doesn't bring back the text of the other branch.
+    buffer.update(cx, |buffer, cx| buffer.undo(cx));
+    assert_eq!(buffer.read(cx).text(), "a\nbcd");
+    assert_eq!(
+        messages(&host, cx),
+        vec![
+            (message_1.id, Role::User, 0..2),
+            (restored.id, Role::Assistant, 2..5)
+        ]
+    );
+    assert_eq!(
+        host.read(cx).branch_for_message(restored.id, cx),
+        Some((0, 2))
+    );
+
+    // Branch points and restored sections are replicated.
+    sync(0, 1, cx);
+    assert_eq!(guest.read(cx).buffer.read(cx).text(), "a\nbcd");
+    assert_eq!(messages(&guest, cx), messages(&host, cx));
+    assert_eq!(
+        guest.read(cx).branch_for_message(restored.id, cx),
+        Some((0, 2))
+    );
+    assert_eq!(
+        sections(&guest, cx),
+        vec![(3..5, SharedString::from("section"))]
+    );
+
+    guest.update(cx, |context, cx| context.select_branch(restored.id, 1, cx));
+    assert_eq!(guest.read(cx).buffer.read(cx).text(), "a\nbcd!");
+    sync(1, 0, cx);
+    assert_eq!(buffer.read(cx).text(), "a\nbcd!");
+    assert_eq!(messages(&host, cx), messages(&guest, cx));
+    let fork_message_id = messages(&host, cx)[1].0;
+    assert_eq!(
+        host.read(cx).branch_for_message(fork_message_id, cx),
+        Some((1, 2))
+    );
+    assert_eq!(
+        sections(&host, cx),
+        vec![(3..5, SharedString::from("section"))]
+    );
+}
+
 #[gpui::test(iterations = 100)]
 async fn test_random_context_collaboration(cx: &mut TestAppContext, mut rng: StdRng) {
     let min_peers = env::var("MIN_PEERS")
@@ -1373,6 +1647,70 @@ fn test_mark_cache_anchors(cx: &mut AppContext) {
     );
 }
 
+#[gpui::test]
+fn test_transcript_export_and_import(cx: &mut AppContext) {
+    let settings_store = SettingsStore::test(cx);
+    LanguageModelRegistry::test(cx);
+    cx.set_global(settings_store);
+    assistant_panel::init(cx);
+    let registry = Arc::new(LanguageRegistry::test(cx.background_executor().clone()));
+    let prompt_builder = Arc::new(PromptBuilder::new(None).unwrap());
+
+    let transcript = ContextTranscript {
+        zed: ContextTranscript::KIND.into(),
+        version: ContextTranscript::VERSION.into(),
+        title: Some("Debugging the migration".into()),
+        messages: vec![
+            TranscriptMessage {
+                role: Role::System,
+                content: "Be brief.".into(),
+            },
+            TranscriptMessage {
+                role: Role::User,
+                content: "Why does the migration hang?\nIt never finishes.".into(),
+            },
+            TranscriptMessage {
+                role: Role::Assistant,
+                content: "The lock isn't released.".into(),
+            },
+        ],
+    };
+    let json = serde_json::to_string(&transcript).unwrap();
+    assert_eq!(ContextTranscript::from_json(&json).unwrap(), transcript);
+    assert!(ContextTranscript::from_json(&json.replace("1.0.0", "0.1.0")).is_err());
+
+    let context =
+        cx.new_model(|cx| Context::local(registry.clone(), None, None, prompt_builder.clone(), cx));
+    context
+        .update(cx, |context, cx| {
+            context.import_transcript(transcript.clone(), cx)
+        })
+        .unwrap();
+    assert_eq!(
+        context.read(cx).buffer.read(cx).text(),
+        "Be brief.\nWhy does the migration hang?\nIt never finishes.\nThe lock isn't released."
+    );
+    assert_eq!(
+        messages(&context, cx)
+            .into_iter()
+            .map(|(_, role, range)| (role, range))
+            .collect::<Vec<_>>(),
+        [
+            (Role::System, 0..10),
+            (Role::User, 10..58),
+            (Role::Assistant, 58..82),
+        ]
+    );
+    assert_eq!(context.read(cx).to_transcript(cx), transcript);
+    assert_eq!(
+        context.read(cx).to_markdown(cx),
+        "# Debugging the migration\n\n\
+         ## System\n\nBe brief.\n\n\
+         ## User\n\nWhy does the migration hang?\nIt never finishes.\n\n\
+         ## Assistant\n\nThe lock isn't released.\n\n"
+    );
+}
+
 fn messages(context: &Model<Context>, cx: &AppContext) -> Vec<(MessageId, Role, Range<usize>)> {
     context
         .read(cx)
@@ -1381,6 +1719,26 @@ fn messages(context: &Model<Context>, cx: &AppContext) -> Vec<(MessageId, Role,
         .collect()
 }
 
+fn sections(context: &Model<Context>, cx: &AppContext) -> Vec<(Range<usize>, SharedString)> {
+    let context = context.read(cx);
+    let buffer = context.buffer.read(cx);
+    context
+        .slash_command_output_sections
+        .iter()
+        .filter(|section| section.range.start.is_valid(buffer))
+        .map(|section| (section.range.to_offset(buffer), section.label.clone()))
+        .collect()
+}
+
+fn contents(context: &Model<Context>, cx: &AppContext) -> Vec<Range<usize>> {
+    let buffer = context.read(cx).buffer.read(cx);
+    context
+        .read(cx)
+        .contents(cx)
+        .map(|content| content.range().to_offset(buffer))
+        .collect()
+}
+
 fn messages_cache(
     context: &Model<Context>,
     cx: &AppContext,
@@ -0,0 +1,129 @@
+//! Handles context servers' requests to sample the active language model.
+
+use std::fmt::Write;
+
+use crate::token_usage::{self, TokenUsageSource};
+use anyhow::{anyhow, Context as _, Result};
+use context_servers::types::{
+    CreateMessageParams, CreateMessageResult, SamplingContent, SamplingRole,
+};
+use futures::StreamExt;
+use gpui::{AsyncAppContext, PromptLevel, Task};
+use language_model::{
+    LanguageModelRegistry, LanguageModelRequest, LanguageModelRequestMessage, MessageContent, Role,
+};
+use workspace::Workspace;
+
+/// Completes the messages sent by a context server with the active language model, once the
+/// user approves it.
+pub(crate) fn create_message(
+    server_id: String,
+    params: CreateMessageParams,
+    cx: AsyncAppContext,
+) -> Task<Result<CreateMessageResult>> {
+    cx.spawn(|mut cx| async move {
+        let request = build_request(&params)?;
+
+        let window = cx
+            .update(|cx| cx.active_window())?
+            .context("no window to ask for approval in")?;
+        let answer = window
+            .update(&mut cx, |_, cx| {
+                cx.prompt(
+                    PromptLevel::Info,
+                    &format!("Allow the {server_id} context server to use the language model?"),
+                    Some(&describe_request(&params)),
+                    &["Allow", "Deny"],
+                )
+            })?
+            .await?;
+        if answer != 0 {
+            return Err(anyhow!("The user declined the request"));
+        }
+
+        let model = cx
+            .update(|cx| LanguageModelRegistry::read_global(cx).active_model())?
+            .context("no language model is configured")?;
+        let project_root = window
+            .downcast::<Workspace>()
+            .and_then(|workspace| {
+                workspace
+                    .read_with(&cx, |workspace, cx| {
+                        token_usage::project_root(workspace.project(), cx)
+                    })
+                    .ok()
+            })
+            .flatten();
+        let mut chunks = token_usage::stream_completion_text(
+            model.clone(),
+            request,
+            TokenUsageSource::ContextServer { server_id },
+            project_root,
+            &cx,
+        )
+        .await?;
+        let mut text = String::new();
+        while let Some(chunk) = chunks.next().await {
+            text.push_str(&chunk?);
+        }
+
+        Ok(CreateMessageResult {
+            role: SamplingRole::Assistant,
+            content: SamplingContent::Text { text },
+            model: model.id().0.to_string(),
+            stop_reason: Some("endTurn".into()),
+        })
+    })
+}
+
+fn build_request(params: &CreateMessageParams) -> Result<LanguageModelRequest> {
+    let mut messages = Vec::new();
+    if let Some(system_prompt) = &params.system_prompt {
+        messages.push(LanguageModelRequestMessage {
+            role: Role::System,
+            content: vec![MessageContent::Text(system_prompt.clone())],
+            cache: false,
+        });
+    }
+    for message in &params.messages {
+        let SamplingContent::Text { text } = &message.content else {
+            return Err(anyhow!("Only text messages can be sampled"));
+        };
+        messages.push(LanguageModelRequestMessage {
+            role: match message.role {
+                SamplingRole::User => Role::User,
+                SamplingRole::Assistant => Role::Assistant,
+            },
+            content: vec![MessageContent::Text(text.clone())],
+            cache: false,
+        });
+    }
+
+    Ok(LanguageModelRequest {
+        messages,
+        tools: Vec::new(),
+        stop: params.stop_sequences.clone(),
+        temperature: params.temperature.unwrap_or(1.0),
+    })
+}
+
+/// Describes the messages to complete, so the user can decide whether to allow it.
+fn describe_request(params: &CreateMessageParams) -> String {
+    let mut description = String::new();
+    if let Some(system_prompt) = &params.system_prompt {
+        writeln!(description, "System: {system_prompt}").unwrap();
+    }
+    for message in &params.messages {
+        let role = match message.role {
+            SamplingRole::User => "User",
+            SamplingRole::Assistant => "Assistant",
+        };
+        match &message.content {
+            SamplingContent::Text { text } => writeln!(description, "{role}: {text}").unwrap(),
+            SamplingContent::Image { mime_type, .. } => {
+                writeln!(description, "{role}: [{mime_type} image]").unwrap()
+            }
+        }
+    }
+    description
+}
@@ -1,11 +1,12 @@
 use crate::{
-    prompts::PromptBuilder, Context, ContextEvent, ContextId, ContextOperation, ContextVersion,
-    SavedContext, SavedContextMetadata,
+    prompts::PromptBuilder, Context, ContextEvent, ContextId, ContextOperation, ContextTranscript,
+    ContextVersion, SavedBranchPoint, SavedContext, SavedContextMetadata,
 };
 use ::proto::AnyProtoClient;
 use anyhow::{anyhow, Context as _, Result};
 use client::{proto, telemetry::Telemetry, Client, TypedEnvelope};
 use clock::ReplicaId;
+use collections::HashMap;
 use fs::Fs;
 use futures::StreamExt;
 use fuzzy::StringMatchCandidate;
@@ -40,9 +41,26 @@ pub struct RemoteContextMetadata {
     pub summary: Option<String>,
 }
 
+/// A saved context matching a search query.
+#[derive(Clone)]
+pub struct SavedContextMatch {
+    pub metadata: SavedContextMetadata,
+    /// The text surrounding the first occurrence of the query in the context's messages, if
+    /// the messages matched.
+    pub excerpt: Option<String>,
+}
+
+/// The text of a saved context, kept around for full-text search.
+struct IndexedContext {
+    mtime: chrono::DateTime<chrono::Local>,
+    text: String,
+    lowercase_text: String,
+}
+
 pub struct ContextStore {
     contexts: Vec<ContextHandle>,
     contexts_metadata: Vec<SavedContextMetadata>,
+    search_index: Arc<HashMap<PathBuf, Arc<IndexedContext>>>,
     host_contexts: Vec<RemoteContextMetadata>,
     fs: Arc<dyn Fs>,
     languages: Arc<LanguageRegistry>,
@@ -100,6 +118,7 @@ impl ContextStore {
                 let mut this = Self {
                     contexts: Vec::new(),
                     contexts_metadata: Vec::new(),
+                    search_index: Default::default(),
                     host_contexts: Vec::new(),
                     fs,
                     languages,
@@ -673,19 +692,28 @@ impl ContextStore {
         .detach_and_log_err(cx);
     }
 
-    pub fn search(&self, query: String, cx: &AppContext) -> Task<Vec<SavedContextMetadata>> {
+    /// Searches saved contexts by fuzzy-matching their titles, and by matching every word of
+    /// the query against their messages.
+    pub fn search(&self, query: String, cx: &AppContext) -> Task<Vec<SavedContextMatch>> {
         let metadata = self.contexts_metadata.clone();
+        let search_index = self.search_index.clone();
         let executor = cx.background_executor().clone();
         cx.background_executor().spawn(async move {
             if query.is_empty() {
                 metadata
+                    .into_iter()
+                    .map(|metadata| SavedContextMatch {
+                        metadata,
+                        excerpt: None,
+                    })
+                    .collect()
             } else {
                 let candidates = metadata
                     .iter()
                     .enumerate()
                     .map(|(id, metadata)| StringMatchCandidate::new(id, metadata.title.clone()))
                     .collect::<Vec<_>>();
-                let matches = fuzzy::match_strings(
+                let title_matches = fuzzy::match_strings(
                     &candidates,
                     &query,
                     false,
@@ -694,15 +722,41 @@ impl ContextStore {
                     executor,
                 )
                 .await;
+                let mut text_matches = full_text_matches(&metadata, &search_index, &query);
 
-                matches
+                let mut matches = title_matches
                     .into_iter()
-                    .map(|mat| metadata[mat.candidate_id].clone())
-                    .collect()
+                    .map(|mat| SavedContextMatch {
+                        metadata: metadata[mat.candidate_id].clone(),
+                        excerpt: text_matches.remove(&mat.candidate_id),
+                    })
+                    .collect::<Vec<_>>();
+                let mut text_matches = text_matches.into_iter().collect::<Vec<_>>();
+                text_matches.sort_by_key(|(ix, _)| *ix);
+                matches.extend(
+                    text_matches
+                        .into_iter()
+                        .map(|(ix, excerpt)| SavedContextMatch {
+                            metadata: metadata[ix].clone(),
+                            excerpt: Some(excerpt),
+                        }),
+                );
+                matches
             }
         })
     }
 
+    /// Creates a new context containing the messages of the given transcript.
+    pub fn import_transcript(
+        &mut self,
+        transcript: ContextTranscript,
+        cx: &mut ModelContext<Self>,
+    ) -> Result<Model<Context>> {
+        let context = self.create(cx);
+        context.update(cx, |context, cx| context.import_transcript(transcript, cx))?;
+        Ok(context)
+    }
+
     pub fn host_contexts(&self) -> &[RemoteContextMetadata] {
         &self.host_contexts
     }
@@ -745,10 +799,220 @@ impl ContextStore {
             }
             contexts.sort_unstable_by_key(|context| Reverse(context.mtime));
 
+            let previous_index = this.read_with(&cx, |this, _| this.search_index.clone())?;
+            let search_index = cx
+                .background_executor()
+                .spawn(index_contexts(fs, contexts.clone(), previous_index))
+                .await;
+
             this.update(&mut cx, |this, cx| {
                 this.contexts_metadata = contexts;
+                this.search_index = Arc::new(search_index);
                 cx.notify();
             })
         })
     }
 }
+
+impl IndexedContext {
+    fn new(saved_context: &SavedContext, mtime: chrono::DateTime<chrono::Local>) -> Self {
+        fn push_branches(text: &mut String, branch_points: &[SavedBranchPoint]) {
+            for branch_point in branch_points {
+                for branch in &branch_point.branches {
+                    text.push('\n');
+                    text.push_str(&branch.text);
+                    push_branches(text, &branch.branch_points);
+                }
+            }
+        }
+
+        // Inactive branches are searched too, since they're part of the conversation.
+        let mut text = saved_context.text.clone();
+        push_branches(&mut text, &saved_context.branch_points);
+        let lowercase_text = text.to_lowercase();
+        Self {
+            mtime,
+            text,
+            lowercase_text,
+        }
+    }
+
+    /// Returns the text around the first occurrence of `term`, which must be lowercase.
+    fn excerpt(&self, term: &str) -> Option<String> {
+        const CONTEXT_BEFORE: usize = 40;
+        const CONTEXT_AFTER: usize = 80;
+
+        let position = self.lowercase_text.find(term)?;
+        // Lowercasing can change the length of some characters, in which case offsets into the
+        // lowercase text aren't valid in the original one.
+        let text = if self.text.len() == self.lowercase_text.len() {
+            &self.text
+        } else {
+            &self.lowercase_text
+        };
+
+        let mut start = position.saturating_sub(CONTEXT_BEFORE);
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = (position + term.len() + CONTEXT_AFTER).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+
+        let mut excerpt = String::new();
+        if start > 0 {
+            excerpt.push('…');
+        }
+        excerpt.push_str(
+            &text[start..end]
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        if end < text.len() {
+            excerpt.push('…');
+        }
+        Some(excerpt)
+    }
+}
+
+async fn index_contexts(
+    fs: Arc<dyn Fs>,
+    contexts: Vec<SavedContextMetadata>,
+    previous_index: Arc<HashMap<PathBuf, Arc<IndexedContext>>>,
+) -> HashMap<PathBuf, Arc<IndexedContext>> {
+    let mut index = HashMap::default();
+    for metadata in contexts {
+        if let Some(indexed_context) = previous_index
+            .get(&metadata.path)
+            .filter(|indexed_context| indexed_context.mtime == metadata.mtime)
+        {
+            index.insert(metadata.path, indexed_context.clone());
+            continue;
+        }
+
+        let Some(saved_context) = fs
+            .load(&metadata.path)
+            .await
+            .and_then(|json| SavedContext::from_json(&json))
+            .log_err()
+        else {
+            continue;
+        };
+        index.insert(
+            metadata.path,
+            Arc::new(IndexedContext::new(&saved_context, metadata.mtime)),
+        );
+    }
+    index
+}
+
+/// Returns the indices of the contexts whose title or messages contain every word of the query,
+/// and whose messages contain at least one of them, with an excerpt of the messages.
+fn full_text_matches(
+    metadata: &[SavedContextMetadata],
+    search_index: &HashMap<PathBuf, Arc<IndexedContext>>,
+    query: &str,
+) -> HashMap<usize, String> {
+    let query = query.to_lowercase();
+    let terms = query.split_whitespace().collect::<Vec<_>>();
+    if terms.is_empty() {
+        return HashMap::default();
+    }
+
+    metadata
+        .iter()
+        .enumerate()
+        .filter_map(|(ix, metadata)| {
+            let indexed_context = search_index.get(&metadata.path)?;
+            let title = metadata.title.to_lowercase();
+            let all_terms_match = terms
+                .iter()
+                .all(|term| title.contains(term) || indexed_context.lowercase_text.contains(term));
+            if !all_terms_match {
+                return None;
+            }
+
+            // Contexts matching only by title are left to the fuzzy matcher.
+            let excerpt = terms
+                .iter()
+                .find_map(|term| indexed_context.excerpt(term))?;
+            Some((ix, excerpt))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_text_matches() {
+        let mtime = chrono::Local::now();
+        let contexts = [
+            (
+                "Database work",
+                "We debugged the migration: the lock wasn't released.",
+            ),
+            ("Migration plan", "Split the users table in two."),
+            ("Lunch", "Nothing about databases here."),
+        ];
+        let metadata = contexts
+            .iter()
+            .map(|(title, _)| SavedContextMetadata {
+                title: title.to_string(),
+                path: PathBuf::from(format!("/contexts/{title} - 1.zed.json")),
+                mtime,
+            })
+            .collect::<Vec<_>>();
+        let search_index = metadata
+            .iter()
+            .zip(contexts)
+            .map(|(metadata, (_, text))| {
+                (
+                    metadata.path.clone(),
+                    Arc::new(IndexedContext {
+                        mtime,
+                        text: text.to_string(),
+                        lowercase_text: text.to_lowercase(),
+                    }),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let matches = full_text_matches(&metadata, &search_index, "DEBUGGED migration");
+        assert_eq!(
+            matches,
+            HashMap::from_iter([(
+                0,
+                "We debugged the migration: the lock wasn't released.".to_string()
+            )])
+        );
+
+        // Words can match either the title or the messages.
+        let matches = full_text_matches(&metadata, &search_index, "migration users");
+        assert_eq!(
+            matches,
+            HashMap::from_iter([(1, "Split the users table in two.".to_string())])
+        );
+
+        // Contexts matching only by title are left to the fuzzy matcher.
+        assert!(full_text_matches(&metadata, &search_index, "lunch").is_empty());
+        assert!(full_text_matches(&metadata, &search_index, "  ").is_empty());
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let text = format!("{} needle {}", "a ".repeat(50), "b ".repeat(50));
+        let indexed_context = IndexedContext {
+            mtime: chrono::Local::now(),
+            lowercase_text: text.to_lowercase(),
+            text,
+        };
+        let excerpt = indexed_context.excerpt("needle").unwrap();
+        assert!(excerpt.starts_with('…') && excerpt.ends_with('…'));
+        assert!(excerpt.contains("a needle b"));
+        assert_eq!(indexed_context.excerpt("haystack"), None);
+    }
+}
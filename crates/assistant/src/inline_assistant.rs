@@ -1,5 +1,8 @@
 use crate::{
-    assistant_settings::AssistantSettings, humanize_token_count, prompts::PromptBuilder,
+    assistant_settings::AssistantSettings,
+    humanize_token_count,
+    prompts::PromptBuilder,
+    token_usage::{self, BudgetWarning, TokenUsageSource},
     AssistantPanel, AssistantPanelEvent, CharOperation, LineDiff, LineOperation, ModelSelector,
     StreamingDiff,
 };
@@ -43,6 +46,7 @@ use std::{
     future::{self, Future},
     mem,
     ops::{Range, RangeInclusive},
+    path::PathBuf,
     pin::Pin,
     sync::Arc,
     task::{self, Poll},
@@ -90,6 +94,8 @@ pub struct InlineAssistant {
     prompt_builder: Arc<PromptBuilder>,
     telemetry: Option<Arc<Telemetry>>,
     fs: Arc<dyn Fs>,
+    /// The last budget warning the user chose to send anyway, which isn't shown again.
+    acknowledged_budget_warning: Option<BudgetWarning>,
 }
 
 pub enum AssistStatus {
@@ -125,6 +131,7 @@ impl InlineAssistant {
             prompt_builder,
             telemetry: Some(telemetry),
             fs,
+            acknowledged_budget_warning: None,
         }
     }
 
@@ -524,6 +531,10 @@ impl InlineAssistant {
             PromptEditorEvent::DismissRequested => {
                 self.dismiss_assist(assist_id, cx);
             }
+            PromptEditorEvent::BudgetWarningAcknowledged(budget_warning) => {
+                self.acknowledged_budget_warning = Some(budget_warning.clone());
+                self.start_assist(assist_id, cx);
+            }
         }
     }
 
@@ -939,6 +950,21 @@ impl InlineAssistant {
             return;
         };
 
+        // Assists without a prompt editor are started by the assistant panel, which warns
+        // about the budget itself.
+        if let Some(decorations) = assist.decorations.as_ref() {
+            if let Some(budget_warning) = token_usage::unacknowledged_budget_warning(
+                self.acknowledged_budget_warning.as_ref(),
+                cx,
+            ) {
+                decorations.prompt_editor.update(cx, |prompt_editor, cx| {
+                    prompt_editor.budget_warning = Some(budget_warning);
+                    cx.notify();
+                });
+                return;
+            }
+        }
+
         self.prompt_history.retain(|prompt| *prompt != user_prompt);
         self.prompt_history.push_back(user_prompt.clone());
         if self.prompt_history.len() > PROMPT_HISTORY_MAX_LEN {
@@ -946,6 +972,11 @@ impl InlineAssistant {
         }
 
         let assistant_panel_context = assist.assistant_panel_context(cx);
+        let project_root = assist
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.upgrade())
+            .and_then(|workspace| token_usage::project_root(workspace.read(cx).project(), cx));
 
         assist
             .codegen
@@ -954,6 +985,7 @@ impl InlineAssistant {
                     assist.range.clone(),
                     user_prompt,
                     assistant_panel_context,
+                    project_root,
                     cx,
                 )
             })
@@ -1346,6 +1378,7 @@ enum PromptEditorEvent {
     ConfirmRequested,
     CancelRequested,
     DismissRequested,
+    BudgetWarningAcknowledged(BudgetWarning),
 }
 
 struct PromptEditor {
@@ -1365,6 +1398,8 @@ struct PromptEditor {
     _token_count_subscriptions: Vec<Subscription>,
     workspace: Option<WeakView<Workspace>>,
     show_rate_limit_notice: bool,
+    /// A warning that the provider's monthly budget is nearly used up, shown before starting.
+    budget_warning: Option<BudgetWarning>,
 }
 
 #[derive(Copy, Clone)]
@@ -1543,7 +1578,16 @@ impl Render for PromptEditor {
                                     ),
                             )
                         }
-                    }),
+                    })
+                    .children(self.budget_warning.as_ref().map(|budget_warning| {
+                        deferred(
+                            anchored()
+                                .position_mode(gpui::AnchoredPositionMode::Local)
+                                .position(point(px(0.), px(24.)))
+                                .anchor(gpui::AnchorCorner::TopLeft)
+                                .child(self.render_budget_warning(budget_warning, cx)),
+                        )
+                    })),
             )
             .child(div().flex_1().child(self.render_prompt_editor(cx)))
             .child(
@@ -1622,6 +1666,7 @@ impl PromptEditor {
             _token_count_subscriptions: token_count_subscriptions,
             workspace,
             show_rate_limit_notice: false,
+            budget_warning: None,
         };
         this.count_tokens(cx);
         this.subscribe_to_editor(cx);
@@ -1754,6 +1799,7 @@ impl PromptEditor {
                     .update(cx, |editor, _| editor.set_read_only(false));
             }
             CodegenStatus::Pending => {
+                self.budget_warning = None;
                 self.editor
                     .update(cx, |editor, _| editor.set_read_only(true));
             }
@@ -1937,6 +1983,27 @@ impl PromptEditor {
         )
     }
 
+    fn render_budget_warning(
+        &self,
+        budget_warning: &BudgetWarning,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        Popover::new().child(div().occlude().p_2().max_w_96().child(
+            token_usage::render_budget_warning(
+                budget_warning,
+                cx.listener(|this, _, cx| {
+                    this.budget_warning = None;
+                    cx.notify();
+                }),
+                cx.listener(|this, _, cx| {
+                    if let Some(budget_warning) = this.budget_warning.take() {
+                        cx.emit(PromptEditorEvent::BudgetWarningAcknowledged(budget_warning));
+                    }
+                }),
+            ),
+        ))
+    }
+
     fn render_rate_limit_notice(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
         Popover::new().child(
             v_flex()
@@ -2321,6 +2388,7 @@ impl Codegen {
         edit_range: Range<Anchor>,
         user_prompt: String,
         assistant_panel_context: Option<LanguageModelRequest>,
+        project_root: Option<PathBuf>,
         cx: &mut ModelContext<Self>,
     ) -> Result<()> {
         let model = LanguageModelRegistry::read_global(cx)
@@ -2346,8 +2414,16 @@ impl Codegen {
             let request =
                 self.build_request(user_prompt, assistant_panel_context, edit_range.clone(), cx)?;
 
-            let chunks =
-                cx.spawn(|_, cx| async move { model.stream_completion_text(request, &cx).await });
+            let chunks = cx.spawn(|_, cx| async move {
+                token_usage::stream_completion_text(
+                    model,
+                    request,
+                    TokenUsageSource::InlineAssistant,
+                    project_root,
+                    &cx,
+                )
+                .await
+            });
             async move { Ok(chunks.await?.boxed()) }.boxed_local()
         };
         self.handle_stream(telemetry_id, edit_range, chunks, cx);
@@ -1,5 +1,6 @@
 use super::create_label_for_command;
 use super::{SlashCommand, SlashCommandOutput};
+use crate::token_usage::{self, TokenUsageSource};
 use anyhow::{anyhow, Result};
 use assistant_slash_command::ArgumentCompletion;
 use feature_flags::FeatureFlag;
@@ -7,12 +8,14 @@ use futures::StreamExt;
 use gpui::{AppContext, AsyncAppContext, Task, WeakView};
 use language::{CodeLabel, LspAdapterDelegate};
 use language_model::{
-    LanguageModelCompletionEvent, LanguageModelRegistry, LanguageModelRequest,
-    LanguageModelRequestMessage, Role,
+    LanguageModelRegistry, LanguageModelRequest, LanguageModelRequestMessage, Role,
 };
 use semantic_index::{FileSummary, SemanticDb};
 use smol::channel;
-use std::sync::{atomic::AtomicBool, Arc};
+use std::{
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc},
+};
 use ui::{BorrowAppContext, WindowContext};
 use util::ResultExt;
 use workspace::Workspace;
@@ -100,6 +103,7 @@ impl SlashCommand for AutoCommand {
         let argument = arguments.join(" ");
         let original_prompt = argument.to_string();
         let project = workspace.read(cx).project().clone();
+        let project_root = token_usage::project_root(&project, cx);
         let Some(project_index) =
             cx.update_global(|index: &mut SemanticDb, cx| index.project_index(project, cx))
         else {
@@ -111,7 +115,7 @@ impl SlashCommand for AutoCommand {
                 .read_with(&cx, |project_index, cx| project_index.all_summaries(cx))?
                 .await?;
 
-            commands_for_summaries(&summaries, &original_prompt, &cx).await
+            commands_for_summaries(&summaries, &original_prompt, project_root, &cx).await
         });
 
         // As a convenience, append /auto's argument to the end of the prompt
@@ -177,6 +181,7 @@ struct CommandToRun {
 async fn commands_for_summaries(
     summaries: &[FileSummary],
     original_prompt: &str,
+    project_root: Option<PathBuf>,
     cx: &AsyncAppContext,
 ) -> Result<Vec<CommandToRun>> {
     if summaries.is_empty() {
@@ -267,7 +272,15 @@ async fn commands_for_summaries(
             let request = make_request(prompt.clone());
             let model = model.clone();
             let tx = tx.clone();
-            let stream = model.stream_completion(request, &cx);
+            let stream = token_usage::stream_completion_text(
+                model,
+                request,
+                TokenUsageSource::SlashCommand {
+                    name: "auto".into(),
+                },
+                project_root.clone(),
+                cx,
+            );
 
             (stream, tx)
         })
@@ -284,13 +297,7 @@ async fn commands_for_summaries(
                     log::info!("Time taken for awaiting /await chunk stream #{ix}: {:?}", start.elapsed());
 
                     let completion: String = events
-                        .filter_map(|event| async {
-                            if let Ok(LanguageModelCompletionEvent::Text(text)) = event {
-                                Some(text)
-                            } else {
-                                None
-                            }
-                        })
+                        .filter_map(|text| async { text.ok() })
                         .collect()
                         .await;
 
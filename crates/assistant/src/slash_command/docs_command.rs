@@ -9,8 +9,8 @@ use assistant_slash_command::{
 };
 use gpui::{AppContext, BackgroundExecutor, Model, Task, WeakView};
 use indexed_docs::{
-    DocsDotRsProvider, IndexedDocsRegistry, IndexedDocsStore, LocalRustdocProvider, PackageName,
-    ProviderId,
+    DocsDotRsProvider, GoDocsProvider, IndexedDocsRegistry, IndexedDocsStore, LocalRustdocProvider,
+    ManPageProvider, PackageName, ProviderId, PythonDocsProvider, TypeScriptDeclarationsProvider,
 };
 use language::LspAdapterDelegate;
 use project::{Project, ProjectPath};
@@ -92,6 +92,87 @@ impl DocsSlashCommand {
         }
     }
 
+    /// Returns the root directory of the workspace's project, by which the providers of the
+    /// docs of the project's own dependencies are keyed.
+    pub(crate) fn project_root(
+        workspace: Option<&WeakView<Workspace>>,
+        cx: &AppContext,
+    ) -> Option<Arc<Path>> {
+        let workspace = workspace?.upgrade()?;
+        let project = workspace.read(cx).project().read(cx);
+        let worktree = project.worktrees(cx).next()?;
+        let project_root = worktree.read(cx).abs_path();
+        Some(project_root)
+    }
+
+    /// Ensures that the indexed doc providers for the other languages used by the project, and
+    /// for man pages, are registered. The providers of the project's dependencies are
+    /// registered for each project, so that every project has its own.
+    fn ensure_local_doc_providers_are_registered(
+        &self,
+        workspace: Option<WeakView<Workspace>>,
+        cx: &mut AppContext,
+    ) {
+        let indexed_docs_registry = IndexedDocsRegistry::global(cx);
+        let Some((fs, worktree)) = maybe!({
+            let workspace = workspace?.upgrade()?;
+            let project = workspace.read(cx).project().clone();
+            let fs = project.read(cx).fs().clone();
+            let worktree = project.read(cx).worktrees(cx).next()?;
+            Some((fs, worktree))
+        }) else {
+            return;
+        };
+        let worktree = worktree.read(cx);
+        let project_root = worktree.abs_path();
+        let is_registered = |provider_id| {
+            indexed_docs_registry
+                .get_project_provider_store(&project_root, provider_id)
+                .is_some()
+        };
+
+        if !is_registered(PythonDocsProvider::id())
+            && PythonDocsProvider::VIRTUAL_ENV_DIRS
+                .iter()
+                .any(|dir| worktree.entry_for_path(dir).is_some())
+        {
+            indexed_docs_registry.register_project_provider(
+                project_root.clone(),
+                Box::new(PythonDocsProvider::new(
+                    fs.clone(),
+                    project_root.to_path_buf(),
+                )),
+            );
+        }
+
+        if !is_registered(GoDocsProvider::id()) && worktree.entry_for_path("go.mod").is_some() {
+            indexed_docs_registry.register_project_provider(
+                project_root.clone(),
+                Box::new(GoDocsProvider::new(project_root.to_path_buf())),
+            );
+        }
+
+        if !is_registered(TypeScriptDeclarationsProvider::id())
+            && worktree.entry_for_path("node_modules").is_some()
+        {
+            indexed_docs_registry.register_project_provider(
+                project_root.clone(),
+                Box::new(TypeScriptDeclarationsProvider::new(
+                    fs.clone(),
+                    project_root.to_path_buf(),
+                )),
+            );
+        }
+
+        if cfg!(not(target_os = "windows"))
+            && indexed_docs_registry
+                .get_provider_store(ManPageProvider::id())
+                .is_none()
+        {
+            indexed_docs_registry.register_provider(Box::new(ManPageProvider::new(fs)));
+        }
+    }
+
     /// Runs just-in-time indexing for a given package, in case the slash command
     /// is run without any entries existing in the index.
     fn run_just_in_time_indexing(
@@ -166,14 +247,18 @@ impl SlashCommand for DocsSlashCommand {
         workspace: Option<WeakView<Workspace>>,
         cx: &mut WindowContext,
     ) -> Task<Result<Vec<ArgumentCompletion>>> {
-        self.ensure_rust_doc_providers_are_registered(workspace, cx);
+        self.ensure_rust_doc_providers_are_registered(workspace.clone(), cx);
+        self.ensure_local_doc_providers_are_registered(workspace.clone(), cx);
 
         let indexed_docs_registry = IndexedDocsRegistry::global(cx);
+        let project_root = Self::project_root(workspace.as_ref(), cx);
         let args = DocsSlashCommandArgs::parse(arguments);
         let store = args
             .provider()
             .ok_or_else(|| anyhow!("no docs provider specified"))
-            .and_then(|provider| IndexedDocsStore::try_global(provider, cx));
+            .and_then(|provider| {
+                IndexedDocsStore::try_for_project(project_root.as_deref(), provider, cx)
+            });
         cx.background_executor().spawn(async move {
             fn build_completions(items: Vec<String>) -> Vec<ArgumentCompletion> {
                 items
@@ -189,7 +274,12 @@ impl SlashCommand for DocsSlashCommand {
 
             match args {
                 DocsSlashCommandArgs::NoProvider => {
-                    let providers = indexed_docs_registry.list_providers();
+                    let providers = match &project_root {
+                        Some(project_root) => {
+                            indexed_docs_registry.list_project_providers(project_root)
+                        }
+                        None => indexed_docs_registry.list_providers(),
+                    };
                     if providers.is_empty() {
                         return Ok(vec![ArgumentCompletion {
                             label: "No available docs providers.".into(),
@@ -216,7 +306,9 @@ impl SlashCommand for DocsSlashCommand {
                 } => {
                     let store = store?;
 
-                    if index {
+                    // Packages whose indexing executes the project's code are only indexed once
+                    // the command is run.
+                    if index && !store.executes_project_code() {
                         // We don't need to hold onto this task, as the `IndexedDocsStore` will hold it
                         // until it completes.
                         drop(store.clone().index(package.as_str().into()));
@@ -269,7 +361,7 @@ impl SlashCommand for DocsSlashCommand {
     fn run(
         self: Arc<Self>,
         arguments: &[String],
-        _workspace: WeakView<Workspace>,
+        workspace: WeakView<Workspace>,
         _delegate: Option<Arc<dyn LspAdapterDelegate>>,
         cx: &mut WindowContext,
     ) -> Task<Result<SlashCommandOutput>> {
@@ -279,11 +371,14 @@ impl SlashCommand for DocsSlashCommand {
 
         let args = DocsSlashCommandArgs::parse(arguments);
         let executor = cx.background_executor().clone();
+        let project_root = Self::project_root(Some(&workspace), cx);
         let task = cx.background_executor().spawn({
             let store = args
                 .provider()
                 .ok_or_else(|| anyhow!("no docs provider specified"))
-                .and_then(|provider| IndexedDocsStore::try_global(provider, cx));
+                .and_then(|provider| {
+                    IndexedDocsStore::try_for_project(project_root.as_deref(), provider, cx)
+                });
             async move {
                 let (provider, key) = match args.clone() {
                     DocsSlashCommandArgs::NoProvider => bail!("no docs provider specified"),
@@ -390,7 +485,17 @@ impl DocsSlashCommandArgs {
             return Self::NoProvider;
         };
 
-        if let Some((package, rest)) = argument.split_once(is_item_path_delimiter) {
+        // Go packages are named by their import path, which contains delimiters, so only the
+        // delimiters after its last segment start the item path, e.g. `net/http.Client`.
+        let package_start = if provider == GoDocsProvider::id() {
+            argument.rfind('/').map_or(0, |ix| ix + 1)
+        } else {
+            0
+        };
+
+        if let Some((package, rest)) = argument[package_start..].split_once(is_item_path_delimiter)
+        {
+            let package = &argument[..package_start + package.len()];
             if rest.trim().is_empty() {
                 Self::SearchPackageDocs {
                     provider,
@@ -438,6 +543,10 @@ fn package_term(provider: &ProviderId) -> &'static str {
         return "crate";
     }
 
+    if provider == &ManPageProvider::id() {
+        return "man page";
+    }
+
     "package"
 }
 
@@ -530,5 +639,35 @@ mod tests {
                 item_path: "gleam_stdlib/gleam/int".into()
             }
         );
+
+        // Go import paths contain delimiters, so only the delimiters after their last segment
+        // start an item path.
+        assert_eq!(
+            DocsSlashCommandArgs::parse(&["go".to_string(), "net/http".to_string()]),
+            DocsSlashCommandArgs::SearchPackageDocs {
+                provider: ProviderId("go".into()),
+                package: "net/http".into(),
+                index: false
+            }
+        );
+        assert_eq!(
+            DocsSlashCommandArgs::parse(&["go".to_string(), "net/http.".to_string()]),
+            DocsSlashCommandArgs::SearchPackageDocs {
+                provider: ProviderId("go".into()),
+                package: "net/http".into(),
+                index: true
+            }
+        );
+        assert_eq!(
+            DocsSlashCommandArgs::parse(&[
+                "go".to_string(),
+                "github.com/user/repo/pkg.Client".to_string()
+            ]),
+            DocsSlashCommandArgs::SearchItemDocs {
+                provider: ProviderId("go".into()),
+                package: "github.com/user/repo/pkg".into(),
+                item_path: "github.com/user/repo/pkg.Client".into()
+            }
+        );
     }
 }
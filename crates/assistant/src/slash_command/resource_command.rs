@@ -0,0 +1,265 @@
+use anyhow::{anyhow, Result};
+use assistant_slash_command::{
+    AfterCompletion, ArgumentCompletion, SlashCommand, SlashCommandOutput,
+    SlashCommandOutputSection,
+};
+use collections::HashMap;
+use context_servers::{
+    manager::{ContextServer, ContextServerManager},
+    protocol::{InitializedContextServerProtocol, ServerCapability},
+    types,
+};
+use futures::{future, future::Shared, FutureExt};
+use gpui::{AppContext, Global, Task, WeakView, WindowContext};
+use language::{CodeLabel, LspAdapterDelegate};
+use std::fmt::Write;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use text::LineEnding;
+use ui::{IconName, SharedString};
+use url::Url;
+use util::ResultExt;
+use workspace::Workspace;
+
+/// The icon of the output sections resources are inserted in, which tells them apart from
+/// the sections of other commands.
+const RESOURCE_ICON: IconName = IconName::FileText;
+
+/// Inserts the contents of a resource exposed by a context server.
+pub struct ResourceSlashCommand;
+
+impl ResourceSlashCommand {
+    /// Whether any running context server exposes resources.
+    pub fn is_available(cx: &AppContext) -> bool {
+        !servers_with_resources(cx).is_empty()
+    }
+}
+
+impl SlashCommand for ResourceSlashCommand {
+    fn name(&self) -> String {
+        "resource".into()
+    }
+
+    fn description(&self) -> String {
+        "insert a context server resource".into()
+    }
+
+    fn menu_text(&self) -> String {
+        "Insert Context Server Resource".into()
+    }
+
+    fn requires_argument(&self) -> bool {
+        true
+    }
+
+    fn complete_argument(
+        self: Arc<Self>,
+        arguments: &[String],
+        _cancel: Arc<AtomicBool>,
+        _workspace: Option<WeakView<Workspace>>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<Vec<ArgumentCompletion>>> {
+        let query = arguments.last().cloned().unwrap_or_default().to_lowercase();
+        let servers = servers_with_resources(cx);
+        cx.foreground_executor().spawn(async move {
+            let resources = list_resources(&servers).await;
+            Ok(resources
+                .into_iter()
+                .filter(|(_, resource)| {
+                    resource.uri.as_str().to_lowercase().contains(&query)
+                        || resource
+                            .name
+                            .as_ref()
+                            .map_or(false, |name| name.to_lowercase().contains(&query))
+                })
+                .map(|(server, resource)| {
+                    let label = match &resource.name {
+                        Some(name) => format!("{name} ({}, {})", resource.uri, server.id),
+                        None => format!("{} ({})", resource.uri, server.id),
+                    };
+                    ArgumentCompletion {
+                        label: CodeLabel::plain(label, None),
+                        new_text: resource.uri.to_string(),
+                        after_completion: AfterCompletion::Run,
+                        replace_previous_arguments: true,
+                    }
+                })
+                .collect())
+        })
+    }
+
+    fn run(
+        self: Arc<Self>,
+        arguments: &[String],
+        _workspace: WeakView<Workspace>,
+        _delegate: Option<Arc<dyn LspAdapterDelegate>>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<SlashCommandOutput>> {
+        let uri = match arguments.first().map(|argument| Url::parse(argument)) {
+            Some(Ok(uri)) => uri,
+            Some(Err(error)) => return Task::ready(Err(anyhow!("invalid resource URI: {error}"))),
+            None => return Task::ready(Err(anyhow!("missing resource URI"))),
+        };
+        let servers = servers_with_resources(cx);
+
+        cx.foreground_executor().spawn(async move {
+            let resources = list_resources(&servers).await;
+            let Some((server, _)) = resources
+                .into_iter()
+                .find(|(_, resource)| resource.uri == uri)
+            else {
+                return Err(anyhow!("no context server exposes {uri}"));
+            };
+            let Some(protocol) = server.client.read().clone() else {
+                return Err(anyhow!("Context server not initialized"));
+            };
+
+            let response = protocol.read_resource(uri.clone()).await?;
+            let mut text = String::new();
+            for content in response.contents {
+                match content.text {
+                    Some(content_text) => text.push_str(&content_text),
+                    None => write!(text, "[binary content of {}]", content.uri).unwrap(),
+                }
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+            // We must normalize the line endings here, since servers might return CR characters.
+            LineEnding::normalize(&mut text);
+
+            Ok(SlashCommandOutput {
+                sections: vec![SlashCommandOutputSection {
+                    range: 0..text.len(),
+                    icon: RESOURCE_ICON,
+                    label: SharedString::from(resource_label(&uri)),
+                }],
+                text,
+                run_commands_in_text: false,
+            })
+        })
+    }
+}
+
+/// The label of the output section a resource is inserted in, which identifies it when it's
+/// updated.
+pub fn resource_label(uri: &Url) -> String {
+    uri.to_string()
+}
+
+/// Returns the URI of the resource a section was inserted for by `/resource`, if any.
+pub fn section_resource<T>(section: &SlashCommandOutputSection<T>) -> Option<Url> {
+    if section.icon != RESOURCE_ICON {
+        return None;
+    }
+    Url::parse(&section.label).ok()
+}
+
+/// The resources attached to contexts, whose updates the servers exposing them are subscribed
+/// to until the last section they're attached in is removed.
+#[derive(Default)]
+struct ResourceSubscriptions(HashMap<Url, ResourceSubscription>);
+
+impl Global for ResourceSubscriptions {}
+
+struct ResourceSubscription {
+    /// The number of sections the resource is attached in.
+    count: usize,
+    /// Resolves to the server that was subscribed to, if any.
+    protocol: Shared<Task<Option<Arc<InitializedContextServerProtocol>>>>,
+}
+
+/// Subscribes to updates of a resource attached to a context, which are reported to the
+/// contexts it's attached to. Each call must be balanced by [`unsubscribe_from_resource`].
+pub fn subscribe_to_resource(uri: Url, cx: &mut AppContext) {
+    if let Some(subscription) = cx.default_global::<ResourceSubscriptions>().0.get_mut(&uri) {
+        subscription.count += 1;
+        return;
+    }
+
+    let servers = servers_with_resources(cx);
+    let protocol = cx.foreground_executor().spawn({
+        let uri = uri.clone();
+        async move {
+            let resources = list_resources(&servers).await;
+            let (server, _) = resources
+                .into_iter()
+                .find(|(_, resource)| resource.uri == uri)?;
+            let protocol = server.client.read().clone()?;
+            if !protocol.can_subscribe_to_resources() {
+                return None;
+            }
+            protocol.subscribe_to_resource(uri).await.log_err()?;
+            Some(protocol)
+        }
+    });
+    cx.global_mut::<ResourceSubscriptions>().0.insert(
+        uri,
+        ResourceSubscription {
+            count: 1,
+            protocol: protocol.shared(),
+        },
+    );
+}
+
+/// Releases a subscription taken with [`subscribe_to_resource`], unsubscribing from the
+/// resource's updates once it isn't attached anywhere.
+pub fn unsubscribe_from_resource(uri: &Url, cx: &mut AppContext) {
+    let subscriptions = &mut cx.default_global::<ResourceSubscriptions>().0;
+    let Some(subscription) = subscriptions.get_mut(uri) else {
+        return;
+    };
+    subscription.count -= 1;
+    if subscription.count > 0 {
+        return;
+    }
+
+    let Some(subscription) = subscriptions.remove(uri) else {
+        return;
+    };
+    let uri = uri.clone();
+    cx.foreground_executor()
+        .spawn(async move {
+            if let Some(protocol) = subscription.protocol.await {
+                protocol.unsubscribe_from_resource(uri).await.log_err();
+            }
+        })
+        .detach();
+}
+
+fn servers_with_resources(cx: &AppContext) -> Vec<Arc<ContextServer>> {
+    ContextServerManager::global(cx)
+        .read(cx)
+        .servers()
+        .into_iter()
+        .filter(|server| {
+            server.client.read().as_ref().map_or(false, |protocol| {
+                protocol.capable(ServerCapability::Resources)
+            })
+        })
+        .collect()
+}
+
+async fn list_resources(
+    servers: &[Arc<ContextServer>],
+) -> Vec<(Arc<ContextServer>, types::Resource)> {
+    let listings = future::join_all(servers.iter().map(|server| {
+        let server = server.clone();
+        async move {
+            let protocol = server.client.read().clone()?;
+            let response = protocol.list_resources().await.log_err()?;
+            Some((server, response.resources))
+        }
+    }))
+    .await;
+
+    listings
+        .into_iter()
+        .flatten()
+        .flat_map(|(server, resources)| {
+            resources
+                .into_iter()
+                .map(move |resource| (server.clone(), resource))
+        })
+        .collect()
+}
@@ -1,6 +1,8 @@
 use crate::{
-    humanize_token_count, prompts::PromptBuilder, AssistantPanel, AssistantPanelEvent,
-    ModelSelector, DEFAULT_CONTEXT_LINES,
+    humanize_token_count,
+    prompts::PromptBuilder,
+    token_usage::{self, BudgetWarning, TokenUsageSource},
+    AssistantPanel, AssistantPanelEvent, ModelSelector, DEFAULT_CONTEXT_LINES,
 };
 use anyhow::{Context as _, Result};
 use client::telemetry::Telemetry;
@@ -12,8 +14,8 @@ use editor::{
 use fs::Fs;
 use futures::{channel::mpsc, SinkExt, StreamExt};
 use gpui::{
-    AppContext, Context, EventEmitter, FocusHandle, FocusableView, Global, Model, ModelContext,
-    Subscription, Task, TextStyle, UpdateGlobal, View, WeakView,
+    anchored, deferred, point, AppContext, Context, EventEmitter, FocusHandle, FocusableView,
+    Global, Model, ModelContext, Subscription, Task, TextStyle, UpdateGlobal, View, WeakView,
 };
 use language::Buffer;
 use language_model::{
@@ -22,13 +24,14 @@ use language_model::{
 use settings::Settings;
 use std::{
     cmp,
+    path::PathBuf,
     sync::Arc,
     time::{Duration, Instant},
 };
 use terminal::Terminal;
 use terminal_view::TerminalView;
 use theme::ThemeSettings;
-use ui::{prelude::*, IconButtonShape, Tooltip};
+use ui::{prelude::*, IconButtonShape, Popover, Tooltip};
 use util::ResultExt;
 use workspace::{notifications::NotificationId, Toast, Workspace};
 
@@ -61,6 +64,8 @@ pub struct TerminalInlineAssistant {
     telemetry: Option<Arc<Telemetry>>,
     fs: Arc<dyn Fs>,
     prompt_builder: Arc<PromptBuilder>,
+    /// The last budget warning the user chose to send anyway, which isn't shown again.
+    acknowledged_budget_warning: Option<BudgetWarning>,
 }
 
 impl Global for TerminalInlineAssistant {}
@@ -78,6 +83,7 @@ impl TerminalInlineAssistant {
             telemetry: Some(telemetry),
             fs,
             prompt_builder,
+            acknowledged_budget_warning: None,
         }
     }
 
@@ -169,6 +175,10 @@ impl TerminalInlineAssistant {
             PromptEditorEvent::Resized { height_in_lines } => {
                 self.insert_prompt_editor_into_terminal(assist_id, *height_in_lines, cx);
             }
+            PromptEditorEvent::BudgetWarningAcknowledged(budget_warning) => {
+                self.acknowledged_budget_warning = Some(budget_warning.clone());
+                self.start_assist(assist_id, cx);
+            }
         }
     }
 
@@ -187,6 +197,19 @@ impl TerminalInlineAssistant {
             return;
         };
 
+        if let Some(budget_warning) = token_usage::unacknowledged_budget_warning(
+            self.acknowledged_budget_warning.as_ref(),
+            cx,
+        ) {
+            if let Some(prompt_editor) = assist.prompt_editor.as_ref() {
+                prompt_editor.update(cx, |prompt_editor, cx| {
+                    prompt_editor.budget_warning = Some(budget_warning);
+                    cx.notify();
+                });
+            }
+            return;
+        }
+
         self.prompt_history.retain(|prompt| *prompt != user_prompt);
         self.prompt_history.push_back(user_prompt.clone());
         if self.prompt_history.len() > PROMPT_HISTORY_MAX_LEN {
@@ -202,12 +225,18 @@ impl TerminalInlineAssistant {
             })
             .log_err();
 
+        let project_root = assist
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.upgrade())
+            .and_then(|workspace| token_usage::project_root(workspace.read(cx).project(), cx));
+
         let codegen = assist.codegen.clone();
         let Some(request) = self.request_for_inline_assist(assist_id, cx).log_err() else {
             return;
         };
 
-        codegen.update(cx, |codegen, cx| codegen.start(request, cx));
+        codegen.update(cx, |codegen, cx| codegen.start(request, project_root, cx));
     }
 
     fn stop_assist(&mut self, assist_id: TerminalInlineAssistId, cx: &mut WindowContext) {
@@ -441,6 +470,7 @@ enum PromptEditorEvent {
     CancelRequested,
     DismissRequested,
     Resized { height_in_lines: u8 },
+    BudgetWarningAcknowledged(BudgetWarning),
 }
 
 struct PromptEditor {
@@ -459,6 +489,8 @@ struct PromptEditor {
     token_count: Option<usize>,
     _token_count_subscriptions: Vec<Subscription>,
     workspace: Option<WeakView<Workspace>>,
+    /// A warning that the provider's monthly budget is nearly used up, shown before starting.
+    budget_warning: Option<BudgetWarning>,
 }
 
 impl EventEmitter<PromptEditorEvent> for PromptEditor {}
@@ -620,7 +652,16 @@ impl Render for PromptEditor {
                         } else {
                             None
                         },
-                    ),
+                    )
+                    .children(self.budget_warning.as_ref().map(|budget_warning| {
+                        deferred(
+                            anchored()
+                                .position_mode(gpui::AnchoredPositionMode::Local)
+                                .position(point(px(0.), px(24.)))
+                                .anchor(gpui::AnchorCorner::TopLeft)
+                                .child(self.render_budget_warning(budget_warning, cx)),
+                        )
+                    })),
             )
             .child(div().flex_1().child(self.render_prompt_editor(cx)))
             .child(
@@ -690,6 +731,7 @@ impl PromptEditor {
             token_count: None,
             _token_count_subscriptions: token_count_subscriptions,
             workspace,
+            budget_warning: None,
         };
         this.count_lines(cx);
         this.count_tokens(cx);
@@ -793,6 +835,7 @@ impl PromptEditor {
                     .update(cx, |editor, _| editor.set_read_only(false));
             }
             CodegenStatus::Pending => {
+                self.budget_warning = None;
                 self.editor
                     .update(cx, |editor, _| editor.set_read_only(true));
             }
@@ -941,6 +984,27 @@ impl PromptEditor {
         Some(token_count)
     }
 
+    fn render_budget_warning(
+        &self,
+        budget_warning: &BudgetWarning,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        Popover::new().child(div().occlude().p_2().max_w_96().child(
+            token_usage::render_budget_warning(
+                budget_warning,
+                cx.listener(|this, _, cx| {
+                    this.budget_warning = None;
+                    cx.notify();
+                }),
+                cx.listener(|this, _, cx| {
+                    if let Some(budget_warning) = this.budget_warning.take() {
+                        cx.emit(PromptEditorEvent::BudgetWarningAcknowledged(budget_warning));
+                    }
+                }),
+            ),
+        ))
+    }
+
     fn render_prompt_editor(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
         let settings = ThemeSettings::get_global(cx);
         let text_style = TextStyle {
@@ -1030,7 +1094,12 @@ impl Codegen {
         }
     }
 
-    pub fn start(&mut self, prompt: LanguageModelRequest, cx: &mut ModelContext<Self>) {
+    pub fn start(
+        &mut self,
+        prompt: LanguageModelRequest,
+        project_root: Option<PathBuf>,
+        cx: &mut ModelContext<Self>,
+    ) {
         let Some(model) = LanguageModelRegistry::read_global(cx).active_model() else {
             return;
         };
@@ -1040,7 +1109,14 @@ impl Codegen {
         self.transaction = Some(TerminalTransaction::start(self.terminal.clone()));
         self.generation = cx.spawn(|this, mut cx| async move {
             let model_telemetry_id = model.telemetry_id();
-            let response = model.stream_completion_text(prompt, &cx).await;
+            let response = token_usage::stream_completion_text(
+                model,
+                prompt,
+                TokenUsageSource::TerminalInlineAssistant,
+                project_root,
+                &cx,
+            )
+            .await;
             let generate = async {
                 let (mut hunks_tx, mut hunks_rx) = mpsc::channel(1);
 
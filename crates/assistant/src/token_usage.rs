@@ -0,0 +1,1226 @@
+use crate::{
+    assistant_settings::{AssistantSettings, ModelPrice, UsageBudget},
+    humanize_token_count, ContextId, ShowTokenUsage,
+};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Local, NaiveDate, Utc};
+use collections::{BTreeMap, BTreeSet, HashMap};
+use editor::Editor;
+use futures::{
+    channel::oneshot,
+    future::{self, BoxFuture},
+    stream::BoxStream,
+    FutureExt, StreamExt,
+};
+use gpui::{
+    AppContext, AsyncAppContext, ClickEvent, Context as _, FontWeight, Global, Model, ModelContext,
+    SharedString, ViewContext, VisualContext,
+};
+use heed::{types::SerdeJson, Database};
+use language_model::{
+    LanguageModel, LanguageModelCompletionEvent, LanguageModelProviderId, LanguageModelRegistry,
+    LanguageModelRequest, LanguageModelRequestMessage, Role, TokenUsage,
+};
+use multi_buffer::MultiBuffer;
+use project::Project;
+use serde::{Deserialize, Serialize};
+use settings::Settings;
+use std::{fmt::Write as _, mem, path::PathBuf, sync::Arc};
+use ui::prelude::*;
+use util::ResultExt;
+use uuid::Uuid;
+use workspace::Workspace;
+
+/// Starts loading the recorded token usage in the background and assigns the
+/// store to a global.
+pub fn init(cx: &mut AppContext) {
+    let db_path = paths::support_dir().join("assistant-token-usage-db.0.mdb");
+    let store = cx.new_model(|cx| TokenUsageStore::new(db_path, cx));
+    cx.set_global(GlobalTokenUsageStore(store));
+}
+
+struct GlobalTokenUsageStore(Model<TokenUsageStore>);
+
+impl Global for GlobalTokenUsageStore {}
+
+/// Where a request was made from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TokenUsageSource {
+    AssistantPanel { context_id: ContextId },
+    InlineAssistant,
+    TerminalInlineAssistant,
+    SlashCommand { name: String },
+    ContextServer { server_id: String },
+}
+
+impl TokenUsageSource {
+    pub fn context_id(&self) -> Option<&ContextId> {
+        match self {
+            Self::AssistantPanel { context_id } => Some(context_id),
+            Self::InlineAssistant
+            | Self::TerminalInlineAssistant
+            | Self::SlashCommand { .. }
+            | Self::ContextServer { .. } => None,
+        }
+    }
+}
+
+/// The tokens used by a single request.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub provider: String,
+    pub model: String,
+    pub source: TokenUsageSource,
+    /// The root of the project the request was made in.
+    #[serde(default)]
+    pub project: Option<PathBuf>,
+    pub usage: TokenUsage,
+    /// Whether the provider didn't report the usage, so it was counted locally.
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+impl TokenUsageRecord {
+    /// Returns what the request cost, if the model's price is known.
+    pub fn cost(&self, prices: &HashMap<String, ModelPrice>) -> Option<f64> {
+        usage_cost(&self.model, &self.usage, prices)
+    }
+}
+
+/// Returns what the given tokens cost, if the model's price is known.
+fn usage_cost(
+    model: &str,
+    usage: &TokenUsage,
+    prices: &HashMap<String, ModelPrice>,
+) -> Option<f64> {
+    let price = prices.get(model)?;
+    let cost = usage.input_tokens as f64 * price.input
+        + usage.output_tokens as f64 * price.output
+        + usage.cache_creation_input_tokens as f64 * price.cache_write.unwrap_or(price.input)
+        + usage.cache_read_input_tokens as f64 * price.cache_read.unwrap_or(price.input);
+    Some(cost / 1_000_000.)
+}
+
+/// The tokens used by a set of requests to the same model. Unlike [`UsageTotals`], they
+/// aren't priced, so that they stay correct when prices change.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct ModelUsage {
+    requests: usize,
+    usage: TokenUsage,
+    estimated: bool,
+}
+
+impl ModelUsage {
+    fn of_record(record: &TokenUsageRecord) -> Self {
+        Self {
+            requests: 1,
+            usage: record.usage,
+            estimated: record.estimated,
+        }
+    }
+
+    fn add(&mut self, other: &ModelUsage) {
+        self.requests += other.requests;
+        self.usage += other.usage;
+        self.estimated |= other.estimated;
+    }
+}
+
+/// The usage of a month's requests, kept up to date as requests are recorded.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct MonthlyUsage {
+    /// The usage of each provider's models.
+    providers: BTreeMap<String, BTreeMap<String, ModelUsage>>,
+    /// The usage of each model in each project.
+    projects: BTreeMap<Option<PathBuf>, BTreeMap<String, ModelUsage>>,
+}
+
+/// Running totals of the recorded requests, so that the usage shown in the assistant
+/// doesn't require going through every request.
+#[derive(Clone, Debug, Default)]
+pub struct RecordedUsage {
+    months: BTreeMap<Month, MonthlyUsage>,
+    /// The usage of each model by each context.
+    contexts: HashMap<ContextId, BTreeMap<String, ModelUsage>>,
+}
+
+impl RecordedUsage {
+    pub fn add(&mut self, record: &TokenUsageRecord) {
+        let usage = ModelUsage::of_record(record);
+        self.add_to_month(
+            Month::of(record.timestamp),
+            &record.provider,
+            &record.model,
+            &record.project,
+            &usage,
+        );
+        if let Some(context_id) = record.source.context_id() {
+            self.contexts
+                .entry(context_id.clone())
+                .or_default()
+                .entry(record.model.clone())
+                .or_default()
+                .add(&usage);
+        }
+    }
+
+    fn add_compacted(&mut self, month: Month, compacted: &CompactedUsage) {
+        self.add_to_month(
+            month,
+            &compacted.provider,
+            &compacted.model,
+            &compacted.project,
+            &compacted.usage,
+        );
+    }
+
+    fn add_to_month(
+        &mut self,
+        month: Month,
+        provider: &str,
+        model: &str,
+        project: &Option<PathBuf>,
+        usage: &ModelUsage,
+    ) {
+        let month = self.months.entry(month).or_default();
+        month
+            .providers
+            .entry(provider.to_string())
+            .or_default()
+            .entry(model.to_string())
+            .or_default()
+            .add(usage);
+        month
+            .projects
+            .entry(project.clone())
+            .or_default()
+            .entry(model.to_string())
+            .or_default()
+            .add(usage);
+    }
+}
+
+/// The combined usage of a month's requests to a model from a project. Once they're old
+/// enough, requests are only stored this way.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct CompactedUsage {
+    provider: String,
+    model: String,
+    project: Option<PathBuf>,
+    usage: ModelUsage,
+}
+
+impl FromIterator<TokenUsageRecord> for RecordedUsage {
+    fn from_iter<I: IntoIterator<Item = TokenUsageRecord>>(records: I) -> Self {
+        let mut usage = Self::default();
+        for record in records {
+            usage.add(&record);
+        }
+        usage
+    }
+}
+
+/// The combined usage of a set of requests.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsageTotals {
+    pub requests: usize,
+    pub usage: TokenUsage,
+    /// What the requests cost, counting only the models whose price is known.
+    pub cost: Option<f64>,
+    pub estimated: bool,
+}
+
+impl UsageTotals {
+    fn add(&mut self, model: &str, usage: &ModelUsage, prices: &HashMap<String, ModelPrice>) {
+        self.requests += usage.requests;
+        self.usage += usage.usage;
+        if let Some(cost) = usage_cost(model, &usage.usage, prices) {
+            *self.cost.get_or_insert(0.) += cost;
+        }
+        self.estimated |= usage.estimated;
+    }
+
+    fn of_models<'a>(
+        models: impl IntoIterator<Item = (&'a String, &'a ModelUsage)>,
+        prices: &HashMap<String, ModelPrice>,
+    ) -> Self {
+        let mut totals = Self::default();
+        for (model, usage) in models {
+            totals.add(model, usage, prices);
+        }
+        totals
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} input, {} output",
+            humanize_token_count(self.usage.input_tokens as usize),
+            humanize_token_count(self.usage.output_tokens as usize),
+        );
+        let cached_tokens =
+            self.usage.cache_creation_input_tokens + self.usage.cache_read_input_tokens;
+        if cached_tokens > 0 {
+            write!(
+                summary,
+                ", {} cached",
+                humanize_token_count(cached_tokens as usize)
+            )
+            .unwrap();
+        }
+        summary.push_str(" tokens");
+        if let Some(cost) = self.cost {
+            write!(summary, " (${cost:.2})").unwrap();
+        }
+        if self.estimated {
+            summary.push_str(", partly estimated");
+        }
+        summary
+    }
+}
+
+/// A calendar month in the local time zone, which budgets are counted over.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Month {
+    pub year: i32,
+    pub month: u32,
+}
+
+impl Month {
+    pub fn of(timestamp: DateTime<Utc>) -> Self {
+        let timestamp = timestamp.with_timezone(&Local);
+        Self {
+            year: timestamp.year(),
+            month: timestamp.month(),
+        }
+    }
+
+    pub fn current() -> Self {
+        Self::of(Utc::now())
+    }
+
+    /// Returns the month that's the given number of months before this one.
+    fn minus(self, months: u32) -> Self {
+        let index = self.year * 12 + self.month as i32 - 1 - months as i32;
+        Self {
+            year: index.div_euclid(12),
+            month: index.rem_euclid(12) as u32 + 1,
+        }
+    }
+
+    fn name(&self) -> String {
+        NaiveDate::from_ymd_opt(self.year, self.month, 1)
+            .map(|date| date.format("%B %Y").to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// A warning that a provider's monthly budget is nearly, or entirely, used up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetWarning {
+    pub provider: String,
+    pub exceeded: bool,
+    pub message: SharedString,
+}
+
+impl BudgetWarning {
+    /// Whether this warning is about the same state of the same budget as another one.
+    pub fn is_same_as(&self, other: &BudgetWarning) -> bool {
+        self.provider == other.provider && self.exceeded == other.exceeded
+    }
+}
+
+/// The tokens used by each of the assistant's requests, stored locally. Only their
+/// totals are kept in memory.
+pub struct TokenUsageStore {
+    usage: RecordedUsage,
+    /// The requests recorded before the database was loaded, which still need to be stored.
+    pending_records: Vec<TokenUsageRecord>,
+    database: Option<TokenUsageDatabase>,
+}
+
+impl TokenUsageStore {
+    pub fn try_global(cx: &AppContext) -> Option<Model<Self>> {
+        cx.try_global::<GlobalTokenUsageStore>()
+            .map(|store| store.0.clone())
+    }
+
+    pub fn new(db_path: PathBuf, cx: &mut ModelContext<Self>) -> Self {
+        let load = cx
+            .background_executor()
+            .spawn(async move { TokenUsageDatabase::open(db_path, Month::current()) });
+        cx.spawn(|this, mut cx| async move {
+            let (database, usage) = load.await?;
+            this.update(&mut cx, |this, cx| {
+                this.usage = usage;
+                this.database = Some(database);
+                // Add the requests that were recorded while the database was loading.
+                for record in mem::take(&mut this.pending_records) {
+                    this.record(record, cx);
+                }
+                cx.notify();
+            })
+        })
+        .detach_and_log_err(cx);
+
+        Self {
+            usage: RecordedUsage::default(),
+            pending_records: Vec::new(),
+            database: None,
+        }
+    }
+
+    pub fn usage(&self) -> &RecordedUsage {
+        &self.usage
+    }
+
+    pub fn record(&mut self, record: TokenUsageRecord, cx: &mut ModelContext<Self>) {
+        self.usage.add(&record);
+        if let Some(database) = self.database.clone() {
+            cx.background_executor()
+                .spawn(async move { database.insert(&record) })
+                .detach_and_log_err(cx);
+        } else {
+            self.pending_records.push(record);
+        }
+        cx.notify();
+    }
+
+    /// Returns the combined usage of all the requests made by a context.
+    pub fn usage_for_context(&self, context_id: &ContextId, cx: &AppContext) -> UsageTotals {
+        let prices = &AssistantSettings::get_global(cx).model_prices;
+        self.usage
+            .contexts
+            .get(context_id)
+            .map(|models| UsageTotals::of_models(models, prices))
+            .unwrap_or_default()
+    }
+
+    /// Returns a warning if the provider's budget for the current month is nearly used up.
+    pub fn budget_warning(
+        &self,
+        provider_id: &LanguageModelProviderId,
+        provider_name: &str,
+        cx: &AppContext,
+    ) -> Option<BudgetWarning> {
+        let settings = AssistantSettings::get_global(cx);
+        let budget = settings.usage_budgets.get(provider_id.0.as_ref())?;
+        check_budget(
+            &self.usage,
+            &provider_id.0,
+            provider_name,
+            budget,
+            &settings.model_prices,
+            Month::current(),
+        )
+    }
+}
+
+fn check_budget(
+    usage: &RecordedUsage,
+    provider: &str,
+    provider_name: &str,
+    budget: &UsageBudget,
+    prices: &HashMap<String, ModelPrice>,
+    month: Month,
+) -> Option<BudgetWarning> {
+    let totals = usage
+        .months
+        .get(&month)
+        .and_then(|month| month.providers.get(provider))
+        .map(|models| UsageTotals::of_models(models, prices))
+        .unwrap_or_default();
+
+    let tokens = totals.usage.total_tokens() as u64;
+    let cost = totals.cost.unwrap_or(0.);
+    let token_fraction = budget.monthly_tokens.map(|limit| {
+        (
+            tokens as f64 / limit.max(1) as f64,
+            format!(
+                "{} of {} tokens",
+                humanize_token_count(tokens as usize),
+                humanize_token_count(limit as usize)
+            ),
+        )
+    });
+    let cost_fraction = budget.monthly_cost.map(|limit| {
+        (
+            if limit > 0. {
+                cost / limit
+            } else {
+                f64::INFINITY
+            },
+            format!("${cost:.2} of ${limit:.2}"),
+        )
+    });
+    let (fraction, used) = token_fraction
+        .into_iter()
+        .chain(cost_fraction)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))?;
+
+    if fraction < budget.warning_threshold {
+        return None;
+    }
+
+    let exceeded = fraction >= 1.;
+    let message = if exceeded {
+        format!("You've used up this month's {provider_name} budget ({used}).")
+    } else {
+        format!(
+            "You've used {:.0}% of this month's {provider_name} budget ({used}).",
+            fraction * 100.
+        )
+    };
+    Some(BudgetWarning {
+        provider: provider.to_string(),
+        exceeded,
+        message: message.into(),
+    })
+}
+
+/// Returns a warning if the active provider's monthly budget is nearly used up.
+pub fn active_provider_budget_warning(cx: &AppContext) -> Option<BudgetWarning> {
+    let provider = LanguageModelRegistry::read_global(cx).active_provider()?;
+    TokenUsageStore::try_global(cx)?
+        .read(cx)
+        .budget_warning(&provider.id(), &provider.name().0, cx)
+}
+
+/// Returns a warning if the active provider's monthly budget is nearly used up, unless the
+/// user already chose to send requests anyway despite the same warning.
+pub fn unacknowledged_budget_warning(
+    acknowledged: Option<&BudgetWarning>,
+    cx: &AppContext,
+) -> Option<BudgetWarning> {
+    active_provider_budget_warning(cx).filter(|warning| {
+        acknowledged.map_or(true, |acknowledged| !acknowledged.is_same_as(warning))
+    })
+}
+
+/// Renders a budget warning, with buttons to dismiss it or to send the request anyway.
+pub fn render_budget_warning(
+    warning: &BudgetWarning,
+    on_dismiss: impl Fn(&ClickEvent, &mut WindowContext) + 'static,
+    on_send_anyway: impl Fn(&ClickEvent, &mut WindowContext) + 'static,
+) -> impl IntoElement {
+    v_flex()
+        .gap_0p5()
+        .child(
+            h_flex()
+                .gap_1p5()
+                .items_center()
+                .child(Icon::new(IconName::Warning).color(Color::Warning))
+                .child(Label::new("Token budget").weight(FontWeight::MEDIUM)),
+        )
+        .child(Label::new(warning.message.clone()))
+        .child(
+            h_flex()
+                .justify_end()
+                .gap_1()
+                .mt_1()
+                .child(Button::new("dismiss-budget-warning", "Dismiss").on_click(on_dismiss))
+                .child(Button::new("send-anyway", "Send Anyway").on_click(on_send_anyway)),
+        )
+}
+
+/// Returns the root of the project's first visible worktree, which usage is attributed to.
+pub fn project_root(project: &Model<Project>, cx: &AppContext) -> Option<PathBuf> {
+    let worktree = project.read(cx).visible_worktrees(cx).next()?;
+    let root = worktree.read(cx).abs_path().to_path_buf();
+    Some(root)
+}
+
+/// The usage reported while a completion streams in, along with its output in
+/// case the usage needs to be counted locally.
+#[derive(Default)]
+pub struct CompletionUsage {
+    reported: Option<TokenUsage>,
+    output: String,
+}
+
+impl CompletionUsage {
+    pub fn observe(&mut self, event: &LanguageModelCompletionEvent) {
+        match event {
+            LanguageModelCompletionEvent::Text(text) => self.output.push_str(text),
+            LanguageModelCompletionEvent::ToolUse(tool_use) => {
+                self.output.push_str(&tool_use.input.to_string())
+            }
+            LanguageModelCompletionEvent::UsageUpdate(usage) => self.reported = Some(*usage),
+            LanguageModelCompletionEvent::Stop(_) => {}
+        }
+    }
+}
+
+/// Records the tokens used by a completion, counting them with the model's
+/// tokenizer if the provider didn't report them.
+pub async fn record_completion_usage(
+    model: Arc<dyn LanguageModel>,
+    request: LanguageModelRequest,
+    completion: CompletionUsage,
+    source: TokenUsageSource,
+    project: Option<PathBuf>,
+    cx: &mut AsyncAppContext,
+) -> Result<()> {
+    let Some(store) = cx.update(|cx| TokenUsageStore::try_global(cx))? else {
+        return Ok(());
+    };
+
+    let (usage, estimated) = if let Some(usage) = completion.reported {
+        (usage, false)
+    } else if completion.output.is_empty() {
+        // The request failed before the model responded.
+        return Ok(());
+    } else {
+        let output = LanguageModelRequest {
+            messages: vec![LanguageModelRequestMessage {
+                role: Role::Assistant,
+                content: vec![completion.output.into()],
+                cache: false,
+            }],
+            tools: Vec::new(),
+            stop: Vec::new(),
+            temperature: 1.,
+        };
+        let (input_tokens, output_tokens) = cx.update(|cx| {
+            (
+                model.count_tokens(request, cx),
+                model.count_tokens(output, cx),
+            )
+        })?;
+        let usage = TokenUsage {
+            input_tokens: input_tokens.await? as u32,
+            output_tokens: output_tokens.await? as u32,
+            ..Default::default()
+        };
+        (usage, true)
+    };
+
+    store.update(cx, |store, cx| {
+        store.record(
+            TokenUsageRecord {
+                timestamp: Utc::now(),
+                provider: model.provider_id().0.to_string(),
+                model: model.id().0.to_string(),
+                source,
+                project,
+                usage,
+                estimated,
+            },
+            cx,
+        )
+    })
+}
+
+/// Streams the text of a completion like [`LanguageModel::stream_completion_text`],
+/// recording the tokens it used once the stream ends.
+pub fn stream_completion_text(
+    model: Arc<dyn LanguageModel>,
+    request: LanguageModelRequest,
+    source: TokenUsageSource,
+    project: Option<PathBuf>,
+    cx: &AsyncAppContext,
+) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+    let events = model.stream_completion(request.clone(), cx);
+    let (completion_tx, completion_rx) = oneshot::channel();
+    cx.spawn(|mut cx| async move {
+        if let Ok(completion) = completion_rx.await {
+            record_completion_usage(model, request, completion, source, project, &mut cx)
+                .await
+                .log_err();
+        }
+    })
+    .detach();
+
+    async move {
+        let mut recorder = CompletionRecorder {
+            completion: CompletionUsage::default(),
+            completion_tx: Some(completion_tx),
+        };
+        Ok(events
+            .await?
+            .filter_map(move |event| {
+                let text = match event {
+                    Ok(event) => {
+                        recorder.completion.observe(&event);
+                        match event {
+                            LanguageModelCompletionEvent::Text(text) => Some(Ok(text)),
+                            _ => None,
+                        }
+                    }
+                    Err(error) => Some(Err(error)),
+                };
+                future::ready(text)
+            })
+            .boxed())
+    }
+    .boxed()
+}
+
+/// Sends the completion's usage to be recorded once its stream is dropped,
+/// whether it finished or was cancelled.
+struct CompletionRecorder {
+    completion: CompletionUsage,
+    completion_tx: Option<oneshot::Sender<CompletionUsage>>,
+}
+
+impl Drop for CompletionRecorder {
+    fn drop(&mut self) {
+        if let Some(completion_tx) = self.completion_tx.take() {
+            completion_tx.send(mem::take(&mut self.completion)).ok();
+        }
+    }
+}
+
+/// The usage of each provider and project during a month.
+#[derive(Debug, PartialEq)]
+pub struct UsageReport {
+    pub month: Month,
+    pub providers: Vec<ProviderUsage>,
+    pub projects: Vec<(Option<PathBuf>, UsageTotals)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ProviderUsage {
+    pub provider: String,
+    pub totals: UsageTotals,
+    pub models: Vec<(String, UsageTotals)>,
+    pub budget: Option<UsageBudget>,
+}
+
+impl UsageReport {
+    pub fn new(usage: &RecordedUsage, month: Month, settings: &AssistantSettings) -> Self {
+        let prices = &settings.model_prices;
+        let Some(monthly_usage) = usage.months.get(&month) else {
+            return Self {
+                month,
+                providers: Vec::new(),
+                projects: Vec::new(),
+            };
+        };
+
+        let providers = monthly_usage
+            .providers
+            .iter()
+            .map(|(provider, models)| ProviderUsage {
+                provider: provider.clone(),
+                totals: UsageTotals::of_models(models, prices),
+                models: models
+                    .iter()
+                    .map(|(model, usage)| {
+                        (
+                            model.clone(),
+                            UsageTotals::of_models([(model, usage)], prices),
+                        )
+                    })
+                    .collect(),
+                budget: settings.usage_budgets.get(provider.as_str()).copied(),
+            })
+            .collect();
+        let projects = monthly_usage
+            .projects
+            .iter()
+            .map(|(project, models)| (project.clone(), UsageTotals::of_models(models, prices)))
+            .collect();
+
+        Self {
+            month,
+            providers,
+            projects,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# Assistant Token Usage for {}\n", self.month.name());
+        if self.providers.is_empty() {
+            markdown.push_str("\nNo requests were made this month.\n");
+            return markdown;
+        }
+
+        let mut estimated = false;
+        for provider in &self.providers {
+            write!(
+                markdown,
+                "\n## {}\n\n{} requests: {}\n",
+                provider.provider,
+                provider.totals.requests,
+                provider.totals.summary()
+            )
+            .unwrap();
+            if let Some(budget) = provider.budget {
+                if let Some(limit) = budget.monthly_tokens {
+                    let tokens = provider.totals.usage.total_tokens() as u64;
+                    write!(
+                        markdown,
+                        "\nToken budget: {} of {} ({:.0}%)\n",
+                        humanize_token_count(tokens as usize),
+                        humanize_token_count(limit as usize),
+                        tokens as f64 / limit.max(1) as f64 * 100.
+                    )
+                    .unwrap();
+                }
+                if let Some(limit) = budget.monthly_cost {
+                    let cost = provider.totals.cost.unwrap_or(0.);
+                    write!(markdown, "\nCost budget: ${cost:.2} of ${limit:.2}\n").unwrap();
+                }
+            }
+
+            markdown.push_str(
+                "\n| Model | Requests | Input | Output | Cache writes | Cache reads | Cost |\n",
+            );
+            markdown.push_str("| --- | --: | --: | --: | --: | --: | --: |\n");
+            for (model, totals) in &provider.models {
+                estimated |= totals.estimated;
+                writeln!(
+                    markdown,
+                    "| {}{} | {} | {} | {} | {} | {} | {} |",
+                    model,
+                    if totals.estimated { "*" } else { "" },
+                    totals.requests,
+                    totals.usage.input_tokens,
+                    totals.usage.output_tokens,
+                    totals.usage.cache_creation_input_tokens,
+                    totals.usage.cache_read_input_tokens,
+                    totals
+                        .cost
+                        .map_or_else(|| "-".to_string(), |cost| format!("${cost:.2}")),
+                )
+                .unwrap();
+            }
+        }
+
+        markdown.push_str("\n## Projects\n\n| Project | Requests | Tokens | Cost |\n");
+        markdown.push_str("| --- | --: | --: | --: |\n");
+        for (project, totals) in &self.projects {
+            writeln!(
+                markdown,
+                "| {} | {} | {} | {} |",
+                project
+                    .as_ref()
+                    .map_or_else(|| "None".to_string(), |path| path.display().to_string()),
+                totals.requests,
+                totals.usage.total_tokens(),
+                totals
+                    .cost
+                    .map_or_else(|| "-".to_string(), |cost| format!("${cost:.2}")),
+            )
+            .unwrap();
+        }
+
+        if estimated {
+            markdown.push_str(
+                "\n\\* Includes requests whose usage wasn't reported by the provider, which were counted locally.\n",
+            );
+        }
+        markdown
+    }
+}
+
+/// Opens this month's usage report in a read-only editor.
+pub fn show_token_usage(
+    workspace: &mut Workspace,
+    _: &ShowTokenUsage,
+    cx: &mut ViewContext<Workspace>,
+) {
+    let Some(store) = TokenUsageStore::try_global(cx) else {
+        return;
+    };
+    let report = UsageReport::new(
+        store.read(cx).usage(),
+        Month::current(),
+        AssistantSettings::get_global(cx),
+    )
+    .to_markdown();
+    let language = workspace
+        .app_state()
+        .languages
+        .language_for_name("Markdown");
+    cx.spawn(|workspace, mut cx| async move {
+        let language = language.await.log_err();
+        workspace
+            .update(&mut cx, |workspace, cx| {
+                workspace.with_local_workspace(cx, |workspace, cx| {
+                    let project = workspace.project();
+                    let buffer = project.update(cx, move |project, cx| {
+                        project.create_local_buffer(&report, language, cx)
+                    });
+                    let buffer = cx.new_model(|cx| {
+                        MultiBuffer::singleton(buffer, cx).with_title("Token Usage".into())
+                    });
+                    workspace.add_item_to_active_pane(
+                        Box::new(cx.new_view(|cx| {
+                            let mut editor =
+                                Editor::for_multibuffer(buffer, Some(project.clone()), true, cx);
+                            editor.set_read_only(true);
+                            editor.set_breadcrumb_header("Token Usage".into());
+                            editor
+                        })),
+                        None,
+                        true,
+                        cx,
+                    );
+                })
+            })?
+            .await
+    })
+    .detach_and_log_err(cx);
+}
+
+#[derive(Clone)]
+/// How many months of requests, counting the current one, are stored individually.
+/// Older requests are replaced with their monthly totals when the database is opened.
+const DETAILED_MONTHS: u32 = 3;
+
+struct TokenUsageDatabase {
+    env: heed::Env,
+    records: Database<SerdeJson<Uuid>, SerdeJson<TokenUsageRecord>>,
+}
+
+impl TokenUsageDatabase {
+    /// Opens the database, compacts the requests from before the last
+    /// [`DETAILED_MONTHS`], and returns the recorded usage.
+    fn open(db_path: PathBuf, current_month: Month) -> Result<(Self, RecordedUsage)> {
+        std::fs::create_dir_all(&db_path)?;
+
+        let env = unsafe {
+            heed::EnvOpenOptions::new()
+                .map_size(256 * 1024 * 1024) // 256MB
+                .max_dbs(2)
+                .open(db_path)?
+        };
+
+        let mut txn = env.write_txn()?;
+        let records: Database<SerdeJson<Uuid>, SerdeJson<TokenUsageRecord>> =
+            env.create_database(&mut txn, Some("records"))?;
+        let months: Database<SerdeJson<Month>, SerdeJson<Vec<CompactedUsage>>> =
+            env.create_database(&mut txn, Some("months"))?;
+
+        let mut compacted_months = BTreeMap::default();
+        for result in months.iter(&txn)? {
+            if let Some((month, compacted)) = result.log_err() {
+                compacted_months.insert(month, compacted);
+            }
+        }
+
+        let first_detailed_month = current_month.minus(DETAILED_MONTHS - 1);
+        let mut detailed_records = Vec::new();
+        let mut compacted_ids = Vec::new();
+        let mut changed_months = BTreeSet::new();
+        for result in records.iter(&txn)? {
+            let Some((id, record)) = result.log_err() else {
+                continue;
+            };
+            let month = Month::of(record.timestamp);
+            if month >= first_detailed_month {
+                detailed_records.push(record);
+                continue;
+            }
+
+            let compacted: &mut Vec<CompactedUsage> = compacted_months.entry(month).or_default();
+            let usage = ModelUsage::of_record(&record);
+            if let Some(existing) = compacted.iter_mut().find(|compacted| {
+                compacted.provider == record.provider
+                    && compacted.model == record.model
+                    && compacted.project == record.project
+            }) {
+                existing.usage.add(&usage);
+            } else {
+                compacted.push(CompactedUsage {
+                    provider: record.provider,
+                    model: record.model,
+                    project: record.project,
+                    usage,
+                });
+            }
+            compacted_ids.push(id);
+            changed_months.insert(month);
+        }
+
+        for month in changed_months {
+            months.put(&mut txn, &month, &compacted_months[&month])?;
+        }
+        for id in &compacted_ids {
+            records.delete(&mut txn, id)?;
+        }
+        txn.commit()?;
+
+        let mut usage = RecordedUsage::default();
+        for (month, compacted) in &compacted_months {
+            for compacted in compacted {
+                usage.add_compacted(*month, compacted);
+            }
+        }
+        detailed_records.sort_by_key(|record| record.timestamp);
+        for record in &detailed_records {
+            usage.add(record);
+        }
+
+        Ok((Self { env, records }, usage))
+    }
+
+    fn insert(&self, record: &TokenUsageRecord) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+        self.records.put(&mut txn, &Uuid::new_v4(), record)?;
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn record(
+        provider: &str,
+        model: &str,
+        day: u32,
+        month: u32,
+        input_tokens: u32,
+        output_tokens: u32,
+    ) -> TokenUsageRecord {
+        TokenUsageRecord {
+            timestamp: Local
+                .with_ymd_and_hms(2024, month, day, 12, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc),
+            provider: provider.into(),
+            model: model.into(),
+            source: TokenUsageSource::InlineAssistant,
+            project: Some(PathBuf::from("/zed")),
+            usage: TokenUsage {
+                input_tokens,
+                output_tokens,
+                ..Default::default()
+            },
+            estimated: false,
+        }
+    }
+
+    #[test]
+    fn test_cost() {
+        let prices = HashMap::from_iter([(
+            "sonnet".to_string(),
+            ModelPrice {
+                input: 3.,
+                output: 15.,
+                cache_write: Some(3.75),
+                cache_read: None,
+            },
+        )]);
+        let mut record = record("anthropic", "sonnet", 1, 9, 1_000_000, 100_000);
+        record.usage.cache_creation_input_tokens = 1_000_000;
+        record.usage.cache_read_input_tokens = 1_000_000;
+        assert_eq!(record.cost(&prices), Some(3. + 1.5 + 3.75 + 3.));
+
+        record.model = "opus".into();
+        assert_eq!(record.cost(&prices), None);
+    }
+
+    #[test]
+    fn test_monthly_report() {
+        let mut settings = AssistantSettings::default();
+        settings.model_prices.insert(
+            "gpt-4o".into(),
+            ModelPrice {
+                input: 5.,
+                output: 15.,
+                ..Default::default()
+            },
+        );
+        let mut estimated = record("ollama", "llama3", 20, 9, 500, 50);
+        estimated.estimated = true;
+        estimated.project = None;
+        let records = [
+            record("anthropic", "sonnet", 30, 8, 1000, 100),
+            record("anthropic", "sonnet", 1, 9, 1000, 100),
+            record("openai", "gpt-4o", 2, 9, 200_000, 100_000),
+            record("anthropic", "sonnet", 15, 9, 3000, 300),
+            record("anthropic", "haiku", 16, 9, 10, 1),
+            estimated,
+        ];
+
+        let report = UsageReport::new(
+            &records.into_iter().collect::<RecordedUsage>(),
+            Month {
+                year: 2024,
+                month: 9,
+            },
+            &settings,
+        );
+        assert_eq!(
+            report
+                .providers
+                .iter()
+                .map(|provider| (
+                    provider.provider.as_str(),
+                    provider.totals.requests,
+                    provider.totals.usage.input_tokens,
+                    provider.totals.usage.output_tokens,
+                    provider.totals.cost,
+                    provider.totals.estimated,
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("anthropic", 3, 4010, 401, None, false),
+                ("ollama", 1, 500, 50, None, true),
+                ("openai", 1, 200_000, 100_000, Some(2.5), false),
+            ]
+        );
+        assert_eq!(
+            report.providers[0]
+                .models
+                .iter()
+                .map(|(model, totals)| (model.as_str(), totals.requests))
+                .collect::<Vec<_>>(),
+            [("haiku", 1), ("sonnet", 2)]
+        );
+        assert_eq!(
+            report
+                .projects
+                .iter()
+                .map(|(project, totals)| (project.clone(), totals.requests))
+                .collect::<Vec<_>>(),
+            [(None, 1), (Some(PathBuf::from("/zed")), 4)]
+        );
+        assert!(report
+            .to_markdown()
+            .contains("| llama3* | 1 | 500 | 50 | 0 | 0 | - |"));
+    }
+
+    #[test]
+    fn test_context_totals() {
+        let context_id = ContextId::new();
+        let mut in_context = record("anthropic", "sonnet", 1, 9, 1000, 100);
+        in_context.source = TokenUsageSource::AssistantPanel {
+            context_id: context_id.clone(),
+        };
+        let mut summary = record("anthropic", "haiku", 2, 9, 500, 10);
+        summary.source = in_context.source.clone();
+        summary.usage.cache_read_input_tokens = 200;
+        let mut usage = [in_context, record("anthropic", "sonnet", 1, 9, 7, 7)]
+            .into_iter()
+            .collect::<RecordedUsage>();
+        usage.add(&summary);
+
+        let prices = HashMap::default();
+        let totals = UsageTotals::of_models(&usage.contexts[&context_id], &prices);
+        assert_eq!(totals.requests, 2);
+        assert_eq!(
+            totals.usage,
+            TokenUsage {
+                input_tokens: 1500,
+                output_tokens: 110,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 200,
+            }
+        );
+        assert_eq!(totals.cost, None);
+        assert!(!usage.contexts.contains_key(&ContextId::new()));
+    }
+
+    #[test]
+    fn test_budget_warnings() {
+        let month = Month {
+            year: 2024,
+            month: 9,
+        };
+        let prices = HashMap::from_iter([(
+            "sonnet".to_string(),
+            ModelPrice {
+                input: 3.,
+                output: 15.,
+                ..Default::default()
+            },
+        )]);
+        let usage = [
+            record("anthropic", "sonnet", 30, 8, 5_000_000, 0),
+            record("anthropic", "sonnet", 1, 9, 700_000, 100_000),
+            record("openai", "gpt-4o", 1, 9, 5_000_000, 0),
+        ]
+        .into_iter()
+        .collect::<RecordedUsage>();
+
+        let budget = UsageBudget {
+            monthly_tokens: Some(1_000_000),
+            monthly_cost: None,
+            warning_threshold: 0.8,
+        };
+        let warning =
+            check_budget(&usage, "anthropic", "Anthropic", &budget, &prices, month).unwrap();
+        assert!(!warning.exceeded);
+        assert_eq!(
+            warning.message.as_ref(),
+            "You've used 80% of this month's Anthropic budget (800k of 1000k tokens)."
+        );
+
+        let budget = UsageBudget {
+            monthly_tokens: Some(2_000_000),
+            ..budget
+        };
+        assert_eq!(
+            check_budget(&usage, "anthropic", "Anthropic", &budget, &prices, month),
+            None
+        );
+
+        // The most used of the token and cost budgets is the one that's warned about.
+        let budget = UsageBudget {
+            monthly_cost: Some(3.5),
+            ..budget
+        };
+        let warning =
+            check_budget(&usage, "anthropic", "Anthropic", &budget, &prices, month).unwrap();
+        assert!(warning.exceeded);
+        assert_eq!(
+            warning.message.as_ref(),
+            "You've used up this month's Anthropic budget ($3.60 of $3.50)."
+        );
+        assert!(!warning.is_same_as(&BudgetWarning {
+            exceeded: false,
+            ..warning.clone()
+        }));
+    }
+
+    #[test]
+    fn test_compact_old_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("token-usage");
+        let current_month = Month {
+            year: 2024,
+            month: 9,
+        };
+        let records = [
+            record("anthropic", "sonnet", 1, 3, 1_000, 100),
+            record("anthropic", "sonnet", 2, 3, 2_000, 200),
+            record("openai", "gpt-4o", 3, 3, 3_000, 300),
+            record("anthropic", "sonnet", 30, 6, 4_000, 400),
+            record("anthropic", "sonnet", 1, 7, 5_000, 500),
+            record("anthropic", "sonnet", 1, 9, 6_000, 600),
+        ];
+        let expected = records.iter().cloned().collect::<RecordedUsage>();
+
+        let (database, usage) = TokenUsageDatabase::open(db_path.clone(), current_month).unwrap();
+        assert!(usage.months.is_empty());
+        for record in &records {
+            database.insert(record).unwrap();
+        }
+        drop(database);
+
+        // Reopening replaces the requests from before July with their monthly totals,
+        // without counting them twice on the next open.
+        for _ in 0..2 {
+            let (database, usage) =
+                TokenUsageDatabase::open(db_path.clone(), current_month).unwrap();
+            assert_eq!(usage.months, expected.months);
+            let txn = database.env.read_txn().unwrap();
+            let mut stored_months = database
+                .records
+                .iter(&txn)
+                .unwrap()
+                .map(|result| Month::of(result.unwrap().1.timestamp).month)
+                .collect::<Vec<_>>();
+            stored_months.sort();
+            assert_eq!(stored_months, [7, 9]);
+        }
+    }
+}
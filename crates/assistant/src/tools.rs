@@ -1 +1,292 @@
+pub mod context_server_tool;
+pub mod diagnostics_tool;
+pub mod edit_file_tool;
+pub mod list_directory_tool;
 pub mod now_tool;
+pub mod read_file_tool;
+pub mod run_task_tool;
+pub mod search_project_tool;
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use fs::Fs;
+use futures::{future::Shared, FutureExt};
+use gpui::{AppContext, Model, PromptLevel, Task, WeakView, WindowContext};
+use project::{Project, ProjectPath};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use settings::{update_settings_file, Settings};
+use workspace::Workspace;
+
+use crate::assistant_settings::{AssistantSettings, ToolApproval};
+
+/// Runs a tool the model asked to use, if the user's approval policy for it allows it.
+///
+/// Prompts are shown one at a time, in the order the tools were used, so the prompt asking
+/// about this tool waits for `previous_prompt` to be answered. Returns the prompt that the
+/// next one must wait for.
+pub(crate) fn run_tool_with_approval(
+    tool: Arc<dyn Tool>,
+    input: serde_json::Value,
+    workspace: WeakView<Workspace>,
+    fs: Arc<dyn Fs>,
+    previous_prompt: Option<Shared<Task<Option<usize>>>>,
+    cx: &mut WindowContext,
+) -> (Task<Result<String>>, Option<Shared<Task<Option<usize>>>>) {
+    let tool_name = tool.name();
+    match AssistantSettings::get_global(cx).tool_approval(&tool_name) {
+        ToolApproval::Allow => (tool.run(input, workspace, cx), previous_prompt),
+        ToolApproval::Deny => (
+            Task::ready(Ok(format!(
+                "The user doesn't allow the `{tool_name}` tool to be used."
+            ))),
+            previous_prompt,
+        ),
+        ToolApproval::Ask => {
+            let message = format!("Allow the assistant to use the `{tool_name}` tool?");
+            let detail = serde_json::to_string_pretty(&input).ok();
+            let prompt = cx
+                .spawn(|mut cx| async move {
+                    if let Some(previous_prompt) = previous_prompt {
+                        previous_prompt.await;
+                    }
+                    cx.prompt(
+                        PromptLevel::Info,
+                        &message,
+                        detail.as_deref(),
+                        &["Allow", "Always Allow", "Deny"],
+                    )
+                    .await
+                    .ok()
+                })
+                .shared();
+
+            let task = cx.spawn({
+                let prompt = prompt.clone();
+                |mut cx| async move {
+                    match prompt.await {
+                        Some(0) => {}
+                        Some(1) => {
+                            let tool_name = tool_name.clone();
+                            cx.update(|cx| {
+                                update_settings_file::<AssistantSettings>(
+                                    fs,
+                                    cx,
+                                    move |settings, _| {
+                                        settings.set_tool_approval(&tool_name, ToolApproval::Allow)
+                                    },
+                                );
+                            })?;
+                        }
+                        _ => {
+                            return Ok(format!(
+                                "The user declined to let the `{tool_name}` tool be used."
+                            ))
+                        }
+                    }
+                    cx.update(|cx| tool.run(input, workspace, cx))?.await
+                }
+            });
+            (task, Some(prompt))
+        }
+    }
+}
+
+/// Returns the JSON schema of a tool's input.
+fn input_schema<T: JsonSchema>() -> serde_json::Value {
+    let schema = schemars::schema_for!(T);
+    serde_json::to_value(&schema).unwrap()
+}
+
+/// Parses a tool's input, reporting malformed input as an error the model can correct.
+fn parse_input<T: DeserializeOwned>(input: serde_json::Value) -> Result<T> {
+    serde_json::from_value(input).map_err(|error| anyhow!("invalid input: {error}"))
+}
+
+fn project_for_workspace(
+    workspace: &WeakView<Workspace>,
+    cx: &AppContext,
+) -> Result<Model<Project>> {
+    let workspace = workspace
+        .upgrade()
+        .ok_or_else(|| anyhow!("workspace was dropped"))?;
+    Ok(workspace.read(cx).project().clone())
+}
+
+/// Resolves a path given by the model, which starts with the name of one of the project's
+/// root directories, to a [`ProjectPath`].
+fn resolve_project_path(
+    project: &Model<Project>,
+    path: &str,
+    cx: &AppContext,
+) -> Result<ProjectPath> {
+    project
+        .read(cx)
+        .find_project_path(Path::new(path), cx)
+        .ok_or_else(|| {
+            let root_names = project
+                .read(cx)
+                .visible_worktrees(cx)
+                .map(|worktree| worktree.read(cx).root_name().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            anyhow!(
+                "{path} is not in the project. Paths must start with one of the project's root directories: {root_names}"
+            )
+        })
+}
+
+/// Refuses to give the model access to files matched by the `private_files` setting, such as
+/// `.env` files and keys, which mustn't leave the user's machine.
+fn ensure_not_private(
+    project: &Model<Project>,
+    project_path: &ProjectPath,
+    path: &str,
+    cx: &AppContext,
+) -> Result<()> {
+    let is_private = project
+        .read(cx)
+        .entry_for_path(project_path, cx)
+        .map_or(false, |entry| entry.is_private);
+    if is_private {
+        Err(anyhow!(
+            "{path} is private, so the assistant isn't allowed to access it"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the path of a file as shown to the model, prefixed with its worktree's root name.
+fn display_path(project: &Project, project_path: &ProjectPath, cx: &AppContext) -> String {
+    match project.worktree_for_id(project_path.worktree_id, cx) {
+        Some(worktree) => Path::new(worktree.read(cx).root_name())
+            .join(&project_path.path)
+            .to_string_lossy()
+            .into_owned(),
+        None => project_path.path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Opens a workspace on a project whose only root directory, `root`, contains the given files.
+#[cfg(test)]
+async fn test_workspace<'a>(
+    tree: serde_json::Value,
+    cx: &'a mut gpui::TestAppContext,
+) -> (WeakView<Workspace>, &'a mut gpui::VisualTestContext) {
+    cx.update(|cx| {
+        workspace::AppState::test(cx);
+        language::init(cx);
+        Project::init_settings(cx);
+        AssistantSettings::register(cx);
+    });
+    let fs = fs::FakeFs::new(cx.executor());
+    fs.insert_tree("/root", tree).await;
+    let project = Project::test(fs, ["/root".as_ref()], cx).await;
+    let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+    (workspace.downgrade(), cx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{TestAppContext, VisualTestContext};
+    use serde_json::json;
+    use settings::SettingsStore;
+
+    struct TestTool;
+
+    impl Tool for TestTool {
+        fn name(&self) -> String {
+            "test_tool".into()
+        }
+
+        fn description(&self) -> String {
+            "A tool for tests.".into()
+        }
+
+        fn input_schema(&self) -> serde_json::Value {
+            json!({})
+        }
+
+        fn run(
+            self: Arc<Self>,
+            input: serde_json::Value,
+            _workspace: WeakView<Workspace>,
+            _cx: &mut WindowContext,
+        ) -> Task<Result<String>> {
+            Task::ready(Ok(format!("ran with {input}")))
+        }
+    }
+
+    fn set_approval(approval: ToolApproval, cx: &mut VisualTestContext) {
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store.update_user_settings::<AssistantSettings>(cx, |settings| {
+                    settings.set_tool_approval("test_tool", approval)
+                });
+            });
+        });
+    }
+
+    fn run_test_tool(
+        input: serde_json::Value,
+        workspace: &WeakView<Workspace>,
+        previous_prompt: Option<Shared<Task<Option<usize>>>>,
+        cx: &mut VisualTestContext,
+    ) -> (Task<Result<String>>, Option<Shared<Task<Option<usize>>>>) {
+        let fs = fs::FakeFs::new(cx.executor());
+        cx.update(|cx| {
+            run_tool_with_approval(
+                Arc::new(TestTool),
+                input,
+                workspace.clone(),
+                fs,
+                previous_prompt,
+                cx,
+            )
+        })
+    }
+
+    #[gpui::test]
+    async fn test_run_tool_with_approval(cx: &mut TestAppContext) {
+        let (workspace, cx) = test_workspace(json!({}), cx).await;
+
+        set_approval(ToolApproval::Allow, cx);
+        let (task, prompt) = run_test_tool(json!(1), &workspace, None, cx);
+        assert!(prompt.is_none());
+        assert!(!cx.has_pending_prompt());
+        assert_eq!(task.await.unwrap(), "ran with 1");
+
+        set_approval(ToolApproval::Deny, cx);
+        let (task, prompt) = run_test_tool(json!(2), &workspace, None, cx);
+        assert!(prompt.is_none());
+        assert!(!cx.has_pending_prompt());
+        assert_eq!(
+            task.await.unwrap(),
+            "The user doesn't allow the `test_tool` tool to be used."
+        );
+
+        // The user is asked about each use in turn, and the tool only runs once it's allowed.
+        set_approval(ToolApproval::Ask, cx);
+        let (first_task, prompt) = run_test_tool(json!(3), &workspace, None, cx);
+        let (second_task, prompt) = run_test_tool(json!(4), &workspace, prompt, cx);
+        assert!(prompt.is_some());
+        cx.run_until_parked();
+        assert!(cx.has_pending_prompt());
+
+        cx.simulate_prompt_answer(2);
+        assert_eq!(
+            first_task.await.unwrap(),
+            "The user declined to let the `test_tool` tool be used."
+        );
+        cx.run_until_parked();
+        assert!(cx.has_pending_prompt());
+
+        cx.simulate_prompt_answer(0);
+        assert_eq!(second_task.await.unwrap(), "ran with 4");
+        assert!(!cx.has_pending_prompt());
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use context_servers::{manager::ContextServerManager, types};
+use gpui::{Task, WeakView, WindowContext};
+use workspace::Workspace;
+
+/// The longest tool name that every language model provider accepts.
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// A tool provided by a context server, named `<server id>__<tool name>`.
+pub struct ContextServerTool {
+    name: String,
+    server_id: String,
+    tool: types::Tool,
+}
+
+impl ContextServerTool {
+    pub fn new(server_id: impl Into<String>, tool: types::Tool) -> Self {
+        let server_id = server_id.into();
+        Self {
+            name: tool_name(&server_id, &tool.name),
+            server_id,
+            tool,
+        }
+    }
+}
+
+/// Returns the name a context server's tool is sent to the model with.
+///
+/// Providers only accept tool names made of ASCII letters, digits, `_` and `-`, of at most
+/// [`MAX_TOOL_NAME_LEN`] characters, so any other characters are replaced with `_`. The
+/// server and tool are recovered from the name through the [`ToolRegistry`](assistant_tool::ToolRegistry).
+fn tool_name(server_id: &str, tool_name: &str) -> String {
+    fn sanitize(name: &str) -> impl Iterator<Item = char> + '_ {
+        name.chars().map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+    }
+
+    let mut name = sanitize(server_id).collect::<String>();
+    name.push_str("__");
+    name.extend(sanitize(tool_name));
+    name.truncate(MAX_TOOL_NAME_LEN);
+    name
+}
+
+impl Tool for ContextServerTool {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn description(&self) -> String {
+        self.tool.description.clone().unwrap_or_else(|| {
+            format!(
+                "Runs the {} tool of the {} context server.",
+                self.tool.name, self.server_id
+            )
+        })
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        self.tool.input_schema.clone()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        _workspace: WeakView<Workspace>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<String>> {
+        let manager = ContextServerManager::global(cx);
+        let Some(server) = manager.read(cx).get_server(&self.server_id) else {
+            return Task::ready(Err(anyhow!("Context server not found")));
+        };
+
+        cx.foreground_executor().spawn(async move {
+            let Some(protocol) = server.client.read().clone() else {
+                return Err(anyhow!("Context server not initialized"));
+            };
+
+            let response = protocol.run_tool(&self.tool.name, Some(input)).await?;
+            let mut output = String::new();
+            for content in response.content {
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                match content {
+                    types::ToolResponseContent::Text { text } => output.push_str(&text),
+                    types::ToolResponseContent::Image { mime_type, .. } => {
+                        write!(output, "[{mime_type} image]").unwrap()
+                    }
+                    types::ToolResponseContent::Resource { resource } => match resource.text {
+                        Some(text) => output.push_str(&text),
+                        None => write!(output, "[binary content of {}]", resource.uri).unwrap(),
+                    },
+                }
+            }
+
+            if response.is_error {
+                Err(anyhow!(output))
+            } else {
+                Ok(output)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    #[test]
+    fn test_tool_names_are_accepted_by_providers() {
+        let valid_name = Regex::new("^[a-zA-Z0-9_-]{1,64}$").unwrap();
+        let cases = [
+            ("github", "create_issue"),
+            ("my.server", "files/read"),
+            ("server with spaces", "tool:with:colons"),
+            ("ünïcödé", "工具"),
+            ("a", &"very-long-tool-name".repeat(10)),
+        ];
+        for (server_id, name) in cases {
+            let tool = ContextServerTool::new(
+                server_id,
+                types::Tool {
+                    name: name.to_string(),
+                    description: None,
+                    input_schema: serde_json::Value::Null,
+                },
+            );
+            assert!(
+                valid_name.is_match(&tool.name()),
+                "invalid tool name {:?}",
+                tool.name()
+            );
+        }
+
+        assert_eq!(tool_name("github", "create_issue"), "github__create_issue");
+        assert_eq!(
+            tool_name("my.server", "files/read"),
+            "my_server__files_read"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::{fmt::Write, sync::Arc};
+
+use anyhow::Result;
+use assistant_tool::Tool;
+use collections::BTreeSet;
+use futures::future;
+use gpui::{Task, WeakView, WindowContext};
+use language::{DiagnosticSeverity, ToPoint};
+use project::{ProjectItem, ProjectPath};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use workspace::Workspace;
+
+use super::{display_path, input_schema, parse_input, project_for_workspace, resolve_project_path};
+
+/// The output stops after this many diagnostics.
+const MAX_DIAGNOSTICS: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DiagnosticsToolInput {
+    /// The path of a file or directory to restrict the diagnostics to, starting with the name of
+    /// one of the project's root directories. Omit it to list the diagnostics of the whole
+    /// project.
+    path: Option<String>,
+    /// Whether to list warnings in addition to errors.
+    #[serde(default)]
+    include_warnings: bool,
+}
+
+pub struct DiagnosticsTool;
+
+impl Tool for DiagnosticsTool {
+    fn name(&self) -> String {
+        "diagnostics".into()
+    }
+
+    fn description(&self) -> String {
+        "Lists the errors, and optionally the warnings, reported by language servers for the project's files.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        input_schema::<DiagnosticsToolInput>()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakView<Workspace>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<String>> {
+        let open_buffers = parse_input::<DiagnosticsToolInput>(input).and_then(|input| {
+            let project = project_for_workspace(&workspace, cx)?;
+            let scope = input
+                .path
+                .as_deref()
+                .map(|path| resolve_project_path(&project, path, cx))
+                .transpose()?;
+
+            let paths = project
+                .read(cx)
+                .diagnostic_summaries(false, cx)
+                .filter(|(_, _, summary)| {
+                    summary.error_count > 0 || (input.include_warnings && summary.warning_count > 0)
+                })
+                .map(|(project_path, _, _)| project_path)
+                .filter(|project_path| {
+                    scope.as_ref().map_or(true, |scope| {
+                        scope.worktree_id == project_path.worktree_id
+                            && project_path.path.starts_with(&scope.path)
+                    })
+                })
+                .collect::<BTreeSet<ProjectPath>>();
+            let open_buffers = project.update(cx, |project, cx| {
+                paths
+                    .into_iter()
+                    .map(|path| project.open_buffer(path, cx))
+                    .collect::<Vec<_>>()
+            });
+            Ok((project, input.include_warnings, open_buffers))
+        });
+        let (project, include_warnings, open_buffers) = match open_buffers {
+            Ok(open_buffers) => open_buffers,
+            Err(error) => return Task::ready(Err(error)),
+        };
+
+        cx.spawn(|cx| async move {
+            let buffers = future::try_join_all(open_buffers).await?;
+            cx.update(|cx| {
+                let mut text = String::new();
+                let mut count = 0;
+                'buffers: for buffer in buffers {
+                    let buffer = buffer.read(cx);
+                    let snapshot = buffer.snapshot();
+                    let path = match buffer.project_path(cx) {
+                        Some(project_path) => display_path(project.read(cx), &project_path, cx),
+                        None => continue,
+                    };
+
+                    let mut entries = snapshot
+                        .diagnostic_groups(None)
+                        .into_iter()
+                        .map(|(_, group)| group.entries[group.primary_ix].clone())
+                        .filter(|entry| {
+                            entry.diagnostic.severity == DiagnosticSeverity::ERROR
+                                || (include_warnings
+                                    && entry.diagnostic.severity == DiagnosticSeverity::WARNING)
+                        })
+                        .map(|entry| (entry.range.start.to_point(&snapshot), entry.diagnostic))
+                        .collect::<Vec<_>>();
+                    entries.sort_by_key(|(start, _)| *start);
+
+                    for (start, diagnostic) in entries {
+                        if count == MAX_DIAGNOSTICS {
+                            writeln!(
+                                text,
+                                "[Stopped after {MAX_DIAGNOSTICS} diagnostics; restrict the path to see the rest]"
+                            )
+                            .unwrap();
+                            break 'buffers;
+                        }
+                        count += 1;
+
+                        let severity = if diagnostic.severity == DiagnosticSeverity::ERROR {
+                            "error"
+                        } else {
+                            "warning"
+                        };
+                        writeln!(
+                            text,
+                            "{path}:{}:{}: {severity}: {}",
+                            start.row + 1,
+                            start.column + 1,
+                            diagnostic.message
+                        )
+                        .unwrap();
+                    }
+                }
+
+                if count == 0 {
+                    text.push_str("No diagnostics found.");
+                }
+                text
+            })
+        })
+    }
+}
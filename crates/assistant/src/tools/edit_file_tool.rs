@@ -0,0 +1,130 @@
+use std::{ops::Range, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use editor::Editor;
+use gpui::{Task, WeakView, WindowContext};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use workspace::Workspace;
+
+use super::{
+    ensure_not_private, input_schema, parse_input, project_for_workspace, resolve_project_path,
+};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EditFileToolInput {
+    /// The path of the file to edit, starting with the name of one of the project's root
+    /// directories.
+    path: String,
+    /// The text to replace. It must appear exactly once in the file, so include enough
+    /// surrounding lines to make it unique.
+    old_text: String,
+    /// The text to replace it with.
+    new_text: String,
+}
+
+pub struct EditFileTool;
+
+impl Tool for EditFileTool {
+    fn name(&self) -> String {
+        "edit_file".into()
+    }
+
+    fn description(&self) -> String {
+        "Proposes an edit to a file of the project by replacing a unique snippet of its text. The edit is applied without saving the file, and shown to the user as a diff to review.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        input_schema::<EditFileToolInput>()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakView<Workspace>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<String>> {
+        let open_buffer = parse_input::<EditFileToolInput>(input).and_then(|input| {
+            let project = project_for_workspace(&workspace, cx)?;
+            let project_path = resolve_project_path(&project, &input.path, cx)?;
+            ensure_not_private(&project, &project_path, &input.path, cx)?;
+            let open_buffer =
+                project.update(cx, |project, cx| project.open_buffer(project_path, cx));
+            Ok((input, open_buffer))
+        });
+        let (input, open_buffer) = match open_buffer {
+            Ok(open_buffer) => open_buffer,
+            Err(error) => return Task::ready(Err(error)),
+        };
+
+        cx.spawn(|mut cx| async move {
+            let buffer = open_buffer.await?;
+            cx.update(|cx| {
+                if buffer.read(cx).read_only() {
+                    return Err(anyhow!("{} is read-only", input.path));
+                }
+
+                let old_text = buffer.read(cx).text();
+                let range = find_unique_match(&old_text, &input.old_text)
+                    .map_err(|error| anyhow!("{error} in {}", input.path))?;
+                let new_text = input.new_text.replace("\r\n", "\n");
+                buffer.update(cx, |buffer, cx| {
+                    buffer.finalize_last_transaction();
+                    buffer.edit([(range, new_text)], None, cx);
+                    buffer.finalize_last_transaction();
+                });
+
+                if let Some(workspace) = workspace.upgrade() {
+                    let title = format!(
+                        "{} (proposed edit)",
+                        buffer
+                            .read(cx)
+                            .file()
+                            .map(|file| file.file_name(cx).to_string_lossy().into_owned())
+                            .unwrap_or_default()
+                    );
+                    Editor::open_diff(&workspace, &buffer, old_text, title, cx);
+                }
+                Ok(format!(
+                    "Edited {} without saving it. The user will review the change, and may undo it.",
+                    input.path
+                ))
+            })?
+        })
+    }
+}
+
+/// Finds the only occurrence of the given snippet in the text.
+fn find_unique_match(text: &str, snippet: &str) -> Result<Range<usize>> {
+    let snippet = snippet.replace("\r\n", "\n");
+    if snippet.is_empty() {
+        return Err(anyhow!("old_text is empty"));
+    }
+
+    let mut matches = text.match_indices(&snippet);
+    let (start, _) = matches
+        .next()
+        .ok_or_else(|| anyhow!("old_text was not found"))?;
+    if matches.next().is_some() {
+        return Err(anyhow!(
+            "old_text appears more than once; include more surrounding lines"
+        ));
+    }
+    Ok(start..start + snippet.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_unique_match() {
+        let text = "fn one() {}\nfn two() {}\nfn one_more() {}\n";
+        assert_eq!(find_unique_match(text, "fn two() {}").unwrap(), 12..23);
+        assert_eq!(find_unique_match(text, "two() {}\r\nfn").unwrap(), 15..26);
+        assert!(find_unique_match(text, "fn one").is_err());
+        assert!(find_unique_match(text, "fn three").is_err());
+        assert!(find_unique_match(text, "").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::{fmt::Write, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use gpui::{Task, WeakView, WindowContext};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use workspace::Workspace;
+
+use super::{display_path, input_schema, parse_input, project_for_workspace, resolve_project_path};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ListDirectoryToolInput {
+    /// The path of the directory to list, starting with the name of one of the project's root
+    /// directories. Use a root directory's name on its own to list its contents.
+    path: String,
+}
+
+pub struct ListDirectoryTool;
+
+impl Tool for ListDirectoryTool {
+    fn name(&self) -> String {
+        "list_directory".into()
+    }
+
+    fn description(&self) -> String {
+        "Lists the files and directories inside a directory of the project. Directories are suffixed with a slash, and ignored entries are marked as such.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        input_schema::<ListDirectoryToolInput>()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakView<Workspace>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<String>> {
+        let listing = parse_input::<ListDirectoryToolInput>(input).and_then(|input| {
+            let project = project_for_workspace(&workspace, cx)?;
+            let project_path = resolve_project_path(&project, &input.path, cx)?;
+            let project = project.read(cx);
+            let worktree = project
+                .worktree_for_id(project_path.worktree_id, cx)
+                .ok_or_else(|| anyhow!("{} is not in the project", input.path))?;
+            let worktree = worktree.read(cx);
+            let entry = worktree
+                .entry_for_path(&project_path.path)
+                .ok_or_else(|| anyhow!("{} doesn't exist", input.path))?;
+            if !entry.is_dir() {
+                return Err(anyhow!("{} is not a directory", input.path));
+            }
+
+            let mut text = format!("{}:\n", display_path(project, &project_path, cx));
+            for child in worktree.child_entries(&project_path.path) {
+                let Some(name) = child.path.file_name() else {
+                    continue;
+                };
+                write!(text, "{}", name.to_string_lossy()).unwrap();
+                if child.is_dir() {
+                    text.push('/');
+                }
+                if child.is_ignored {
+                    text.push_str(" (ignored)");
+                }
+                text.push('\n');
+            }
+            Ok(text)
+        });
+
+        Task::ready(listing)
+    }
+}
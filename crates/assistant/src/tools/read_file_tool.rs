@@ -0,0 +1,192 @@
+use std::{fmt::Write, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use assistant_tool::Tool;
+use gpui::{Task, WeakView, WindowContext};
+use language::Point;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use workspace::Workspace;
+
+use super::{
+    ensure_not_private, input_schema, parse_input, project_for_workspace, resolve_project_path,
+};
+
+/// Files are truncated after this many lines, unless a range is requested.
+const MAX_LINES: u32 = 1000;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReadFileToolInput {
+    /// The path of the file to read, starting with the name of one of the project's root
+    /// directories.
+    path: String,
+    /// The one-based line number at which to start reading.
+    start_line: Option<u32>,
+    /// The one-based line number at which to stop reading, inclusive.
+    end_line: Option<u32>,
+}
+
+pub struct ReadFileTool;
+
+impl Tool for ReadFileTool {
+    fn name(&self) -> String {
+        "read_file".into()
+    }
+
+    fn description(&self) -> String {
+        "Reads the contents of a file in the project, or of a range of its lines. Each line is prefixed with its line number.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        input_schema::<ReadFileToolInput>()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakView<Workspace>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<String>> {
+        let open_buffer = parse_input::<ReadFileToolInput>(input).and_then(|input| {
+            let project = project_for_workspace(&workspace, cx)?;
+            let project_path = resolve_project_path(&project, &input.path, cx)?;
+            ensure_not_private(&project, &project_path, &input.path, cx)?;
+            let open_buffer =
+                project.update(cx, |project, cx| project.open_buffer(project_path, cx));
+            Ok((input, open_buffer))
+        });
+        let (input, open_buffer) = match open_buffer {
+            Ok(open_buffer) => open_buffer,
+            Err(error) => return Task::ready(Err(error)),
+        };
+
+        cx.spawn(|cx| async move {
+            let buffer = open_buffer.await?;
+            let snapshot = buffer.read_with(&cx, |buffer, _| buffer.snapshot())?;
+
+            let line_count = snapshot.max_point().row + 1;
+            let start_row = input.start_line.unwrap_or(1).max(1) - 1;
+            if start_row >= line_count {
+                return Err(anyhow!(
+                    "{} has {line_count} lines, but reading from line {} was requested",
+                    input.path,
+                    start_row + 1
+                ));
+            }
+            let requested_end_row = input.end_line.map(|line| line.max(1) - 1);
+            let end_row = requested_end_row
+                .unwrap_or(start_row + MAX_LINES - 1)
+                .min(line_count - 1);
+
+            let mut text = String::new();
+            let range = Point::new(start_row, 0)..Point::new(end_row, snapshot.line_len(end_row));
+            let lines = snapshot.text_for_range(range).collect::<String>();
+            for (row, line) in (start_row..).zip(lines.split('\n')) {
+                writeln!(text, "{:>5} {line}", row + 1).unwrap();
+            }
+            if requested_end_row.is_none() && end_row + 1 < line_count {
+                writeln!(
+                    text,
+                    "[{} more lines; request a range starting at line {} to read them]",
+                    line_count - end_row - 1,
+                    end_row + 2
+                )
+                .unwrap();
+            }
+            Ok(text)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::test_workspace;
+    use gpui::{TestAppContext, VisualTestContext};
+    use serde_json::json;
+
+    async fn read_file(
+        input: serde_json::Value,
+        workspace: &WeakView<Workspace>,
+        cx: &mut VisualTestContext,
+    ) -> Result<String> {
+        cx.update(|cx| Arc::new(ReadFileTool).run(input, workspace.clone(), cx))
+            .await
+    }
+
+    #[gpui::test]
+    async fn test_read_file_line_ranges(cx: &mut TestAppContext) {
+        let long_file = (1..=1500)
+            .map(|line| format!("line {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (workspace, cx) = test_workspace(
+            json!({
+                "short.txt": "one\ntwo\nthree\nfour\nfive",
+                "long.txt": long_file,
+                ".env": "API_KEY=secret",
+            }),
+            cx,
+        )
+        .await;
+
+        assert_eq!(
+            read_file(json!({ "path": "root/short.txt" }), &workspace, cx)
+                .await
+                .unwrap(),
+            "    1 one\n    2 two\n    3 three\n    4 four\n    5 five\n"
+        );
+        assert_eq!(
+            read_file(
+                json!({ "path": "root/short.txt", "start_line": 2, "end_line": 3 }),
+                &workspace,
+                cx
+            )
+            .await
+            .unwrap(),
+            "    2 two\n    3 three\n"
+        );
+        assert_eq!(
+            read_file(
+                json!({ "path": "root/short.txt", "start_line": 4, "end_line": 100 }),
+                &workspace,
+                cx
+            )
+            .await
+            .unwrap(),
+            "    4 four\n    5 five\n"
+        );
+        assert!(read_file(
+            json!({ "path": "root/short.txt", "start_line": 6 }),
+            &workspace,
+            cx
+        )
+        .await
+        .is_err());
+
+        // Long files are truncated unless a range is requested.
+        let text = read_file(json!({ "path": "root/long.txt" }), &workspace, cx)
+            .await
+            .unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), MAX_LINES as usize + 1);
+        assert_eq!(lines[MAX_LINES as usize - 1], " 1000 line 1000");
+        assert_eq!(
+            lines[MAX_LINES as usize],
+            "[500 more lines; request a range starting at line 1001 to read them]"
+        );
+        let text = read_file(
+            json!({ "path": "root/long.txt", "start_line": 1001 }),
+            &workspace,
+            cx,
+        )
+        .await
+        .unwrap();
+        assert_eq!(text.lines().count(), 500);
+
+        // Private files are never read.
+        assert!(read_file(json!({ "path": "root/.env" }), &workspace, cx)
+            .await
+            .is_err());
+    }
+}
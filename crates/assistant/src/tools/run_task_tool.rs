@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context as _, Result};
+use assistant_tool::Tool;
+use editor::tasks::task_context;
+use gpui::{Task, WeakView, WindowContext};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use terminal::TaskStatus;
+use terminal_view::terminal_panel::TerminalPanel;
+use workspace::Workspace;
+
+use super::{input_schema, parse_input};
+
+/// The number of lines of the task's output returned to the model.
+const OUTPUT_LINES: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RunTaskToolInput {
+    /// The label of the task to run.
+    label: String,
+}
+
+pub struct RunTaskTool;
+
+impl Tool for RunTaskTool {
+    fn name(&self) -> String {
+        "run_task".into()
+    }
+
+    fn description(&self) -> String {
+        "Runs one of the tasks configured for the project in a terminal, waits for it to finish, and returns the end of its output. An unknown label returns the list of available tasks.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        input_schema::<RunTaskToolInput>()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakView<Workspace>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<String>> {
+        let input = match parse_input::<RunTaskToolInput>(input) {
+            Ok(input) => input,
+            Err(error) => return Task::ready(Err(error)),
+        };
+
+        cx.spawn(|mut cx| async move {
+            let (context_task, templates) = workspace.update(&mut cx, |workspace, cx| {
+                let context_task = task_context(workspace, cx);
+                let templates = workspace
+                    .project()
+                    .update(cx, |project, cx| project.task_templates(None, None, cx));
+                (context_task, templates)
+            })?;
+            let mut task_context = context_task.await;
+            let templates = templates.await?;
+
+            let Some((source_kind, template)) = templates
+                .iter()
+                .find(|(_, template)| template.label == input.label)
+                .cloned()
+            else {
+                let labels = templates
+                    .iter()
+                    .map(|(_, template)| template.label.as_str())
+                    .collect::<Vec<_>>();
+                return Err(if labels.is_empty() {
+                    anyhow!("The project has no tasks")
+                } else {
+                    anyhow!(
+                        "No task is labeled {:?}. The available tasks are: {}",
+                        input.label,
+                        labels.join(", ")
+                    )
+                });
+            };
+
+            let spawn_terminal = workspace.update(&mut cx, |workspace, cx| {
+                let project = workspace.project().clone();
+                if task_context.cwd.is_none() {
+                    task_context.cwd = project
+                        .read(cx)
+                        .visible_worktrees(cx)
+                        .next()
+                        .map(|worktree| worktree.read(cx).abs_path().to_path_buf());
+                }
+                let mut resolved_task = template
+                    .resolve_task(&source_kind.to_id_base(), &task_context)
+                    .with_context(|| format!("failed to resolve task {:?}", input.label))?;
+                let spawn_in_terminal = resolved_task
+                    .resolved
+                    .clone()
+                    .with_context(|| format!("failed to resolve task {:?}", input.label))?;
+                resolved_task.resolved = Some(spawn_in_terminal.clone());
+                project.update(cx, |project, cx| {
+                    project.task_inventory().update(cx, |inventory, _| {
+                        inventory.task_scheduled(source_kind, resolved_task);
+                    })
+                });
+
+                let terminal_panel = workspace
+                    .panel::<TerminalPanel>(cx)
+                    .context("the terminal panel is not available")?;
+                anyhow::Ok(terminal_panel.update(cx, |terminal_panel, cx| {
+                    terminal_panel.spawn_in_new_terminal(spawn_in_terminal, cx)
+                }))
+            })??;
+
+            let terminal = spawn_terminal.await?;
+            terminal
+                .update(&mut cx, |terminal, cx| terminal.wait_for_completed_task(cx))?
+                .await;
+
+            terminal.update(&mut cx, |terminal, _| {
+                let status = match terminal.task().map(|task| task.status) {
+                    Some(TaskStatus::Completed { success: true }) => "succeeded",
+                    Some(TaskStatus::Completed { success: false }) => "failed",
+                    _ => "stopped without reporting an exit code",
+                };
+                let output = terminal.last_n_non_empty_lines(OUTPUT_LINES).join("\n");
+                format!(
+                    "The task {:?} {status}. The last lines of its output were:\n{output}",
+                    input.label
+                )
+            })
+        })
+    }
+}
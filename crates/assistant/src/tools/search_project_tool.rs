@@ -0,0 +1,217 @@
+use std::{fmt::Write, sync::Arc};
+
+use anyhow::Result;
+use assistant_tool::Tool;
+use gpui::{Task, WeakView, WindowContext};
+use language::{Point, ToPoint};
+use project::{
+    search::{SearchQuery, SearchResult},
+    ProjectItem,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use util::paths::PathMatcher;
+use workspace::Workspace;
+
+use super::{display_path, input_schema, parse_input, project_for_workspace};
+
+/// The search stops after this many matching lines.
+const MAX_MATCHES: usize = 200;
+/// Matching lines are truncated after this many characters.
+const MAX_LINE_LEN: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SearchProjectToolInput {
+    /// The text to search for.
+    query: String,
+    /// Whether the query is a regular expression.
+    #[serde(default)]
+    regex: bool,
+    /// Whether the search is case sensitive.
+    #[serde(default)]
+    case_sensitive: bool,
+    /// Glob patterns that the paths of searched files must match, such as `**/*.rs`.
+    #[serde(default)]
+    include: Vec<String>,
+}
+
+pub struct SearchProjectTool;
+
+impl Tool for SearchProjectTool {
+    fn name(&self) -> String {
+        "search_project".into()
+    }
+
+    fn description(&self) -> String {
+        "Searches the files of the project for text or a regular expression, returning each matching line prefixed with its path and line number.".into()
+    }
+
+    fn input_schema(&self) -> serde_json::Value {
+        input_schema::<SearchProjectToolInput>()
+    }
+
+    fn run(
+        self: Arc<Self>,
+        input: serde_json::Value,
+        workspace: WeakView<Workspace>,
+        cx: &mut WindowContext,
+    ) -> Task<Result<String>> {
+        let search = parse_input::<SearchProjectToolInput>(input).and_then(|input| {
+            let project = project_for_workspace(&workspace, cx)?;
+            let files_to_include = PathMatcher::new(&input.include)?;
+            let query = if input.regex {
+                SearchQuery::regex(
+                    &input.query,
+                    false,
+                    input.case_sensitive,
+                    false,
+                    files_to_include,
+                    PathMatcher::default(),
+                    None,
+                )?
+            } else {
+                SearchQuery::text(
+                    &input.query,
+                    false,
+                    input.case_sensitive,
+                    false,
+                    files_to_include,
+                    PathMatcher::default(),
+                    None,
+                )?
+            };
+            let results = project.update(cx, |project, cx| project.search(query, cx));
+            Ok((project, results))
+        });
+        let (project, results) = match search {
+            Ok(search) => search,
+            Err(error) => return Task::ready(Err(error)),
+        };
+
+        cx.spawn(|cx| async move {
+            let mut text = String::new();
+            let mut match_count = 0;
+            let mut private_file_count = 0;
+            // Dropping the receiver once enough matches are found cancels the search.
+            while let Ok(SearchResult::Buffer { buffer, ranges }) = results.recv().await {
+                let truncated = cx.update(|cx| {
+                    // Files matched by the `private_files` setting mustn't be shown to the model.
+                    if buffer.read(cx).file().map_or(false, |file| file.is_private()) {
+                        private_file_count += 1;
+                        return false;
+                    }
+
+                    let snapshot = buffer.read(cx).snapshot();
+                    let Some(project_path) = buffer.read(cx).project_path(cx) else {
+                        return false;
+                    };
+                    let path = display_path(project.read(cx), &project_path, cx);
+
+                    let mut last_row = None;
+                    for range in ranges {
+                        let row = range.start.to_point(&snapshot).row;
+                        if last_row == Some(row) {
+                            continue;
+                        }
+                        last_row = Some(row);
+
+                        if match_count == MAX_MATCHES {
+                            return true;
+                        }
+                        match_count += 1;
+
+                        let line = snapshot
+                            .text_for_range(Point::new(row, 0)..Point::new(row, snapshot.line_len(row)))
+                            .collect::<String>();
+                        let line = line.trim();
+                        let line = match line.char_indices().nth(MAX_LINE_LEN) {
+                            Some((ix, _)) => format!("{}…", &line[..ix]),
+                            None => line.to_string(),
+                        };
+                        writeln!(text, "{path}:{}: {line}", row + 1).unwrap();
+                    }
+                    false
+                })?;
+
+                if truncated {
+                    writeln!(
+                        text,
+                        "[The search stopped after {MAX_MATCHES} matches; narrow the query to see the rest]"
+                    )
+                    .unwrap();
+                    break;
+                }
+            }
+
+            if match_count == 0 {
+                text.push_str("No matches found.\n");
+            }
+            if private_file_count > 0 {
+                writeln!(
+                    text,
+                    "[{private_file_count} private files also matched, which can't be shown]"
+                )
+                .unwrap();
+            }
+            Ok(text)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::test_workspace;
+    use gpui::{TestAppContext, VisualTestContext};
+    use serde_json::json;
+
+    async fn search_project(
+        input: serde_json::Value,
+        workspace: &WeakView<Workspace>,
+        cx: &mut VisualTestContext,
+    ) -> String {
+        cx.update(|cx| Arc::new(SearchProjectTool).run(input, workspace.clone(), cx))
+            .await
+            .unwrap()
+    }
+
+    #[gpui::test]
+    async fn test_search_project(cx: &mut TestAppContext) {
+        let many_matches = (1..=MAX_MATCHES + 50)
+            .map(|line| format!("needle {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (workspace, cx) = test_workspace(
+            json!({
+                "many.txt": many_matches,
+                "config.rs": "let api_key = std::env::var(\"API_KEY\");",
+                ".env": "api_key=secret",
+            }),
+            cx,
+        )
+        .await;
+
+        // The search stops once enough matching lines have been found.
+        let text = search_project(json!({ "query": "needle" }), &workspace, cx).await;
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), MAX_MATCHES + 1);
+        assert_eq!(lines[0], "root/many.txt:1: needle 1");
+        assert_eq!(
+            lines[MAX_MATCHES],
+            format!(
+                "[The search stopped after {MAX_MATCHES} matches; narrow the query to see the rest]"
+            )
+        );
+
+        // Matches in private files are left out.
+        let text = search_project(json!({ "query": "api_key" }), &workspace, cx).await;
+        assert_eq!(
+            text,
+            "root/config.rs:1: let api_key = std::env::var(\"API_KEY\");\n\
+             [1 private files also matched, which can't be shown]\n"
+        );
+
+        let text = search_project(json!({ "query": "haystack" }), &workspace, cx).await;
+        assert_eq!(text, "No matches found.\n");
+    }
+}
@@ -2958,6 +2958,15 @@ async fn forward_find_search_candidates_request(
     if host_version.is_some_and(|host_version| host_version < ZedVersion::with_search_candidates())
     {
         let query = request.query.ok_or_else(|| anyhow!("missing query"))?;
+        if query.structural {
+            return Err(anyhow!("host does not support structural search"))?;
+        }
+        if query
+            .scope
+            .is_some_and(|scope| scope.kind != proto::search_scope::Kind::Everywhere as i32)
+        {
+            return Err(anyhow!("host does not support scoped search"))?;
+        }
         let search = proto::SearchProject {
             project_id: project_id.to_proto(),
             query: query.query,
@@ -893,9 +893,8 @@ impl RandomizedTest for ProjectCollaborationTest {
                 let search = cx.executor().spawn(async move {
                     let mut results = HashMap::default();
                     while let Some(result) = search.next().await {
-                        if let SearchResult::Buffer { buffer, ranges } = result {
-                            results.entry(buffer).or_insert(ranges);
-                        }
+                        let SearchResult::Buffer { buffer, ranges } = result;
+                        results.entry(buffer).or_insert(ranges);
                     }
                     results
                 });
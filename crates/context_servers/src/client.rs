@@ -1,32 +1,35 @@
 use anyhow::{anyhow, Context, Result};
 use collections::HashMap;
-use futures::{channel::oneshot, io::BufWriter, select, AsyncRead, AsyncWrite, FutureExt};
+use futures::{channel::oneshot, select, FutureExt, Stream, StreamExt};
 use gpui::{AsyncAppContext, BackgroundExecutor, Task};
 use parking_lot::Mutex;
 use postage::barrier;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{value::RawValue, Value};
-use smol::{
-    channel,
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    process::{self, Child},
-};
+use smol::channel;
 use std::{
     fmt,
-    path::PathBuf,
     sync::{
         atomic::{AtomicI32, Ordering::SeqCst},
         Arc,
     },
     time::{Duration, Instant},
 };
-use util::TryFutureExt;
+use util::{ResultExt, TryFutureExt};
+
+use crate::transport::Transport;
 
 const JSON_RPC_VERSION: &str = "2.0";
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
 
 type ResponseHandler = Box<dyn Send + FnOnce(Result<String, Error>)>;
-type NotificationHandler = Box<dyn Send + FnMut(RequestId, Value, AsyncAppContext)>;
+type NotificationHandler = Box<dyn Send + FnMut(Option<RequestId>, Value, AsyncAppContext)>;
+type RequestHandler = Box<dyn Send + FnMut(Value, AsyncAppContext) -> Task<Result<Value>>>;
+
+/// The JSON-RPC error code for requests whose method isn't handled.
+const METHOD_NOT_FOUND: i32 = -32601;
+/// The JSON-RPC error code for requests that failed.
+const INTERNAL_ERROR: i32 = -32603;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -39,8 +42,8 @@ pub struct Client {
     server_id: ContextServerId,
     next_id: AtomicI32,
     outbound_tx: channel::Sender<String>,
-    name: Arc<str>,
     notification_handlers: Arc<Mutex<HashMap<&'static str, NotificationHandler>>>,
+    request_handlers: Arc<Mutex<HashMap<&'static str, RequestHandler>>>,
     response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
     #[allow(clippy::type_complexity)]
     #[allow(dead_code)]
@@ -48,7 +51,8 @@ pub struct Client {
     #[allow(dead_code)]
     output_done_rx: Mutex<Option<barrier::Receiver>>,
     executor: BackgroundExecutor,
-    server: Arc<Mutex<Option<Child>>>,
+    #[allow(dead_code)]
+    transport: Arc<dyn Transport>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -100,87 +104,84 @@ struct Notification<'a, T> {
     params: T,
 }
 
+/// A notification, or a request when it has an id, sent by the server.
 #[derive(Debug, Clone, Deserialize)]
 struct AnyNotification<'a> {
     jsonrpc: &'a str,
-    id: RequestId,
+    #[serde(default)]
+    id: Option<RequestId>,
     method: String,
     #[serde(default)]
     params: Option<Value>,
 }
 
+/// A response to a request sent by the server.
+#[derive(Serialize)]
+struct OutgoingResponse {
+    jsonrpc: &'static str,
+    id: RequestId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Error>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Error {
+    #[serde(default)]
+    code: i32,
     message: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct ModelContextServerBinary {
-    pub executable: PathBuf,
-    pub args: Vec<String>,
-    pub env: Option<HashMap<String, String>>,
-}
-
 impl Client {
     /// Creates a new Client instance for a context server.
     ///
-    /// This function initializes a new Client by spawning a child process for the context server,
-    /// setting up communication channels, and initializing handlers for input/output operations.
-    /// It takes a server ID, binary information, and an async app context as input.
+    /// This function sets up communication channels over the given transport, and initializes
+    /// handlers for input/output operations. It takes a server ID, the transport connected to
+    /// the server, and an async app context as input.
     pub fn new(
         server_id: ContextServerId,
-        binary: ModelContextServerBinary,
+        transport: Arc<dyn Transport>,
         cx: AsyncAppContext,
-    ) -> Result<Self> {
-        log::info!(
-            "starting context server (executable={:?}, args={:?})",
-            binary.executable,
-            &binary.args
-        );
-
-        let mut command = process::Command::new(&binary.executable);
-        command
-            .args(&binary.args)
-            .envs(binary.env.unwrap_or_default())
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .kill_on_drop(true);
-
-        let mut server = command.spawn().with_context(|| {
-            format!(
-                "failed to spawn command. (path={:?}, args={:?})",
-                binary.executable, &binary.args
-            )
-        })?;
-
-        let stdin = server.stdin.take().unwrap();
-        let stdout = server.stdout.take().unwrap();
-        let stderr = server.stderr.take().unwrap();
-
+    ) -> Self {
         let (outbound_tx, outbound_rx) = channel::unbounded::<String>();
         let (output_done_tx, output_done_rx) = barrier::channel();
 
         let notification_handlers =
             Arc::new(Mutex::new(HashMap::<_, NotificationHandler>::default()));
+        let request_handlers = Arc::new(Mutex::new(HashMap::<_, RequestHandler>::default()));
         let response_handlers =
             Arc::new(Mutex::new(Some(HashMap::<_, ResponseHandler>::default())));
 
         let stdout_input_task = cx.spawn({
+            let stdout = transport.receive();
             let notification_handlers = notification_handlers.clone();
+            let request_handlers = request_handlers.clone();
             let response_handlers = response_handlers.clone();
+            let outbound_tx = outbound_tx.clone();
             move |cx| {
-                Self::handle_input(stdout, notification_handlers, response_handlers, cx).log_err()
+                Self::handle_input(
+                    stdout,
+                    notification_handlers,
+                    request_handlers,
+                    response_handlers,
+                    outbound_tx,
+                    cx,
+                )
+                .log_err()
             }
         });
-        let stderr_input_task = cx.spawn(|_| Self::handle_stderr(stderr).log_err());
+        let stderr_input_task = cx.spawn({
+            let stderr = transport.receive_err();
+            |_| Self::handle_stderr(stderr).log_err()
+        });
         let input_task = cx.spawn(|_| async move {
             let (stdout, stderr) = futures::join!(stdout_input_task, stderr_input_task);
             stdout.or(stderr)
         });
         let output_task = cx.background_executor().spawn({
             Self::handle_output(
-                stdin,
+                transport.clone(),
                 outbound_rx,
                 output_done_tx,
                 response_handlers.clone(),
@@ -188,109 +189,130 @@ impl Client {
             .log_err()
         });
 
-        let mut context_server = Self {
+        Self {
             server_id,
             notification_handlers,
+            request_handlers,
             response_handlers,
-            name: "".into(),
             next_id: Default::default(),
             outbound_tx,
             executor: cx.background_executor().clone(),
             io_tasks: Mutex::new(Some((input_task, output_task))),
             output_done_rx: Mutex::new(Some(output_done_rx)),
-            server: Arc::new(Mutex::new(Some(server))),
-        };
-
-        if let Some(name) = binary.executable.file_name() {
-            context_server.name = name.to_string_lossy().into();
+            transport,
         }
-
-        Ok(context_server)
     }
 
     /// Handles input from the server's stdout.
     ///
-    /// This function continuously reads lines from the provided stdout stream,
-    /// parses them as JSON-RPC responses or notifications, and dispatches them
-    /// to the appropriate handlers. It processes both responses (which are matched
-    /// to pending requests) and notifications (which trigger registered handlers).
+    /// This function continuously reads messages from the provided stdout stream,
+    /// parses them as JSON-RPC responses, requests or notifications, and dispatches them
+    /// to the appropriate handlers. It processes responses (which are matched to pending
+    /// requests), requests (whose handler's result is sent back to the server) and
+    /// notifications (which trigger registered handlers).
     async fn handle_input<Stdout>(
         stdout: Stdout,
         notification_handlers: Arc<Mutex<HashMap<&'static str, NotificationHandler>>>,
+        request_handlers: Arc<Mutex<HashMap<&'static str, RequestHandler>>>,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
+        outbound_tx: channel::Sender<String>,
         cx: AsyncAppContext,
     ) -> anyhow::Result<()>
     where
-        Stdout: AsyncRead + Unpin + Send + 'static,
+        Stdout: Stream<Item = String> + Unpin + Send + 'static,
     {
-        let mut stdout = BufReader::new(stdout);
-        let mut buffer = String::new();
-
-        loop {
-            buffer.clear();
-            if stdout.read_line(&mut buffer).await? == 0 {
-                return Ok(());
-            }
-
+        let mut stdout = stdout;
+        while let Some(buffer) = stdout.next().await {
             let content = buffer.trim();
 
             if !content.is_empty() {
-                if let Ok(response) = serde_json::from_str::<AnyResponse>(content) {
+                // Requests and notifications are told apart from responses by their method.
+                if let Ok(notification) = serde_json::from_str::<AnyNotification>(content) {
+                    let method = notification.method.as_str();
+                    let params = notification.params.unwrap_or(Value::Null);
+                    let request = notification.id.clone().and_then(|id| {
+                        let mut request_handlers = request_handlers.lock();
+                        let handler = request_handlers.get_mut(method)?;
+                        Some((id, handler(params.clone(), cx.clone())))
+                    });
+                    if let Some((id, task)) = request {
+                        let outbound_tx = outbound_tx.clone();
+                        cx.spawn(|_| async move {
+                            let response = match task.await {
+                                Ok(result) => OutgoingResponse {
+                                    jsonrpc: JSON_RPC_VERSION,
+                                    id,
+                                    result: Some(result),
+                                    error: None,
+                                },
+                                Err(error) => OutgoingResponse {
+                                    jsonrpc: JSON_RPC_VERSION,
+                                    id,
+                                    result: None,
+                                    error: Some(Error {
+                                        code: INTERNAL_ERROR,
+                                        message: error.to_string(),
+                                    }),
+                                },
+                            };
+                            outbound_tx
+                                .try_send(serde_json::to_string(&response).unwrap())
+                                .log_err();
+                        })
+                        .detach();
+                    } else if let Some(handler) = notification_handlers.lock().get_mut(method) {
+                        handler(notification.id, params, cx.clone());
+                    } else if let Some(id) = notification.id {
+                        let response = OutgoingResponse {
+                            jsonrpc: JSON_RPC_VERSION,
+                            id,
+                            result: None,
+                            error: Some(Error {
+                                code: METHOD_NOT_FOUND,
+                                message: format!("method not found: {method}"),
+                            }),
+                        };
+                        outbound_tx
+                            .try_send(serde_json::to_string(&response).unwrap())
+                            .log_err();
+                    }
+                } else if let Ok(response) = serde_json::from_str::<AnyResponse>(content) {
                     if let Some(handlers) = response_handlers.lock().as_mut() {
                         if let Some(handler) = handlers.remove(&response.id) {
                             handler(Ok(content.to_string()));
                         }
                     }
-                } else if let Ok(notification) = serde_json::from_str::<AnyNotification>(content) {
-                    let mut notification_handlers = notification_handlers.lock();
-                    if let Some(handler) =
-                        notification_handlers.get_mut(notification.method.as_str())
-                    {
-                        handler(
-                            notification.id,
-                            notification.params.unwrap_or(Value::Null),
-                            cx.clone(),
-                        );
-                    }
                 }
             }
 
             smol::future::yield_now().await;
         }
+        Ok(())
     }
 
     /// Handles the stderr output from the context server.
     /// Continuously reads and logs any error messages from the server.
     async fn handle_stderr<Stderr>(stderr: Stderr) -> anyhow::Result<()>
     where
-        Stderr: AsyncRead + Unpin + Send + 'static,
+        Stderr: Stream<Item = String> + Unpin + Send + 'static,
     {
-        let mut stderr = BufReader::new(stderr);
-        let mut buffer = String::new();
-
-        loop {
-            buffer.clear();
-            if stderr.read_line(&mut buffer).await? == 0 {
-                return Ok(());
-            }
+        let mut stderr = stderr;
+        while let Some(buffer) = stderr.next().await {
             log::warn!("context server stderr: {}", buffer.trim());
             smol::future::yield_now().await;
         }
+        Ok(())
     }
 
-    /// Handles the output to the context server's stdin.
+    /// Handles the output to the context server.
     /// This function continuously receives messages from the outbound channel,
-    /// writes them to the server's stdin, and manages the lifecycle of response handlers.
-    async fn handle_output<Stdin>(
-        stdin: Stdin,
+    /// sends them over the transport, and manages the lifecycle of response handlers.
+    async fn handle_output(
+        transport: Arc<dyn Transport>,
         outbound_rx: channel::Receiver<String>,
         output_done_tx: barrier::Sender,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
-    ) -> anyhow::Result<()>
-    where
-        Stdin: AsyncWrite + Unpin + Send + 'static,
-    {
-        let mut stdin = BufWriter::new(stdin);
+    ) -> anyhow::Result<()> {
         let _clear_response_handlers = util::defer({
             let response_handlers = response_handlers.clone();
             move || {
@@ -300,9 +322,7 @@ impl Client {
         while let Ok(message) = outbound_rx.recv().await {
             log::trace!("outgoing message: {}", message);
 
-            stdin.write_all(message.as_bytes()).await?;
-            stdin.write_all(b"\n").await?;
-            stdin.flush().await?;
+            transport.send(message).await?;
         }
         drop(output_done_tx);
         Ok(())
@@ -342,7 +362,7 @@ impl Client {
         let send = self
             .outbound_tx
             .try_send(request)
-            .context("failed to write to context server's stdin");
+            .context("failed to send message to context server");
 
         let executor = self.executor.clone();
         let started = Instant::now();
@@ -399,8 +419,13 @@ impl Client {
             .insert(method, Box::new(move |_, params, cx| f(params, cx)));
     }
 
-    pub fn name(&self) -> &str {
-        &self.name
+    /// Registers a handler for requests sent by the server. The result of the task it
+    /// returns is sent back to the server as the response.
+    pub fn on_request<F>(&self, method: &'static str, f: F)
+    where
+        F: 'static + Send + FnMut(Value, AsyncAppContext) -> Task<Result<Value>>,
+    {
+        self.request_handlers.lock().insert(method, Box::new(f));
     }
 
     pub fn server_id(&self) -> ContextServerId {
@@ -408,14 +433,6 @@ impl Client {
     }
 }
 
-impl Drop for Client {
-    fn drop(&mut self) {
-        if let Some(mut server) = self.server.lock().take() {
-            let _ = server.kill();
-        }
-    }
-}
-
 impl fmt::Display for ContextServerId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
@@ -426,7 +443,92 @@ impl fmt::Debug for Client {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Context Server Client")
             .field("id", &self.server_id.0)
-            .field("name", &self.name)
             .finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+    use serde_json::json;
+    use std::iter;
+
+    #[gpui::test]
+    async fn test_handle_input(cx: &mut TestAppContext) {
+        let notification_handlers =
+            Arc::new(Mutex::new(HashMap::<_, NotificationHandler>::default()));
+        let request_handlers = Arc::new(Mutex::new(HashMap::<_, RequestHandler>::default()));
+        let response_handlers =
+            Arc::new(Mutex::new(Some(HashMap::<_, ResponseHandler>::default())));
+        let (outbound_tx, outbound_rx) = channel::unbounded::<String>();
+
+        let (response_tx, response_rx) = oneshot::channel();
+        response_handlers.lock().as_mut().unwrap().insert(
+            RequestId::Int(1),
+            Box::new(move |response| {
+                response_tx.send(response.unwrap()).ok();
+            }),
+        );
+        let notifications = Arc::new(Mutex::new(Vec::new()));
+        notification_handlers.lock().insert(
+            "notifications/tools/list_changed",
+            Box::new({
+                let notifications = notifications.clone();
+                move |_, params, _| notifications.lock().push(params)
+            }),
+        );
+        request_handlers.lock().insert(
+            "ping",
+            Box::new(|params, _| Task::ready(Ok(json!({ "pong": params })))),
+        );
+
+        let input = futures::stream::iter([
+            json!({ "jsonrpc": "2.0", "id": 1, "result": { "value": 42 } }).to_string(),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/tools/list_changed",
+                "params": { "changed": true },
+            })
+            .to_string(),
+            json!({ "jsonrpc": "2.0", "id": "a", "method": "ping", "params": [1] }).to_string(),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "unknown" }).to_string(),
+        ]);
+        Client::handle_input(
+            input,
+            notification_handlers,
+            request_handlers,
+            response_handlers.clone(),
+            outbound_tx,
+            cx.to_async(),
+        )
+        .await
+        .unwrap();
+        cx.run_until_parked();
+
+        // Responses are routed to the handler of the request with the same id.
+        let response: Value = serde_json::from_str(&response_rx.await.unwrap()).unwrap();
+        assert_eq!(response["result"], json!({ "value": 42 }));
+        assert!(response_handlers.lock().as_ref().unwrap().is_empty());
+
+        // Notifications are routed to their handler.
+        assert_eq!(*notifications.lock(), [json!({ "changed": true })]);
+
+        // Requests are answered with their handler's result, or an error if they have none.
+        let mut outgoing = iter::from_fn(|| outbound_rx.try_recv().ok())
+            .map(|message| serde_json::from_str::<Value>(&message).unwrap())
+            .collect::<Vec<_>>();
+        outgoing.sort_by_key(|message| message["id"].to_string());
+        assert_eq!(
+            outgoing,
+            [
+                json!({ "jsonrpc": "2.0", "id": "a", "result": { "pong": [1] } }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 2,
+                    "error": { "code": METHOD_NOT_FOUND, "message": "method not found: unknown" },
+                }),
+            ]
+        );
+    }
+}
@@ -6,6 +6,7 @@ pub mod client;
 pub mod manager;
 pub mod protocol;
 mod registry;
+pub mod transport;
 pub mod types;
 
 pub use registry::*;
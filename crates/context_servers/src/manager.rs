@@ -14,6 +14,7 @@
 //! The module also includes initialization logic to set up the context server system
 //! and react to changes in settings.
 
+use anyhow::Context as _;
 use collections::{HashMap, HashSet};
 use gpui::{AppContext, AsyncAppContext, Context, EventEmitter, Global, Model, ModelContext, Task};
 use log;
@@ -23,9 +24,13 @@ use serde::{Deserialize, Serialize};
 use settings::{Settings, SettingsSources, SettingsStore};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+use util::{ResultExt, TryFutureExt};
 
 use crate::{
     client::{self, Client},
+    transport::{ModelContextServerBinary, SseTransport, StdioTransport, Transport},
     types,
 };
 
@@ -37,9 +42,27 @@ pub struct ContextServerSettings {
 #[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
 pub struct ServerConfig {
     pub id: String,
-    pub executable: String,
-    pub args: Vec<String>,
-    pub env: Option<HashMap<String, String>>,
+    #[serde(flatten)]
+    pub transport: ServerTransportConfig,
+}
+
+/// How to connect to a context server.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
+#[serde(untagged)]
+pub enum ServerTransportConfig {
+    /// Runs the server as a child process, exchanging messages over its stdin and stdout.
+    Stdio {
+        executable: String,
+        args: Vec<String>,
+        env: Option<HashMap<String, String>>,
+    },
+    /// Connects to a running server over HTTP, receiving its messages as server-sent events.
+    Sse {
+        url: String,
+        /// Headers sent with every request, e.g. to authenticate with the server.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
 }
 
 impl Settings for ContextServerSettings {
@@ -70,19 +93,54 @@ impl ContextServer {
         }
     }
 
-    async fn start(&self, cx: &AsyncAppContext) -> anyhow::Result<()> {
+    async fn start(
+        &self,
+        sampling_handler: Option<SamplingHandler>,
+        cx: &AsyncAppContext,
+    ) -> anyhow::Result<()> {
         log::info!("starting context server {}", self.config.id,);
+        let mut closed = None;
+        let transport: Arc<dyn Transport> = match &self.config.transport {
+            ServerTransportConfig::Stdio {
+                executable,
+                args,
+                env,
+            } => Arc::new(StdioTransport::new(
+                ModelContextServerBinary {
+                    executable: Path::new(executable).to_path_buf(),
+                    args: args.clone(),
+                    env: env.clone(),
+                },
+                cx.background_executor(),
+            )?),
+            ServerTransportConfig::Sse { url, headers } => {
+                let url = Url::parse(url)
+                    .with_context(|| format!("invalid context server URL {url:?}"))?;
+                let http_client = cx.update(|cx| cx.http_client())?;
+                let transport = SseTransport::connect(
+                    url,
+                    headers.clone(),
+                    http_client,
+                    cx.background_executor().clone(),
+                )
+                .await?;
+                closed = Some(transport.closed());
+                Arc::new(transport)
+            }
+        };
         let client = Client::new(
             client::ContextServerId(self.config.id.clone()),
-            client::ModelContextServerBinary {
-                executable: Path::new(&self.config.executable).to_path_buf(),
-                args: self.config.args.clone(),
-                env: self.config.env.clone(),
-            },
+            transport,
             cx.clone(),
-        )?;
+        );
 
-        let protocol = crate::protocol::ModelContextProtocol::new(client);
+        let mut protocol = crate::protocol::ModelContextProtocol::new(client);
+        if let Some(sampling_handler) = sampling_handler {
+            let server_id = self.id.clone();
+            protocol.on_create_message(move |params, cx| {
+                sampling_handler(server_id.clone(), params, cx)
+            });
+        }
         let client_info = types::EntityInfo {
             name: "Zed".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
@@ -95,7 +153,74 @@ impl ContextServer {
             initialized_protocol.initialize,
         );
 
+        let server_id = self.id.clone();
+        initialized_protocol.on_notification(
+            types::ServerNotificationType::ResourcesUpdated,
+            move |params, cx| {
+                let Some(params) =
+                    serde_json::from_value::<types::ResourcesUpdatedParams>(params).log_err()
+                else {
+                    return;
+                };
+                let server_id = server_id.clone();
+                cx.update(|cx| {
+                    ContextServerManager::global(cx).update(cx, |_, cx| {
+                        cx.emit(Event::ResourceUpdated {
+                            server_id,
+                            uri: params.uri,
+                        })
+                    })
+                })
+                .ok();
+            },
+        );
+        let server_id = self.id.clone();
+        initialized_protocol.on_notification(
+            types::ServerNotificationType::ToolsListChanged,
+            move |_, cx| {
+                let server_id = server_id.clone();
+                cx.update(|cx| {
+                    ContextServerManager::global(cx)
+                        .update(cx, |_, cx| cx.emit(Event::ToolsChanged { server_id }))
+                })
+                .ok();
+            },
+        );
+
         *self.client.write() = Some(Arc::new(initialized_protocol));
+
+        if let Some(closed) = closed {
+            let config = self.config.clone();
+            cx.spawn(|mut cx| {
+                async move {
+                    // The transport is only dropped before it's closed when the server is stopped.
+                    if closed.await.is_err() {
+                        return Ok(());
+                    }
+
+                    log::warn!("context server {} disconnected, reconnecting", config.id);
+                    let manager = cx.update(|cx| ContextServerManager::global(cx))?;
+                    manager
+                        .update(&mut cx, |manager, cx| manager.remove_server(&config.id, cx))?
+                        .await?;
+                    cx.background_executor().timer(RECONNECT_DELAY).await;
+                    let is_configured = cx.update(|cx| {
+                        ContextServerSettings::get_global(cx)
+                            .servers
+                            .contains(&config)
+                    })?;
+                    if is_configured {
+                        manager
+                            .update(&mut cx, |manager, cx| manager.add_server(config, cx))?
+                            .await?;
+                    }
+                    anyhow::Ok(())
+                }
+                .log_err()
+            })
+            .detach();
+        }
+
         Ok(())
     }
 
@@ -108,6 +233,20 @@ impl ContextServer {
     }
 }
 
+/// How long to wait before reconnecting to a server whose connection was dropped.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Completes the messages a context server asks the client to sample, given the server's id.
+pub type SamplingHandler = Arc<
+    dyn Send
+        + Sync
+        + Fn(
+            String,
+            types::CreateMessageParams,
+            AsyncAppContext,
+        ) -> Task<anyhow::Result<types::CreateMessageResult>>,
+>;
+
 /// A Context server manager manages the starting and stopping
 /// of all servers. To obtain a server to interact with, a crate
 /// must go through the `GlobalContextServerManager` which holds
@@ -115,11 +254,25 @@ impl ContextServer {
 pub struct ContextServerManager {
     servers: HashMap<String, Arc<ContextServer>>,
     pending_servers: HashSet<String>,
+    sampling_handler: Option<SamplingHandler>,
 }
 
 pub enum Event {
-    ServerStarted { server_id: String },
-    ServerStopped { server_id: String },
+    ServerStarted {
+        server_id: String,
+    },
+    ServerStopped {
+        server_id: String,
+    },
+    /// The server's list of tools changed.
+    ToolsChanged {
+        server_id: String,
+    },
+    /// A resource the client subscribed to was updated.
+    ResourceUpdated {
+        server_id: String,
+        uri: Url,
+    },
 }
 
 impl Global for ContextServerManager {}
@@ -136,8 +289,15 @@ impl ContextServerManager {
         Self {
             servers: HashMap::default(),
             pending_servers: HashSet::default(),
+            sampling_handler: None,
         }
     }
+
+    /// Sets the handler for the servers' sampling requests. Servers only learn that the client
+    /// supports sampling when they start, so it only applies to servers started afterwards.
+    pub fn set_sampling_handler(&mut self, handler: SamplingHandler) {
+        self.sampling_handler = Some(handler);
+    }
     pub fn global(cx: &AppContext) -> Model<Self> {
         cx.global::<GlobalContextServerManager>().0.clone()
     }
@@ -154,9 +314,10 @@ impl ContextServerManager {
             return Task::ready(Ok(()));
         }
 
+        let sampling_handler = self.sampling_handler.clone();
         let task = cx.spawn(|this, mut cx| async move {
             let server = Arc::new(ContextServer::new(config));
-            server.start(&cx).await?;
+            server.start(sampling_handler, &cx).await?;
             this.update(&mut cx, |this, cx| {
                 this.servers.insert(server_id.clone(), server);
                 this.pending_servers.remove(&server_id);
@@ -201,12 +362,13 @@ impl ContextServerManager {
         cx: &mut ModelContext<Self>,
     ) -> Task<anyhow::Result<()>> {
         let id = id.to_string();
+        let sampling_handler = self.sampling_handler.clone();
         cx.spawn(|this, mut cx| async move {
             if let Some(server) = this.update(&mut cx, |this, _cx| this.servers.remove(&id))? {
                 server.stop().await?;
                 let config = server.config.clone();
                 let new_server = Arc::new(ContextServer::new(config));
-                new_server.start(&cx).await?;
+                new_server.start(sampling_handler, &cx).await?;
                 this.update(&mut cx, |this, cx| {
                     this.servers.insert(id.clone(), new_server);
                     cx.emit(Event::ServerStopped {
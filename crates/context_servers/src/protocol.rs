@@ -7,6 +7,8 @@
 
 use anyhow::Result;
 use collections::HashMap;
+use gpui::{AsyncAppContext, Task};
+use url::Url;
 
 use crate::client::Client;
 use crate::types;
@@ -17,11 +19,40 @@ const PROTOCOL_VERSION: u32 = 1;
 
 pub struct ModelContextProtocol {
     inner: Client,
+    handles_sampling: bool,
 }
 
 impl ModelContextProtocol {
     pub fn new(inner: Client) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            handles_sampling: false,
+        }
+    }
+
+    /// Registers the handler for the server's requests to sample the client's language
+    /// model. The client only advertises sampling when a handler is registered before
+    /// initializing.
+    pub fn on_create_message<F>(&mut self, mut f: F)
+    where
+        F: 'static
+            + Send
+            + FnMut(
+                types::CreateMessageParams,
+                AsyncAppContext,
+            ) -> Task<Result<types::CreateMessageResult>>,
+    {
+        self.handles_sampling = true;
+        self.inner.on_request(
+            types::ServerRequestType::CreateMessage.as_str(),
+            move |params, cx| match serde_json::from_value(params) {
+                Ok(params) => {
+                    let result = f(params, cx.clone());
+                    cx.spawn(|_| async move { Ok(serde_json::to_value(result.await?)?) })
+                }
+                Err(error) => Task::ready(Err(error.into())),
+            },
+        );
     }
 
     pub async fn initialize(
@@ -32,7 +63,7 @@ impl ModelContextProtocol {
             protocol_version: PROTOCOL_VERSION,
             capabilities: types::ClientCapabilities {
                 experimental: None,
-                sampling: None,
+                sampling: self.handles_sampling.then(HashMap::default),
             },
             client_info,
         };
@@ -128,6 +159,115 @@ impl InitializedContextServerProtocol {
         Ok(response)
     }
 
+    /// List the MCP tools.
+    pub async fn list_tools(&self) -> Result<Vec<types::Tool>> {
+        self.check_capability(ServerCapability::Tools)?;
+
+        let response: types::ListToolsResponse = self
+            .inner
+            .request(types::RequestType::ListTools.as_str(), ())
+            .await?;
+
+        Ok(response.tools)
+    }
+
+    /// Calls a tool with the given arguments and returns the result.
+    pub async fn run_tool<P: AsRef<str>>(
+        &self,
+        tool: P,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<types::CallToolResponse> {
+        self.check_capability(ServerCapability::Tools)?;
+
+        let params = types::CallToolParams {
+            name: tool.as_ref().to_string(),
+            arguments,
+        };
+
+        let response: types::CallToolResponse = self
+            .inner
+            .request(types::RequestType::CallTool.as_str(), params)
+            .await?;
+
+        Ok(response)
+    }
+
+    /// List the MCP resources.
+    pub async fn list_resources(&self) -> Result<types::ResourcesListResponse> {
+        self.check_capability(ServerCapability::Resources)?;
+
+        let response: types::ResourcesListResponse = self
+            .inner
+            .request(types::RequestType::ResourcesList.as_str(), ())
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Reads the contents of a resource.
+    pub async fn read_resource(&self, uri: Url) -> Result<types::ResourcesReadResponse> {
+        self.check_capability(ServerCapability::Resources)?;
+
+        let params = types::ResourcesReadParams { uri };
+        let response: types::ResourcesReadResponse = self
+            .inner
+            .request(types::RequestType::ResourcesRead.as_str(), params)
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Whether the server notifies subscribers when a resource is updated.
+    pub fn can_subscribe_to_resources(&self) -> bool {
+        self.initialize
+            .capabilities
+            .resources
+            .as_ref()
+            .map_or(false, |resources| resources.subscribe == Some(true))
+    }
+
+    /// Asks the server to send a notification whenever the resource is updated.
+    pub async fn subscribe_to_resource(&self, uri: Url) -> Result<()> {
+        if !self.can_subscribe_to_resources() {
+            return Err(anyhow::anyhow!(
+                "Server does not support resource subscriptions"
+            ));
+        }
+
+        let params = types::ResourcesSubscribeParams { uri };
+        let _: serde_json::Value = self
+            .inner
+            .request(types::RequestType::ResourcesSubscribe.as_str(), params)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stops the notifications sent when the resource is updated.
+    pub async fn unsubscribe_from_resource(&self, uri: Url) -> Result<()> {
+        if !self.can_subscribe_to_resources() {
+            return Err(anyhow::anyhow!(
+                "Server does not support resource subscriptions"
+            ));
+        }
+
+        let params = types::ResourcesUnsubscribeParams { uri };
+        let _: serde_json::Value = self
+            .inner
+            .request(types::RequestType::ResourcesUnsubscribe.as_str(), params)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Registers a handler for a notification sent by the server.
+    pub fn on_notification<F>(&self, notification: types::ServerNotificationType, f: F)
+    where
+        F: 'static + Send + FnMut(serde_json::Value, AsyncAppContext),
+    {
+        self.inner.on_notification(notification.as_str(), f);
+    }
+
     pub async fn completion<P: Into<String>>(
         &self,
         reference: types::CompletionReference,
@@ -167,3 +307,182 @@ impl InitializedContextServerProtocol {
         self.inner.request(method, params).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::ContextServerId,
+        transport::Transport,
+        types::{CreateMessageResult, SamplingContent, SamplingRole},
+    };
+    use futures::{future::BoxFuture, stream::BoxStream, FutureExt as _, StreamExt as _};
+    use gpui::TestAppContext;
+    use parking_lot::Mutex;
+    use serde_json::{json, Value};
+    use smol::channel;
+    use std::sync::Arc;
+
+    /// A transport whose other end is driven by the test.
+    struct FakeTransport {
+        sent_tx: channel::Sender<String>,
+        received_rx: Mutex<Option<channel::Receiver<String>>>,
+    }
+
+    impl Transport for FakeTransport {
+        fn send(&self, message: String) -> BoxFuture<'static, Result<()>> {
+            let sent_tx = self.sent_tx.clone();
+            async move { Ok(sent_tx.send(message).await?) }.boxed()
+        }
+
+        fn receive(&self) -> BoxStream<'static, String> {
+            self.received_rx.lock().take().unwrap().boxed()
+        }
+
+        fn receive_err(&self) -> BoxStream<'static, String> {
+            futures::stream::empty().boxed()
+        }
+    }
+
+    fn fake_protocol(
+        cx: &mut TestAppContext,
+    ) -> (
+        ModelContextProtocol,
+        channel::Receiver<String>,
+        channel::Sender<String>,
+    ) {
+        let (sent_tx, sent_rx) = channel::unbounded();
+        let (received_tx, received_rx) = channel::unbounded();
+        let transport = FakeTransport {
+            sent_tx,
+            received_rx: Mutex::new(Some(received_rx)),
+        };
+        let client = Client::new(
+            ContextServerId("test".into()),
+            Arc::new(transport),
+            cx.to_async(),
+        );
+        (ModelContextProtocol::new(client), sent_rx, received_tx)
+    }
+
+    async fn initialize(
+        protocol: ModelContextProtocol,
+        sent_rx: &channel::Receiver<String>,
+        received_tx: &channel::Sender<String>,
+        cx: &mut TestAppContext,
+    ) -> (InitializedContextServerProtocol, Value) {
+        let initialize = cx.spawn(|_| {
+            protocol.initialize(types::EntityInfo {
+                name: "Zed".into(),
+                version: "1.0.0".into(),
+            })
+        });
+        let request: Value = serde_json::from_str(&sent_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(request["method"], "initialize");
+        received_tx
+            .send(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": {
+                        "protocolVersion": PROTOCOL_VERSION,
+                        "capabilities": {},
+                        "serverInfo": { "name": "test", "version": "1.0.0" },
+                    },
+                })
+                .to_string(),
+            )
+            .await
+            .unwrap();
+        let protocol = initialize.await.unwrap();
+        let notification: Value = serde_json::from_str(&sent_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(notification["method"], "notifications/initialized");
+        (protocol, request["params"]["capabilities"].clone())
+    }
+
+    #[gpui::test]
+    async fn test_sampling_capability(cx: &mut TestAppContext) {
+        let (protocol, sent_rx, received_tx) = fake_protocol(cx);
+        let (_protocol, capabilities) = initialize(protocol, &sent_rx, &received_tx, cx).await;
+        assert_eq!(capabilities["sampling"], Value::Null);
+
+        let (mut protocol, sent_rx, received_tx) = fake_protocol(cx);
+        protocol.on_create_message(|_, _| unreachable!());
+        let (_protocol, capabilities) = initialize(protocol, &sent_rx, &received_tx, cx).await;
+        assert_eq!(capabilities["sampling"], json!({}));
+    }
+
+    #[gpui::test]
+    async fn test_sampling_round_trip(cx: &mut TestAppContext) {
+        let (mut protocol, sent_rx, received_tx) = fake_protocol(cx);
+        protocol.on_create_message(|params, _| {
+            let text = params
+                .messages
+                .iter()
+                .filter_map(|message| match &message.content {
+                    SamplingContent::Text { text } => Some(text.as_str()),
+                    SamplingContent::Image { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            Task::ready(Ok(CreateMessageResult {
+                role: SamplingRole::Assistant,
+                content: SamplingContent::Text {
+                    text: format!("echo: {text}"),
+                },
+                model: "fake".into(),
+                stop_reason: Some("endTurn".into()),
+            }))
+        });
+        let (_protocol, _) = initialize(protocol, &sent_rx, &received_tx, cx).await;
+
+        received_tx
+            .send(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 7,
+                    "method": "sampling/createMessage",
+                    "params": {
+                        "messages": [
+                            { "role": "user", "content": { "type": "text", "text": "hello" } },
+                        ],
+                        "maxTokens": 100,
+                    },
+                })
+                .to_string(),
+            )
+            .await
+            .unwrap();
+        let response: Value = serde_json::from_str(&sent_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(
+            response,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 7,
+                "result": {
+                    "role": "assistant",
+                    "content": { "type": "text", "text": "echo: hello" },
+                    "model": "fake",
+                    "stopReason": "endTurn",
+                },
+            })
+        );
+
+        // Malformed requests are answered with an error.
+        received_tx
+            .send(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 8,
+                    "method": "sampling/createMessage",
+                    "params": { "messages": "hello" },
+                })
+                .to_string(),
+            )
+            .await
+            .unwrap();
+        let response: Value = serde_json::from_str(&sent_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(response["id"], 8);
+        assert!(response["error"]["message"].is_string());
+    }
+}
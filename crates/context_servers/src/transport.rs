@@ -0,0 +1,21 @@
+mod sse_transport;
+mod stdio_transport;
+
+use anyhow::Result;
+use futures::{future::BoxFuture, stream::BoxStream};
+
+pub use sse_transport::*;
+pub use stdio_transport::*;
+
+/// The connection over which a [`Client`](crate::client::Client) exchanges JSON-RPC messages
+/// with a context server.
+pub trait Transport: Send + Sync {
+    /// Sends a message to the server.
+    fn send(&self, message: String) -> BoxFuture<'static, Result<()>>;
+
+    /// Returns the messages sent by the server. The stream ends when the connection is closed.
+    fn receive(&self) -> BoxStream<'static, String>;
+
+    /// Returns the diagnostic output of the server, which is logged.
+    fn receive_err(&self) -> BoxStream<'static, String>;
+}
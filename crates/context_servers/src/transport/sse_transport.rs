@@ -0,0 +1,379 @@
+use anyhow::{anyhow, Context as _, Result};
+use collections::HashMap;
+use futures::{
+    channel::oneshot,
+    future::{BoxFuture, Shared},
+    io::BufReader,
+    stream::{self, BoxStream},
+    AsyncBufReadExt, AsyncReadExt, FutureExt, StreamExt,
+};
+use gpui::{BackgroundExecutor, Task};
+use http_client::{AsyncBody, HttpClient, Method, Request, Response, Url};
+use isahc::config::Configurable;
+use smol::channel;
+use std::{sync::Arc, time::Duration};
+use util::ResultExt;
+
+use super::Transport;
+
+/// Connects to a context server over HTTP. The server sends its messages as server-sent
+/// events, and the client posts its messages to the endpoint the server announces.
+pub struct SseTransport {
+    endpoint: Url,
+    headers: HashMap<String, String>,
+    http_client: Arc<dyn HttpClient>,
+    message_rx: channel::Receiver<String>,
+    closed: Shared<oneshot::Receiver<()>>,
+    #[allow(dead_code)]
+    event_task: Task<Option<()>>,
+}
+
+impl SseTransport {
+    /// Opens the server's event stream, and waits for the server to announce the endpoint
+    /// that messages are posted to. The headers are sent with every request, e.g. to
+    /// authenticate with the server.
+    pub async fn connect(
+        url: Url,
+        headers: HashMap<String, String>,
+        http_client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Result<Self> {
+        log::info!("connecting to context server (url={url})");
+
+        let request = build_request(Method::GET, &url, &headers)
+            .header("Accept", "text/event-stream")
+            // The event stream is idle while the server has nothing to say, which mustn't be
+            // mistaken for a stalled connection.
+            .low_speed_timeout(0, Duration::ZERO)
+            .body(AsyncBody::empty())?;
+        let response = http_client.send(request).await?;
+        let response = check_status(response)
+            .await
+            .with_context(|| format!("failed to connect to context server at {url}"))?;
+
+        let mut events = EventStream::new(response.into_body());
+        let (message_tx, message_rx) = channel::unbounded();
+        let endpoint = loop {
+            let event = events.next().await?.ok_or_else(|| {
+                anyhow!(
+                    "context server at {url} closed the connection before announcing its endpoint"
+                )
+            })?;
+            match event.name.as_str() {
+                "endpoint" => {
+                    let endpoint = url
+                        .join(&event.data)
+                        .with_context(|| format!("invalid endpoint {:?}", event.data))?;
+                    // Messages are posted with the configured headers, which mustn't be sent
+                    // to any other server.
+                    if endpoint.origin() != url.origin() {
+                        return Err(anyhow!(
+                            "context server at {url} announced an endpoint on another origin: {endpoint}"
+                        ));
+                    }
+                    break endpoint;
+                }
+                "message" => message_tx.send(event.data).await?,
+                _ => {}
+            }
+        };
+
+        let (closed_tx, closed_rx) = oneshot::channel();
+        let event_task = executor.spawn(
+            async move {
+                let result = async {
+                    while let Some(event) = events.next().await? {
+                        if event.name == "message" {
+                            message_tx.send(event.data).await?;
+                        }
+                    }
+                    anyhow::Ok(())
+                }
+                .await;
+                closed_tx.send(()).ok();
+                result
+            }
+            .log_err(),
+        );
+
+        Ok(Self {
+            endpoint,
+            headers,
+            http_client,
+            message_rx,
+            closed: closed_rx.shared(),
+            event_task,
+        })
+    }
+
+    /// Resolves once the server's event stream is closed or fails, after which nothing the
+    /// server sends is received. Resolves with an error if the transport is dropped first.
+    pub fn closed(&self) -> Shared<oneshot::Receiver<()>> {
+        self.closed.clone()
+    }
+}
+
+impl Transport for SseTransport {
+    fn send(&self, message: String) -> BoxFuture<'static, Result<()>> {
+        let request = build_request(Method::POST, &self.endpoint, &self.headers)
+            .header("Content-Type", "application/json")
+            .body(AsyncBody::from(message));
+        let http_client = self.http_client.clone();
+        async move {
+            let response = http_client.send(request?).await?;
+            check_status(response)
+                .await
+                .context("context server rejected message")?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn receive(&self) -> BoxStream<'static, String> {
+        self.message_rx.clone().boxed()
+    }
+
+    fn receive_err(&self) -> BoxStream<'static, String> {
+        stream::empty().boxed()
+    }
+}
+
+fn build_request(
+    method: Method,
+    url: &Url,
+    headers: &HashMap<String, String>,
+) -> isahc::http::request::Builder {
+    let mut request = Request::builder().method(method).uri(url.as_str());
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    request
+}
+
+async fn check_status(mut response: Response<AsyncBody>) -> Result<Response<AsyncBody>> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+
+    let mut body = String::new();
+    response.body_mut().read_to_string(&mut body).await?;
+    Err(anyhow!("status {}: {}", response.status(), body.trim()))
+}
+
+struct Event {
+    name: String,
+    data: String,
+}
+
+/// Parses server-sent events.
+struct EventStream {
+    reader: BufReader<AsyncBody>,
+    buffer: String,
+}
+
+impl EventStream {
+    fn new(body: AsyncBody) -> Self {
+        Self {
+            reader: BufReader::new(body),
+            buffer: String::new(),
+        }
+    }
+
+    /// Returns the next event, or `None` once the stream is closed.
+    async fn next(&mut self) -> Result<Option<Event>> {
+        let mut name = None;
+        let mut data = String::new();
+        loop {
+            self.buffer.clear();
+            if self.reader.read_line(&mut self.buffer).await? == 0 {
+                return Ok(None);
+            }
+
+            let line = self.buffer.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                // A blank line dispatches the event, unless it has no data.
+                if data.is_empty() {
+                    name = None;
+                    continue;
+                }
+                data.pop();
+                return Ok(Some(Event {
+                    name: name.unwrap_or_else(|| "message".to_string()),
+                    data,
+                }));
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => name = Some(value.to_string()),
+                "data" => {
+                    data.push_str(value);
+                    data.push('\n');
+                }
+                // Comments, event ids and retry intervals aren't used.
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Client, ContextServerId};
+    use futures::TryStreamExt;
+    use gpui::TestAppContext;
+    use http_client::FakeHttpClient;
+    use parking_lot::Mutex;
+    use serde_json::{json, Value};
+
+    #[gpui::test]
+    async fn test_sse_transport(cx: &mut TestAppContext) {
+        let (events_tx, events_rx) = channel::unbounded::<String>();
+        let events_rx = Arc::new(Mutex::new(Some(events_rx)));
+        let http_client = FakeHttpClient::create(move |request| {
+            let events_tx = events_tx.clone();
+            let events_rx = events_rx.clone();
+            async move {
+                assert_eq!(request.headers()["Authorization"], "Bearer secret");
+                let method = request.method().clone();
+                let uri = request.uri().clone();
+                match (method, uri.path()) {
+                    (Method::GET, "/sse") => {
+                        let events = events_rx.lock().take().expect("connected twice");
+                        events_tx
+                            .send(
+                                ": connected\n\nevent: endpoint\ndata: /messages?session=1\n\n"
+                                    .into(),
+                            )
+                            .await
+                            .unwrap();
+                        let body = events
+                            .map(|event| Ok::<_, std::io::Error>(event.into_bytes()))
+                            .into_async_read();
+                        Ok(Response::builder()
+                            .status(200)
+                            .body(AsyncBody::from_reader(body))
+                            .unwrap())
+                    }
+                    (Method::POST, "/messages") => {
+                        assert_eq!(uri.query(), Some("session=1"));
+                        let mut body = String::new();
+                        request.into_body().read_to_string(&mut body).await.unwrap();
+                        let message: Value = serde_json::from_str(&body).unwrap();
+                        let response = json!({
+                            "jsonrpc": "2.0",
+                            "id": message["id"],
+                            "result": { "echo": message["params"] },
+                        });
+                        events_tx
+                            .send(format!("event: message\ndata: {response}\n\n"))
+                            .await
+                            .unwrap();
+                        Ok(Response::builder()
+                            .status(202)
+                            .body(AsyncBody::empty())
+                            .unwrap())
+                    }
+                    _ => Ok(Response::builder()
+                        .status(404)
+                        .body(AsyncBody::empty())
+                        .unwrap()),
+                }
+            }
+        });
+
+        let transport = SseTransport::connect(
+            Url::parse("http://mcp.example.com/sse").unwrap(),
+            HashMap::from_iter([("Authorization".to_string(), "Bearer secret".to_string())]),
+            http_client,
+            cx.executor(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            transport.endpoint.as_str(),
+            "http://mcp.example.com/messages?session=1"
+        );
+
+        let client = Client::new(
+            ContextServerId("test".into()),
+            Arc::new(transport),
+            cx.to_async(),
+        );
+        let response: Value = client
+            .request("echo", json!({ "text": "hello" }))
+            .await
+            .unwrap();
+        assert_eq!(response, json!({ "echo": { "text": "hello" } }));
+    }
+
+    #[gpui::test]
+    async fn test_sse_transport_rejects_endpoint_on_another_origin(cx: &mut TestAppContext) {
+        for endpoint in [
+            "https://attacker.example.com/messages",
+            "https://mcp.example.com/messages",
+            "http://mcp.example.com:8080/messages",
+            "//attacker.example.com/messages",
+        ] {
+            let http_client = FakeHttpClient::create(move |_| async move {
+                Ok(Response::builder()
+                    .status(200)
+                    .body(AsyncBody::from(format!(
+                        "event: endpoint\ndata: {endpoint}\n\n"
+                    )))
+                    .unwrap())
+            });
+            let result = SseTransport::connect(
+                Url::parse("http://mcp.example.com/sse").unwrap(),
+                HashMap::from_iter([("Authorization".to_string(), "Bearer secret".to_string())]),
+                http_client,
+                cx.executor(),
+            )
+            .await;
+            assert!(result.is_err(), "accepted endpoint {endpoint}");
+        }
+    }
+
+    #[gpui::test]
+    async fn test_sse_transport_closed(cx: &mut TestAppContext) {
+        let (events_tx, events_rx) = channel::unbounded::<String>();
+        let events_rx = Arc::new(Mutex::new(Some(events_rx)));
+        let http_client = FakeHttpClient::create(move |_| {
+            let events = events_rx.lock().take().expect("connected twice");
+            async move {
+                let body = events
+                    .map(|event| Ok::<_, std::io::Error>(event.into_bytes()))
+                    .into_async_read();
+                Ok(Response::builder()
+                    .status(200)
+                    .body(AsyncBody::from_reader(body))
+                    .unwrap())
+            }
+        });
+        events_tx
+            .send("event: endpoint\ndata: /messages\n\n".into())
+            .await
+            .unwrap();
+
+        let transport = SseTransport::connect(
+            Url::parse("http://mcp.example.com/sse").unwrap(),
+            HashMap::default(),
+            http_client,
+            cx.executor(),
+        )
+        .await
+        .unwrap();
+        let mut closed = transport.closed();
+        cx.run_until_parked();
+        assert_eq!((&mut closed).now_or_never(), None);
+
+        drop(events_tx);
+        cx.run_until_parked();
+        assert_eq!(closed.now_or_never(), Some(Ok(())));
+        assert_eq!(transport.receive().next().await, None);
+    }
+}
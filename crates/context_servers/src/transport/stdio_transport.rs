@@ -0,0 +1,129 @@
+use anyhow::{Context as _, Result};
+use collections::HashMap;
+use futures::{future::BoxFuture, io::BufWriter, stream::BoxStream, FutureExt, StreamExt};
+use gpui::{BackgroundExecutor, Task};
+use serde::Deserialize;
+use smol::{
+    channel,
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    process::{self, Child},
+};
+use std::path::PathBuf;
+use util::ResultExt;
+
+use super::Transport;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelContextServerBinary {
+    pub executable: PathBuf,
+    pub args: Vec<String>,
+    pub env: Option<HashMap<String, String>>,
+}
+
+/// Runs a context server as a child process, exchanging messages over its stdin and stdout.
+pub struct StdioTransport {
+    stdin_tx: channel::Sender<String>,
+    stdout_rx: channel::Receiver<String>,
+    stderr_rx: channel::Receiver<String>,
+    #[allow(dead_code)]
+    io_tasks: Vec<Task<Option<()>>>,
+    #[allow(dead_code)]
+    server: Child,
+}
+
+impl StdioTransport {
+    /// Spawns the server's process. It's killed when the transport is dropped.
+    pub fn new(binary: ModelContextServerBinary, executor: &BackgroundExecutor) -> Result<Self> {
+        log::info!(
+            "starting context server (executable={:?}, args={:?})",
+            binary.executable,
+            &binary.args
+        );
+
+        let mut command = process::Command::new(&binary.executable);
+        command
+            .args(&binary.args)
+            .envs(binary.env.unwrap_or_default())
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut server = command.spawn().with_context(|| {
+            format!(
+                "failed to spawn command. (path={:?}, args={:?})",
+                binary.executable, &binary.args
+            )
+        })?;
+
+        let stdin = server.stdin.take().unwrap();
+        let stdout = server.stdout.take().unwrap();
+        let stderr = server.stderr.take().unwrap();
+
+        let (stdin_tx, stdin_rx) = channel::unbounded();
+        let (stdout_tx, stdout_rx) = channel::unbounded();
+        let (stderr_tx, stderr_rx) = channel::unbounded();
+        let io_tasks = vec![
+            executor.spawn(Self::write_lines(stdin, stdin_rx).log_err()),
+            executor.spawn(Self::read_lines(stdout, stdout_tx).log_err()),
+            executor.spawn(Self::read_lines(stderr, stderr_tx).log_err()),
+        ];
+
+        Ok(Self {
+            stdin_tx,
+            stdout_rx,
+            stderr_rx,
+            io_tasks,
+            server,
+        })
+    }
+
+    async fn write_lines<Stdin>(stdin: Stdin, lines: channel::Receiver<String>) -> Result<()>
+    where
+        Stdin: AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut stdin = BufWriter::new(stdin);
+        while let Ok(line) = lines.recv().await {
+            stdin.write_all(line.as_bytes()).await?;
+            stdin.write_all(b"\n").await?;
+            stdin.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn read_lines<Output>(output: Output, lines: channel::Sender<String>) -> Result<()>
+    where
+        Output: AsyncRead + Unpin + Send + 'static,
+    {
+        let mut output = BufReader::new(output);
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            if output.read_line(&mut buffer).await? == 0 {
+                return Ok(());
+            }
+            lines.send(buffer.clone()).await?;
+        }
+    }
+}
+
+impl Transport for StdioTransport {
+    fn send(&self, message: String) -> BoxFuture<'static, Result<()>> {
+        let stdin_tx = self.stdin_tx.clone();
+        async move {
+            stdin_tx
+                .send(message)
+                .await
+                .context("failed to write to context server's stdin")
+        }
+        .boxed()
+    }
+
+    fn receive(&self) -> BoxStream<'static, String> {
+        self.stdout_rx.clone().boxed()
+    }
+
+    fn receive_err(&self) -> BoxStream<'static, String> {
+        self.stderr_rx.clone().boxed()
+    }
+}
@@ -7,6 +7,7 @@ use url::Url;
 pub enum RequestType {
     Initialize,
     CallTool,
+    ListTools,
     ResourcesUnsubscribe,
     ResourcesSubscribe,
     ResourcesRead,
@@ -22,6 +23,7 @@ impl RequestType {
         match self {
             RequestType::Initialize => "initialize",
             RequestType::CallTool => "tools/call",
+            RequestType::ListTools => "tools/list",
             RequestType::ResourcesUnsubscribe => "resources/unsubscribe",
             RequestType::ResourcesSubscribe => "resources/subscribe",
             RequestType::ResourcesRead => "resources/read",
@@ -132,6 +134,36 @@ pub struct InitializeResponse {
     pub server_info: EntityInfo,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListToolsResponse {
+    pub tools: Vec<Tool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResponse {
+    pub content: Vec<ToolResponseContent>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ToolResponseContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Resource {
+        resource: ResourceContent,
+    },
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourcesReadResponse {
@@ -212,7 +244,7 @@ pub struct ResourcesCapabilities {
     pub subscribe: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Tool {
     pub name: String,
@@ -227,10 +259,12 @@ pub struct EntityInfo {
     pub version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Resource {
     pub uri: Url,
+    pub name: Option<String>,
+    pub description: Option<String>,
     pub mime_type: Option<String>,
 }
 
@@ -239,6 +273,7 @@ pub struct Resource {
 pub struct ResourceContent {
     pub uri: Url,
     pub mime_type: Option<String>,
+    #[serde(default)]
     pub content_type: String,
     pub text: Option<String>,
     pub data: Option<String>,
@@ -261,6 +296,95 @@ pub enum LoggingLevel {
     Error,
 }
 
+// Server Requests
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ServerRequestType {
+    CreateMessage,
+}
+
+impl ServerRequestType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServerRequestType::CreateMessage => "sampling/createMessage",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: u32,
+    #[serde(default)]
+    pub stop_sequences: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: SamplingRole,
+    pub content: SamplingContent,
+    pub model: String,
+    pub stop_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingMessage {
+    pub role: SamplingRole,
+    pub content: SamplingContent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplingRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SamplingContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+}
+
+// Server Notifications
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ServerNotificationType {
+    ResourcesUpdated,
+    ResourcesListChanged,
+    ToolsListChanged,
+}
+
+impl ServerNotificationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServerNotificationType::ResourcesUpdated => "notifications/resources/updated",
+            ServerNotificationType::ResourcesListChanged => "notifications/resources/list_changed",
+            ServerNotificationType::ToolsListChanged => "notifications/tools/list_changed",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourcesUpdatedParams {
+    pub uri: Url,
+}
+
 // Client Notifications
 
 #[derive(Debug, Serialize)]
@@ -1267,5 +1267,9 @@ mod tests {
         fn load(&self, _: &AppContext) -> Task<Result<String>> {
             unimplemented!()
         }
+
+        fn load_bytes(&self, _: &AppContext) -> Task<Result<Vec<u8>>> {
+            unimplemented!()
+        }
     }
 }
@@ -502,6 +502,9 @@ pub struct Editor {
     ime_transaction: Option<TransactionId>,
     active_diagnostics: Option<ActiveDiagnosticGroup>,
     soft_wrap_mode_override: Option<language_settings::SoftWrap>,
+    /// Whether the editor shows a buffer that was opened in large-file mode,
+    /// which disables inlay hints, soft wrap and Git-related features.
+    large_file_mode: bool,
     project: Option<Model<Project>>,
     completion_provider: Option<Box<dyn CompletionProvider>>,
     collaboration_hub: Option<Box<dyn CollaborationHub>>,
@@ -578,6 +581,9 @@ pub struct Editor {
     show_git_blame_inline_delay_task: Option<Task<()>>,
     git_blame_inline_enabled: bool,
     serialize_dirty_buffers: bool,
+    /// The fingerprint of the buffer's text at the given version, saved along with the
+    /// selections and folds so that it isn't recomputed whenever they change.
+    file_state_fingerprint: Option<(clock::Global, String)>,
     show_selection_menu: Option<bool>,
     blame: Option<Model<GitBlame>>,
     blame_subscription: Option<Subscription>,
@@ -1774,8 +1780,18 @@ impl Editor {
 
         let blink_manager = cx.new_model(|cx| BlinkManager::new(CURSOR_BLINK_INTERVAL, cx));
 
-        let soft_wrap_mode_override = matches!(mode, EditorMode::SingleLine { .. })
-            .then(|| language_settings::SoftWrap::PreferLine);
+        let large_file_mode = mode == EditorMode::Full
+            && buffer
+                .read(cx)
+                .as_singleton()
+                .map_or(false, |buffer| buffer.read(cx).is_large_file());
+        let soft_wrap_mode_override = if matches!(mode, EditorMode::SingleLine { .. }) {
+            Some(language_settings::SoftWrap::PreferLine)
+        } else if large_file_mode {
+            Some(language_settings::SoftWrap::None)
+        } else {
+            None
+        };
 
         let mut project_subscriptions = Vec::new();
         if mode == EditorMode::Full {
@@ -1849,6 +1865,7 @@ impl Editor {
             ime_transaction: Default::default(),
             active_diagnostics: None,
             soft_wrap_mode_override,
+            large_file_mode,
             completion_provider: project.clone().map(|project| Box::new(project) as _),
             collaboration_hub: project.clone().map(|project| Box::new(project) as _),
             project,
@@ -1859,7 +1876,7 @@ impl Editor {
             show_gutter: mode == EditorMode::Full,
             show_line_numbers: None,
             use_relative_line_numbers: None,
-            show_git_diff_gutter: None,
+            show_git_diff_gutter: large_file_mode.then_some(false),
             show_code_actions: None,
             show_runnables: None,
             show_wrap_guides: None,
@@ -1923,10 +1940,12 @@ impl Editor {
             show_git_blame_inline: false,
             show_selection_menu: None,
             show_git_blame_inline_delay_task: None,
-            git_blame_inline_enabled: ProjectSettings::get_global(cx).git.inline_blame_enabled(),
+            git_blame_inline_enabled: ProjectSettings::get_global(cx).git.inline_blame_enabled()
+                && !large_file_mode,
             serialize_dirty_buffers: ProjectSettings::get_global(cx)
                 .session
                 .restore_unsaved_buffers,
+            file_state_fingerprint: None,
             blame: None,
             blame_subscription: None,
             file_header_size,
@@ -3941,7 +3960,7 @@ impl Editor {
     }
 
     fn refresh_inlay_hints(&mut self, reason: InlayHintRefreshReason, cx: &mut ViewContext<Self>) {
-        if self.project.is_none() || self.mode != EditorMode::Full {
+        if self.project.is_none() || self.mode != EditorMode::Full || self.large_file_mode {
             return;
         }
 
@@ -7699,6 +7718,10 @@ impl Editor {
                 }),
                 cx,
             );
+            cx.emit(EditorEvent::PushedToNavHistory {
+                anchor: cursor_anchor,
+                is_deactivate: new_position.is_none(),
+            });
         }
     }
 
@@ -10368,6 +10391,7 @@ impl Editor {
             }
 
             self.scrollbar_marker_state.dirty = true;
+            cx.emit(EditorEvent::FoldsChanged);
         }
     }
 
@@ -10403,6 +10427,7 @@ impl Editor {
             cx.notify();
             self.scrollbar_marker_state.dirty = true;
             self.active_indent_guides_state.dirty = true;
+            cx.emit(EditorEvent::FoldsChanged);
         }
     }
 
@@ -12736,6 +12761,7 @@ pub enum EditorEvent {
         local: bool,
         autoscroll: bool,
     },
+    FoldsChanged,
     Closed,
     TransactionUndone {
         transaction_id: clock::Lamport,
@@ -12743,6 +12769,12 @@ pub enum EditorEvent {
     TransactionBegun {
         transaction_id: clock::Lamport,
     },
+    /// The cursor position was recorded in the navigation history, either because the cursor
+    /// jumped away from it or because the editor was deactivated.
+    PushedToNavHistory {
+        anchor: Anchor,
+        is_deactivate: bool,
+    },
 }
 
 impl EventEmitter<EditorEvent> for Editor {}
@@ -14,7 +14,9 @@ pub struct EditorSettings {
     pub use_on_type_format: bool,
     pub toolbar: Toolbar,
     pub scrollbar: Scrollbar,
+    pub minimap: Minimap,
     pub gutter: Gutter,
+    pub sticky_scroll: StickyScroll,
     pub scroll_beyond_last_line: ScrollBeyondLastLine,
     pub vertical_scroll_margin: f32,
     pub scroll_sensitivity: f32,
@@ -106,6 +108,12 @@ pub struct Scrollbar {
     pub cursors: bool,
 }
 
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct Minimap {
+    pub enabled: bool,
+    pub width: f32,
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct Gutter {
     pub line_numbers: bool,
@@ -114,6 +122,12 @@ pub struct Gutter {
     pub folds: bool,
 }
 
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct StickyScroll {
+    pub enabled: bool,
+    pub max_lines: u32,
+}
+
 /// When to show the scrollbar in the editor.
 ///
 /// Default: auto
@@ -211,8 +225,12 @@ pub struct EditorSettingsContent {
     pub toolbar: Option<ToolbarContent>,
     /// Scrollbar related settings
     pub scrollbar: Option<ScrollbarContent>,
+    /// Minimap related settings
+    pub minimap: Option<MinimapContent>,
     /// Gutter related settings
     pub gutter: Option<GutterContent>,
+    /// Sticky scroll related settings
+    pub sticky_scroll: Option<StickyScrollContent>,
     /// Whether the editor will scroll beyond the last line.
     ///
     /// Default: one_page
@@ -332,6 +350,19 @@ pub struct ScrollbarContent {
     pub cursors: Option<bool>,
 }
 
+/// Minimap related settings
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct MinimapContent {
+    /// Whether to show a scaled-down view of the buffer next to the scrollbar.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The width of the minimap in pixels.
+    ///
+    /// Default: 100
+    pub width: Option<f32>,
+}
+
 /// Gutter related settings
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct GutterContent {
@@ -353,6 +384,20 @@ pub struct GutterContent {
     pub folds: Option<bool>,
 }
 
+/// Sticky scroll related settings
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct StickyScrollContent {
+    /// Whether to pin the first lines of the scopes enclosing the top of the
+    /// editor, such as modules, impls, functions and blocks, above the text.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The maximum number of scopes to pin at once.
+    ///
+    /// Default: 5
+    pub max_lines: Option<u32>,
+}
+
 impl EditorSettings {
     pub fn jupyter_enabled(cx: &AppContext) -> bool {
         EditorSettings::get_global(cx).jupyter.enabled
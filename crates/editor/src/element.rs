@@ -18,7 +18,7 @@ use crate::{
     hunk_status,
     items::BufferSearchHighlights,
     mouse_context_menu::{self, MenuPosition, MouseContextMenu},
-    scroll::scroll_amount::ScrollAmount,
+    scroll::{scroll_amount::ScrollAmount, Autoscroll},
     BlockId, CodeActionsMenu, CursorShape, CustomBlockId, DisplayPoint, DisplayRow,
     DocumentHighlightRead, DocumentHighlightWrite, Editor, EditorMode, EditorSettings,
     EditorSnapshot, EditorStyle, ExpandExcerpts, FocusedBlock, GutterDimensions, HalfPageDown,
@@ -1183,6 +1183,240 @@ impl EditorElement {
         })
     }
 
+    fn minimap_width(&self, snapshot: &EditorSnapshot, cx: &WindowContext) -> Pixels {
+        let settings = EditorSettings::get_global(cx).minimap;
+        if settings.enabled && snapshot.mode == EditorMode::Full {
+            px(settings.width.max(0.))
+        } else {
+            Pixels::ZERO
+        }
+    }
+
+    fn layout_minimap(
+        &self,
+        snapshot: &EditorSnapshot,
+        bounds: Bounds<Pixels>,
+        scroll_position: gpui::Point<f32>,
+        rows_per_page: f32,
+        max_scroll_top: f32,
+        cx: &mut WindowContext,
+    ) -> Option<MinimapLayout> {
+        let width = self.minimap_width(snapshot, cx);
+        if width <= Pixels::ZERO {
+            return None;
+        }
+
+        let minimap_bounds = Bounds::from_corners(
+            point(self.scrollbar_left(&bounds) - width, bounds.top()),
+            point(self.scrollbar_left(&bounds), bounds.bottom()),
+        );
+        let row_height = MinimapLayout::ROW_HEIGHT;
+        let column_width = MinimapLayout::COLUMN_WIDTH;
+
+        // When the buffer doesn't fit, the minimap scrolls proportionally to the editor.
+        let minimap_rows = minimap_bounds.size.height / row_height;
+        let total_rows = snapshot.max_point().row().next_row().as_f32();
+        let minimap_top = if total_rows <= minimap_rows || max_scroll_top <= 0. {
+            0.
+        } else {
+            (scroll_position.y / max_scroll_top).clamp(0., 1.) * (total_rows - minimap_rows)
+        };
+        let start_row = DisplayRow(minimap_top as u32);
+        let end_row = DisplayRow(cmp::min(
+            (minimap_top + minimap_rows).ceil() as u32,
+            snapshot.max_point().row().next_row().0,
+        ));
+        let max_columns = (width / column_width) as u32;
+        let row_top =
+            |row: DisplayRow| minimap_bounds.top() + (row.as_f32() - minimap_top) * row_height;
+        let columns_bounds = |row: DisplayRow, start: u32, end: u32| Bounds {
+            origin: point(
+                minimap_bounds.left() + start.min(max_columns) as f32 * column_width,
+                row_top(row),
+            ),
+            size: size(
+                (end.min(max_columns) - start.min(max_columns)) as f32 * column_width,
+                row_height,
+            ),
+        };
+        let range_quads = |range: Range<DisplayPoint>, color: Hsla, quads: &mut Vec<PaintQuad>| {
+            if range.start.row() == range.end.row() {
+                let start = range.start.column();
+                let end = range.end.column().max(start + 1);
+                quads.push(fill(columns_bounds(range.start.row(), start, end), color));
+            } else {
+                let top = row_top(range.start.row().max(start_row));
+                let bottom = row_top(range.end.row().min(end_row)) + row_height;
+                quads.push(fill(
+                    Bounds::from_corners(
+                        point(minimap_bounds.left(), top),
+                        point(minimap_bounds.right(), bottom),
+                    ),
+                    color,
+                ));
+            }
+        };
+
+        let mut text_quads = Vec::new();
+        let mut row = start_row;
+        let mut column = 0;
+        for chunk in snapshot.highlighted_chunks(start_row..end_row, true, &self.style) {
+            let color = chunk
+                .style
+                .and_then(|style| style.color)
+                .unwrap_or(self.style.text.color)
+                .opacity(0.6);
+            for (ix, line) in chunk.text.split('\n').enumerate() {
+                if ix > 0 {
+                    row.0 += 1;
+                    column = 0;
+                }
+                let mut run_start = None;
+                for c in line.chars() {
+                    if c.is_whitespace() {
+                        if let Some(start) = run_start.take() {
+                            text_quads.push(fill(columns_bounds(row, start, column), color));
+                        }
+                    } else if run_start.is_none() {
+                        run_start = Some(column);
+                    }
+                    column += 1;
+                }
+                if let Some(start) = run_start {
+                    text_quads.push(fill(columns_bounds(row, start, column), color));
+                }
+            }
+        }
+        text_quads.retain(|quad| quad.bounds.size.width > Pixels::ZERO);
+
+        let start_anchor = snapshot
+            .buffer_snapshot
+            .anchor_before(DisplayPoint::new(start_row, 0).to_offset(snapshot, Bias::Left));
+        let end_anchor = snapshot
+            .buffer_snapshot
+            .anchor_after(DisplayPoint::new(end_row, 0).to_offset(snapshot, Bias::Right));
+        let theme = cx.theme().clone();
+        let mut marker_quads = Vec::new();
+
+        let start_buffer_row =
+            MultiBufferRow(DisplayPoint::new(start_row, 0).to_point(snapshot).row);
+        let end_buffer_row =
+            MultiBufferRow(DisplayPoint::new(end_row, 0).to_point(snapshot).row + 1);
+        for hunk in snapshot
+            .buffer_snapshot
+            .git_diff_hunks_in_range(start_buffer_row..end_buffer_row)
+        {
+            let start = MultiBufferPoint::new(hunk.associated_range.start.0, 0)
+                .to_display_point(snapshot)
+                .row();
+            let mut end = MultiBufferPoint::new(hunk.associated_range.end.0, 0)
+                .to_display_point(snapshot)
+                .row();
+            if end != start {
+                end.0 -= 1;
+            }
+            let color = match hunk_status(&hunk) {
+                DiffHunkStatus::Added => theme.status().created,
+                DiffHunkStatus::Modified => theme.status().modified,
+                DiffHunkStatus::Removed => theme.status().deleted,
+            };
+            marker_quads.push(fill(
+                Bounds::from_corners(
+                    point(minimap_bounds.left(), row_top(start.max(start_row))),
+                    point(
+                        minimap_bounds.left() + MinimapLayout::GIT_MARKER_WIDTH,
+                        row_top(end.min(end_row)) + row_height,
+                    ),
+                ),
+                color,
+            ));
+        }
+
+        let editor = self.editor.read(cx);
+        let buffer = &snapshot.buffer_snapshot;
+        for (highlight_id, (_, ranges)) in editor.background_highlights.iter() {
+            let is_search_highlights = *highlight_id == TypeId::of::<BufferSearchHighlights>();
+            let is_symbol_occurrences = *highlight_id == TypeId::of::<DocumentHighlightRead>()
+                || *highlight_id == TypeId::of::<DocumentHighlightWrite>();
+            if !is_search_highlights && !is_symbol_occurrences {
+                continue;
+            }
+            let mut color = theme.status().info;
+            if is_symbol_occurrences {
+                color.fade_out(0.5);
+            }
+            let start_ix =
+                ranges.partition_point(|range| range.end.cmp(&start_anchor, buffer).is_lt());
+            for range in &ranges[start_ix..] {
+                if range.start.cmp(&end_anchor, buffer).is_gt() {
+                    break;
+                }
+                range_quads(
+                    range.clone().to_display_points(snapshot),
+                    color,
+                    &mut marker_quads,
+                );
+            }
+        }
+
+        let diagnostics = buffer
+            .diagnostics_in_range::<_, Point>(
+                start_anchor.to_point(buffer)..end_anchor.to_point(buffer),
+                false,
+            )
+            // Paint the most severe diagnostics last.
+            .sorted_by_key(|diagnostic| cmp::Reverse(diagnostic.diagnostic.severity));
+        for diagnostic in diagnostics {
+            let color = match diagnostic.diagnostic.severity {
+                DiagnosticSeverity::ERROR => theme.status().error,
+                DiagnosticSeverity::WARNING => theme.status().warning,
+                DiagnosticSeverity::INFORMATION => theme.status().info,
+                _ => theme.status().hint,
+            };
+            range_quads(
+                diagnostic.range.start.to_display_point(snapshot)
+                    ..diagnostic.range.end.to_display_point(snapshot),
+                color,
+                &mut marker_quads,
+            );
+        }
+
+        let player = theme.players().local();
+        for selection in editor
+            .selections
+            .disjoint_in_range::<Point>(start_anchor..end_anchor, cx)
+        {
+            let range = selection.start.to_display_point(snapshot)
+                ..selection.end.to_display_point(snapshot);
+            if range.is_empty() {
+                let head = range.start.row();
+                marker_quads.push(fill(
+                    columns_bounds(head, 0, max_columns),
+                    player.cursor.opacity(0.4),
+                ));
+            } else {
+                range_quads(range, player.selection, &mut marker_quads);
+            }
+        }
+
+        let thumb_bounds = Bounds {
+            origin: point(
+                minimap_bounds.left(),
+                minimap_bounds.top() + (scroll_position.y - minimap_top) * row_height,
+            ),
+            size: size(width, rows_per_page * row_height),
+        };
+
+        Some(MinimapLayout {
+            hitbox: cx.insert_hitbox(minimap_bounds, false),
+            text_quads,
+            marker_quads,
+            thumb_bounds,
+            minimap_top,
+            rows_per_page,
+        })
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn prepaint_gutter_fold_toggles(
         &self,
@@ -1859,6 +2093,78 @@ impl EditorElement {
             .collect()
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn layout_sticky_headers(
+        &self,
+        snapshot: &EditorSnapshot,
+        scroll_position: gpui::Point<f32>,
+        show_line_numbers: bool,
+        line_height: Pixels,
+        hitbox: &Hitbox,
+        editor_width: Pixels,
+        cx: &mut WindowContext,
+    ) -> Vec<StickyHeaderLayout> {
+        let settings = EditorSettings::get_global(cx).sticky_scroll;
+        if !settings.enabled
+            || snapshot.mode != EditorMode::Full
+            || snapshot.buffer_snapshot.as_singleton().is_none()
+        {
+            return Vec::new();
+        }
+
+        let font_size = self.style.text.font_size.to_pixels(cx.rem_size());
+        sticky_scopes(snapshot, scroll_position.y, settings.max_lines as usize)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(slot, scope)| {
+                let line = Self::layout_lines(
+                    scope.row..scope.row.next_row(),
+                    &[],
+                    snapshot,
+                    &self.style,
+                    editor_width,
+                    cx,
+                )
+                .pop()?;
+                let buffer_row = DisplayPoint::new(scope.row, 0).to_point(snapshot).row;
+                let line_number = show_line_numbers.then(|| {
+                    let number = (buffer_row + 1).to_string();
+                    let run = TextRun {
+                        len: number.len(),
+                        font: self.style.text.font(),
+                        color: cx.theme().colors().editor_line_number,
+                        background_color: None,
+                        underline: None,
+                        strikethrough: None,
+                    };
+                    cx.text_system()
+                        .shape_line(number.into(), font_size, &[run])
+                        .unwrap()
+                });
+                let indent = snapshot
+                    .buffer_snapshot
+                    .indent_size_for_line(MultiBufferRow(buffer_row))
+                    .len;
+                let offset = line_height * scope.top;
+                let hitbox = cx.insert_hitbox(
+                    Bounds {
+                        origin: hitbox.origin + point(Pixels::ZERO, offset),
+                        size: size(hitbox.size.width, line_height),
+                    },
+                    false,
+                );
+                Some(StickyHeaderLayout {
+                    line,
+                    line_number,
+                    offset,
+                    target: Point::new(buffer_row, indent),
+                    slot,
+                    hitbox,
+                })
+            })
+            .collect()
+    }
+
     fn layout_lines(
         rows: Range<DisplayRow>,
         line_number_layouts: &[Option<ShapedLine>],
@@ -3649,6 +3955,100 @@ impl EditorElement {
         }
     }
 
+    fn paint_minimap(&mut self, layout: &mut EditorLayout, cx: &mut WindowContext) {
+        let Some(minimap) = layout.minimap.as_ref() else {
+            return;
+        };
+
+        cx.paint_layer(minimap.hitbox.bounds, |cx| {
+            cx.paint_quad(quad(
+                minimap.hitbox.bounds,
+                Corners::default(),
+                self.style.background,
+                Edges {
+                    top: Pixels::ZERO,
+                    right: Pixels::ZERO,
+                    bottom: Pixels::ZERO,
+                    left: ScrollbarLayout::BORDER_WIDTH,
+                },
+                cx.theme().colors().border_variant,
+            ));
+            for quad in minimap.text_quads.iter().chain(&minimap.marker_quads) {
+                cx.paint_quad(quad.clone());
+            }
+            cx.paint_quad(fill(
+                minimap.thumb_bounds,
+                cx.theme().colors().scrollbar_thumb_background,
+            ));
+        });
+
+        cx.set_cursor_style(CursorStyle::Arrow, &minimap.hitbox);
+
+        // Clicking or dragging on the minimap centers the editor on the row under the mouse.
+        let minimap_top = minimap.minimap_top;
+        let rows_per_page = minimap.rows_per_page;
+        let scroll_to = {
+            let hitbox = minimap.hitbox.clone();
+            move |editor: &mut Editor, y: Pixels, cx: &mut ViewContext<Editor>| {
+                let y = y.clamp(hitbox.top(), hitbox.bottom());
+                let row = minimap_top + (y - hitbox.top()) / MinimapLayout::ROW_HEIGHT;
+                let mut position = editor.scroll_position(cx);
+                position.y = (row - rows_per_page / 2.).max(0.);
+                editor.set_scroll_position(position, cx);
+            }
+        };
+
+        cx.on_mouse_event({
+            let editor = self.editor.clone();
+            let hitbox = minimap.hitbox.clone();
+            let scroll_to = scroll_to.clone();
+            move |event: &MouseDownEvent, phase, cx| {
+                if phase == DispatchPhase::Capture
+                    || event.button != MouseButton::Left
+                    || !hitbox.is_hovered(cx)
+                {
+                    return;
+                }
+                editor.update(cx, |editor, cx| {
+                    editor.scroll_manager.set_is_dragging_minimap(true, cx);
+                    scroll_to(editor, event.position.y, cx);
+                });
+                cx.stop_propagation();
+            }
+        });
+
+        if self.editor.read(cx).scroll_manager.is_dragging_minimap() {
+            cx.on_mouse_event({
+                let editor = self.editor.clone();
+                move |event: &MouseMoveEvent, phase, cx| {
+                    if phase == DispatchPhase::Capture {
+                        return;
+                    }
+                    editor.update(cx, |editor, cx| {
+                        if event.pressed_button == Some(MouseButton::Left) {
+                            scroll_to(editor, event.position.y, cx);
+                        } else {
+                            editor.scroll_manager.set_is_dragging_minimap(false, cx);
+                        }
+                    });
+                    cx.stop_propagation();
+                }
+            });
+            cx.on_mouse_event({
+                let editor = self.editor.clone();
+                move |_: &MouseUpEvent, phase, cx| {
+                    if phase == DispatchPhase::Capture {
+                        return;
+                    }
+                    editor.update(cx, |editor, cx| {
+                        editor.scroll_manager.set_is_dragging_minimap(false, cx);
+                    });
+                    cx.stop_propagation();
+                }
+            });
+        }
+    }
+
     fn collect_fast_scrollbar_markers(
         &self,
         layout: &EditorLayout,
@@ -3899,6 +4299,103 @@ impl EditorElement {
         }
     }
 
+    fn paint_sticky_headers(&mut self, layout: &mut EditorLayout, cx: &mut WindowContext) {
+        let Some(lowest_header_bottom) = layout
+            .sticky_headers
+            .iter()
+            .map(|header| header.offset)
+            .max()
+            .map(|offset| offset + layout.position_map.line_height)
+        else {
+            return;
+        };
+
+        let line_height = layout.position_map.line_height;
+        let scroll_left = layout.position_map.scroll_pixel_position.x;
+        cx.paint_layer(layout.hitbox.bounds, |cx| {
+            // Inner headers are pushed up underneath outer ones when their scope ends.
+            for header in layout.sticky_headers.iter().rev() {
+                let top = layout.hitbox.top() + header.offset;
+                let gutter_bounds = Bounds {
+                    origin: point(layout.gutter_hitbox.left(), top),
+                    size: size(layout.gutter_hitbox.size.width, line_height),
+                };
+                let text_bounds = Bounds {
+                    origin: point(layout.text_hitbox.left(), top),
+                    size: size(layout.text_hitbox.size.width, line_height),
+                };
+                cx.paint_quad(fill(
+                    gutter_bounds,
+                    cx.theme().colors().editor_gutter_background,
+                ));
+                cx.paint_quad(fill(text_bounds, self.style.background));
+
+                if let Some(line_number) = &header.line_number {
+                    let origin = point(
+                        gutter_bounds.right()
+                            - line_number.width
+                            - layout.gutter_dimensions.right_padding,
+                        top,
+                    );
+                    line_number.paint(origin, line_height, cx).log_err();
+                }
+                cx.with_content_mask(
+                    Some(ContentMask {
+                        bounds: text_bounds,
+                    }),
+                    |cx| {
+                        let origin = point(layout.content_origin.x - scroll_left, top);
+                        header.line.draw_text(origin, line_height, cx);
+                    },
+                );
+            }
+
+            cx.paint_quad(fill(
+                Bounds {
+                    origin: point(
+                        layout.hitbox.left(),
+                        layout.hitbox.top() + lowest_header_bottom - px(1.),
+                    ),
+                    size: size(layout.hitbox.size.width, px(1.)),
+                },
+                cx.theme().colors().border_variant,
+            ));
+        });
+
+        for header in &layout.sticky_headers {
+            cx.set_cursor_style(CursorStyle::PointingHand, &header.hitbox);
+        }
+
+        cx.on_mouse_event({
+            let editor = self.editor.clone();
+            let headers = layout
+                .sticky_headers
+                .iter()
+                .map(|header| (header.hitbox.clone(), header.target, header.slot))
+                .collect::<Vec<_>>();
+            move |event: &MouseDownEvent, phase, cx| {
+                if phase != DispatchPhase::Bubble || event.button != MouseButton::Left {
+                    return;
+                }
+                // Prefer the innermost header when they overlap.
+                let Some((_, target, slot)) = headers
+                    .iter()
+                    .rev()
+                    .find(|(hitbox, _, _)| hitbox.is_hovered(cx))
+                else {
+                    return;
+                };
+                editor.update(cx, |editor, cx| {
+                    // Keep the clicked header's line just below the headers of its parent scopes.
+                    editor.change_selections(Some(Autoscroll::top_relative(*slot)), cx, |s| {
+                        s.select_ranges([*target..*target])
+                    });
+                });
+                cx.stop_propagation();
+            }
+        });
+    }
+
     fn paint_mouse_context_menu(&mut self, layout: &mut EditorLayout, cx: &mut WindowContext) {
         if let Some(mouse_context_menu) = layout.mouse_context_menu.as_mut() {
             mouse_context_menu.paint(cx);
@@ -4571,9 +5068,27 @@ impl LineWithInvisibles {
         let line_y = line_height
             * (row.as_f32() - layout.position_map.scroll_pixel_position.y / line_height);
 
-        let mut fragment_origin =
-            content_origin + gpui::point(-layout.position_map.scroll_pixel_position.x, line_y);
+        self.draw_text(
+            content_origin + gpui::point(-layout.position_map.scroll_pixel_position.x, line_y),
+            line_height,
+            cx,
+        );
+
+        self.draw_invisibles(
+            selection_ranges,
+            layout,
+            content_origin,
+            line_y,
+            row,
+            line_height,
+            whitespace_setting,
+            cx,
+        );
+    }
 
+    /// Paints the text of the line, leaving gaps for its elements.
+    fn draw_text(&self, origin: gpui::Point<Pixels>, line_height: Pixels, cx: &mut WindowContext) {
+        let mut fragment_origin = origin;
         for fragment in &self.fragments {
             match fragment {
                 LineFragment::Text(line) => {
@@ -4585,17 +5100,6 @@ impl LineWithInvisibles {
                 }
             }
         }
-
-        self.draw_invisibles(
-            selection_ranges,
-            layout,
-            content_origin,
-            line_y,
-            row,
-            line_height,
-            whitespace_setting,
-            cx,
-        );
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -4980,7 +5484,7 @@ impl Element for EditorElement {
                     let text_width = bounds.size.width - gutter_dimensions.width;
 
                     let right_margin = if snapshot.mode == EditorMode::Full {
-                        EditorElement::SCROLLBAR_WIDTH
+                        EditorElement::SCROLLBAR_WIDTH + self.minimap_width(&snapshot, cx)
                     } else {
                         px(0.)
                     };
@@ -5343,6 +5847,16 @@ impl Element for EditorElement {
                         );
                     });
 
+                    let sticky_headers = self.layout_sticky_headers(
+                        &snapshot,
+                        scroll_position,
+                        !line_numbers.is_empty(),
+                        line_height,
+                        &hitbox,
+                        editor_width,
+                        cx,
+                    );
+
                     let cursors = self.collect_cursors(&snapshot, cx);
                     let visible_row_range = start_row..end_row;
                     let non_visible_cursors = cursors
@@ -5373,6 +5887,15 @@ impl Element for EditorElement {
                         cx,
                     );
 
+                    let minimap = self.layout_minimap(
+                        &snapshot,
+                        bounds,
+                        scroll_position,
+                        height_in_lines,
+                        max_scroll_top,
+                        cx,
+                    );
+
                     let gutter_settings = EditorSettings::get_global(cx).gutter;
 
                     let expanded_add_hunks_by_rows = self.editor.update(cx, |editor, _| {
@@ -5590,6 +6113,7 @@ impl Element for EditorElement {
                         display_hunks,
                         content_origin,
                         scrollbar_layout,
+                        minimap,
                         active_rows,
                         highlighted_rows,
                         highlighted_ranges,
@@ -5600,6 +6124,7 @@ impl Element for EditorElement {
                         blamed_display_rows,
                         inline_blame,
                         blocks,
+                        sticky_headers,
                         cursors,
                         visible_cursors,
                         selections,
@@ -5693,6 +6218,9 @@ impl Element for EditorElement {
                         });
                     }
 
+                    self.paint_sticky_headers(layout, cx);
+                    self.paint_minimap(layout, cx);
+
                     self.paint_scrollbar(layout, cx);
                     self.paint_mouse_context_menu(layout, cx);
                 });
@@ -5727,6 +6255,7 @@ pub struct EditorLayout {
     gutter_dimensions: GutterDimensions,
     content_origin: gpui::Point<Pixels>,
     scrollbar_layout: Option<ScrollbarLayout>,
+    minimap: Option<MinimapLayout>,
     mode: EditorMode,
     wrap_guides: SmallVec<[(Pixels, bool); 2]>,
     indent_guides: Option<Vec<IndentGuideLayout>>,
@@ -5739,6 +6268,7 @@ pub struct EditorLayout {
     blamed_display_rows: Option<Vec<AnyElement>>,
     inline_blame: Option<AnyElement>,
     blocks: Vec<BlockLayout>,
+    sticky_headers: Vec<StickyHeaderLayout>,
     highlighted_ranges: Vec<(Range<DisplayPoint>, Hsla)>,
     highlighted_gutter_ranges: Vec<(Range<DisplayPoint>, Hsla)>,
     redacted_ranges: Vec<Range<DisplayPoint>>,
@@ -5767,6 +6297,23 @@ struct ColoredRange<T> {
     color: Hsla,
 }
 
+struct MinimapLayout {
+    hitbox: Hitbox,
+    text_quads: Vec<PaintQuad>,
+    marker_quads: Vec<PaintQuad>,
+    /// The part of the minimap that is visible in the editor.
+    thumb_bounds: Bounds<Pixels>,
+    /// The display row at the top of the minimap, which scrolls along with the editor.
+    minimap_top: f32,
+    rows_per_page: f32,
+}
+
+impl MinimapLayout {
+    const ROW_HEIGHT: Pixels = px(2.);
+    const COLUMN_WIDTH: Pixels = px(1.);
+    const GIT_MARKER_WIDTH: Pixels = px(2.);
+}
+
 #[derive(Clone)]
 struct ScrollbarLayout {
     hitbox: Hitbox,
@@ -5947,6 +6494,85 @@ impl PositionMap {
     }
 }
 
+struct StickyHeaderLayout {
+    line: LineWithInvisibles,
+    line_number: Option<ShapedLine>,
+    /// The distance from the top of the editor, which is less than the header's slot
+    /// when the end of its scope pushes it up.
+    offset: Pixels,
+    /// Where to move the cursor when the header is clicked.
+    target: Point,
+    slot: usize,
+    hitbox: Hitbox,
+}
+
+/// A scope whose first line is pinned to the top of the editor.
+#[derive(Debug, PartialEq)]
+struct StickyScope {
+    row: DisplayRow,
+    /// The distance from the top of the editor, in lines.
+    top: f32,
+}
+
+/// Returns the scopes enclosing the top of the editor whose first lines are scrolled out of
+/// view or covered by other headers, outermost first. Scopes come from the language's
+/// outline and bracket queries.
+fn sticky_scopes(
+    snapshot: &EditorSnapshot,
+    scroll_top: f32,
+    max_scopes: usize,
+) -> Vec<StickyScope> {
+    let top_row = DisplayRow(scroll_top as u32);
+    if max_scopes == 0 || top_row > snapshot.max_point().row() {
+        return Vec::new();
+    }
+
+    // Each header covers a row, so the scopes enclosing the covered rows may need one too.
+    let buffer = &snapshot.buffer_snapshot;
+    let last_row = cmp::min(top_row.0 + max_scopes as u32, snapshot.max_point().row().0);
+    let mut scopes = Vec::<Range<DisplayRow>>::new();
+    for row in top_row.0..=last_row {
+        let offset = DisplayPoint::new(DisplayRow(row), 0).to_offset(snapshot, Bias::Left);
+        if let Some((_, items)) = buffer.symbols_containing(offset, None) {
+            scopes.extend(items.into_iter().map(|item| {
+                item.range.start.to_display_point(snapshot).row()
+                    ..item.range.end.to_display_point(snapshot).row()
+            }));
+        }
+        if let Some(brackets) = buffer.enclosing_bracket_ranges(offset..offset) {
+            scopes.extend(brackets.map(|(open, close)| {
+                open.start.to_display_point(snapshot).row()
+                    ..close.end.to_display_point(snapshot).row()
+            }));
+        }
+    }
+    scopes.retain(|scope| scope.start < scope.end);
+    scopes.sort_unstable_by_key(|scope| (scope.start, cmp::Reverse(scope.end)));
+    scopes.dedup_by_key(|scope| scope.start);
+
+    let mut sticky_scopes = Vec::new();
+    for scope in scopes {
+        if sticky_scopes.len() == max_scopes {
+            break;
+        }
+        let slot = sticky_scopes.len() as f32;
+        // Scopes are sorted by their start, so the remaining ones are visible too.
+        if scope.start.as_f32() >= scroll_top + slot {
+            break;
+        }
+        // Once the last line of the scope reaches the header, it starts pushing it up.
+        let end_top = scope.end.as_f32() - scroll_top;
+        if end_top + 1. <= slot {
+            continue;
+        }
+        sticky_scopes.push(StickyScope {
+            row: scope.start,
+            top: slot.min(end_top),
+        });
+    }
+    sticky_scopes
+}
+
 struct BlockLayout {
     id: BlockId,
     row: Option<DisplayRow>,
@@ -6324,16 +6950,63 @@ mod tests {
     use super::*;
     use crate::{
         display_map::{BlockDisposition, BlockProperties},
+        editor_settings::MinimapContent,
         editor_tests::{init_test, update_test_language_settings},
         Editor, MultiBuffer,
     };
-    use gpui::{TestAppContext, VisualTestContext};
-    use language::language_settings;
+    use gpui::{Modifiers, TestAppContext, VisualTestContext};
+    use language::{language_settings, Buffer, Language, LanguageConfig};
     use log::info;
+    use settings::SettingsStore;
     use std::num::NonZeroU32;
     use ui::Context;
     use util::test::sample_text;
 
+    #[gpui::test]
+    async fn test_sticky_scopes(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig::default(),
+                Some(tree_sitter_rust::language()),
+            )
+            .with_outline_query(
+                r#"
+                    (impl_item "impl" @context type: (_) @name) @item
+                    (function_item "fn" @context name: (_) @name) @item
+                    "#,
+            )
+            .unwrap()
+            .with_brackets_query(r#"("{" @open "}" @close)"#)
+            .unwrap(),
+        );
+        let text = format!(
+            "impl Foo {{\n    fn bar() {{\n        if true {{\n{}        }}\n    }}\n}}\n",
+            "            baz();\n".repeat(10)
+        );
+        let buffer = cx.new_model(|cx| Buffer::local(text, cx).with_language(language, cx));
+        let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+        let window = cx.add_window(|cx| Editor::new(EditorMode::Full, buffer, None, true, cx));
+        cx.run_until_parked();
+
+        let snapshot = window.update(cx, |editor, cx| editor.snapshot(cx)).unwrap();
+        let scopes = |scroll_top: f32, max_scopes: usize| {
+            sticky_scopes(&snapshot, scroll_top, max_scopes)
+                .into_iter()
+                .map(|scope| (scope.row.0, scope.top))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(scopes(0., 5), vec![]);
+        // Each header covers the first line of the next scope.
+        assert_eq!(scopes(0.5, 5), vec![(0, 0.), (1, 1.), (2, 2.)]);
+        assert_eq!(scopes(5., 5), vec![(0, 0.), (1, 1.), (2, 2.)]);
+        assert_eq!(scopes(5., 2), vec![(0, 0.), (1, 1.)]);
+        // The end of the function pushes its header up, and the `if` block is over.
+        assert_eq!(scopes(13.5, 5), vec![(0, 0.), (1, 0.5)]);
+        assert_eq!(scopes(16., 5), vec![]);
+    }
+
     #[gpui::test]
     fn test_shape_line_numbers(cx: &mut TestAppContext) {
         init_test(cx, |_| {});
@@ -6765,6 +7438,127 @@ mod tests {
         }
     }
 
+    #[gpui::test]
+    fn test_minimap_layout(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        update_test_minimap_settings(cx, true);
+        let (editor, cx) = cx.add_window_view(|cx| {
+            let buffer = MultiBuffer::build_simple(&"fn main() {}\n".repeat(1000), cx);
+            Editor::new(EditorMode::Full, buffer, None, true, cx)
+        });
+        cx.run_until_parked();
+
+        // At the top of the buffer, the thumb is at the top of the minimap and covers the
+        // rows that are visible in the editor.
+        let state = draw_editor(&editor, cx);
+        let minimap = state.minimap.as_ref().unwrap();
+        let hitbox = minimap.hitbox.bounds;
+        assert_eq!(hitbox.size.width, px(100.));
+        assert_eq!(
+            hitbox.right(),
+            state.hitbox.right() - EditorElement::SCROLLBAR_WIDTH
+        );
+        assert!(!minimap.text_quads.is_empty());
+        assert_eq!(minimap.minimap_top, 0.);
+        assert_eq!(minimap.thumb_bounds.top(), hitbox.top());
+        assert_eq!(
+            minimap.thumb_bounds.size.height,
+            minimap.rows_per_page * MinimapLayout::ROW_HEIGHT
+        );
+
+        // The buffer doesn't fit in the minimap, so it scrolls along with the editor, and
+        // the thumb follows the editor's scroll position.
+        editor.update(cx, |editor, cx| {
+            editor.set_scroll_position(point(0., 500.), cx);
+        });
+        let state = draw_editor(&editor, cx);
+        let minimap = state.minimap.as_ref().unwrap();
+        assert!(minimap.minimap_top > 0.);
+        assert!(minimap.minimap_top < 500.);
+        assert_eq!(
+            minimap.thumb_bounds.top(),
+            hitbox.top() + (500. - minimap.minimap_top) * MinimapLayout::ROW_HEIGHT
+        );
+        assert!(minimap.thumb_bounds.bottom() <= hitbox.bottom());
+
+        // The minimap is hidden when it's disabled.
+        update_test_minimap_settings(cx, false);
+        let state = draw_editor(&editor, cx);
+        assert!(state.minimap.is_none());
+    }
+
+    #[gpui::test]
+    fn test_minimap_mouse_scrolling(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        update_test_minimap_settings(cx, true);
+        let (editor, cx) = cx.add_window_view(|cx| {
+            let buffer = MultiBuffer::build_simple(&"fn main() {}\n".repeat(1000), cx);
+            Editor::new(EditorMode::Full, buffer, None, true, cx)
+        });
+        cx.run_until_parked();
+
+        let state = draw_editor(&editor, cx);
+        let minimap = state.minimap.as_ref().unwrap();
+        let hitbox = minimap.hitbox.bounds;
+        let rows_per_page = minimap.rows_per_page;
+        let x = hitbox.center().x;
+        let scroll_top = |cx: &mut VisualTestContext| {
+            editor.update(cx, |editor, cx| editor.scroll_position(cx).y)
+        };
+
+        // Clicking centers the editor on the row under the mouse.
+        cx.simulate_mouse_down(
+            point(x, hitbox.top() + px(400.)),
+            MouseButton::Left,
+            Modifiers::none(),
+        );
+        assert_eq!(scroll_top(cx), 200. - rows_per_page / 2.);
+        assert!(editor.update(cx, |editor, _| editor.scroll_manager.is_dragging_minimap()));
+
+        // Dragging keeps scrolling, relative to the minimap's new scroll position.
+        let minimap_top = draw_editor(&editor, cx).minimap.unwrap().minimap_top;
+        cx.simulate_mouse_move(
+            point(x, hitbox.top() + px(600.)),
+            MouseButton::Left,
+            Modifiers::none(),
+        );
+        assert_eq!(scroll_top(cx), minimap_top + 300. - rows_per_page / 2.);
+
+        // Releasing the mouse stops the drag.
+        let scrolled_to = scroll_top(cx);
+        cx.simulate_mouse_up(
+            point(x, hitbox.top() + px(600.)),
+            MouseButton::Left,
+            Modifiers::none(),
+        );
+        assert!(!editor.update(cx, |editor, _| editor.scroll_manager.is_dragging_minimap()));
+        cx.simulate_mouse_move(point(x, hitbox.top() + px(100.)), None, Modifiers::none());
+        assert_eq!(scroll_top(cx), scrolled_to);
+    }
+
+    fn update_test_minimap_settings(cx: &mut TestAppContext, enabled: bool) {
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.minimap = Some(MinimapContent {
+                        enabled: Some(enabled),
+                        width: Some(100.),
+                    });
+                });
+            });
+        });
+    }
+
+    /// Lays out and paints the editor over the whole window, returning its layout.
+    fn draw_editor(editor: &View<Editor>, cx: &mut VisualTestContext) -> EditorLayout {
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let viewport_size = cx.update(|cx| cx.viewport_size());
+        let (_, state) = cx.draw(point(px(0.), px(0.)), viewport_size, |_| {
+            EditorElement::new(editor, style)
+        });
+        state
+    }
+
     fn collect_invisibles_from_new_editor(
         cx: &mut TestAppContext,
         editor_mode: EditorMode,
@@ -5,7 +5,10 @@ use std::{
 
 use collections::{hash_map, HashMap, HashSet};
 use git::diff::{DiffHunk, DiffHunkStatus};
-use gpui::{Action, AppContext, CursorStyle, Hsla, Model, MouseButton, Subscription, Task, View};
+use gpui::{
+    Action, AppContext, CursorStyle, Hsla, Model, MouseButton, Subscription, Task, View,
+    WindowContext,
+};
 use language::Buffer;
 use multi_buffer::{
     Anchor, AnchorRangeExt, ExcerptRange, MultiBuffer, MultiBufferRow, MultiBufferSnapshot, ToPoint,
@@ -17,6 +20,7 @@ use ui::{
     IntoElement, ParentElement, Pixels, Styled, ViewContext, VisualContext,
 };
 use util::{debug_panic, RangeExt};
+use workspace::Workspace;
 
 use crate::{
     editor_settings::CurrentLineHighlight,
@@ -66,6 +70,44 @@ pub(super) struct ExpandedHunk {
 }
 
 impl Editor {
+    /// Opens a read-only editor in the workspace, showing the changes made to the buffer
+    /// since it had the given text.
+    pub fn open_diff(
+        workspace: &View<Workspace>,
+        buffer: &Model<Buffer>,
+        old_text: String,
+        title: String,
+        cx: &mut WindowContext,
+    ) {
+        let project = workspace.read(cx).project().clone();
+        let buffer = buffer.read(cx);
+        let language = buffer.language().cloned();
+        let text = buffer.text();
+
+        let diff_buffer = cx.new_model(|cx| {
+            let mut buffer = Buffer::local(text, cx);
+            buffer.set_language(language, cx);
+            buffer.set_diff_base(Some(old_text), cx);
+            buffer
+        });
+        let multibuffer =
+            cx.new_model(|cx| MultiBuffer::singleton(diff_buffer.clone(), cx).with_title(title));
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::for_multibuffer(multibuffer, Some(project), true, cx);
+            editor.set_read_only(true);
+            cx.subscribe(&diff_buffer, |editor, _, event, cx| {
+                if let language::Event::DiffBaseChanged = event {
+                    editor.expand_all_hunk_diffs(&ExpandAllHunkDiffs, cx);
+                }
+            })
+            .detach();
+            editor
+        });
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item_to_active_pane(Box::new(editor), None, true, cx);
+        });
+    }
+
     pub(super) fn open_hunk_context_menu(
         &mut self,
         hovered_hunk: HoveredHunk,
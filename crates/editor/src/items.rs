@@ -1,9 +1,9 @@
 use crate::{
     editor_settings::SeedQuerySetting,
-    persistence::{SerializedEditor, DB},
+    persistence::{SerializedEditor, SerializedFileState, SerializedSelection, DB},
     scroll::ScrollAnchor,
     Anchor, Autoscroll, Editor, EditorEvent, EditorSettings, ExcerptId, ExcerptRange, MultiBuffer,
-    MultiBufferSnapshot, NavigationData, SearchWithinRange, ToPoint as _,
+    MultiBufferSnapshot, NavigationData, SearchWithinRange, ToOffset as _, ToPoint as _,
 };
 use anyhow::{anyhow, Context as _, Result};
 use collections::HashSet;
@@ -16,7 +16,8 @@ use gpui::{
     VisualContext, WeakView, WindowContext,
 };
 use language::{
-    proto::serialize_anchor as serialize_text_anchor, Bias, Buffer, CharKind, Point, SelectionGoal,
+    proto::serialize_anchor as serialize_text_anchor, text_fingerprint, Bias, Buffer, CharKind,
+    Point, SelectionGoal, SerializedUndoHistory,
 };
 use multi_buffer::AnchorRangeExt;
 use project::{
@@ -35,6 +36,7 @@ use std::{
     ops::Range,
     path::Path,
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use text::{BufferId, Selection};
 use theme::{Theme, ThemeSettings};
@@ -49,6 +51,11 @@ use workspace::{
 
 pub const MAX_TAB_TITLE_LEN: usize = 24;
 
+const MAX_PERSISTED_UNDO_TRANSACTIONS: usize = 1_000;
+const MAX_PERSISTED_UNDO_BYTES: usize = 1024 * 1024;
+const MAX_PERSISTED_UNDO_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+const MAX_PERSISTED_FILE_STATE_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 90);
+
 impl FollowableItem for Editor {
     fn remote_id(&self) -> Option<ViewId> {
         self.remote_id
@@ -720,6 +727,7 @@ impl Item for Editor {
     ) -> Task<Result<()>> {
         self.report_editor_event("save", None, cx);
         let buffers = self.buffer().clone().read(cx).all_buffers();
+        let saved_buffers = buffers.clone();
         cx.spawn(|this, mut cx| async move {
             if format {
                 this.update(&mut cx, |editor, cx| {
@@ -761,6 +769,12 @@ impl Item for Editor {
                 }
             }
 
+            cx.update(|cx| {
+                for buffer in &saved_buffers {
+                    persist_undo_history(buffer, cx).detach_and_log_err(cx);
+                }
+            })?;
+
             Ok(())
         })
     }
@@ -1040,6 +1054,8 @@ impl SerializableItem for Editor {
                                     }
                                     buffer.set_text(buffer_text, cx);
                                 })?;
+                            } else {
+                                cx.update(|cx| restore_undo_history(buffer.clone(), cx))?;
                             }
 
                             Ok(buffer)
@@ -1057,6 +1073,7 @@ impl SerializableItem for Editor {
                 cx.new_view(|cx| {
                     let mut editor = Editor::for_buffer(buffer, Some(project), cx);
 
+                    editor.restore_file_state(None, cx);
                     editor.read_scroll_position_from_db(item_id, workspace_id, cx);
                     editor
                 })
@@ -1094,10 +1111,46 @@ impl SerializableItem for Editor {
         let mtime = buffer.read(cx).saved_mtime();
 
         let snapshot = buffer.read(cx).snapshot();
-
-        Some(cx.spawn(|_this, cx| async move {
-            cx.background_executor()
+        let version = snapshot.version().clone();
+        // The fingerprint is only computed once per version of the buffer, as the
+        // selections can change many times in between. Large files would have to be
+        // fingerprinted as a whole on every change, so their state isn't saved.
+        let fingerprint = self
+            .file_state_fingerprint
+            .as_ref()
+            .filter(|(fingerprint_version, _)| *fingerprint_version == version)
+            .map(|(_, fingerprint)| fingerprint.clone());
+        let file_state = path
+            .clone()
+            .filter(|_| !buffer.read(cx).is_large_file())
+            .map(|path| (path, snapshot.as_rope().clone(), self.file_state(cx)));
+
+        Some(cx.spawn(|this, mut cx| async move {
+            let fingerprint = cx
+                .background_executor()
                 .spawn(async move {
+                    let mut computed_fingerprint = None;
+                    if let Some((path, text, (selections, folds))) = file_state {
+                        let fingerprint = match fingerprint {
+                            Some(fingerprint) => fingerprint,
+                            None => computed_fingerprint.insert(text_fingerprint(&text)).clone(),
+                        };
+                        let state = SerializedFileState {
+                            fingerprint,
+                            selections,
+                            folds,
+                        };
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+                        DB.save_file_state(path, serde_json::to_string(&state)?, now)
+                            .await
+                            .context("failed to save editor file state")?;
+                        DB.delete_file_states_saved_before(
+                            now - MAX_PERSISTED_FILE_STATE_AGE.as_secs() as i64,
+                        )
+                        .await
+                        .context("failed to delete old editor file states")?;
+                    }
+
                     let (contents, language) = if serialize_dirty_buffers && is_dirty {
                         let contents = snapshot.text();
                         let language = snapshot.language().map(|lang| lang.name().to_string());
@@ -1115,11 +1168,18 @@ impl SerializableItem for Editor {
 
                     DB.save_serialized_editor(item_id, workspace_id, editor)
                         .await
-                        .context("failed to save serialized editor")
+                        .context("failed to save serialized editor")?;
+                    anyhow::Ok(computed_fingerprint)
                 })
                 .await
                 .context("failed to save contents of buffer")?;
 
+            if let Some(fingerprint) = fingerprint {
+                this.update(&mut cx, |this, _| {
+                    this.file_state_fingerprint = Some((version, fingerprint));
+                })
+                .ok();
+            }
             Ok(())
         }))
     }
@@ -1127,7 +1187,11 @@ impl SerializableItem for Editor {
     fn should_serialize(&self, event: &Self::Event) -> bool {
         matches!(
             event,
-            EditorEvent::Saved | EditorEvent::DirtyChanged | EditorEvent::BufferEdited
+            EditorEvent::Saved
+                | EditorEvent::DirtyChanged
+                | EditorEvent::BufferEdited
+                | EditorEvent::SelectionsChanged { local: true }
+                | EditorEvent::FoldsChanged
         )
     }
 }
@@ -1140,10 +1204,211 @@ impl ProjectItem for Editor {
         buffer: Model<Buffer>,
         cx: &mut ViewContext<Self>,
     ) -> Self {
-        Self::for_buffer(buffer, Some(project), cx)
+        restore_undo_history(buffer.clone(), cx);
+        let mut editor = Self::for_buffer(buffer, Some(project), cx);
+        editor.restore_file_state(Some(Autoscroll::center()), cx);
+        editor
     }
 }
 
+impl Editor {
+    /// Returns the offsets of the editor's selections and folds, to be persisted
+    /// for its file.
+    fn file_state(
+        &mut self,
+        cx: &mut WindowContext,
+    ) -> (Vec<SerializedSelection>, Vec<Range<usize>>) {
+        let selections = self
+            .selections
+            .all::<usize>(cx)
+            .into_iter()
+            .map(|selection| SerializedSelection {
+                start: selection.start,
+                end: selection.end,
+                reversed: selection.reversed,
+            })
+            .collect();
+        let snapshot = self.snapshot(cx);
+        let buffer = &snapshot.buffer_snapshot;
+        let folds = snapshot
+            .folds_in_range(0..buffer.len())
+            .map(|fold| fold.range.start.to_offset(buffer)..fold.range.end.to_offset(buffer))
+            .collect();
+        (selections, folds)
+    }
+
+    /// Restores the selections and folds persisted for the editor's file. If the file
+    /// has changed since they were persisted, the folds are discarded and the selections
+    /// are clipped to the file's new contents. Nothing is restored if the buffer is
+    /// edited or the selections change before the state has been read.
+    fn restore_file_state(&mut self, autoscroll: Option<Autoscroll>, cx: &mut ViewContext<Self>) {
+        let Some(buffer) = self.buffer().read(cx).as_singleton() else {
+            return;
+        };
+        let buffer = buffer.read(cx);
+        if buffer.is_large_file() {
+            return;
+        }
+        let Some(path) = buffer
+            .file()
+            .and_then(|file| file.as_local())
+            .map(|file| file.abs_path(cx))
+        else {
+            return;
+        };
+        let version = buffer.version();
+        let text = buffer.as_rope().clone();
+        let initial_selections = self.selections.all::<usize>(cx);
+
+        cx.spawn(|this, mut cx| async move {
+            let Some((state, unchanged)) = cx
+                .background_executor()
+                .spawn(async move {
+                    let state = DB
+                        .get_file_state(path)
+                        .log_err()
+                        .flatten()
+                        .and_then(|state| {
+                            serde_json::from_str::<SerializedFileState>(&state).log_err()
+                        })?;
+                    let unchanged = state.fingerprint == text_fingerprint(&text);
+                    Some((state, unchanged))
+                })
+                .await
+            else {
+                return;
+            };
+
+            this.update(&mut cx, |this, cx| {
+                let buffer_edited = this
+                    .buffer()
+                    .read(cx)
+                    .as_singleton()
+                    .map_or(true, |buffer| buffer.read(cx).version() != version);
+                if buffer_edited || this.selections.all::<usize>(cx) != initial_selections {
+                    return;
+                }
+
+                if unchanged {
+                    let placeholder = this.default_fold_placeholder(cx);
+                    this.fold_ranges(
+                        state
+                            .folds
+                            .into_iter()
+                            .map(|range| (range, placeholder.clone())),
+                        false,
+                        cx,
+                    );
+                }
+
+                if !state.selections.is_empty() {
+                    let snapshot = this.buffer().read(cx).snapshot(cx);
+                    let clip = |offset: usize| {
+                        snapshot.clip_offset(offset.min(snapshot.len()), Bias::Left)
+                    };
+                    let ranges = state
+                        .selections
+                        .into_iter()
+                        .map(|selection| {
+                            let (start, end) = (clip(selection.start), clip(selection.end));
+                            if selection.reversed {
+                                end..start
+                            } else {
+                                start..end
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    this.change_selections(autoscroll, cx, |selections| {
+                        selections.select_ranges(ranges)
+                    });
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+}
+
+/// Stores the buffer's undo history for its file, so that it can be restored by
+/// [`restore_undo_history`] once the file is reopened, as long as it hasn't changed.
+fn persist_undo_history(buffer: &Model<Buffer>, cx: &mut AppContext) -> Task<Result<()>> {
+    let buffer = buffer.read(cx);
+    let Some(path) = buffer
+        .file()
+        .and_then(|file| file.as_local())
+        .map(|file| file.abs_path(cx))
+    else {
+        return Task::ready(Ok(()));
+    };
+    if buffer.is_large_file() {
+        return Task::ready(Ok(()));
+    }
+    let history = buffer.serialize_undo_history(MAX_PERSISTED_UNDO_TRANSACTIONS, cx);
+    cx.background_executor().spawn(async move {
+        let history = history.await;
+        if history.transactions.is_empty() {
+            return DB.delete_undo_history(path).await;
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        DB.save_undo_history(path, serde_json::to_string(&history)?, now)
+            .await?;
+        DB.delete_undo_histories_saved_before(now - MAX_PERSISTED_UNDO_AGE.as_secs() as i64)
+            .await
+    })
+}
+
+/// Starts recording the buffer's undo history, and restores the history persisted for its
+/// file. The history is discarded if the file has changed since it was persisted.
+fn restore_undo_history(buffer: Model<Buffer>, cx: &mut AppContext) {
+    let Some(path) = buffer
+        .read(cx)
+        .file()
+        .and_then(|file| file.as_local())
+        .map(|file| file.abs_path(cx))
+    else {
+        return;
+    };
+    let started_recording = buffer.update(cx, |buffer, _| {
+        buffer.record_undo_history(MAX_PERSISTED_UNDO_BYTES)
+    });
+    if !started_recording {
+        return;
+    }
+
+    cx.spawn(|mut cx| async move {
+        let Some(history) = cx
+            .background_executor()
+            .spawn({
+                let path = path.clone();
+                async move { DB.get_undo_history(path) }
+            })
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let (text, version) = buffer.update(&mut cx, |buffer, _| {
+            (buffer.as_rope().clone(), buffer.version())
+        })?;
+        let history = cx
+            .background_executor()
+            .spawn(async move {
+                let history = serde_json::from_str::<SerializedUndoHistory>(&history).ok()?;
+                history.applies_to(&text).then_some(history)
+            })
+            .await;
+        let Some(history) = history else {
+            return DB.delete_undo_history(path).await;
+        };
+        buffer.update(&mut cx, |buffer, _| {
+            buffer.restore_undo_history(history, &version)
+        })?;
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
 impl EventEmitter<SearchEvent> for Editor {}
 
 pub(crate) enum BufferSearchHighlights {}
@@ -1266,6 +1531,14 @@ impl SearchableItem for Editor {
     ) {
         let text = self.buffer.read(cx);
         let text = text.snapshot(cx);
+        if query.is_structural() {
+            if let Some(replacement) = structural_replacement(&text, identifier, query) {
+                self.transact(cx, |this, cx| {
+                    this.edit([(identifier.clone(), Arc::from(replacement))], cx);
+                });
+            }
+            return;
+        }
         let text = text.text_for_range(identifier.clone()).collect::<Vec<_>>();
         let text: Cow<_> = if text.len() == 1 {
             text.first().cloned().unwrap().into()
@@ -1290,6 +1563,12 @@ impl SearchableItem for Editor {
         let text = text.snapshot(cx);
         let mut edits = vec![];
         for m in matches {
+            if query.is_structural() {
+                if let Some(replacement) = structural_replacement(&text, m, query) {
+                    edits.push((m.clone(), Arc::from(replacement)));
+                }
+                continue;
+            }
             let text = text.text_for_range(m.clone()).collect::<Vec<_>>();
             let text: Cow<_> = if text.len() == 1 {
                 text.first().cloned().unwrap().into()
@@ -1470,6 +1749,18 @@ pub fn active_match_index(
     }
 }
 
+/// Computes the replacement for a structural search match, which depends on the
+/// syntax tree of the buffer containing it rather than just the matched text.
+pub fn structural_replacement(
+    buffer: &MultiBufferSnapshot,
+    range: &Range<Anchor>,
+    query: &SearchQuery,
+) -> Option<String> {
+    let excerpt = buffer.excerpt_containing(range.clone())?;
+    let range = excerpt.map_range_to_buffer(range.to_offset(buffer));
+    query.replacement_for_range(excerpt.buffer(), range)
+}
+
 pub fn entry_label_color(selected: bool) -> Color {
     if selected {
         Color::Default
@@ -1589,6 +1880,18 @@ mod tests {
             .unwrap()
     }
 
+    async fn open_editor(
+        project: &Model<Project>,
+        path: &str,
+        cx: &mut VisualTestContext,
+    ) -> View<Editor> {
+        let buffer = project
+            .update(cx, |project, cx| project.open_local_buffer(path, cx))
+            .await
+            .unwrap();
+        cx.new_view(|cx| Editor::for_project_item(project.clone(), buffer, cx))
+    }
+
     fn rust_language() -> Arc<language::Language> {
         Arc::new(language::Language::new(
             language::LanguageConfig {
@@ -1743,4 +2046,55 @@ mod tests {
             });
         }
     }
+
+    #[gpui::test]
+    async fn test_restore_file_state(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let text = "one\ntwo\nthree\nfour\n";
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_file("/restored.rs", text.into()).await;
+        fs.insert_file("/changed.rs", text.into()).await;
+        let project = Project::test(fs.clone(), ["/".as_ref()], cx).await;
+        let (_workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+
+        let selections = vec![SerializedSelection {
+            start: 4,
+            end: 8,
+            reversed: true,
+        }];
+        let folds = vec![9..18];
+        let fingerprint = text_fingerprint(&text.into());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        for (path, fingerprint) in [
+            ("/restored.rs", fingerprint.clone()),
+            ("/changed.rs", "stale".to_string()),
+        ] {
+            let state = SerializedFileState {
+                fingerprint,
+                selections: selections.clone(),
+                folds: folds.clone(),
+            };
+            DB.save_file_state(path.into(), serde_json::to_string(&state).unwrap(), now)
+                .await
+                .unwrap();
+        }
+
+        // The selections and folds are restored if the file hasn't changed.
+        let editor = open_editor(&project, "/restored.rs", cx).await;
+        cx.run_until_parked();
+        editor.update(cx, |editor, cx| {
+            assert_eq!(editor.file_state(cx), (selections.clone(), folds.clone()));
+        });
+
+        // Otherwise only the selections are.
+        let editor = open_editor(&project, "/changed.rs", cx).await;
+        cx.run_until_parked();
+        editor.update(cx, |editor, cx| {
+            assert_eq!(editor.file_state(cx), (selections.clone(), Vec::new()));
+        });
+    }
 }
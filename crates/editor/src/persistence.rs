@@ -1,6 +1,8 @@
 use anyhow::Result;
 use db::sqlez::bindable::{Bind, Column, StaticColumnCount};
 use db::sqlez::statement::Statement;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use std::path::PathBuf;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -75,6 +77,22 @@ impl Column for SerializedEditor {
     }
 }
 
+/// The selections and folds of an editor for a file, restored when the file is reopened.
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) struct SerializedFileState {
+    /// The [`language::text_fingerprint`] of the file's contents when the state was saved.
+    pub(crate) fingerprint: String,
+    pub(crate) selections: Vec<SerializedSelection>,
+    pub(crate) folds: Vec<Range<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SerializedSelection {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) reversed: bool,
+}
+
 define_connection!(
     // Current schema shape using pseudo-rust syntax:
     // editors(
@@ -89,6 +107,18 @@ define_connection!(
     //   mtime_seconds: Option<i64>,
     //   mtime_nanos: Option<i32>,
     // )
+    //
+    // undo_histories(
+    //   path: PathBuf,
+    //   history: String, // JSON-serialized `language::SerializedUndoHistory`
+    //   saved_at: i64, // Seconds since the Unix epoch
+    // )
+    //
+    // editor_file_states(
+    //   path: PathBuf,
+    //   state: String, // JSON-serialized `SerializedFileState`
+    //   saved_at: i64, // Seconds since the Unix epoch
+    // )
     pub static ref DB: EditorDb<WorkspaceDb> =
         &[sql! (
             CREATE TABLE editors(
@@ -136,6 +166,23 @@ define_connection!(
             ALTER TABLE editors ADD COLUMN mtime_seconds INTEGER DEFAULT NULL;
             ALTER TABLE editors ADD COLUMN mtime_nanos INTEGER DEFAULT NULL;
         ),
+        sql! (
+            CREATE TABLE undo_histories(
+                path BLOB NOT NULL PRIMARY KEY,
+                history TEXT NOT NULL,
+                saved_at INTEGER NOT NULL
+            ) STRICT;
+        ),
+        sql! (
+            CREATE TABLE editor_file_states(
+                path BLOB NOT NULL PRIMARY KEY,
+                state TEXT NOT NULL
+            ) STRICT;
+        ),
+        sql! (
+            ALTER TABLE editor_file_states ADD COLUMN saved_at INTEGER NOT NULL DEFAULT 0;
+            CREATE INDEX editor_file_states_saved_at ON editor_file_states(saved_at);
+        ),
         ];
 );
 
@@ -190,6 +237,65 @@ impl EditorDb {
         }
     }
 
+    query! {
+        pub fn get_undo_history(path: PathBuf) -> Result<Option<String>> {
+            SELECT history FROM undo_histories
+            WHERE path = ?
+        }
+    }
+
+    query! {
+        pub async fn save_undo_history(path: PathBuf, history: String, saved_at: i64) -> Result<()> {
+            INSERT INTO undo_histories
+                (path, history, saved_at)
+            VALUES
+                (?1, ?2, ?3)
+            ON CONFLICT DO UPDATE SET
+                history = ?2,
+                saved_at = ?3
+        }
+    }
+
+    query! {
+        pub async fn delete_undo_history(path: PathBuf) -> Result<()> {
+            DELETE FROM undo_histories
+            WHERE path = ?
+        }
+    }
+
+    query! {
+        pub async fn delete_undo_histories_saved_before(saved_at: i64) -> Result<()> {
+            DELETE FROM undo_histories
+            WHERE saved_at < ?
+        }
+    }
+
+    query! {
+        pub fn get_file_state(path: PathBuf) -> Result<Option<String>> {
+            SELECT state FROM editor_file_states
+            WHERE path = ?
+        }
+    }
+
+    query! {
+        pub async fn save_file_state(path: PathBuf, state: String, saved_at: i64) -> Result<()> {
+            INSERT INTO editor_file_states
+                (path, state, saved_at)
+            VALUES
+                (?1, ?2, ?3)
+            ON CONFLICT DO UPDATE SET
+                state = ?2,
+                saved_at = ?3
+        }
+    }
+
+    query! {
+        pub async fn delete_file_states_saved_before(saved_at: i64) -> Result<()> {
+            DELETE FROM editor_file_states
+            WHERE saved_at < ?
+        }
+    }
+
     pub async fn delete_unloaded_items(
         &self,
         workspace: WorkspaceId,
@@ -297,4 +403,49 @@ mod tests {
             .unwrap();
         assert_eq!(have, serialized_editor);
     }
+
+    #[gpui::test]
+    async fn test_save_and_expire_undo_history() {
+        let path = PathBuf::from("/root/undo.txt");
+        DB.save_undo_history(path.clone(), "history".into(), 100)
+            .await
+            .unwrap();
+        assert_eq!(
+            DB.get_undo_history(path.clone()).unwrap(),
+            Some("history".into())
+        );
+
+        DB.save_undo_history(path.clone(), "newer history".into(), 200)
+            .await
+            .unwrap();
+        DB.delete_undo_histories_saved_before(150).await.unwrap();
+        assert_eq!(
+            DB.get_undo_history(path.clone()).unwrap(),
+            Some("newer history".into())
+        );
+
+        DB.delete_undo_histories_saved_before(250).await.unwrap();
+        assert_eq!(DB.get_undo_history(path).unwrap(), None);
+    }
+
+    #[gpui::test]
+    async fn test_save_and_expire_file_state() {
+        let path = PathBuf::from("/root/state.txt");
+        assert_eq!(DB.get_file_state(path.clone()).unwrap(), None);
+
+        DB.save_file_state(path.clone(), "state".into(), 100)
+            .await
+            .unwrap();
+        DB.save_file_state(path.clone(), "newer state".into(), 200)
+            .await
+            .unwrap();
+        DB.delete_file_states_saved_before(150).await.unwrap();
+        assert_eq!(
+            DB.get_file_state(path.clone()).unwrap(),
+            Some("newer state".into())
+        );
+
+        DB.delete_file_states_saved_before(250).await.unwrap();
+        assert_eq!(DB.get_file_state(path).unwrap(), None);
+    }
 }
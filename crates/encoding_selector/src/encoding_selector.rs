@@ -0,0 +1,227 @@
+mod active_buffer_encoding;
+
+pub use active_buffer_encoding::ActiveBufferEncoding;
+use anyhow::anyhow;
+use editor::Editor;
+use gpui::{
+    actions, AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Model,
+    ParentElement, Render, Styled, Task, View, ViewContext, VisualContext, WeakView,
+};
+use language::{Buffer, Encoding};
+use picker::{Picker, PickerDelegate};
+use project::Project;
+use std::sync::Arc;
+use ui::{prelude::*, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::{notifications::NotifyTaskExt, ModalView, Workspace};
+
+actions!(encoding_selector, [ReopenWithEncoding, SaveWithEncoding]);
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(EncodingSelector::register).detach();
+}
+
+/// What to do with the buffer once an encoding is picked.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Decode the file on disk again with the picked encoding.
+    Reopen,
+    /// Write the buffer to disk with the picked encoding.
+    Save,
+}
+
+pub struct EncodingSelector {
+    picker: View<Picker<EncodingSelectorDelegate>>,
+}
+
+impl EncodingSelector {
+    fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
+        workspace.register_action(move |workspace, _: &ReopenWithEncoding, cx| {
+            Self::toggle(workspace, Mode::Reopen, cx);
+        });
+        workspace.register_action(move |workspace, _: &SaveWithEncoding, cx| {
+            Self::toggle(workspace, Mode::Save, cx);
+        });
+    }
+
+    fn toggle(
+        workspace: &mut Workspace,
+        mode: Mode,
+        cx: &mut ViewContext<Workspace>,
+    ) -> Option<()> {
+        let buffer = workspace
+            .active_item(cx)?
+            .act_as::<Editor>(cx)?
+            .read(cx)
+            .buffer()
+            .read(cx)
+            .as_singleton()?;
+        // Remote buffers are decoded and encoded by the host.
+        buffer.read(cx).file()?.as_local()?;
+        let project = workspace.project().clone();
+
+        workspace.toggle_modal(cx, move |cx| {
+            EncodingSelector::new(buffer, project, mode, cx)
+        });
+        Some(())
+    }
+
+    fn new(
+        buffer: Model<Buffer>,
+        project: Model<Project>,
+        mode: Mode,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let current_encoding = buffer.read(cx).encoding();
+        let delegate = EncodingSelectorDelegate {
+            encoding_selector: cx.view().downgrade(),
+            buffer,
+            project,
+            mode,
+            matches: Encoding::ALL.to_vec(),
+            selected_index: Encoding::ALL
+                .iter()
+                .position(|encoding| *encoding == current_encoding)
+                .unwrap_or(0),
+        };
+
+        let picker = cx.new_view(|cx| Picker::uniform_list(delegate, cx));
+        Self { picker }
+    }
+}
+
+impl Render for EncodingSelector {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl FocusableView for EncodingSelector {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for EncodingSelector {}
+impl ModalView for EncodingSelector {}
+
+pub struct EncodingSelectorDelegate {
+    encoding_selector: WeakView<EncodingSelector>,
+    buffer: Model<Buffer>,
+    project: Model<Project>,
+    mode: Mode,
+    matches: Vec<Encoding>,
+    selected_index: usize,
+}
+
+impl EncodingSelectorDelegate {
+    fn reopen(&self, encoding: Encoding, cx: &mut AppContext) -> Task<anyhow::Result<()>> {
+        if self.buffer.read(cx).is_dirty() {
+            return Task::ready(Err(anyhow!(
+                "Save or discard your changes before reopening the file with another encoding"
+            )));
+        }
+
+        let reloaded = self
+            .buffer
+            .update(cx, |buffer, cx| buffer.reload_with_encoding(encoding, cx));
+        cx.spawn(|_| async move {
+            // The reload is abandoned if the file isn't valid in the given encoding.
+            reloaded
+                .await
+                .map_err(|_| anyhow!("The file can't be decoded as {encoding}"))?;
+            Ok(())
+        })
+    }
+
+    fn save(&self, encoding: Encoding, cx: &mut AppContext) -> Task<anyhow::Result<()>> {
+        let buffer = self.buffer.clone();
+        let previous_encoding = buffer.read(cx).encoding();
+        buffer.update(cx, |buffer, cx| buffer.set_encoding(encoding, cx));
+        let save = self
+            .project
+            .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx));
+        cx.spawn(|mut cx| async move {
+            if let Err(error) = save.await {
+                buffer.update(&mut cx, |buffer, cx| {
+                    buffer.set_encoding(previous_encoding, cx)
+                })?;
+                return Err(error);
+            }
+            Ok(())
+        })
+    }
+}
+
+impl PickerDelegate for EncodingSelectorDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _cx: &mut WindowContext) -> Arc<str> {
+        match self.mode {
+            Mode::Reopen => "Reopen with encoding...".into(),
+            Mode::Save => "Save with encoding...".into(),
+        }
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn confirm(&mut self, _: bool, cx: &mut ViewContext<Picker<Self>>) {
+        if let Some(encoding) = self.matches.get(self.selected_index).copied() {
+            let task = match self.mode {
+                Mode::Reopen => self.reopen(encoding, cx),
+                Mode::Save => self.save(encoding, cx),
+            };
+            task.detach_and_notify_err(cx);
+        }
+        self.dismissed(cx);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        self.encoding_selector
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, _: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let query = query.to_lowercase();
+        self.matches = Encoding::ALL
+            .into_iter()
+            .filter(|encoding| encoding.name().to_lowercase().contains(&query))
+            .collect();
+        self.selected_index = self
+            .selected_index
+            .min(self.matches.len().saturating_sub(1));
+        Task::ready(())
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let encoding = *self.matches.get(ix)?;
+        let mut label = encoding.name().to_string();
+        if self.buffer.read(cx).encoding() == encoding {
+            label.push_str(" (current)");
+        }
+
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(Label::new(label)),
+        )
+    }
+}
@@ -0,0 +1,497 @@
+mod persistence;
+
+use anyhow::{anyhow, Result};
+use collections::HashSet;
+use editor::{Editor, EditorMode};
+use gpui::{
+    actions, rems, AppContext, DismissEvent, EntityId, EventEmitter, FocusHandle, FocusableView,
+    Global, Model, ParentElement, Render, Styled, Task, View, ViewContext, VisualContext, WeakView,
+    WindowContext,
+};
+use language::{text_fingerprint, Buffer};
+use persistence::DB;
+use picker::{Picker, PickerDelegate};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use time::{OffsetDateTime, UtcOffset};
+use time_format::TimestampFormat;
+use ui::{prelude::*, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+actions!(file_history, [ToggleTimeline]);
+
+/// Files larger than this aren't snapshotted.
+const MAX_SNAPSHOT_LEN: usize = 4 * 1024 * 1024;
+const MAX_SNAPSHOTS_PER_FILE: usize = 100;
+const MAX_SNAPSHOT_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+/// The oldest snapshots of any file are discarded once the compressed contents of all the
+/// snapshots take up more than this many bytes.
+const MAX_TOTAL_SNAPSHOTS_LEN: usize = 64 * 1024 * 1024;
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(FileTimeline::register).detach();
+}
+
+/// The buffers whose saves are being snapshotted.
+#[derive(Default)]
+struct TrackedBuffers(HashSet<EntityId>);
+
+impl Global for TrackedBuffers {}
+
+/// Snapshots the buffer when it's first opened in an editor and whenever it's saved or
+/// reloaded, however many editors it's open in.
+fn track_buffer(buffer: &Model<Buffer>, cx: &mut AppContext) {
+    let buffer_id = buffer.entity_id();
+    if !cx.default_global::<TrackedBuffers>().0.insert(buffer_id) {
+        return;
+    }
+
+    snapshot_buffer(buffer.read(cx), cx);
+    cx.subscribe(buffer, |buffer, event, cx| {
+        if let language::Event::Saved | language::Event::Reloaded = event {
+            snapshot_buffer(buffer.read(cx), cx);
+        }
+    })
+    .detach();
+    cx.observe_release(buffer, move |_, cx| {
+        cx.default_global::<TrackedBuffers>().0.remove(&buffer_id);
+    })
+    .detach();
+}
+
+/// Stores a snapshot of the buffer's contents, if it's backed by a local file whose
+/// contents it matches and they differ from the file's latest snapshot.
+fn snapshot_buffer(buffer: &Buffer, cx: &AppContext) {
+    let Some(file) = buffer.file().and_then(|file| file.as_local()) else {
+        return;
+    };
+    if buffer.is_dirty() || buffer.is_large_file() || buffer.len() > MAX_SNAPSHOT_LEN {
+        return;
+    }
+
+    let path = file.abs_path(cx);
+    let text = buffer.as_rope().clone();
+    cx.background_executor()
+        .spawn(async move {
+            let fingerprint = text_fingerprint(&text);
+            if DB.get_latest_fingerprint(path.clone())?.as_ref() == Some(&fingerprint) {
+                return Ok(());
+            }
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+            DB.save_snapshot(
+                path,
+                fingerprint,
+                text.to_string(),
+                now,
+                MAX_SNAPSHOTS_PER_FILE,
+            )
+            .await?;
+            DB.prune_snapshots(
+                now - MAX_SNAPSHOT_AGE.as_secs() as i64,
+                MAX_TOTAL_SNAPSHOTS_LEN,
+            )
+            .await
+        })
+        .detach_and_log_err(cx);
+}
+
+pub struct FileTimeline {
+    picker: View<Picker<FileTimelineDelegate>>,
+}
+
+impl FocusableView for FileTimeline {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for FileTimeline {}
+impl ModalView for FileTimeline {}
+
+impl Render for FileTimeline {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl FileTimeline {
+    fn register(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+        if editor.mode() != EditorMode::Full {
+            return;
+        }
+        let Some(buffer) = editor.buffer().read(cx).as_singleton() else {
+            return;
+        };
+
+        track_buffer(&buffer, cx);
+
+        let handle = cx.view().downgrade();
+        editor
+            .register_action(move |_: &ToggleTimeline, cx| {
+                if let Some(editor) = handle.upgrade() {
+                    Self::toggle(editor, cx).detach_and_log_err(cx);
+                }
+            })
+            .detach();
+    }
+
+    fn toggle(editor: View<Editor>, cx: &mut WindowContext) -> Task<Result<()>> {
+        match Self::timeline_target(&editor, cx) {
+            Ok((workspace, buffer, path)) => {
+                let text = buffer.read(cx).as_rope().clone();
+                cx.spawn(|mut cx| async move {
+                    let snapshots = cx
+                        .background_executor()
+                        .spawn({
+                            let path = path.clone();
+                            async move { Self::snapshot_entries(path, text_fingerprint(&text)) }
+                        })
+                        .await?;
+                    workspace.update(&mut cx, |workspace, cx| {
+                        let workspace_handle = cx.view().downgrade();
+                        workspace.toggle_modal(cx, |cx| {
+                            let delegate = FileTimelineDelegate {
+                                timeline: cx.view().downgrade(),
+                                workspace: workspace_handle,
+                                editor,
+                                buffer,
+                                path,
+                                snapshots,
+                                matches: Vec::new(),
+                                selected_index: 0,
+                            };
+                            let picker = cx.new_view(|cx| Picker::uniform_list(delegate, cx));
+                            FileTimeline { picker }
+                        });
+                    })
+                })
+            }
+            Err(error) => Task::ready(Err(error)),
+        }
+    }
+
+    /// Returns the workspace, buffer and path of the file whose timeline the editor shows.
+    fn timeline_target(
+        editor: &View<Editor>,
+        cx: &WindowContext,
+    ) -> Result<(View<Workspace>, Model<Buffer>, PathBuf)> {
+        let workspace = editor
+            .read(cx)
+            .workspace()
+            .ok_or_else(|| anyhow!("editor is not part of a workspace"))?;
+        let buffer = editor
+            .read(cx)
+            .buffer()
+            .read(cx)
+            .as_singleton()
+            .ok_or_else(|| anyhow!("timeline requires a singleton buffer"))?;
+        let path = buffer
+            .read(cx)
+            .file()
+            .and_then(|file| file.as_local())
+            .map(|file| file.abs_path(cx))
+            .ok_or_else(|| anyhow!("timeline requires a local file"))?;
+        Ok((workspace, buffer, path))
+    }
+
+    fn snapshot_entries(path: PathBuf, current_fingerprint: String) -> Result<Vec<SnapshotEntry>> {
+        let now = OffsetDateTime::now_utc();
+        let local_offset = chrono::Local::now().offset().local_minus_utc();
+        let timezone = UtcOffset::from_whole_seconds(local_offset)?;
+        DB.get_snapshots(path)?
+            .into_iter()
+            .map(|(id, fingerprint, saved_at)| {
+                let saved_at = OffsetDateTime::from_unix_timestamp(saved_at)?;
+                Ok(SnapshotEntry {
+                    id,
+                    is_current: fingerprint == current_fingerprint,
+                    label: time_format::format_localized_timestamp(
+                        saved_at,
+                        now,
+                        timezone,
+                        TimestampFormat::EnhancedAbsolute,
+                    )
+                    .into(),
+                    relative_label: time_format::format_localized_timestamp(
+                        saved_at,
+                        now,
+                        timezone,
+                        TimestampFormat::Relative,
+                    )
+                    .into(),
+                })
+            })
+            .collect()
+    }
+}
+
+struct SnapshotEntry {
+    id: i64,
+    /// Whether the snapshot's contents match the buffer's current text.
+    is_current: bool,
+    label: SharedString,
+    relative_label: SharedString,
+}
+
+struct FileTimelineDelegate {
+    timeline: WeakView<FileTimeline>,
+    workspace: WeakView<Workspace>,
+    editor: View<Editor>,
+    buffer: Model<Buffer>,
+    path: PathBuf,
+    /// The file's snapshots, newest first.
+    snapshots: Vec<SnapshotEntry>,
+    matches: Vec<usize>,
+    selected_index: usize,
+}
+
+/// Opens a read-only editor showing the changes to the buffer since the given snapshot.
+fn open_snapshot_diff(
+    workspace: &View<Workspace>,
+    buffer: &Model<Buffer>,
+    path: &Path,
+    label: &str,
+    contents: String,
+    cx: &mut WindowContext,
+) {
+    let title = format!(
+        "{} (since {label})",
+        path.file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default(),
+    );
+    Editor::open_diff(workspace, buffer, contents, title, cx);
+}
+
+/// Replaces the buffer's text with the snapshot's contents, as a single undoable transaction.
+fn restore_snapshot(buffer: Model<Buffer>, contents: String, cx: &mut WindowContext) -> Task<()> {
+    let diff = buffer.read(cx).diff(contents, cx);
+    cx.spawn(|mut cx| async move {
+        let diff = diff.await;
+        buffer
+            .update(&mut cx, |buffer, cx| {
+                buffer.finalize_last_transaction();
+                buffer.apply_diff(diff, cx);
+                buffer.finalize_last_transaction();
+            })
+            .log_err();
+    })
+}
+
+impl PickerDelegate for FileTimelineDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _cx: &mut WindowContext) -> Arc<str> {
+        "Select a snapshot to diff, or secondary-confirm to restore it...".into()
+    }
+
+    fn no_matches_text(&self, _cx: &mut WindowContext) -> SharedString {
+        "No snapshots of this file".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, _: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let query = query.to_lowercase();
+        self.matches = self
+            .snapshots
+            .iter()
+            .enumerate()
+            .filter(|(_, snapshot)| {
+                snapshot.label.to_lowercase().contains(&query)
+                    || snapshot.relative_label.to_lowercase().contains(&query)
+            })
+            .map(|(ix, _)| ix)
+            .collect();
+        self.selected_index = 0;
+        Task::ready(())
+    }
+
+    fn confirm(&mut self, secondary: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(snapshot) = self
+            .matches
+            .get(self.selected_index)
+            .and_then(|ix| self.snapshots.get(*ix))
+        else {
+            return;
+        };
+        let id = snapshot.id;
+        let label = snapshot.label.clone();
+        let contents = cx
+            .background_executor()
+            .spawn(async move { DB.get_snapshot_contents(id) });
+        cx.spawn(|picker, mut cx| async move {
+            let Some(contents) = contents.await? else {
+                return Ok(());
+            };
+            picker.update(&mut cx, |picker, cx| {
+                let delegate = &picker.delegate;
+                if secondary {
+                    restore_snapshot(delegate.buffer.clone(), contents, cx).detach();
+                    delegate.editor.focus_handle(cx).focus(cx);
+                } else if let Some(workspace) = delegate.workspace.upgrade() {
+                    open_snapshot_diff(
+                        &workspace,
+                        &delegate.buffer,
+                        &delegate.path,
+                        &label,
+                        contents,
+                        cx,
+                    );
+                }
+                delegate
+                    .timeline
+                    .update(cx, |_, cx| cx.emit(DismissEvent))
+                    .log_err();
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        self.timeline
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let snapshot = self.snapshots.get(*self.matches.get(ix)?)?;
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .child(Label::new(snapshot.label.clone()))
+                        .child(
+                            Label::new(snapshot.relative_label.clone())
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        ),
+                )
+                .when(snapshot.is_current, |item| {
+                    item.end_slot(
+                        Label::new("Current")
+                            .size(LabelSize::Small)
+                            .color(Color::Accent),
+                    )
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::Project;
+    use serde_json::json;
+    use workspace::AppState;
+
+    #[gpui::test]
+    async fn test_open_snapshot_diff(cx: &mut TestAppContext) {
+        let (workspace, buffer, cx) = init_test(cx).await;
+
+        cx.update(|cx| {
+            open_snapshot_diff(
+                &workspace,
+                &buffer,
+                Path::new("/root/a.txt"),
+                "yesterday",
+                "one\nTWO\nthree\n".into(),
+                cx,
+            )
+        });
+        cx.run_until_parked();
+
+        let diff_editor = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_item_as::<Editor>(cx)
+                .expect("the diff should be the active item")
+        });
+        diff_editor.update(cx, |editor, cx| {
+            assert!(editor.read_only(cx));
+            let multibuffer = editor.buffer().read(cx);
+            assert_eq!(multibuffer.title(cx), "a.txt (since yesterday)");
+
+            let diff_buffer = multibuffer.as_singleton().unwrap().read(cx);
+            assert_eq!(diff_buffer.text(), "one\ntwo\nthree\nfour\n");
+            let snapshot = diff_buffer.snapshot();
+            let hunk_rows = snapshot
+                .git_diff_hunks_in_row_range(0..u32::MAX)
+                .map(|hunk| hunk.associated_range)
+                .collect::<Vec<_>>();
+            assert_eq!(hunk_rows, [1..2, 3..4]);
+        });
+        // The diff is a copy, so the file's own buffer is left alone.
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(buffer.text(), "one\ntwo\nthree\nfour\n");
+            assert!(!buffer.is_dirty());
+        });
+    }
+
+    #[gpui::test]
+    async fn test_restore_snapshot(cx: &mut TestAppContext) {
+        let (_, buffer, cx) = init_test(cx).await;
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "zero\n")], None, cx));
+
+        cx.update(|cx| restore_snapshot(buffer.clone(), "one\nTWO\n".into(), cx))
+            .await;
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(buffer.text(), "one\nTWO\n");
+            assert!(buffer.is_dirty());
+        });
+
+        // Undoing the restore brings back everything it replaced at once, and no more.
+        buffer.update(cx, |buffer, cx| buffer.undo(cx));
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(buffer.text(), "zero\none\ntwo\nthree\nfour\n");
+        });
+    }
+
+    /// Opens `/root/a.txt` in a workspace, without tracking it, so the tests don't write
+    /// snapshots to the shared database.
+    async fn init_test(
+        cx: &mut TestAppContext,
+    ) -> (View<Workspace>, Model<Buffer>, &mut VisualTestContext) {
+        cx.update(|cx| {
+            AppState::test(cx);
+            language::init(cx);
+            Project::init_settings(cx);
+            editor::init(cx);
+        });
+        let fs = fs::FakeFs::new(cx.executor());
+        fs.insert_tree("/root", json!({ "a.txt": "one\ntwo\nthree\nfour\n" }))
+            .await;
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/a.txt", cx)
+            })
+            .await
+            .unwrap();
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        (workspace, buffer, cx)
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use db::{define_connection, query, sqlez_macros::sql};
+
+/// The metadata of a stored snapshot, as stored in the database: `(id, fingerprint, saved_at)`,
+/// where `saved_at` is in seconds since the Unix epoch.
+pub(crate) type SnapshotMetadata = (i64, String, i64);
+
+define_connection! {
+    // Current schema shape using pseudo-rust syntax:
+    // file_snapshots(
+    //   id: i64,
+    //   path: PathBuf,
+    //   fingerprint: String, // `language::text_fingerprint` of the contents
+    //   contents: Vec<u8>, // The contents, compressed with zstd
+    //   saved_at: i64, // Seconds since the Unix epoch
+    // )
+    pub static ref DB: FileHistoryDb<()> =
+        &[
+            sql!(
+                CREATE TABLE file_snapshots (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    path BLOB NOT NULL,
+                    fingerprint TEXT NOT NULL,
+                    contents TEXT NOT NULL,
+                    saved_at INTEGER NOT NULL
+                ) STRICT;
+
+                CREATE INDEX file_snapshots_by_path ON file_snapshots (path, id);
+            ),
+            // Snapshots are compressed, and the uncompressed ones are discarded.
+            sql!(
+                DROP TABLE file_snapshots;
+
+                CREATE TABLE file_snapshots (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    path BLOB NOT NULL,
+                    fingerprint TEXT NOT NULL,
+                    contents BLOB NOT NULL,
+                    saved_at INTEGER NOT NULL
+                ) STRICT;
+
+                CREATE INDEX file_snapshots_by_path ON file_snapshots (path, id);
+                CREATE INDEX file_snapshots_by_saved_at ON file_snapshots (saved_at);
+            ),
+        ];
+}
+
+/// The zstd compression level of the stored snapshots.
+const COMPRESSION_LEVEL: i32 = 3;
+
+impl FileHistoryDb {
+    query! {
+        pub fn get_snapshots(path: PathBuf) -> Result<Vec<SnapshotMetadata>> {
+            SELECT id, fingerprint, saved_at
+            FROM file_snapshots
+            WHERE path = ?
+            ORDER BY id DESC
+        }
+    }
+
+    query! {
+        pub fn get_latest_fingerprint(path: PathBuf) -> Result<Option<String>> {
+            SELECT fingerprint
+            FROM file_snapshots
+            WHERE path = ?
+            ORDER BY id DESC
+            LIMIT 1
+        }
+    }
+
+    query! {
+        fn get_compressed_contents(id: i64) -> Result<Option<Vec<u8>>> {
+            SELECT contents
+            FROM file_snapshots
+            WHERE id = ?
+        }
+    }
+
+    pub fn get_snapshot_contents(&self, id: i64) -> Result<Option<String>> {
+        let Some(compressed) = self.get_compressed_contents(id)? else {
+            return Ok(None);
+        };
+        let contents = zstd::stream::decode_all(compressed.as_slice())?;
+        Ok(Some(String::from_utf8(contents)?))
+    }
+
+    /// Stores a snapshot of the file at the given path, unless its latest snapshot already has
+    /// the same contents. Only the `max_snapshots` most recent snapshots are kept for each file.
+    pub async fn save_snapshot(
+        &self,
+        path: PathBuf,
+        fingerprint: String,
+        contents: String,
+        saved_at: i64,
+        max_snapshots: usize,
+    ) -> Result<()> {
+        let contents = zstd::stream::encode_all(contents.as_bytes(), COMPRESSION_LEVEL)?;
+        self.write(move |conn| {
+            conn.with_savepoint("save_snapshot", || {
+                conn.exec_bound(sql!(
+                    INSERT INTO file_snapshots
+                        (path, fingerprint, contents, saved_at)
+                    SELECT ?1, ?2, ?3, ?4
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM (
+                            SELECT fingerprint FROM file_snapshots
+                            WHERE path = ?1
+                            ORDER BY id DESC
+                            LIMIT 1
+                        )
+                        WHERE fingerprint = ?2
+                    )
+                ))?((path.clone(), fingerprint, contents, saved_at))?;
+
+                conn.exec_bound(sql!(
+                    DELETE FROM file_snapshots
+                    WHERE path = ?1 AND id NOT IN (
+                        SELECT id FROM file_snapshots
+                        WHERE path = ?1
+                        ORDER BY id DESC
+                        LIMIT ?2
+                    )
+                ))?((path, max_snapshots as i64))
+            })
+        })
+        .await
+    }
+
+    /// Discards the snapshots of every file saved before `expired_before`, and then the oldest
+    /// snapshots until the compressed contents of those left take up at most `max_total_len`
+    /// bytes.
+    pub async fn prune_snapshots(&self, expired_before: i64, max_total_len: usize) -> Result<()> {
+        self.write(move |conn| {
+            conn.with_savepoint("prune_snapshots", || {
+                conn.exec_bound(sql!(
+                    DELETE FROM file_snapshots WHERE saved_at < ?
+                ))?(expired_before)?;
+
+                conn.exec_bound(sql!(
+                    DELETE FROM file_snapshots
+                    WHERE id IN (
+                        SELECT id FROM (
+                            SELECT
+                                id,
+                                SUM(LENGTH(contents)) OVER (
+                                    ORDER BY saved_at DESC, id DESC
+                                ) AS total_len
+                            FROM file_snapshots
+                        )
+                        WHERE total_len > ?
+                    )
+                ))?(i64::try_from(max_total_len).unwrap_or(i64::MAX))
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[gpui::test]
+    async fn test_save_and_get_snapshots() {
+        let path = PathBuf::from("/root/a.rs");
+        let save = |contents: &str, saved_at| {
+            DB.save_snapshot(
+                path.clone(),
+                format!("fingerprint of {contents}"),
+                contents.to_string(),
+                saved_at,
+                2,
+            )
+        };
+
+        // Snapshots are stored compressed.
+        let compressed_path = PathBuf::from("/root/compressed.txt");
+        let contents = "fn main() {}\n".repeat(10_000);
+        DB.save_snapshot(
+            compressed_path.clone(),
+            "fingerprint".into(),
+            contents.clone(),
+            90,
+            1,
+        )
+        .await
+        .unwrap();
+        let id = DB.get_snapshots(compressed_path).unwrap()[0].0;
+        assert!(DB.get_compressed_contents(id).unwrap().unwrap().len() < contents.len() / 10);
+        assert_eq!(DB.get_snapshot_contents(id).unwrap(), Some(contents));
+
+        save("one", 100).await.unwrap();
+        // Saving the same contents again doesn't create a new snapshot.
+        save("one", 110).await.unwrap();
+        save("two", 120).await.unwrap();
+        let snapshots = DB.get_snapshots(path.clone()).unwrap();
+        assert_eq!(
+            snapshots
+                .iter()
+                .map(|(_, fingerprint, saved_at)| (fingerprint.as_str(), *saved_at))
+                .collect::<Vec<_>>(),
+            [("fingerprint of two", 120), ("fingerprint of one", 100)]
+        );
+        assert_eq!(
+            DB.get_latest_fingerprint(path.clone()).unwrap().as_deref(),
+            Some("fingerprint of two")
+        );
+        assert_eq!(
+            DB.get_snapshot_contents(snapshots[1].0).unwrap().as_deref(),
+            Some("one")
+        );
+
+        // Only the most recent snapshots are kept.
+        save("three", 130).await.unwrap();
+        let snapshots = DB.get_snapshots(path.clone()).unwrap();
+        assert_eq!(
+            snapshots
+                .iter()
+                .map(|(_, _, saved_at)| *saved_at)
+                .collect::<Vec<_>>(),
+            [130, 120]
+        );
+
+        // Expired snapshots are discarded, whichever file they're of.
+        DB.save_snapshot(
+            PathBuf::from("/root/b.rs"),
+            "fingerprint of four".into(),
+            "four".into(),
+            140,
+            2,
+        )
+        .await
+        .unwrap();
+        DB.prune_snapshots(125, usize::MAX).await.unwrap();
+        assert_eq!(DB.get_snapshots(path.clone()).unwrap().len(), 1);
+        assert_eq!(
+            DB.get_snapshots(PathBuf::from("/root/b.rs")).unwrap().len(),
+            1
+        );
+
+        // The oldest snapshots are discarded once they take up too much space.
+        let compressed_len = |id| DB.get_compressed_contents(id).unwrap().unwrap().len();
+        let newest_id = DB.get_snapshots(PathBuf::from("/root/b.rs")).unwrap()[0].0;
+        DB.prune_snapshots(0, compressed_len(newest_id))
+            .await
+            .unwrap();
+        assert!(DB.get_snapshots(path).unwrap().is_empty());
+        assert_eq!(
+            DB.get_snapshot_contents(newest_id).unwrap().as_deref(),
+            Some("four")
+        );
+    }
+}
@@ -0,0 +1,1029 @@
+mod hex_prompt;
+mod search;
+
+use anyhow::{anyhow, Result};
+use file_icons::FileIcons;
+use fs::Fs;
+use gpui::{
+    actions, div, uniform_list, AnyElement, AppContext, EventEmitter, FocusHandle, FocusableView,
+    InteractiveElement, IntoElement, KeyDownEvent, Model, ModelContext, MouseButton, ParentElement,
+    Render, Styled, Subscription, Task, UniformListScrollHandle, View, ViewContext, VisualContext,
+    WeakView, WindowContext,
+};
+use hex_prompt::{HexPrompt, PromptKind};
+use project::{Project, ProjectEntryId, ProjectPath};
+use settings::Settings;
+use std::{ops::Range, path::PathBuf, pin::Pin, sync::Arc};
+use ui::prelude::*;
+use workspace::{
+    item::{Item, ItemEvent, ProjectItem, TabContentParams},
+    ItemSettings, Workspace, WorkspaceId,
+};
+
+actions!(
+    hex_editor,
+    [
+        OpenInHexEditor,
+        MoveLeft,
+        MoveRight,
+        MoveUp,
+        MoveDown,
+        PageUp,
+        PageDown,
+        MoveToBeginning,
+        MoveToEnd,
+        Backspace,
+        Delete,
+        Undo,
+        Redo,
+        ToggleInsertMode,
+        SwitchColumn,
+        GoToOffset,
+        FindBytes,
+        FindNext,
+    ]
+);
+
+const BYTES_PER_ROW: usize = 16;
+const ROWS_PER_PAGE: usize = 32;
+
+/// Registers the hex editor as a way of opening files.
+///
+/// This must be called before any other item that opens files is registered (e.g. the
+/// editor), so that it's only used for files which none of the other items can open,
+/// such as files that aren't valid UTF-8.
+pub fn init(cx: &mut AppContext) {
+    workspace::register_project_item::<HexEditor>(cx);
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(HexEditor::open_active_item);
+    })
+    .detach();
+}
+
+/// The raw contents of a file, as edited in a [`HexEditor`].
+pub struct HexFile {
+    abs_path: PathBuf,
+    project_path: ProjectPath,
+    entry_id: Option<ProjectEntryId>,
+    data: Vec<u8>,
+    version: usize,
+    saved_version: usize,
+    undo_stack: Vec<HexEdit>,
+    redo_stack: Vec<HexEdit>,
+}
+
+/// An edit made to a [`HexFile`], which can be undone.
+struct HexEdit {
+    offset: usize,
+    old_bytes: Vec<u8>,
+    new_bytes: Vec<u8>,
+}
+
+pub enum HexFileEvent {
+    Edited,
+    Saved,
+}
+
+impl EventEmitter<HexFileEvent> for HexFile {}
+
+impl project::Item for HexFile {
+    fn try_open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Option<Task<Result<Model<Self>>>> {
+        // Files of remote projects can't be read through the local file system.
+        if !project.read(cx).is_local() {
+            return None;
+        }
+        Some(Self::open(project, path, cx))
+    }
+
+    fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
+        self.entry_id
+    }
+
+    fn project_path(&self, _: &AppContext) -> Option<ProjectPath> {
+        Some(self.project_path.clone())
+    }
+}
+
+impl HexFile {
+    pub fn open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Task<Result<Model<Self>>> {
+        let project = project.read(cx);
+        let fs = project.fs().clone();
+        let abs_path = project.absolute_path(path, cx);
+        let entry_id = project.entry_for_path(path, cx).map(|entry| entry.id);
+        let project_path = path.clone();
+        cx.spawn(|mut cx| async move {
+            let abs_path = abs_path.ok_or_else(|| anyhow!("failed to find the absolute path"))?;
+            let data = fs.load_bytes(&abs_path).await?;
+            cx.new_model(|_| Self {
+                abs_path,
+                project_path,
+                entry_id,
+                data,
+                version: 0,
+                saved_version: 0,
+                undo_stack: Vec::new(),
+                redo_stack: Vec::new(),
+            })
+        })
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.version != self.saved_version
+    }
+
+    /// Replaces the bytes in the given range.
+    pub fn edit(&mut self, range: Range<usize>, bytes: &[u8], cx: &mut ModelContext<Self>) {
+        let offset = range.start;
+        let old_bytes = self.data.splice(range, bytes.iter().copied()).collect();
+        self.undo_stack.push(HexEdit {
+            offset,
+            old_bytes,
+            new_bytes: bytes.to_vec(),
+        });
+        self.redo_stack.clear();
+        self.edited(cx);
+    }
+
+    /// Reverts the last edit, returning the offset it was made at.
+    pub fn undo(&mut self, cx: &mut ModelContext<Self>) -> Option<usize> {
+        let edit = self.undo_stack.pop()?;
+        let offset = edit.offset;
+        self.data.splice(
+            offset..offset + edit.new_bytes.len(),
+            edit.old_bytes.iter().copied(),
+        );
+        self.redo_stack.push(edit);
+        self.edited(cx);
+        Some(offset)
+    }
+
+    /// Makes the last undone edit again, returning the offset it was made at.
+    pub fn redo(&mut self, cx: &mut ModelContext<Self>) -> Option<usize> {
+        let edit = self.redo_stack.pop()?;
+        let offset = edit.offset;
+        self.data.splice(
+            offset..offset + edit.old_bytes.len(),
+            edit.new_bytes.iter().copied(),
+        );
+        self.undo_stack.push(edit);
+        self.edited(cx);
+        Some(offset)
+    }
+
+    fn edited(&mut self, cx: &mut ModelContext<Self>) {
+        self.version += 1;
+        cx.emit(HexFileEvent::Edited);
+        cx.notify();
+    }
+
+    pub fn save(&mut self, fs: Arc<dyn Fs>, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
+        let abs_path = self.abs_path.clone();
+        self.write(fs, abs_path, cx)
+    }
+
+    pub fn save_as(
+        &mut self,
+        fs: Arc<dyn Fs>,
+        abs_path: PathBuf,
+        project_path: ProjectPath,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<()>> {
+        let write = self.write(fs, abs_path.clone(), cx);
+        cx.spawn(|this, mut cx| async move {
+            write.await?;
+            this.update(&mut cx, |this, cx| {
+                this.abs_path = abs_path;
+                this.project_path = project_path;
+                // The entry is only known once the worktree picks up the new file.
+                this.entry_id = None;
+                cx.notify();
+            })
+        })
+    }
+
+    pub fn reload(&mut self, fs: Arc<dyn Fs>, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
+        let abs_path = self.abs_path.clone();
+        cx.spawn(|this, mut cx| async move {
+            let data = fs.load_bytes(&abs_path).await?;
+            this.update(&mut cx, |this, cx| {
+                this.data = data;
+                // The edits can't be undone, since they may not apply to the new contents.
+                this.undo_stack.clear();
+                this.redo_stack.clear();
+                this.version += 1;
+                this.saved_version = this.version;
+                cx.emit(HexFileEvent::Edited);
+                cx.emit(HexFileEvent::Saved);
+                cx.notify();
+            })
+        })
+    }
+
+    fn write(
+        &mut self,
+        fs: Arc<dyn Fs>,
+        abs_path: PathBuf,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<()>> {
+        let data = self.data.clone();
+        let version = self.version;
+        cx.spawn(|this, mut cx| async move {
+            let mut content = futures::io::Cursor::new(data);
+            fs.create_file_with(&abs_path, Pin::new(&mut content))
+                .await?;
+            this.update(&mut cx, |this, cx| {
+                this.saved_version = version;
+                cx.emit(HexFileEvent::Saved);
+                cx.notify();
+            })
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    Hex,
+    Ascii,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditMode {
+    Overwrite,
+    Insert,
+}
+
+pub enum Event {
+    Edited,
+    Saved,
+}
+
+/// A view of a file's raw bytes, with offset, hexadecimal and ASCII columns.
+pub struct HexEditor {
+    file: Model<HexFile>,
+    workspace: Option<WeakView<Workspace>>,
+    focus_handle: FocusHandle,
+    scroll_handle: UniformListScrollHandle,
+    /// The offset of the byte under the cursor, which can be one past the last byte.
+    cursor: usize,
+    column: Column,
+    mode: EditMode,
+    /// Whether the high nibble of the byte under the cursor has just been typed.
+    editing_low_nibble: bool,
+    last_search: Option<Vec<u8>>,
+    _subscription: Subscription,
+}
+
+impl HexEditor {
+    fn new(file: Model<HexFile>, cx: &mut ViewContext<Self>) -> Self {
+        let subscription = cx.subscribe(&file, |this, _, event, cx| {
+            match event {
+                HexFileEvent::Edited => {
+                    this.cursor = this.cursor.min(this.len(cx));
+                    cx.emit(Event::Edited);
+                }
+                HexFileEvent::Saved => cx.emit(Event::Saved),
+            }
+            cx.notify();
+        });
+        Self {
+            file,
+            workspace: None,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: UniformListScrollHandle::new(),
+            cursor: 0,
+            column: Column::Hex,
+            mode: EditMode::Overwrite,
+            editing_low_nibble: false,
+            last_search: None,
+            _subscription: subscription,
+        }
+    }
+
+    /// Opens the file of the active item in a hex editor.
+    fn open_active_item(
+        workspace: &mut Workspace,
+        _: &OpenInHexEditor,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let Some(project_path) = workspace
+            .active_item(cx)
+            .and_then(|item| item.project_path(cx))
+        else {
+            return;
+        };
+        let project = workspace.project().clone();
+        let open = HexFile::open(&project, &project_path, cx);
+        cx.spawn(|workspace, mut cx| async move {
+            let file = open.await?;
+            workspace.update(&mut cx, |workspace, cx| {
+                let pane = workspace.active_pane().clone();
+                workspace.open_project_item::<HexEditor>(pane, file, true, true, cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn len(&self, cx: &AppContext) -> usize {
+        self.file.read(cx).len()
+    }
+
+    pub fn mode(&self) -> EditMode {
+        self.mode
+    }
+
+    /// Moves the cursor to the given offset, scrolling it into view.
+    pub fn go_to_offset(&mut self, offset: usize, cx: &mut ViewContext<Self>) {
+        self.cursor = offset.min(self.len(cx));
+        self.editing_low_nibble = false;
+        self.scroll_handle
+            .scroll_to_item(self.cursor / BYTES_PER_ROW);
+        cx.notify();
+    }
+
+    /// Moves the cursor to the next occurrence of the given bytes after it, wrapping around
+    /// to the beginning of the file. Returns whether the bytes were found.
+    pub fn find(&mut self, pattern: Vec<u8>, cx: &mut ViewContext<Self>) -> bool {
+        let found = search::find_bytes(self.file.read(cx).data(), &pattern, self.cursor + 1);
+        self.last_search = Some(pattern);
+        if let Some(offset) = found {
+            self.go_to_offset(offset, cx);
+        }
+        found.is_some()
+    }
+
+    fn move_by(&mut self, delta: isize, cx: &mut ViewContext<Self>) {
+        self.go_to_offset(self.cursor.saturating_add_signed(delta), cx);
+    }
+
+    fn move_left(&mut self, _: &MoveLeft, cx: &mut ViewContext<Self>) {
+        self.move_by(-1, cx);
+    }
+
+    fn move_right(&mut self, _: &MoveRight, cx: &mut ViewContext<Self>) {
+        self.move_by(1, cx);
+    }
+
+    fn move_up(&mut self, _: &MoveUp, cx: &mut ViewContext<Self>) {
+        if self.cursor >= BYTES_PER_ROW {
+            self.move_by(-(BYTES_PER_ROW as isize), cx);
+        }
+    }
+
+    fn move_down(&mut self, _: &MoveDown, cx: &mut ViewContext<Self>) {
+        if self.cursor + BYTES_PER_ROW <= self.len(cx) {
+            self.move_by(BYTES_PER_ROW as isize, cx);
+        }
+    }
+
+    fn page_up(&mut self, _: &PageUp, cx: &mut ViewContext<Self>) {
+        self.move_by(-((BYTES_PER_ROW * ROWS_PER_PAGE) as isize), cx);
+    }
+
+    fn page_down(&mut self, _: &PageDown, cx: &mut ViewContext<Self>) {
+        self.move_by((BYTES_PER_ROW * ROWS_PER_PAGE) as isize, cx);
+    }
+
+    fn move_to_beginning(&mut self, _: &MoveToBeginning, cx: &mut ViewContext<Self>) {
+        self.go_to_offset(0, cx);
+    }
+
+    fn move_to_end(&mut self, _: &MoveToEnd, cx: &mut ViewContext<Self>) {
+        self.go_to_offset(self.len(cx), cx);
+    }
+
+    /// In insert mode, deletes the byte before the cursor. In overwrite mode, moves the
+    /// cursor back.
+    fn backspace(&mut self, _: &Backspace, cx: &mut ViewContext<Self>) {
+        if self.cursor == 0 {
+            return;
+        }
+        if self.mode == EditMode::Insert {
+            let offset = self.cursor - 1;
+            self.file
+                .update(cx, |file, cx| file.edit(offset..offset + 1, &[], cx));
+        }
+        self.move_by(-1, cx);
+    }
+
+    /// Deletes the byte under the cursor, in insert mode.
+    fn delete(&mut self, _: &Delete, cx: &mut ViewContext<Self>) {
+        if self.mode == EditMode::Insert && self.cursor < self.len(cx) {
+            let offset = self.cursor;
+            self.file
+                .update(cx, |file, cx| file.edit(offset..offset + 1, &[], cx));
+            self.editing_low_nibble = false;
+        }
+    }
+
+    fn undo(&mut self, _: &Undo, cx: &mut ViewContext<Self>) {
+        if let Some(offset) = self.file.update(cx, |file, cx| file.undo(cx)) {
+            self.go_to_offset(offset, cx);
+        }
+    }
+
+    fn redo(&mut self, _: &Redo, cx: &mut ViewContext<Self>) {
+        if let Some(offset) = self.file.update(cx, |file, cx| file.redo(cx)) {
+            self.go_to_offset(offset, cx);
+        }
+    }
+
+    fn toggle_insert_mode(&mut self, _: &ToggleInsertMode, cx: &mut ViewContext<Self>) {
+        self.mode = match self.mode {
+            EditMode::Overwrite => EditMode::Insert,
+            EditMode::Insert => EditMode::Overwrite,
+        };
+        cx.notify();
+    }
+
+    fn switch_column(&mut self, _: &SwitchColumn, cx: &mut ViewContext<Self>) {
+        self.column = match self.column {
+            Column::Hex => Column::Ascii,
+            Column::Ascii => Column::Hex,
+        };
+        self.editing_low_nibble = false;
+        cx.notify();
+    }
+
+    fn toggle_prompt(&mut self, kind: PromptKind, cx: &mut ViewContext<Self>) {
+        let Some(workspace) = self.workspace.as_ref().and_then(|w| w.upgrade()) else {
+            return;
+        };
+        let hex_editor = cx.view().downgrade();
+        workspace.update(cx, |workspace, cx| {
+            workspace.toggle_modal(cx, |cx| HexPrompt::new(kind, hex_editor, cx));
+        });
+    }
+
+    fn go_to_offset_prompt(&mut self, _: &GoToOffset, cx: &mut ViewContext<Self>) {
+        self.toggle_prompt(PromptKind::GoToOffset, cx);
+    }
+
+    fn find_bytes_prompt(&mut self, _: &FindBytes, cx: &mut ViewContext<Self>) {
+        self.toggle_prompt(PromptKind::FindBytes, cx);
+    }
+
+    fn find_next(&mut self, _: &FindNext, cx: &mut ViewContext<Self>) {
+        match self.last_search.clone() {
+            Some(pattern) => {
+                self.find(pattern, cx);
+            }
+            None => self.toggle_prompt(PromptKind::FindBytes, cx),
+        }
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.control
+            || keystroke.modifiers.alt
+            || keystroke.modifiers.platform
+            || keystroke.modifiers.function
+        {
+            return;
+        }
+        let input = keystroke.ime_key.as_deref().unwrap_or(&keystroke.key);
+        let mut chars = input.chars();
+        let (Some(input), None) = (chars.next(), chars.next()) else {
+            return;
+        };
+
+        let handled = match self.column {
+            Column::Hex => self.input_hex_digit(input, cx),
+            Column::Ascii => self.input_ascii(input, cx),
+        };
+        if handled {
+            cx.stop_propagation();
+        }
+    }
+
+    /// Sets one nibble of the byte under the cursor, moving to the next byte once both
+    /// nibbles have been typed.
+    fn input_hex_digit(&mut self, input: char, cx: &mut ViewContext<Self>) -> bool {
+        let Some(digit) = input.to_digit(16).map(|digit| digit as u8) else {
+            return false;
+        };
+        let offset = self.cursor;
+        let editing_low_nibble = self.editing_low_nibble;
+        let insert = !editing_low_nibble && self.mode == EditMode::Insert;
+        self.file.update(cx, |file, cx| {
+            if insert || offset == file.len() {
+                file.edit(offset..offset, &[digit << 4], cx);
+            } else {
+                let byte = file.data()[offset];
+                let byte = if editing_low_nibble {
+                    (byte & 0xF0) | digit
+                } else {
+                    (digit << 4) | (byte & 0x0F)
+                };
+                file.edit(offset..offset + 1, &[byte], cx);
+            }
+        });
+
+        if editing_low_nibble {
+            self.move_by(1, cx);
+        } else {
+            self.editing_low_nibble = true;
+            cx.notify();
+        }
+        true
+    }
+
+    /// Writes an ASCII character at the cursor, and moves to the next byte.
+    fn input_ascii(&mut self, input: char, cx: &mut ViewContext<Self>) -> bool {
+        if !input.is_ascii() || input.is_ascii_control() {
+            return false;
+        }
+        let offset = self.cursor;
+        let insert = self.mode == EditMode::Insert;
+        self.file.update(cx, |file, cx| {
+            let range = if insert || offset == file.len() {
+                offset..offset
+            } else {
+                offset..offset + 1
+            };
+            file.edit(range, &[input as u8], cx);
+        });
+        self.move_by(1, cx);
+        true
+    }
+
+    fn click(&mut self, offset: usize, column: Column, cx: &mut ViewContext<Self>) {
+        self.column = column;
+        self.go_to_offset(offset, cx);
+        self.focus_handle.focus(cx);
+    }
+
+    fn render_rows(&mut self, range: Range<usize>, cx: &mut ViewContext<Self>) -> Vec<AnyElement> {
+        let data = self.file.read(cx).data();
+        let colors = cx.theme().colors();
+        let focused = self.focus_handle.is_focused(cx);
+
+        range
+            .map(|row| {
+                let row_start = row * BYTES_PER_ROW;
+                let row_end = (row_start + BYTES_PER_ROW).min(data.len());
+                let cell = |offset: usize, column: Column, text: String| {
+                    let is_cursor = offset == self.cursor;
+                    div()
+                        .id(("byte", offset * 2 + (column == Column::Ascii) as usize))
+                        .when(is_cursor, |cell| {
+                            cell.bg(if column == self.column && focused {
+                                colors.editor_active_line_background
+                            } else {
+                                colors.element_selected
+                            })
+                            .when(column == self.column, |cell| {
+                                cell.border_b_1().border_color(colors.text_accent)
+                            })
+                        })
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |this, _, cx| this.click(offset, column, cx)),
+                        )
+                        .child(text)
+                };
+
+                let mut hex = h_flex().gap_1();
+                let mut ascii = h_flex();
+                for offset in row_start..row_start + BYTES_PER_ROW {
+                    if offset < row_end {
+                        let byte = data[offset];
+                        hex = hex.child(cell(offset, Column::Hex, format!("{byte:02X}")));
+                        let character = if byte.is_ascii_graphic() || byte == b' ' {
+                            byte as char
+                        } else {
+                            '.'
+                        };
+                        ascii = ascii.child(cell(offset, Column::Ascii, character.to_string()));
+                    } else if offset == data.len() {
+                        // The position past the last byte, where new bytes can be appended.
+                        hex = hex.child(cell(offset, Column::Hex, "  ".into()));
+                        ascii = ascii.child(cell(offset, Column::Ascii, " ".into()));
+                    } else {
+                        hex = hex.child(div().child("  "));
+                    }
+                }
+
+                h_flex()
+                    .gap_4()
+                    .child(
+                        div()
+                            .text_color(colors.editor_line_number)
+                            .child(format!("{row_start:08X}")),
+                    )
+                    .child(hex)
+                    .child(ascii)
+                    .into_any_element()
+            })
+            .collect()
+    }
+
+    fn render_status(&self, cx: &ViewContext<Self>) -> impl IntoElement {
+        let len = self.len(cx);
+        let byte = self
+            .file
+            .read(cx)
+            .data()
+            .get(self.cursor)
+            .map(|byte| format!("{byte:#04X} ({byte})"))
+            .unwrap_or_else(|| "end of file".into());
+        let mode = match self.mode {
+            EditMode::Overwrite => "Overwrite",
+            EditMode::Insert => "Insert",
+        };
+
+        h_flex()
+            .px_2()
+            .py_1()
+            .gap_4()
+            .border_t_1()
+            .border_color(cx.theme().colors().border_variant)
+            .child(
+                Label::new(format!(
+                    "Offset {:#X} of {:#X} bytes: {byte}",
+                    self.cursor, len
+                ))
+                .size(LabelSize::Small)
+                .color(Color::Muted),
+            )
+            .child(Label::new(mode).size(LabelSize::Small).color(Color::Muted))
+    }
+}
+
+impl EventEmitter<Event> for HexEditor {}
+
+impl FocusableView for HexEditor {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for HexEditor {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let row_count = self.len(cx) / BYTES_PER_ROW + 1;
+
+        v_flex()
+            .key_context("HexEditor")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::move_left))
+            .on_action(cx.listener(Self::move_right))
+            .on_action(cx.listener(Self::move_up))
+            .on_action(cx.listener(Self::move_down))
+            .on_action(cx.listener(Self::page_up))
+            .on_action(cx.listener(Self::page_down))
+            .on_action(cx.listener(Self::move_to_beginning))
+            .on_action(cx.listener(Self::move_to_end))
+            .on_action(cx.listener(Self::backspace))
+            .on_action(cx.listener(Self::delete))
+            .on_action(cx.listener(Self::undo))
+            .on_action(cx.listener(Self::redo))
+            .on_action(cx.listener(Self::toggle_insert_mode))
+            .on_action(cx.listener(Self::switch_column))
+            .on_action(cx.listener(Self::go_to_offset_prompt))
+            .on_action(cx.listener(Self::find_bytes_prompt))
+            .on_action(cx.listener(Self::find_next))
+            .on_key_down(cx.listener(Self::key_down))
+            .size_full()
+            .bg(cx.theme().colors().editor_background)
+            .font_buffer(cx)
+            .child(
+                uniform_list(
+                    cx.view().clone(),
+                    "hex-editor-rows",
+                    row_count,
+                    |this, range, cx| this.render_rows(range, cx),
+                )
+                .flex_grow()
+                .px_2()
+                .track_scroll(self.scroll_handle.clone()),
+            )
+            .child(self.render_status(cx))
+    }
+}
+
+impl Item for HexEditor {
+    type Event = Event;
+
+    fn to_item_events(event: &Event, mut f: impl FnMut(ItemEvent)) {
+        match event {
+            Event::Edited => {
+                f(ItemEvent::Edit);
+                f(ItemEvent::UpdateTab);
+            }
+            Event::Saved => f(ItemEvent::UpdateTab),
+        }
+    }
+
+    fn tab_content(&self, params: TabContentParams, cx: &WindowContext) -> AnyElement {
+        let path = &self.file.read(cx).abs_path;
+        let title = path
+            .file_name()
+            .unwrap_or_else(|| path.as_os_str())
+            .to_string_lossy()
+            .to_string();
+        Label::new(title)
+            .single_line()
+            .color(params.text_color())
+            .italic(params.preview)
+            .into_any_element()
+    }
+
+    fn tab_icon(&self, cx: &WindowContext) -> Option<Icon> {
+        ItemSettings::get_global(cx)
+            .file_icons
+            .then(|| FileIcons::get_icon(&self.file.read(cx).abs_path, cx))
+            .flatten()
+            .map(Icon::from_path)
+    }
+
+    fn tab_tooltip_text(&self, cx: &AppContext) -> Option<SharedString> {
+        Some(
+            self.file
+                .read(cx)
+                .abs_path
+                .to_string_lossy()
+                .to_string()
+                .into(),
+        )
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(gpui::EntityId, &dyn project::Item),
+    ) {
+        f(self.file.entity_id(), self.file.read(cx))
+    }
+
+    fn is_singleton(&self, _cx: &AppContext) -> bool {
+        true
+    }
+
+    fn clone_on_split(
+        &self,
+        _workspace_id: Option<WorkspaceId>,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<View<Self>> {
+        let workspace = self.workspace.clone();
+        Some(cx.new_view(|cx| {
+            let mut editor = Self::new(self.file.clone(), cx);
+            editor.workspace = workspace;
+            editor
+        }))
+    }
+
+    fn added_to_workspace(&mut self, workspace: &mut Workspace, _: &mut ViewContext<Self>) {
+        self.workspace = Some(workspace.weak_handle());
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.file.read(cx).is_dirty()
+    }
+
+    fn can_save(&self, _cx: &AppContext) -> bool {
+        true
+    }
+
+    fn save(
+        &mut self,
+        _format: bool,
+        project: Model<Project>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        let fs = project.read(cx).fs().clone();
+        self.file.update(cx, |file, cx| file.save(fs, cx))
+    }
+
+    fn save_as(
+        &mut self,
+        project: Model<Project>,
+        path: ProjectPath,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        let project = project.read(cx);
+        let fs = project.fs().clone();
+        let Some(abs_path) = project.absolute_path(&path, cx) else {
+            return Task::ready(Err(anyhow!("failed to find the absolute path")));
+        };
+        self.file
+            .update(cx, |file, cx| file.save_as(fs, abs_path, path, cx))
+    }
+
+    fn reload(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        let fs = project.read(cx).fs().clone();
+        self.file.update(cx, |file, cx| file.reload(fs, cx))
+    }
+}
+
+impl ProjectItem for HexEditor {
+    type Item = HexFile;
+
+    fn for_project_item(
+        _project: Model<Project>,
+        item: Model<Self::Item>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new(item, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::{TestAppContext, VisualTestContext};
+    use std::path::Path;
+    use workspace::AppState;
+
+    #[gpui::test]
+    async fn test_hex_digit_input(cx: &mut TestAppContext) {
+        let (editor, _, cx) = open_hex_editor(&[0x12, 0x34], cx).await;
+        editor.update(cx, |editor, cx| {
+            // In overwrite mode, each digit replaces a nibble of the byte under the cursor.
+            assert!(editor.input_hex_digit('a', cx));
+            assert_eq!(editor.file.read(cx).data(), [0xA2, 0x34]);
+            assert_eq!(editor.cursor, 0);
+            assert!(editor.input_hex_digit('B', cx));
+            assert_eq!(editor.file.read(cx).data(), [0xAB, 0x34]);
+            assert_eq!(editor.cursor, 1);
+            assert!(!editor.input_hex_digit('g', cx));
+            assert_eq!(editor.file.read(cx).data(), [0xAB, 0x34]);
+
+            // In insert mode, the first digit inserts a byte.
+            editor.toggle_insert_mode(&ToggleInsertMode, cx);
+            assert_eq!(editor.mode(), EditMode::Insert);
+            assert!(editor.input_hex_digit('c', cx));
+            assert_eq!(editor.file.read(cx).data(), [0xAB, 0xC0, 0x34]);
+            assert!(editor.input_hex_digit('d', cx));
+            assert_eq!(editor.file.read(cx).data(), [0xAB, 0xCD, 0x34]);
+            assert_eq!(editor.cursor, 2);
+
+            // Past the last byte, digits append a byte in either mode.
+            editor.toggle_insert_mode(&ToggleInsertMode, cx);
+            editor.move_to_end(&MoveToEnd, cx);
+            assert!(editor.input_hex_digit('e', cx));
+            assert!(editor.input_hex_digit('f', cx));
+            assert_eq!(editor.file.read(cx).data(), [0xAB, 0xCD, 0x34, 0xEF]);
+            assert_eq!(editor.cursor, 4);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_ascii_input(cx: &mut TestAppContext) {
+        let (editor, _, cx) = open_hex_editor(b"abc", cx).await;
+        editor.update(cx, |editor, cx| {
+            editor.switch_column(&SwitchColumn, cx);
+
+            assert!(editor.input_ascii('X', cx));
+            assert_eq!(editor.file.read(cx).data(), b"Xbc");
+            assert_eq!(editor.cursor, 1);
+            assert!(!editor.input_ascii('\n', cx));
+            assert!(!editor.input_ascii('é', cx));
+            assert_eq!(editor.file.read(cx).data(), b"Xbc");
+
+            editor.toggle_insert_mode(&ToggleInsertMode, cx);
+            assert!(editor.input_ascii('Y', cx));
+            assert_eq!(editor.file.read(cx).data(), b"XYbc");
+            assert_eq!(editor.cursor, 2);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_backspace_and_delete(cx: &mut TestAppContext) {
+        let (editor, _, cx) = open_hex_editor(b"abcd", cx).await;
+        editor.update(cx, |editor, cx| {
+            // In overwrite mode, backspace only moves the cursor, and delete does nothing.
+            editor.go_to_offset(2, cx);
+            editor.backspace(&Backspace, cx);
+            assert_eq!(editor.cursor, 1);
+            editor.delete(&Delete, cx);
+            assert_eq!(editor.file.read(cx).data(), b"abcd");
+
+            // In insert mode, they remove bytes.
+            editor.toggle_insert_mode(&ToggleInsertMode, cx);
+            editor.backspace(&Backspace, cx);
+            assert_eq!(editor.file.read(cx).data(), b"bcd");
+            assert_eq!(editor.cursor, 0);
+            editor.backspace(&Backspace, cx);
+            assert_eq!(editor.file.read(cx).data(), b"bcd");
+            editor.delete(&Delete, cx);
+            assert_eq!(editor.file.read(cx).data(), b"cd");
+            assert_eq!(editor.cursor, 0);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_undo_redo(cx: &mut TestAppContext) {
+        let (editor, _, cx) = open_hex_editor(b"abcd", cx).await;
+        editor.update(cx, |editor, cx| {
+            editor.switch_column(&SwitchColumn, cx);
+            editor.toggle_insert_mode(&ToggleInsertMode, cx);
+            editor.go_to_offset(1, cx);
+            editor.input_ascii('X', cx);
+            editor.delete(&Delete, cx);
+            assert_eq!(editor.file.read(cx).data(), b"aXcd");
+
+            editor.undo(&Undo, cx);
+            assert_eq!(editor.file.read(cx).data(), b"aXbcd");
+            assert_eq!(editor.cursor, 2);
+            editor.undo(&Undo, cx);
+            assert_eq!(editor.file.read(cx).data(), b"abcd");
+            assert_eq!(editor.cursor, 1);
+            // There's nothing left to undo.
+            editor.undo(&Undo, cx);
+            assert_eq!(editor.file.read(cx).data(), b"abcd");
+
+            editor.redo(&Redo, cx);
+            assert_eq!(editor.file.read(cx).data(), b"aXbcd");
+            assert_eq!(editor.cursor, 1);
+
+            // A new edit discards the edits that were undone.
+            editor.go_to_offset(0, cx);
+            editor.input_ascii('Y', cx);
+            assert_eq!(editor.file.read(cx).data(), b"YaXbcd");
+            editor.redo(&Redo, cx);
+            assert_eq!(editor.file.read(cx).data(), b"YaXbcd");
+        });
+    }
+
+    #[gpui::test]
+    async fn test_save_and_reload(cx: &mut TestAppContext) {
+        let (editor, fs, cx) = open_hex_editor(&[0x00, 0xFF], cx).await;
+        let file = editor.update(cx, |editor, cx| {
+            editor.input_hex_digit('7', cx);
+            editor.input_hex_digit('f', cx);
+            assert!(editor.file.read(cx).is_dirty());
+            editor.file.clone()
+        });
+
+        file.update(cx, |file, cx| file.save(fs.clone(), cx))
+            .await
+            .unwrap();
+        assert_eq!(
+            fs.load_bytes(Path::new("/dir/data.bin")).await.unwrap(),
+            [0x7F, 0xFF]
+        );
+        assert!(!file.read_with(cx, |file, _| file.is_dirty()));
+
+        fs.insert_file("/dir/data.bin", vec![0x01, 0x02, 0x03])
+            .await;
+        file.update(cx, |file, cx| file.reload(fs.clone(), cx))
+            .await
+            .unwrap();
+        editor.update(cx, |editor, cx| {
+            assert_eq!(editor.file.read(cx).data(), [0x01, 0x02, 0x03]);
+            assert!(!editor.file.read(cx).is_dirty());
+            // The edits made before reloading can't be undone.
+            editor.undo(&Undo, cx);
+            assert_eq!(editor.file.read(cx).data(), [0x01, 0x02, 0x03]);
+        });
+    }
+
+    async fn open_hex_editor<'a>(
+        data: &[u8],
+        cx: &'a mut TestAppContext,
+    ) -> (View<HexEditor>, Arc<FakeFs>, &'a mut VisualTestContext) {
+        cx.update(|cx| {
+            AppState::test(cx);
+            Project::init_settings(cx);
+        });
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_file("/dir/data.bin", data.to_vec()).await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let worktree_id = project.read_with(cx, |project, cx| {
+            project.worktrees(cx).next().unwrap().read(cx).id()
+        });
+        let path = ProjectPath {
+            worktree_id,
+            path: Path::new("data.bin").into(),
+        };
+        let file = cx
+            .update(|cx| HexFile::open(&project, &path, cx))
+            .await
+            .unwrap();
+        let (editor, cx) = cx.add_window_view(|cx| HexEditor::new(file, cx));
+        (editor, fs, cx)
+    }
+}
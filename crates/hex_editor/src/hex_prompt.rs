@@ -0,0 +1,162 @@
+use editor::Editor;
+use gpui::{
+    div, prelude::*, AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Render,
+    SharedString, Styled, Subscription, View, ViewContext, VisualContext, WeakView,
+};
+use ui::{prelude::*, v_flex, Label};
+use workspace::ModalView;
+
+use crate::{
+    search::{parse_byte_pattern, parse_offset},
+    HexEditor,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PromptKind {
+    GoToOffset,
+    FindBytes,
+}
+
+/// A modal asking for an offset to jump to, or a byte pattern to search for, in a [`HexEditor`].
+pub(crate) struct HexPrompt {
+    kind: PromptKind,
+    query_editor: View<Editor>,
+    hex_editor: WeakView<HexEditor>,
+    error: Option<SharedString>,
+    _subscription: Subscription,
+}
+
+impl ModalView for HexPrompt {}
+
+impl FocusableView for HexPrompt {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.query_editor.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for HexPrompt {}
+
+impl HexPrompt {
+    pub(crate) fn new(
+        kind: PromptKind,
+        hex_editor: WeakView<HexEditor>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let query_editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text(
+                match kind {
+                    PromptKind::GoToOffset => "0x0",
+                    PromptKind::FindBytes => "DE AD BE EF",
+                },
+                cx,
+            );
+            editor
+        });
+        let subscription = cx.subscribe(&query_editor, |this, _, event, cx| match event {
+            editor::EditorEvent::Blurred => cx.emit(DismissEvent),
+            editor::EditorEvent::BufferEdited => {
+                this.error = None;
+                cx.notify();
+            }
+            _ => {}
+        });
+
+        Self {
+            kind,
+            query_editor,
+            hex_editor,
+            error: None,
+            _subscription: subscription,
+        }
+    }
+
+    fn cancel(&mut self, _: &menu::Cancel, cx: &mut ViewContext<Self>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        let Some(hex_editor) = self.hex_editor.upgrade() else {
+            cx.emit(DismissEvent);
+            return;
+        };
+        let query = self.query_editor.read(cx).text(cx);
+
+        let result = match self.kind {
+            PromptKind::GoToOffset => match parse_offset(&query) {
+                Some(offset) if offset <= hex_editor.read(cx).len(cx) => {
+                    hex_editor.update(cx, |hex_editor, cx| hex_editor.go_to_offset(offset, cx));
+                    Ok(())
+                }
+                Some(_) => Err("Offset is past the end of the file"),
+                None => Err("Invalid offset"),
+            },
+            PromptKind::FindBytes => match parse_byte_pattern(&query) {
+                Some(pattern) => {
+                    if hex_editor.update(cx, |hex_editor, cx| hex_editor.find(pattern, cx)) {
+                        Ok(())
+                    } else {
+                        Err("Pattern not found")
+                    }
+                }
+                None => Err("Invalid byte pattern"),
+            },
+        };
+
+        match result {
+            Ok(()) => {
+                hex_editor.focus_handle(cx).focus(cx);
+                cx.emit(DismissEvent);
+            }
+            Err(error) => {
+                self.error = Some(error.into());
+                cx.notify();
+            }
+        }
+    }
+}
+
+impl Render for HexPrompt {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let help_text = self.error.clone().unwrap_or_else(|| match self.kind {
+            PromptKind::GoToOffset => {
+                "Go to offset, in decimal or in hexadecimal with a 0x prefix".into()
+            }
+            PromptKind::FindBytes => {
+                "Find hexadecimal bytes, or an ASCII string in double quotes".into()
+            }
+        });
+
+        div()
+            .elevation_2(cx)
+            .key_context("HexPrompt")
+            .on_action(cx.listener(Self::cancel))
+            .on_action(cx.listener(Self::confirm))
+            .w_96()
+            .child(
+                v_flex()
+                    .px_1()
+                    .pt_0p5()
+                    .gap_px()
+                    .child(
+                        v_flex()
+                            .py_0p5()
+                            .px_1()
+                            .child(div().px_1().py_0p5().child(self.query_editor.clone())),
+                    )
+                    .child(
+                        div()
+                            .h_px()
+                            .w_full()
+                            .bg(cx.theme().colors().element_background),
+                    )
+                    .child(h_flex().justify_between().px_2().py_1().child(
+                        Label::new(help_text).color(if self.error.is_some() {
+                            Color::Error
+                        } else {
+                            Color::Muted
+                        }),
+                    )),
+            )
+    }
+}
@@ -0,0 +1,96 @@
+/// Parses an offset, written either in decimal or in hexadecimal with a `0x` prefix.
+pub(crate) fn parse_offset(query: &str) -> Option<usize> {
+    let query = query.trim().replace('_', "");
+    if let Some(hex) = query
+        .strip_prefix("0x")
+        .or_else(|| query.strip_prefix("0X"))
+    {
+        usize::from_str_radix(hex, 16).ok()
+    } else {
+        query.parse().ok()
+    }
+}
+
+/// Parses a byte pattern, written either as hexadecimal byte values (e.g. `DE AD BE EF`,
+/// whitespace being optional) or as a double-quoted ASCII string (e.g. `"ELF"`).
+pub(crate) fn parse_byte_pattern(query: &str) -> Option<Vec<u8>> {
+    let query = query.trim();
+    if let Some(text) = query
+        .strip_prefix('"')
+        .and_then(|query| query.strip_suffix('"'))
+    {
+        return (!text.is_empty()).then(|| text.as_bytes().to_vec());
+    }
+
+    let digits = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<_>>>()?;
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return None;
+    }
+    Some(
+        digits
+            .chunks(2)
+            .map(|nibbles| (nibbles[0] << 4) | nibbles[1])
+            .collect(),
+    )
+}
+
+/// Returns the offset of the first occurrence of `pattern` at or after `start`,
+/// wrapping around to the beginning of `data` if there is none.
+pub(crate) fn find_bytes(data: &[u8], pattern: &[u8], start: usize) -> Option<usize> {
+    if pattern.is_empty() || pattern.len() > data.len() {
+        return None;
+    }
+    let start = start.min(data.len());
+    let find_in = |range: std::ops::Range<usize>| {
+        data[range.clone()]
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+            .map(|position| range.start + position)
+    };
+    find_in(start..data.len()).or_else(|| find_in(0..(start + pattern.len() - 1).min(data.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("1024"), Some(1024));
+        assert_eq!(parse_offset(" 0x1F "), Some(0x1F));
+        assert_eq!(parse_offset("0X00_10"), Some(0x10));
+        assert_eq!(parse_offset("1F"), None);
+        assert_eq!(parse_offset(""), None);
+    }
+
+    #[test]
+    fn test_parse_byte_pattern() {
+        assert_eq!(
+            parse_byte_pattern("DE AD be ef"),
+            Some(vec![0xDE, 0xAD, 0xBE, 0xEF])
+        );
+        assert_eq!(parse_byte_pattern("7f454c46"), Some(b"\x7fELF".to_vec()));
+        assert_eq!(parse_byte_pattern("\"ELF\""), Some(b"ELF".to_vec()));
+        assert_eq!(parse_byte_pattern("ABC"), None);
+        assert_eq!(parse_byte_pattern("GG"), None);
+        assert_eq!(parse_byte_pattern("\"\""), None);
+        assert_eq!(parse_byte_pattern(""), None);
+    }
+
+    #[test]
+    fn test_find_bytes() {
+        let data = b"abcabcab";
+        assert_eq!(find_bytes(data, b"bc", 0), Some(1));
+        assert_eq!(find_bytes(data, b"bc", 2), Some(4));
+        // Searches wrap around to the beginning.
+        assert_eq!(find_bytes(data, b"bc", 5), Some(1));
+        assert_eq!(find_bytes(data, b"ab", 7), Some(0));
+        assert_eq!(find_bytes(data, b"cd", 0), None);
+        assert_eq!(find_bytes(data, b"", 0), None);
+        assert_eq!(find_bytes(b"ab", b"abc", 0), None);
+    }
+}
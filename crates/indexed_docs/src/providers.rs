@@ -1 +1,41 @@
+pub mod go;
+pub mod man;
+pub mod python;
 pub mod rustdoc;
+pub mod typescript;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use sha2::{Digest, Sha256};
+use smol::process::Command;
+
+/// Returns the path of the database in which a provider stores the docs of a project's
+/// dependencies, apart from those of other projects.
+pub(crate) fn project_database_path(provider: &str, project_root: &Path) -> PathBuf {
+    let root_hash = format!(
+        "{:x}",
+        Sha256::digest(project_root.to_string_lossy().as_bytes())
+    );
+    paths::support_dir()
+        .join("docs")
+        .join(provider)
+        .join(&root_hash[..16])
+        .join(format!("{provider}-db.0.mdb"))
+}
+
+/// Runs the command to completion, returning its standard output.
+pub(crate) async fn command_output(command: &mut Command) -> Result<String> {
+    let output = command
+        .output()
+        .await
+        .with_context(|| format!("failed to run {command:?}"))?;
+    if !output.status.success() {
+        bail!(
+            "{command:?} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,356 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use smol::process::Command;
+
+use crate::providers::{command_output, project_database_path};
+use crate::{IndexedDocsDatabase, IndexedDocsProvider, PackageName, ProviderId};
+
+/// The headings `go doc -all` groups a package's declarations under.
+const SECTION_HEADINGS: &[&str] = &["CONSTANTS", "VARIABLES", "FUNCTIONS", "TYPES"];
+
+/// Provides the docs of the Go packages the project's module depends on, including the standard
+/// library, using `go doc`.
+pub struct GoDocsProvider {
+    module_root: PathBuf,
+    dependencies: RwLock<Option<(Vec<PackageName>, Instant)>>,
+}
+
+impl GoDocsProvider {
+    pub fn id() -> ProviderId {
+        ProviderId("go".into())
+    }
+
+    pub fn new(module_root: PathBuf) -> Self {
+        Self {
+            module_root,
+            dependencies: RwLock::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl IndexedDocsProvider for GoDocsProvider {
+    fn id(&self) -> ProviderId {
+        Self::id()
+    }
+
+    fn database_path(&self) -> PathBuf {
+        project_database_path("go", &self.module_root)
+    }
+
+    async fn suggest_packages(&self) -> Result<Vec<PackageName>> {
+        if let Some((packages, fetched_at)) = &*self.dependencies.read() {
+            if fetched_at.elapsed() < Duration::from_secs(300) {
+                return Ok(packages.clone());
+            }
+        }
+
+        let output = command_output(
+            Command::new("go")
+                .args(["list", "-deps", "./..."])
+                .current_dir(&self.module_root),
+        )
+        .await?;
+        let packages = output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| PackageName::from(line.trim()))
+            .collect::<Vec<_>>();
+
+        *self.dependencies.write() = Some((packages.clone(), Instant::now()));
+
+        Ok(packages)
+    }
+
+    async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()> {
+        let output = command_output(
+            Command::new("go")
+                .args(["doc", "-all", &package.to_string()])
+                .current_dir(&self.module_root),
+        )
+        .await?;
+
+        for (key, markdown) in convert_go_doc_to_markdown(&package, &output) {
+            database.insert(key, markdown).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A top-level declaration in the output of `go doc -all`, along with its doc comment.
+struct GoDeclaration<'a> {
+    code: Vec<&'a str>,
+    doc: Vec<&'a str>,
+}
+
+impl GoDeclaration<'_> {
+    /// Returns the declaration's name, qualified by its receiver's type for methods. Groups of
+    /// constants and variables don't have a name.
+    fn name(&self) -> Option<String> {
+        let first_line = self.code.first()?;
+        if let Some(rest) = first_line.strip_prefix("func (") {
+            let (receiver, rest) = rest.split_once(')')?;
+            let receiver_type = receiver.split_whitespace().last()?.trim_start_matches('*');
+            let receiver_type = receiver_type.split('[').next()?;
+            return Some(format!(
+                "{receiver_type}.{}",
+                identifier(rest.trim_start())?
+            ));
+        }
+
+        let rest = ["func ", "type ", "const ", "var "]
+            .iter()
+            .find_map(|keyword| first_line.strip_prefix(keyword))?;
+        identifier(rest).map(ToString::to_string)
+    }
+
+    fn push_markdown(&self, markdown: &mut String) {
+        writeln!(markdown, "```go\n{}\n```\n", self.code.join("\n")).unwrap();
+        push_doc(markdown, &self.doc);
+    }
+}
+
+/// Converts the output of `go doc -all` to Markdown, keyed by the package's import path and by
+/// `<import path>.<name>` for each of its functions, types and methods.
+fn convert_go_doc_to_markdown(package: &str, go_doc: &str) -> Vec<(String, String)> {
+    let mut overview = Vec::new();
+    let mut sections = Vec::<(&str, Vec<GoDeclaration>)>::new();
+    for line in go_doc.lines() {
+        if SECTION_HEADINGS.contains(&line) {
+            sections.push((line, Vec::new()));
+            continue;
+        }
+
+        let Some((_, declarations)) = sections.last_mut() else {
+            // The first line is the package clause, which doesn't need to be repeated.
+            if !line.starts_with("package ") {
+                overview.push(line.strip_prefix("    ").unwrap_or(line));
+            }
+            continue;
+        };
+
+        // Declarations start in the first column, and are followed by their doc comment
+        // indented by four spaces.
+        let starts_declaration = !line.is_empty() && !line.starts_with([' ', '\t', ')', '}']);
+        match declarations.last_mut() {
+            Some(declaration) if !starts_declaration => {
+                if let Some(doc_line) = line.strip_prefix("    ") {
+                    declaration.doc.push(doc_line);
+                } else if declaration.doc.is_empty() && !line.is_empty() {
+                    declaration.code.push(line);
+                } else {
+                    declaration.doc.push(line);
+                }
+            }
+            _ if starts_declaration => declarations.push(GoDeclaration {
+                code: vec![line],
+                doc: Vec::new(),
+            }),
+            _ => {}
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut markdown = format!("# `{package}`\n\n");
+    push_doc(&mut markdown, &overview);
+    for (section, declarations) in &sections {
+        let mut heading = section.to_lowercase();
+        heading[..1].make_ascii_uppercase();
+        writeln!(markdown, "## {heading}\n").unwrap();
+        if matches!(*section, "CONSTANTS" | "VARIABLES") {
+            // Constants and variables are short enough to be included in full.
+            for declaration in declarations {
+                declaration.push_markdown(&mut markdown);
+            }
+        } else {
+            for name in declarations.iter().filter_map(GoDeclaration::name) {
+                writeln!(markdown, "- `{package}.{name}`").unwrap();
+            }
+            markdown.push('\n');
+        }
+    }
+    entries.push((package.to_string(), markdown.trim_end().to_string() + "\n"));
+
+    for (_, declarations) in &sections {
+        for (ix, declaration) in declarations.iter().enumerate() {
+            let Some(name) = declaration.name() else {
+                continue;
+            };
+            if declaration.code[0].starts_with("const ") || declaration.code[0].starts_with("var ")
+            {
+                continue;
+            }
+
+            let mut markdown = format!("# `{package}.{name}`\n\n");
+            declaration.push_markdown(&mut markdown);
+
+            // A type's methods follow it.
+            if declaration.code[0].starts_with("type ") {
+                let methods = declarations[ix + 1..]
+                    .iter()
+                    .take_while(|declaration| !declaration.code[0].starts_with("type "))
+                    .filter(|declaration| declaration.code[0].starts_with("func ("))
+                    .collect::<Vec<_>>();
+                if !methods.is_empty() {
+                    markdown.push_str("## Methods\n\n");
+                    for method in methods {
+                        writeln!(markdown, "- `{}`", method.code[0]).unwrap();
+                    }
+                    markdown.push('\n');
+                }
+            }
+
+            entries.push((
+                format!("{package}.{name}"),
+                markdown.trim_end().to_string() + "\n",
+            ));
+        }
+    }
+
+    entries
+}
+
+fn identifier(text: &str) -> Option<&str> {
+    let end = text
+        .find(|char: char| !char.is_alphanumeric() && char != '_')
+        .unwrap_or(text.len());
+    (end > 0).then(|| &text[..end])
+}
+
+fn push_doc(markdown: &mut String, doc: &[&str]) {
+    let doc = doc.join("\n");
+    let doc = doc.trim();
+    if !doc.is_empty() {
+        markdown.push_str(doc);
+        markdown.push_str("\n\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_convert_go_doc_to_markdown() {
+        let go_doc = indoc! {"
+            package http // import \"net/http\"
+
+            Package http provides HTTP client and server implementations.
+
+            CONSTANTS
+
+            const (
+            	MethodGet  = \"GET\"
+            	MethodHead = \"HEAD\"
+            )
+                Common HTTP methods.
+
+
+            FUNCTIONS
+
+            func Get(url string) (resp *Response, err error)
+                Get issues a GET to the specified URL.
+
+
+            TYPES
+
+            type Client struct {
+            	Timeout time.Duration
+            }
+                A Client is an HTTP client.
+
+            func (c *Client) Do(req *Request) (*Response, error)
+                Do sends an HTTP request and returns an HTTP response.
+
+        "};
+
+        assert_eq!(
+            convert_go_doc_to_markdown("net/http", go_doc),
+            vec![
+                (
+                    "net/http".to_string(),
+                    indoc! {"
+                        # `net/http`
+
+                        Package http provides HTTP client and server implementations.
+
+                        ## Constants
+
+                        ```go
+                        const (
+                        	MethodGet  = \"GET\"
+                        	MethodHead = \"HEAD\"
+                        )
+                        ```
+
+                        Common HTTP methods.
+
+                        ## Functions
+
+                        - `net/http.Get`
+
+                        ## Types
+
+                        - `net/http.Client`
+                        - `net/http.Client.Do`
+                    "}
+                    .to_string()
+                ),
+                (
+                    "net/http.Get".to_string(),
+                    indoc! {"
+                        # `net/http.Get`
+
+                        ```go
+                        func Get(url string) (resp *Response, err error)
+                        ```
+
+                        Get issues a GET to the specified URL.
+                    "}
+                    .to_string()
+                ),
+                (
+                    "net/http.Client".to_string(),
+                    indoc! {"
+                        # `net/http.Client`
+
+                        ```go
+                        type Client struct {
+                        	Timeout time.Duration
+                        }
+                        ```
+
+                        A Client is an HTTP client.
+
+                        ## Methods
+
+                        - `func (c *Client) Do(req *Request) (*Response, error)`
+                    "}
+                    .to_string()
+                ),
+                (
+                    "net/http.Client.Do".to_string(),
+                    indoc! {"
+                        # `net/http.Client.Do`
+
+                        ```go
+                        func (c *Client) Do(req *Request) (*Response, error)
+                        ```
+
+                        Do sends an HTTP request and returns an HTTP response.
+                    "}
+                    .to_string()
+                ),
+            ]
+        );
+    }
+}
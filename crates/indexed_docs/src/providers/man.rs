@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use fs::Fs;
+use futures::StreamExt;
+use smol::process::Command;
+use util::ResultExt;
+
+use crate::providers::command_output;
+use crate::{IndexedDocsDatabase, IndexedDocsProvider, PackageName, ProviderId};
+
+/// The directories searched for man pages when `manpath` isn't available.
+const DEFAULT_MAN_PATH: &[&str] = &["/usr/share/man", "/usr/local/share/man"];
+
+/// The extensions of compressed man pages.
+const COMPRESSION_EXTENSIONS: &[&str] = &["gz", "bz2", "xz", "zst", "Z"];
+
+/// Provides the man pages installed on the system. Each page is keyed by its name and section,
+/// e.g. `printf(3)`, and by its name alone for the page in the lowest section.
+pub struct ManPageProvider {
+    fs: Arc<dyn Fs>,
+}
+
+impl ManPageProvider {
+    pub fn id() -> ProviderId {
+        ProviderId("man".into())
+    }
+
+    pub fn new(fs: Arc<dyn Fs>) -> Self {
+        Self { fs }
+    }
+
+    /// Returns the sections each page is in, by the page's name.
+    async fn pages(&self) -> Result<BTreeMap<String, BTreeSet<String>>> {
+        let man_path = command_output(&mut Command::new("manpath"))
+            .await
+            .log_err()
+            .map(|output| {
+                output
+                    .trim()
+                    .split(':')
+                    .map(PathBuf::from)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(|| DEFAULT_MAN_PATH.iter().map(PathBuf::from).collect());
+
+        let mut pages = BTreeMap::<String, BTreeSet<String>>::new();
+        for man_dir in man_path {
+            let Ok(mut section_dirs) = self.fs.read_dir(&man_dir).await else {
+                continue;
+            };
+            while let Some(section_dir) = section_dirs.next().await {
+                let Ok(section_dir) = section_dir else {
+                    continue;
+                };
+                let is_section_dir = section_dir
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(false, |name| name.starts_with("man"));
+                if !is_section_dir {
+                    continue;
+                }
+
+                let Ok(mut page_paths) = self.fs.read_dir(&section_dir).await else {
+                    continue;
+                };
+                while let Some(page_path) = page_paths.next().await {
+                    let Some((name, section)) = page_path
+                        .ok()
+                        .as_ref()
+                        .and_then(|path| path.file_name()?.to_str())
+                        .and_then(parse_page_file_name)
+                    else {
+                        continue;
+                    };
+                    pages.entry(name).or_default().insert(section);
+                }
+            }
+        }
+
+        Ok(pages)
+    }
+}
+
+#[async_trait]
+impl IndexedDocsProvider for ManPageProvider {
+    fn id(&self) -> ProviderId {
+        Self::id()
+    }
+
+    fn database_path(&self) -> PathBuf {
+        paths::support_dir().join("docs/man/man-db.0.mdb")
+    }
+
+    async fn suggest_packages(&self) -> Result<Vec<PackageName>> {
+        Ok(self
+            .pages()
+            .await?
+            .keys()
+            .map(|name| PackageName::from(name.as_str()))
+            .collect())
+    }
+
+    async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()> {
+        let name = package.to_string();
+        let Some(sections) = self.pages().await?.remove(&name) else {
+            bail!("no man page found for {name}");
+        };
+
+        for (ix, section) in sections.iter().enumerate() {
+            let output = command_output(
+                Command::new("man")
+                    .args([section, &name])
+                    .env("MANWIDTH", "80")
+                    .env("MANPAGER", "cat")
+                    .env("PAGER", "cat")
+                    .env("GROFF_NO_SGR", "1"),
+            )
+            .await?;
+
+            let markdown = format!(
+                "# `{name}({section})`\n\n```\n{}\n```\n",
+                strip_overstrikes(&output).trim_end()
+            );
+            if ix == 0 {
+                database.insert(name.clone(), markdown.clone()).await?;
+            }
+            database
+                .insert(format!("{name}({section})"), markdown)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the name and section of a man page from its file name, e.g. `ls.1.gz`.
+fn parse_page_file_name(file_name: &str) -> Option<(String, String)> {
+    let file_name = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if COMPRESSION_EXTENSIONS.contains(&extension) => stem,
+        _ => file_name,
+    };
+    let (name, section) = file_name.rsplit_once('.')?;
+    let is_section = section
+        .chars()
+        .next()
+        .map_or(false, |char| char.is_ascii_digit() || char == 'n');
+    (!name.is_empty() && is_section).then(|| (name.to_string(), section.to_string()))
+}
+
+/// Removes the backspace sequences some `man` implementations use to render bold and underlined
+/// text, even when writing to a pipe.
+fn strip_overstrikes(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for char in text.chars() {
+        if char == '\u{8}' {
+            result.pop();
+        } else {
+            result.push(char);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_page_file_name() {
+        assert_eq!(
+            parse_page_file_name("ls.1.gz"),
+            Some(("ls".to_string(), "1".to_string()))
+        );
+        assert_eq!(
+            parse_page_file_name("python3.11.1"),
+            Some(("python3.11".to_string(), "1".to_string()))
+        );
+        assert_eq!(
+            parse_page_file_name("SSL_read.3ssl.bz2"),
+            Some(("SSL_read".to_string(), "3ssl".to_string()))
+        );
+        assert_eq!(parse_page_file_name("README"), None);
+        assert_eq!(parse_page_file_name("whatis.gz"), None);
+    }
+
+    #[test]
+    fn test_strip_overstrikes() {
+        assert_eq!(
+            strip_overstrikes("N\u{8}NA\u{8}AM\u{8}ME\u{8}E\n_\u{8}f_\u{8}i_\u{8}l_\u{8}e"),
+            "NAME\nfile"
+        );
+    }
+}
@@ -0,0 +1,424 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use fs::Fs;
+use futures::StreamExt;
+use serde::Deserialize;
+use smol::process::Command;
+
+use crate::providers::{command_output, project_database_path};
+use crate::{IndexedDocsDatabase, IndexedDocsProvider, PackageName, ProviderId};
+
+const INTROSPECT_SCRIPT: &str = include_str!("./python/introspect.py");
+
+/// Provides the docstrings of the Python packages installed in the project's virtual environment.
+///
+/// Indexing a package imports it, and its modules, with the virtual environment's interpreter,
+/// which executes the project's code: the interpreter itself, its site customizations and the
+/// packages' code all come from the project. So packages are only indexed when the user runs
+/// the `/docs` command, and the installed packages are listed by reading the virtual
+/// environment's `site-packages` directory.
+pub struct PythonDocsProvider {
+    fs: Arc<dyn Fs>,
+    project_root: PathBuf,
+}
+
+impl PythonDocsProvider {
+    /// The directories, relative to the project root, in which a virtual environment is looked
+    /// for.
+    pub const VIRTUAL_ENV_DIRS: &'static [&'static str] = &[".venv", "venv", "env"];
+
+    pub fn id() -> ProviderId {
+        ProviderId("python".into())
+    }
+
+    pub fn new(fs: Arc<dyn Fs>, project_root: PathBuf) -> Self {
+        Self { fs, project_root }
+    }
+
+    async fn python_path(&self) -> Result<PathBuf> {
+        for dir in Self::VIRTUAL_ENV_DIRS {
+            let python_path = if cfg!(windows) {
+                self.project_root.join(dir).join("Scripts/python.exe")
+            } else {
+                self.project_root.join(dir).join("bin/python")
+            };
+            if self.fs.is_file(&python_path).await {
+                return Ok(python_path);
+            }
+        }
+
+        Err(anyhow!(
+            "no Python virtual environment found in {:?}",
+            self.project_root
+        ))
+    }
+
+    /// Returns the `site-packages` directories of the project's virtual environments.
+    async fn site_packages_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        for dir in Self::VIRTUAL_ENV_DIRS {
+            let virtual_env = self.project_root.join(dir);
+            if cfg!(windows) {
+                dirs.push(virtual_env.join("Lib/site-packages"));
+                continue;
+            }
+            // On Unix, the directory is versioned, e.g. `lib/python3.12/site-packages`.
+            let Ok(mut versions) = self.fs.read_dir(&virtual_env.join("lib")).await else {
+                continue;
+            };
+            while let Some(version) = versions.next().await {
+                if let Ok(version) = version {
+                    if version
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with("python"))
+                    {
+                        dirs.push(version.join("site-packages"));
+                    }
+                }
+            }
+        }
+        dirs
+    }
+
+    /// Returns the name of the importable top-level package or module at the given path in
+    /// `site-packages`, if it's public.
+    async fn package_name(&self, path: &Path) -> Option<String> {
+        let file_name = path.file_name()?.to_str()?;
+        let name = if self.fs.is_dir(path).await {
+            // Only regular packages are listed, like `pkgutil.iter_modules` does.
+            if !self.fs.is_file(&path.join("__init__.py")).await {
+                return None;
+            }
+            file_name
+        } else {
+            // Extension modules are suffixed with the ABI they're built for, e.g.
+            // `_yaml.cpython-312-x86_64-linux-gnu.so`.
+            let (name, extension) = file_name.split_once('.')?;
+            let extension = extension.rsplit('.').next()?;
+            if !["py", "so", "pyd"].contains(&extension) {
+                return None;
+            }
+            name
+        };
+        (!name.starts_with('_') && !name.contains('-')).then(|| name.to_string())
+    }
+
+    async fn introspect(&self, args: &[&str]) -> Result<String> {
+        let python_path = self.python_path().await?;
+        command_output(
+            Command::new(python_path)
+                .arg("-c")
+                .arg(INTROSPECT_SCRIPT)
+                .args(args)
+                .current_dir(&self.project_root),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl IndexedDocsProvider for PythonDocsProvider {
+    fn id(&self) -> ProviderId {
+        Self::id()
+    }
+
+    fn database_path(&self) -> PathBuf {
+        project_database_path("python", &self.project_root)
+    }
+
+    fn executes_project_code(&self) -> bool {
+        true
+    }
+
+    async fn suggest_packages(&self) -> Result<Vec<PackageName>> {
+        let mut packages = Vec::new();
+        for dir in self.site_packages_dirs().await {
+            let Ok(mut children) = self.fs.read_dir(&dir).await else {
+                continue;
+            };
+            while let Some(child) = children.next().await {
+                if let Some(package) = self.package_name(&child?).await {
+                    packages.push(package);
+                }
+            }
+        }
+        packages.sort();
+        packages.dedup();
+        Ok(packages
+            .iter()
+            .map(|package| PackageName::from(package.as_str()))
+            .collect())
+    }
+
+    async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()> {
+        let output = self.introspect(&["index", &package.to_string()]).await?;
+        let modules = serde_json::from_str::<Vec<PythonModule>>(&output)
+            .with_context(|| format!("failed to parse the docs of {package}"))?;
+
+        for module in &modules {
+            for (key, markdown) in python_module_to_markdown(module) {
+                database.insert(key, markdown).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PythonModule {
+    name: String,
+    doc: Option<String>,
+    classes: Vec<PythonClass>,
+    functions: Vec<PythonFunction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PythonClass {
+    name: String,
+    signature: Option<String>,
+    doc: Option<String>,
+    methods: Vec<PythonFunction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PythonFunction {
+    name: String,
+    signature: Option<String>,
+    doc: Option<String>,
+}
+
+/// Converts a module's docs to Markdown, keyed by the dotted path of the module and of each of
+/// its classes and functions.
+fn python_module_to_markdown(module: &PythonModule) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+
+    let mut markdown = format!("# `{}`\n\n", module.name);
+    push_doc(&mut markdown, module.doc.as_deref());
+    for (heading, members) in [
+        (
+            "Classes",
+            module
+                .classes
+                .iter()
+                .map(|class| (&class.name, class.doc.as_deref()))
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "Functions",
+            module
+                .functions
+                .iter()
+                .map(|function| (&function.name, function.doc.as_deref()))
+                .collect(),
+        ),
+    ] {
+        if members.is_empty() {
+            continue;
+        }
+        writeln!(markdown, "## {heading}\n").unwrap();
+        for (name, doc) in members {
+            write!(markdown, "- `{}.{name}`", module.name).unwrap();
+            if let Some(summary) = doc.and_then(|doc| doc.lines().next()) {
+                write!(markdown, ": {summary}").unwrap();
+            }
+            markdown.push('\n');
+        }
+        markdown.push('\n');
+    }
+    entries.push((module.name.clone(), markdown));
+
+    for class in &module.classes {
+        let path = format!("{}.{}", module.name, class.name);
+        let mut markdown = format!("# `{path}`\n\n");
+        push_signature(
+            &mut markdown,
+            "class",
+            &class.name,
+            class.signature.as_deref(),
+        );
+        push_doc(&mut markdown, class.doc.as_deref());
+        if !class.methods.is_empty() {
+            markdown.push_str("## Methods\n\n");
+            for method in &class.methods {
+                writeln!(markdown, "### `{}`\n", method.name).unwrap();
+                push_signature(
+                    &mut markdown,
+                    "def",
+                    &method.name,
+                    method.signature.as_deref(),
+                );
+                push_doc(&mut markdown, method.doc.as_deref());
+            }
+        }
+        entries.push((path, markdown));
+    }
+
+    for function in &module.functions {
+        let path = format!("{}.{}", module.name, function.name);
+        let mut markdown = format!("# `{path}`\n\n");
+        push_signature(
+            &mut markdown,
+            "def",
+            &function.name,
+            function.signature.as_deref(),
+        );
+        push_doc(&mut markdown, function.doc.as_deref());
+        entries.push((path, markdown));
+    }
+
+    entries
+}
+
+fn push_signature(markdown: &mut String, keyword: &str, name: &str, signature: Option<&str>) {
+    if let Some(signature) = signature {
+        writeln!(markdown, "```python\n{keyword} {name}{signature}\n```\n").unwrap();
+    }
+}
+
+fn push_doc(markdown: &mut String, doc: Option<&str>) {
+    if let Some(doc) = doc.filter(|doc| !doc.trim().is_empty()) {
+        markdown.push_str(doc.trim());
+        markdown.push_str("\n\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[cfg(not(windows))]
+    #[gpui::test]
+    async fn test_suggest_packages(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/project",
+            json!({
+                ".venv": {
+                    "lib": {
+                        "python3.12": {
+                            "site-packages": {
+                                "requests": { "__init__.py": "" },
+                                "requests-2.32.3.dist-info": { "METADATA": "" },
+                                "namespace": { "module.py": "" },
+                                "six.py": "",
+                                "yaml": { "__init__.py": "" },
+                                "_yaml.cpython-312-x86_64-linux-gnu.so": "",
+                                "_virtualenv.pth": "",
+                            }
+                        }
+                    }
+                }
+            }),
+        )
+        .await;
+
+        // There's no interpreter in the virtual environment, so the packages are listed
+        // without running one.
+        let provider = PythonDocsProvider::new(fs, "/project".into());
+        assert_eq!(
+            provider.suggest_packages().await.unwrap(),
+            ["requests", "six", "yaml"].map(PackageName::from)
+        );
+    }
+
+    #[test]
+    fn test_python_module_to_markdown() {
+        let module = serde_json::from_str::<PythonModule>(indoc! {r#"
+            {
+                "name": "requests.sessions",
+                "doc": "Provides a Session object.",
+                "classes": [
+                    {
+                        "name": "Session",
+                        "signature": "()",
+                        "doc": "A Requests session.\n\nProvides cookie persistence.",
+                        "methods": [
+                            {
+                                "name": "get",
+                                "signature": "(self, url, **kwargs)",
+                                "doc": "Sends a GET request."
+                            }
+                        ]
+                    }
+                ],
+                "functions": [
+                    { "name": "session", "signature": "()", "doc": null }
+                ]
+            }
+        "#})
+        .unwrap();
+
+        assert_eq!(
+            python_module_to_markdown(&module),
+            vec![
+                (
+                    "requests.sessions".to_string(),
+                    indoc! {"
+                        # `requests.sessions`
+
+                        Provides a Session object.
+
+                        ## Classes
+
+                        - `requests.sessions.Session`: A Requests session.
+
+                        ## Functions
+
+                        - `requests.sessions.session`
+
+                    "}
+                    .to_string()
+                ),
+                (
+                    "requests.sessions.Session".to_string(),
+                    indoc! {"
+                        # `requests.sessions.Session`
+
+                        ```python
+                        class Session()
+                        ```
+
+                        A Requests session.
+
+                        Provides cookie persistence.
+
+                        ## Methods
+
+                        ### `get`
+
+                        ```python
+                        def get(self, url, **kwargs)
+                        ```
+
+                        Sends a GET request.
+
+                    "}
+                    .to_string()
+                ),
+                (
+                    "requests.sessions.session".to_string(),
+                    indoc! {"
+                        # `requests.sessions.session`
+
+                        ```python
+                        def session()
+                        ```
+
+                    "}
+                    .to_string()
+                ),
+            ]
+        );
+    }
+}
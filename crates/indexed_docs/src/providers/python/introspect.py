@@ -0,0 +1,107 @@
+"""Describes a Python package installed in an environment, as JSON on stdout.
+
+    introspect.py index <package>  describes the package's modules, classes and functions
+
+The package and its modules are imported, which runs their code.
+"""
+
+import contextlib
+import importlib
+import inspect
+import json
+import pkgutil
+import sys
+
+# Some packages have thousands of modules, most of which are internal.
+MAX_MODULES = 500
+
+
+def is_public(name):
+    return not name.startswith("_")
+
+
+def signature(obj):
+    try:
+        return str(inspect.signature(obj))
+    except (TypeError, ValueError):
+        return None
+
+
+def describe_function(name, function):
+    return {
+        "name": name,
+        "signature": signature(function),
+        "doc": inspect.getdoc(function),
+    }
+
+
+def describe_class(name, cls):
+    methods = []
+    for method_name, member in vars(cls).items():
+        if method_name != "__init__" and not is_public(method_name):
+            continue
+        method = getattr(cls, method_name, None)
+        if inspect.isroutine(method):
+            methods.append(describe_function(method_name, method))
+    return {
+        "name": name,
+        "signature": signature(cls),
+        "doc": inspect.getdoc(cls),
+        "methods": methods,
+    }
+
+
+def describe_module(module):
+    classes = []
+    functions = []
+    for name, member in vars(module).items():
+        # Members imported from elsewhere are documented where they're defined.
+        if not is_public(name) or getattr(member, "__module__", None) != module.__name__:
+            continue
+        if inspect.isclass(member):
+            classes.append(describe_class(name, member))
+        elif inspect.isroutine(member):
+            functions.append(describe_function(name, member))
+    return {
+        "name": module.__name__,
+        "doc": inspect.getdoc(module),
+        "classes": classes,
+        "functions": functions,
+    }
+
+
+def import_module(name):
+    try:
+        return importlib.import_module(name)
+    except (Exception, SystemExit) as error:
+        print(f"failed to import {name}: {error}", file=sys.stderr)
+        return None
+
+
+def index_package(name):
+    package = import_module(name)
+    if package is None:
+        sys.exit(f"package {name} could not be imported")
+
+    modules = [describe_module(package)]
+    for module_info in pkgutil.walk_packages(
+        getattr(package, "__path__", []), prefix=f"{name}.", onerror=lambda _: None
+    ):
+        if len(modules) >= MAX_MODULES:
+            break
+        if not all(is_public(part) for part in module_info.name.split(".")):
+            continue
+        module = import_module(module_info.name)
+        if module is not None:
+            modules.append(describe_module(module))
+    return modules
+
+
+if __name__ == "__main__":
+    # Modules that print when they're imported mustn't corrupt the output.
+    with contextlib.redirect_stdout(sys.stderr):
+        if len(sys.argv) == 3 and sys.argv[1] == "index":
+            result = index_package(sys.argv[2])
+        else:
+            sys.exit(__doc__)
+    json.dump(result, sys.stdout)
@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use fs::Fs;
+use futures::StreamExt;
+
+use crate::providers::project_database_path;
+use crate::{IndexedDocsDatabase, IndexedDocsProvider, PackageName, ProviderId};
+
+/// Declarations whose body is a block, rather than an expression or type ending in a semicolon.
+const BLOCK_KEYWORDS: &[&str] = &[
+    "class",
+    "interface",
+    "enum",
+    "namespace",
+    "module",
+    "global",
+];
+
+/// Declarations whose bodies contain further declarations.
+const SCOPE_KEYWORDS: &[&str] = &["namespace", "module", "global"];
+
+const MODIFIERS: &[&str] = &["export", "declare", "default", "abstract", "async"];
+
+const DECLARATION_KEYWORDS: &[&str] = &[
+    "function",
+    "class",
+    "interface",
+    "type",
+    "enum",
+    "namespace",
+    "module",
+    "global",
+    "const",
+    "let",
+    "var",
+];
+
+/// Provides the TypeScript declarations installed in the project's `node_modules/@types`.
+pub struct TypeScriptDeclarationsProvider {
+    fs: Arc<dyn Fs>,
+    project_root: PathBuf,
+}
+
+impl TypeScriptDeclarationsProvider {
+    pub fn id() -> ProviderId {
+        ProviderId("typescript".into())
+    }
+
+    pub fn new(fs: Arc<dyn Fs>, project_root: PathBuf) -> Self {
+        Self { fs, project_root }
+    }
+
+    fn types_dir(&self) -> PathBuf {
+        self.project_root.join("node_modules/@types")
+    }
+
+    /// Returns the paths of the declaration files in the given directory and its descendants.
+    async fn declaration_files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut dirs_to_visit = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs_to_visit.pop() {
+            let mut children = self.fs.read_dir(&dir).await?;
+            while let Some(child) = children.next().await {
+                let child = child?;
+                let Some(file_name) = child.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                if file_name.ends_with(".d.ts") || file_name.ends_with(".d.mts") {
+                    files.push(child);
+                } else if file_name != "node_modules" && self.fs.is_dir(&child).await {
+                    dirs_to_visit.push(child);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[async_trait]
+impl IndexedDocsProvider for TypeScriptDeclarationsProvider {
+    fn id(&self) -> ProviderId {
+        Self::id()
+    }
+
+    fn database_path(&self) -> PathBuf {
+        project_database_path("typescript", &self.project_root)
+    }
+
+    async fn suggest_packages(&self) -> Result<Vec<PackageName>> {
+        let mut packages = Vec::new();
+        let mut children = self.fs.read_dir(&self.types_dir()).await?;
+        while let Some(child) = children.next().await {
+            if let Some(name) = child?.file_name().and_then(|name| name.to_str()) {
+                packages.push(PackageName::from(name));
+            }
+        }
+        packages.sort();
+        Ok(packages)
+    }
+
+    async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()> {
+        let package_dir = self.types_dir().join(package.to_string());
+        if !self.fs.is_dir(&package_dir).await {
+            bail!("no type declarations found for {package}. try installing @types/{package}");
+        }
+
+        let mut declarations = BTreeMap::<String, Vec<String>>::new();
+        for path in self.declaration_files(&package_dir).await? {
+            let source = self.fs.load(&path).await?;
+            let module = path
+                .strip_prefix(&package_dir)?
+                .to_string_lossy()
+                .trim_end_matches(".d.ts")
+                .trim_end_matches(".d.mts")
+                .replace('\\', "/");
+            let key = if module == "index" {
+                package.to_string()
+            } else {
+                format!("{package}/{module}")
+            };
+            database
+                .insert(
+                    key.clone(),
+                    format!("# `{key}`\n\n```ts\n{}\n```\n", source.trim()),
+                )
+                .await?;
+
+            for (name, range) in typescript_declarations(&source) {
+                declarations
+                    .entry(format!("{package}.{name}"))
+                    .or_default()
+                    .push(dedent(&source, range));
+            }
+        }
+
+        // Overloads and merged declarations share a key.
+        for (key, declarations) in declarations {
+            database
+                .insert(
+                    key.clone(),
+                    format!("# `{key}`\n\n```ts\n{}\n```\n", declarations.join("\n\n")),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the named declarations in a declaration file, along with their doc comments. The
+/// declarations in namespaces and ambient modules are qualified by the namespace's name.
+fn typescript_declarations(source: &str) -> Vec<(String, Range<usize>)> {
+    let mut declarations = Vec::new();
+    collect_declarations(source, 0..source.len(), "", &mut declarations);
+    declarations
+}
+
+fn collect_declarations(
+    source: &str,
+    scope: Range<usize>,
+    prefix: &str,
+    declarations: &mut Vec<(String, Range<usize>)>,
+) {
+    let mut ix = scope.start;
+    let mut doc_start = None;
+    while ix < scope.end {
+        let rest = &source[ix..scope.end];
+        let trimmed = rest.trim_start();
+        ix += rest.len() - trimmed.len();
+        if trimmed.is_empty() {
+            break;
+        }
+
+        if trimmed.starts_with("/*") {
+            let end = trimmed.find("*/").map_or(trimmed.len(), |end| end + 2);
+            if trimmed.starts_with("/**") {
+                doc_start = Some(ix);
+            }
+            ix += end;
+            continue;
+        }
+        if trimmed.starts_with("//") {
+            ix += trimmed.find('\n').unwrap_or(trimmed.len());
+            continue;
+        }
+
+        let start = doc_start.take().unwrap_or(ix);
+        let head = parse_declaration_head(trimmed);
+        let is_block = head
+            .as_ref()
+            .map_or(false, |(keyword, _)| BLOCK_KEYWORDS.contains(keyword));
+        let (end, body) = statement_end(source, ix..scope.end, is_block);
+
+        if let Some((keyword, name)) = head {
+            let name = match name {
+                Some(name) if prefix.is_empty() => name.to_string(),
+                Some(name) => format!("{prefix}.{name}"),
+                None => prefix.to_string(),
+            };
+            match body {
+                Some(body) if SCOPE_KEYWORDS.contains(&keyword) => {
+                    collect_declarations(source, body, &name, declarations);
+                }
+                _ if !name.is_empty() => declarations.push((name, start..end)),
+                _ => {}
+            }
+        }
+
+        ix = end;
+    }
+}
+
+/// Returns the keyword and name of the declaration at the start of the text, skipping any
+/// modifiers. Global augmentations don't have a name.
+fn parse_declaration_head(text: &str) -> Option<(&'static str, Option<&str>)> {
+    let mut rest = text;
+    loop {
+        let word = identifier(rest)?;
+        rest = rest[word.len()..].trim_start();
+        if MODIFIERS.contains(&word) {
+            continue;
+        }
+
+        let keyword = *DECLARATION_KEYWORDS
+            .iter()
+            .find(|keyword| **keyword == word)?;
+        if keyword == "global" {
+            return Some((keyword, None));
+        }
+
+        // Ambient modules are named by a string literal, e.g. `declare module "fs"`.
+        let name = if let Some(quoted) = rest.strip_prefix(['"', '\'']) {
+            quoted.split(['"', '\'']).next()
+        } else {
+            identifier(rest.trim_start_matches('*'))
+        };
+        return Some((keyword, name));
+    }
+}
+
+/// Returns the end of the statement starting at the range's start, and the range of its body if
+/// it's a block declaration.
+fn statement_end(
+    source: &str,
+    range: Range<usize>,
+    is_block: bool,
+) -> (usize, Option<Range<usize>>) {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut body_start = None;
+    let mut ix = range.start;
+    while ix < range.end {
+        match bytes[ix] {
+            quote @ (b'"' | b'\'' | b'`') => {
+                ix += 1;
+                while ix < range.end && bytes[ix] != quote {
+                    if bytes[ix] == b'\\' {
+                        ix += 1;
+                    }
+                    ix += 1;
+                }
+            }
+            b'/' if bytes.get(ix + 1) == Some(&b'/') => {
+                ix += source[ix..range.end].find('\n').unwrap_or(range.end - ix) - 1;
+            }
+            b'/' if bytes.get(ix + 1) == Some(&b'*') => {
+                ix += source[ix..range.end]
+                    .find("*/")
+                    .map_or(range.end - ix, |end| end + 1);
+            }
+            b'{' | b'(' | b'[' => {
+                if depth == 0 && bytes[ix] == b'{' && is_block && body_start.is_none() {
+                    body_start = Some(ix + 1);
+                }
+                depth += 1;
+            }
+            b'}' | b')' | b']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && bytes[ix] == b'}' {
+                    if let Some(body_start) = body_start {
+                        return (ix + 1, Some(body_start..ix));
+                    }
+                }
+            }
+            b';' if depth == 0 => return (ix + 1, None),
+            _ => {}
+        }
+        ix += 1;
+    }
+    (range.end, None)
+}
+
+fn identifier(text: &str) -> Option<&str> {
+    let end = text
+        .find(|char: char| !char.is_alphanumeric() && char != '_' && char != '$')
+        .unwrap_or(text.len());
+    (end > 0).then(|| &text[..end])
+}
+
+/// Returns the text in the range, with the indentation of its first line removed from each line.
+fn dedent(source: &str, range: Range<usize>) -> String {
+    let line_start = source[..range.start].rfind('\n').map_or(0, |ix| ix + 1);
+    let indent = &source[line_start..range.start];
+    source[range]
+        .lines()
+        .map(|line| line.strip_prefix(indent).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_typescript_declarations() {
+        let source = indoc! {r#"
+            /// <reference types="node" />
+            import { Readable } from "stream";
+
+            /** Reads the entire contents of a file. */
+            export function readFile(path: string): Promise<string>;
+            export function readFile(path: string, encoding: "utf8"): Promise<string>;
+
+            export interface Options {
+                /** Whether to follow symlinks. */
+                follow?: boolean;
+                nested: { depth: number };
+            }
+
+            export type Callback = (error: Error | null) => void;
+
+            declare module "fs/promises" {
+                /**
+                 * Removes a file.
+                 */
+                function unlink(path: string): Promise<void>;
+
+                namespace constants {
+                    const F_OK: number;
+                }
+            }
+
+            declare global {
+                interface Window { fs: unknown }
+            }
+        "#};
+
+        let declarations = typescript_declarations(source)
+            .into_iter()
+            .map(|(name, range)| (name, dedent(source, range)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            declarations,
+            vec![
+                (
+                    "readFile".to_string(),
+                    indoc! {"
+                        /** Reads the entire contents of a file. */
+                        export function readFile(path: string): Promise<string>;"}
+                    .to_string()
+                ),
+                (
+                    "readFile".to_string(),
+                    r#"export function readFile(path: string, encoding: "utf8"): Promise<string>;"#
+                        .to_string()
+                ),
+                (
+                    "Options".to_string(),
+                    indoc! {"
+                        export interface Options {
+                            /** Whether to follow symlinks. */
+                            follow?: boolean;
+                            nested: { depth: number };
+                        }"}
+                    .to_string()
+                ),
+                (
+                    "Callback".to_string(),
+                    "export type Callback = (error: Error | null) => void;".to_string()
+                ),
+                (
+                    "fs/promises.unlink".to_string(),
+                    indoc! {"
+                        /**
+                         * Removes a file.
+                         */
+                        function unlink(path: string): Promise<void>;"}
+                    .to_string()
+                ),
+                (
+                    "fs/promises.constants.F_OK".to_string(),
+                    "const F_OK: number;".to_string()
+                ),
+                (
+                    "Window".to_string(),
+                    "interface Window { fs: unknown }".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use collections::HashMap;
@@ -13,6 +14,9 @@ impl Global for GlobalIndexedDocsRegistry {}
 pub struct IndexedDocsRegistry {
     executor: BackgroundExecutor,
     stores_by_provider: RwLock<HashMap<ProviderId, Arc<IndexedDocsStore>>>,
+    /// The stores of the providers that document a project's own dependencies, by the
+    /// project's root directory.
+    project_stores_by_root: RwLock<HashMap<Arc<Path>, HashMap<ProviderId, Arc<IndexedDocsStore>>>>,
 }
 
 impl IndexedDocsRegistry {
@@ -31,6 +35,7 @@ impl IndexedDocsRegistry {
         Self {
             executor,
             stores_by_provider: RwLock::new(HashMap::default()),
+            project_stores_by_root: RwLock::new(HashMap::default()),
         }
     }
 
@@ -42,6 +47,22 @@ impl IndexedDocsRegistry {
             .collect::<Vec<_>>()
     }
 
+    /// Lists the providers available in the project with the given root, including those that
+    /// aren't specific to any project.
+    pub fn list_project_providers(&self, project_root: &Path) -> Vec<ProviderId> {
+        let mut providers = self.list_providers();
+        if let Some(project_stores) = self.project_stores_by_root.read().get(project_root) {
+            providers.extend(
+                project_stores
+                    .keys()
+                    .filter(|provider| !providers.contains(provider))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            );
+        }
+        providers
+    }
+
     pub fn register_provider(
         &self,
         provider: Box<dyn IndexedDocsProvider + Send + Sync + 'static>,
@@ -55,4 +76,36 @@ impl IndexedDocsRegistry {
     pub fn get_provider_store(&self, provider_id: ProviderId) -> Option<Arc<IndexedDocsStore>> {
         self.stores_by_provider.read().get(&provider_id).cloned()
     }
+
+    /// Registers a provider of the docs of the dependencies of the project with the given root.
+    /// Each project has its own store, so the docs of one project's dependencies are never
+    /// shown in another.
+    pub fn register_project_provider(
+        &self,
+        project_root: Arc<Path>,
+        provider: Box<dyn IndexedDocsProvider + Send + Sync + 'static>,
+    ) {
+        self.project_stores_by_root
+            .write()
+            .entry(project_root)
+            .or_default()
+            .insert(
+                provider.id(),
+                Arc::new(IndexedDocsStore::new(provider, self.executor.clone())),
+            );
+    }
+
+    /// Returns the store of the provider in the project with the given root, falling back to
+    /// the provider's store that isn't specific to any project.
+    pub fn get_project_provider_store(
+        &self,
+        project_root: &Path,
+        provider_id: ProviderId,
+    ) -> Option<Arc<IndexedDocsStore>> {
+        self.project_stores_by_root
+            .read()
+            .get(project_root)
+            .and_then(|project_stores| project_stores.get(&provider_id).cloned())
+            .or_else(|| self.get_provider_store(provider_id))
+    }
 }
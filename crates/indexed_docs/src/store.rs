@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
@@ -46,6 +46,13 @@ pub trait IndexedDocsProvider {
     /// local project or a registry) before a package has been indexed.
     async fn suggest_packages(&self) -> Result<Vec<PackageName>>;
 
+    /// Returns whether indexing a package executes code from the project, such as its
+    /// interpreter or the package itself. Such packages are only indexed when the user runs
+    /// the command that inserts their docs, never while its arguments are being completed.
+    fn executes_project_code(&self) -> bool {
+        false
+    }
+
     /// Indexes the package with the given name.
     async fn index(&self, package: PackageName, database: Arc<IndexedDocsDatabase>) -> Result<()>;
 }
@@ -69,6 +76,21 @@ impl IndexedDocsStore {
             .ok_or_else(|| anyhow!("no indexed docs store found for {provider}"))
     }
 
+    /// Returns the provider's store for the project with the given root, if there's a project.
+    pub fn try_for_project(
+        project_root: Option<&Path>,
+        provider: ProviderId,
+        cx: &AppContext,
+    ) -> Result<Arc<Self>> {
+        let Some(project_root) = project_root else {
+            return Self::try_global(provider, cx);
+        };
+        let registry = IndexedDocsRegistry::global(cx);
+        registry
+            .get_project_provider_store(project_root, provider.clone())
+            .ok_or_else(|| anyhow!("no indexed docs store found for {provider}"))
+    }
+
     pub fn new(
         provider: Box<dyn IndexedDocsProvider + Send + Sync + 'static>,
         executor: BackgroundExecutor,
@@ -92,6 +114,11 @@ impl IndexedDocsStore {
         }
     }
 
+    /// Returns whether indexing a package executes code from the project.
+    pub fn executes_project_code(&self) -> bool {
+        self.provider.executes_project_code()
+    }
+
     pub fn latest_error_for_package(&self, package: &PackageName) -> Option<Arc<str>> {
         self.latest_errors_by_package.read().get(package).cloned()
     }
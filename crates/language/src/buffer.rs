@@ -19,6 +19,7 @@ use crate::{
 use anyhow::{anyhow, Context, Result};
 use async_watch as watch;
 pub use clock::ReplicaId;
+use collections::HashMap;
 use futures::channel::oneshot;
 use gpui::{
     AnyElement, AppContext, EventEmitter, HighlightStyle, ModelContext, Pixels, Task, TaskLabel,
@@ -26,8 +27,10 @@ use gpui::{
 };
 use lsp::LanguageServerId;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use settings::WorktreeId;
+use sha2::{Digest, Sha256};
 use similar::{ChangeTag, TextDiff};
 use smallvec::SmallVec;
 use smol::future::yield_now;
@@ -35,7 +38,7 @@ use std::{
     any::Any,
     cell::Cell,
     cmp::{self, Ordering, Reverse},
-    collections::BTreeMap,
+    collections::{BTreeMap, VecDeque},
     ffi::OsStr,
     fmt,
     future::Future,
@@ -53,9 +56,9 @@ use text::operation_queue::OperationQueue;
 use text::*;
 pub use text::{
     Anchor, Bias, Buffer as TextBuffer, BufferId, BufferSnapshot as TextBufferSnapshot, Edit,
-    OffsetRangeExt, OffsetUtf16, Patch, Point, PointUtf16, Rope, Selection, SelectionGoal,
-    Subscription, TextDimension, TextSummary, ToOffset, ToOffsetUtf16, ToPoint, ToPointUtf16,
-    Transaction, TransactionId, Unclipped,
+    Encoding, OffsetRangeExt, OffsetUtf16, Patch, Point, PointUtf16, Rope, Selection,
+    SelectionGoal, Subscription, TextDimension, TextSummary, ToOffset, ToOffsetUtf16, ToPoint,
+    ToPointUtf16, Transaction, TransactionId, Unclipped,
 };
 use theme::SyntaxTheme;
 #[cfg(any(test, feature = "test-support"))]
@@ -114,6 +117,11 @@ pub struct Buffer {
     completion_triggers_timestamp: clock::Lamport,
     deferred_ops: OperationQueue<Operation>,
     capability: Capability,
+    large_file: bool,
+    undo_history: Option<PersistentUndoHistory>,
+    encoding: Encoding,
+    saved_line_ending: LineEnding,
+    has_mixed_line_endings: bool,
     has_conflict: bool,
     diff_base_version: usize,
     /// Memoize calls to has_changes_since(saved_version).
@@ -390,6 +398,9 @@ pub trait LocalFile: File {
     /// Loads the file's contents from disk.
     fn load(&self, cx: &AppContext) -> Task<Result<String>>;
 
+    /// Loads the file's raw contents from disk, without decoding them.
+    fn load_bytes(&self, cx: &AppContext) -> Task<Result<Vec<u8>>>;
+
     /// Returns true if the file should not be shared with collaborators.
     fn is_private(&self, _: &AppContext) -> bool {
         false
@@ -527,6 +538,99 @@ pub struct Diff {
     edits: Vec<(Range<usize>, Arc<str>)>,
 }
 
+/// A buffer's undo history, captured as plain-text edits so that it can be persisted
+/// and restored onto a buffer with the same contents after a restart.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerializedUndoHistory {
+    /// The [`text_fingerprint`] of the buffer's text after the last transaction.
+    pub fingerprint: String,
+    /// The edits performed by each transaction on the undo stack, oldest first.
+    pub transactions: Vec<Vec<SerializedEdit>>,
+}
+
+/// A single edit within a [`SerializedUndoHistory`] transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerializedEdit {
+    /// The replaced range, in the text preceding this edit. The edits of a transaction
+    /// are applied one after another.
+    pub range: Range<usize>,
+    pub old_text: String,
+    pub new_text: String,
+}
+
+impl SerializedEdit {
+    fn len(&self) -> usize {
+        self.old_text.len() + self.new_text.len()
+    }
+}
+
+impl SerializedUndoHistory {
+    /// Whether the history was captured from the given text, i.e. the text matches its
+    /// fingerprint and each of its transactions can be undone.
+    pub fn applies_to(&self, text: &Rope) -> bool {
+        if self.fingerprint != text_fingerprint(text) {
+            return false;
+        }
+
+        let mut text = text.to_string();
+        for edit in self.transactions.iter().flatten().rev() {
+            let new_range = edit.range.start..edit.range.start + edit.new_text.len();
+            if text.get(new_range.clone()) != Some(edit.new_text.as_str()) {
+                return false;
+            }
+            text.replace_range(new_range, &edit.old_text);
+        }
+        true
+    }
+}
+
+/// The undo history of a buffer whose transactions can outlive it. See
+/// [`Buffer::record_undo_history`].
+struct PersistentUndoHistory {
+    /// The edits performed by each local edit operation, oldest first.
+    recorded_edits: VecDeque<(clock::Lamport, Vec<SerializedEdit>)>,
+    recorded_len: usize,
+    max_len: usize,
+    /// Transactions restored from a previous session, which are undone once the buffer's
+    /// own undo stack is exhausted.
+    restored_transactions: Vec<Vec<SerializedEdit>>,
+    /// The version of the buffer to which the restored transactions apply.
+    restored_version: clock::Global,
+    /// The restored transactions that were undone, keyed by the transaction that undid
+    /// them. Redoing that transaction redoes the restored one.
+    undone_restored_transactions: HashMap<TransactionId, Vec<SerializedEdit>>,
+}
+
+impl PersistentUndoHistory {
+    fn record(&mut self, edit_id: clock::Lamport, edits: Vec<SerializedEdit>) {
+        self.recorded_len += edits.iter().map(SerializedEdit::len).sum::<usize>();
+        self.recorded_edits.push_back((edit_id, edits));
+        while self.recorded_len > self.max_len {
+            let Some((_, edits)) = self.recorded_edits.pop_front() else {
+                break;
+            };
+            self.recorded_len -= edits.iter().map(SerializedEdit::len).sum::<usize>();
+        }
+    }
+
+    /// Returns the edits performed by the given transaction, if they were recorded.
+    fn transaction_edits(&self, transaction: &Transaction) -> Option<Vec<SerializedEdit>> {
+        if let Some(edits) = self.undone_restored_transactions.get(&transaction.id) {
+            return Some(edits.clone());
+        }
+
+        let mut edits = Vec::new();
+        for edit_id in &transaction.edit_ids {
+            let ix = self
+                .recorded_edits
+                .binary_search_by_key(edit_id, |(id, _)| *id)
+                .ok()?;
+            edits.extend_from_slice(&self.recorded_edits[ix].1);
+        }
+        Some(edits)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct DiagnosticEndpoint {
     offset: usize,
@@ -630,8 +734,14 @@ impl Buffer {
             rpc::proto::LineEnding::from_i32(message.line_ending)
                 .ok_or_else(|| anyhow!("missing line_ending"))?,
         ));
+        this.saved_line_ending = this.line_ending();
         this.saved_version = proto::deserialize_version(&message.saved_version);
         this.saved_mtime = message.saved_mtime.map(|time| time.into());
+        this.large_file = message.large_file;
+        this.encoding = proto::deserialize_encoding(
+            rpc::proto::Encoding::from_i32(message.encoding)
+                .ok_or_else(|| anyhow!("invalid encoding"))?,
+        );
         Ok(this)
     }
 
@@ -645,6 +755,8 @@ impl Buffer {
             line_ending: proto::serialize_line_ending(self.line_ending()) as i32,
             saved_version: proto::serialize_version(&self.saved_version),
             saved_mtime: self.saved_mtime.map(|time| time.into()),
+            large_file: self.large_file,
+            encoding: proto::serialize_encoding(self.encoding) as i32,
         }
     }
 
@@ -723,6 +835,7 @@ impl Buffer {
         Self {
             saved_mtime,
             saved_version: buffer.version(),
+            saved_line_ending: buffer.line_ending(),
             preview_version: buffer.version(),
             reload_task: None,
             transaction_depth: 0,
@@ -739,6 +852,10 @@ impl Buffer {
             git_diff: git::diff::BufferDiff::new(),
             file,
             capability,
+            large_file: false,
+            undo_history: None,
+            encoding: Encoding::default(),
+            has_mixed_line_endings: false,
             syntax_map: Mutex::new(SyntaxMap::new()),
             parsing_in_background: false,
             non_text_state_update_count: 0,
@@ -830,6 +947,67 @@ impl Buffer {
         cx.emit(Event::CapabilityChanged)
     }
 
+    /// Whether the buffer was opened in large-file mode, in which it isn't
+    /// parsed and its Git diff isn't computed.
+    pub fn is_large_file(&self) -> bool {
+        self.large_file
+    }
+
+    /// Marks the buffer as a large file, discarding its syntax tree and Git diff.
+    pub fn set_large_file(&mut self, large_file: bool, cx: &mut ModelContext<Self>) {
+        self.large_file = large_file;
+        if large_file {
+            self.undo_history = None;
+            self.syntax_map.lock().clear();
+            self.git_diff = git::diff::BufferDiff::new();
+            self.non_text_state_update_count += 1;
+        } else {
+            self.reparse(cx);
+            if let Some(recalc_task) = self.git_diff_recalc(cx) {
+                recalc_task.detach();
+            }
+        }
+        cx.notify();
+    }
+
+    /// The character encoding of the buffer's file on disk.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Assigns the encoding with which the buffer's file is written when it's saved.
+    pub fn set_encoding(&mut self, encoding: Encoding, cx: &mut ModelContext<Self>) {
+        self.encoding = encoding;
+        cx.notify();
+    }
+
+    /// Whether the buffer's file had lines terminated by different kinds of line
+    /// separators when it was loaded. They're all converted to the buffer's line
+    /// ending when it's saved.
+    pub fn has_mixed_line_endings(&self) -> bool {
+        self.has_mixed_line_endings
+    }
+
+    pub fn set_has_mixed_line_endings(
+        &mut self,
+        has_mixed_line_endings: bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.has_mixed_line_endings = has_mixed_line_endings;
+        cx.notify();
+    }
+
+    /// Assigns the line ending with which the buffer's lines are terminated when
+    /// it's saved. The buffer is dirty until it's saved with that line ending.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding, cx: &mut ModelContext<Self>) {
+        let was_dirty = self.is_dirty();
+        self.text.set_line_ending(line_ending);
+        if self.is_dirty() != was_dirty {
+            cx.emit(Event::DirtyChanged);
+        }
+        cx.notify();
+    }
+
     /// This method is called to signal that the buffer has been saved.
     pub fn did_save(
         &mut self,
@@ -838,6 +1016,8 @@ impl Buffer {
         cx: &mut ModelContext<Self>,
     ) {
         self.saved_version = version;
+        self.saved_line_ending = self.line_ending();
+        self.has_mixed_line_endings = false;
         self.has_unsaved_edits
             .set((self.saved_version().clone(), false));
         self.has_conflict = false;
@@ -856,19 +1036,37 @@ impl Buffer {
     pub fn reload(
         &mut self,
         cx: &mut ModelContext<Self>,
+    ) -> oneshot::Receiver<Option<Transaction>> {
+        self.reload_with_encoding(self.encoding, cx)
+    }
+
+    /// Reloads the contents of the buffer from disk, decoding them with the given encoding,
+    /// which becomes the buffer's encoding once they're reloaded.
+    pub fn reload_with_encoding(
+        &mut self,
+        encoding: Encoding,
+        cx: &mut ModelContext<Self>,
     ) -> oneshot::Receiver<Option<Transaction>> {
         let (tx, rx) = futures::channel::oneshot::channel();
         let prev_version = self.text.version();
         self.reload_task = Some(cx.spawn(|this, mut cx| async move {
-            let Some((new_mtime, new_text)) = this.update(&mut cx, |this, cx| {
+            let Some((new_mtime, new_bytes)) = this.update(&mut cx, |this, cx| {
                 let file = this.file.as_ref()?.as_local()?;
-                Some((file.mtime(), file.load(cx)))
+                Some((file.mtime(), file.load_bytes(cx)))
             })?
             else {
                 return Ok(());
             };
 
-            let new_text = new_text.await?;
+            let new_bytes = new_bytes.await?;
+            let (new_text, has_mixed_line_endings) = cx
+                .background_executor()
+                .spawn(async move {
+                    let new_text = encoding.decode(&new_bytes)?;
+                    let has_mixed_line_endings = LineEnding::is_mixed(&new_text);
+                    anyhow::Ok((new_text, has_mixed_line_endings))
+                })
+                .await?;
             let diff = this
                 .update(&mut cx, |this, cx| this.diff(new_text.clone(), cx))?
                 .await;
@@ -878,6 +1076,8 @@ impl Buffer {
                     this.apply_diff(diff, cx);
                     tx.send(this.finalize_last_transaction().cloned()).ok();
                     this.has_conflict = false;
+                    this.encoding = encoding;
+                    this.has_mixed_line_endings = has_mixed_line_endings;
                     this.did_reload(this.version(), this.line_ending(), new_mtime, cx);
                 } else {
                     if !diff.edits.is_empty()
@@ -898,6 +1098,27 @@ impl Buffer {
         rx
     }
 
+    /// Appends the next chunk of a file whose text is loaded one chunk at a time. Appending it
+    /// can't be undone, and doesn't make the buffer dirty.
+    pub fn append_loaded_chunk(&mut self, chunk: &str, cx: &mut ModelContext<Self>) {
+        if chunk.is_empty() {
+            return;
+        }
+        let was_dirty = self.is_dirty();
+        let end = self.len();
+        self.finalize_last_transaction();
+        self.start_transaction();
+        self.edit([(end..end, chunk)], None, cx);
+        if let Some(transaction_id) = self.end_transaction(cx) {
+            self.forget_transaction(transaction_id);
+        }
+        let became_dirty = !was_dirty && self.is_dirty();
+        self.did_reload(self.version(), self.line_ending(), self.saved_mtime, cx);
+        if became_dirty {
+            cx.emit(Event::DirtyChanged);
+        }
+    }
+
     /// This method is called to signal that the buffer has been reloaded.
     pub fn did_reload(
         &mut self,
@@ -910,6 +1131,7 @@ impl Buffer {
         self.has_unsaved_edits
             .set((self.saved_version.clone(), false));
         self.text.set_line_ending(line_ending);
+        self.saved_line_ending = line_ending;
         self.saved_mtime = mtime;
         cx.emit(Event::Reloaded);
         cx.notify();
@@ -989,6 +1211,9 @@ impl Buffer {
 
     /// Recomputes the Git diff status.
     pub fn git_diff_recalc(&mut self, cx: &mut ModelContext<Self>) -> Option<Task<()>> {
+        if self.large_file {
+            return None;
+        }
         let diff_base = self.diff_base.clone()?;
         let snapshot = self.snapshot();
 
@@ -1072,7 +1297,7 @@ impl Buffer {
     /// for the same buffer, we only initiate a new parse if we are not already
     /// parsing in the background.
     pub fn reparse(&mut self, cx: &mut ModelContext<Self>) {
-        if self.parsing_in_background {
+        if self.parsing_in_background || self.large_file {
             return;
         }
         let language = if let Some(language) = self.language.clone() {
@@ -1423,68 +1648,23 @@ impl Buffer {
 
     /// Spawns a background task that asynchronously computes a `Diff` between the buffer's text
     /// and the given new text.
+    ///
+    /// The diff adopts the new text's line ending, unless the new text has a single line, in
+    /// which case the buffer's line ending is kept.
     pub fn diff(&self, mut new_text: String, cx: &AppContext) -> Task<Diff> {
         let old_text = self.as_rope().clone();
+        let old_line_ending = self.line_ending();
         let base_version = self.version();
         cx.background_executor()
             .spawn_labeled(*BUFFER_DIFF_TASK, async move {
                 let old_text = old_text.to_string();
-                let line_ending = LineEnding::detect(&new_text);
+                let line_ending = LineEnding::try_detect(&new_text).unwrap_or(old_line_ending);
                 LineEnding::normalize(&mut new_text);
 
-                let diff = TextDiff::from_chars(old_text.as_str(), new_text.as_str());
-                let empty: Arc<str> = Arc::default();
-
-                let mut edits = Vec::new();
-                let mut old_offset = 0;
-                let mut new_offset = 0;
-                let mut last_edit: Option<(Range<usize>, Range<usize>)> = None;
-                for change in diff.iter_all_changes().map(Some).chain([None]) {
-                    if let Some(change) = &change {
-                        let len = change.value().len();
-                        match change.tag() {
-                            ChangeTag::Equal => {
-                                old_offset += len;
-                                new_offset += len;
-                            }
-                            ChangeTag::Delete => {
-                                let old_end_offset = old_offset + len;
-                                if let Some((last_old_range, _)) = &mut last_edit {
-                                    last_old_range.end = old_end_offset;
-                                } else {
-                                    last_edit =
-                                        Some((old_offset..old_end_offset, new_offset..new_offset));
-                                }
-                                old_offset = old_end_offset;
-                            }
-                            ChangeTag::Insert => {
-                                let new_end_offset = new_offset + len;
-                                if let Some((_, last_new_range)) = &mut last_edit {
-                                    last_new_range.end = new_end_offset;
-                                } else {
-                                    last_edit =
-                                        Some((old_offset..old_offset, new_offset..new_end_offset));
-                                }
-                                new_offset = new_end_offset;
-                            }
-                        }
-                    }
-
-                    if let Some((old_range, new_range)) = &last_edit {
-                        if old_offset > old_range.end
-                            || new_offset > new_range.end
-                            || change.is_none()
-                        {
-                            let text = if new_range.is_empty() {
-                                empty.clone()
-                            } else {
-                                new_text[new_range.clone()].into()
-                            };
-                            edits.push((old_range.clone(), text));
-                            last_edit.take();
-                        }
-                    }
-                }
+                let edits = text_edits(&old_text, &new_text)
+                    .into_iter()
+                    .map(|(old_range, new_range)| (old_range, new_text[new_range].into()))
+                    .collect();
 
                 Diff {
                     base_version,
@@ -1574,6 +1754,140 @@ impl Buffer {
         self.end_transaction(cx)
     }
 
+    /// Starts recording the edits performed on the buffer, so that its undo history can be
+    /// captured by [`Buffer::serialize_undo_history`]. The oldest edits are dropped once the
+    /// recorded text exceeds `max_len` bytes. Large files aren't recorded. Returns whether
+    /// the recording was started, i.e. the buffer wasn't being recorded already.
+    pub fn record_undo_history(&mut self, max_len: usize) -> bool {
+        if self.large_file || self.undo_history.is_some() {
+            return false;
+        }
+        self.undo_history = Some(PersistentUndoHistory {
+            recorded_edits: VecDeque::new(),
+            recorded_len: 0,
+            max_len,
+            restored_transactions: Vec::new(),
+            restored_version: self.version(),
+            undone_restored_transactions: HashMap::default(),
+        });
+        true
+    }
+
+    /// Captures at most `max_transactions` of the most recent transactions on the undo stack,
+    /// along with any restored transactions preceding them, as a [`SerializedUndoHistory`].
+    /// Only transactions whose edits were recorded are captured, see
+    /// [`Buffer::record_undo_history`].
+    pub fn serialize_undo_history(
+        &self,
+        max_transactions: usize,
+        cx: &AppContext,
+    ) -> Task<SerializedUndoHistory> {
+        let mut transactions = Vec::new();
+        if let Some(history) = self.undo_history.as_ref() {
+            let mut len = 0;
+            let mut is_complete = true;
+            for entry in self.text.undo_stack().iter().rev() {
+                let Some(edits) = history.transaction_edits(entry.transaction()) else {
+                    is_complete = false;
+                    break;
+                };
+                len += edits.iter().map(SerializedEdit::len).sum::<usize>();
+                if transactions.len() == max_transactions || len > history.max_len {
+                    is_complete = false;
+                    break;
+                }
+                transactions.push(edits);
+            }
+            if is_complete {
+                for transaction in history.restored_transactions.iter().rev() {
+                    len += transaction.iter().map(SerializedEdit::len).sum::<usize>();
+                    if transactions.len() == max_transactions || len > history.max_len {
+                        break;
+                    }
+                    transactions.push(transaction.clone());
+                }
+            }
+            transactions.reverse();
+        }
+
+        let text = self.as_rope().clone();
+        cx.background_executor().spawn(async move {
+            SerializedUndoHistory {
+                fingerprint: text_fingerprint(&text),
+                transactions,
+            }
+        })
+    }
+
+    /// Restores a [`SerializedUndoHistory`], so that its transactions are undone once the
+    /// buffer's own undo stack is exhausted. The buffer's text isn't changed. The history is
+    /// only restored if the undo history is being recorded, the buffer has no history of its
+    /// own, and its version is unchanged since the history was found to apply to its text
+    /// with [`SerializedUndoHistory::applies_to`]. Returns whether the history was restored.
+    pub fn restore_undo_history(
+        &mut self,
+        history: SerializedUndoHistory,
+        version: &clock::Global,
+    ) -> bool {
+        if self.text.peek_undo_stack().is_some()
+            || self.text.peek_redo_stack().is_some()
+            || self.version != *version
+        {
+            return false;
+        }
+        let Some(undo_history) = self.undo_history.as_mut() else {
+            return false;
+        };
+        undo_history.restored_transactions = history.transactions;
+        undo_history.restored_version = version.clone();
+        undo_history.undone_restored_transactions.clear();
+        true
+    }
+
+    /// Undoes the most recent restored transaction, by applying the inverse of its edits
+    /// in a transaction that is placed on the redo stack. Redoing that transaction undoes
+    /// its edits, which redoes the restored transaction.
+    fn undo_restored_transaction(&mut self, cx: &mut ModelContext<Self>) -> Option<TransactionId> {
+        let history = self.undo_history.as_mut()?;
+        if self
+            .text
+            .edits_since::<usize>(&history.restored_version)
+            .next()
+            .is_some()
+        {
+            // The restored transactions no longer apply to the buffer's text.
+            history.restored_transactions.clear();
+            return None;
+        }
+        let transaction = history.restored_transactions.pop()?;
+
+        // Don't record the inverse edits, as the restored transaction stands for them.
+        let mut history = self.undo_history.take()?;
+        let redo_stack = self.text.take_redo_stack();
+        self.finalize_last_transaction();
+        self.start_transaction();
+        for edit in transaction.iter().rev() {
+            let start = edit.range.start;
+            self.edit(
+                [(start..start + edit.new_text.len(), edit.old_text.as_str())],
+                None,
+                cx,
+            );
+        }
+        let transaction_id = self.end_transaction(cx);
+        if let Some(transaction_id) = transaction_id {
+            self.finalize_last_transaction();
+            self.text.move_to_redo_stack(transaction_id);
+            history
+                .undone_restored_transactions
+                .insert(transaction_id, transaction);
+        }
+        self.text.restore_redo_stack(redo_stack);
+        history.restored_version = self.version();
+        self.undo_history = Some(history);
+        transaction_id
+    }
+
     fn has_unsaved_edits(&self) -> bool {
         let (last_version, has_unsaved_edits) = self.has_unsaved_edits.take();
 
@@ -1594,6 +1908,7 @@ impl Buffer {
         self.capability != Capability::ReadOnly
             && (self.has_conflict
                 || self.has_unsaved_edits()
+                || (self.file.is_some() && self.line_ending() != self.saved_line_ending)
                 || self
                     .file
                     .as_ref()
@@ -1815,8 +2130,47 @@ impl Buffer {
         let autoindent_request = autoindent_mode
             .and_then(|mode| self.language.as_ref().map(|_| (self.snapshot(), mode)));
 
+        let old_texts = self.undo_history.as_ref().and_then(|history| {
+            let len = edits
+                .iter()
+                .map(|(range, new_text)| range.len() + new_text.len())
+                .sum::<usize>();
+            (len <= history.max_len).then(|| {
+                edits
+                    .iter()
+                    .map(|(range, _)| self.text_for_range(range.clone()).collect::<String>())
+                    .collect::<Vec<_>>()
+            })
+        });
         let edit_operation = self.text.edit(edits.iter().cloned());
         let edit_id = edit_operation.timestamp();
+        if let Some(history) = self.undo_history.as_mut() {
+            if let Some(old_texts) = old_texts {
+                let mut delta = 0_isize;
+                let recorded_edits = edits
+                    .iter()
+                    .zip(old_texts)
+                    .zip(&edit_operation.as_edit().unwrap().new_text)
+                    .map(|(((range, _), old_text), new_text)| {
+                        // Each edit is recorded relative to the text preceding it, rather
+                        // than the text preceding the operation.
+                        let start = (range.start as isize + delta) as usize;
+                        delta += new_text.len() as isize - old_text.len() as isize;
+                        SerializedEdit {
+                            range: start..start + old_text.len(),
+                            old_text,
+                            new_text: new_text.to_string(),
+                        }
+                    })
+                    .collect();
+                history.record(edit_id, recorded_edits);
+            } else {
+                // The edit is too large to be recorded, so none of the preceding edits can
+                // be undone after a restart either.
+                history.recorded_edits.clear();
+                history.recorded_len = 0;
+            }
+        }
 
         if let Some((before_edit, mode)) = autoindent_request {
             let mut delta = 0isize;
@@ -2130,7 +2484,7 @@ impl Buffer {
             self.did_edit(&old_version, was_dirty, cx);
             Some(transaction_id)
         } else {
-            None
+            self.undo_restored_transaction(cx)
         }
     }
 
@@ -2709,6 +3063,51 @@ impl BufferSnapshot {
         })
     }
 
+    /// Returns the ranges within the given range that the language's overrides query
+    /// assigns to the scope with the given name, such as `comment` or `string`.
+    ///
+    /// Overlapping ranges, e.g. from nested languages, are merged, and the result is sorted.
+    pub fn override_scope_ranges<T: ToOffset>(
+        &self,
+        range: Range<T>,
+        scope_name: &str,
+    ) -> Vec<Range<usize>> {
+        let range = range.to_offset(self);
+        let mut matches = self.syntax.matches(range, &self.text, |grammar| {
+            grammar.override_config.as_ref().map(|config| &config.query)
+        });
+        let configs = matches
+            .grammars()
+            .iter()
+            .map(|grammar| grammar.override_config.as_ref().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut ranges = Vec::<Range<usize>>::new();
+        while let Some(mat) = matches.peek() {
+            let config = configs[mat.grammar_index];
+            for capture in mat.captures {
+                if config
+                    .values
+                    .get(&capture.index)
+                    .map_or(false, |(name, _)| name == scope_name)
+                {
+                    ranges.push(capture.node.byte_range());
+                }
+            }
+            matches.advance();
+        }
+
+        ranges.sort_unstable_by_key(|range| (range.start, Reverse(range.end)));
+        let mut merged = Vec::<Range<usize>>::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
     /// Returns a tuple of the range and character kind of the word
     /// surrounding the given position.
     pub fn surrounding_word<T: ToOffset>(&self, start: T) -> (Range<usize>, Option<CharKind>) {
@@ -4288,6 +4687,64 @@ impl CharClassifier {
     }
 }
 
+/// Computes a fingerprint of the given text, which can be used to check whether
+/// state persisted for a buffer still applies to its contents.
+pub fn text_fingerprint(text: &Rope) -> String {
+    let mut hasher = Sha256::new();
+    for chunk in text.chunks() {
+        hasher.update(chunk.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Computes the minimal character-level edits that transform `old_text` into `new_text`,
+/// returning each edit's range in the old text and the corresponding range in the new text.
+fn text_edits(old_text: &str, new_text: &str) -> Vec<(Range<usize>, Range<usize>)> {
+    let diff = TextDiff::from_chars(old_text, new_text);
+
+    let mut edits = Vec::new();
+    let mut old_offset = 0;
+    let mut new_offset = 0;
+    let mut last_edit: Option<(Range<usize>, Range<usize>)> = None;
+    for change in diff.iter_all_changes().map(Some).chain([None]) {
+        if let Some(change) = &change {
+            let len = change.value().len();
+            match change.tag() {
+                ChangeTag::Equal => {
+                    old_offset += len;
+                    new_offset += len;
+                }
+                ChangeTag::Delete => {
+                    let old_end_offset = old_offset + len;
+                    if let Some((last_old_range, _)) = &mut last_edit {
+                        last_old_range.end = old_end_offset;
+                    } else {
+                        last_edit = Some((old_offset..old_end_offset, new_offset..new_offset));
+                    }
+                    old_offset = old_end_offset;
+                }
+                ChangeTag::Insert => {
+                    let new_end_offset = new_offset + len;
+                    if let Some((_, last_new_range)) = &mut last_edit {
+                        last_new_range.end = new_end_offset;
+                    } else {
+                        last_edit = Some((old_offset..old_offset, new_offset..new_end_offset));
+                    }
+                    new_offset = new_end_offset;
+                }
+            }
+        }
+
+        if let Some((old_range, new_range)) = &last_edit {
+            if old_offset > old_range.end || new_offset > new_range.end || change.is_none() {
+                edits.push((old_range.clone(), new_range.clone()));
+                last_edit.take();
+            }
+        }
+    }
+    edits
+}
+
 /// Find all of the ranges of whitespace that occur at the ends of lines
 /// in the given rope.
 ///
@@ -367,6 +367,123 @@ async fn test_apply_diff(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_serialize_and_restore_undo_history(cx: &mut TestAppContext) {
+    let buffer = cx.new_model(|cx| {
+        let mut buffer = Buffer::local("one two three", cx);
+        assert!(buffer.record_undo_history(usize::MAX));
+        assert!(!buffer.record_undo_history(usize::MAX));
+        buffer
+    });
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(4..7, "TWO")], None, cx);
+        buffer.edit([(0..0, "zero "), (13..13, "\nfour")], None, cx);
+        buffer.edit([(9..13, "")], None, cx);
+    });
+    let history = buffer
+        .update(cx, |buffer, cx| {
+            buffer.serialize_undo_history(usize::MAX, cx)
+        })
+        .await;
+    assert_eq!(
+        history.transactions,
+        [
+            vec![serialized_edit(4..7, "two", "TWO")],
+            vec![
+                serialized_edit(0..0, "", "zero "),
+                serialized_edit(18..18, "", "\nfour")
+            ],
+            vec![serialized_edit(9..13, "TWO ", "")],
+        ]
+    );
+
+    // Only the most recent transactions are kept when the history is bounded.
+    let bounded_history = buffer
+        .update(cx, |buffer, cx| buffer.serialize_undo_history(2, cx))
+        .await;
+    assert_eq!(bounded_history.transactions, history.transactions[1..]);
+
+    let text = buffer.read_with(cx, |buffer, _| buffer.text());
+    assert_eq!(text, "zero one three\nfour");
+    assert!(history.applies_to(&Rope::from(text.as_str())));
+    assert!(!history.applies_to(&Rope::from("zero one three\nfive")));
+
+    // Restoring the history makes its transactions undoable without editing the buffer.
+    let restored_buffer = cx.new_model(|cx| {
+        let mut buffer = Buffer::local(text.clone(), cx);
+        buffer.record_undo_history(usize::MAX);
+        buffer
+    });
+    restored_buffer.update(cx, |buffer, cx| {
+        let version = buffer.version();
+        assert!(buffer.restore_undo_history(history.clone(), &version));
+        assert_eq!(buffer.version(), version);
+        assert!(!buffer.is_dirty());
+
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), "zero one TWO three\nfour");
+        assert!(buffer.is_dirty());
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), "one TWO three");
+        buffer.redo(cx);
+        assert_eq!(buffer.text(), "zero one TWO three\nfour");
+        buffer.undo(cx);
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), "one two three");
+        assert_eq!(buffer.undo(cx), None);
+
+        buffer.redo(cx);
+        buffer.redo(cx);
+        buffer.redo(cx);
+        assert_eq!(buffer.text(), text);
+        assert!(!buffer.is_dirty());
+    });
+
+    // Restored transactions are captured again, along with the ones that follow them.
+    restored_buffer.update(cx, |buffer, cx| {
+        buffer.undo(cx);
+        buffer.edit([(0..5, "")], None, cx);
+        assert_eq!(buffer.text(), "one TWO three\nfour");
+    });
+    let restored_history = restored_buffer
+        .update(cx, |buffer, cx| {
+            buffer.serialize_undo_history(usize::MAX, cx)
+        })
+        .await;
+    assert_eq!(
+        restored_history.transactions,
+        [
+            history.transactions[0].clone(),
+            history.transactions[1].clone(),
+            vec![serialized_edit(0..5, "zero ", "")],
+        ]
+    );
+
+    // Restored transactions are discarded once the buffer is edited outside of its undo
+    // history, e.g. by a collaborator.
+    let other_buffer = cx.new_model(|cx| {
+        let mut buffer = Buffer::local(text.clone(), cx);
+        buffer.record_undo_history(usize::MAX);
+        buffer
+    });
+    other_buffer.update(cx, |buffer, cx| {
+        let version = buffer.version();
+        assert!(buffer.restore_undo_history(history, &version));
+        buffer.edit([(0..0, "// ")], None, cx);
+        buffer.forget_transaction(buffer.peek_undo_stack().unwrap().transaction_id());
+        assert_eq!(buffer.undo(cx), None);
+        assert_eq!(buffer.text(), format!("// {text}"));
+    });
+}
+
+fn serialized_edit(range: Range<usize>, old_text: &str, new_text: &str) -> SerializedEdit {
+    SerializedEdit {
+        range,
+        old_text: old_text.into(),
+        new_text: new_text.into(),
+    }
+}
+
 #[gpui::test(iterations = 10)]
 async fn test_normalize_whitespace(cx: &mut gpui::TestAppContext) {
     let text = [
@@ -2158,6 +2275,60 @@ fn test_language_scope_at_with_rust(cx: &mut AppContext) {
     });
 }
 
+#[gpui::test]
+fn test_override_scope_ranges(cx: &mut AppContext) {
+    init_settings(cx, |_| {});
+
+    cx.new_model(|cx| {
+        let language = Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::language()),
+        )
+        .with_override_query(
+            r#"
+                (string_literal) @string
+                [(line_comment) (block_comment)] @comment
+            "#,
+        )
+        .unwrap();
+
+        let text = r#"
+            // one
+            const S: &str = "two"; /* three */
+        "#
+        .unindent();
+
+        let buffer = Buffer::local(text.clone(), cx).with_language(Arc::new(language), cx);
+        let snapshot = buffer.snapshot();
+        let text_for_ranges = |ranges: Vec<Range<usize>>| {
+            ranges
+                .into_iter()
+                .map(|range| &text[range])
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            text_for_ranges(snapshot.override_scope_ranges(0..text.len(), "comment")),
+            ["// one", "/* three */"]
+        );
+        assert_eq!(
+            text_for_ranges(snapshot.override_scope_ranges(0..text.len(), "string")),
+            ["\"two\""]
+        );
+        assert_eq!(
+            text_for_ranges(
+                snapshot.override_scope_ranges(text.find("const").unwrap()..text.len(), "comment")
+            ),
+            ["/* three */"]
+        );
+
+        buffer
+    });
+}
+
 #[gpui::test]
 fn test_language_scope_at_with_combined_injections(cx: &mut AppContext) {
     init_settings(cx, |_| {});
@@ -2286,6 +2457,26 @@ fn test_serialization(cx: &mut gpui::AppContext) {
     assert_eq!(buffer2.read(cx).text(), "abcDF");
 }
 
+#[gpui::test]
+fn test_large_file_serialization(cx: &mut AppContext) {
+    let buffer = cx.new_model(|cx| {
+        let mut buffer = Buffer::local("fn main() {}", cx).with_language(Arc::new(rust_lang()), cx);
+        buffer.set_large_file(true, cx);
+        buffer
+    });
+
+    let state = buffer.read(cx).to_proto(cx);
+    let remote_buffer = cx.new_model(|cx| {
+        Buffer::from_proto(1, Capability::ReadWrite, state, None)
+            .unwrap()
+            .with_language(Arc::new(rust_lang()), cx)
+    });
+    remote_buffer.update(cx, |buffer, _| {
+        assert!(buffer.is_large_file());
+        assert!(buffer.snapshot().syntax_layers().next().is_none());
+    });
+}
+
 #[gpui::test]
 async fn test_find_matching_indent(cx: &mut TestAppContext) {
     cx.update(|cx| init_settings(cx, |_| {}));
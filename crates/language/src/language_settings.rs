@@ -1,6 +1,6 @@
 //! Provides `language`-related settings.
 
-use crate::{File, Language, LanguageName, LanguageServerName};
+use crate::{File, Language, LanguageName, LanguageServerName, LineEnding};
 use anyhow::Result;
 use collections::{HashMap, HashSet};
 use core::slice;
@@ -87,6 +87,8 @@ pub struct LanguageSettings {
     /// Whether or not to ensure there's a single newline at the end of a buffer
     /// when saving it.
     pub ensure_final_newline_on_save: bool,
+    /// The line ending with which new files are created.
+    pub line_ending: LineEndingSetting,
     /// How to perform a buffer format.
     pub formatter: SelectedFormatter,
     /// Zed's Prettier integration settings.
@@ -269,6 +271,12 @@ pub struct LanguageSettingsContent {
     /// Default: true
     #[serde(default)]
     pub ensure_final_newline_on_save: Option<bool>,
+    /// The line ending with which new files are created. Existing files keep
+    /// their line ending.
+    ///
+    /// Default: system
+    #[serde(default)]
+    pub line_ending: Option<LineEndingSetting>,
     /// How to perform a buffer format.
     ///
     /// Default: auto
@@ -495,6 +503,30 @@ impl<'de> Deserialize<'de> for FormatOnSave {
     }
 }
 
+/// The line ending with which new files are created.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEndingSetting {
+    /// The platform's line ending: CRLF on Windows, and LF elsewhere.
+    #[default]
+    System,
+    /// Line feeds (`\n`).
+    Lf,
+    /// Carriage returns followed by line feeds (`\r\n`).
+    Crlf,
+}
+
+impl LineEndingSetting {
+    /// Returns the line ending this setting resolves to on the current platform.
+    pub fn line_ending(self) -> LineEnding {
+        match self {
+            LineEndingSetting::System => LineEnding::default(),
+            LineEndingSetting::Lf => LineEnding::Unix,
+            LineEndingSetting::Crlf => LineEnding::Windows,
+        }
+    }
+}
+
 /// Controls how whitespace should be displayedin the editor.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -1060,6 +1092,7 @@ fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent
         &mut settings.ensure_final_newline_on_save,
         src.ensure_final_newline_on_save,
     );
+    merge(&mut settings.line_ending, src.line_ending);
     merge(
         &mut settings.enable_language_server,
         src.enable_language_server,
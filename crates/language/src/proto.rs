@@ -27,6 +27,32 @@ pub fn serialize_line_ending(message: text::LineEnding) -> proto::LineEnding {
     }
 }
 
+/// Deserializes a `[text::Encoding]` from the RPC representation.
+pub fn deserialize_encoding(message: proto::Encoding) -> text::Encoding {
+    match message {
+        proto::Encoding::Utf8 => text::Encoding::Utf8,
+        proto::Encoding::Utf8Bom => text::Encoding::Utf8Bom,
+        proto::Encoding::Utf16Le => text::Encoding::Utf16Le,
+        proto::Encoding::Utf16Be => text::Encoding::Utf16Be,
+        proto::Encoding::Windows1252 => text::Encoding::Windows1252,
+        proto::Encoding::ShiftJis => text::Encoding::ShiftJis,
+        proto::Encoding::Gbk => text::Encoding::Gbk,
+    }
+}
+
+/// Serializes a [`text::Encoding`] to be sent over RPC.
+pub fn serialize_encoding(encoding: text::Encoding) -> proto::Encoding {
+    match encoding {
+        text::Encoding::Utf8 => proto::Encoding::Utf8,
+        text::Encoding::Utf8Bom => proto::Encoding::Utf8Bom,
+        text::Encoding::Utf16Le => proto::Encoding::Utf16Le,
+        text::Encoding::Utf16Be => proto::Encoding::Utf16Be,
+        text::Encoding::Windows1252 => proto::Encoding::Windows1252,
+        text::Encoding::ShiftJis => proto::Encoding::ShiftJis,
+        text::Encoding::Gbk => proto::Encoding::Gbk,
+    }
+}
+
 /// Serializes a [`crate::Operation`] to be sent over RPC.
 pub fn serialize_operation(operation: &crate::Operation) -> proto::Operation {
     proto::Operation {
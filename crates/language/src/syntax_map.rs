@@ -205,9 +205,10 @@ struct ChangedRegion {
 #[derive(Default)]
 struct ChangeRegionSet(Vec<ChangedRegion>);
 
-struct TextProvider<'a>(&'a Rope);
+/// Provides the text of a rope to tree-sitter queries, without copying it.
+pub struct TextProvider<'a>(pub &'a Rope);
 
-struct ByteChunks<'a>(text::Chunks<'a>);
+pub struct ByteChunks<'a>(text::Chunks<'a>);
 
 pub(crate) struct QueryCursorHandle(Option<QueryCursor>);
 
@@ -3,7 +3,7 @@ use crate::{
     LanguageModelId, LanguageModelName, LanguageModelProvider, LanguageModelProviderId,
     LanguageModelProviderName, LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role,
 };
-use crate::{LanguageModelCompletionEvent, LanguageModelToolUse, StopReason};
+use crate::{LanguageModelCompletionEvent, LanguageModelToolUse, StopReason, TokenUsage};
 use anthropic::{AnthropicError, ContentDelta, Event, ResponseContent};
 use anyhow::{anyhow, Context as _, Result};
 use collections::{BTreeMap, HashMap};
@@ -443,12 +443,14 @@ pub fn map_to_language_model_completion_events(
     struct State {
         events: Pin<Box<dyn Send + Stream<Item = Result<Event, AnthropicError>>>>,
         tool_uses_by_index: HashMap<usize, RawToolUse>,
+        usage: TokenUsage,
     }
 
     futures::stream::unfold(
         State {
             events,
             tool_uses_by_index: HashMap::default(),
+            usage: TokenUsage::default(),
         },
         |mut state| async move {
             while let Some(event) = state.events.next().await {
@@ -510,7 +512,15 @@ pub fn map_to_language_model_completion_events(
                                 ));
                             }
                         }
-                        Event::MessageDelta { delta, .. } => {
+                        Event::MessageStart { message } => {
+                            update_usage(&mut state.usage, &message.usage);
+                            return Some((
+                                Some(Ok(LanguageModelCompletionEvent::UsageUpdate(state.usage))),
+                                state,
+                            ));
+                        }
+                        Event::MessageDelta { delta, usage } => {
+                            update_usage(&mut state.usage, &usage);
                             if let Some(stop_reason) = delta.stop_reason.as_deref() {
                                 let stop_reason = match stop_reason {
                                     "end_turn" => StopReason::EndTurn,
@@ -525,6 +535,12 @@ pub fn map_to_language_model_completion_events(
                                 ));
                             }
                         }
+                        Event::MessageStop => {
+                            return Some((
+                                Some(Ok(LanguageModelCompletionEvent::UsageUpdate(state.usage))),
+                                state,
+                            ));
+                        }
                         Event::Error { error } => {
                             return Some((
                                 Some(Err(anyhow!(AnthropicError::ApiError(error)))),
@@ -545,6 +561,23 @@ pub fn map_to_language_model_completion_events(
     .filter_map(|event| async move { event })
 }
 
+/// Anthropic reports the input tokens when the message starts and the output tokens as it's
+/// generated, so only the counts present in each event are updated.
+fn update_usage(token_usage: &mut TokenUsage, usage: &anthropic::Usage) {
+    if let Some(input_tokens) = usage.input_tokens {
+        token_usage.input_tokens = input_tokens;
+    }
+    if let Some(output_tokens) = usage.output_tokens {
+        token_usage.output_tokens = output_tokens;
+    }
+    if let Some(cache_creation_input_tokens) = usage.cache_creation_input_tokens {
+        token_usage.cache_creation_input_tokens = cache_creation_input_tokens;
+    }
+    if let Some(cache_read_input_tokens) = usage.cache_read_input_tokens {
+        token_usage.cache_read_input_tokens = cache_read_input_tokens;
+    }
+}
+
 struct ConfigurationView {
     api_key_editor: View<Editor>,
     state: gpui::Model<State>,
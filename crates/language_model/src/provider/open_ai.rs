@@ -1,14 +1,15 @@
 use anyhow::{anyhow, Result};
 use collections::BTreeMap;
 use editor::{Editor, EditorElement, EditorStyle};
-use futures::{future::BoxFuture, FutureExt, StreamExt};
+use futures::{future::BoxFuture, FutureExt, Stream, StreamExt};
 use gpui::{
     AnyView, AppContext, AsyncAppContext, FontStyle, ModelContext, Subscription, Task, TextStyle,
     View, WhiteSpace,
 };
 use http_client::HttpClient;
 use open_ai::{
-    stream_completion, FunctionDefinition, ResponseStreamEvent, ToolChoice, ToolDefinition,
+    stream_completion, FunctionDefinition, ResponseStreamEvent, StreamOptions, ToolChoice,
+    ToolDefinition,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -19,12 +20,12 @@ use theme::ThemeSettings;
 use ui::{prelude::*, Icon, IconName, Tooltip};
 use util::ResultExt;
 
-use crate::LanguageModelCompletionEvent;
 use crate::{
     settings::AllLanguageModelSettings, LanguageModel, LanguageModelId, LanguageModelName,
     LanguageModelProvider, LanguageModelProviderId, LanguageModelProviderName,
     LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role,
 };
+use crate::{LanguageModelCompletionEvent, TokenUsage};
 
 const PROVIDER_ID: &str = "openai";
 const PROVIDER_NAME: &str = "OpenAI";
@@ -302,14 +303,15 @@ impl LanguageModel for OpenAiLanguageModel {
         'static,
         Result<futures::stream::BoxStream<'static, Result<LanguageModelCompletionEvent>>>,
     > {
-        let request = request.into_open_ai(self.model.id().into(), self.max_output_tokens());
-        let completions = self.stream_completion(request, cx);
-        async move {
-            Ok(open_ai::extract_text_from_events(completions.await?)
-                .map(|result| result.map(LanguageModelCompletionEvent::Text))
-                .boxed())
+        let mut request = request.into_open_ai(self.model.id().into(), self.max_output_tokens());
+        if request.stream {
+            request.stream_options = Some(StreamOptions {
+                include_usage: true,
+            });
         }
-        .boxed()
+        let completions = self.stream_completion(request, cx);
+        async move { Ok(map_to_language_model_completion_events(completions.await?).boxed()) }
+            .boxed()
     }
 
     fn use_any_tool(
@@ -350,6 +352,44 @@ impl LanguageModel for OpenAiLanguageModel {
     }
 }
 
+/// Converts OpenAI's streamed response to text events, followed by the token usage that's
+/// reported in the final event.
+pub fn map_to_language_model_completion_events(
+    events: impl Stream<Item = Result<ResponseStreamEvent>>,
+) -> impl Stream<Item = Result<LanguageModelCompletionEvent>> {
+    events.flat_map(|event| {
+        let mut completion_events = Vec::new();
+        match event {
+            Ok(mut event) => {
+                if let Some(text) = event.choices.pop().and_then(|choice| choice.delta.content) {
+                    completion_events.push(Ok(LanguageModelCompletionEvent::Text(text)));
+                }
+                if let Some(usage) = event.usage.as_ref() {
+                    completion_events.push(Ok(LanguageModelCompletionEvent::UsageUpdate(
+                        token_usage(usage),
+                    )));
+                }
+            }
+            Err(error) => completion_events.push(Err(error)),
+        }
+        futures::stream::iter(completion_events)
+    })
+}
+
+/// Converts OpenAI's usage, whose prompt tokens include the ones read from the cache.
+pub fn token_usage(usage: &open_ai::Usage) -> TokenUsage {
+    let cached_tokens = usage
+        .prompt_tokens_details
+        .as_ref()
+        .map_or(0, |details| details.cached_tokens);
+    TokenUsage {
+        input_tokens: usage.prompt_tokens.saturating_sub(cached_tokens),
+        output_tokens: usage.completion_tokens,
+        cache_read_input_tokens: cached_tokens,
+        ..Default::default()
+    }
+}
+
 pub fn count_open_ai_tokens(
     request: LanguageModelRequest,
     model: open_ai::Model,
@@ -0,0 +1,928 @@
+use anyhow::{anyhow, Context as _, Result};
+use collections::BTreeMap;
+use editor::{Editor, EditorElement, EditorStyle};
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use gpui::{
+    AnyView, AppContext, AsyncAppContext, FontStyle, ModelContext, Subscription, Task, TextStyle,
+    View, WhiteSpace,
+};
+use http_client::HttpClient;
+use open_ai::{
+    stream_completion, FunctionContent, FunctionDefinition, ImageUrl, MessagePart, RequestMessage,
+    ResponseStreamEvent, ToolCall, ToolCallContent, ToolChoice, ToolDefinition,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsStore};
+use std::{sync::Arc, time::Duration};
+use theme::ThemeSettings;
+use ui::{prelude::*, Icon, IconName};
+use util::ResultExt;
+
+use crate::{
+    settings::AllLanguageModelSettings, LanguageModel, LanguageModelCompletionEvent,
+    LanguageModelId, LanguageModelName, LanguageModelProvider, LanguageModelProviderId,
+    LanguageModelProviderName, LanguageModelProviderState, LanguageModelRequest,
+    LanguageModelToolUse, MessageContent, RateLimiter, Role, StopReason,
+};
+
+const PROVIDER_ID_PREFIX: &str = "openai_compatible:";
+
+/// Returns the ID of the OpenAI-compatible provider configured with the given name.
+pub fn provider_id(name: &str) -> LanguageModelProviderId {
+    LanguageModelProviderId(format!("{PROVIDER_ID_PREFIX}{name}").into())
+}
+
+/// Returns whether the provider is an OpenAI-compatible one, configured in the settings.
+pub fn is_open_ai_compatible_provider(id: &LanguageModelProviderId) -> bool {
+    id.0.starts_with(PROVIDER_ID_PREFIX)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenAiCompatibleSettings {
+    pub name: String,
+    pub api_url: String,
+    pub api_key: ApiKeySource,
+    pub low_speed_timeout: Option<Duration>,
+    pub available_models: Vec<AvailableModel>,
+}
+
+impl OpenAiCompatibleSettings {
+    /// The URL the API key is saved under in the keychain. It includes the provider's name,
+    /// since several providers can use the same server with different keys.
+    fn credentials_url(&self) -> String {
+        format!("{}#{}", self.api_url, self.name)
+    }
+}
+
+/// Where the API key sent to an OpenAI-compatible server comes from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApiKeySource {
+    /// The API key is entered in the assistant's configuration, and saved in the keychain.
+    #[default]
+    Keychain,
+    /// The API key is read from an environment variable.
+    Env { var: String },
+    /// No API key is needed, e.g. for servers running locally.
+    None,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AvailableModel {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub max_tokens: usize,
+    pub max_output_tokens: Option<u32>,
+    /// Whether the model can be given tools to use.
+    #[serde(default)]
+    pub supports_tools: bool,
+    /// Whether the model accepts images in user messages.
+    #[serde(default)]
+    pub supports_images: bool,
+}
+
+pub struct OpenAiCompatibleLanguageModelProvider {
+    name: Arc<str>,
+    http_client: Arc<dyn HttpClient>,
+    state: gpui::Model<State>,
+}
+
+pub struct State {
+    name: Arc<str>,
+    api_key: Option<String>,
+    _subscription: Subscription,
+}
+
+impl State {
+    fn settings<'a>(&self, cx: &'a AppContext) -> Option<&'a OpenAiCompatibleSettings> {
+        AllLanguageModelSettings::get_global(cx)
+            .openai_compatible
+            .iter()
+            .find(|settings| *settings.name == *self.name)
+    }
+
+    fn is_authenticated(&self, cx: &AppContext) -> bool {
+        self.api_key.is_some()
+            || self
+                .settings(cx)
+                .map_or(false, |settings| settings.api_key == ApiKeySource::None)
+    }
+
+    fn reset_api_key(&self, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
+        let Some(settings) = self.settings(cx) else {
+            return Task::ready(Ok(()));
+        };
+        let delete_credentials = cx.delete_credentials(&settings.credentials_url());
+        cx.spawn(|this, mut cx| async move {
+            delete_credentials.await.log_err();
+            this.update(&mut cx, |this, cx| {
+                this.api_key = None;
+                cx.notify();
+            })
+        })
+    }
+
+    fn set_api_key(&mut self, api_key: String, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
+        let Some(settings) = self.settings(cx) else {
+            return Task::ready(Err(anyhow!("{} is no longer configured", self.name)));
+        };
+        let write_credentials =
+            cx.write_credentials(&settings.credentials_url(), "Bearer", api_key.as_bytes());
+
+        cx.spawn(|this, mut cx| async move {
+            write_credentials.await?;
+            this.update(&mut cx, |this, cx| {
+                this.api_key = Some(api_key);
+                cx.notify();
+            })
+        })
+    }
+
+    fn authenticate(&self, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
+        if self.is_authenticated(cx) {
+            return Task::ready(Ok(()));
+        }
+        let Some(settings) = self.settings(cx).cloned() else {
+            return Task::ready(Err(anyhow!("{} is no longer configured", self.name)));
+        };
+
+        cx.spawn(|this, mut cx| async move {
+            let api_key = match settings.api_key {
+                ApiKeySource::Keychain => {
+                    let (_, api_key) = cx
+                        .update(|cx| cx.read_credentials(&settings.credentials_url()))?
+                        .await?
+                        .ok_or_else(|| anyhow!("credentials not found"))?;
+                    String::from_utf8(api_key)?
+                }
+                ApiKeySource::Env { var } => std::env::var(&var)
+                    .with_context(|| format!("the {var} environment variable isn't set"))?,
+                ApiKeySource::None => return Ok(()),
+            };
+            this.update(&mut cx, |this, cx| {
+                this.api_key = Some(api_key);
+                cx.notify();
+            })
+        })
+    }
+}
+
+impl OpenAiCompatibleLanguageModelProvider {
+    pub fn new(name: Arc<str>, http_client: Arc<dyn HttpClient>, cx: &mut AppContext) -> Self {
+        let state = cx.new_model(|cx| State {
+            name: name.clone(),
+            api_key: None,
+            _subscription: cx.observe_global::<SettingsStore>(|_this: &mut State, cx| {
+                cx.notify();
+            }),
+        });
+
+        Self {
+            name,
+            http_client,
+            state,
+        }
+    }
+}
+
+impl LanguageModelProviderState for OpenAiCompatibleLanguageModelProvider {
+    type ObservableEntity = State;
+
+    fn observable_entity(&self) -> Option<gpui::Model<Self::ObservableEntity>> {
+        Some(self.state.clone())
+    }
+}
+
+impl LanguageModelProvider for OpenAiCompatibleLanguageModelProvider {
+    fn id(&self) -> LanguageModelProviderId {
+        provider_id(&self.name)
+    }
+
+    fn name(&self) -> LanguageModelProviderName {
+        LanguageModelProviderName(self.name.to_string().into())
+    }
+
+    fn provided_models(&self, cx: &AppContext) -> Vec<Arc<dyn LanguageModel>> {
+        let Some(settings) = self.state.read(cx).settings(cx) else {
+            return Vec::new();
+        };
+
+        let mut models = BTreeMap::default();
+        for model in &settings.available_models {
+            models.insert(model.name.clone(), model.clone());
+        }
+
+        models
+            .into_values()
+            .map(|model| {
+                Arc::new(OpenAiCompatibleLanguageModel {
+                    id: LanguageModelId::from(model.name.clone()),
+                    provider_name: self.name.clone(),
+                    model,
+                    state: self.state.clone(),
+                    http_client: self.http_client.clone(),
+                    request_limiter: RateLimiter::new(4),
+                }) as Arc<dyn LanguageModel>
+            })
+            .collect()
+    }
+
+    fn is_authenticated(&self, cx: &AppContext) -> bool {
+        self.state.read(cx).is_authenticated(cx)
+    }
+
+    fn authenticate(&self, cx: &mut AppContext) -> Task<Result<()>> {
+        self.state.update(cx, |state, cx| state.authenticate(cx))
+    }
+
+    fn configuration_view(&self, cx: &mut WindowContext) -> AnyView {
+        cx.new_view(|cx| ConfigurationView::new(self.state.clone(), cx))
+            .into()
+    }
+
+    fn reset_credentials(&self, cx: &mut AppContext) -> Task<Result<()>> {
+        self.state.update(cx, |state, cx| state.reset_api_key(cx))
+    }
+}
+
+pub struct OpenAiCompatibleLanguageModel {
+    id: LanguageModelId,
+    provider_name: Arc<str>,
+    model: AvailableModel,
+    state: gpui::Model<State>,
+    http_client: Arc<dyn HttpClient>,
+    request_limiter: RateLimiter,
+}
+
+impl OpenAiCompatibleLanguageModel {
+    fn stream_completion(
+        &self,
+        request: open_ai::Request,
+        cx: &AsyncAppContext,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<ResponseStreamEvent>>>> {
+        let http_client = self.http_client.clone();
+        let Ok(settings) = cx.read_model(&self.state, |state, cx| {
+            let settings = state.settings(cx)?;
+            let api_key = match settings.api_key {
+                ApiKeySource::None => Some(String::new()),
+                _ => state.api_key.clone(),
+            };
+            Some((
+                api_key,
+                settings.api_url.clone(),
+                settings.low_speed_timeout,
+            ))
+        }) else {
+            return futures::future::ready(Err(anyhow!("App state dropped"))).boxed();
+        };
+
+        let provider_name = self.provider_name.clone();
+        let future = self.request_limiter.stream(async move {
+            let (api_key, api_url, low_speed_timeout) =
+                settings.ok_or_else(|| anyhow!("{provider_name} is no longer configured"))?;
+            let api_key = api_key.ok_or_else(|| anyhow!("Missing {provider_name} API Key"))?;
+            let request = stream_completion(
+                http_client.as_ref(),
+                &api_url,
+                &api_key,
+                request,
+                low_speed_timeout,
+            );
+            let response = request.await?;
+            Ok(response)
+        });
+
+        async move { Ok(future.await?.boxed()) }.boxed()
+    }
+}
+
+impl LanguageModel for OpenAiCompatibleLanguageModel {
+    fn id(&self) -> LanguageModelId {
+        self.id.clone()
+    }
+
+    fn name(&self) -> LanguageModelName {
+        LanguageModelName::from(
+            self.model
+                .display_name
+                .clone()
+                .unwrap_or_else(|| self.model.name.clone()),
+        )
+    }
+
+    fn provider_id(&self) -> LanguageModelProviderId {
+        provider_id(&self.provider_name)
+    }
+
+    fn provider_name(&self) -> LanguageModelProviderName {
+        LanguageModelProviderName(self.provider_name.to_string().into())
+    }
+
+    fn telemetry_id(&self) -> String {
+        format!("openai_compatible/{}", self.model.name)
+    }
+
+    fn max_token_count(&self) -> usize {
+        self.model.max_tokens
+    }
+
+    fn max_output_tokens(&self) -> Option<u32> {
+        self.model.max_output_tokens
+    }
+
+    fn count_tokens(
+        &self,
+        request: LanguageModelRequest,
+        cx: &AppContext,
+    ) -> BoxFuture<'static, Result<usize>> {
+        // The server's tokenizer isn't known, so the count is an estimate.
+        let model = open_ai::Model::Custom {
+            name: self.model.name.clone(),
+            display_name: self.model.display_name.clone(),
+            max_tokens: self.model.max_tokens,
+            max_output_tokens: self.model.max_output_tokens,
+            max_completion_tokens: None,
+        };
+        super::open_ai::count_open_ai_tokens(request, model, cx)
+    }
+
+    fn stream_completion(
+        &self,
+        request: LanguageModelRequest,
+        cx: &AsyncAppContext,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<LanguageModelCompletionEvent>>>> {
+        let request = into_open_ai_request(request, &self.model);
+        let completions = self.stream_completion(request, cx);
+        async move { Ok(map_to_completion_events(completions.await?)) }.boxed()
+    }
+
+    fn use_any_tool(
+        &self,
+        request: LanguageModelRequest,
+        tool_name: String,
+        tool_description: String,
+        schema: serde_json::Value,
+        cx: &AsyncAppContext,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+        if !self.model.supports_tools {
+            return futures::future::ready(Err(anyhow!(
+                "{} doesn't support tools",
+                self.model.name
+            )))
+            .boxed();
+        }
+
+        let mut request = into_open_ai_request(request, &self.model);
+        request.tool_choice = Some(ToolChoice::Other(ToolDefinition::Function {
+            function: FunctionDefinition {
+                name: tool_name.clone(),
+                description: None,
+                parameters: None,
+            },
+        }));
+        request.tools = vec![ToolDefinition::Function {
+            function: FunctionDefinition {
+                name: tool_name.clone(),
+                description: Some(tool_description),
+                parameters: Some(schema),
+            },
+        }];
+
+        let response = self.stream_completion(request, cx);
+        self.request_limiter
+            .run(async move {
+                let response = response.await?;
+                Ok(
+                    open_ai::extract_tool_args_from_events(tool_name, Box::pin(response))
+                        .await?
+                        .boxed(),
+                )
+            })
+            .boxed()
+    }
+}
+
+/// Converts the request to the OpenAI format, leaving out the tools and images the model
+/// doesn't support.
+fn into_open_ai_request(request: LanguageModelRequest, model: &AvailableModel) -> open_ai::Request {
+    let mut messages = Vec::new();
+    for message in request.messages {
+        match message.role {
+            Role::System => messages.push(RequestMessage::System {
+                content: message.string_contents(),
+            }),
+            Role::User => {
+                let mut parts = Vec::new();
+                for content in message.content {
+                    match content {
+                        MessageContent::Text(text) => parts.push(MessagePart::Text { text }),
+                        MessageContent::Image(image) if model.supports_images => {
+                            parts.push(MessagePart::ImageUrl {
+                                image_url: ImageUrl {
+                                    url: format!("data:image/png;base64,{}", image.source),
+                                },
+                            })
+                        }
+                        // Tool results have to directly follow the message that used the tools.
+                        MessageContent::ToolResult(tool_result) => {
+                            messages.push(RequestMessage::Tool {
+                                content: tool_result.content,
+                                tool_call_id: tool_result.tool_use_id,
+                            })
+                        }
+                        MessageContent::Image(_) | MessageContent::ToolUse(_) => {}
+                    }
+                }
+
+                let has_images = parts
+                    .iter()
+                    .any(|part| matches!(part, MessagePart::ImageUrl { .. }));
+                let content = if has_images {
+                    open_ai::MessageContent::Multipart(parts)
+                } else {
+                    // Not every server accepts a list of parts, so text is sent as a string.
+                    let text = parts
+                        .into_iter()
+                        .filter_map(|part| match part {
+                            MessagePart::Text { text } => Some(text),
+                            MessagePart::ImageUrl { .. } => None,
+                        })
+                        .collect::<String>();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    open_ai::MessageContent::Plain(text)
+                };
+                messages.push(RequestMessage::User { content });
+            }
+            Role::Assistant => {
+                let mut text = String::new();
+                let mut tool_calls = Vec::new();
+                for content in message.content {
+                    match content {
+                        MessageContent::Text(chunk) => text.push_str(&chunk),
+                        MessageContent::ToolUse(tool_use) => tool_calls.push(ToolCall {
+                            id: tool_use.id,
+                            content: ToolCallContent::Function {
+                                function: FunctionContent {
+                                    name: tool_use.name,
+                                    arguments: tool_use.input.to_string(),
+                                },
+                            },
+                        }),
+                        MessageContent::Image(_) | MessageContent::ToolResult(_) => {}
+                    }
+                }
+                messages.push(RequestMessage::Assistant {
+                    content: (!text.is_empty() || tool_calls.is_empty()).then_some(text),
+                    tool_calls,
+                });
+            }
+        }
+    }
+
+    let tools = if model.supports_tools {
+        request
+            .tools
+            .into_iter()
+            .map(|tool| ToolDefinition::Function {
+                function: FunctionDefinition {
+                    name: tool.name,
+                    description: Some(tool.description),
+                    parameters: Some(tool.input_schema),
+                },
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    open_ai::Request {
+        model: model.name.clone(),
+        messages,
+        stream: true,
+        stream_options: None,
+        max_tokens: model.max_output_tokens,
+        stop: request.stop,
+        temperature: request.temperature,
+        tool_choice: None,
+        tools,
+    }
+}
+
+/// Converts the streamed response to completion events. Tool calls are streamed in pieces, so
+/// they're emitted once the response finishes.
+fn map_to_completion_events(
+    events: BoxStream<'static, Result<ResponseStreamEvent>>,
+) -> BoxStream<'static, Result<LanguageModelCompletionEvent>> {
+    #[derive(Default)]
+    struct RawToolCall {
+        id: String,
+        name: String,
+        arguments: String,
+    }
+
+    struct State {
+        events: BoxStream<'static, Result<ResponseStreamEvent>>,
+        tool_calls_by_index: BTreeMap<usize, RawToolCall>,
+    }
+
+    futures::stream::unfold(
+        State {
+            events,
+            tool_calls_by_index: BTreeMap::default(),
+        },
+        |mut state| async move {
+            let event = state.events.next().await?;
+            let mut completion_events = Vec::new();
+            match event {
+                Ok(event) => {
+                    if let Some(usage) = event.usage.as_ref() {
+                        completion_events.push(Ok(LanguageModelCompletionEvent::UsageUpdate(
+                            super::open_ai::token_usage(usage),
+                        )));
+                    }
+                    for choice in event.choices {
+                        if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+                            completion_events.push(Ok(LanguageModelCompletionEvent::Text(text)));
+                        }
+
+                        for tool_call in choice.delta.tool_calls.into_iter().flatten() {
+                            let raw_tool_call = state
+                                .tool_calls_by_index
+                                .entry(tool_call.index)
+                                .or_default();
+                            if let Some(id) = tool_call.id {
+                                raw_tool_call.id = id;
+                            }
+                            if let Some(function) = tool_call.function {
+                                if let Some(name) = function.name {
+                                    raw_tool_call.name = name;
+                                }
+                                if let Some(arguments) = function.arguments {
+                                    raw_tool_call.arguments.push_str(&arguments);
+                                }
+                            }
+                        }
+
+                        if let Some(finish_reason) = choice.finish_reason {
+                            for (_, tool_call) in std::mem::take(&mut state.tool_calls_by_index) {
+                                let arguments = if tool_call.arguments.trim().is_empty() {
+                                    "{}"
+                                } else {
+                                    &tool_call.arguments
+                                };
+                                completion_events.push(
+                                    serde_json::from_str(arguments)
+                                        .map(|input| {
+                                            LanguageModelCompletionEvent::ToolUse(
+                                                LanguageModelToolUse {
+                                                    id: tool_call.id,
+                                                    name: tool_call.name,
+                                                    input,
+                                                },
+                                            )
+                                        })
+                                        .map_err(|error| anyhow!(error)),
+                                );
+                            }
+
+                            let stop_reason = match finish_reason.as_str() {
+                                "length" => StopReason::MaxTokens,
+                                "tool_calls" => StopReason::ToolUse,
+                                _ => StopReason::EndTurn,
+                            };
+                            completion_events
+                                .push(Ok(LanguageModelCompletionEvent::Stop(stop_reason)));
+                        }
+                    }
+                }
+                Err(error) => completion_events.push(Err(error)),
+            }
+            Some((completion_events, state))
+        },
+    )
+    .flat_map(futures::stream::iter)
+    .boxed()
+}
+
+struct ConfigurationView {
+    api_key_editor: View<Editor>,
+    state: gpui::Model<State>,
+    load_credentials_task: Option<Task<()>>,
+}
+
+impl ConfigurationView {
+    fn new(state: gpui::Model<State>, cx: &mut ViewContext<Self>) -> Self {
+        let api_key_editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("API key", cx);
+            editor
+        });
+
+        cx.observe(&state, |_, _, cx| {
+            cx.notify();
+        })
+        .detach();
+
+        let load_credentials_task = Some(cx.spawn({
+            let state = state.clone();
+            |this, mut cx| async move {
+                if let Some(task) = state
+                    .update(&mut cx, |state, cx| state.authenticate(cx))
+                    .log_err()
+                {
+                    // We don't log an error, because "not signed in" is also an error.
+                    let _ = task.await;
+                }
+
+                this.update(&mut cx, |this, cx| {
+                    this.load_credentials_task = None;
+                    cx.notify();
+                })
+                .log_err();
+            }
+        }));
+
+        Self {
+            api_key_editor,
+            state,
+            load_credentials_task,
+        }
+    }
+
+    fn save_api_key(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        let api_key = self.api_key_editor.read(cx).text(cx);
+        if api_key.is_empty() {
+            return;
+        }
+
+        let state = self.state.clone();
+        cx.spawn(|_, mut cx| async move {
+            state
+                .update(&mut cx, |state, cx| state.set_api_key(api_key, cx))?
+                .await
+        })
+        .detach_and_log_err(cx);
+
+        cx.notify();
+    }
+
+    fn reset_api_key(&mut self, cx: &mut ViewContext<Self>) {
+        self.api_key_editor
+            .update(cx, |editor, cx| editor.set_text("", cx));
+
+        let state = self.state.clone();
+        cx.spawn(|_, mut cx| async move {
+            state
+                .update(&mut cx, |state, cx| state.reset_api_key(cx))?
+                .await
+        })
+        .detach_and_log_err(cx);
+
+        cx.notify();
+    }
+
+    fn render_api_key_editor(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let settings = ThemeSettings::get_global(cx);
+        let text_style = TextStyle {
+            color: cx.theme().colors().text,
+            font_family: settings.ui_font.family.clone(),
+            font_features: settings.ui_font.features.clone(),
+            font_fallbacks: settings.ui_font.fallbacks.clone(),
+            font_size: rems(0.875).into(),
+            font_weight: settings.ui_font.weight,
+            font_style: FontStyle::Normal,
+            line_height: relative(1.3),
+            background_color: None,
+            underline: None,
+            strikethrough: None,
+            white_space: WhiteSpace::Normal,
+            truncate: None,
+        };
+        EditorElement::new(
+            &self.api_key_editor,
+            EditorStyle {
+                background: cx.theme().colors().editor_background,
+                local_player: cx.theme().players().local(),
+                text: text_style,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+impl Render for ConfigurationView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let state = self.state.read(cx);
+        let name = state.name.clone();
+        let is_authenticated = state.is_authenticated(cx);
+        let Some(api_key_source) = state.settings(cx).map(|settings| settings.api_key.clone())
+        else {
+            return div()
+                .child(Label::new(format!(
+                    "{name} is no longer configured in your settings."
+                )))
+                .into_any();
+        };
+
+        if self.load_credentials_task.is_some() {
+            return div().child(Label::new("Loading credentials...")).into_any();
+        }
+
+        match api_key_source {
+            ApiKeySource::Keychain if !is_authenticated => v_flex()
+                .size_full()
+                .on_action(cx.listener(Self::save_api_key))
+                .child(Label::new(format!(
+                    "Paste the API key for {name} below and hit enter to use the assistant:"
+                )))
+                .child(
+                    h_flex()
+                        .w_full()
+                        .my_2()
+                        .px_2()
+                        .py_1()
+                        .bg(cx.theme().colors().editor_background)
+                        .rounded_md()
+                        .child(self.render_api_key_editor(cx)),
+                )
+                .into_any(),
+            ApiKeySource::Env { var } if !is_authenticated => v_flex()
+                .size_full()
+                .child(Label::new(format!(
+                    "Assign the API key for {name} to the {var} environment variable and restart Zed."
+                )))
+                .into_any(),
+            api_key_source => h_flex()
+                .size_full()
+                .justify_between()
+                .child(
+                    h_flex()
+                        .gap_1()
+                        .child(Icon::new(IconName::Check).color(Color::Success))
+                        .child(Label::new(match &api_key_source {
+                            ApiKeySource::Keychain => "API key configured.".to_string(),
+                            ApiKeySource::Env { var } => {
+                                format!("API key set in {var} environment variable.")
+                            }
+                            ApiKeySource::None => format!("{name} doesn't need an API key."),
+                        })),
+                )
+                .when(api_key_source == ApiKeySource::Keychain, |this| {
+                    this.child(
+                        Button::new("reset-key", "Reset key")
+                            .icon(Some(IconName::Trash))
+                            .icon_size(IconSize::Small)
+                            .icon_position(IconPosition::Start)
+                            .on_click(cx.listener(|this, _, cx| this.reset_api_key(cx))),
+                    )
+                })
+                .into_any(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LanguageModelRequestMessage, LanguageModelRequestTool, TokenUsage};
+    use futures::AsyncReadExt;
+    use gpui::TestAppContext;
+    use http_client::{AsyncBody, FakeHttpClient, Response};
+    use serde_json::{json, Value};
+
+    #[gpui::test]
+    async fn test_open_ai_compatible_provider(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let settings_store = SettingsStore::test(cx);
+            cx.set_global(settings_store);
+            AllLanguageModelSettings::register(cx);
+            SettingsStore::update_global(cx, |store, cx| {
+                store.update_user_settings::<AllLanguageModelSettings>(cx, |settings| {
+                    settings.openai_compatible = Some(vec![serde_json::from_value(json!({
+                        "name": "Local",
+                        "api_url": "http://localhost:8000/v1",
+                        "api_key": { "type": "none" },
+                        "available_models": [
+                            { "name": "qwen", "max_tokens": 32768, "supports_tools": true }
+                        ]
+                    }))
+                    .unwrap()]);
+                });
+            });
+        });
+
+        let http_client = FakeHttpClient::create(|request| async move {
+            assert_eq!(request.uri().path(), "/v1/chat/completions");
+            let mut body = String::new();
+            request.into_body().read_to_string(&mut body).await.unwrap();
+            let body: Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(body["model"], "qwen");
+            assert_eq!(body["tools"][0]["function"]["name"], "read_file");
+
+            let chunks = [
+                json!({ "content": "Let me look." }),
+                json!({ "tool_calls": [{
+                    "index": 0,
+                    "id": "call_1",
+                    "function": { "name": "read_file", "arguments": "{\"path\":" }
+                }] }),
+                json!({ "tool_calls": [{
+                    "index": 0,
+                    "function": { "arguments": " \"README.md\"}" }
+                }] }),
+            ];
+            let mut events = chunks
+                .into_iter()
+                .map(|delta| {
+                    json!({
+                        "created": 0,
+                        "model": "qwen",
+                        "choices": [{ "index": 0, "delta": delta, "finish_reason": null }]
+                    })
+                })
+                .collect::<Vec<_>>();
+            events.push(json!({
+                "created": 0,
+                "model": "qwen",
+                "choices": [{ "index": 0, "delta": {}, "finish_reason": "tool_calls" }]
+            }));
+            events.push(json!({
+                "created": 0,
+                "model": "qwen",
+                "choices": [],
+                "usage": {
+                    "prompt_tokens": 100,
+                    "completion_tokens": 20,
+                    "total_tokens": 120,
+                    "prompt_tokens_details": { "cached_tokens": 60 }
+                }
+            }));
+            let body = events
+                .iter()
+                .map(|event| format!("data: {event}\n\n"))
+                .chain(["data: [DONE]\n\n".to_string()])
+                .collect::<String>();
+            Ok(Response::builder()
+                .status(200)
+                .body(AsyncBody::from(body))
+                .unwrap())
+        });
+
+        let model = cx.update(|cx| {
+            let provider =
+                OpenAiCompatibleLanguageModelProvider::new("Local".into(), http_client, cx);
+            assert_eq!(provider.id(), provider_id("Local"));
+            assert!(provider.is_authenticated(cx));
+
+            let models = provider.provided_models(cx);
+            assert_eq!(models.len(), 1);
+            models[0].clone()
+        });
+        assert_eq!(model.name().0, "qwen");
+        assert_eq!(model.max_token_count(), 32768);
+
+        let request = LanguageModelRequest {
+            messages: vec![LanguageModelRequestMessage {
+                role: Role::User,
+                content: vec!["What's in the README?".into()],
+                cache: false,
+            }],
+            tools: vec![LanguageModelRequestTool {
+                name: "read_file".into(),
+                description: "Reads a file".into(),
+                input_schema: json!({ "type": "object" }),
+            }],
+            stop: Vec::new(),
+            temperature: 1.0,
+        };
+        let events = model
+            .stream_completion(request, &cx.to_async())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            events,
+            vec![
+                LanguageModelCompletionEvent::Text("Let me look.".into()),
+                LanguageModelCompletionEvent::ToolUse(LanguageModelToolUse {
+                    id: "call_1".into(),
+                    name: "read_file".into(),
+                    input: json!({ "path": "README.md" }),
+                }),
+                LanguageModelCompletionEvent::Stop(StopReason::ToolUse),
+                // Cached prompt tokens are reported separately from the other input tokens.
+                LanguageModelCompletionEvent::UsageUpdate(TokenUsage {
+                    input_tokens: 40,
+                    output_tokens: 20,
+                    cache_read_input_tokens: 60,
+                    ..Default::default()
+                }),
+            ]
+        );
+    }
+}
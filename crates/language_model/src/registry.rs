@@ -1,15 +1,22 @@
 use crate::{
     provider::{
-        anthropic::AnthropicLanguageModelProvider, cloud::CloudLanguageModelProvider,
-        copilot_chat::CopilotChatLanguageModelProvider, google::GoogleLanguageModelProvider,
-        ollama::OllamaLanguageModelProvider, open_ai::OpenAiLanguageModelProvider,
+        anthropic::AnthropicLanguageModelProvider,
+        cloud::CloudLanguageModelProvider,
+        copilot_chat::CopilotChatLanguageModelProvider,
+        google::GoogleLanguageModelProvider,
+        ollama::OllamaLanguageModelProvider,
+        open_ai::OpenAiLanguageModelProvider,
+        open_ai_compatible::{self, OpenAiCompatibleLanguageModelProvider},
     },
+    settings::AllLanguageModelSettings,
     LanguageModel, LanguageModelId, LanguageModelProvider, LanguageModelProviderId,
     LanguageModelProviderState,
 };
 use client::{Client, UserStore};
 use collections::BTreeMap;
 use gpui::{AppContext, EventEmitter, Global, Model, ModelContext};
+use http_client::HttpClient;
+use settings::{Settings, SettingsStore};
 use std::sync::Arc;
 use ui::Context;
 
@@ -48,6 +55,13 @@ fn register_language_model_providers(
     );
     registry.register_provider(CopilotChatLanguageModelProvider::new(cx), cx);
 
+    let http_client = client.http_client();
+    registry.sync_openai_compatible_providers(http_client.clone(), cx);
+    cx.observe_global::<SettingsStore>(move |registry, cx| {
+        registry.sync_openai_compatible_providers(http_client.clone(), cx);
+    })
+    .detach();
+
     cx.observe_flag::<feature_flags::LanguageModels, _>(move |enabled, cx| {
         let user_store = user_store.clone();
         let client = client.clone();
@@ -143,6 +157,49 @@ impl LanguageModelRegistry {
         }
     }
 
+    /// Registers a provider for each OpenAI-compatible server in the settings, and unregisters
+    /// the ones for servers that were removed.
+    fn sync_openai_compatible_providers(
+        &mut self,
+        http_client: Arc<dyn HttpClient>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let names = AllLanguageModelSettings::get_global(cx)
+            .openai_compatible
+            .iter()
+            .map(|settings| settings.name.clone())
+            .collect::<Vec<_>>();
+
+        let removed_ids = self
+            .providers
+            .keys()
+            .filter(|id| open_ai_compatible::is_open_ai_compatible_provider(id))
+            .filter(|id| {
+                !names
+                    .iter()
+                    .any(|name| open_ai_compatible::provider_id(name) == **id)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        for id in removed_ids {
+            self.unregister_provider(id, cx);
+        }
+
+        for name in names {
+            if !self
+                .providers
+                .contains_key(&open_ai_compatible::provider_id(&name))
+            {
+                let provider = OpenAiCompatibleLanguageModelProvider::new(
+                    name.into(),
+                    http_client.clone(),
+                    cx,
+                );
+                self.register_provider(provider, cx);
+            }
+        }
+    }
+
     pub fn providers(&self) -> Vec<Arc<dyn LanguageModelProvider>> {
         let zed_provider_id = LanguageModelProviderId(crate::provider::cloud::PROVIDER_ID.into());
         let mut providers = Vec::with_capacity(self.providers.len());
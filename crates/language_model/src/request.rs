@@ -249,7 +249,7 @@ impl LanguageModelRequest {
                 .into_iter()
                 .map(|msg| match msg.role {
                     Role::User => open_ai::RequestMessage::User {
-                        content: msg.string_contents(),
+                        content: msg.string_contents().into(),
                     },
                     Role::Assistant => open_ai::RequestMessage::Assistant {
                         content: Some(msg.string_contents()),
@@ -261,6 +261,7 @@ impl LanguageModelRequest {
                 })
                 .collect(),
             stream,
+            stream_options: None,
             stop: self.stop,
             temperature: self.temperature,
             max_tokens: max_output_tokens,
@@ -16,6 +16,7 @@ use crate::{
         google::GoogleSettings,
         ollama::OllamaSettings,
         open_ai::OpenAiSettings,
+        open_ai_compatible::{self, OpenAiCompatibleSettings},
     },
     LanguageModelCacheConfiguration,
 };
@@ -61,6 +62,7 @@ pub struct AllLanguageModelSettings {
     pub zed_dot_dev: ZedDotDevSettings,
     pub google: GoogleSettings,
     pub copilot_chat: CopilotChatSettings,
+    pub openai_compatible: Vec<OpenAiCompatibleSettings>,
 }
 
 #[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
@@ -72,6 +74,7 @@ pub struct AllLanguageModelSettingsContent {
     pub zed_dot_dev: Option<ZedDotDevSettingsContent>,
     pub google: Option<GoogleSettingsContent>,
     pub copilot_chat: Option<CopilotChatSettingsContent>,
+    pub openai_compatible: Option<Vec<OpenAiCompatibleSettingsContent>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
@@ -238,6 +241,20 @@ pub struct CopilotChatSettingsContent {
     low_speed_timeout_in_seconds: Option<u64>,
 }
 
+/// A server that implements OpenAI's chat completions API, e.g. vLLM or LM Studio.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct OpenAiCompatibleSettingsContent {
+    /// The name the provider is shown under, which must be unique.
+    pub name: String,
+    /// The base URL of the API, e.g. `http://localhost:8000/v1`.
+    pub api_url: String,
+    #[serde(default)]
+    pub api_key: open_ai_compatible::ApiKeySource,
+    pub low_speed_timeout_in_seconds: Option<u64>,
+    #[serde(default)]
+    pub available_models: Vec<open_ai_compatible::AvailableModel>,
+}
+
 impl settings::Settings for AllLanguageModelSettings {
     const KEY: Option<&'static str> = Some("language_models");
 
@@ -362,6 +379,23 @@ impl settings::Settings for AllLanguageModelSettings {
                 settings.copilot_chat.low_speed_timeout =
                     Some(Duration::from_secs(low_speed_timeout));
             }
+
+            // OpenAI-compatible servers are configured as a list, which replaces the one from
+            // earlier sources as a whole.
+            if let Some(servers) = value.openai_compatible.as_ref() {
+                settings.openai_compatible = servers
+                    .iter()
+                    .map(|server| OpenAiCompatibleSettings {
+                        name: server.name.clone(),
+                        api_url: server.api_url.clone(),
+                        api_key: server.api_key.clone(),
+                        low_speed_timeout: server
+                            .low_speed_timeout_in_seconds
+                            .map(Duration::from_secs),
+                        available_models: server.available_models.clone(),
+                    })
+                    .collect();
+            }
         }
 
         Ok(settings)
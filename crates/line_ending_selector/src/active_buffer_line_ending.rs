@@ -0,0 +1,111 @@
+use editor::Editor;
+use gpui::{div, IntoElement, ParentElement, Render, Subscription, View, ViewContext, WeakView};
+use language::LineEnding;
+use ui::{
+    Button, ButtonCommon, Clickable, Color, FluentBuilder, IconName, IconPosition, IconSize,
+    LabelSize, Tooltip,
+};
+use workspace::{item::ItemHandle, StatusItemView, Workspace};
+
+use crate::LineEndingSelector;
+
+pub struct ActiveBufferLineEnding {
+    active_line_ending: Option<(LineEnding, bool)>,
+    workspace: WeakView<Workspace>,
+    _observe_active_editor: Option<Subscription>,
+    _observe_active_buffer: Option<Subscription>,
+}
+
+impl ActiveBufferLineEnding {
+    pub fn new(workspace: &Workspace) -> Self {
+        Self {
+            active_line_ending: None,
+            workspace: workspace.weak_handle(),
+            _observe_active_editor: None,
+            _observe_active_buffer: None,
+        }
+    }
+
+    fn update_line_ending(&mut self, editor: View<Editor>, cx: &mut ViewContext<Self>) {
+        self.active_line_ending = None;
+        self._observe_active_buffer = None;
+
+        let editor = editor.read(cx);
+        if let Some(buffer) = editor.buffer().read(cx).as_singleton() {
+            let read_line_ending =
+                |buffer: &language::Buffer| (buffer.line_ending(), buffer.has_mixed_line_endings());
+            self.active_line_ending = Some(read_line_ending(buffer.read(cx)));
+            self._observe_active_buffer = Some(cx.observe(&buffer, move |this, buffer, cx| {
+                let line_ending = read_line_ending(buffer.read(cx));
+                if this.active_line_ending != Some(line_ending) {
+                    this.active_line_ending = Some(line_ending);
+                    cx.notify();
+                }
+            }));
+        }
+
+        cx.notify();
+    }
+}
+
+impl Render for ActiveBufferLineEnding {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        div().when_some(
+            self.active_line_ending,
+            |el, (line_ending, has_mixed_line_endings)| {
+                el.child(
+                    Button::new("change-line-ending", line_ending.label())
+                        .label_size(LabelSize::Small)
+                        .when(has_mixed_line_endings, |button| {
+                            button
+                                .icon(IconName::Warning)
+                                .icon_position(IconPosition::Start)
+                                .icon_size(IconSize::XSmall)
+                                .icon_color(Color::Warning)
+                        })
+                        .on_click(cx.listener(|this, _, cx| {
+                            if let Some(workspace) = this.workspace.upgrade() {
+                                workspace.update(cx, |workspace, cx| {
+                                    LineEndingSelector::toggle(workspace, cx)
+                                });
+                            }
+                        }))
+                        .tooltip(move |cx| {
+                            if has_mixed_line_endings {
+                                Tooltip::with_meta(
+                                    "Select Line Ending",
+                                    None,
+                                    format!(
+                                        "The file has mixed line endings, which will be converted to {} on save",
+                                        line_ending.label()
+                                    ),
+                                    cx,
+                                )
+                            } else {
+                                Tooltip::text("Select Line Ending", cx)
+                            }
+                        }),
+                )
+            },
+        )
+    }
+}
+
+impl StatusItemView for ActiveBufferLineEnding {
+    fn set_active_pane_item(
+        &mut self,
+        active_pane_item: Option<&dyn ItemHandle>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if let Some(editor) = active_pane_item.and_then(|item| item.act_as::<Editor>(cx)) {
+            self._observe_active_editor = Some(cx.observe(&editor, Self::update_line_ending));
+            self.update_line_ending(editor, cx);
+        } else {
+            self.active_line_ending = None;
+            self._observe_active_editor = None;
+            self._observe_active_buffer = None;
+        }
+
+        cx.notify();
+    }
+}
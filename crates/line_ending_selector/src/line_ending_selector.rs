@@ -0,0 +1,173 @@
+mod active_buffer_line_ending;
+
+pub use active_buffer_line_ending::ActiveBufferLineEnding;
+use editor::Editor;
+use gpui::{
+    actions, AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Model,
+    ParentElement, Render, Styled, Task, View, ViewContext, VisualContext, WeakView,
+};
+use language::{Buffer, LineEnding};
+use picker::{Picker, PickerDelegate};
+use std::sync::Arc;
+use ui::{prelude::*, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+actions!(line_ending_selector, [Toggle]);
+
+const LINE_ENDINGS: [LineEnding; 2] = [LineEnding::Unix, LineEnding::Windows];
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(LineEndingSelector::register).detach();
+}
+
+pub struct LineEndingSelector {
+    picker: View<Picker<LineEndingSelectorDelegate>>,
+}
+
+impl LineEndingSelector {
+    fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
+        workspace.register_action(move |workspace, _: &Toggle, cx| {
+            Self::toggle(workspace, cx);
+        });
+    }
+
+    fn toggle(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) -> Option<()> {
+        // Buffers are written by the host, which wouldn't see a guest's line ending.
+        if workspace.project().read(cx).is_via_collab() {
+            return None;
+        }
+        let buffer = workspace
+            .active_item(cx)?
+            .act_as::<Editor>(cx)?
+            .read(cx)
+            .buffer()
+            .read(cx)
+            .as_singleton()?;
+
+        workspace.toggle_modal(cx, move |cx| LineEndingSelector::new(buffer, cx));
+        Some(())
+    }
+
+    fn new(buffer: Model<Buffer>, cx: &mut ViewContext<Self>) -> Self {
+        let current_line_ending = buffer.read(cx).line_ending();
+        let delegate = LineEndingSelectorDelegate {
+            line_ending_selector: cx.view().downgrade(),
+            buffer,
+            matches: LINE_ENDINGS.to_vec(),
+            selected_index: LINE_ENDINGS
+                .iter()
+                .position(|line_ending| *line_ending == current_line_ending)
+                .unwrap_or(0),
+        };
+
+        let picker = cx.new_view(|cx| Picker::uniform_list(delegate, cx));
+        Self { picker }
+    }
+}
+
+impl Render for LineEndingSelector {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl FocusableView for LineEndingSelector {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for LineEndingSelector {}
+impl ModalView for LineEndingSelector {}
+
+pub struct LineEndingSelectorDelegate {
+    line_ending_selector: WeakView<LineEndingSelector>,
+    buffer: Model<Buffer>,
+    matches: Vec<LineEnding>,
+    selected_index: usize,
+}
+
+fn description(line_ending: LineEnding) -> &'static str {
+    match line_ending {
+        LineEnding::Unix => "Line feeds, as on Linux and macOS",
+        LineEnding::Windows => "Carriage returns and line feeds, as on Windows",
+    }
+}
+
+impl PickerDelegate for LineEndingSelectorDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _cx: &mut WindowContext) -> Arc<str> {
+        "Select a line ending...".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn confirm(&mut self, _: bool, cx: &mut ViewContext<Picker<Self>>) {
+        if let Some(line_ending) = self.matches.get(self.selected_index).copied() {
+            self.buffer.update(cx, |buffer, cx| {
+                buffer.set_line_ending(line_ending, cx);
+            });
+        }
+        self.dismissed(cx);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        self.line_ending_selector
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, _: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let query = query.to_lowercase();
+        self.matches = LINE_ENDINGS
+            .into_iter()
+            .filter(|line_ending| {
+                line_ending.label().to_lowercase().contains(&query)
+                    || description(*line_ending).to_lowercase().contains(&query)
+            })
+            .collect();
+        self.selected_index = self
+            .selected_index
+            .min(self.matches.len().saturating_sub(1));
+        Task::ready(())
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let line_ending = *self.matches.get(ix)?;
+        let mut label = line_ending.label().to_string();
+        if self.buffer.read(cx).line_ending() == line_ending {
+            label.push_str(" (current)");
+        }
+
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(
+                    h_flex().gap_2().child(Label::new(label)).child(
+                        Label::new(description(line_ending))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    ),
+                ),
+        )
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    project_settings::ProjectSettings,
     search::SearchQuery,
     worktree_store::{WorktreeStore, WorktreeStoreEvent},
     Item, NoRepositoryError, ProjectPath,
@@ -14,13 +15,18 @@ use gpui::{
 };
 use http_client::Url;
 use language::{
-    proto::{deserialize_line_ending, deserialize_version, serialize_version, split_operations},
-    Buffer, Capability, Event as BufferEvent, File as _, Language, Operation,
+    language_settings::language_settings,
+    proto::{
+        deserialize_encoding, deserialize_line_ending, deserialize_version, serialize_encoding,
+        serialize_version, split_operations,
+    },
+    Buffer, Capability, Event as BufferEvent, File as _, Language, LineEnding, Operation,
 };
 use rpc::{
     proto::{self, AnyProtoClient},
     ErrorExt as _, TypedEnvelope,
 };
+use settings::Settings as _;
 use smol::channel::Receiver;
 use std::{io, path::Path, str::FromStr as _, sync::Arc, time::Instant};
 use text::BufferId;
@@ -30,6 +36,9 @@ use worktree::{
     WorktreeId,
 };
 
+/// Large files are loaded this many bytes at a time.
+pub(crate) const LARGE_FILE_CHUNK_LEN: usize = 1024 * 1024;
+
 /// A set of open buffers.
 pub struct BufferStore {
     downstream_client: Option<AnyProtoClient>,
@@ -314,23 +323,50 @@ impl BufferStore {
         worktree: Model<Worktree>,
         cx: &mut ModelContext<Self>,
     ) -> Task<Result<Model<Buffer>>> {
+        // Files that were scanned are known to be large before they're loaded, and are loaded
+        // one chunk at a time. Files that weren't are loaded as a whole, and are opened in
+        // large-file mode if their text turns out to be large.
+        let large_file_threshold = ProjectSettings::get_global(cx).large_files.threshold_bytes;
         let load_buffer = worktree.update(cx, |worktree, cx| {
+            let size = worktree
+                .entry_for_path(path.as_ref())
+                .map(|entry| entry.size);
+            if size.is_some_and(|size| size > large_file_threshold) {
+                return Self::load_large_file(path.clone(), worktree, cx);
+            }
+
             let load_file = worktree.load_file(path.as_ref(), cx);
             let reservation = cx.reserve_model();
             let buffer_id = BufferId::from(reservation.entity_id().as_non_zero_u64());
             cx.spawn(move |_, mut cx| async move {
                 let loaded = load_file.await?;
-                let text_buffer = cx
+                let is_large_file = size.unwrap_or(loaded.text.len() as u64) > large_file_threshold;
+                let encoding = loaded.encoding;
+                let diff_base = loaded.diff_base.filter(|_| !is_large_file);
+                let (text_buffer, has_mixed_line_endings) = cx
                     .background_executor()
-                    .spawn(async move { text::Buffer::new(0, buffer_id, loaded.text) })
+                    .spawn(async move {
+                        let has_mixed_line_endings =
+                            !is_large_file && LineEnding::is_mixed(&loaded.text);
+                        (
+                            text::Buffer::new(0, buffer_id, loaded.text),
+                            has_mixed_line_endings,
+                        )
+                    })
                     .await;
-                cx.insert_model(reservation, |_| {
-                    Buffer::build(
+                cx.insert_model(reservation, |cx| {
+                    let mut buffer = Buffer::build(
                         text_buffer,
-                        loaded.diff_base,
+                        diff_base,
                         Some(loaded.file),
                         Capability::ReadWrite,
-                    )
+                    );
+                    buffer.set_encoding(encoding, cx);
+                    buffer.set_has_mixed_line_endings(has_mixed_line_endings, cx);
+                    if is_large_file {
+                        buffer.set_large_file(true, cx);
+                    }
+                    buffer
                 })
             })
         });
@@ -365,6 +401,64 @@ impl BufferStore {
         })
     }
 
+    /// Opens a large file in large-file mode as soon as its first chunk is loaded. The buffer is
+    /// read-only until the rest of the chunks are appended to it, so that it isn't saved without
+    /// them.
+    fn load_large_file(
+        path: Arc<Path>,
+        worktree: &Worktree,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<Model<Buffer>>> {
+        let load_file = worktree.load_file_in_chunks(path.as_ref(), LARGE_FILE_CHUNK_LEN, cx);
+        let reservation = cx.reserve_model();
+        let buffer_id = BufferId::from(reservation.entity_id().as_non_zero_u64());
+        cx.spawn(move |_, mut cx| async move {
+            let loaded = load_file.await?;
+            let first_chunk = loaded.text;
+            let text_buffer = cx
+                .background_executor()
+                .spawn(async move { text::Buffer::new(0, buffer_id, first_chunk) })
+                .await;
+            let buffer = cx.insert_model(reservation, |cx| {
+                let mut buffer =
+                    Buffer::build(text_buffer, None, Some(loaded.file), Capability::ReadOnly);
+                buffer.set_encoding(loaded.encoding, cx);
+                buffer.set_large_file(true, cx);
+                buffer
+            })?;
+
+            let mut remaining_chunks = loaded.remaining_chunks;
+            let weak_buffer = buffer.downgrade();
+            cx.spawn(|mut cx| {
+                async move {
+                    let mut version = weak_buffer.update(&mut cx, |buffer, _| buffer.version())?;
+                    while let Some(chunk) = remaining_chunks.next().await {
+                        let chunk = chunk?;
+                        let appended = weak_buffer.update(&mut cx, |buffer, cx| {
+                            // The buffer was reloaded with all of the file's text.
+                            if buffer.version() != version {
+                                return None;
+                            }
+                            buffer.append_loaded_chunk(&chunk, cx);
+                            Some(buffer.version())
+                        })?;
+                        let Some(appended_version) = appended else {
+                            break;
+                        };
+                        version = appended_version;
+                    }
+                    weak_buffer.update(&mut cx, |buffer, cx| {
+                        buffer.set_capability(Capability::ReadWrite, cx)
+                    })
+                }
+                .log_err()
+            })
+            .detach();
+
+            Ok(buffer)
+        })
+    }
+
     fn open_remote_buffer_internal(
         &self,
         path: &Arc<Path>,
@@ -419,8 +513,15 @@ impl BufferStore {
         cx: &mut ModelContext<Self>,
     ) -> Model<Buffer> {
         let buffer = cx.new_model(|cx| {
-            Buffer::local(text, cx)
-                .with_language(language.unwrap_or_else(|| language::PLAIN_TEXT.clone()), cx)
+            let language = language.unwrap_or_else(|| language::PLAIN_TEXT.clone());
+            let line_ending = language_settings(Some(&language), None, cx)
+                .line_ending
+                .line_ending();
+            let mut buffer = Buffer::local(text, cx).with_language(language, cx);
+            if LineEnding::try_detect(text).is_none() {
+                buffer.set_line_ending(line_ending, cx);
+            }
+            buffer
         });
         self.add_buffer(buffer.clone(), cx).log_err();
         buffer
@@ -485,6 +586,7 @@ impl BufferStore {
         let buffer = buffer_handle.read(cx);
         let text = buffer.as_rope().clone();
         let line_ending = buffer.line_ending();
+        let encoding = buffer.encoding();
         let version = buffer.version();
         let buffer_id = buffer.remote_id();
         if buffer.file().is_some_and(|file| !file.is_created()) {
@@ -492,7 +594,7 @@ impl BufferStore {
         }
 
         let save = worktree.update(cx, |worktree, cx| {
-            worktree.write_file(path.as_ref(), text, line_ending, cx)
+            worktree.write_file(path.as_ref(), text, line_ending, encoding, cx)
         });
 
         cx.spawn(move |this, mut cx| async move {
@@ -516,6 +618,7 @@ impl BufferStore {
                             buffer_id: buffer_id.to_proto(),
                             version: serialize_version(&version),
                             mtime: mtime.map(|time| time.into()),
+                            encoding: serialize_encoding(encoding) as i32,
                         })
                         .log_err();
                 }
@@ -539,6 +642,7 @@ impl BufferStore {
         let buffer = buffer_handle.read(cx);
         let buffer_id = buffer.remote_id().into();
         let version = buffer.version();
+        let encoding = buffer.encoding();
         let rpc = tree.client();
         let project_id = tree.project_id();
         cx.spawn(move |_, mut cx| async move {
@@ -548,12 +652,18 @@ impl BufferStore {
                     buffer_id,
                     new_path,
                     version: serialize_version(&version),
+                    encoding: Some(serialize_encoding(encoding) as i32),
                 })
                 .await?;
             let version = deserialize_version(&response.version);
             let mtime = response.mtime.map(|mtime| mtime.into());
+            let encoding = deserialize_encoding(
+                proto::Encoding::from_i32(response.encoding)
+                    .ok_or_else(|| anyhow!("invalid encoding"))?,
+            );
 
             buffer_handle.update(&mut cx, |buffer, cx| {
+                buffer.set_encoding(encoding, cx);
                 buffer.did_save(version.clone(), mtime, cx);
             })?;
 
@@ -831,7 +941,9 @@ impl BufferStore {
         fs: Arc<dyn Fs>,
         cx: &mut ModelContext<Self>,
     ) -> Receiver<Model<Buffer>> {
-        let (tx, rx) = smol::channel::unbounded();
+        const MAX_CONCURRENT_BUFFER_OPENS: usize = 64;
+        // Buffers are only opened as fast as the search consumes them.
+        let (tx, rx) = smol::channel::bounded(MAX_CONCURRENT_BUFFER_OPENS);
         let mut open_buffers = HashSet::default();
         let mut unnamed_buffers = Vec::new();
         for handle in self.buffers() {
@@ -844,7 +956,6 @@ impl BufferStore {
             };
         }
 
-        const MAX_CONCURRENT_BUFFER_OPENS: usize = 64;
         let mut project_paths_rx = self
             .worktree_store
             .update(cx, |worktree_store, cx| {
@@ -1125,6 +1236,7 @@ impl BufferStore {
                         mtime: buffer.saved_mtime().map(|time| time.into()),
                         line_ending: language::proto::serialize_line_ending(buffer.line_ending())
                             as i32,
+                        encoding: serialize_encoding(buffer.encoding()) as i32,
                     })
                     .log_err();
 
@@ -1309,7 +1421,17 @@ impl BufferStore {
                 buffer.wait_for_version(deserialize_version(&envelope.payload.version))
             })?
             .await?;
-        let buffer_id = buffer.update(&mut cx, |buffer, _| buffer.remote_id())?;
+        let buffer_id = buffer.update(&mut cx, |buffer, cx| {
+            // Guests choose the encoding the file is saved with.
+            if let Some(encoding) = envelope
+                .payload
+                .encoding
+                .and_then(proto::Encoding::from_i32)
+            {
+                buffer.set_encoding(deserialize_encoding(encoding), cx);
+            }
+            buffer.remote_id()
+        })?;
 
         if let Some(new_path) = envelope.payload.new_path {
             let new_path = ProjectPath::from_proto(new_path);
@@ -1327,6 +1449,7 @@ impl BufferStore {
             buffer_id: buffer_id.into(),
             version: serialize_version(buffer.saved_version()),
             mtime: buffer.saved_mtime().map(|time| time.into()),
+            encoding: serialize_encoding(buffer.encoding()) as i32,
         })
     }
 
@@ -1362,9 +1485,14 @@ impl BufferStore {
         let buffer_id = BufferId::new(envelope.payload.buffer_id)?;
         let version = deserialize_version(&envelope.payload.version);
         let mtime = envelope.payload.mtime.map(|time| time.into());
+        let encoding = deserialize_encoding(
+            proto::Encoding::from_i32(envelope.payload.encoding)
+                .ok_or_else(|| anyhow!("invalid encoding"))?,
+        );
         this.update(&mut cx, |this, cx| {
             if let Some(buffer) = this.get_possibly_incomplete(buffer_id) {
                 buffer.update(cx, |buffer, cx| {
+                    buffer.set_encoding(encoding, cx);
                     buffer.did_save(version, mtime, cx);
                 });
             }
@@ -1383,9 +1511,14 @@ impl BufferStore {
             proto::LineEnding::from_i32(envelope.payload.line_ending)
                 .ok_or_else(|| anyhow!("missing line ending"))?,
         );
+        let encoding = deserialize_encoding(
+            proto::Encoding::from_i32(envelope.payload.encoding)
+                .ok_or_else(|| anyhow!("invalid encoding"))?,
+        );
         this.update(&mut cx, |this, cx| {
             if let Some(buffer) = this.get_possibly_incomplete(buffer_id) {
                 buffer.update(cx, |buffer, cx| {
+                    buffer.set_encoding(encoding, cx);
                     buffer.did_reload(version, line_ending, mtime, cx);
                 });
             }
@@ -653,12 +653,20 @@ impl LspStore {
             }) {
                 buffer.set_language(Some(new_language.clone()), cx);
             }
+
+            // Files that don't exist on disk yet are created with the language's line ending.
+            if buffer.file().map_or(true, |file| !file.is_created()) {
+                let line_ending = language_settings(Some(&new_language), buffer.file(), cx)
+                    .line_ending
+                    .line_ending();
+                buffer.set_line_ending(line_ending, cx);
+            }
         });
 
         let buffer_file = buffer.read(cx).file().cloned();
         let buffer_file = File::from_dyn(buffer_file.as_ref());
 
-        if let Some(file) = buffer_file {
+        if let Some(file) = buffer_file.filter(|_| !buffer.read(cx).is_large_file()) {
             let worktree = file.worktree.clone();
             self.start_language_servers(&worktree, new_language.name(), cx)
         }
@@ -2475,6 +2483,9 @@ impl LspStore {
 
         let buffer = buffer_handle.read(cx);
         let buffer_id = buffer.remote_id();
+        if buffer.is_large_file() {
+            return;
+        }
 
         if let Some(file) = File::from_dyn(buffer.file()) {
             if !file.is_local() {
@@ -6165,6 +6176,9 @@ impl LspStore {
         buffer: &Buffer,
         cx: &AppContext,
     ) -> Vec<LanguageServerId> {
+        if buffer.is_large_file() {
+            return Vec::new();
+        }
         if let Some((file, language)) = File::from_dyn(buffer.file()).zip(buffer.language()) {
             let worktree_id = file.worktree_id(cx);
             self.languages
@@ -7,6 +7,7 @@ pub mod lsp_store;
 mod prettier_support;
 pub mod project_settings;
 pub mod search;
+pub mod search_index;
 mod task_inventory;
 pub mod terminals;
 pub mod worktree_store;
@@ -47,12 +48,12 @@ use language::{
         SelectedFormatter,
     },
     proto::{
-        deserialize_anchor, serialize_anchor, serialize_line_ending, serialize_version,
-        split_operations,
+        deserialize_anchor, serialize_anchor, serialize_encoding, serialize_line_ending,
+        serialize_version, split_operations,
     },
     Buffer, CachedLspAdapter, Capability, CodeLabel, ContextProvider, DiagnosticEntry, Diff,
     Documentation, Event as BufferEvent, File as _, Language, LanguageRegistry, LanguageServerName,
-    PointUtf16, ToOffset, ToPointUtf16, Transaction, Unclipped,
+    ParseStatus, PointUtf16, ToOffset, ToPointUtf16, Transaction, Unclipped,
 };
 use lsp::{CompletionContext, DocumentHighlightKind, LanguageServer, LanguageServerId};
 use lsp_command::*;
@@ -111,6 +112,7 @@ pub use lsp_store::{
 };
 
 const MAX_PROJECT_SEARCH_HISTORY_SIZE: usize = 500;
+/// Remote searches respond with all of their results at once, so they stop after this many.
 const MAX_SEARCH_RESULT_FILES: usize = 5_000;
 const MAX_SEARCH_RESULT_RANGES: usize = 10_000;
 
@@ -1282,6 +1284,10 @@ impl Project {
             }
         });
 
+        self.worktree_store.update(cx, |worktree_store, cx| {
+            worktree_store.update_search_index(cx)
+        });
+
         cx.notify();
     }
 
@@ -2392,6 +2398,7 @@ impl Project {
                                 version: serialize_version(&buffer.version()),
                                 mtime: buffer.saved_mtime().map(|t| t.into()),
                                 line_ending: serialize_line_ending(buffer.line_ending()) as i32,
+                                encoding: serialize_encoding(buffer.encoding()) as i32,
                             })
                             .log_err();
                     }
@@ -3529,18 +3536,17 @@ impl Project {
         query: SearchQuery,
         cx: &mut ModelContext<Self>,
     ) -> Receiver<SearchResult> {
-        let (result_tx, result_rx) = smol::channel::unbounded();
+        // Results are produced as fast as they are consumed, so that every match is reachable
+        // without loading the whole project into memory when only the first few are looked at.
+        let (result_tx, result_rx) = smol::channel::bounded(1024);
 
         let matching_buffers_rx = if query.is_opened_only() {
             self.sort_candidate_buffers(&query, cx)
         } else {
-            self.search_for_candidate_buffers(&query, MAX_SEARCH_RESULT_FILES + 1, cx)
+            self.search_for_candidate_buffers(&query, usize::MAX, cx)
         };
 
         cx.spawn(|_, cx| async move {
-            let mut range_count = 0;
-            let mut buffer_count = 0;
-            let mut limit_reached = false;
             let query = Arc::new(query);
             let mut chunks = matching_buffers_rx.ready_chunks(64);
 
@@ -3548,11 +3554,22 @@ impl Project {
             // 64 buffers at a time to avoid overwhelming the main thread. For each
             // opened buffer, we will spawn a background task that retrieves all the
             // ranges in the buffer matched by the query.
-            'outer: while let Some(matching_buffer_chunk) = chunks.next().await {
+            while let Some(matching_buffer_chunk) = chunks.next().await {
                 let mut chunk_results = Vec::new();
                 for buffer in matching_buffer_chunk {
                     let buffer = buffer.clone();
                     let query = query.clone();
+                    if query.uses_syntax() {
+                        // Wait for freshly opened buffers to be parsed, as the query matches
+                        // against their syntax trees.
+                        let mut parse_status =
+                            buffer.read_with(&cx, |buffer, _| buffer.parse_status())?;
+                        while *parse_status.borrow() != ParseStatus::Idle {
+                            if parse_status.changed().await.is_err() {
+                                break;
+                            }
+                        }
+                    }
                     let snapshot = buffer.read_with(&cx, |buffer, _| buffer.snapshot())?;
                     chunk_results.push(cx.background_executor().spawn(async move {
                         let ranges = query
@@ -3571,25 +3588,13 @@ impl Project {
                 let chunk_results = futures::future::join_all(chunk_results).await;
                 for result in chunk_results {
                     if let Some((buffer, ranges)) = result.log_err() {
-                        range_count += ranges.len();
-                        buffer_count += 1;
                         result_tx
                             .send(SearchResult::Buffer { buffer, ranges })
                             .await?;
-                        if buffer_count > MAX_SEARCH_RESULT_FILES
-                            || range_count > MAX_SEARCH_RESULT_RANGES
-                        {
-                            limit_reached = true;
-                            break 'outer;
-                        }
                     }
                 }
             }
 
-            if limit_reached {
-                result_tx.send(SearchResult::LimitReached).await?;
-            }
-
             anyhow::Ok(())
         })
         .detach();
@@ -4532,10 +4537,19 @@ impl Project {
 
         cx.spawn(move |mut cx| async move {
             let mut locations = Vec::new();
+            let mut buffer_count = 0;
             let mut limit_reached = false;
             while let Some(result) = result.next().await {
+                if buffer_count >= MAX_SEARCH_RESULT_FILES
+                    || locations.len() >= MAX_SEARCH_RESULT_RANGES
+                {
+                    // Dropping the results stops the search.
+                    limit_reached = true;
+                    break;
+                }
                 match result {
                     SearchResult::Buffer { buffer, ranges } => {
+                        buffer_count += 1;
                         for range in ranges {
                             let start = serialize_anchor(&range.start);
                             let end = serialize_anchor(&range.end);
@@ -4549,13 +4563,11 @@ impl Project {
                             });
                         }
                     }
-                    SearchResult::LimitReached => limit_reached = true,
                 }
             }
             Ok(proto::SearchProjectResponse {
                 locations,
                 limit_reached,
-                // will restart
             })
         })
         .await
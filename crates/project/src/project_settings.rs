@@ -43,6 +43,14 @@ pub struct ProjectSettings {
     /// Configuration for session-related features
     #[serde(default)]
     pub session: SessionSettings,
+
+    /// Configuration for opening large files
+    #[serde(default)]
+    pub large_files: LargeFileSettings,
+
+    /// Configuration for the index that speeds up project search
+    #[serde(default)]
+    pub search_index: SearchIndexSettings,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
@@ -160,6 +168,46 @@ impl Default for SessionSettings {
     }
 }
 
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct LargeFileSettings {
+    /// Files larger than this many bytes are opened in large-file mode, which
+    /// skips syntax parsing, language servers, Git diffs, inlay hints and soft wrap.
+    ///
+    /// Default: 20000000
+    pub threshold_bytes: u64,
+}
+
+impl Default for LargeFileSettings {
+    fn default() -> Self {
+        Self {
+            threshold_bytes: 20_000_000,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SearchIndexSettings {
+    /// Whether to index the contents of local worktrees, so that project searches can
+    /// skip files that can't match.
+    ///
+    /// Default: false
+    pub enabled: bool,
+    /// Files larger than this many bytes, or than the large file threshold, aren't
+    /// indexed and are always read when searching.
+    ///
+    /// Default: 1048576
+    pub max_file_size_bytes: u64,
+}
+
+impl Default for SearchIndexSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_file_size_bytes: 1024 * 1024,
+        }
+    }
+}
+
 impl Settings for ProjectSettings {
     const KEY: Option<&'static str> = None;
 
@@ -1,11 +1,11 @@
-use crate::{Event, *};
+use crate::{search::SearchScope, Event, *};
 use fs::FakeFs;
 use futures::{future, StreamExt};
 use gpui::{AppContext, SemanticVersion, UpdateGlobal};
 use http_client::Url;
 use language::{
-    language_settings::{AllLanguageSettings, LanguageSettingsContent},
-    tree_sitter_rust, tree_sitter_typescript, Diagnostic, DiagnosticSet, FakeLspAdapter,
+    language_settings::{AllLanguageSettings, LanguageSettingsContent, LineEndingSetting},
+    tree_sitter_rust, tree_sitter_typescript, Diagnostic, DiagnosticSet, Encoding, FakeLspAdapter,
     LanguageConfig, LanguageMatcher, LanguageName, LineEnding, OffsetRangeExt, Point, ToPoint,
 };
 use lsp::{DiagnosticSeverity, NumberOrString};
@@ -1609,6 +1609,102 @@ async fn test_toggling_enable_language_server(cx: &mut gpui::TestAppContext) {
         .await;
 }
 
+#[gpui::test]
+async fn test_opening_large_files(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings::<ProjectSettings>(cx, |settings| {
+                settings.large_files.threshold_bytes = 10;
+            });
+        })
+    });
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({ "small.rs": "a", "large.rs": "fn main() {}\n" }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let mut fake_servers = language_registry.register_fake_lsp_adapter("Rust", Default::default());
+
+    // Large files aren't parsed, and don't start language servers.
+    let large_buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/large.rs", cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    large_buffer.update(cx, |buffer, _| {
+        assert!(buffer.is_large_file());
+        assert!(buffer.snapshot().syntax_layers().next().is_none());
+    });
+    assert!(fake_servers.try_next().is_err());
+
+    // Smaller files are opened as usual, and only they are reported to the server.
+    let small_buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/small.rs", cx)
+        })
+        .await
+        .unwrap();
+    small_buffer.update(cx, |buffer, _| assert!(!buffer.is_large_file()));
+    let mut fake_server = fake_servers.next().await.unwrap();
+    assert_eq!(
+        fake_server
+            .receive_notification::<lsp::notification::DidOpenTextDocument>()
+            .await
+            .text_document
+            .uri
+            .as_str(),
+        "file:///dir/small.rs"
+    );
+}
+
+#[gpui::test]
+async fn test_loading_large_files_in_chunks(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings::<ProjectSettings>(cx, |settings| {
+                settings.large_files.threshold_bytes = 10;
+            });
+        })
+    });
+
+    // The file is loaded in three chunks, the first of which ends between a "\r" and a "\n".
+    let chunk_len = crate::buffer_store::LARGE_FILE_CHUNK_LEN;
+    let text = format!("head\n{}", "abcd\r\n".repeat(2 * chunk_len / 6));
+    assert_eq!(&text[chunk_len - 1..chunk_len + 1], "\r\n");
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({})).await;
+    fs.insert_file("/dir/large.txt", text.clone().into_bytes())
+        .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/large.txt", cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+
+    // Once every chunk is appended, the buffer can be edited, and appending them can't be undone.
+    buffer.update(cx, |buffer, cx| {
+        assert!(buffer.is_large_file());
+        assert_eq!(buffer.text(), text.replace("\r\n", "\n"));
+        assert_eq!(buffer.capability(), Capability::ReadWrite);
+        assert!(!buffer.is_dirty());
+        assert!(buffer.undo(cx).is_none());
+    });
+}
+
 #[gpui::test(iterations = 3)]
 async fn test_transforming_diagnostics(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -2816,6 +2912,73 @@ async fn test_save_file(cx: &mut gpui::TestAppContext) {
     assert_eq!(new_text, buffer.update(cx, |buffer, _| buffer.text()));
 }
 
+#[gpui::test]
+async fn test_save_file_with_encoding(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({})).await;
+    fs.insert_file(
+        "/dir/file.txt",
+        Encoding::ShiftJis.encode("こんにちは、世界\n").unwrap(),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/file.txt", cx))
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.encoding(), Encoding::ShiftJis);
+        assert_eq!(buffer.text(), "こんにちは、世界\n");
+        buffer.edit([(buffer.len()..buffer.len(), "さようなら\n")], None, cx);
+    });
+
+    // The file is saved in the encoding it was opened with.
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load_bytes(Path::new("/dir/file.txt")).await.unwrap(),
+        Encoding::ShiftJis
+            .encode("こんにちは、世界\nさようなら\n")
+            .unwrap()
+    );
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/file.txt", cx))
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.encoding(), Encoding::ShiftJis);
+        assert_eq!(buffer.text(), "こんにちは、世界\nさようなら\n");
+        buffer.set_encoding(Encoding::Utf8Bom, cx);
+    });
+
+    // Saving with another encoding converts the file.
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load_bytes(Path::new("/dir/file.txt")).await.unwrap(),
+        "\u{FEFF}こんにちは、世界\nさようなら\n".as_bytes()
+    );
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/file.txt", cx))
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(buffer.encoding(), Encoding::Utf8Bom);
+        assert_eq!(buffer.text(), "こんにちは、世界\nさようなら\n");
+    });
+}
+
 #[gpui::test(iterations = 30)]
 async fn test_file_changes_multiple_times_on_disk(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -3548,6 +3711,114 @@ async fn test_buffer_line_endings(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_converting_buffer_line_endings(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "mixed.txt": "one\r\ntwo\nthree\r\n",
+            "long.txt": format!("{}\r\nshort\r\n", "a".repeat(2000)),
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    project.read_with(cx, |project, _| project.languages().add(rust_lang()));
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                settings.languages.insert(
+                    "Rust".into(),
+                    LanguageSettingsContent {
+                        line_ending: Some(LineEndingSetting::Crlf),
+                        ..Default::default()
+                    },
+                );
+            });
+        })
+    });
+
+    // Files with mixed line endings are flagged, and converted when they're saved.
+    let mixed_buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/mixed.txt", cx))
+        .await
+        .unwrap();
+    mixed_buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        assert!(buffer.has_mixed_line_endings());
+        assert!(!buffer.is_dirty());
+
+        buffer.set_line_ending(LineEnding::Unix, cx);
+        assert!(buffer.is_dirty());
+    });
+    project
+        .update(cx, |project, cx| {
+            project.save_buffer(mixed_buffer.clone(), cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load("/dir/mixed.txt".as_ref()).await.unwrap(),
+        "one\ntwo\nthree\n",
+    );
+    mixed_buffer.update(cx, |buffer, _| {
+        assert!(!buffer.has_mixed_line_endings());
+        assert!(!buffer.is_dirty());
+    });
+
+    // Reloading a file keeps its line ending, even when its first line is long
+    // or when it no longer has several lines.
+    let long_buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/long.txt", cx))
+        .await
+        .unwrap();
+    long_buffer.update(cx, |buffer, _| {
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+    });
+    fs.save(
+        "/dir/long.txt".as_ref(),
+        &format!("{}\nshorter\n", "b".repeat(2000)).as_str().into(),
+        LineEnding::Windows,
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    long_buffer.update(cx, |buffer, _| {
+        assert!(buffer.text().ends_with("shorter\n"));
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        assert!(!buffer.is_dirty());
+    });
+    let diff = long_buffer
+        .update(cx, |buffer, cx| buffer.diff("single line".into(), cx))
+        .await;
+    long_buffer.update(cx, |buffer, cx| {
+        buffer.apply_diff(diff, cx);
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+    });
+
+    // New files use the line ending configured for their language.
+    let new_buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/new.rs", cx))
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    new_buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        buffer.set_text("fn main() {\n}\n", cx);
+    });
+    project
+        .update(cx, |project, cx| project.save_buffer(new_buffer, cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load("/dir/new.rs".as_ref()).await.unwrap(),
+        "fn main() {\r\n}\r\n",
+    );
+}
+
 #[gpui::test]
 async fn test_grouped_diagnostics(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -3999,6 +4270,278 @@ async fn test_search(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_search_after_files_change(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = 2;",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    cx.run_until_parked();
+
+    let query = |text: &str| {
+        SearchQuery::text(
+            text,
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        search(&project, query("TWO"), cx).await.unwrap(),
+        HashMap::from_iter([("dir/two.rs".to_string(), vec![6..9])])
+    );
+
+    fs.insert_file("/dir/two.rs", "const THREE: usize = 3;".into())
+        .await;
+    fs.insert_file("/dir/four.rs", "const FOUR: usize = TWO + TWO;".into())
+        .await;
+    cx.run_until_parked();
+
+    assert_eq!(
+        search(&project, query("TWO"), cx).await.unwrap(),
+        HashMap::from_iter([("dir/four.rs".to_string(), vec![20..23, 26..29])])
+    );
+    assert_eq!(
+        search(&project, query("THREE"), cx).await.unwrap(),
+        HashMap::from_iter([("dir/two.rs".to_string(), vec![6..11])])
+    );
+}
+
+#[gpui::test]
+async fn test_search_index_settings(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    let update_settings = |cx: &mut gpui::TestAppContext, enabled: bool| {
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |settings, cx| {
+                settings.update_user_settings::<ProjectSettings>(cx, |settings| {
+                    settings.search_index.enabled = enabled;
+                    settings.search_index.max_file_size_bytes = 16;
+                });
+            })
+        });
+    };
+    update_settings(cx, true);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "small.rs": "let TWO = 2;",
+            "large.rs": "let TWO: usize = 2;",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    cx.run_until_parked();
+
+    let query = |text: &str| {
+        SearchQuery::text(
+            text,
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        search(&project, query("TWO"), cx).await.unwrap(),
+        HashMap::from_iter([
+            ("dir/small.rs".to_string(), vec![4..7]),
+            ("dir/large.rs".to_string(), vec![4..7]),
+        ])
+    );
+
+    update_settings(cx, false);
+    fs.insert_file("/dir/small.rs", "let THREE = 3;".into())
+        .await;
+    cx.run_until_parked();
+    assert_eq!(
+        search(&project, query("THREE"), cx).await.unwrap(),
+        HashMap::from_iter([("dir/small.rs".to_string(), vec![4..9])])
+    );
+
+    update_settings(cx, true);
+    cx.run_until_parked();
+    assert_eq!(
+        search(&project, query("THREE"), cx).await.unwrap(),
+        HashMap::from_iter([("dir/small.rs".to_string(), vec![4..9])])
+    );
+    assert_eq!(
+        search(&project, query("TWO"), cx).await.unwrap(),
+        HashMap::from_iter([("dir/large.rs".to_string(), vec![4..7])])
+    );
+}
+
+#[gpui::test]
+async fn test_search_with_scope(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let text = r#"
+        // Call one() here.
+        fn one() {}
+        fn two() {
+            let s = "one";
+            one();
+        }
+        fn three() {
+            one();
+        }
+    "#
+    .unindent();
+    let occurrences = text
+        .match_indices("one")
+        .map(|(ix, _)| ix..ix + 3)
+        .collect::<Vec<_>>();
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({ "lib.rs": text })).await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    let language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            matcher: LanguageMatcher {
+                path_suffixes: vec!["rs".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    )
+    .with_override_query(
+        r#"
+        (string_literal) @string
+        [(line_comment) (block_comment)] @comment
+        "#,
+    )
+    .unwrap()
+    .with_outline_query(
+        r#"
+        (function_item
+            "fn" @context
+            name: (_) @name
+            body: (block "{" @open "}" @close)) @item
+        "#,
+    )
+    .unwrap();
+    project.update(cx, |project, _| project.languages().add(Arc::new(language)));
+
+    let search_in_scope = |scope| {
+        SearchQuery::text(
+            "one",
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            None,
+        )
+        .unwrap()
+        .with_scope(scope)
+    };
+    let expected = |indices: &[usize]| {
+        HashMap::from_iter([(
+            "dir/lib.rs".to_string(),
+            indices
+                .iter()
+                .map(|ix| occurrences[*ix].clone())
+                .collect::<Vec<_>>(),
+        )])
+    };
+
+    assert_eq!(
+        search(&project, search_in_scope(SearchScope::Comments), cx)
+            .await
+            .unwrap(),
+        expected(&[0])
+    );
+    assert_eq!(
+        search(&project, search_in_scope(SearchScope::Strings), cx)
+            .await
+            .unwrap(),
+        expected(&[2])
+    );
+    assert_eq!(
+        search(&project, search_in_scope(SearchScope::Code), cx)
+            .await
+            .unwrap(),
+        expected(&[1, 3, 4])
+    );
+    assert_eq!(
+        search(
+            &project,
+            search_in_scope(SearchScope::Symbol("two".into())),
+            cx
+        )
+        .await
+        .unwrap(),
+        expected(&[2, 3])
+    );
+}
+
+#[gpui::test]
+async fn test_structural_search(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "one.rs": "fn one() { foo(1, 2); }",
+            "two.rs": "fn two() { foo(a); bar(1, 2); }",
+            "three.rs": "// foo(1, 2)\nfn three() { foo(x, y) }",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages().add(rust_lang()));
+
+    let query = SearchQuery::structural(
+        "foo($A, $B)",
+        false,
+        Default::default(),
+        Default::default(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        search(&project, query.clone(), cx).await.unwrap(),
+        HashMap::from_iter([
+            ("dir/one.rs".to_string(), vec![11..20]),
+            ("dir/three.rs".to_string(), vec![26..35])
+        ])
+    );
+
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/one.rs", cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    let query = query.with_replacement("bar($B, $A)".to_string());
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(
+            query.replacement_for_range(&buffer.snapshot(), 11..20),
+            Some("bar(2, 1)".to_string())
+        );
+    });
+}
+
 #[gpui::test]
 async fn test_search_with_inclusions(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -5254,7 +5797,6 @@ async fn search(
             SearchResult::Buffer { buffer, ranges } => {
                 results.entry(buffer).or_insert(ranges);
             }
-            SearchResult::LimitReached => {}
         }
     }
     Ok(results
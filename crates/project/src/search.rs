@@ -1,8 +1,10 @@
+mod structural;
+
 use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
 use anyhow::Result;
 use client::proto;
 use gpui::Model;
-use language::{Buffer, BufferSnapshot};
+use language::{Buffer, BufferSnapshot, ToOffset};
 use regex::{Captures, Regex, RegexBuilder};
 use smol::future::yield_now;
 use std::{
@@ -15,6 +17,8 @@ use std::{
 use text::Anchor;
 use util::paths::PathMatcher;
 
+pub use structural::{StructuralMatch, StructuralPattern};
+
 static TEXT_REPLACEMENT_SPECIAL_CHARACTERS_REGEX: OnceLock<Regex> = OnceLock::new();
 
 pub enum SearchResult {
@@ -22,7 +26,6 @@ pub enum SearchResult {
         buffer: Model<Buffer>,
         ranges: Vec<Range<Anchor>>,
     },
-    LimitReached,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -32,12 +35,125 @@ pub enum SearchInputKind {
     Exclude,
 }
 
+/// The parts of the code, as determined by its syntax, that a search is restricted to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SearchScope {
+    #[default]
+    Everywhere,
+    /// Only matches inside comments.
+    Comments,
+    /// Only matches inside string literals.
+    Strings,
+    /// Only matches outside of comments and string literals.
+    Code,
+    /// Only matches inside the body of symbols with the given name.
+    Symbol(String),
+}
+
+impl SearchScope {
+    fn to_proto(&self) -> proto::SearchScope {
+        let (kind, symbol) = match self {
+            SearchScope::Everywhere => (proto::search_scope::Kind::Everywhere, String::new()),
+            SearchScope::Comments => (proto::search_scope::Kind::Comments, String::new()),
+            SearchScope::Strings => (proto::search_scope::Kind::Strings, String::new()),
+            SearchScope::Code => (proto::search_scope::Kind::Code, String::new()),
+            SearchScope::Symbol(symbol) => (proto::search_scope::Kind::Symbol, symbol.clone()),
+        };
+        proto::SearchScope {
+            kind: kind.into(),
+            symbol,
+        }
+    }
+
+    fn from_proto(scope: Option<proto::SearchScope>) -> Self {
+        let Some(scope) = scope else {
+            return SearchScope::Everywhere;
+        };
+        match proto::search_scope::Kind::from_i32(scope.kind) {
+            Some(proto::search_scope::Kind::Comments) => SearchScope::Comments,
+            Some(proto::search_scope::Kind::Strings) => SearchScope::Strings,
+            Some(proto::search_scope::Kind::Code) => SearchScope::Code,
+            Some(proto::search_scope::Kind::Symbol) => SearchScope::Symbol(scope.symbol),
+            Some(proto::search_scope::Kind::Everywhere) | None => SearchScope::Everywhere,
+        }
+    }
+
+    /// Removes the matches that fall outside of this scope.
+    fn filter_matches(&self, buffer: &BufferSnapshot, matches: &mut Vec<Range<usize>>) {
+        let Some((first, last)) = matches.first().zip(matches.last()) else {
+            return;
+        };
+        let range = first.start..last.end;
+        match self {
+            SearchScope::Everywhere => {}
+            SearchScope::Comments => {
+                let comments = buffer.override_scope_ranges(range, "comment");
+                matches.retain(|mat| ranges_contain(&comments, mat));
+            }
+            SearchScope::Strings => {
+                let strings = buffer.override_scope_ranges(range, "string");
+                matches.retain(|mat| ranges_contain(&strings, mat));
+            }
+            SearchScope::Code => {
+                let comments = buffer.override_scope_ranges(range.clone(), "comment");
+                let strings = buffer.override_scope_ranges(range, "string");
+                matches.retain(|mat| {
+                    !ranges_intersect(&comments, mat) && !ranges_intersect(&strings, mat)
+                });
+            }
+            SearchScope::Symbol(symbol) => {
+                let mut bodies = buffer
+                    .outline_items_containing(range, false, None)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|item| {
+                        let name = item
+                            .name_ranges
+                            .iter()
+                            .filter_map(|range| item.text.get(range.clone()))
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        name == symbol.trim()
+                    })
+                    .map(|item| {
+                        let range = item.body_range.unwrap_or(item.range);
+                        range.start.to_offset(buffer)..range.end.to_offset(buffer)
+                    })
+                    .collect::<Vec<_>>();
+                bodies.sort_unstable_by_key(|range| range.start);
+                matches.retain(|mat| {
+                    bodies
+                        .iter()
+                        .any(|body| body.start <= mat.start && mat.end <= body.end)
+                });
+            }
+        }
+    }
+}
+
+/// Whether one of the sorted, disjoint `ranges` contains `range`.
+fn ranges_contain(ranges: &[Range<usize>], range: &Range<usize>) -> bool {
+    let ix = ranges.partition_point(|probe| probe.end < range.end);
+    ranges
+        .get(ix)
+        .map_or(false, |probe| probe.start <= range.start)
+}
+
+/// Whether one of the sorted, disjoint `ranges` intersects `range`.
+fn ranges_intersect(ranges: &[Range<usize>], range: &Range<usize>) -> bool {
+    let ix = ranges.partition_point(|probe| probe.end <= range.start);
+    ranges
+        .get(ix)
+        .map_or(false, |probe| probe.start < range.end)
+}
+
 #[derive(Clone, Debug)]
 pub struct SearchInputs {
     query: Arc<str>,
     files_to_include: PathMatcher,
     files_to_exclude: PathMatcher,
     buffers: Option<Vec<Model<Buffer>>>,
+    scope: SearchScope,
 }
 
 impl SearchInputs {
@@ -53,6 +169,9 @@ impl SearchInputs {
     pub fn buffers(&self) -> &Option<Vec<Model<Buffer>>> {
         &self.buffers
     }
+    pub fn scope(&self) -> &SearchScope {
+        &self.scope
+    }
 }
 #[derive(Clone, Debug)]
 pub enum SearchQuery {
@@ -74,6 +193,13 @@ pub enum SearchQuery {
         include_ignored: bool,
         inner: SearchInputs,
     },
+
+    Structural {
+        pattern: Arc<StructuralPattern>,
+        replacement: Option<String>,
+        include_ignored: bool,
+        inner: SearchInputs,
+    },
 }
 
 impl SearchQuery {
@@ -95,6 +221,7 @@ impl SearchQuery {
             files_to_exclude,
             files_to_include,
             buffers,
+            scope: SearchScope::default(),
         };
         Ok(Self::Text {
             search: Arc::new(search),
@@ -135,6 +262,7 @@ impl SearchQuery {
             files_to_exclude,
             files_to_include,
             buffers,
+            scope: SearchScope::default(),
         };
         Ok(Self::Regex {
             regex,
@@ -147,6 +275,30 @@ impl SearchQuery {
         })
     }
 
+    pub fn structural(
+        query: impl ToString,
+        include_ignored: bool,
+        files_to_include: PathMatcher,
+        files_to_exclude: PathMatcher,
+        buffers: Option<Vec<Model<Buffer>>>,
+    ) -> Result<Self> {
+        let query = query.to_string();
+        let pattern = StructuralPattern::new(&query)?;
+        let inner = SearchInputs {
+            query: query.into(),
+            files_to_exclude,
+            files_to_include,
+            buffers,
+            scope: SearchScope::default(),
+        };
+        Ok(Self::Structural {
+            pattern: Arc::new(pattern),
+            replacement: None,
+            include_ignored,
+            inner,
+        })
+    }
+
     pub fn from_proto_v1(message: proto::SearchProject) -> Result<Self> {
         if message.regex {
             Self::regex(
@@ -172,7 +324,16 @@ impl SearchQuery {
     }
 
     pub fn from_proto(message: proto::SearchQuery) -> Result<Self> {
-        if message.regex {
+        let scope = SearchScope::from_proto(message.scope.clone());
+        let query = if message.structural {
+            Self::structural(
+                message.query,
+                message.include_ignored,
+                deserialize_path_matches(&message.files_to_include)?,
+                deserialize_path_matches(&message.files_to_exclude)?,
+                None, // search opened only don't need search remote
+            )
+        } else if message.regex {
             Self::regex(
                 message.query,
                 message.whole_word,
@@ -192,8 +353,22 @@ impl SearchQuery {
                 deserialize_path_matches(&message.files_to_exclude)?,
                 None, // search opened only don't need search remote
             )
+        }?;
+        Ok(query.with_scope(scope))
+    }
+
+    /// Restricts the search to the given part of the code.
+    pub fn with_scope(mut self, scope: SearchScope) -> Self {
+        match self {
+            Self::Text { ref mut inner, .. }
+            | Self::Regex { ref mut inner, .. }
+            | Self::Structural { ref mut inner, .. } => {
+                inner.scope = scope;
+                self
+            }
         }
     }
+
     pub fn with_replacement(mut self, new_replacement: String) -> Self {
         match self {
             Self::Text {
@@ -203,6 +378,10 @@ impl SearchQuery {
             | Self::Regex {
                 ref mut replacement,
                 ..
+            }
+            | Self::Structural {
+                ref mut replacement,
+                ..
             } => {
                 *replacement = Some(new_replacement);
                 self
@@ -231,6 +410,8 @@ impl SearchQuery {
             include_ignored: self.include_ignored(),
             files_to_include: self.files_to_include().sources().join(","),
             files_to_exclude: self.files_to_exclude().sources().join(","),
+            structural: self.is_structural(),
+            scope: Some(self.scope().to_proto()),
         }
     }
 
@@ -269,17 +450,23 @@ impl SearchQuery {
                     Ok(false)
                 }
             }
+            Self::Structural { pattern, .. } => {
+                let mut text = String::new();
+                BufReader::new(stream).read_to_string(&mut text)?;
+                Ok(pattern.may_match(&text))
+            }
         }
     }
     /// Returns the replacement text for this `SearchQuery`.
     pub fn replacement(&self) -> Option<&str> {
         match self {
-            SearchQuery::Text { replacement, .. } | SearchQuery::Regex { replacement, .. } => {
-                replacement.as_deref()
-            }
+            SearchQuery::Text { replacement, .. }
+            | SearchQuery::Regex { replacement, .. }
+            | SearchQuery::Structural { replacement, .. } => replacement.as_deref(),
         }
     }
     /// Replaces search hits if replacement is set. `text` is assumed to be a string that matches this `SearchQuery` exactly, without any leftovers on either side.
+    /// Structural replacements depend on the surrounding syntax tree, use [`Self::replacement_for_range`] for those.
     pub fn replacement_for<'a>(&self, text: &'a str) -> Option<Cow<'a, str>> {
         match self {
             SearchQuery::Text { replacement, .. } => replacement.clone().map(Cow::from),
@@ -302,6 +489,26 @@ impl SearchQuery {
                     None
                 }
             }
+            SearchQuery::Structural { .. } => None,
+        }
+    }
+
+    /// Replaces the search hit at the given range of the buffer if replacement is set.
+    pub fn replacement_for_range(
+        &self,
+        buffer: &BufferSnapshot,
+        range: Range<usize>,
+    ) -> Option<String> {
+        match self {
+            SearchQuery::Structural {
+                pattern,
+                replacement,
+                ..
+            } => pattern.replacement_for_range(buffer, range, replacement.as_deref()?),
+            _ => {
+                let text = buffer.text_for_range(range).collect::<String>();
+                self.replacement_for(&text).map(Cow::into_owned)
+            }
         }
     }
 
@@ -393,6 +600,26 @@ impl SearchQuery {
                     }
                 }
             }
+            Self::Structural { pattern, .. } => {
+                let range = range_offset..range_offset + rope.len();
+                matches.extend(
+                    pattern
+                        .matches(buffer, range)
+                        .into_iter()
+                        .map(|mat| mat.range.start - range_offset..mat.range.end - range_offset),
+                );
+            }
+        }
+
+        let scope = self.scope();
+        if *scope != SearchScope::Everywhere {
+            for mat in &mut matches {
+                *mat = mat.start + range_offset..mat.end + range_offset;
+            }
+            scope.filter_matches(buffer, &mut matches);
+            for mat in &mut matches {
+                *mat = mat.start - range_offset..mat.end - range_offset;
+            }
         }
 
         matches
@@ -410,6 +637,7 @@ impl SearchQuery {
         match self {
             Self::Text { whole_word, .. } => *whole_word,
             Self::Regex { whole_word, .. } => *whole_word,
+            Self::Structural { .. } => false,
         }
     }
 
@@ -417,6 +645,7 @@ impl SearchQuery {
         match self {
             Self::Text { case_sensitive, .. } => *case_sensitive,
             Self::Regex { case_sensitive, .. } => *case_sensitive,
+            Self::Structural { .. } => false,
         }
     }
 
@@ -428,6 +657,9 @@ impl SearchQuery {
             Self::Regex {
                 include_ignored, ..
             } => *include_ignored,
+            Self::Structural {
+                include_ignored, ..
+            } => *include_ignored,
         }
     }
 
@@ -435,10 +667,23 @@ impl SearchQuery {
         matches!(self, Self::Regex { .. })
     }
 
+    pub fn is_structural(&self) -> bool {
+        matches!(self, Self::Structural { .. })
+    }
+
+    /// Whether matching depends on the syntax trees of the searched buffers.
+    pub fn uses_syntax(&self) -> bool {
+        self.is_structural() || *self.scope() != SearchScope::Everywhere
+    }
+
     pub fn files_to_include(&self) -> &PathMatcher {
         self.as_inner().files_to_include()
     }
 
+    pub fn scope(&self) -> &SearchScope {
+        self.as_inner().scope()
+    }
+
     pub fn files_to_exclude(&self) -> &PathMatcher {
         self.as_inner().files_to_exclude()
     }
@@ -472,7 +717,9 @@ impl SearchQuery {
     }
     pub fn as_inner(&self) -> &SearchInputs {
         match self {
-            Self::Regex { inner, .. } | Self::Text { inner, .. } => inner,
+            Self::Regex { inner, .. }
+            | Self::Text { inner, .. }
+            | Self::Structural { inner, .. } => inner,
         }
     }
 }
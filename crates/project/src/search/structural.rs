@@ -0,0 +1,575 @@
+use anyhow::{anyhow, Result};
+use collections::HashMap;
+use language::{with_parser, BufferSnapshot, Grammar, GrammarId, Node, TextProvider, Tree};
+use parking_lot::Mutex;
+use regex::{Captures, Regex};
+use std::{
+    fmt,
+    ops::Range,
+    sync::{Arc, OnceLock},
+};
+use tree_sitter::{Query, QueryCursor};
+
+const METAVARIABLE_PREFIX: &str = "__ZED_META_";
+const MULTI_METAVARIABLE_PREFIX: &str = "__ZED_METAS_";
+
+static METAVARIABLE_REGEX: OnceLock<Regex> = OnceLock::new();
+static REPLACEMENT_VARIABLE_REGEX: OnceLock<Regex> = OnceLock::new();
+static QUERY_CAPTURE_REGEX: OnceLock<Regex> = OnceLock::new();
+static WORD_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// A pattern that matches code by its syntax tree rather than by its text.
+///
+/// Patterns are either code templates, where metavariables like `$A` match any
+/// single syntax node and `$$$ARGS` matches any sequence of nodes, e.g.
+/// `foo($A, $$$REST)`, or tree-sitter queries, e.g.
+/// `(call_expression function: (identifier) @name) @match`. A query's match
+/// covers its `@match` capture if it has one, and all of its captures otherwise.
+///
+/// Patterns are parsed with the grammar of each buffer they are matched against,
+/// so the same pattern can match code in every language it is valid in.
+#[derive(Debug)]
+pub struct StructuralPattern {
+    kind: PatternKind,
+    /// Words that occur in every piece of code that the pattern matches.
+    required_words: Vec<String>,
+    compiled: CompiledPatterns,
+}
+
+#[derive(Debug)]
+enum PatternKind {
+    Template { code: String },
+    Query { source: String },
+}
+
+/// The pattern compiled with each grammar it has been matched against, or `None` if it
+/// isn't valid in that grammar, so that it's only compiled once per language.
+#[derive(Default)]
+struct CompiledPatterns(Mutex<HashMap<GrammarId, Option<CompiledPattern>>>);
+
+impl fmt::Debug for CompiledPatterns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledPatterns").finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone)]
+enum CompiledPattern {
+    Template(Tree),
+    Query(Arc<Query>),
+}
+
+/// A piece of code matched by a [`StructuralPattern`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuralMatch {
+    pub range: Range<usize>,
+    /// The ranges matched by each metavariable or query capture.
+    pub bindings: HashMap<String, Range<usize>>,
+}
+
+impl StructuralPattern {
+    pub fn new(source: &str) -> Result<Self> {
+        let source = source.trim();
+        if source.is_empty() {
+            return Err(anyhow!("empty structural search pattern"));
+        }
+
+        let is_query = (source.starts_with('(') || source.starts_with('['))
+            && QUERY_CAPTURE_REGEX
+                .get_or_init(|| Regex::new(r"@[A-Za-z_]").unwrap())
+                .is_match(source);
+        if is_query {
+            return Ok(Self {
+                kind: PatternKind::Query {
+                    source: source.to_string(),
+                },
+                required_words: Vec::new(),
+                compiled: CompiledPatterns::default(),
+            });
+        }
+
+        let metavariables =
+            METAVARIABLE_REGEX.get_or_init(|| Regex::new(r"\$(\$\$)?([A-Z_][A-Z0-9_]*)").unwrap());
+        let literal_code = metavariables.replace_all(source, " ");
+        let required_words = WORD_REGEX
+            .get_or_init(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap())
+            .find_iter(&literal_code)
+            .map(|word| word.as_str().to_string())
+            .collect::<Vec<_>>();
+        if required_words.is_empty() && literal_code.trim().is_empty() {
+            return Err(anyhow!(
+                "structural search pattern must contain code besides metavariables"
+            ));
+        }
+
+        let code = metavariables
+            .replace_all(source, |captures: &Captures| {
+                let prefix = if captures.get(1).is_some() {
+                    MULTI_METAVARIABLE_PREFIX
+                } else {
+                    METAVARIABLE_PREFIX
+                };
+                format!("{prefix}{}", &captures[2])
+            })
+            .into_owned();
+        Ok(Self {
+            kind: PatternKind::Template { code },
+            required_words,
+            compiled: CompiledPatterns::default(),
+        })
+    }
+
+    /// Whether the pattern can match somewhere in the given text. This is a fast,
+    /// conservative check that doesn't parse the text.
+    pub fn may_match(&self, text: &str) -> bool {
+        self.required_words
+            .iter()
+            .all(|word| text.contains(word.as_str()))
+    }
+
+    /// Finds the non-overlapping matches of the pattern within the given range of the buffer.
+    pub fn matches(&self, buffer: &BufferSnapshot, range: Range<usize>) -> Vec<StructuralMatch> {
+        let mut matches = Vec::new();
+        for layer in buffer.syntax_layers() {
+            let Some(grammar) = layer.language.grammar() else {
+                continue;
+            };
+            match (&self.kind, self.compile(grammar)) {
+                (PatternKind::Template { code }, Some(CompiledPattern::Template(tree))) => {
+                    Template { code, tree }.find(layer.node(), buffer, range.clone(), &mut matches);
+                }
+                (PatternKind::Query { .. }, Some(CompiledPattern::Query(query))) => {
+                    find_query_matches(&query, layer.node(), buffer, range.clone(), &mut matches);
+                }
+                _ => {}
+            }
+        }
+
+        matches.sort_by(|a, b| {
+            a.range
+                .start
+                .cmp(&b.range.start)
+                .then(b.range.end.cmp(&a.range.end))
+        });
+        let mut end = 0;
+        matches.retain(|mat| {
+            if mat.range.start < end && !mat.range.is_empty() {
+                return false;
+            }
+            end = mat.range.end;
+            true
+        });
+        matches
+    }
+
+    fn compile(&self, grammar: &Grammar) -> Option<CompiledPattern> {
+        if let Some(compiled) = self.compiled.0.lock().get(&grammar.id()) {
+            return compiled.clone();
+        }
+
+        let compiled = match &self.kind {
+            PatternKind::Template { code } => Template::parse(code, grammar)
+                .map(|template| CompiledPattern::Template(template.tree)),
+            PatternKind::Query { source } => Query::new(&grammar.ts_language, source)
+                .ok()
+                .map(|query| CompiledPattern::Query(Arc::new(query))),
+        };
+        self.compiled
+            .0
+            .lock()
+            .insert(grammar.id(), compiled.clone());
+        compiled
+    }
+
+    /// Returns the replacement for the match covering exactly `range`, with each
+    /// `$NAME` in the replacement substituted by the code bound to that name.
+    pub fn replacement_for_range(
+        &self,
+        buffer: &BufferSnapshot,
+        range: Range<usize>,
+        replacement: &str,
+    ) -> Option<String> {
+        let mat = self
+            .matches(buffer, range.clone())
+            .into_iter()
+            .find(|mat| mat.range == range)?;
+        Some(
+            REPLACEMENT_VARIABLE_REGEX
+                .get_or_init(|| Regex::new(r"\$(\$\$)?([A-Za-z_][A-Za-z0-9_.]*)").unwrap())
+                .replace_all(replacement, |captures: &Captures| {
+                    match mat.bindings.get(&captures[2]) {
+                        Some(binding) => buffer.text_for_range(binding.clone()).collect(),
+                        None => captures[0].to_string(),
+                    }
+                })
+                .into_owned(),
+        )
+    }
+}
+
+fn find_query_matches(
+    query: &Query,
+    root: Node,
+    buffer: &BufferSnapshot,
+    range: Range<usize>,
+    matches: &mut Vec<StructuralMatch>,
+) {
+    let match_capture_ix = query.capture_index_for_name("match");
+    let capture_names = query.capture_names();
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(range.clone());
+    for query_match in cursor.matches(query, root, TextProvider(buffer.as_rope())) {
+        let captures = query_match.captures;
+        let match_range = if let Some(match_capture_ix) = match_capture_ix {
+            let Some(capture) = captures
+                .iter()
+                .find(|capture| capture.index == match_capture_ix)
+            else {
+                continue;
+            };
+            capture.node.byte_range()
+        } else {
+            let Some(start) = captures.iter().map(|c| c.node.start_byte()).min() else {
+                continue;
+            };
+            let end = captures.iter().map(|c| c.node.end_byte()).max().unwrap();
+            start..end
+        };
+        if match_range.start < range.start || match_range.end > range.end {
+            continue;
+        }
+
+        let bindings = captures
+            .iter()
+            .map(|capture| {
+                (
+                    capture_names[capture.index as usize].to_string(),
+                    capture.node.byte_range(),
+                )
+            })
+            .collect();
+        matches.push(StructuralMatch {
+            range: match_range,
+            bindings,
+        });
+    }
+}
+
+/// A code template parsed with a particular grammar.
+struct Template<'a> {
+    code: &'a str,
+    tree: Tree,
+}
+
+impl<'a> Template<'a> {
+    fn parse(code: &'a str, grammar: &Grammar) -> Option<Self> {
+        let tree = with_parser(|parser| {
+            parser.set_language(&grammar.ts_language).ok()?;
+            parser.parse(code, None)
+        })?;
+        Some(Self { code, tree })
+    }
+
+    /// The node to match against, i.e. the outermost node spanning the entire template
+    /// below wrappers like the grammar's root node.
+    fn root(&self) -> Node {
+        let start = self.code.len() - self.code.trim_start().len();
+        let end = self.code.trim_end().len();
+        let mut node = self.tree.root_node();
+        loop {
+            let children = significant_children(node);
+            match children.as_slice() {
+                [child]
+                    if child.is_named()
+                        && child.start_byte() == start
+                        && child.end_byte() == end =>
+                {
+                    node = *child
+                }
+                _ if node.start_byte() == start && node.end_byte() == end => return node,
+                [child] if child.is_named() => node = *child,
+                _ => return node,
+            }
+        }
+    }
+
+    fn find(
+        &self,
+        root: Node,
+        buffer: &BufferSnapshot,
+        range: Range<usize>,
+        matches: &mut Vec<StructuralMatch>,
+    ) {
+        let pattern = self.root();
+        if self.metavariable(pattern).is_some() {
+            return;
+        }
+
+        let mut cursor = root.walk();
+        loop {
+            let node = cursor.node();
+            let mut descend = node.end_byte() > range.start && node.start_byte() < range.end;
+            if descend
+                && node.kind_id() == pattern.kind_id()
+                && node.start_byte() >= range.start
+                && node.end_byte() <= range.end
+            {
+                let mut bindings = HashMap::default();
+                if self.matches_node(pattern, node, buffer, &mut bindings) {
+                    matches.push(StructuralMatch {
+                        range: node.byte_range(),
+                        bindings,
+                    });
+                    descend = false;
+                }
+            }
+
+            if descend && cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns the name of the metavariable the node stands for, and whether it
+    /// matches a sequence of nodes.
+    fn metavariable(&self, node: Node) -> Option<(&'a str, bool)> {
+        let text = &self.code[node.byte_range()];
+        let (name, multiple) = if let Some(name) = text.strip_prefix(MULTI_METAVARIABLE_PREFIX) {
+            (name, true)
+        } else {
+            (text.strip_prefix(METAVARIABLE_PREFIX)?, false)
+        };
+        name.chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            .then_some((name, multiple))
+    }
+
+    fn matches_node(
+        &self,
+        pattern: Node,
+        node: Node,
+        buffer: &BufferSnapshot,
+        bindings: &mut HashMap<String, Range<usize>>,
+    ) -> bool {
+        if let Some((name, false)) = self.metavariable(pattern) {
+            return bind(bindings, name, node.byte_range(), buffer);
+        }
+        if pattern.kind_id() != node.kind_id() {
+            return false;
+        }
+
+        let pattern_children = significant_children(pattern);
+        let children = significant_children(node);
+        if pattern_children.is_empty() {
+            let pattern_text = &self.code[pattern.byte_range()];
+            return children.is_empty()
+                && node.byte_range().len() == pattern_text.len()
+                && bytes_in_range(buffer, node.byte_range()).eq(pattern_text.bytes());
+        }
+        self.matches_sequence(&pattern_children, &children, buffer, bindings)
+    }
+
+    fn matches_sequence(
+        &self,
+        patterns: &[Node],
+        nodes: &[Node],
+        buffer: &BufferSnapshot,
+        bindings: &mut HashMap<String, Range<usize>>,
+    ) -> bool {
+        let Some((pattern, remaining_patterns)) = patterns.split_first() else {
+            return nodes.is_empty();
+        };
+
+        if let Some((name, true)) = self.metavariable(*pattern) {
+            for count in 0..=nodes.len() {
+                let range = match nodes[..count] {
+                    [] => 0..0,
+                    [first, .., last] => first.start_byte()..last.end_byte(),
+                    [only] => only.byte_range(),
+                };
+                let mut attempt = bindings.clone();
+                if bind(&mut attempt, name, range, buffer)
+                    && self.matches_sequence(
+                        remaining_patterns,
+                        &nodes[count..],
+                        buffer,
+                        &mut attempt,
+                    )
+                {
+                    *bindings = attempt;
+                    return true;
+                }
+            }
+            return false;
+        }
+
+        let Some((node, remaining_nodes)) = nodes.split_first() else {
+            return false;
+        };
+        let mut attempt = bindings.clone();
+        if self.matches_node(*pattern, *node, buffer, &mut attempt)
+            && self.matches_sequence(remaining_patterns, remaining_nodes, buffer, &mut attempt)
+        {
+            *bindings = attempt;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Binds the metavariable to the range, or checks that the range has the same
+/// text as the one the metavariable is already bound to.
+fn bind(
+    bindings: &mut HashMap<String, Range<usize>>,
+    name: &str,
+    range: Range<usize>,
+    buffer: &BufferSnapshot,
+) -> bool {
+    if let Some(existing) = bindings.get(name) {
+        existing.len() == range.len()
+            && bytes_in_range(buffer, existing.clone()).eq(bytes_in_range(buffer, range))
+    } else {
+        bindings.insert(name.to_string(), range);
+        true
+    }
+}
+
+fn bytes_in_range(buffer: &BufferSnapshot, range: Range<usize>) -> impl Iterator<Item = u8> + '_ {
+    buffer.text_for_range(range).flat_map(str::bytes)
+}
+
+/// The children that take part in matching, leaving out comments and the nodes
+/// tree-sitter inserted to recover from errors.
+fn significant_children(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .filter(|child| !child.is_extra() && !child.is_missing())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{Context as _, TestAppContext};
+    use language::{tree_sitter_rust, Buffer, Language, LanguageConfig};
+    use std::sync::Arc;
+
+    fn rust_buffer(text: &str, cx: &mut TestAppContext) -> BufferSnapshot {
+        let language = Arc::new(Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::language()),
+        ));
+        let buffer = cx.new_model(|cx| Buffer::local(text, cx).with_language(language, cx));
+        cx.run_until_parked();
+        buffer.read_with(cx, |buffer, _| buffer.snapshot())
+    }
+
+    fn matched_text(pattern: &StructuralPattern, buffer: &BufferSnapshot) -> Vec<String> {
+        pattern
+            .matches(buffer, 0..buffer.len())
+            .into_iter()
+            .map(|mat| buffer.text_for_range(mat.range).collect())
+            .collect()
+    }
+
+    #[gpui::test]
+    fn test_template_matches(cx: &mut TestAppContext) {
+        let buffer = rust_buffer(
+            "fn main() { foo(1, x + 2); foo(a); foo(b, c, d); bar(1, 2); foo(foo(3, 4), 5); }",
+            cx,
+        );
+
+        let pattern = StructuralPattern::new("foo($A, $B)").unwrap();
+        assert_eq!(
+            matched_text(&pattern, &buffer),
+            ["foo(1, x + 2)", "foo(foo(3, 4), 5)"]
+        );
+
+        let pattern = StructuralPattern::new("foo($$$ARGS)").unwrap();
+        assert_eq!(
+            matched_text(&pattern, &buffer),
+            [
+                "foo(1, x + 2)",
+                "foo(a)",
+                "foo(b, c, d)",
+                "foo(foo(3, 4), 5)"
+            ]
+        );
+
+        // A metavariable used twice must match the same code both times.
+        let buffer = rust_buffer("fn main() { x == x; x == y; f(a) == f(a); }", cx);
+        let pattern = StructuralPattern::new("$A == $A").unwrap();
+        assert_eq!(matched_text(&pattern, &buffer), ["x == x", "f(a) == f(a)"]);
+
+        assert!(StructuralPattern::new("$A").is_err());
+    }
+
+    #[gpui::test]
+    fn test_template_ignores_formatting_and_comments(cx: &mut TestAppContext) {
+        let buffer = rust_buffer(
+            "fn main() {\n    foo(\n        1, // one\n        2\n    );\n    foo(\"1, 2\");\n}",
+            cx,
+        );
+        let pattern = StructuralPattern::new("foo(1, 2)").unwrap();
+        assert_eq!(
+            matched_text(&pattern, &buffer),
+            ["foo(\n        1, // one\n        2\n    )"]
+        );
+    }
+
+    #[gpui::test]
+    fn test_query_matches(cx: &mut TestAppContext) {
+        let buffer = rust_buffer("fn one() {} fn two() { three(); }", cx);
+
+        let pattern = StructuralPattern::new("(function_item name: (identifier) @name)").unwrap();
+        assert_eq!(matched_text(&pattern, &buffer), ["one", "two"]);
+        assert_eq!(matched_text(&pattern, &buffer), ["one", "two"]);
+        assert_eq!(pattern.compiled.0.lock().len(), 1);
+
+        let pattern =
+            StructuralPattern::new("(call_expression function: (identifier) @name) @match")
+                .unwrap();
+        assert_eq!(matched_text(&pattern, &buffer), ["three()"]);
+    }
+
+    #[gpui::test]
+    fn test_replacement(cx: &mut TestAppContext) {
+        let buffer = rust_buffer("fn main() { foo(1, x + 2); foo(); }", cx);
+
+        let pattern = StructuralPattern::new("foo($A, $B)").unwrap();
+        let range = pattern.matches(&buffer, 0..buffer.len())[0].range.clone();
+        assert_eq!(
+            pattern.replacement_for_range(&buffer, range, "bar($B, $A)"),
+            Some("bar(x + 2, 1)".to_string())
+        );
+
+        let pattern = StructuralPattern::new("foo($$$ARGS)").unwrap();
+        let ranges = pattern
+            .matches(&buffer, 0..buffer.len())
+            .into_iter()
+            .map(|mat| mat.range)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pattern.replacement_for_range(&buffer, ranges[1].clone(), "bar($$$ARGS, $OTHER)"),
+            Some("bar(, $OTHER)".to_string())
+        );
+
+        let pattern =
+            StructuralPattern::new("(call_expression function: (identifier) @name) @match")
+                .unwrap();
+        let range = pattern.matches(&buffer, 0..buffer.len())[1].range.clone();
+        assert_eq!(
+            pattern.replacement_for_range(&buffer, range, "$name!()"),
+            Some("foo!()".to_string())
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! A content index of the files in local worktrees, used to skip reading files that
+//! can't match a project search.
+//!
+//! For every indexed file we store the set of (ASCII-lowercased) byte trigrams it
+//! contains. A text or regex query yields the trigrams that any match must contain,
+//! so files missing one of them don't need to be read at all. The index is kept up to
+//! date from worktree scan events, and files whose contents may have changed since
+//! they were indexed are always read.
+
+use std::{path::Path, sync::Arc, time::SystemTime};
+
+use collections::HashMap;
+use fs::Fs;
+use futures::StreamExt;
+use gpui::{AppContext, Model, Task};
+use parking_lot::RwLock;
+use smol::channel::Sender;
+use worktree::{PathChange, Snapshot, UpdatedEntriesSet, Worktree, WorktreeId};
+
+use crate::search::SearchQuery;
+
+pub type Trigram = u32;
+
+/// The trigram indices of all local worktrees in a project.
+#[derive(Default)]
+pub struct SearchIndex {
+    worktrees: HashMap<WorktreeId, WorktreeSearchIndex>,
+}
+
+struct WorktreeSearchIndex {
+    index: Arc<RwLock<TrigramIndex>>,
+    /// Files larger than this aren't indexed, and are always read when searching.
+    max_file_size: u64,
+    updates_tx: Sender<IndexUpdate>,
+    _maintain_index: Task<()>,
+}
+
+enum IndexUpdate {
+    Upsert {
+        path: Arc<Path>,
+        mtime: Option<SystemTime>,
+    },
+    Remove(Arc<Path>),
+}
+
+impl SearchIndex {
+    /// Starts indexing the files of the given worktree that aren't ignored and are no
+    /// larger than `max_file_size` bytes, replacing any existing index of the worktree.
+    pub fn add_worktree(
+        &mut self,
+        worktree: &Model<Worktree>,
+        max_file_size: u64,
+        fs: Arc<dyn Fs>,
+        cx: &AppContext,
+    ) {
+        let worktree = worktree.read(cx);
+        if !worktree.is_local() {
+            return;
+        }
+
+        let index = Arc::new(RwLock::new(TrigramIndex::default()));
+        let (updates_tx, updates_rx) = smol::channel::unbounded();
+        let root_path = worktree.abs_path();
+        let maintain_index = cx.background_executor().spawn({
+            let index = index.clone();
+            async move {
+                let mut updates = updates_rx.ready_chunks(256);
+                while let Some(updates) = updates.next().await {
+                    for update in updates {
+                        Self::apply_update(&index, &root_path, fs.as_ref(), update).await;
+                    }
+                }
+            }
+        });
+
+        let worktree_index = WorktreeSearchIndex {
+            index,
+            max_file_size,
+            updates_tx,
+            _maintain_index: maintain_index,
+        };
+        // Entries that were scanned before the worktree was added won't be reported again.
+        let snapshot = worktree.snapshot();
+        for entry in snapshot.files(false, 0) {
+            worktree_index.upsert(&snapshot, &entry.path);
+        }
+        self.worktrees.insert(worktree.id(), worktree_index);
+    }
+
+    pub fn remove_worktree(&mut self, worktree_id: WorktreeId) {
+        self.worktrees.remove(&worktree_id);
+    }
+
+    pub fn entries_changed(
+        &mut self,
+        worktree: &Model<Worktree>,
+        changes: &UpdatedEntriesSet,
+        cx: &AppContext,
+    ) {
+        let worktree = worktree.read(cx);
+        let Some(worktree_index) = self.worktrees.get(&worktree.id()) else {
+            return;
+        };
+        let snapshot = worktree.snapshot();
+        for (path, _, change) in changes.iter() {
+            if *change == PathChange::Removed {
+                worktree_index.send(IndexUpdate::Remove(path.clone()));
+            } else {
+                worktree_index.upsert(&snapshot, path);
+            }
+        }
+    }
+
+    /// Returns the maximum size of the files indexed in the given worktree, if it is being
+    /// indexed.
+    pub fn max_file_size(&self, worktree_id: WorktreeId) -> Option<u64> {
+        Some(self.worktrees.get(&worktree_id)?.max_file_size)
+    }
+
+    /// Returns the index of the given worktree, if it is being indexed.
+    pub fn worktree_index(&self, worktree_id: WorktreeId) -> Option<Arc<RwLock<TrigramIndex>>> {
+        Some(self.worktrees.get(&worktree_id)?.index.clone())
+    }
+
+    async fn apply_update(
+        index: &RwLock<TrigramIndex>,
+        root_path: &Path,
+        fs: &dyn Fs,
+        update: IndexUpdate,
+    ) {
+        match update {
+            IndexUpdate::Remove(path) => index.write().remove(&path),
+            IndexUpdate::Upsert { path, mtime } => {
+                if index.read().is_indexed(&path, mtime) {
+                    return;
+                }
+                match fs.load(&root_path.join(&path)).await {
+                    Ok(text) => {
+                        let trigrams = text_trigrams(&text);
+                        index.write().insert(path, mtime, trigrams);
+                    }
+                    // Binary or unreadable files are left unindexed, so that searches read them.
+                    Err(_) => index.write().remove(&path),
+                }
+            }
+        }
+    }
+}
+
+impl WorktreeSearchIndex {
+    fn upsert(&self, snapshot: &Snapshot, path: &Arc<Path>) {
+        let Some(entry) = snapshot.entry_for_path(path) else {
+            return;
+        };
+        if !entry.is_file() || entry.is_fifo {
+            return;
+        }
+        if entry.is_ignored || entry.is_external || entry.size > self.max_file_size {
+            self.send(IndexUpdate::Remove(path.clone()));
+        } else {
+            self.send(IndexUpdate::Upsert {
+                path: path.clone(),
+                mtime: entry.mtime,
+            });
+        }
+    }
+
+    fn send(&self, update: IndexUpdate) {
+        self.updates_tx.try_send(update).ok();
+    }
+}
+
+/// The trigrams contained in the files of a single worktree.
+#[derive(Default)]
+pub struct TrigramIndex {
+    files: HashMap<Arc<Path>, IndexedFile>,
+}
+
+struct IndexedFile {
+    mtime: Option<SystemTime>,
+    /// Sorted and deduplicated.
+    trigrams: Vec<Trigram>,
+}
+
+impl TrigramIndex {
+    fn insert(&mut self, path: Arc<Path>, mtime: Option<SystemTime>, trigrams: Vec<Trigram>) {
+        self.files.insert(path, IndexedFile { mtime, trigrams });
+    }
+
+    fn remove(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    fn is_indexed(&self, path: &Path, mtime: Option<SystemTime>) -> bool {
+        mtime.is_some()
+            && self
+                .files
+                .get(path)
+                .map_or(false, |file| file.mtime == mtime)
+    }
+
+    /// Whether the file at the given path may contain all of the given trigrams, or `None`
+    /// if its contents as of the given modification time haven't been indexed.
+    pub fn may_contain(
+        &self,
+        path: &Path,
+        mtime: Option<SystemTime>,
+        trigrams: &[Trigram],
+    ) -> Option<bool> {
+        if !self.is_indexed(path, mtime) {
+            return None;
+        }
+        let file = self.files.get(path)?;
+        Some(
+            trigrams
+                .iter()
+                .all(|trigram| file.trigrams.binary_search(trigram).is_ok()),
+        )
+    }
+}
+
+fn trigram(bytes: &[u8]) -> Trigram {
+    u32::from_be_bytes([
+        0,
+        bytes[0].to_ascii_lowercase(),
+        bytes[1].to_ascii_lowercase(),
+        bytes[2].to_ascii_lowercase(),
+    ])
+}
+
+fn text_trigrams(text: &str) -> Vec<Trigram> {
+    let mut trigrams = text.as_bytes().windows(3).map(trigram).collect::<Vec<_>>();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    trigrams
+}
+
+/// Returns trigrams that every file matching the query contains, or `None` if the query
+/// doesn't restrict which trigrams a matching file contains.
+pub fn query_trigrams(query: &SearchQuery) -> Option<Vec<Trigram>> {
+    let literals = match query {
+        // Text queries ignore the case of ASCII characters only, just like the index.
+        SearchQuery::Text { .. } => vec![query.as_str().to_string()],
+        SearchQuery::Regex { case_sensitive, .. } => {
+            regex_literals(query.as_str(), *case_sensitive)?
+        }
+        SearchQuery::Structural { .. } => return None,
+    };
+
+    let mut trigrams = literals
+        .iter()
+        .flat_map(|literal| literal.as_bytes().windows(3).map(trigram))
+        .collect::<Vec<_>>();
+    if trigrams.is_empty() {
+        return None;
+    }
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    Some(trigrams)
+}
+
+/// Extracts the literal strings that occur in every match of the regex.
+///
+/// This is deliberately conservative: patterns with alternations or inline flags yield
+/// `None`, and anything inside a group or followed by a quantifier that allows zero
+/// repetitions is ignored.
+fn regex_literals(pattern: &str, case_sensitive: bool) -> Option<Vec<String>> {
+    if pattern.contains('|') || pattern.contains("(?") {
+        return None;
+    }
+
+    let mut literals = Vec::new();
+    let mut literal = String::new();
+    let mut depth = 0_usize;
+    let mut chars = pattern.chars().peekable();
+    let end_literal = |literal: &mut String, literals: &mut Vec<String>| {
+        if literal.len() >= 3 {
+            literals.push(literal.clone());
+        }
+        literal.clear();
+    };
+    while let Some(c) = chars.next() {
+        let literal_char = match c {
+            '\\' => match chars.next() {
+                Some(escaped) if escaped.is_ascii_punctuation() => Some(escaped),
+                Some(escaped) => {
+                    // Skip the arguments of escapes like `\x41`, `\u{263A}` and `\pL`, so
+                    // that they aren't mistaken for literal text.
+                    let argument_len = match escaped {
+                        'x' => 2,
+                        'u' => 4,
+                        'U' => 8,
+                        'p' | 'P' => 1,
+                        _ => 0,
+                    };
+                    if argument_len > 0 {
+                        if chars.peek() == Some(&'{') {
+                            for c in chars.by_ref() {
+                                if c == '}' {
+                                    break;
+                                }
+                            }
+                        } else {
+                            for _ in 0..argument_len {
+                                chars.next();
+                            }
+                        }
+                    }
+                    None
+                }
+                None => None,
+            },
+            '[' => {
+                // Skip the character class, which may start with a literal `]`.
+                if chars.peek() == Some(&'^') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                }
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        ']' => break,
+                        _ => {}
+                    }
+                }
+                None
+            }
+            '(' => {
+                depth += 1;
+                None
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                None
+            }
+            '?' | '*' => {
+                literal.pop();
+                None
+            }
+            '{' => {
+                literal.pop();
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+                None
+            }
+            '+' | '.' | '^' | '$' => None,
+            c => Some(c),
+        };
+
+        match literal_char {
+            // With Unicode case folding, `k` and `s` also match non-ASCII characters.
+            Some(c)
+                if depth == 0
+                    && (case_sensitive
+                        || (c.is_ascii() && !matches!(c.to_ascii_lowercase(), 'k' | 's'))) =>
+            {
+                literal.push(c)
+            }
+            _ => end_literal(&mut literal, &mut literals),
+        }
+    }
+    end_literal(&mut literal, &mut literals);
+    Some(literals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_literals() {
+        assert_eq!(
+            regex_literals(r"fn\s+foo_bar\(", true),
+            Some(vec!["foo_bar(".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"abcd?efg*hij", true),
+            Some(vec!["abc".to_string(), "hij".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"one(two)?three[xyz]four{2}", true),
+            Some(vec![
+                "one".to_string(),
+                "three".to_string(),
+                "fou".to_string()
+            ])
+        );
+        assert_eq!(
+            regex_literals(r"^Kelvin\.Mask$", false),
+            Some(vec!["elvin.Ma".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"\x41ABCD", true),
+            Some(vec!["ABCD".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"\x{1F600}smile", true),
+            Some(vec!["smile".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"\u263Asmile", true),
+            Some(vec!["smile".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"\U0001F600smile", true),
+            Some(vec!["smile".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"\pLetter", true),
+            Some(vec!["etter".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"\p{Greek}abc", true),
+            Some(vec!["abc".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"ab\dcd\wefg", true),
+            Some(vec!["efg".to_string()])
+        );
+        assert_eq!(
+            regex_literals(r"\.\*foo", true),
+            Some(vec![".*foo".to_string()])
+        );
+        assert_eq!(regex_literals(r"foo|bar", true), None);
+        assert_eq!(regex_literals(r"(?i)foo", true), None);
+    }
+
+    #[test]
+    fn test_trigram_index() {
+        let mut index = TrigramIndex::default();
+        let path: Arc<Path> = Path::new("a.rs").into();
+        let mtime = Some(SystemTime::UNIX_EPOCH);
+        index.insert(path.clone(), mtime, text_trigrams("fn Foo() {}"));
+
+        let query_trigrams = |text: &str| {
+            query_trigrams(
+                &SearchQuery::text(
+                    text,
+                    false,
+                    false,
+                    false,
+                    Default::default(),
+                    Default::default(),
+                    None,
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            index.may_contain(&path, mtime, &query_trigrams("foo()")),
+            Some(true)
+        );
+        assert_eq!(
+            index.may_contain(&path, mtime, &query_trigrams("bar")),
+            Some(false)
+        );
+        assert_eq!(
+            index.may_contain(&path, Some(SystemTime::now()), &query_trigrams("bar")),
+            None
+        );
+        assert_eq!(
+            index.may_contain(Path::new("b.rs"), mtime, &query_trigrams("bar")),
+            None
+        );
+    }
+}
@@ -15,11 +15,13 @@ use futures::{
 use gpui::{
     AppContext, AsyncAppContext, EntityId, EventEmitter, Model, ModelContext, Task, WeakModel,
 };
+use parking_lot::RwLock;
 use postage::oneshot;
 use rpc::{
     proto::{self, AnyProtoClient, SSH_PROJECT_ID},
     TypedEnvelope,
 };
+use settings::Settings as _;
 use smol::{
     channel::{Receiver, Sender},
     stream::StreamExt,
@@ -28,7 +30,12 @@ use text::ReplicaId;
 use util::{paths::compare_paths, ResultExt};
 use worktree::{Entry, ProjectEntryId, Worktree, WorktreeId, WorktreeSettings};
 
-use crate::{search::SearchQuery, ProjectPath};
+use crate::{
+    project_settings::ProjectSettings,
+    search::SearchQuery,
+    search_index::{self, SearchIndex, Trigram, TrigramIndex},
+    ProjectPath,
+};
 
 struct MatchingEntry {
     worktree_path: Arc<Path>,
@@ -46,6 +53,7 @@ pub struct WorktreeStore {
     #[allow(clippy::type_complexity)]
     loading_worktrees:
         HashMap<Arc<Path>, Shared<Task<Result<Model<Worktree>, Arc<anyhow::Error>>>>>,
+    search_index: SearchIndex,
     fs: Arc<dyn Fs>,
 }
 
@@ -75,6 +83,7 @@ impl WorktreeStore {
             is_shared: retain_worktrees,
             worktrees: Vec::new(),
             worktrees_reordered: false,
+            search_index: SearchIndex::default(),
             fs,
         }
     }
@@ -314,8 +323,19 @@ impl WorktreeStore {
 
         cx.emit(WorktreeStoreEvent::WorktreeAdded(worktree.clone()));
 
+        if worktree.read(cx).is_local() {
+            self.update_search_index(cx);
+            cx.subscribe(worktree, |this, worktree, event, cx| {
+                if let worktree::Event::UpdatedEntries(changes) = event {
+                    this.search_index.entries_changed(&worktree, changes, cx);
+                }
+            })
+            .detach();
+        }
+
         let handle_id = worktree.entity_id();
-        cx.observe_release(worktree, move |_, worktree, cx| {
+        cx.observe_release(worktree, move |this, worktree, cx| {
+            this.search_index.remove_worktree(worktree.id());
             cx.emit(WorktreeStoreEvent::WorktreeRemoved(
                 handle_id,
                 worktree.id(),
@@ -324,10 +344,31 @@ impl WorktreeStore {
         .detach();
     }
 
+    /// Indexes the contents of local worktrees, or drops their indices, according to the
+    /// search index settings.
+    pub fn update_search_index(&mut self, cx: &mut ModelContext<Self>) {
+        let settings = ProjectSettings::get_global(cx);
+        let enabled = settings.search_index.enabled;
+        let max_file_size = settings
+            .search_index
+            .max_file_size_bytes
+            .min(settings.large_files.threshold_bytes);
+        for worktree in self.worktrees().collect::<Vec<_>>() {
+            let worktree_id = worktree.read(cx).id();
+            if !enabled || !worktree.read(cx).is_local() {
+                self.search_index.remove_worktree(worktree_id);
+            } else if self.search_index.max_file_size(worktree_id) != Some(max_file_size) {
+                self.search_index
+                    .add_worktree(&worktree, max_file_size, self.fs.clone(), cx);
+            }
+        }
+    }
+
     pub fn remove_worktree(&mut self, id_to_remove: WorktreeId, cx: &mut ModelContext<Self>) {
         self.worktrees.retain(|worktree| {
             if let Some(worktree) = worktree.upgrade() {
                 if worktree.read(cx).id() == id_to_remove {
+                    self.search_index.remove_worktree(id_to_remove);
                     cx.emit(WorktreeStoreEvent::WorktreeRemoved(
                         worktree.entity_id(),
                         id_to_remove,
@@ -482,7 +523,11 @@ impl WorktreeStore {
             .visible_worktrees(cx)
             .filter_map(|tree| {
                 let tree = tree.read(cx);
-                Some((tree.snapshot(), tree.as_local()?.settings()))
+                Some((
+                    tree.snapshot(),
+                    tree.as_local()?.settings(),
+                    self.search_index.worktree_index(tree.id()),
+                ))
             })
             .collect::<Vec<_>>();
 
@@ -496,7 +541,7 @@ impl WorktreeStore {
         // against the version of the file on disk.
         let (filter_tx, filter_rx) = smol::channel::bounded(64);
         let (output_tx, mut output_rx) = smol::channel::bounded(64);
-        let (matching_paths_tx, matching_paths_rx) = smol::channel::unbounded();
+        let (matching_paths_tx, matching_paths_rx) = smol::channel::bounded(64);
 
         let input = cx.background_executor().spawn({
             let fs = fs.clone();
@@ -640,14 +685,19 @@ impl WorktreeStore {
 
     async fn find_candidate_paths(
         fs: Arc<dyn Fs>,
-        snapshots: Vec<(worktree::Snapshot, WorktreeSettings)>,
+        snapshots: Vec<(
+            worktree::Snapshot,
+            WorktreeSettings,
+            Option<Arc<RwLock<TrigramIndex>>>,
+        )>,
         open_entries: HashSet<ProjectEntryId>,
         query: SearchQuery,
         filter_tx: Sender<MatchingEntry>,
         output_tx: Sender<oneshot::Receiver<ProjectPath>>,
     ) -> Result<()> {
         let include_root = snapshots.len() > 1;
-        for (snapshot, settings) in snapshots {
+        let trigrams = search_index::query_trigrams(&query);
+        for (snapshot, settings, index) in snapshots {
             let mut entries: Vec<_> = snapshot.entries(query.include_ignored(), 0).collect();
             entries.sort_by(|a, b| compare_paths((&a.path, a.is_file()), (&b.path, b.is_file())));
             for entry in entries {
@@ -684,9 +734,14 @@ impl WorktreeStore {
                     }
                 }
 
+                let is_open = open_entries.contains(&entry.id);
+                if !is_open && !Self::may_contain(&index, &trigrams, entry) {
+                    continue;
+                }
+
                 let (mut tx, rx) = oneshot::channel();
 
-                if open_entries.contains(&entry.id) {
+                if is_open {
                     tx.send(ProjectPath {
                         worktree_id: snapshot.id(),
                         path: entry.path.clone(),
@@ -711,6 +766,21 @@ impl WorktreeStore {
         Ok(())
     }
 
+    /// Whether the on-disk contents of the entry may match the query, according to the index.
+    fn may_contain(
+        index: &Option<Arc<RwLock<TrigramIndex>>>,
+        trigrams: &Option<Vec<Trigram>>,
+        entry: &Entry,
+    ) -> bool {
+        let (Some(index), Some(trigrams)) = (index, trigrams) else {
+            return true;
+        };
+        index
+            .read()
+            .may_contain(&entry.path, entry.mtime, trigrams)
+            .unwrap_or(true)
+    }
+
     async fn filter_paths(
         fs: &Arc<dyn Fs>,
         mut input: Receiver<MatchingEntry>,
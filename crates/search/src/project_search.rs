@@ -1,17 +1,17 @@
 use crate::{
     FocusSearch, NextHistoryQuery, PreviousHistoryQuery, ReplaceAll, ReplaceNext, SearchOptions,
     SelectNextMatch, SelectPrevMatch, ToggleCaseSensitive, ToggleIncludeIgnored, ToggleRegex,
-    ToggleReplace, ToggleWholeWord,
+    ToggleReplace, ToggleStructural, ToggleWholeWord,
 };
 use collections::{HashMap, HashSet};
 use editor::{
     actions::SelectAll,
-    items::active_match_index,
+    items::{active_match_index, structural_replacement},
     scroll::{Autoscroll, Axis},
     Anchor, Editor, EditorElement, EditorEvent, EditorSettings, EditorStyle, MultiBuffer,
     MAX_TAB_TITLE_LEN,
 };
-use futures::StreamExt;
+use futures::{stream::LocalBoxStream, StreamExt};
 use gpui::{
     actions, div, Action, AnyElement, AnyView, AppContext, Context as _, EntityId, EventEmitter,
     FocusHandle, FocusableView, Global, Hsla, InteractiveElement, IntoElement, KeyContext, Model,
@@ -21,7 +21,7 @@ use gpui::{
 use language::Buffer;
 use menu::Confirm;
 use project::{
-    search::{SearchInputKind, SearchQuery},
+    search::{SearchInputKind, SearchQuery, SearchResult, SearchScope},
     search_history::SearchHistoryCursor,
     Project, ProjectPath,
 };
@@ -34,8 +34,8 @@ use std::{
 };
 use theme::ThemeSettings;
 use ui::{
-    h_flex, prelude::*, v_flex, Icon, IconButton, IconName, KeyBinding, Label, LabelCommon,
-    LabelSize, Selectable, Tooltip,
+    h_flex, prelude::*, v_flex, ContextMenu, DropdownMenu, Icon, IconButton, IconName, KeyBinding,
+    Label, LabelCommon, LabelSize, Selectable, Tooltip,
 };
 use util::paths::PathMatcher;
 use workspace::{
@@ -48,9 +48,20 @@ use workspace::{
 const MIN_INPUT_WIDTH_REMS: f32 = 15.;
 const MAX_INPUT_WIDTH_REMS: f32 = 30.;
 
+/// Once a page of results has this many files or matches, the remaining results are only
+/// loaded on request.
+const RESULTS_PAGE_FILES: usize = 1_000;
+const RESULTS_PAGE_MATCHES: usize = 5_000;
+
 actions!(
     project_search,
-    [SearchInNew, ToggleFocus, NextField, ToggleFilters]
+    [
+        SearchInNew,
+        ToggleFocus,
+        NextField,
+        ToggleFilters,
+        LoadMoreResults
+    ]
 );
 
 #[derive(Default)]
@@ -67,6 +78,9 @@ pub fn init(cx: &mut AppContext) {
         register_workspace_action(workspace, move |search_bar, _: &ToggleFilters, cx| {
             search_bar.toggle_filters(cx);
         });
+        register_workspace_action(workspace, move |search_bar, _: &LoadMoreResults, cx| {
+            search_bar.load_more_results(cx);
+        });
         register_workspace_action(workspace, move |search_bar, _: &ToggleCaseSensitive, cx| {
             search_bar.toggle_search_option(SearchOptions::CASE_SENSITIVE, cx);
         });
@@ -76,6 +90,9 @@ pub fn init(cx: &mut AppContext) {
         register_workspace_action(workspace, move |search_bar, _: &ToggleRegex, cx| {
             search_bar.toggle_search_option(SearchOptions::REGEX, cx);
         });
+        register_workspace_action(workspace, move |search_bar, _: &ToggleStructural, cx| {
+            search_bar.toggle_search_option(SearchOptions::STRUCTURAL, cx);
+        });
         register_workspace_action(workspace, move |search_bar, action: &ToggleReplace, cx| {
             search_bar.toggle_replace(action, cx)
         });
@@ -131,7 +148,8 @@ pub struct ProjectSearch {
     last_search_query_text: Option<String>,
     search_id: usize,
     no_results: Option<bool>,
-    limit_reached: bool,
+    /// The results of the active query that haven't been loaded yet.
+    unloaded_results: Option<LocalBoxStream<'static, Vec<SearchResult>>>,
     search_history_cursor: SearchHistoryCursor,
     search_included_history_cursor: SearchHistoryCursor,
     search_excluded_history_cursor: SearchHistoryCursor,
@@ -144,6 +162,37 @@ enum InputPanel {
     Include,
 }
 
+/// Which part of the code, by syntax, the search is restricted to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ScopeFilter {
+    #[default]
+    Everywhere,
+    Comments,
+    Strings,
+    Code,
+    Symbol,
+}
+
+impl ScopeFilter {
+    const ALL: [ScopeFilter; 5] = [
+        ScopeFilter::Everywhere,
+        ScopeFilter::Comments,
+        ScopeFilter::Strings,
+        ScopeFilter::Code,
+        ScopeFilter::Symbol,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            ScopeFilter::Everywhere => "Everywhere",
+            ScopeFilter::Comments => "Only in comments",
+            ScopeFilter::Strings => "Only in strings",
+            ScopeFilter::Code => "Exclude comments and strings",
+            ScopeFilter::Symbol => "Within symbol",
+        }
+    }
+}
+
 pub struct ProjectSearchView {
     workspace: WeakView<Workspace>,
     focus_handle: FocusHandle,
@@ -157,6 +206,8 @@ pub struct ProjectSearchView {
     search_id: usize,
     included_files_editor: View<Editor>,
     excluded_files_editor: View<Editor>,
+    scope_filter: ScopeFilter,
+    scope_symbol_editor: View<Editor>,
     filters_enabled: bool,
     replace_enabled: bool,
     included_opened_only: bool,
@@ -188,7 +239,7 @@ impl ProjectSearch {
             last_search_query_text: None,
             search_id: 0,
             no_results: None,
-            limit_reached: false,
+            unloaded_results: None,
             search_history_cursor: Default::default(),
             search_included_history_cursor: Default::default(),
             search_excluded_history_cursor: Default::default(),
@@ -207,7 +258,7 @@ impl ProjectSearch {
             last_search_query_text: self.last_search_query_text.clone(),
             search_id: self.search_id,
             no_results: self.no_results,
-            limit_reached: self.limit_reached,
+            unloaded_results: None,
             search_history_cursor: self.search_history_cursor.clone(),
             search_included_history_cursor: self.search_included_history_cursor.clone(),
             search_excluded_history_cursor: self.search_excluded_history_cursor.clone(),
@@ -251,61 +302,68 @@ impl ProjectSearch {
         self.search_id += 1;
         self.active_query = Some(query);
         self.match_ranges.clear();
+        self.unloaded_results = None;
+        self.excerpts.update(cx, |excerpts, cx| excerpts.clear(cx));
+        self.no_results = Some(true);
+        self.load_results(search.ready_chunks(1024).boxed_local(), cx);
+    }
+
+    fn has_unloaded_results(&self) -> bool {
+        self.unloaded_results.is_some()
+    }
+
+    fn load_more_results(&mut self, cx: &mut ModelContext<Self>) {
+        if let Some(results) = self.unloaded_results.take() {
+            self.load_results(results, cx);
+        }
+    }
+
+    /// Loads a page of results, keeping the rest of the stream around to be loaded later.
+    fn load_results(
+        &mut self,
+        mut results: LocalBoxStream<'static, Vec<SearchResult>>,
+        cx: &mut ModelContext<Self>,
+    ) {
         self.pending_search = Some(cx.spawn(|this, mut cx| async move {
-            let mut matches = search.ready_chunks(1024);
             let this = this.upgrade()?;
-            this.update(&mut cx, |this, cx| {
-                this.match_ranges.clear();
-                this.excerpts.update(cx, |this, cx| this.clear(cx));
-                this.no_results = Some(true);
-                this.limit_reached = false;
-            })
-            .ok()?;
-
-            let mut limit_reached = false;
-            while let Some(results) = matches.next().await {
+            let mut loaded_files = 0;
+            let mut loaded_matches = 0;
+            let mut exhausted = true;
+            while let Some(results) = results.next().await {
                 let tasks = results
                     .into_iter()
                     .map(|result| {
                         let this = this.clone();
 
                         cx.spawn(|mut cx| async move {
-                            match result {
-                                project::search::SearchResult::Buffer { buffer, ranges } => {
-                                    let mut match_ranges_rx =
-                                        this.update(&mut cx, |this, cx| {
-                                            this.excerpts.update(cx, |excerpts, cx| {
-                                                excerpts.stream_excerpts_with_context_lines(
-                                                    buffer,
-                                                    ranges,
-                                                    editor::DEFAULT_MULTIBUFFER_CONTEXT,
-                                                    cx,
-                                                )
-                                            })
-                                        })?;
-
-                                    let mut match_ranges = vec![];
-                                    while let Some(range) = match_ranges_rx.next().await {
-                                        match_ranges.push(range);
-                                    }
-                                    anyhow::Ok((match_ranges, false))
-                                }
-                                project::search::SearchResult::LimitReached => {
-                                    anyhow::Ok((vec![], true))
-                                }
+                            let SearchResult::Buffer { buffer, ranges } = result;
+                            let mut match_ranges_rx = this.update(&mut cx, |this, cx| {
+                                this.excerpts.update(cx, |excerpts, cx| {
+                                    excerpts.stream_excerpts_with_context_lines(
+                                        buffer,
+                                        ranges,
+                                        editor::DEFAULT_MULTIBUFFER_CONTEXT,
+                                        cx,
+                                    )
+                                })
+                            })?;
+
+                            let mut match_ranges = vec![];
+                            while let Some(range) = match_ranges_rx.next().await {
+                                match_ranges.push(range);
                             }
+                            anyhow::Ok(match_ranges)
                         })
                     })
                     .collect::<Vec<_>>();
 
+                loaded_files += tasks.len();
                 let result_ranges = futures::future::join_all(tasks).await;
                 let mut combined_ranges = vec![];
-                for (ranges, result_limit_reached) in result_ranges.into_iter().flatten() {
+                for ranges in result_ranges.into_iter().flatten() {
                     combined_ranges.extend(ranges);
-                    if result_limit_reached {
-                        limit_reached = result_limit_reached;
-                    }
                 }
+                loaded_matches += combined_ranges.len();
                 this.update(&mut cx, |this, cx| {
                     if !combined_ranges.is_empty() {
                         this.no_results = Some(false);
@@ -314,10 +372,17 @@ impl ProjectSearch {
                     }
                 })
                 .ok()?;
+
+                if loaded_files >= RESULTS_PAGE_FILES || loaded_matches >= RESULTS_PAGE_MATCHES {
+                    exhausted = false;
+                    break;
+                }
             }
 
             this.update(&mut cx, |this, cx| {
-                this.limit_reached = limit_reached;
+                if !exhausted {
+                    this.unloaded_results = Some(results);
+                }
                 this.pending_search.take();
                 cx.notify();
             })
@@ -591,6 +656,14 @@ impl ProjectSearchView {
 
     fn toggle_search_option(&mut self, option: SearchOptions, cx: &mut ViewContext<Self>) {
         self.search_options.toggle(option);
+        // Regex and structural patterns are mutually exclusive ways to read the query.
+        if self.search_options.contains(option) {
+            if option == SearchOptions::REGEX {
+                self.search_options.remove(SearchOptions::STRUCTURAL);
+            } else if option == SearchOptions::STRUCTURAL {
+                self.search_options.remove(SearchOptions::REGEX);
+            }
+        }
         ActiveSettings::update_global(cx, |settings, cx| {
             settings.0.insert(
                 self.model.read(cx).project.downgrade(),
@@ -603,6 +676,33 @@ impl ProjectSearchView {
         self.included_opened_only = !self.included_opened_only;
     }
 
+    fn set_scope_filter(&mut self, scope_filter: ScopeFilter, cx: &mut ViewContext<Self>) {
+        self.scope_filter = scope_filter;
+        if scope_filter == ScopeFilter::Symbol {
+            self.scope_symbol_editor.focus_handle(cx).focus(cx);
+        } else if self.model.read(cx).active_query.is_some() {
+            self.search(cx);
+        }
+        cx.notify();
+    }
+
+    fn search_scope(&self, cx: &AppContext) -> SearchScope {
+        match self.scope_filter {
+            ScopeFilter::Everywhere => SearchScope::Everywhere,
+            ScopeFilter::Comments => SearchScope::Comments,
+            ScopeFilter::Strings => SearchScope::Strings,
+            ScopeFilter::Code => SearchScope::Code,
+            ScopeFilter::Symbol => {
+                let symbol = self.scope_symbol_editor.read(cx).text(cx);
+                if symbol.trim().is_empty() {
+                    SearchScope::Everywhere
+                } else {
+                    SearchScope::Symbol(symbol.trim().to_string())
+                }
+            }
+        }
+    }
+
     fn replace_next(&mut self, _: &ReplaceNext, cx: &mut ViewContext<Self>) {
         if self.model.read(cx).match_ranges.is_empty() {
             return;
@@ -626,6 +726,28 @@ impl ProjectSearchView {
     pub fn replacement(&self, cx: &AppContext) -> String {
         self.replacement_editor.read(cx).text(cx)
     }
+
+    /// What the active match would be replaced with, for queries whose replacement
+    /// depends on the matched text.
+    fn replacement_preview(&self, cx: &AppContext) -> Option<String> {
+        let active_index = self.active_match_index?;
+        let model = self.model.read(cx);
+        let query = model.active_query.as_ref()?;
+        if !query.is_regex() && !query.is_structural() {
+            return None;
+        }
+        let range = model.match_ranges.get(active_index)?;
+        let query = query.clone().with_replacement(self.replacement(cx));
+        let buffer = self.results_editor.read(cx).buffer().read(cx).snapshot(cx);
+        if query.is_structural() {
+            structural_replacement(&buffer, range, &query)
+        } else {
+            let text = buffer.text_for_range(range.clone()).collect::<String>();
+            query
+                .replacement_for(&text)
+                .map(|replacement| replacement.into_owned())
+        }
+    }
     fn replace_all(&mut self, _: &ReplaceAll, cx: &mut ViewContext<Self>) {
         if self.active_match_index.is_none() {
             return;
@@ -716,6 +838,7 @@ impl ProjectSearchView {
             }
             editor
         });
+        subscriptions.push(cx.observe(&replacement_editor, |_, _, cx| cx.notify()));
         let results_editor = cx.new_view(|cx| {
             let mut editor = Editor::for_multibuffer(excerpts, Some(project.clone()), true, cx);
             editor.set_searchable(false);
@@ -759,6 +882,18 @@ impl ProjectSearchView {
             }),
         );
 
+        let scope_symbol_editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("Symbol name", cx);
+
+            editor
+        });
+        subscriptions.push(
+            cx.subscribe(&scope_symbol_editor, |_, _, event: &EditorEvent, cx| {
+                cx.emit(ViewEvent::EditorEvent(event.clone()))
+            }),
+        );
+
         let focus_handle = cx.focus_handle();
         subscriptions.push(cx.on_focus_in(&focus_handle, |this, cx| {
             if this.focus_handle.is_focused(cx) {
@@ -784,6 +919,8 @@ impl ProjectSearchView {
             active_match_index: None,
             included_files_editor,
             excluded_files_editor,
+            scope_filter: ScopeFilter::default(),
+            scope_symbol_editor,
             filters_enabled,
             replace_enabled: false,
             included_opened_only: false,
@@ -977,7 +1114,32 @@ impl ProjectSearchView {
                 }
             };
 
-        let query = if self.search_options.contains(SearchOptions::REGEX) {
+        let query = if self.search_options.contains(SearchOptions::STRUCTURAL) {
+            match SearchQuery::structural(
+                text,
+                self.search_options.contains(SearchOptions::INCLUDE_IGNORED),
+                included_files,
+                excluded_files,
+                open_buffers,
+            ) {
+                Ok(query) => {
+                    let should_unmark_error = self.panels_with_errors.remove(&InputPanel::Query);
+                    if should_unmark_error {
+                        cx.notify();
+                    }
+
+                    Some(query)
+                }
+                Err(_e) => {
+                    let should_mark_error = self.panels_with_errors.insert(InputPanel::Query);
+                    if should_mark_error {
+                        cx.notify();
+                    }
+
+                    None
+                }
+            }
+        } else if self.search_options.contains(SearchOptions::REGEX) {
             match SearchQuery::regex(
                 text,
                 self.search_options.contains(SearchOptions::WHOLE_WORD),
@@ -1038,7 +1200,7 @@ impl ProjectSearchView {
         if query.as_ref().is_some_and(|query| query.is_empty()) {
             return None;
         }
-        query
+        query.map(|query| query.with_scope(self.search_scope(cx)))
     }
 
     fn open_buffers(&self, cx: &mut ViewContext<Self>) -> Vec<Model<Buffer>> {
@@ -1323,6 +1485,9 @@ impl ProjectSearchBar {
                     &project_view.included_files_editor,
                     &project_view.excluded_files_editor,
                 ]);
+                if project_view.scope_filter == ScopeFilter::Symbol {
+                    views.push(&project_view.scope_symbol_editor);
+                }
             }
             let current_index = match views
                 .iter()
@@ -1395,6 +1560,16 @@ impl ProjectSearchBar {
         }
     }
 
+    fn load_more_results(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(search_view) = self.active_project_search.as_ref() {
+            search_view.update(cx, |search_view, cx| {
+                search_view
+                    .model
+                    .update(cx, |model, cx| model.load_more_results(cx));
+            });
+        }
+    }
+
     fn toggle_opened_only(&mut self, cx: &mut ViewContext<Self>) -> bool {
         if let Some(search_view) = self.active_project_search.as_ref() {
             search_view.update(cx, |search_view, cx| {
@@ -1567,10 +1742,32 @@ impl ProjectSearchBar {
 
 impl Render for ProjectSearchBar {
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        let Some(search) = self.active_project_search.clone() else {
+        let Some(search_view) = self.active_project_search.clone() else {
             return div();
         };
-        let search = search.read(cx);
+        let scope_menu = search_view.read(cx).filters_enabled.then(|| {
+            let current_scope_filter = search_view.read(cx).scope_filter;
+            ContextMenu::build(cx, |mut menu, _| {
+                for scope_filter in ScopeFilter::ALL {
+                    let search_view = search_view.downgrade();
+                    menu = menu.toggleable_entry(
+                        scope_filter.label(),
+                        scope_filter == current_scope_filter,
+                        IconPosition::Start,
+                        None,
+                        move |cx| {
+                            search_view
+                                .update(cx, |search_view, cx| {
+                                    search_view.set_scope_filter(scope_filter, cx)
+                                })
+                                .ok();
+                        },
+                    );
+                }
+                menu
+            })
+        });
+        let search = search_view.read(cx);
 
         let query_column = h_flex()
             .flex_1()
@@ -1606,6 +1803,12 @@ impl Render for ProjectSearchBar {
                         cx.listener(|this, _, cx| {
                             this.toggle_search_option(SearchOptions::REGEX, cx);
                         }),
+                    ))
+                    .child(SearchOptions::STRUCTURAL.as_button(
+                        self.is_option_enabled(SearchOptions::STRUCTURAL, cx),
+                        cx.listener(|this, _, cx| {
+                            this.toggle_search_option(SearchOptions::STRUCTURAL, cx);
+                        }),
                     )),
             );
 
@@ -1640,7 +1843,7 @@ impl Render for ProjectSearchBar {
                 ),
         );
 
-        let limit_reached = search.model.read(cx).limit_reached;
+        let has_unloaded_results = search.model.read(cx).has_unloaded_results();
         let match_text = search
             .active_match_index
             .and_then(|index| {
@@ -1648,7 +1851,7 @@ impl Render for ProjectSearchBar {
                 let match_quantity = search.model.read(cx).match_ranges.len();
                 if match_quantity > 0 {
                     debug_assert!(match_quantity >= index);
-                    if limit_reached {
+                    if has_unloaded_results {
                         Some(format!("{index}/{match_quantity}+").to_string())
                     } else {
                         Some(format!("{index}/{match_quantity}").to_string())
@@ -1686,23 +1889,22 @@ impl Render for ProjectSearchBar {
                     }))
                     .tooltip(|cx| Tooltip::for_action("Go to next match", &SelectNextMatch, cx)),
             )
-            .child(
-                h_flex()
-                    .id("matches")
-                    .min_w(rems_from_px(40.))
-                    .child(
-                        Label::new(match_text).color(if search.active_match_index.is_some() {
-                            Color::Default
-                        } else {
-                            Color::Disabled
+            .child(h_flex().id("matches").min_w(rems_from_px(40.)).child(
+                Label::new(match_text).color(if search.active_match_index.is_some() {
+                    Color::Default
+                } else {
+                    Color::Disabled
+                }),
+            ))
+            .when(has_unloaded_results, |el| {
+                el.child(
+                    IconButton::new("project-search-load-more", IconName::Ellipsis)
+                        .on_click(|_, cx| cx.dispatch_action(LoadMoreResults.boxed_clone()))
+                        .tooltip(|cx| {
+                            Tooltip::for_action("Load more results", &LoadMoreResults, cx)
                         }),
-                    )
-                    .when(limit_reached, |el| {
-                        el.tooltip(|cx| {
-                            Tooltip::text("Search limits reached.\nTry narrowing your search.", cx)
-                        })
-                    }),
-            );
+                )
+            });
 
         let search_line = h_flex()
             .flex_1()
@@ -1751,6 +1953,14 @@ impl Render for ProjectSearchBar {
                 .gap_2()
                 .child(replace_column)
                 .child(replace_actions)
+                .when_some(search.replacement_preview(cx), |this, preview| {
+                    this.child(
+                        Label::new(preview)
+                            .size(LabelSize::Small)
+                            .color(Color::Muted)
+                            .single_line(),
+                    )
+                })
         });
 
         let filter_line = search.filters_enabled.then(|| {
@@ -1797,6 +2007,28 @@ impl Render for ProjectSearchBar {
                         )
                         .child(self.render_text_input(&search.excluded_files_editor, cx)),
                 )
+                .when(search.scope_filter == ScopeFilter::Symbol, |this| {
+                    this.child(
+                        h_flex()
+                            .flex_1()
+                            .min_w(rems(6.))
+                            .max_w(rems(12.))
+                            .h_8()
+                            .px_2()
+                            .py_1()
+                            .border_1()
+                            .border_color(cx.theme().colors().border)
+                            .rounded_lg()
+                            .child(self.render_text_input(&search.scope_symbol_editor, cx)),
+                    )
+                })
+                .when_some(scope_menu, |this, scope_menu| {
+                    this.child(DropdownMenu::new(
+                        "project-search-scope",
+                        search.scope_filter.label(),
+                        scope_menu,
+                    ))
+                })
                 .child(
                     IconButton::new("project-search-opened-only", IconName::FileDoc)
                         .selected(self.is_opened_only_enabled(cx))
@@ -1828,6 +2060,9 @@ impl Render for ProjectSearchBar {
             .on_action(cx.listener(|this, _: &ToggleFilters, cx| {
                 this.toggle_filters(cx);
             }))
+            .on_action(cx.listener(|this, _: &LoadMoreResults, cx| {
+                this.load_more_results(cx);
+            }))
             .capture_action(cx.listener(|this, action, cx| {
                 this.tab(action, cx);
                 cx.stop_propagation();
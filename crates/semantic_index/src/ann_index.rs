@@ -0,0 +1,445 @@
+//! An approximate nearest neighbor index over a worktree's chunk embeddings, so that searches
+//! don't need to score every chunk.
+//!
+//! Once a worktree has enough chunks for an exhaustive search to get slow, its chunks are
+//! partitioned into lists by their nearest centroid, computed with k-means. A search scores the
+//! chunks of the lists whose centroids are nearest to the query using quantized embeddings,
+//! then rescores the best candidates with their exact embeddings.
+
+use crate::{
+    embedding::Embedding,
+    embedding_index::{EmbeddedChunk, EmbeddedFile},
+};
+use anyhow::{Context as _, Result};
+use collections::HashMap;
+use heed::{
+    types::{SerdeBincode, Str},
+    RoTxn, RwTxn,
+};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, sync::Arc};
+
+/// Below this many chunks, searches score every chunk.
+const MIN_CHUNK_COUNT: usize = 10_000;
+const MAX_LIST_COUNT: usize = 256;
+const MIN_PROBED_LIST_COUNT: usize = 8;
+const TRAINING_SAMPLES_PER_LIST: usize = 16;
+const TRAINING_ITERATIONS: usize = 8;
+/// How many times larger the index can grow before its centroids are computed again, to keep
+/// the lists balanced.
+const RETRAINING_GROWTH_FACTOR: usize = 4;
+/// How many times more candidates than requested results are rescored.
+const RESCORED_CANDIDATES_FACTOR: usize = 4;
+const METADATA_KEY: &str = "metadata";
+
+#[derive(Clone, Copy)]
+pub struct AnnIndex {
+    embedding_db: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+    metadata_db: heed::Database<Str, SerdeBincode<AnnMetadata>>,
+    postings_db: heed::Database<Str, SerdeBincode<Vec<Posting>>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct AnnMetadata {
+    chunk_count: usize,
+    trained_chunk_count: usize,
+    centroids: Vec<Embedding>,
+}
+
+/// A chunk of a file in one of the index's lists.
+#[derive(Serialize, Deserialize)]
+pub struct Posting {
+    chunk_ix: u32,
+    embedding: QuantizedEmbedding,
+}
+
+/// An embedding with each dimension stored in a byte, which is precise enough to pick the
+/// candidates that are rescored.
+#[derive(Serialize, Deserialize)]
+struct QuantizedEmbedding(Vec<i8>);
+
+impl QuantizedEmbedding {
+    fn new(embedding: &Embedding) -> Self {
+        Self(
+            embedding
+                .as_slice()
+                .iter()
+                .map(|value| (value * 127.).round().clamp(-127., 127.) as i8)
+                .collect(),
+        )
+    }
+
+    fn similarity(&self, other: &[f32]) -> f32 {
+        debug_assert_eq!(self.0.len(), other.len());
+        self.0
+            .iter()
+            .zip(other)
+            .map(|(a, b)| *a as f32 * b)
+            .sum::<f32>()
+            / 127.
+    }
+}
+
+impl AnnIndex {
+    pub fn new(
+        embedding_db: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+        metadata_db: heed::Database<Str, SerdeBincode<AnnMetadata>>,
+        postings_db: heed::Database<Str, SerdeBincode<Vec<Posting>>>,
+    ) -> Self {
+        Self {
+            embedding_db,
+            metadata_db,
+            postings_db,
+        }
+    }
+
+    /// Adds a file's chunks to the index. This must be called before the file is saved in the
+    /// embedding database.
+    pub fn insert_file(&self, txn: &mut RwTxn, key: &str, file: &EmbeddedFile) -> Result<()> {
+        let mut metadata = self.metadata(txn)?;
+        metadata.chunk_count += file.chunks.len();
+        self.insert_postings(txn, &metadata.centroids, key, file)?;
+        self.metadata_db.put(txn, METADATA_KEY, &metadata)?;
+        Ok(())
+    }
+
+    /// Removes a file's chunks from the index. This must be called before the file is deleted
+    /// from the embedding database.
+    pub fn remove_file(&self, txn: &mut RwTxn, key: &str, file: &EmbeddedFile) -> Result<()> {
+        let mut metadata = self.metadata(txn)?;
+        metadata.chunk_count = metadata.chunk_count.saturating_sub(file.chunks.len());
+        let mut lists = file
+            .chunks
+            .iter()
+            .map(|chunk| nearest_centroid(&metadata.centroids, chunk.embedding.as_slice()))
+            .collect::<Vec<_>>();
+        lists.sort_unstable();
+        lists.dedup();
+        for list in lists.into_iter().flatten() {
+            self.postings_db.delete(txn, &posting_key(list, key))?;
+        }
+        self.metadata_db.put(txn, METADATA_KEY, &metadata)?;
+        Ok(())
+    }
+
+    /// Whether the centroids need to be computed, because the index got large enough to need
+    /// them or grew too much since they were.
+    pub fn needs_training(&self, txn: &RoTxn) -> Result<bool> {
+        let metadata = self.metadata(txn)?;
+        Ok(metadata.chunk_count >= MIN_CHUNK_COUNT
+            && (metadata.centroids.is_empty()
+                || metadata.chunk_count >= metadata.trained_chunk_count * RETRAINING_GROWTH_FACTOR))
+    }
+
+    /// Computes the centroids from a sample of the chunks, and rebuilds the lists.
+    pub fn train(&self, db_connection: &heed::Env) -> Result<()> {
+        let txn = db_connection
+            .read_txn()
+            .context("failed to create read transaction")?;
+        let metadata = self.metadata(&txn)?;
+        let list_count = list_count(metadata.chunk_count);
+        let sample_count = list_count * TRAINING_SAMPLES_PER_LIST;
+        let stride = (metadata.chunk_count / sample_count).max(1);
+        let mut samples = Vec::with_capacity(sample_count);
+        let mut chunk_ix = 0;
+        'files: for entry in self.embedding_db.iter(&txn)? {
+            let (_, file) = entry?;
+            // Sample chunks from across the whole worktree.
+            for chunk in file.chunks {
+                if chunk_ix % stride == 0 {
+                    samples.push(chunk.embedding);
+                    if samples.len() == sample_count {
+                        break 'files;
+                    }
+                }
+                chunk_ix += 1;
+            }
+        }
+        if samples.len() < list_count {
+            return Ok(());
+        }
+
+        log::debug!(
+            "computing {list_count} centroids from {} chunks",
+            samples.len()
+        );
+        let centroids = kmeans(&samples, list_count);
+
+        let mut write_txn = db_connection.write_txn()?;
+        self.postings_db.clear(&mut write_txn)?;
+        let mut chunk_count = 0;
+        for entry in self.embedding_db.iter(&txn)? {
+            let (key, file) = entry?;
+            chunk_count += file.chunks.len();
+            self.insert_postings(&mut write_txn, &centroids, key, &file)?;
+        }
+        self.metadata_db.put(
+            &mut write_txn,
+            METADATA_KEY,
+            &AnnMetadata {
+                chunk_count,
+                trained_chunk_count: chunk_count,
+                centroids,
+            },
+        )?;
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    /// Returns the files' chunks most similar to the query along with their index in the file,
+    /// or `None` if the index has no centroids yet, in which case every chunk needs to be scored.
+    pub fn search(
+        &self,
+        txn: &RoTxn,
+        query: &Embedding,
+        limit: usize,
+    ) -> Result<Option<Vec<(Arc<Path>, usize, EmbeddedChunk)>>> {
+        let Some(metadata) = self.metadata_db.get(txn, METADATA_KEY)? else {
+            return Ok(None);
+        };
+        if metadata.centroids.is_empty() {
+            return Ok(None);
+        }
+
+        let mut lists = metadata
+            .centroids
+            .iter()
+            .enumerate()
+            .map(|(list, centroid)| (list, dot(centroid.as_slice(), query.as_slice())))
+            .collect::<Vec<_>>();
+        lists.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+        lists.truncate(probed_list_count(metadata.centroids.len()));
+
+        let mut keys = Vec::new();
+        let mut candidates = Vec::new();
+        for (list, _) in lists {
+            let prefix = posting_key(list, "");
+            for entry in self.postings_db.prefix_iter(txn, &prefix)? {
+                let (key, postings) = entry?;
+                let key_ix = keys.len();
+                keys.push(key[prefix.len()..].to_string());
+                candidates.extend(postings.into_iter().map(|posting| {
+                    (
+                        posting.embedding.similarity(query.as_slice()),
+                        key_ix,
+                        posting.chunk_ix as usize,
+                    )
+                }));
+            }
+        }
+        candidates.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.truncate(limit * RESCORED_CANDIDATES_FACTOR);
+
+        let mut chunk_ixs_by_key = HashMap::<usize, Vec<usize>>::default();
+        for (_, key_ix, chunk_ix) in candidates {
+            chunk_ixs_by_key.entry(key_ix).or_default().push(chunk_ix);
+        }
+
+        let mut results = Vec::new();
+        for (key_ix, chunk_ixs) in chunk_ixs_by_key {
+            let Some(file) = self.embedding_db.get(txn, &keys[key_ix])? else {
+                continue;
+            };
+            for chunk_ix in chunk_ixs {
+                if let Some(chunk) = file.chunks.get(chunk_ix) {
+                    let score = dot(chunk.embedding.as_slice(), query.as_slice());
+                    results.push((score, file.path.clone(), chunk_ix, chunk.clone()));
+                }
+            }
+        }
+        results.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        results.truncate(limit);
+        Ok(Some(
+            results
+                .into_iter()
+                .map(|(_, path, chunk_ix, chunk)| (path, chunk_ix, chunk))
+                .collect(),
+        ))
+    }
+
+    fn metadata(&self, txn: &RoTxn) -> Result<AnnMetadata> {
+        if let Some(metadata) = self.metadata_db.get(txn, METADATA_KEY)? {
+            return Ok(metadata);
+        }
+
+        // Embeddings saved before the index existed are counted once.
+        let mut chunk_count = 0;
+        for entry in self.embedding_db.iter(txn)? {
+            chunk_count += entry?.1.chunks.len();
+        }
+        Ok(AnnMetadata {
+            chunk_count,
+            ..Default::default()
+        })
+    }
+
+    fn insert_postings(
+        &self,
+        txn: &mut RwTxn,
+        centroids: &[Embedding],
+        key: &str,
+        file: &EmbeddedFile,
+    ) -> Result<()> {
+        let mut postings_by_list = HashMap::<usize, Vec<Posting>>::default();
+        for (chunk_ix, chunk) in file.chunks.iter().enumerate() {
+            if let Some(list) = nearest_centroid(centroids, chunk.embedding.as_slice()) {
+                postings_by_list.entry(list).or_default().push(Posting {
+                    chunk_ix: chunk_ix as u32,
+                    embedding: QuantizedEmbedding::new(&chunk.embedding),
+                });
+            }
+        }
+        for (list, postings) in postings_by_list {
+            self.postings_db
+                .put(txn, &posting_key(list, key), &postings)?;
+        }
+        Ok(())
+    }
+}
+
+fn posting_key(list: usize, key: &str) -> String {
+    format!("{list:08x}\0{key}")
+}
+
+fn list_count(chunk_count: usize) -> usize {
+    ((chunk_count as f64).sqrt() as usize).clamp(1, MAX_LIST_COUNT)
+}
+
+fn probed_list_count(list_count: usize) -> usize {
+    (list_count / 8).max(MIN_PROBED_LIST_COUNT).min(list_count)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    debug_assert_eq!(a.len(), b.len());
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn nearest_centroid(centroids: &[Embedding], embedding: &[f32]) -> Option<usize> {
+    centroids
+        .iter()
+        .map(|centroid| dot(centroid.as_slice(), embedding))
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(list, _)| list)
+}
+
+/// Partitions the embeddings into `k` clusters, returning their normalized centroids.
+fn kmeans(embeddings: &[Embedding], k: usize) -> Vec<Embedding> {
+    let k = k.min(embeddings.len());
+    let mut centroids = (0..k)
+        .map(|ix| embeddings[ix * embeddings.len() / k].clone())
+        .collect::<Vec<_>>();
+    let Some(dimensions) = centroids.first().map(|centroid| centroid.as_slice().len()) else {
+        return centroids;
+    };
+
+    for _ in 0..TRAINING_ITERATIONS {
+        let mut sums = vec![vec![0f32; dimensions]; k];
+        for embedding in embeddings {
+            if let Some(list) = nearest_centroid(&centroids, embedding.as_slice()) {
+                for (sum, value) in sums[list].iter_mut().zip(embedding.as_slice()) {
+                    *sum += value;
+                }
+            }
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(sums) {
+            // Lists without any embeddings keep their centroid.
+            if sum.iter().any(|value| *value != 0.) {
+                *centroid = Embedding::new(sum);
+            }
+        }
+    }
+    centroids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::Chunk;
+
+    fn embedding(seed: usize) -> Embedding {
+        Embedding::new(
+            (0..16)
+                .map(|dimension| ((seed * 31 + dimension * 17) as f32).sin())
+                .collect(),
+        )
+    }
+
+    fn file(path: &str, seeds: std::ops::Range<usize>) -> EmbeddedFile {
+        EmbeddedFile {
+            path: Path::new(path).into(),
+            mtime: None,
+            chunks: seeds
+                .map(|seed| EmbeddedChunk {
+                    chunk: Chunk {
+                        range: seed..seed + 1,
+                        digest: Default::default(),
+                    },
+                    embedding: embedding(seed),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_ann_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_connection = unsafe {
+            heed::EnvOpenOptions::new()
+                .map_size(64 * 1024 * 1024)
+                .max_dbs(3)
+                .open(temp_dir.path())
+                .unwrap()
+        };
+        let mut txn = db_connection.write_txn().unwrap();
+        let index = AnnIndex::new(
+            db_connection
+                .create_database(&mut txn, Some("embeddings"))
+                .unwrap(),
+            db_connection
+                .create_database(&mut txn, Some("metadata"))
+                .unwrap(),
+            db_connection
+                .create_database(&mut txn, Some("postings"))
+                .unwrap(),
+        );
+        assert!(index.search(&txn, &embedding(0), 1).unwrap().is_none());
+
+        for file_ix in 0..100 {
+            let key = format!("file{file_ix}");
+            let file = file(&key, file_ix * 10..file_ix * 10 + 10);
+            index.insert_file(&mut txn, &key, &file).unwrap();
+            index.embedding_db.put(&mut txn, &key, &file).unwrap();
+        }
+        txn.commit().unwrap();
+        index.train(&db_connection).unwrap();
+
+        let txn = db_connection.read_txn().unwrap();
+        let results = index.search(&txn, &embedding(421), 3).unwrap().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0.as_ref(), Path::new("file42"));
+        assert_eq!(results[0].1, 1);
+        assert_eq!(results[0].2.chunk.range, 421..422);
+        drop(txn);
+
+        // Files changed after training are added to the existing lists.
+        let mut txn = db_connection.write_txn().unwrap();
+        let old_file = index.embedding_db.get(&txn, "file42").unwrap().unwrap();
+        index.remove_file(&mut txn, "file42", &old_file).unwrap();
+        index.embedding_db.delete(&mut txn, "file42").unwrap();
+        let new_file = file("file100", 421..422);
+        index.insert_file(&mut txn, "file100", &new_file).unwrap();
+        index
+            .embedding_db
+            .put(&mut txn, "file100", &new_file)
+            .unwrap();
+        txn.commit().unwrap();
+
+        let txn = db_connection.read_txn().unwrap();
+        let results = index.search(&txn, &embedding(421), 3).unwrap().unwrap();
+        assert_eq!(results[0].0.as_ref(), Path::new("file100"));
+        assert!(results
+            .iter()
+            .all(|(path, _, _)| path.as_ref() != Path::new("file42")));
+    }
+}
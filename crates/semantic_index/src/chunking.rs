@@ -118,6 +118,41 @@ fn syntactic_ranges(
     Some(ranges)
 }
 
+/// Returns the names of the file's outline items (types, functions, etc), along with their
+/// offsets.
+pub fn symbols(text: &str, language: Option<&Arc<Language>>, path: &Path) -> Vec<(usize, String)> {
+    let Some(grammar) = language.and_then(|language| language.grammar()) else {
+        return Vec::new();
+    };
+    let Some(outline) = grammar.outline_config.as_ref() else {
+        return Vec::new();
+    };
+    let tree = with_parser(|parser| {
+        parser.set_language(&grammar.ts_language).log_err()?;
+        parser.parse(text, None)
+    });
+    let Some(tree) = tree else {
+        log::error!("failed to parse file {path:?} for symbols");
+        return Vec::new();
+    };
+
+    with_query_cursor(|cursor| {
+        cursor
+            .matches(&outline.query, tree.root_node(), text.as_bytes())
+            .flat_map(|mat| {
+                mat.captures
+                    .iter()
+                    .filter(|capture| capture.index == outline.name_capture_ix)
+                    .filter_map(|capture| {
+                        let name = text.get(capture.node.byte_range())?;
+                        Some((capture.node.start_byte(), name.to_string()))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    })
+}
+
 fn chunk_text_with_syntactic_ranges(
     text: &str,
     mut syntactic_ranges: &[Range<usize>],
@@ -1,7 +1,9 @@
 use crate::{
+    ann_index::AnnIndex,
     chunking::{self, Chunk},
     embedding::{Embedding, EmbeddingProvider, TextToEmbed},
     indexing::{IndexingEntryHandle, IndexingEntrySet},
+    lexical_index::{LexicalFile, LexicalIndex},
 };
 use anyhow::{anyhow, Context as _, Result};
 use collections::Bound;
@@ -30,6 +32,8 @@ pub struct EmbeddingIndex {
     worktree: Model<Worktree>,
     db_connection: heed::Env,
     db: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+    ann_index: AnnIndex,
+    lexical_index: LexicalIndex,
     fs: Arc<dyn Fs>,
     language_registry: Arc<LanguageRegistry>,
     embedding_provider: Arc<dyn EmbeddingProvider>,
@@ -42,6 +46,8 @@ impl EmbeddingIndex {
         fs: Arc<dyn Fs>,
         db_connection: heed::Env,
         embedding_db: heed::Database<Str, SerdeBincode<EmbeddedFile>>,
+        ann_index: AnnIndex,
+        lexical_index: LexicalIndex,
         language_registry: Arc<LanguageRegistry>,
         embedding_provider: Arc<dyn EmbeddingProvider>,
         entry_ids_being_indexed: Arc<IndexingEntrySet>,
@@ -51,6 +57,8 @@ impl EmbeddingIndex {
             fs,
             db_connection,
             db: embedding_db,
+            ann_index,
+            lexical_index,
             language_registry,
             embedding_provider,
             entry_ids_being_indexed,
@@ -61,18 +69,27 @@ impl EmbeddingIndex {
         &self.db
     }
 
+    pub fn ann_index(&self) -> &AnnIndex {
+        &self.ann_index
+    }
+
+    pub fn lexical_index(&self) -> &LexicalIndex {
+        &self.lexical_index
+    }
+
     pub fn index_entries_changed_on_disk(
         &self,
         cx: &AppContext,
     ) -> impl Future<Output = Result<()>> {
         let worktree = self.worktree.read(cx).snapshot();
         let worktree_abs_path = worktree.abs_path().clone();
+        let backfill = self.backfill_lexical_index(worktree.clone(), cx);
         let scan = self.scan_entries(worktree, cx);
         let chunk = self.chunk_files(worktree_abs_path, scan.updated_entries, cx);
         let embed = Self::embed_files(self.embedding_provider.clone(), chunk.files, cx);
         let persist = self.persist_embeddings(scan.deleted_entry_ranges, embed.files, cx);
         async move {
-            futures::try_join!(scan.task, chunk.task, embed.task, persist)?;
+            futures::try_join!(backfill, scan.task, chunk.task, embed.task, persist)?;
             Ok(())
         }
     }
@@ -218,6 +235,75 @@ impl EmbeddingIndex {
         }
     }
 
+    /// Indexes the terms of the files whose embeddings were saved before the lexical index
+    /// existed, without embedding them again.
+    fn backfill_lexical_index(&self, worktree: Snapshot, cx: &AppContext) -> Task<Result<()>> {
+        let db_connection = self.db_connection.clone();
+        let db = self.db;
+        let lexical_index = self.lexical_index;
+        let language_registry = self.language_registry.clone();
+        let fs = self.fs.clone();
+        cx.background_executor().spawn(async move {
+            let txn = db_connection
+                .read_txn()
+                .context("failed to create read transaction")?;
+            let missing_keys = lexical_index.missing_files(&txn, db)?;
+            drop(txn);
+            if missing_keys.is_empty() {
+                return Ok(());
+            }
+
+            log::debug!("indexing the terms of {} files", missing_keys.len());
+            for keys in missing_keys.chunks(64) {
+                let mut lexical_files = Vec::new();
+                for key in keys {
+                    let txn = db_connection
+                        .read_txn()
+                        .context("failed to create read transaction")?;
+                    let Some(embedded_file) = db.get(&txn, key)? else {
+                        continue;
+                    };
+                    drop(txn);
+
+                    // Files changed since they were embedded are indexed when they're embedded
+                    // again, since their chunks no longer match their text.
+                    let Some(entry) = worktree.entry_for_path(&embedded_file.path) else {
+                        continue;
+                    };
+                    if entry.mtime != embedded_file.mtime {
+                        continue;
+                    }
+                    let Some(text) = fs
+                        .load(&worktree.abs_path().join(&embedded_file.path))
+                        .await
+                        .log_err()
+                    else {
+                        continue;
+                    };
+                    let language = language_registry
+                        .language_for_file_path(&embedded_file.path)
+                        .await
+                        .ok();
+                    let chunks = embedded_file
+                        .chunks
+                        .into_iter()
+                        .map(|embedded_chunk| embedded_chunk.chunk)
+                        .collect::<Vec<_>>();
+                    let symbols = chunking::symbols(&text, language.as_ref(), &embedded_file.path);
+                    lexical_files.push((key, LexicalFile::new(&text, &chunks, &symbols)));
+                }
+
+                let mut txn = db_connection.write_txn()?;
+                for (key, lexical_file) in &lexical_files {
+                    lexical_index.insert_missing_file(&mut txn, key, lexical_file)?;
+                }
+                txn.commit()?;
+            }
+
+            Ok(())
+        })
+    }
+
     fn chunk_files(
         &self,
         worktree_abs_path: Arc<Path>,
@@ -240,12 +326,19 @@ impl EmbeddingIndex {
                                             .language_for_file_path(&entry.path)
                                             .await
                                             .ok();
+                                        let chunks = chunking::chunk_text(
+                                            &text,
+                                            language.as_ref(),
+                                            &entry.path,
+                                        );
+                                        let symbols = chunking::symbols(
+                                            &text,
+                                            language.as_ref(),
+                                            &entry.path,
+                                        );
                                         let chunked_file = ChunkedFile {
-                                            chunks: chunking::chunk_text(
-                                                &text,
-                                                language.as_ref(),
-                                                &entry.path,
-                                            ),
+                                            lexical: LexicalFile::new(&text, &chunks, &symbols),
+                                            chunks,
                                             handle,
                                             path: entry.path,
                                             mtime: entry.mtime,
@@ -341,7 +434,7 @@ impl EmbeddingIndex {
 
                     if embedded_all_chunks {
                         embedded_files_tx
-                            .send((embedded_file, chunked_file.handle))
+                            .send((embedded_file, chunked_file.lexical, chunked_file.handle))
                             .await?;
                     }
                 }
@@ -358,17 +451,30 @@ impl EmbeddingIndex {
     fn persist_embeddings(
         &self,
         mut deleted_entry_ranges: channel::Receiver<(Bound<String>, Bound<String>)>,
-        embedded_files: channel::Receiver<(EmbeddedFile, IndexingEntryHandle)>,
+        embedded_files: channel::Receiver<(EmbeddedFile, LexicalFile, IndexingEntryHandle)>,
         cx: &AppContext,
     ) -> Task<Result<()>> {
         let db_connection = self.db_connection.clone();
         let db = self.db;
+        let ann_index = self.ann_index;
+        let lexical_index = self.lexical_index;
         cx.background_executor().spawn(async move {
             while let Some(deletion_range) = deleted_entry_ranges.next().await {
                 let mut txn = db_connection.write_txn()?;
                 let start = deletion_range.0.as_ref().map(|start| start.as_str());
                 let end = deletion_range.1.as_ref().map(|end| end.as_str());
                 log::debug!("deleting embeddings in range {:?}", &(start, end));
+                let deleted_files = db
+                    .range(&txn, &(start, end))?
+                    .map(|entry| {
+                        let (key, file) = entry?;
+                        Ok((key.to_string(), file))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                for (key, file) in &deleted_files {
+                    ann_index.remove_file(&mut txn, key, file)?;
+                    lexical_index.remove_file(&mut txn, key)?;
+                }
                 db.delete_range(&mut txn, &(start, end))?;
                 txn.commit()?;
             }
@@ -376,9 +482,14 @@ impl EmbeddingIndex {
             let mut embedded_files = embedded_files.chunks_timeout(4096, Duration::from_secs(2));
             while let Some(embedded_files) = embedded_files.next().await {
                 let mut txn = db_connection.write_txn()?;
-                for (file, _) in &embedded_files {
+                for (file, lexical_file, _) in &embedded_files {
                     log::debug!("saving embedding for file {:?}", file.path);
                     let key = db_key_for_path(&file.path);
+                    if let Some(old_file) = db.get(&txn, &key)? {
+                        ann_index.remove_file(&mut txn, &key, &old_file)?;
+                    }
+                    ann_index.insert_file(&mut txn, &key, file)?;
+                    lexical_index.insert_file(&mut txn, &key, lexical_file)?;
                     db.put(&mut txn, &key, file)?;
                 }
                 txn.commit()?;
@@ -387,6 +498,15 @@ impl EmbeddingIndex {
                 log::debug!("committed");
             }
 
+            let txn = db_connection
+                .read_txn()
+                .context("failed to create read transaction")?;
+            let needs_training = ann_index.needs_training(&txn)?;
+            drop(txn);
+            if needs_training {
+                ann_index.train(&db_connection)?;
+            }
+
             Ok(())
         })
     }
@@ -444,10 +564,11 @@ pub struct ChunkedFile {
     pub handle: IndexingEntryHandle,
     pub text: String,
     pub chunks: Vec<Chunk>,
+    pub lexical: LexicalFile,
 }
 
 pub struct EmbedFiles {
-    pub files: channel::Receiver<(EmbeddedFile, IndexingEntryHandle)>,
+    pub files: channel::Receiver<(EmbeddedFile, LexicalFile, IndexingEntryHandle)>,
     pub task: Task<Result<()>>,
 }
 
@@ -464,6 +585,6 @@ pub struct EmbeddedChunk {
     pub embedding: Embedding,
 }
 
-fn db_key_for_path(path: &Arc<Path>) -> String {
+pub(crate) fn db_key_for_path(path: &Arc<Path>) -> String {
     path.to_string_lossy().replace('/', "\0")
 }
@@ -0,0 +1,455 @@
+//! A BM25 index over the text of a worktree's chunks, so that searches find the chunks that
+//! mention the exact identifiers in the query, which embeddings tend to miss.
+
+use crate::chunking::Chunk;
+use anyhow::Result;
+use collections::{BTreeMap, HashMap};
+use heed::{
+    types::{DecodeIgnore, SerdeBincode, Str},
+    RoTxn, RwTxn,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const STATS_KEY: &str = "stats";
+/// How quickly repeated terms stop increasing a chunk's score.
+const K1: f32 = 1.2;
+/// How much a chunk's length is taken into account.
+const B: f32 = 0.75;
+/// Longer identifiers are unlikely to be searched for, and aren't indexed.
+const MAX_TERM_LEN: usize = 64;
+/// Terms found in more chunks than this are too common to tell the chunks apart, so searches
+/// stop reading their postings and ignore them.
+const MAX_TERM_CHUNKS: usize = 2_000;
+/// Words that are too common in queries and comments to be worth indexing.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "do", "does", "for", "from", "how", "in",
+    "is", "it", "of", "on", "or", "that", "the", "this", "to", "what", "when", "where", "which",
+    "with",
+];
+
+#[derive(Clone, Copy)]
+pub struct LexicalIndex {
+    postings_db: heed::Database<Str, SerdeBincode<FilePostings>>,
+    files_db: heed::Database<Str, SerdeBincode<IndexedFile>>,
+    stats_db: heed::Database<Str, SerdeBincode<LexicalStats>>,
+}
+
+/// The occurrences of a term in a file. They're keyed by the term and a hash of the file's
+/// key, as LMDB keys can't be longer than 511 bytes.
+#[derive(Serialize, Deserialize)]
+pub struct FilePostings {
+    key: String,
+    postings: Vec<TermPosting>,
+}
+
+/// The occurrences of a term in one of a file's chunks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TermPosting {
+    chunk_ix: u32,
+    count: u32,
+    chunk_len: u32,
+    /// Whether the term names one of the outline items in the chunk.
+    is_symbol: bool,
+}
+
+/// The terms of a file's chunks.
+#[derive(Clone, Debug, Default)]
+pub struct LexicalFile {
+    chunk_lens: Vec<u32>,
+    postings: BTreeMap<String, Vec<TermPosting>>,
+}
+
+/// What's needed to remove a file from the index.
+#[derive(Serialize, Deserialize)]
+pub struct IndexedFile {
+    chunk_lens: Vec<u32>,
+    terms: Vec<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct LexicalStats {
+    chunk_count: u64,
+    term_count: u64,
+}
+
+/// How well a chunk matches a query's terms.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LexicalMatch {
+    pub score: f32,
+    /// The number of the query's terms naming one of the chunk's outline items.
+    pub symbol_count: usize,
+}
+
+impl LexicalFile {
+    /// Indexes the terms in the file's chunks. `symbols` are the names of the file's outline
+    /// items along with their offsets.
+    pub fn new(text: &str, chunks: &[Chunk], symbols: &[(usize, String)]) -> Self {
+        let mut file = Self::default();
+        for (chunk_ix, chunk) in chunks.iter().enumerate() {
+            let Some(chunk_text) = text.get(chunk.range.clone()) else {
+                file.chunk_lens.push(0);
+                continue;
+            };
+
+            let mut counts = HashMap::<String, u32>::default();
+            let mut chunk_len = 0;
+            tokenize(chunk_text, |term| {
+                *counts.entry(term).or_default() += 1;
+                chunk_len += 1;
+            });
+            let mut symbol_terms = Vec::new();
+            for (offset, name) in symbols {
+                if chunk.range.contains(offset) {
+                    tokenize(name, |term| symbol_terms.push(term));
+                }
+            }
+
+            for (term, count) in counts {
+                let is_symbol = symbol_terms.contains(&term);
+                file.postings.entry(term).or_default().push(TermPosting {
+                    chunk_ix: chunk_ix as u32,
+                    count,
+                    chunk_len,
+                    is_symbol,
+                });
+            }
+            file.chunk_lens.push(chunk_len);
+        }
+        file
+    }
+}
+
+impl LexicalIndex {
+    pub fn new(
+        postings_db: heed::Database<Str, SerdeBincode<FilePostings>>,
+        files_db: heed::Database<Str, SerdeBincode<IndexedFile>>,
+        stats_db: heed::Database<Str, SerdeBincode<LexicalStats>>,
+    ) -> Self {
+        Self {
+            postings_db,
+            files_db,
+            stats_db,
+        }
+    }
+
+    /// Adds a file's terms to the index, replacing the ones it had.
+    pub fn insert_file(&self, txn: &mut RwTxn, key: &str, file: &LexicalFile) -> Result<()> {
+        self.remove_file(txn, key)?;
+
+        let mut stats = self.stats_db.get(txn, STATS_KEY)?.unwrap_or_default();
+        stats.chunk_count += file.chunk_lens.len() as u64;
+        stats.term_count += file.chunk_lens.iter().map(|len| *len as u64).sum::<u64>();
+        for (term, postings) in &file.postings {
+            self.postings_db.put(
+                txn,
+                &posting_key(term, key),
+                &FilePostings {
+                    key: key.to_string(),
+                    postings: postings.clone(),
+                },
+            )?;
+        }
+        self.files_db.put(
+            txn,
+            key,
+            &IndexedFile {
+                chunk_lens: file.chunk_lens.clone(),
+                terms: file.postings.keys().cloned().collect(),
+            },
+        )?;
+        self.stats_db.put(txn, STATS_KEY, &stats)?;
+        Ok(())
+    }
+
+    /// Adds a file's terms to the index, unless it was already indexed.
+    pub fn insert_missing_file(
+        &self,
+        txn: &mut RwTxn,
+        key: &str,
+        file: &LexicalFile,
+    ) -> Result<()> {
+        if self.files_db.get(txn, key)?.is_none() {
+            self.insert_file(txn, key, file)?;
+        }
+        Ok(())
+    }
+
+    pub fn remove_file(&self, txn: &mut RwTxn, key: &str) -> Result<()> {
+        let Some(file) = self.files_db.get(txn, key)? else {
+            return Ok(());
+        };
+
+        let mut stats = self.stats_db.get(txn, STATS_KEY)?.unwrap_or_default();
+        stats.chunk_count = stats
+            .chunk_count
+            .saturating_sub(file.chunk_lens.len() as u64);
+        stats.term_count = stats
+            .term_count
+            .saturating_sub(file.chunk_lens.iter().map(|len| *len as u64).sum());
+        for term in &file.terms {
+            self.postings_db.delete(txn, &posting_key(term, key))?;
+        }
+        self.files_db.delete(txn, key)?;
+        self.stats_db.put(txn, STATS_KEY, &stats)?;
+        Ok(())
+    }
+
+    /// Returns the keys of the files in the embedding database that aren't in the index.
+    pub fn missing_files<T>(
+        &self,
+        txn: &RoTxn,
+        embedding_db: heed::Database<Str, T>,
+    ) -> Result<Vec<String>> {
+        let embedding_db = embedding_db.remap_data_type::<DecodeIgnore>();
+        let files_db = self.files_db.remap_data_type::<DecodeIgnore>();
+        let mut missing = Vec::new();
+        for entry in embedding_db.iter(txn)? {
+            let (key, _) = entry?;
+            if files_db.get(txn, key)?.is_none() {
+                missing.push(key.to_string());
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Scores the chunks containing any of the query's terms with BM25, keyed by their file's
+    /// key and their index in the file. Terms found in too many chunks are ignored.
+    pub fn search(
+        &self,
+        txn: &RoTxn,
+        query: &str,
+    ) -> Result<HashMap<(String, usize), LexicalMatch>> {
+        let mut matches = HashMap::<(String, usize), LexicalMatch>::default();
+        let Some(stats) = self.stats_db.get(txn, STATS_KEY)? else {
+            return Ok(matches);
+        };
+        let chunk_count = stats.chunk_count as f32;
+        let average_chunk_len = stats.term_count as f32 / chunk_count.max(1.);
+
+        'terms: for term in query_terms(query) {
+            let prefix = format!("{term}\0");
+            let mut postings = Vec::new();
+            for entry in self.postings_db.prefix_iter(txn, &prefix)? {
+                let (_, file_postings) = entry?;
+                let key = file_postings.key;
+                postings.extend(
+                    file_postings
+                        .postings
+                        .into_iter()
+                        .map(|posting| (key.clone(), posting)),
+                );
+                if postings.len() > MAX_TERM_CHUNKS {
+                    continue 'terms;
+                }
+            }
+
+            let document_frequency = postings.len() as f32;
+            let idf =
+                (1. + (chunk_count - document_frequency + 0.5) / (document_frequency + 0.5)).ln();
+            for (key, posting) in postings {
+                let count = posting.count as f32;
+                let chunk_len = posting.chunk_len as f32;
+                let lexical_match = matches.entry((key, posting.chunk_ix as usize)).or_default();
+                lexical_match.score += idf * count * (K1 + 1.)
+                    / (count + K1 * (1. - B + B * chunk_len / average_chunk_len.max(1.)));
+                if posting.is_symbol {
+                    lexical_match.symbol_count += 1;
+                }
+            }
+        }
+        Ok(matches)
+    }
+}
+
+/// Returns the distinct terms of a query.
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    tokenize(query, |term| {
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    });
+    terms
+}
+
+fn posting_key(term: &str, key: &str) -> String {
+    format!("{term}\0{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Splits text into lowercase identifiers, along with the words they're made of, so that
+/// `SumTree::append` matches `sumtree`, `sum`, `tree` and `append`. Stop words are skipped.
+pub fn tokenize(text: &str, mut f: impl FnMut(String)) {
+    let mut emit = |term: String| {
+        if !STOP_WORDS.contains(&term.as_str()) {
+            f(term);
+        }
+    };
+    for identifier in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        if identifier.is_empty() || identifier.len() > MAX_TERM_LEN {
+            continue;
+        }
+
+        let words = words(identifier);
+        if words.len() > 1 {
+            for word in words {
+                emit(word.to_lowercase());
+            }
+        }
+        emit(identifier.to_lowercase());
+    }
+}
+
+/// Splits an identifier at underscores and changes of case, e.g. `HTTPServer_url` into
+/// `HTTP`, `Server` and `url`.
+fn words(identifier: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    for part in identifier.split('_').filter(|part| !part.is_empty()) {
+        let chars = part.char_indices().collect::<Vec<_>>();
+        let mut start = 0;
+        for (ix, (offset, char)) in chars.iter().enumerate().skip(1) {
+            let prev = chars[ix - 1].1;
+            let next_is_lowercase = chars
+                .get(ix + 1)
+                .map_or(false, |(_, next)| next.is_lowercase());
+            let is_boundary = char.is_uppercase()
+                && (prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_is_lowercase));
+            if is_boundary {
+                words.push(&part[start..*offset]);
+                start = *offset;
+            }
+        }
+        words.push(&part[start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(text: &str) -> Vec<String> {
+        let mut terms = Vec::new();
+        tokenize(text, |term| terms.push(term));
+        terms
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            terms("SumTree::append(HTTPServer_url, 42)"),
+            [
+                "sum",
+                "tree",
+                "sumtree",
+                "append",
+                "http",
+                "server",
+                "url",
+                "httpserver_url",
+                "42"
+            ]
+        );
+        assert_eq!(terms("How is the tree split?"), ["tree", "split"]);
+    }
+
+    #[test]
+    fn test_lexical_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_connection = unsafe {
+            heed::EnvOpenOptions::new()
+                .map_size(64 * 1024 * 1024)
+                .max_dbs(3)
+                .open(temp_dir.path())
+                .unwrap()
+        };
+        let mut txn = db_connection.write_txn().unwrap();
+        let index = LexicalIndex::new(
+            db_connection
+                .create_database(&mut txn, Some("postings"))
+                .unwrap(),
+            db_connection
+                .create_database(&mut txn, Some("files"))
+                .unwrap(),
+            db_connection
+                .create_database(&mut txn, Some("stats"))
+                .unwrap(),
+        );
+
+        let chunk = |range| Chunk {
+            range,
+            digest: Default::default(),
+        };
+        let definition = "impl SumTree {\n    fn append(&mut self) {}\n}\n";
+        index
+            .insert_file(
+                &mut txn,
+                "sum_tree.rs",
+                &LexicalFile::new(
+                    definition,
+                    &[chunk(0..definition.len())],
+                    &[(5, "SumTree".into()), (22, "append".into())],
+                ),
+            )
+            .unwrap();
+        let usage = "let mut tree = SumTree::new();\ntree.append();\nlet list = Vec::new();\n";
+        index
+            .insert_file(
+                &mut txn,
+                "usage.rs",
+                &LexicalFile::new(usage, &[chunk(0..31), chunk(31..usage.len())], &[]),
+            )
+            .unwrap();
+
+        let matches = index.search(&txn, "SumTree::append").unwrap();
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[&("sum_tree.rs".to_string(), 0)].symbol_count, 4);
+        assert_eq!(matches[&("usage.rs".to_string(), 0)].symbol_count, 0);
+        assert!(
+            matches[&("sum_tree.rs".to_string(), 0)].score
+                > matches[&("usage.rs".to_string(), 1)].score
+        );
+
+        // Keys longer than LMDB's limit can be indexed.
+        let long_key = format!("{}/long.rs", "dir/".repeat(200));
+        let text = "fn append_all() {}\n";
+        index
+            .insert_file(
+                &mut txn,
+                &long_key,
+                &LexicalFile::new(text, &[chunk(0..text.len())], &[]),
+            )
+            .unwrap();
+        let matches = index.search(&txn, "append_all").unwrap();
+        assert_eq!(matches.keys().collect::<Vec<_>>(), [&(long_key.clone(), 0)]);
+        index.remove_file(&mut txn, &long_key).unwrap();
+
+        // Terms found in too many chunks are ignored.
+        let line = "let common = 1;\n";
+        let text = line.repeat(MAX_TERM_CHUNKS + 1);
+        let chunks = (0..=MAX_TERM_CHUNKS)
+            .map(|ix| chunk(ix * line.len()..(ix + 1) * line.len()))
+            .collect::<Vec<_>>();
+        index
+            .insert_file(
+                &mut txn,
+                "common.rs",
+                &LexicalFile::new(&text, &chunks, &[]),
+            )
+            .unwrap();
+        assert!(index.search(&txn, "common").unwrap().is_empty());
+        index.remove_file(&mut txn, "common.rs").unwrap();
+
+        index.remove_file(&mut txn, "sum_tree.rs").unwrap();
+        let matches = index.search(&txn, "SumTree::append").unwrap();
+        assert_eq!(
+            matches
+                .keys()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            ["usage.rs", "usage.rs"]
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use crate::{
     embedding::{EmbeddingProvider, TextToEmbed},
+    embedding_index::db_key_for_path,
+    lexical_index::{self, LexicalMatch},
     summary_index::FileSummary,
     worktree_index::{WorktreeIndex, WorktreeIndexHandle},
+    SemanticIndexSettings,
 };
 use anyhow::{anyhow, Context, Result};
-use collections::HashMap;
+use collections::{HashMap, HashSet};
 use fs::Fs;
 use futures::{stream::StreamExt, FutureExt};
 use gpui::{
@@ -14,10 +17,23 @@ use language::LanguageRegistry;
 use log;
 use project::{Project, Worktree, WorktreeId};
 use serde::{Deserialize, Serialize};
+use settings::Settings;
 use smol::channel;
-use std::{cmp::Ordering, future::Future, num::NonZeroUsize, ops::Range, path::Path, sync::Arc};
+use std::{
+    cmp::Ordering,
+    future::Future,
+    num::NonZeroUsize,
+    ops::Range,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 use util::ResultExt;
 
+/// The BM25 score at which matching the query's terms adds half of the lexical weight.
+const LEXICAL_SATURATION: f32 = 5.;
+const RECENCY_HALF_LIFE: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[derive(Debug)]
 pub struct SearchResult {
     pub worktree: Model<Worktree>,
@@ -216,47 +232,10 @@ impl ProjectIndex {
         limit: usize,
         cx: &AppContext,
     ) -> Task<Result<Vec<SearchResult>>> {
-        let (chunks_tx, chunks_rx) = channel::bounded(1024);
-        let mut worktree_scan_tasks = Vec::new();
-        for worktree_index in self.worktree_indices.values() {
-            let worktree_index = worktree_index.clone();
-            let chunks_tx = chunks_tx.clone();
-            worktree_scan_tasks.push(cx.spawn(|cx| async move {
-                let index = match worktree_index {
-                    WorktreeIndexHandle::Loading { index } => {
-                        index.clone().await.map_err(|error| anyhow!(error))?
-                    }
-                    WorktreeIndexHandle::Loaded { index } => index.clone(),
-                };
-
-                index
-                    .read_with(&cx, |index, cx| {
-                        let worktree_id = index.worktree().read(cx).id();
-                        let db_connection = index.db_connection().clone();
-                        let db = *index.embedding_index().db();
-                        cx.background_executor().spawn(async move {
-                            let txn = db_connection
-                                .read_txn()
-                                .context("failed to create read transaction")?;
-                            let db_entries = db.iter(&txn).context("failed to iterate database")?;
-                            for db_entry in db_entries {
-                                let (_key, db_embedded_file) = db_entry?;
-                                for chunk in db_embedded_file.chunks {
-                                    chunks_tx
-                                        .send((worktree_id, db_embedded_file.path.clone(), chunk))
-                                        .await?;
-                                }
-                            }
-                            anyhow::Ok(())
-                        })
-                    })?
-                    .await
-            }));
-        }
-        drop(chunks_tx);
-
+        let worktree_indices = self.worktree_indices.values().cloned().collect::<Vec<_>>();
         let project = self.project.clone();
         let embedding_provider = self.embedding_provider.clone();
+        let weights = *SemanticIndexSettings::get_global(cx);
         cx.spawn(|cx| async move {
             #[cfg(debug_assertions)]
             let embedding_query_start = std::time::Instant::now();
@@ -270,6 +249,127 @@ impl ProjectIndex {
                 .next()
                 .ok_or_else(|| anyhow!("no embedding for query"))?;
 
+            let query_term_count = lexical_index::query_terms(&query).len();
+            let (chunks_tx, chunks_rx) = channel::bounded(1024);
+            let mut worktree_scan_tasks = Vec::new();
+            for worktree_index in worktree_indices {
+                let chunks_tx = chunks_tx.clone();
+                let query = query.clone();
+                let query_embedding = query_embedding.clone();
+                worktree_scan_tasks.push(cx.spawn(|cx| async move {
+                    let index = match worktree_index {
+                        WorktreeIndexHandle::Loading { index } => {
+                            index.await.map_err(|error| anyhow!(error))?
+                        }
+                        WorktreeIndexHandle::Loaded { index } => index,
+                    };
+
+                    index
+                        .read_with(&cx, |index, cx| {
+                            let worktree = index.worktree().read(cx).snapshot();
+                            let worktree_id = worktree.id();
+                            let db_connection = index.db_connection().clone();
+                            let db = *index.embedding_index().db();
+                            let ann_index = *index.embedding_index().ann_index();
+                            let lexical_index = *index.embedding_index().lexical_index();
+                            cx.background_executor().spawn(async move {
+                                let txn = db_connection
+                                    .read_txn()
+                                    .context("failed to create read transaction")?;
+                                let lexical_matches = lexical_index.search(&txn, &query)?;
+                                let now = SystemTime::now();
+                                let boost_for = |key: &str, chunk_ix: usize, path: &Path| {
+                                    let lexical_match = lexical_matches
+                                        .get(&(key.to_string(), chunk_ix))
+                                        .copied()
+                                        .unwrap_or_default();
+                                    let mtime =
+                                        worktree.entry_for_path(path).and_then(|entry| entry.mtime);
+                                    score_boost(
+                                        &weights,
+                                        lexical_match,
+                                        query_term_count,
+                                        mtime,
+                                        now,
+                                    )
+                                };
+                                let mut sent_chunks = HashSet::default();
+
+                                // The chunks that best match the query's terms are candidates,
+                                // even if their embeddings aren't similar to the query's.
+                                let mut lexical_candidates = lexical_matches
+                                    .iter()
+                                    .map(|((key, chunk_ix), lexical_match)| {
+                                        (key.as_str(), *chunk_ix, lexical_match.score)
+                                    })
+                                    .collect::<Vec<_>>();
+                                lexical_candidates.sort_unstable_by(|a, b| b.2.total_cmp(&a.2));
+                                lexical_candidates.truncate(limit);
+                                for (key, chunk_ix, _) in lexical_candidates {
+                                    let Some(file) = db.get(&txn, key)? else {
+                                        continue;
+                                    };
+                                    let Some(chunk) = file.chunks.get(chunk_ix) else {
+                                        continue;
+                                    };
+                                    let boost = boost_for(key, chunk_ix, &file.path);
+                                    sent_chunks.insert((key.to_string(), chunk_ix));
+                                    chunks_tx
+                                        .send((
+                                            worktree_id,
+                                            file.path.clone(),
+                                            chunk.clone(),
+                                            boost,
+                                        ))
+                                        .await?;
+                                }
+
+                                // Large worktrees only need the chunks nearest to the query
+                                // to be scored.
+                                if let Some(chunks) =
+                                    ann_index.search(&txn, &query_embedding, limit)?
+                                {
+                                    for (path, chunk_ix, chunk) in chunks {
+                                        let key = db_key_for_path(&path);
+                                        if sent_chunks.contains(&(key.clone(), chunk_ix)) {
+                                            continue;
+                                        }
+                                        let boost = boost_for(&key, chunk_ix, &path);
+                                        chunks_tx.send((worktree_id, path, chunk, boost)).await?;
+                                    }
+                                    return anyhow::Ok(());
+                                }
+
+                                let db_entries =
+                                    db.iter(&txn).context("failed to iterate database")?;
+                                for db_entry in db_entries {
+                                    let (key, db_embedded_file) = db_entry?;
+                                    for (chunk_ix, chunk) in
+                                        db_embedded_file.chunks.into_iter().enumerate()
+                                    {
+                                        if sent_chunks.contains(&(key.to_string(), chunk_ix)) {
+                                            continue;
+                                        }
+                                        let boost =
+                                            boost_for(key, chunk_ix, &db_embedded_file.path);
+                                        chunks_tx
+                                            .send((
+                                                worktree_id,
+                                                db_embedded_file.path.clone(),
+                                                chunk,
+                                                boost,
+                                            ))
+                                            .await?;
+                                    }
+                                }
+                                anyhow::Ok(())
+                            })
+                        })?
+                        .await
+                }));
+            }
+            drop(chunks_tx);
+
             let mut results_by_worker = Vec::new();
             for _ in 0..cx.background_executor().num_cpus() {
                 results_by_worker.push(Vec::<WorktreeSearchResult>::new());
@@ -282,8 +382,9 @@ impl ProjectIndex {
                 .scoped(|cx| {
                     for results in results_by_worker.iter_mut() {
                         cx.spawn(async {
-                            while let Ok((worktree_id, path, chunk)) = chunks_rx.recv().await {
-                                let score = chunk.embedding.similarity(&query_embedding);
+                            while let Ok((worktree_id, path, chunk, boost)) = chunks_rx.recv().await
+                            {
+                                let score = chunk.embedding.similarity(&query_embedding) + boost;
                                 let ix = match results.binary_search_by(|probe| {
                                     score.partial_cmp(&probe.score).unwrap_or(Ordering::Equal)
                                 }) {
@@ -521,3 +622,101 @@ impl ProjectIndex {
 }
 
 impl EventEmitter<Status> for ProjectIndex {}
+
+/// Adds to a chunk's score for matching the query's terms, defining symbols named in the query,
+/// and belonging to a recently edited file.
+fn score_boost(
+    weights: &SemanticIndexSettings,
+    lexical_match: LexicalMatch,
+    query_term_count: usize,
+    mtime: Option<SystemTime>,
+    now: SystemTime,
+) -> f32 {
+    let lexical = lexical_match.score / (lexical_match.score + LEXICAL_SATURATION);
+    let symbols = lexical_match.symbol_count as f32 / query_term_count.max(1) as f32;
+    let recency = mtime
+        .and_then(|mtime| now.duration_since(mtime).ok())
+        .map_or(0., |age| {
+            0.5f32.powf(age.as_secs_f32() / RECENCY_HALF_LIFE.as_secs_f32())
+        });
+    weights.lexical_weight * lexical
+        + weights.symbol_weight * symbols
+        + weights.recency_weight * recency
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunking::Chunk,
+        lexical_index::{LexicalFile, LexicalIndex},
+    };
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    fn test_fused_ranking(cx: &mut AppContext) {
+        let store = SettingsStore::test(cx);
+        cx.set_global(store);
+        SemanticIndexSettings::register(cx);
+        let weights = *SemanticIndexSettings::get_global(cx);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_connection = unsafe {
+            heed::EnvOpenOptions::new()
+                .map_size(64 * 1024 * 1024)
+                .max_dbs(3)
+                .open(temp_dir.path())
+                .unwrap()
+        };
+        let mut txn = db_connection.write_txn().unwrap();
+        let index = LexicalIndex::new(
+            db_connection
+                .create_database(&mut txn, Some("postings"))
+                .unwrap(),
+            db_connection
+                .create_database(&mut txn, Some("files"))
+                .unwrap(),
+            db_connection
+                .create_database(&mut txn, Some("stats"))
+                .unwrap(),
+        );
+
+        let definition = "fn parse_config_file(path: &Path) -> Config {\n    todo!()\n}\n";
+        let explanation = "// Reads the user's preferences from disk.\n";
+        let text = format!("{definition}{explanation}");
+        let chunks = [
+            Chunk {
+                range: 0..definition.len(),
+                digest: Default::default(),
+            },
+            Chunk {
+                range: definition.len()..text.len(),
+                digest: Default::default(),
+            },
+        ];
+        index
+            .insert_file(
+                &mut txn,
+                "config.rs",
+                &LexicalFile::new(&text, &chunks, &[(3, "parse_config_file".into())]),
+            )
+            .unwrap();
+
+        let query = "parse_config_file";
+        let matches = index.search(&txn, query).unwrap();
+        let query_term_count = lexical_index::query_terms(query).len();
+        let now = SystemTime::now();
+        let score = |chunk_ix: usize, similarity: f32| {
+            let lexical_match = matches
+                .get(&("config.rs".to_string(), chunk_ix))
+                .copied()
+                .unwrap_or_default();
+            similarity + score_boost(&weights, lexical_match, query_term_count, None, now)
+        };
+
+        // The chunk defining the identifier named in the query ranks first, even though the
+        // other chunk's embedding is more similar to the query's.
+        assert!(score(0, 0.5) > score(1, 0.75));
+        assert_eq!(score(1, 0.75), 0.75);
+    }
+}
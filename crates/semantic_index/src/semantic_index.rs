@@ -1,9 +1,12 @@
+mod ann_index;
 mod chunking;
 mod embedding;
 mod embedding_index;
 mod indexing;
+mod lexical_index;
 mod project_index;
 mod project_index_debug_view;
+mod semantic_index_settings;
 mod summary_backlog;
 mod summary_index;
 mod worktree_index;
@@ -13,12 +16,14 @@ use collections::HashMap;
 use gpui::{AppContext, AsyncAppContext, BorrowAppContext, Context, Global, Model, WeakModel};
 use project::Project;
 use project_index::ProjectIndex;
+use settings::Settings;
 use std::{path::PathBuf, sync::Arc};
 use ui::ViewContext;
 use workspace::Workspace;
 
 pub use embedding::*;
 pub use project_index_debug_view::ProjectIndexDebugView;
+pub use semantic_index_settings::SemanticIndexSettings;
 pub use summary_index::FileSummary;
 
 pub struct SemanticDb {
@@ -50,6 +55,7 @@ impl SemanticDb {
             .context("opening database connection")?;
 
         cx.update(|cx| {
+            SemanticIndexSettings::register(cx);
             cx.observe_new_views(
                 |workspace: &mut Workspace, cx: &mut ViewContext<Workspace>| {
                     let project = workspace.project().clone();
@@ -334,6 +340,7 @@ mod tests {
                 mtime: None,
                 handle: indexing_entries.insert(ProjectEntryId::from_proto(0)),
                 text: "abcdefghijklmnop".to_string(),
+                lexical: Default::default(),
                 chunks: [0..4, 4..8, 8..12, 12..16]
                     .into_iter()
                     .map(|range| Chunk {
@@ -349,6 +356,7 @@ mod tests {
                 mtime: None,
                 handle: indexing_entries.insert(ProjectEntryId::from_proto(1)),
                 text: "qrstuvwxyz".to_string(),
+                lexical: Default::default(),
                 chunks: [0..4, 4..8, 8..10]
                     .into_iter()
                     .map(|range| Chunk {
@@ -366,7 +374,7 @@ mod tests {
 
         let mut embedded_files_rx = embed_files_task.files;
         let mut embedded_files = Vec::new();
-        while let Some((embedded_file, _)) = embedded_files_rx.next().await {
+        while let Some((embedded_file, _, _)) = embedded_files_rx.next().await {
             embedded_files.push(embedded_file);
         }
 
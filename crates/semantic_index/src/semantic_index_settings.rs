@@ -0,0 +1,42 @@
+use gpui::AppContext;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::{Settings, SettingsSources};
+
+/// How much each signal adds to a search result's score, on top of the similarity of its
+/// embedding to the query's.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SemanticIndexSettings {
+    pub lexical_weight: f32,
+    pub symbol_weight: f32,
+    pub recency_weight: f32,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct SemanticIndexSettingsContent {
+    /// How much matching the query's words adds to a result's score.
+    ///
+    /// Default: 0.5
+    pub lexical_weight: Option<f32>,
+    /// How much defining symbols named in the query adds to a result's score.
+    ///
+    /// Default: 0.3
+    pub symbol_weight: Option<f32>,
+    /// How much belonging to a file edited just now adds to a result's score.
+    ///
+    /// Default: 0.05
+    pub recency_weight: Option<f32>,
+}
+
+impl Settings for SemanticIndexSettings {
+    const KEY: Option<&'static str> = Some("semantic_index");
+
+    type FileContent = SemanticIndexSettingsContent;
+
+    fn load(
+        sources: SettingsSources<Self::FileContent>,
+        _: &mut AppContext,
+    ) -> anyhow::Result<Self> {
+        sources.json_merge()
+    }
+}
@@ -1,6 +1,8 @@
+use crate::ann_index::AnnIndex;
 use crate::embedding::EmbeddingProvider;
 use crate::embedding_index::EmbeddingIndex;
 use crate::indexing::IndexingEntrySet;
+use crate::lexical_index::LexicalIndex;
 use crate::summary_index::SummaryIndex;
 use anyhow::Result;
 use feature_flags::{AutoCommand, FeatureFlagAppExt};
@@ -63,12 +65,54 @@ impl WorktreeIndex {
                         let embedding_index = {
                             let db_name = worktree_abs_path.to_string_lossy();
                             let db = db_connection.create_database(&mut txn, Some(&db_name))?;
+                            let ann_index = {
+                                // Prepend something that wouldn't be found at the beginning of an
+                                // absolute path, so we don't get db key namespace conflicts with
+                                // embeddings, which use the abs path as a key.
+                                let metadata_db_name =
+                                    format!("ann-{}", worktree_abs_path.to_string_lossy());
+                                let postings_db_name =
+                                    format!("ann-postings-{}", worktree_abs_path.to_string_lossy());
+                                AnnIndex::new(
+                                    db,
+                                    db_connection
+                                        .create_database(&mut txn, Some(&metadata_db_name))?,
+                                    db_connection
+                                        .create_database(&mut txn, Some(&postings_db_name))?,
+                                )
+                            };
+                            let lexical_index = {
+                                // The databases are versioned, so that they're rebuilt when
+                                // the format of the postings changes.
+                                let postings_db_name = format!(
+                                    "lexical-postings-1-{}",
+                                    worktree_abs_path.to_string_lossy()
+                                );
+                                let files_db_name = format!(
+                                    "lexical-files-1-{}",
+                                    worktree_abs_path.to_string_lossy()
+                                );
+                                let stats_db_name = format!(
+                                    "lexical-stats-1-{}",
+                                    worktree_abs_path.to_string_lossy()
+                                );
+                                LexicalIndex::new(
+                                    db_connection
+                                        .create_database(&mut txn, Some(&postings_db_name))?,
+                                    db_connection
+                                        .create_database(&mut txn, Some(&files_db_name))?,
+                                    db_connection
+                                        .create_database(&mut txn, Some(&stats_db_name))?,
+                                )
+                            };
 
                             EmbeddingIndex::new(
                                 worktree_for_index,
                                 embedding_fs,
                                 db_connection.clone(),
                                 db,
+                                ann_index,
+                                lexical_index,
                                 language_registry,
                                 embedding_provider,
                                 Arc::clone(&entries_being_indexed),
@@ -1250,6 +1250,7 @@ impl SearchableItem for TerminalView {
                 .unwrap()),
             ),
             SearchQuery::Regex { .. } => regex_search_for_query(&query),
+            SearchQuery::Structural { .. } => None,
         };
 
         if let Some(s) = searcher {
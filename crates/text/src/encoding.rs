@@ -0,0 +1,226 @@
+use anyhow::{anyhow, Result};
+use encoding_rs::DecoderResult;
+use std::{borrow::Cow, fmt};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// The character encoding of a file on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+    ShiftJis,
+    Gbk,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 7] = [
+        Encoding::Utf8,
+        Encoding::Utf8Bom,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Windows1252,
+        Encoding::ShiftJis,
+        Encoding::Gbk,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16 LE",
+            Encoding::Utf16Be => "UTF-16 BE",
+            Encoding::Windows1252 => "Windows-1252",
+            Encoding::ShiftJis => "Shift JIS",
+            Encoding::Gbk => "GBK",
+        }
+    }
+
+    /// Detects the encoding of the given file contents, returning `None` if they don't
+    /// appear to be text.
+    ///
+    /// UTF-16 is only detected when the contents start with a byte order mark. Contents that
+    /// aren't valid UTF-8 are decoded as Shift JIS or GBK if they look like Japanese or Chinese
+    /// text, and as Windows-1252 (a superset of Latin-1) otherwise.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(UTF8_BOM) {
+            return Some(Encoding::Utf8Bom);
+        } else if bytes.starts_with(UTF16_LE_BOM) {
+            return Some(Encoding::Utf16Le);
+        } else if bytes.starts_with(UTF16_BE_BOM) {
+            return Some(Encoding::Utf16Be);
+        } else if std::str::from_utf8(bytes).is_ok() {
+            return Some(Encoding::Utf8);
+        } else if bytes.contains(&0) {
+            return None;
+        }
+
+        let cjk_score = |encoding: Encoding| {
+            let text = encoding.decode(bytes).ok()?;
+            let mut score = 0_isize;
+            let mut previous_weight = None;
+            for c in text.chars() {
+                let weight = match c {
+                    // Hiragana and katakana only appear in Japanese.
+                    '\u{3040}'..='\u{30FF}' if encoding == Encoding::ShiftJis => Some(2),
+                    // CJK punctuation, ideographs and full-width forms.
+                    '\u{3000}'..='\u{303F}' | '\u{4E00}'..='\u{9FFF}' => Some(1),
+                    '\u{FF01}'..='\u{FF60}' => Some(1),
+                    c if !c.is_ascii() => {
+                        score -= 1;
+                        None
+                    }
+                    _ => None,
+                };
+                // Japanese and Chinese characters appear next to each other, whereas the
+                // accented letters of Latin-1 text decode to isolated ones.
+                if let (Some(previous_weight), Some(weight)) = (previous_weight, weight) {
+                    score += previous_weight.max(weight);
+                }
+                previous_weight = weight;
+            }
+            Some(score).filter(|score| *score > 0)
+        };
+        let shift_jis_score = cjk_score(Encoding::ShiftJis);
+        let gbk_score = cjk_score(Encoding::Gbk);
+        Some(match (shift_jis_score, gbk_score) {
+            (Some(shift_jis_score), Some(gbk_score)) if gbk_score > shift_jis_score => {
+                Encoding::Gbk
+            }
+            (Some(_), _) => Encoding::ShiftJis,
+            (None, Some(_)) => Encoding::Gbk,
+            (None, None) => Encoding::Windows1252,
+        })
+    }
+
+    /// Like [`Encoding::detect`], but for the first bytes of file contents that may end in the
+    /// middle of a character.
+    pub fn detect_prefix(bytes: &[u8]) -> Option<Self> {
+        match std::str::from_utf8(bytes) {
+            Err(error) if error.error_len().is_none() => {
+                Self::detect(&bytes[..error.valid_up_to()])
+            }
+            _ => Self::detect(bytes),
+        }
+    }
+
+    /// Decodes the given file contents, skipping the byte order mark if this encoding has one.
+    /// Returns an error if the contents aren't valid in this encoding.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String> {
+        let decoded: Option<Cow<str>> = match self {
+            Encoding::Utf8 => std::str::from_utf8(bytes).ok().map(Cow::Borrowed),
+            Encoding::Utf8Bom => std::str::from_utf8(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes))
+                .ok()
+                .map(Cow::Borrowed),
+            Encoding::Utf16Le => encoding_rs::UTF_16LE
+                .decode_without_bom_handling_and_without_replacement(
+                    bytes.strip_prefix(UTF16_LE_BOM).unwrap_or(bytes),
+                ),
+            Encoding::Utf16Be => encoding_rs::UTF_16BE
+                .decode_without_bom_handling_and_without_replacement(
+                    bytes.strip_prefix(UTF16_BE_BOM).unwrap_or(bytes),
+                ),
+            Encoding::Windows1252 => {
+                encoding_rs::WINDOWS_1252.decode_without_bom_handling_and_without_replacement(bytes)
+            }
+            Encoding::ShiftJis => {
+                encoding_rs::SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes)
+            }
+            Encoding::Gbk => {
+                encoding_rs::GBK.decode_without_bom_handling_and_without_replacement(bytes)
+            }
+        };
+        decoded
+            .map(|text| text.into_owned())
+            .ok_or_else(|| anyhow!("contents are not valid {}", self.name()))
+    }
+
+    /// Returns a decoder for file contents that are read one chunk at a time.
+    pub fn chunk_decoder(&self) -> ChunkDecoder {
+        let decoder = match self {
+            Encoding::Utf8 => encoding_rs::UTF_8.new_decoder_without_bom_handling(),
+            Encoding::Utf8Bom => encoding_rs::UTF_8.new_decoder_with_bom_removal(),
+            Encoding::Utf16Le => encoding_rs::UTF_16LE.new_decoder_with_bom_removal(),
+            Encoding::Utf16Be => encoding_rs::UTF_16BE.new_decoder_with_bom_removal(),
+            Encoding::Windows1252 => encoding_rs::WINDOWS_1252.new_decoder_without_bom_handling(),
+            Encoding::ShiftJis => encoding_rs::SHIFT_JIS.new_decoder_without_bom_handling(),
+            Encoding::Gbk => encoding_rs::GBK.new_decoder_without_bom_handling(),
+        };
+        ChunkDecoder {
+            encoding: *self,
+            decoder,
+        }
+    }
+
+    /// Encodes the given text, prefixed with a byte order mark if this encoding has one.
+    /// Returns an error if the text contains characters this encoding can't represent.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let legacy_encoding = match self {
+            Encoding::Utf8 => return Ok(text.as_bytes().to_vec()),
+            Encoding::Utf8Bom => return Ok([UTF8_BOM, text.as_bytes()].concat()),
+            Encoding::Utf16Le => {
+                let mut bytes = UTF16_LE_BOM.to_vec();
+                bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+                return Ok(bytes);
+            }
+            Encoding::Utf16Be => {
+                let mut bytes = UTF16_BE_BOM.to_vec();
+                bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+                return Ok(bytes);
+            }
+            Encoding::Windows1252 => encoding_rs::WINDOWS_1252,
+            Encoding::ShiftJis => encoding_rs::SHIFT_JIS,
+            Encoding::Gbk => encoding_rs::GBK,
+        };
+
+        let (bytes, _, had_unmappable_characters) = legacy_encoding.encode(text);
+        if had_unmappable_characters {
+            Err(anyhow!(
+                "text contains characters that can't be encoded as {}",
+                self.name()
+            ))
+        } else {
+            Ok(bytes.into_owned())
+        }
+    }
+}
+
+/// Decodes file contents that are read one chunk at a time, carrying the bytes of a character
+/// that's split between two chunks over to the next one.
+pub struct ChunkDecoder {
+    encoding: Encoding,
+    decoder: encoding_rs::Decoder,
+}
+
+impl ChunkDecoder {
+    /// Decodes the next chunk of the contents. Returns an error if they aren't valid in the
+    /// decoder's encoding, or if the `last` chunk ends in the middle of a character.
+    pub fn decode(&mut self, bytes: &[u8], last: bool) -> Result<String> {
+        let capacity = self
+            .decoder
+            .max_utf8_buffer_length_without_replacement(bytes.len())
+            .ok_or_else(|| anyhow!("chunk of {} bytes is too large", bytes.len()))?;
+        let mut text = String::with_capacity(capacity);
+        let (result, _) = self
+            .decoder
+            .decode_to_string_without_replacement(bytes, &mut text, last);
+        match result {
+            DecoderResult::InputEmpty => Ok(text),
+            DecoderResult::Malformed(..) | DecoderResult::OutputFull => {
+                Err(anyhow!("contents are not valid {}", self.encoding.name()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -176,6 +176,16 @@ fn test_line_endings() {
         LineEnding::detect(&"abcd\r\n".repeat(1000)),
         LineEnding::Windows
     );
+    assert_eq!(
+        LineEnding::detect(&format!("{}\r\n", "a".repeat(5000))),
+        LineEnding::Windows
+    );
+    assert_eq!(LineEnding::try_detect("abcd"), None);
+
+    assert!(!LineEnding::is_mixed("one\ntwo\nthree"));
+    assert!(!LineEnding::is_mixed("one\r\ntwo\r\n"));
+    assert!(LineEnding::is_mixed("one\r\ntwo\nthree"));
+    assert!(LineEnding::is_mixed("one\rtwo\r\nthree"));
 
     let mut buffer = Buffer::new(0, BufferId::new(1).unwrap(), "one\r\ntwo\rthree".into());
     assert_eq!(buffer.text(), "one\ntwo\nthree");
@@ -189,6 +199,48 @@ fn test_line_endings() {
     buffer.check_invariants();
 }
 
+#[test]
+fn test_encodings() {
+    let japanese = b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\x81A\x90\xa2\x8aE";
+    let chinese = b"\xc4\xe3\xba\xc3\xa3\xac\xca\xc0\xbd\xe7";
+    let latin = b"Caf\xe9 cr\xe8me";
+    let cases: [(&[u8], Encoding, &str); 7] = [
+        ("héllo".as_bytes(), Encoding::Utf8, "héllo"),
+        (b"\xEF\xBB\xBFabc", Encoding::Utf8Bom, "abc"),
+        (b"\xFF\xFEa\x00\xE9\x00", Encoding::Utf16Le, "aé"),
+        (b"\xFE\xFF\x00a\x00\xE9", Encoding::Utf16Be, "aé"),
+        (latin, Encoding::Windows1252, "Café crème"),
+        (japanese, Encoding::ShiftJis, "こんにちは、世界"),
+        (chinese, Encoding::Gbk, "你好，世界"),
+    ];
+    for (bytes, encoding, text) in cases {
+        assert_eq!(Encoding::detect(bytes), Some(encoding));
+        assert_eq!(encoding.decode(bytes).unwrap(), text);
+        assert_eq!(encoding.encode(text).unwrap(), bytes);
+
+        // Decoding one byte at a time splits characters and byte order marks across chunks.
+        let mut decoder = encoding.chunk_decoder();
+        let mut decoded = String::new();
+        for (ix, byte) in bytes.iter().enumerate() {
+            let last = ix == bytes.len() - 1;
+            decoded.push_str(&decoder.decode(&[*byte], last).unwrap());
+        }
+        assert_eq!(decoded, text);
+    }
+
+    // A prefix of UTF-8 contents is still UTF-8 when it ends in the middle of a character,
+    // but the contents can't end there.
+    let prefix = &"héllo".as_bytes()[..2];
+    assert_eq!(Encoding::detect_prefix(prefix), Some(Encoding::Utf8));
+    assert!(Encoding::Utf8.chunk_decoder().decode(prefix, true).is_err());
+
+    // Invalid UTF-8 containing null bytes isn't text.
+    assert_eq!(Encoding::detect(b"\x00\x01\xFF\xFE\xFD"), None);
+
+    assert!(Encoding::Utf8.decode(latin).is_err());
+    assert!(Encoding::ShiftJis.encode("é").is_err());
+}
+
 #[test]
 fn test_line_len() {
     let mut buffer = Buffer::new(0, BufferId::new(1).unwrap(), "".into());
@@ -1,4 +1,5 @@
 mod anchor;
+mod encoding;
 pub mod locator;
 #[cfg(any(test, feature = "test-support"))]
 pub mod network;
@@ -14,6 +15,7 @@ pub use anchor::*;
 use anyhow::{anyhow, Context as _, Result};
 pub use clock::ReplicaId;
 use collections::{HashMap, HashSet};
+pub use encoding::{ChunkDecoder, Encoding};
 use locator::Locator;
 use operation_queue::OperationQueue;
 pub use patch::Patch;
@@ -133,6 +135,10 @@ impl HistoryEntry {
     pub fn transaction_id(&self) -> TransactionId {
         self.transaction.id
     }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
 }
 
 struct History {
@@ -1305,6 +1311,11 @@ impl Buffer {
         self.history.redo_stack.last()
     }
 
+    /// Returns the entries of the undo stack, oldest first.
+    pub fn undo_stack(&self) -> &[HistoryEntry] {
+        &self.history.undo_stack
+    }
+
     pub fn start_transaction(&mut self) -> Option<TransactionId> {
         self.start_transaction_at(Instant::now())
     }
@@ -1382,6 +1393,23 @@ impl Buffer {
         self.history.forget(transaction_id);
     }
 
+    /// Moves a transaction from the undo stack to the redo stack without undoing it, so
+    /// that redoing it undoes its edits.
+    pub fn move_to_redo_stack(&mut self, transaction_id: TransactionId) -> bool {
+        self.history.remove_from_undo(transaction_id).is_some()
+    }
+
+    /// Removes the entries of the redo stack, oldest first.
+    pub fn take_redo_stack(&mut self) -> Vec<HistoryEntry> {
+        std::mem::take(&mut self.history.redo_stack)
+    }
+
+    /// Places the given entries at the bottom of the redo stack.
+    pub fn restore_redo_stack(&mut self, mut entries: Vec<HistoryEntry>) {
+        entries.append(&mut self.history.redo_stack);
+        self.history.redo_stack = entries;
+    }
+
     pub fn merge_transactions(&mut self, transaction: TransactionId, destination: TransactionId) {
         self.history.merge_transactions(transaction, destination);
     }
@@ -3011,21 +3039,43 @@ impl LineEnding {
         }
     }
 
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineEnding::Unix => "LF",
+            LineEnding::Windows => "CRLF",
+        }
+    }
+
     pub fn detect(text: &str) -> Self {
-        let mut max_ix = cmp::min(text.len(), 1000);
-        while !text.is_char_boundary(max_ix) {
-            max_ix -= 1;
+        Self::try_detect(text).unwrap_or_default()
+    }
+
+    /// Returns the line ending of the first line in the text, or `None` if it has a single line.
+    pub fn try_detect(text: &str) -> Option<Self> {
+        let ix = text.find('\n')?;
+        if ix > 0 && text.as_bytes()[ix - 1] == b'\r' {
+            Some(Self::Windows)
+        } else {
+            Some(Self::Unix)
         }
+    }
 
-        if let Some(ix) = text[..max_ix].find(['\n']) {
-            if ix > 0 && text.as_bytes()[ix - 1] == b'\r' {
-                Self::Windows
-            } else {
-                Self::Unix
+    /// Returns whether the text's lines are terminated by different kinds of line separators.
+    pub fn is_mixed(text: &str) -> bool {
+        let bytes = text.as_bytes();
+        let mut first_separator = None;
+        for (ix, _) in text.match_indices(['\r', '\n']) {
+            let separator = match bytes[ix] {
+                b'\n' if ix > 0 && bytes[ix - 1] == b'\r' => continue,
+                b'\n' => "\n",
+                _ if bytes.get(ix + 1) == Some(&b'\n') => "\r\n",
+                _ => "\r",
+            };
+            if *first_separator.get_or_insert(separator) != separator {
+                return true;
             }
-        } else {
-            Self::default()
         }
+        false
     }
 
     pub fn normalize(text: &mut String) {
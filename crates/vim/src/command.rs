@@ -15,6 +15,8 @@ use util::ResultExt;
 use workspace::{notifications::NotifyResultExt, SaveIntent};
 
 use crate::{
+    helix::parse_helix_regex_command,
+    mapping::{parse_map_command, AddMappings, VimMappings},
     motion::{EndOfDocument, Motion, StartOfDocument},
     normal::{
         search::{FindCommand, ReplaceCommand, Replacement},
@@ -107,6 +109,7 @@ pub fn register(editor: &mut Editor, cx: &mut ViewContext<Vim>) {
             }
             Some(Ok(result)) => result,
         };
+        vim.push_jump(None, cx);
         vim.move_cursor(Motion::StartOfDocument, Some(buffer_row.0 as usize + 1), cx);
     });
 
@@ -646,6 +649,12 @@ pub fn command_interceptor(mut input: &str, cx: &AppContext) -> Option<CommandIn
             }
             .boxed_clone(),
         )
+    } else if let Some(helix_regex) = parse_helix_regex_command(query) {
+        Some(helix_regex.boxed_clone())
+    } else if let Some(mappings) = parse_map_command(query, VimMappings::leader(cx)) {
+        mappings
+            .ok()
+            .map(|mappings| AddMappings { mappings }.boxed_clone())
     } else if query.starts_with('s') {
         let mut substitute = "substitute".chars().peekable();
         let mut query = query.chars().peekable();
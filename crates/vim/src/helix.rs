@@ -0,0 +1,546 @@
+use editor::{display_map::DisplaySnapshot, movement, scroll::Autoscroll, DisplayPoint, Editor};
+use gpui::{actions, impl_actions, ViewContext};
+use language::{Selection, SelectionGoal};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{motion::Motion, state::Mode, Vim};
+
+actions!(
+    vim,
+    [
+        HelixDelete,
+        HelixChange,
+        HelixYank,
+        HelixInsert,
+        HelixAppend,
+        HelixSelectLine,
+        HelixCollapseSelection,
+        HelixFlipSelections,
+        HelixKeepPrimarySelection,
+    ]
+);
+
+/// The ways a regex can be applied to the current selections.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum HelixRegexOperation {
+    /// Select every match inside the selections.
+    Select,
+    /// Split the selections on every match.
+    Split,
+    /// Keep only the selections that contain a match.
+    Keep,
+    /// Remove the selections that contain a match.
+    Remove,
+}
+
+impl HelixRegexOperation {
+    const ALL: [Self; 4] = [Self::Select, Self::Split, Self::Keep, Self::Remove];
+
+    fn command(&self) -> &'static str {
+        match self {
+            Self::Select => "select",
+            Self::Split => "split",
+            Self::Keep => "keep",
+            Self::Remove => "remove",
+        }
+    }
+}
+
+/// Opens the command palette to enter the regex for a selection operation.
+#[derive(Clone, Deserialize, PartialEq)]
+pub struct HelixPrompt(pub HelixRegexOperation);
+
+/// Applies a regex to the current selections.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct HelixRegex {
+    pub operation: HelixRegexOperation,
+    pub pattern: String,
+}
+
+impl_actions!(vim, [HelixPrompt, HelixRegex]);
+
+pub(crate) fn register(editor: &mut Editor, cx: &mut ViewContext<Vim>) {
+    Vim::action(editor, cx, |vim, _: &HelixDelete, cx| {
+        vim.record_current_action(cx);
+        vim.helix_delete(cx);
+    });
+    Vim::action(editor, cx, |vim, _: &HelixChange, cx| {
+        vim.start_recording(cx);
+        vim.helix_delete(cx);
+        vim.switch_mode(Mode::Insert, true, cx);
+    });
+    Vim::action(editor, cx, Vim::helix_yank);
+    Vim::action(editor, cx, |vim, _: &HelixInsert, cx| {
+        vim.start_recording(cx);
+        vim.update_editor(cx, |_, editor, cx| {
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|_, selection| selection.collapse_to(selection.start, selection.goal))
+            });
+        });
+        vim.switch_mode(Mode::Insert, true, cx);
+    });
+    Vim::action(editor, cx, |vim, _: &HelixAppend, cx| {
+        vim.start_recording(cx);
+        vim.update_editor(cx, |_, editor, cx| {
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let end = if selection.is_empty() {
+                        movement::saturating_right(map, selection.end)
+                    } else {
+                        selection.end
+                    };
+                    selection.collapse_to(end, selection.goal)
+                })
+            });
+        });
+        vim.switch_mode(Mode::Insert, true, cx);
+    });
+    Vim::action(editor, cx, Vim::helix_select_line);
+    Vim::action(editor, cx, |vim, _: &HelixCollapseSelection, cx| {
+        vim.update_editor(cx, |_, editor, cx| {
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.move_with(|map, selection| {
+                    let cursor = helix_cursor(map, selection);
+                    selection.collapse_to(cursor, SelectionGoal::None)
+                })
+            });
+        });
+    });
+    Vim::action(editor, cx, |vim, _: &HelixFlipSelections, cx| {
+        vim.update_editor(cx, |_, editor, cx| {
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.move_with(|_, selection| {
+                    if !selection.is_empty() {
+                        selection.reversed = !selection.reversed;
+                    }
+                })
+            });
+        });
+    });
+    Vim::action(editor, cx, |vim, _: &HelixKeepPrimarySelection, cx| {
+        vim.update_editor(cx, |_, editor, cx| {
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                let newest = s.newest_anchor().clone();
+                s.select_anchors(vec![newest]);
+            });
+        });
+    });
+    Vim::action(editor, cx, |vim, action: &HelixPrompt, cx| {
+        let Some(workspace) = vim.workspace(cx) else {
+            return;
+        };
+        let query = format!("{}/", action.0.command());
+        workspace.update(cx, |workspace, cx| {
+            command_palette::CommandPalette::toggle(workspace, &query, cx);
+        })
+    });
+    Vim::action(editor, cx, |vim, action: &HelixRegex, cx| {
+        vim.helix_regex(action.operation, &action.pattern, cx)
+    });
+}
+
+/// Parses the commands entered after a [`HelixPrompt`], e.g. `split/,\s*`.
+pub(crate) fn parse_helix_regex_command(query: &str) -> Option<HelixRegex> {
+    HelixRegexOperation::ALL.into_iter().find_map(|operation| {
+        let pattern = query.strip_prefix(operation.command())?.strip_prefix('/')?;
+        let pattern = match pattern.strip_suffix('/') {
+            Some(stripped) if !stripped.ends_with('\\') => stripped,
+            _ => pattern,
+        };
+        if pattern.is_empty() {
+            return None;
+        }
+        Some(HelixRegex {
+            operation,
+            pattern: pattern.to_string(),
+        })
+    })
+}
+
+/// Returns the position of the character the cursor is on. As in visual mode,
+/// a forward selection's cursor is on the last selected character.
+fn helix_cursor(map: &DisplaySnapshot, selection: &Selection<DisplayPoint>) -> DisplayPoint {
+    if selection.is_empty() || selection.reversed {
+        selection.head()
+    } else {
+        movement::left(map, selection.head())
+    }
+}
+
+fn next_line_start(map: &DisplaySnapshot, mut point: DisplayPoint) -> DisplayPoint {
+    if point.row() == map.max_point().row() {
+        return map.max_point();
+    }
+    *point.row_mut() += 1;
+    *point.column_mut() = 0;
+    point
+}
+
+/// Whether the motion selects the text it moves over, rather than just moving the cursor.
+fn selects_text(motion: &Motion) -> bool {
+    matches!(
+        motion,
+        Motion::NextWordStart { .. }
+            | Motion::NextWordEnd { .. }
+            | Motion::PreviousWordStart { .. }
+            | Motion::PreviousWordEnd { .. }
+            | Motion::NextSubwordStart { .. }
+            | Motion::NextSubwordEnd { .. }
+            | Motion::PreviousSubwordStart { .. }
+            | Motion::PreviousSubwordEnd { .. }
+            | Motion::FindForward { .. }
+            | Motion::FindBackward { .. }
+    )
+}
+
+impl Vim {
+    pub(crate) fn helix_motion(
+        &mut self,
+        motion: Motion,
+        times: Option<usize>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.update_editor(cx, |_, editor, cx| {
+            let text_layout_details = editor.text_layout_details(cx);
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.move_with(|map, selection| {
+                    let cursor = helix_cursor(map, selection);
+                    if !selects_text(&motion) {
+                        if let Some((new_point, goal)) = motion.move_point(
+                            map,
+                            cursor,
+                            selection.goal,
+                            times,
+                            &text_layout_details,
+                        ) {
+                            selection.collapse_to(new_point, goal);
+                        }
+                        return;
+                    }
+
+                    // Successive motions select adjacent ranges rather than
+                    // overlapping on the cursor character, so a forward
+                    // selection continues from its end.
+                    let extends_forward = !selection.is_empty() && !selection.reversed;
+                    let origin = if extends_forward {
+                        selection.end
+                    } else {
+                        cursor
+                    };
+                    let Some((new_point, _)) =
+                        motion.move_point(map, origin, selection.goal, times, &text_layout_details)
+                    else {
+                        return;
+                    };
+                    if new_point == origin {
+                        return;
+                    }
+
+                    if new_point > origin {
+                        selection.start = origin;
+                        selection.end = if motion.inclusive() {
+                            movement::right(map, new_point)
+                        } else {
+                            new_point
+                        };
+                        selection.reversed = false;
+                    } else {
+                        selection.end = if selection.reversed && !selection.is_empty() {
+                            selection.start
+                        } else {
+                            movement::right(map, cursor)
+                        };
+                        selection.start = new_point;
+                        selection.reversed = true;
+                    }
+                    selection.goal = SelectionGoal::None;
+                })
+            });
+        });
+    }
+
+    /// Deletes the selected text, or the character under the cursor for empty selections.
+    fn helix_delete(&mut self, cx: &mut ViewContext<Self>) {
+        self.update_editor(cx, |vim, editor, cx| {
+            editor.transact(cx, |editor, cx| {
+                editor.change_selections(None, cx, |s| {
+                    s.move_with(|map, selection| {
+                        if selection.is_empty() {
+                            selection.end = movement::right(map, selection.start);
+                            selection.reversed = false;
+                        }
+                    })
+                });
+                vim.copy_selections_content(editor, false, cx);
+                editor.insert("", cx);
+            });
+        });
+    }
+
+    fn helix_yank(&mut self, _: &HelixYank, cx: &mut ViewContext<Self>) {
+        self.update_editor(cx, |vim, editor, cx| {
+            let mut collapsed = Vec::new();
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    if selection.is_empty() {
+                        collapsed.push(selection.id);
+                        selection.end = movement::saturating_right(map, selection.start);
+                    }
+                })
+            });
+            vim.yank_selections_content(editor, false, cx);
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|_, selection| {
+                    if collapsed.contains(&selection.id) {
+                        selection.collapse_to(selection.start, selection.goal)
+                    }
+                })
+            });
+        });
+    }
+
+    /// Extends each selection to whole lines, or by another line if it already covers whole lines.
+    fn helix_select_line(&mut self, _: &HelixSelectLine, cx: &mut ViewContext<Self>) {
+        let count = self.take_count(cx).unwrap_or(1);
+        self.update_editor(cx, |_, editor, cx| {
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.move_with(|map, selection| {
+                    let start = DisplayPoint::new(selection.start.row(), 0);
+                    let mut end = selection.end;
+                    let ends_at_line_start = end.column() == 0 && end.row() > start.row();
+                    if !ends_at_line_start || selection.start == start {
+                        end = next_line_start(map, end);
+                    }
+                    for _ in 1..count {
+                        end = next_line_start(map, end);
+                    }
+                    selection.start = start;
+                    selection.end = end;
+                    selection.reversed = false;
+                    selection.goal = SelectionGoal::None;
+                })
+            });
+        });
+    }
+
+    fn helix_regex(
+        &mut self,
+        operation: HelixRegexOperation,
+        pattern: &str,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(error) => {
+                let Some(workspace) = self.workspace(cx) else {
+                    return;
+                };
+                workspace.update(cx, |workspace, cx| {
+                    workspace.show_error(&error, cx);
+                });
+                return;
+            }
+        };
+
+        self.update_editor(cx, |_, editor, cx| {
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let mut ranges = Vec::new();
+            for selection in editor.selections.all::<usize>(cx) {
+                let offset = selection.start;
+                let text = snapshot
+                    .text_for_range(selection.start..selection.end)
+                    .collect::<String>();
+                match operation {
+                    HelixRegexOperation::Select => ranges.extend(
+                        regex
+                            .find_iter(&text)
+                            .filter(|found| !found.is_empty())
+                            .map(|found| offset + found.start()..offset + found.end()),
+                    ),
+                    HelixRegexOperation::Split => {
+                        let mut start = 0;
+                        for found in regex.find_iter(&text) {
+                            if found.start() > start {
+                                ranges.push(offset + start..offset + found.start());
+                            }
+                            start = start.max(found.end());
+                        }
+                        if start < text.len() {
+                            ranges.push(offset + start..offset + text.len());
+                        }
+                    }
+                    HelixRegexOperation::Keep | HelixRegexOperation::Remove => {
+                        let keep =
+                            regex.is_match(&text) == (operation == HelixRegexOperation::Keep);
+                        if keep {
+                            if selection.reversed {
+                                ranges.push(selection.end..selection.start);
+                            } else {
+                                ranges.push(selection.start..selection.end);
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Like Helix, an operation that would leave no selections does nothing.
+            if ranges.is_empty() {
+                return;
+            }
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| s.select_ranges(ranges));
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+
+    use crate::{state::Mode, test::VimTestContext};
+
+    use super::{parse_helix_regex_command, HelixRegexOperation};
+
+    async fn helix_context(cx: &mut gpui::TestAppContext) -> VimTestContext {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.update_global(|store: &mut settings::SettingsStore, cx| {
+            store.update_user_settings::<crate::VimSettings>(cx, |settings| {
+                settings.helix_mode = Some(true)
+            });
+        });
+        // Returning to normal mode enters Helix's normal mode instead.
+        cx.set_state("ˇ", Mode::Normal);
+        assert_eq!(cx.mode(), Mode::HelixNormal);
+        cx
+    }
+
+    #[test]
+    fn test_parse_helix_regex_command() {
+        let command = parse_helix_regex_command("split/,\\s*").unwrap();
+        assert_eq!(command.operation, HelixRegexOperation::Split);
+        assert_eq!(command.pattern, ",\\s*");
+
+        let command = parse_helix_regex_command("keep/foo/").unwrap();
+        assert_eq!(command.operation, HelixRegexOperation::Keep);
+        assert_eq!(command.pattern, "foo");
+
+        assert_eq!(
+            parse_helix_regex_command("select/a\\/").unwrap().pattern,
+            "a\\/"
+        );
+        assert!(parse_helix_regex_command("split").is_none());
+        assert!(parse_helix_regex_command("remove/").is_none());
+    }
+
+    #[gpui::test]
+    async fn test_helix_word_motions_select(cx: &mut gpui::TestAppContext) {
+        let mut cx = helix_context(cx).await;
+
+        cx.set_state("ˇone two three", Mode::HelixNormal);
+        cx.simulate_keystrokes("w");
+        cx.assert_state("«one ˇ»two three", Mode::HelixNormal);
+        cx.simulate_keystrokes("w");
+        cx.assert_state("one «two ˇ»three", Mode::HelixNormal);
+        cx.simulate_keystrokes("e");
+        cx.assert_state("one two «threeˇ»", Mode::HelixNormal);
+        cx.simulate_keystrokes("b");
+        cx.assert_state("one two «ˇthree»", Mode::HelixNormal);
+        cx.simulate_keystrokes("b");
+        cx.assert_state("one «ˇtwo »three", Mode::HelixNormal);
+
+        // Other motions just move the cursor.
+        cx.simulate_keystrokes("l");
+        cx.assert_state("one tˇwo three", Mode::HelixNormal);
+    }
+
+    #[gpui::test]
+    async fn test_helix_select_then_act(cx: &mut gpui::TestAppContext) {
+        let mut cx = helix_context(cx).await;
+
+        cx.set_state("ˇone two three", Mode::HelixNormal);
+        cx.simulate_keystrokes("w d");
+        cx.assert_state("ˇtwo three", Mode::HelixNormal);
+        assert_eq!(
+            cx.read_from_clipboard()
+                .map(|item| item.text().unwrap().to_string()),
+            Some("one ".into())
+        );
+
+        cx.simulate_keystrokes("e c");
+        cx.assert_state("ˇ three", Mode::Insert);
+        cx.simulate_keystrokes("2 escape");
+        cx.assert_state("ˇ2 three", Mode::HelixNormal);
+
+        // An empty selection acts on the character under the cursor.
+        cx.simulate_keystrokes("d");
+        cx.assert_state("ˇ three", Mode::HelixNormal);
+
+        cx.set_state(
+            indoc! {"
+                ˇone
+                two
+                three"},
+            Mode::HelixNormal,
+        );
+        cx.simulate_keystrokes("x");
+        cx.assert_state(
+            indoc! {"
+                «one
+                ˇ»two
+                three"},
+            Mode::HelixNormal,
+        );
+        cx.simulate_keystrokes("x");
+        cx.assert_state(
+            indoc! {"
+                «one
+                two
+                ˇ»three"},
+            Mode::HelixNormal,
+        );
+        cx.simulate_keystrokes("d");
+        cx.assert_state("ˇthree", Mode::HelixNormal);
+    }
+
+    #[gpui::test]
+    async fn test_helix_multiple_selections(cx: &mut gpui::TestAppContext) {
+        let mut cx = helix_context(cx).await;
+
+        cx.set_state("«foo, bar, bazˇ»", Mode::HelixNormal);
+        cx.dispatch_action(super::HelixRegex {
+            operation: HelixRegexOperation::Split,
+            pattern: ",\\s*".into(),
+        });
+        cx.assert_state("«fooˇ», «barˇ», «bazˇ»", Mode::HelixNormal);
+
+        cx.dispatch_action(super::HelixRegex {
+            operation: HelixRegexOperation::Remove,
+            pattern: "^b".into(),
+        });
+        cx.assert_state("«fooˇ», bar, baz", Mode::HelixNormal);
+
+        cx.set_state("«foo(a, b)ˇ» + bar(c)", Mode::HelixNormal);
+        cx.dispatch_action(super::HelixRegex {
+            operation: HelixRegexOperation::Select,
+            pattern: "\\w+".into(),
+        });
+        cx.assert_state("«fooˇ»(«aˇ», «bˇ») + bar(c)", Mode::HelixNormal);
+
+        cx.dispatch_action(super::HelixRegex {
+            operation: HelixRegexOperation::Keep,
+            pattern: "^[ab]$".into(),
+        });
+        cx.assert_state("foo(«aˇ», «bˇ») + bar(c)", Mode::HelixNormal);
+
+        cx.simulate_keystrokes("c x escape");
+        cx.assert_state("foo(ˇx, ˇx) + bar(c)", Mode::HelixNormal);
+
+        cx.set_state("«aˇ» «bˇ» «cˇ»", Mode::HelixNormal);
+        cx.simulate_keystrokes(",");
+        cx.assert_state("a b «cˇ»", Mode::HelixNormal);
+        cx.simulate_keystrokes("alt-;");
+        cx.assert_state("a b «ˇc»", Mode::HelixNormal);
+        cx.simulate_keystrokes(";");
+        cx.assert_state("a b ˇc", Mode::HelixNormal);
+    }
+}
@@ -0,0 +1,629 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use collections::HashMap;
+use editor::{scroll::Autoscroll, Anchor, Bias, Direction, Editor, ToPoint as _};
+use gpui::{actions, AppContext, EntityId, Global, Task, ViewContext, WeakModel};
+use language::{Buffer, Point, ToPoint as _};
+use multi_buffer::MultiBufferRow;
+use util::ResultExt;
+use workspace::{Workspace, WorkspaceId};
+
+use crate::{
+    persistence::{SerializedJump, SerializedMark, DB},
+    Vim, VimAddon,
+};
+
+actions!(vim, [JumpListOlder, JumpListNewer]);
+
+/// The maximum number of entries kept in the jump list, as in vim.
+const MAX_JUMPS: usize = 100;
+
+/// How long to wait after the last change before saving the marks and jumps.
+const PERSIST_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// A position in a file, used for global marks and the jump list.
+#[derive(Clone, Debug)]
+pub(crate) struct FileMark {
+    pub(crate) path: Arc<Path>,
+    /// While the buffer is open, the anchor keeps the mark in place as the
+    /// buffer is edited.
+    anchor: Option<(WeakModel<Buffer>, language::Anchor)>,
+    point: Point,
+}
+
+impl FileMark {
+    fn point(&self, cx: &AppContext) -> Point {
+        self.anchor
+            .as_ref()
+            .and_then(|(buffer, anchor)| Some(anchor.to_point(&buffer.upgrade()?.read(cx))))
+            .unwrap_or(self.point)
+    }
+
+    fn is_on_same_line(&self, other: &FileMark, cx: &AppContext) -> bool {
+        self.path == other.path && self.point(cx).row == other.point(cx).row
+    }
+
+    /// Returns the mark for the given position in the editor, which must be
+    /// showing a single local file.
+    fn for_editor(editor: &Editor, position: Option<Anchor>, cx: &AppContext) -> Option<Self> {
+        let buffer = editor.buffer().read(cx).as_singleton()?;
+        let path = buffer.read(cx).file()?.as_local()?.abs_path(cx);
+        let point = match position {
+            Some(anchor) => anchor.to_point(&editor.buffer().read(cx).read(cx)),
+            None => editor.selections.newest::<Point>(cx).head(),
+        };
+        let anchor = buffer.read(cx).anchor_before(point);
+        Some(Self {
+            path: path.into(),
+            anchor: Some((buffer.downgrade(), anchor)),
+            point,
+        })
+    }
+}
+
+/// The global marks and jump list of a workspace.
+#[derive(Default)]
+pub(crate) struct WorkspaceMarks {
+    workspace_entity_id: EntityId,
+    database_id: Option<WorkspaceId>,
+    marks: HashMap<String, FileMark>,
+    jumps: Vec<FileMark>,
+    /// The position in the jump list, which is `jumps.len()` unless the
+    /// user is navigating through it.
+    jump_index: usize,
+    /// Whether the marks and jumps saved for the workspace have been read.
+    /// Until then, saving would overwrite them.
+    loaded: bool,
+    /// Whether there are changes that haven't been saved yet.
+    dirty: bool,
+    persist_task: Option<Task<()>>,
+}
+
+impl WorkspaceMarks {
+    fn push_jump(&mut self, mark: FileMark, cx: &AppContext) {
+        self.jumps
+            .retain(|existing| !existing.is_on_same_line(&mark, cx));
+        self.jumps.push(mark);
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.drain(..self.jumps.len() - MAX_JUMPS);
+        }
+        self.jump_index = self.jumps.len();
+    }
+
+    fn is_navigating(&self) -> bool {
+        self.jump_index < self.jumps.len()
+    }
+
+    /// Saves the marks and jumps once no changes have been made for a while.
+    fn persist(&mut self, cx: &AppContext) {
+        self.dirty = true;
+        if !self.loaded {
+            return;
+        }
+        let Some(database_id) = self.database_id else {
+            return;
+        };
+        let workspace_entity_id = self.workspace_entity_id;
+        self.persist_task = Some(cx.spawn(|mut cx| async move {
+            cx.background_executor().timer(PERSIST_DEBOUNCE).await;
+            let Ok(Some((marks, jumps))) = cx.update_global::<FileMarks, _>(|file_marks, cx| {
+                let state = file_marks.workspaces.get_mut(&workspace_entity_id)?;
+                state.dirty = false;
+                Some(state.serialize(cx))
+            }) else {
+                return;
+            };
+            DB.save_marks_and_jumps(database_id, marks, jumps)
+                .await
+                .log_err();
+        }));
+    }
+
+    /// Saves any pending changes right away.
+    fn flush(&mut self, cx: &AppContext) {
+        self.persist_task.take();
+        if !self.loaded || !self.dirty {
+            return;
+        }
+        let Some(database_id) = self.database_id else {
+            return;
+        };
+        self.dirty = false;
+        let (marks, jumps) = self.serialize(cx);
+        cx.background_executor()
+            .spawn(async move {
+                DB.save_marks_and_jumps(database_id, marks, jumps)
+                    .await
+                    .log_err();
+            })
+            .detach();
+    }
+
+    fn serialize(&self, cx: &AppContext) -> (Vec<SerializedMark>, Vec<SerializedJump>) {
+        let marks = self
+            .marks
+            .iter()
+            .map(|(name, mark)| {
+                let point = mark.point(cx);
+                (
+                    name.clone(),
+                    mark.path.to_path_buf(),
+                    point.row,
+                    point.column,
+                )
+            })
+            .collect::<Vec<SerializedMark>>();
+        let jumps = self
+            .jumps
+            .iter()
+            .map(|mark| {
+                let point = mark.point(cx);
+                (mark.path.to_path_buf(), point.row, point.column)
+            })
+            .collect::<Vec<SerializedJump>>();
+        (marks, jumps)
+    }
+}
+
+/// The [`WorkspaceMarks`] of every open workspace.
+#[derive(Default)]
+pub(crate) struct FileMarks {
+    workspaces: HashMap<EntityId, WorkspaceMarks>,
+}
+
+impl Global for FileMarks {}
+
+pub(crate) fn register(editor: &mut Editor, cx: &mut ViewContext<Vim>) {
+    Vim::action(editor, cx, |vim, _: &JumpListOlder, cx| {
+        vim.move_in_jump_list(Direction::Prev, cx)
+    });
+    Vim::action(editor, cx, |vim, _: &JumpListNewer, cx| {
+        vim.move_in_jump_list(Direction::Next, cx)
+    });
+}
+
+/// Tracks the global marks and jump list of a new workspace, restoring them
+/// from the database.
+pub(crate) fn register_workspace(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
+    let workspace_entity_id = cx.view().entity_id();
+    let database_id = workspace.database_id();
+    cx.default_global::<FileMarks>().workspaces.insert(
+        workspace_entity_id,
+        WorkspaceMarks {
+            workspace_entity_id,
+            database_id,
+            ..Default::default()
+        },
+    );
+    cx.on_release(move |_, _, cx| {
+        let state = cx
+            .default_global::<FileMarks>()
+            .workspaces
+            .remove(&workspace_entity_id);
+        if let Some(mut state) = state {
+            state.flush(cx);
+        }
+    })
+    .detach();
+
+    if let Some(database_id) = database_id {
+        load_workspace_marks(workspace_entity_id, database_id, cx).detach();
+    }
+}
+
+/// Reads the marks and jumps saved for the workspace, merging them with the
+/// ones recorded since it was opened.
+fn load_workspace_marks(
+    workspace_entity_id: EntityId,
+    database_id: WorkspaceId,
+    cx: &AppContext,
+) -> Task<()> {
+    cx.spawn(|mut cx| async move {
+        // If they can't be read, the workspace starts without them, and saves
+        // the ones recorded from now on.
+        let (marks, jumps) = cx
+            .background_executor()
+            .spawn(async move {
+                anyhow::Ok((
+                    DB.get_global_marks(database_id)?,
+                    DB.get_jumps(database_id)?,
+                ))
+            })
+            .await
+            .log_err()
+            .unwrap_or_default();
+        let to_mark = |path: PathBuf, row, column| FileMark {
+            path: path.into(),
+            anchor: None,
+            point: Point::new(row, column),
+        };
+        cx.update_global::<FileMarks, _>(|file_marks, cx| {
+            let Some(state) = file_marks.workspaces.get_mut(&workspace_entity_id) else {
+                return;
+            };
+            state.loaded = true;
+            // Anything recorded before the database was read is more recent.
+            for (name, path, row, column) in marks {
+                state
+                    .marks
+                    .entry(name)
+                    .or_insert_with(|| to_mark(path, row, column));
+            }
+            let recorded = std::mem::take(&mut state.jumps);
+            state.jumps = jumps
+                .into_iter()
+                .map(|(path, row, column)| to_mark(path, row, column))
+                .chain(recorded)
+                .collect();
+            if state.jumps.len() > MAX_JUMPS {
+                state.jumps.drain(..state.jumps.len() - MAX_JUMPS);
+            }
+            state.jump_index = state.jumps.len();
+            if state.dirty {
+                state.persist(cx);
+            }
+        })
+        .ok();
+    })
+}
+
+impl Vim {
+    fn update_workspace_marks<R>(
+        &mut self,
+        cx: &mut ViewContext<Self>,
+        update: impl FnOnce(&mut WorkspaceMarks, &mut AppContext) -> R,
+    ) -> Option<R> {
+        let workspace_entity_id = self.workspace(cx)?.entity_id();
+        cx.update_global::<FileMarks, _>(|file_marks, cx| {
+            let state = file_marks.workspaces.get_mut(&workspace_entity_id)?;
+            Some(update(state, cx))
+        })
+    }
+
+    fn file_mark(
+        &mut self,
+        position: Option<Anchor>,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<FileMark> {
+        let editor = self.editor()?;
+        FileMark::for_editor(editor.read(cx), position, cx)
+    }
+
+    /// Records a jump from the given position (or the cursor) in the jump list.
+    pub(crate) fn push_jump(&mut self, position: Option<Anchor>, cx: &mut ViewContext<Self>) {
+        let Some(mark) = self.file_mark(position, cx) else {
+            return;
+        };
+        self.update_workspace_marks(cx, |state, cx| {
+            state.push_jump(mark, cx);
+            state.persist(cx);
+        });
+    }
+
+    /// Records the cursor in the jump list when switching to another editor,
+    /// unless the switch was caused by moving through the jump list.
+    pub(crate) fn push_jump_on_editor_change(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(mark) = self.file_mark(None, cx) else {
+            return;
+        };
+        self.update_workspace_marks(cx, |state, cx| {
+            if !state.is_navigating() {
+                state.push_jump(mark, cx);
+                state.persist(cx);
+            }
+        });
+    }
+
+    pub(crate) fn create_global_mark(&mut self, name: String, cx: &mut ViewContext<Self>) {
+        let Some(mark) = self.file_mark(None, cx) else {
+            return;
+        };
+        self.update_workspace_marks(cx, |state, cx| {
+            state.marks.insert(name, mark);
+            state.persist(cx);
+        });
+    }
+
+    pub(crate) fn global_mark(
+        &mut self,
+        name: &str,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<FileMark> {
+        self.update_workspace_marks(cx, |state, _| state.marks.get(name).cloned())
+            .flatten()
+    }
+
+    /// Returns the position of the mark in this editor's buffer, if it is in
+    /// the same file.
+    pub(crate) fn file_mark_anchor(
+        &mut self,
+        mark: &FileMark,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Anchor> {
+        let current = self.file_mark(None, cx)?;
+        if current.path != mark.path {
+            return None;
+        }
+        let point = mark.point(cx);
+        self.update_editor(cx, |_, editor, cx| {
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            snapshot.anchor_before(snapshot.clip_point(point, Bias::Left))
+        })
+    }
+
+    /// Moves the cursor to the mark, opening its file if necessary.
+    pub(crate) fn open_file_mark(
+        &mut self,
+        mark: FileMark,
+        line: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let point = mark.point(cx);
+        if self
+            .file_mark(None, cx)
+            .is_some_and(|current| current.path == mark.path)
+        {
+            self.update_editor(cx, |_, editor, cx| select_point(editor, point, line, cx));
+            return;
+        }
+
+        let Some(workspace) = self.workspace(cx) else {
+            return;
+        };
+        let open = workspace.update(cx, |workspace, cx| {
+            workspace.open_abs_path(mark.path.to_path_buf(), true, cx)
+        });
+        cx.spawn(|_, mut cx| async move {
+            let item = open.await?;
+            let Some(editor) = item.downcast::<Editor>() else {
+                return Ok(());
+            };
+            let vim = editor.update(&mut cx, |editor, _| {
+                editor.addon::<VimAddon>().map(|addon| addon.view.clone())
+            })?;
+            match vim {
+                Some(vim) => vim.update(&mut cx, |vim, cx| {
+                    vim.run_command(cx, |vim, cx| {
+                        vim.update_editor(cx, |_, editor, cx| {
+                            select_point(editor, point, line, cx)
+                        });
+                    })
+                })?,
+                None => {
+                    editor.update(&mut cx, |editor, cx| select_point(editor, point, line, cx))?
+                }
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn move_in_jump_list(&mut self, direction: Direction, cx: &mut ViewContext<Self>) {
+        let count = self.take_count(cx).unwrap_or(1);
+        let current = self.file_mark(None, cx);
+        let target = self.update_workspace_marks(cx, |state, cx| {
+            if direction == Direction::Prev && !state.is_navigating() {
+                // Remember where we came from, so that we can jump back to it.
+                if let Some(current) = current {
+                    state.push_jump(current, cx);
+                    state.jump_index = state.jumps.len().saturating_sub(1);
+                }
+            }
+            let index = match direction {
+                Direction::Prev => state.jump_index.checked_sub(count)?,
+                Direction::Next => state.jump_index + count,
+            };
+            let mark = state.jumps.get(index)?.clone();
+            state.jump_index = index;
+            Some(mark)
+        });
+        if let Some(mark) = target.flatten() {
+            self.open_file_mark(mark, false, cx);
+        }
+    }
+}
+
+fn select_point(editor: &mut Editor, point: Point, line: bool, cx: &mut ViewContext<Editor>) {
+    let snapshot = editor.buffer().read(cx).snapshot(cx);
+    let mut point = snapshot.clip_point(point, Bias::Left);
+    if line {
+        point.column = snapshot.indent_size_for_line(MultiBufferRow(point.row)).len;
+    }
+    editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+        s.select_ranges([point..point])
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use editor::{scroll::Autoscroll, Editor};
+    use indoc::indoc;
+    use language::Point;
+
+    use crate::{persistence::DB, state::Mode, test::VimTestContext};
+
+    use super::load_workspace_marks;
+
+    /// Returns the path of the active editor and the position of its cursor.
+    fn active_position(cx: &mut VimTestContext) -> (PathBuf, Point) {
+        cx.workspace(|workspace, cx| {
+            let editor = workspace.active_item_as::<Editor>(cx).unwrap();
+            let editor = editor.read(cx);
+            let buffer = editor.buffer().read(cx).as_singleton().unwrap();
+            let path = buffer
+                .read(cx)
+                .file()
+                .unwrap()
+                .as_local()
+                .unwrap()
+                .abs_path(cx);
+            (path, editor.selections.newest::<Point>(cx).head())
+        })
+    }
+
+    async fn add_file(cx: &mut VimTestContext, path: &str, text: &str) {
+        let fs = cx.workspace(|workspace, _| workspace.app_state().fs.as_fake());
+        fs.insert_file(path, text.into()).await;
+        cx.run_until_parked();
+    }
+
+    #[gpui::test]
+    async fn test_jump_list(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                ˇone
+                two
+                three
+                four"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes("shift-g");
+        cx.simulate_keystrokes("k k");
+        cx.simulate_keystrokes("g g");
+        cx.assert_state(
+            indoc! {"
+                ˇone
+                two
+                three
+                four"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes("ctrl-o");
+        cx.assert_state(
+            indoc! {"
+                one
+                ˇtwo
+                three
+                four"},
+            Mode::Normal,
+        );
+        // There is nothing older
+        cx.simulate_keystrokes("ctrl-o");
+        cx.assert_state(
+            indoc! {"
+                one
+                ˇtwo
+                three
+                four"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes("ctrl-i ctrl-i");
+        cx.assert_state(
+            indoc! {"
+                ˇone
+                two
+                three
+                four"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_jump_list_records_editor_jumps(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        let text = (0..30)
+            .map(|row| format!("line {row}\n"))
+            .collect::<String>();
+        cx.set_state(&format!("ˇ{text}"), Mode::Normal);
+
+        // Jumps made by editor commands, like going to a definition, are recorded.
+        cx.update_editor(|editor, cx| {
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_ranges([Point::new(20, 0)..Point::new(20, 0)])
+            })
+        });
+        cx.simulate_keystrokes("ctrl-o");
+        let cursor = cx.update_editor(|editor, cx| editor.selections.newest::<Point>(cx).head());
+        assert_eq!(cursor, Point::new(0, 0));
+
+        cx.simulate_keystrokes("ctrl-i");
+        let cursor = cx.update_editor(|editor, cx| editor.selections.newest::<Point>(cx).head());
+        assert_eq!(cursor, Point::new(20, 0));
+
+        // Vim motions that aren't jumps are not recorded.
+        cx.simulate_keystrokes("g g 2 5 j ctrl-o");
+        let cursor = cx.update_editor(|editor, cx| editor.selections.newest::<Point>(cx).head());
+        assert_eq!(cursor, Point::new(20, 0));
+    }
+
+    #[gpui::test]
+    async fn test_global_marks_across_files(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        add_file(&mut cx, "/root/dir/other.rs", "alpha\nbeta\ngamma\n").await;
+        cx.set_state("one\nˇtwo\nthree\n", Mode::Normal);
+        cx.simulate_keystrokes("m A");
+
+        let open = cx.workspace(|workspace, cx| {
+            workspace.open_abs_path(PathBuf::from("/root/dir/other.rs"), true, cx)
+        });
+        open.await.unwrap();
+        cx.simulate_keystrokes("j j m B");
+        assert_eq!(
+            active_position(&mut cx),
+            (PathBuf::from("/root/dir/other.rs"), Point::new(2, 0))
+        );
+
+        cx.simulate_keystrokes("' A");
+        cx.run_until_parked();
+        assert_eq!(
+            active_position(&mut cx),
+            (PathBuf::from("/root/dir/file.rs"), Point::new(1, 0))
+        );
+
+        cx.simulate_keystrokes("` B");
+        cx.run_until_parked();
+        assert_eq!(
+            active_position(&mut cx),
+            (PathBuf::from("/root/dir/other.rs"), Point::new(2, 0))
+        );
+    }
+
+    #[gpui::test]
+    async fn test_restore_marks_and_jumps(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        add_file(&mut cx, "/root/dir/other.rs", "alpha\nbeta\ngamma\n").await;
+        cx.set_state("ˇone\ntwo\nthree\n", Mode::Normal);
+
+        let database_id = workspace::WORKSPACE_DB.next_id().await.unwrap();
+        DB.save_marks_and_jumps(
+            database_id,
+            vec![("A".to_string(), PathBuf::from("/root/dir/other.rs"), 1, 2)],
+            vec![(PathBuf::from("/root/dir/other.rs"), 2, 0)],
+        )
+        .await
+        .unwrap();
+        let workspace_entity_id = cx.workspace(|_, cx| cx.view().entity_id());
+        cx.update(|cx| load_workspace_marks(workspace_entity_id, database_id, cx))
+            .await;
+
+        cx.simulate_keystrokes("` A");
+        cx.run_until_parked();
+        assert_eq!(
+            active_position(&mut cx),
+            (PathBuf::from("/root/dir/other.rs"), Point::new(1, 2))
+        );
+
+        // Jumping to the mark was recorded after the saved jump.
+        cx.simulate_keystrokes("ctrl-o");
+        cx.run_until_parked();
+        assert_eq!(
+            active_position(&mut cx),
+            (PathBuf::from("/root/dir/file.rs"), Point::new(0, 0))
+        );
+        cx.simulate_keystrokes("ctrl-o");
+        cx.run_until_parked();
+        assert_eq!(
+            active_position(&mut cx),
+            (PathBuf::from("/root/dir/other.rs"), Point::new(2, 0))
+        );
+    }
+}
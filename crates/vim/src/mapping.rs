@@ -0,0 +1,604 @@
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Result};
+use editor::Editor;
+use gpui::{impl_actions, Action, AppContext, Global, KeyBinding, Keystroke, ViewContext};
+use serde::Deserialize;
+use util::ResultExt;
+
+use settings::Settings;
+
+use crate::{Vim, VimModeSetting};
+
+/// The maximum number of keystrokes a single mapping may expand to, including
+/// the keystrokes produced by recursive mappings. Mirrors vim's `maxmapdepth`.
+const MAX_MAPPED_KEYSTROKES: usize = 1000;
+
+const DEFAULT_LEADER: &str = "\\";
+
+/// The modes a mapping can apply to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum MapMode {
+    Normal,
+    Visual,
+    Insert,
+    OperatorPending,
+}
+
+impl MapMode {
+    fn context(&self) -> &'static str {
+        match self {
+            MapMode::Normal => "Editor && vim_mode == normal && !VimNoremap",
+            MapMode::Visual => "Editor && vim_mode == visual && !VimNoremap",
+            MapMode::Insert => "Editor && vim_mode == insert && !VimNoremap",
+            MapMode::OperatorPending => "Editor && vim_mode == operator && !VimNoremap",
+        }
+    }
+}
+
+/// A mapping from a sequence of keystrokes to the keystrokes that should
+/// be replayed in its place.
+///
+/// Both sides are stored as space separated keystrokes in the keymap syntax
+/// (e.g. `ctrl-w shift-j`).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Mapping {
+    pub mode: MapMode,
+    pub lhs: String,
+    pub rhs: String,
+    /// Whether the replayed keystrokes may trigger other user mappings
+    /// (`:map`) or only the built-in bindings (`:noremap`).
+    pub remap: bool,
+}
+
+impl Mapping {
+    fn key_binding(&self) -> Result<KeyBinding> {
+        KeyBinding::load(
+            &self.lhs,
+            Box::new(ReplayMapping {
+                keystrokes: self.rhs.clone(),
+                remap: self.remap,
+            }),
+            Some(self.mode.context()),
+        )
+    }
+}
+
+/// Replays the right hand side of a user mapping.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ReplayMapping {
+    pub keystrokes: String,
+    pub remap: bool,
+}
+
+/// Defines new mappings, as typed on the command line (e.g. `:nnoremap Y y$`).
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct AddMappings {
+    pub mappings: Vec<Mapping>,
+}
+
+impl_actions!(vim, [ReplayMapping, AddMappings]);
+
+/// The user's mappings, both from the vimrc and those added at runtime.
+///
+/// This is kept separately from the [`crate::state::VimGlobals`] so that
+/// mappings survive toggling vim mode off and on again.
+#[derive(Default)]
+pub(crate) struct VimMappings {
+    leader: Option<String>,
+    vimrc_mappings: Vec<Mapping>,
+    runtime_mappings: Vec<Mapping>,
+
+    pending_keystrokes: VecDeque<(Keystroke, bool)>,
+    replaying: bool,
+    /// Set while replaying the keystrokes of a non-recursive mapping, so
+    /// that the keymap skips user mappings.
+    pub(crate) noremap: bool,
+}
+
+impl Global for VimMappings {}
+
+impl VimMappings {
+    /// The leader set by the vimrc, used to expand `<leader>` in mappings
+    /// defined on the command line.
+    pub(crate) fn leader(cx: &AppContext) -> &str {
+        cx.try_global::<Self>()
+            .and_then(|mappings| mappings.leader.as_deref())
+            .unwrap_or(DEFAULT_LEADER)
+    }
+
+    fn mappings(&self) -> impl Iterator<Item = &Mapping> {
+        self.vimrc_mappings
+            .iter()
+            .chain(self.runtime_mappings.iter())
+    }
+}
+
+pub(crate) fn register(editor: &mut Editor, cx: &mut ViewContext<Vim>) {
+    Vim::action(editor, cx, |vim, action: &ReplayMapping, cx| {
+        vim.replay_mapping(action, cx)
+    });
+
+    Vim::action(editor, cx, |_, action: &AddMappings, cx| {
+        let mappings = cx.default_global::<VimMappings>();
+        mappings
+            .runtime_mappings
+            .extend(action.mappings.iter().cloned());
+        let bindings = action
+            .mappings
+            .iter()
+            .filter_map(|mapping| mapping.key_binding().log_err())
+            .collect::<Vec<_>>();
+        cx.bind_keys(bindings);
+    });
+}
+
+/// Replaces the mappings defined by the user's vimrc with those in `content`.
+///
+/// Lines that cannot be parsed are skipped and reported in the returned error.
+/// The new mappings take effect the next time [`bind_user_mappings`] is called.
+pub fn load_vimrc(content: &str, cx: &mut AppContext) -> Result<()> {
+    let mut leader = None;
+    let mut mappings = Vec::new();
+    let mut errors = Vec::new();
+
+    for (ix, line) in content.lines().enumerate() {
+        let line = line.trim_start_matches([' ', '\t', ':']).trim_end();
+        if line.is_empty() || line.starts_with('"') {
+            continue;
+        }
+        let result = if let Some(value) = parse_let_mapleader(line) {
+            value.map(|value| leader = Some(value))
+        } else {
+            let current_leader = leader.as_deref().unwrap_or(DEFAULT_LEADER);
+            match parse_map_command(line, current_leader) {
+                Some(parsed) => parsed.map(|parsed| mappings.extend(parsed)),
+                None => Err(anyhow!("unsupported command")),
+            }
+        };
+        if let Err(error) = result {
+            errors.push(format!("line {}: {error}", ix + 1));
+        }
+    }
+
+    let vim_mappings = cx.default_global::<VimMappings>();
+    vim_mappings.leader = leader;
+    vim_mappings.vimrc_mappings = mappings;
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid vimrc\n{}", errors.join("\n")))
+    }
+}
+
+/// Adds the user's mappings to the keymap.
+///
+/// This must be called after the other keymaps are loaded so that the mappings
+/// take precedence over the built-in vim bindings.
+pub fn bind_user_mappings(cx: &mut AppContext) {
+    if !VimModeSetting::get_global(cx).0 {
+        return;
+    }
+    let Some(mappings) = cx.try_global::<VimMappings>() else {
+        return;
+    };
+    let bindings = mappings
+        .mappings()
+        .filter_map(|mapping| mapping.key_binding().log_err())
+        .collect::<Vec<_>>();
+    cx.bind_keys(bindings);
+}
+
+/// Parses a `:map` family command such as `nnoremap <leader>w :w<CR>`.
+///
+/// Returns `None` if the line is not a mapping command at all.
+pub(crate) fn parse_map_command(line: &str, leader: &str) -> Option<Result<Vec<Mapping>>> {
+    let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let (modes, remap): (&[MapMode], bool) = match command {
+        "map" => (
+            &[MapMode::Normal, MapMode::Visual, MapMode::OperatorPending],
+            true,
+        ),
+        "no" | "noremap" => (
+            &[MapMode::Normal, MapMode::Visual, MapMode::OperatorPending],
+            false,
+        ),
+        "nm" | "nmap" => (&[MapMode::Normal], true),
+        "nn" | "nnoremap" => (&[MapMode::Normal], false),
+        "vm" | "vmap" | "xm" | "xmap" => (&[MapMode::Visual], true),
+        "vn" | "vnoremap" | "xn" | "xnoremap" => (&[MapMode::Visual], false),
+        "om" | "omap" => (&[MapMode::OperatorPending], true),
+        "ono" | "onoremap" => (&[MapMode::OperatorPending], false),
+        "im" | "imap" => (&[MapMode::Insert], true),
+        "ino" | "inoremap" => (&[MapMode::Insert], false),
+        _ => return None,
+    };
+
+    Some(parse_map_args(args, leader).map(|(lhs, rhs)| {
+        modes
+            .iter()
+            .map(|mode| Mapping {
+                mode: *mode,
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+                remap,
+            })
+            .collect()
+    }))
+}
+
+fn parse_map_args(mut args: &str, leader: &str) -> Result<(String, String)> {
+    loop {
+        args = args.trim_start();
+        let Some(rest) = ["<silent>", "<nowait>", "<unique>"]
+            .iter()
+            .find_map(|modifier| args.strip_prefix(modifier))
+        else {
+            break;
+        };
+        args = rest;
+    }
+    if args.starts_with("<buffer>") || args.starts_with("<expr>") {
+        return Err(anyhow!(
+            "{} mappings are not supported",
+            &args[..args.find('>').unwrap() + 1]
+        ));
+    }
+
+    let (lhs, rhs) = args
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| anyhow!("expected a key sequence and its replacement"))?;
+    let lhs = vim_keys_to_keystrokes(lhs, leader)?;
+    if lhs.is_empty() {
+        return Err(anyhow!("empty key sequence"));
+    }
+    let rhs = rhs.trim_start();
+    let rhs = if rhs.eq_ignore_ascii_case("<nop>") {
+        Vec::new()
+    } else {
+        vim_keys_to_keystrokes(rhs, leader)?
+    };
+    Ok((lhs.join(" "), rhs.join(" ")))
+}
+
+/// Converts vim's key notation (e.g. `<C-w>J`, `<leader>ff`) into keystrokes
+/// in the keymap syntax (e.g. `ctrl-w shift-j`).
+pub(crate) fn vim_keys_to_keystrokes(keys: &str, leader: &str) -> Result<Vec<String>> {
+    let mut keystrokes = Vec::new();
+    let mut chars = keys.char_indices().peekable();
+    while let Some((ix, char)) = chars.next() {
+        if char == '<' {
+            if let Some(len) = keys[ix..].find('>').filter(|len| *len > 1) {
+                let name = &keys[ix + 1..ix + len];
+                if name.eq_ignore_ascii_case("leader") {
+                    if leader.eq_ignore_ascii_case("<leader>") {
+                        return Err(anyhow!("mapleader cannot refer to itself"));
+                    }
+                    keystrokes.extend(vim_keys_to_keystrokes(leader, leader)?);
+                } else {
+                    keystrokes.push(special_key_to_keystroke(name)?);
+                }
+                while chars
+                    .peek()
+                    .is_some_and(|(next_ix, _)| *next_ix <= ix + len)
+                {
+                    chars.next();
+                }
+                continue;
+            }
+        }
+        keystrokes.push(char_to_keystroke(char));
+    }
+    Ok(keystrokes)
+}
+
+fn char_to_keystroke(char: char) -> String {
+    match char {
+        ' ' => "space".to_string(),
+        '\t' => "tab".to_string(),
+        char if char.is_ascii_uppercase() => format!("shift-{}", char.to_ascii_lowercase()),
+        char => char.to_string(),
+    }
+}
+
+fn special_key_to_keystroke(name: &str) -> Result<String> {
+    let mut modifiers = String::new();
+    let mut key = name;
+    while key.len() > 2 && key.as_bytes()[1] == b'-' {
+        let modifier = match key.as_bytes()[0].to_ascii_lowercase() {
+            b'c' => "ctrl-",
+            b's' => "shift-",
+            b'a' | b'm' => "alt-",
+            b'd' => "cmd-",
+            _ => return Err(anyhow!("unknown modifier in <{name}>")),
+        };
+        modifiers.push_str(modifier);
+        key = &key[2..];
+    }
+
+    let key = match key.to_ascii_lowercase().as_str() {
+        "cr" | "enter" | "return" => "enter".to_string(),
+        "esc" => "escape".to_string(),
+        "tab" => "tab".to_string(),
+        "bs" => "backspace".to_string(),
+        "del" => "delete".to_string(),
+        "space" => "space".to_string(),
+        "lt" => "<".to_string(),
+        "bar" => "|".to_string(),
+        "bslash" => "\\".to_string(),
+        "up" => "up".to_string(),
+        "down" => "down".to_string(),
+        "left" => "left".to_string(),
+        "right" => "right".to_string(),
+        "home" => "home".to_string(),
+        "end" => "end".to_string(),
+        "pageup" => "pageup".to_string(),
+        "pagedown" => "pagedown".to_string(),
+        function_key
+            if function_key.len() > 1
+                && function_key.starts_with('f')
+                && function_key[1..].parse::<u8>().is_ok() =>
+        {
+            function_key.to_string()
+        }
+        _ if key.chars().count() == 1 => {
+            let char = key.chars().next().unwrap();
+            // <C-A> and <C-a> are the same key in vim.
+            if modifiers.is_empty() {
+                char_to_keystroke(char)
+            } else {
+                char.to_ascii_lowercase().to_string()
+            }
+        }
+        _ => return Err(anyhow!("unknown key <{name}>")),
+    };
+    Ok(modifiers + &key)
+}
+
+/// Parses `let mapleader = "<value>"`, returning `None` if the line is not
+/// setting the leader.
+fn parse_let_mapleader(line: &str) -> Option<Result<String>> {
+    let rest = line.strip_prefix("let")?.trim_start();
+    let rest = rest
+        .strip_prefix("g:mapleader")
+        .or_else(|| rest.strip_prefix("mapleader"))?;
+    let value = rest.trim_start().strip_prefix('=')?.trim();
+    let unquoted = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        });
+    Some(match unquoted {
+        // In double quoted strings `"\<Space>"` is vim's notation for a special key.
+        Some(value) if value.starts_with("\\<") => Ok(value[1..].to_string()),
+        Some("\\\\") => Ok("\\".to_string()),
+        Some(value) if !value.is_empty() => Ok(value.to_string()),
+        _ => Err(anyhow!("expected a quoted mapleader")),
+    })
+}
+
+impl Vim {
+    fn replay_mapping(&mut self, action: &ReplayMapping, cx: &mut ViewContext<Self>) {
+        let keystrokes = action
+            .keystrokes
+            .split_whitespace()
+            .filter_map(|keystroke| Keystroke::parse(keystroke).log_err())
+            .collect::<Vec<_>>();
+
+        let mappings = cx.default_global::<VimMappings>();
+        for keystroke in keystrokes.into_iter().rev() {
+            mappings
+                .pending_keystrokes
+                .push_front((keystroke, action.remap));
+        }
+        if mappings.replaying {
+            return;
+        }
+        mappings.replaying = true;
+
+        let mut app_cx = AppContext::to_async(cx);
+        cx.window_context()
+            .spawn(|mut cx| async move {
+                let result = async {
+                    for _ in 0..MAX_MAPPED_KEYSTROKES {
+                        let next = cx.update(|cx| {
+                            let mappings = cx.global_mut::<VimMappings>();
+                            let next = mappings.pending_keystrokes.pop_front();
+                            if let Some((_, remap)) = &next {
+                                if mappings.noremap == *remap {
+                                    mappings.noremap = !remap;
+                                    // Re-render so the key context reflects the new state
+                                    // before the keystroke is dispatched.
+                                    cx.refresh();
+                                }
+                            }
+                            next
+                        })?;
+                        let Some((keystroke, _)) = next else {
+                            return Ok(());
+                        };
+                        cx.update(|cx| {
+                            let focused = cx.focused();
+                            cx.dispatch_keystroke(keystroke);
+                            if cx.focused() != focused {
+                                // See `Workspace::send_keystrokes`: vim needs the focus
+                                // change to be observed before the next keystroke.
+                                cx.draw();
+                            }
+                        })?;
+                    }
+                    Err(anyhow!(
+                        "mapping expanded to over {MAX_MAPPED_KEYSTROKES} keystrokes"
+                    ))
+                }
+                .await;
+
+                app_cx
+                    .update_global::<VimMappings, _>(|mappings, _| {
+                        mappings.pending_keystrokes.clear();
+                        mappings.replaying = false;
+                        mappings.noremap = false;
+                    })
+                    .ok();
+                cx.update(|cx| cx.refresh()).ok();
+                result
+            })
+            .detach_and_log_err(cx);
+    }
+}
+
+/// Whether the action was dispatched by a user mapping rather than
+/// by a built-in binding, and so should not be recorded for replay.
+pub(crate) fn is_mapping_action(action: &dyn Action) -> bool {
+    action.as_any().is::<ReplayMapping>()
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+
+    use crate::{state::Mode, test::VimTestContext};
+
+    use super::*;
+
+    #[test]
+    fn test_vim_keys_to_keystrokes() {
+        let keys = |keys: &str| vim_keys_to_keystrokes(keys, "\\").unwrap().join(" ");
+        assert_eq!(keys("dd"), "d d");
+        assert_eq!(keys("Y"), "shift-y");
+        assert_eq!(keys("y$"), "y $");
+        assert_eq!(keys("<C-w>J"), "ctrl-w shift-j");
+        assert_eq!(keys("<C-S-Tab>"), "ctrl-shift-tab");
+        assert_eq!(keys(":w<CR>"), ": w enter");
+        assert_eq!(keys("<leader>f"), "\\ f");
+        assert_eq!(keys("<lt>a"), "< a");
+        assert_eq!(keys("a<b"), "a < b");
+        assert_eq!(keys("<F5>"), "f5");
+        assert_eq!(
+            vim_keys_to_keystrokes("<leader>w", "<Space>")
+                .unwrap()
+                .join(" "),
+            "space w"
+        );
+        assert!(vim_keys_to_keystrokes("<Unknown>", "\\").is_err());
+    }
+
+    #[test]
+    fn test_parse_map_command() {
+        let mappings = parse_map_command("nnoremap <silent> Y y$", "\\")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            mappings,
+            vec![Mapping {
+                mode: MapMode::Normal,
+                lhs: "shift-y".into(),
+                rhs: "y $".into(),
+                remap: false,
+            }]
+        );
+
+        let mappings = parse_map_command("map Q <Nop>", "\\").unwrap().unwrap();
+        assert_eq!(mappings.len(), 3);
+        assert!(mappings.iter().all(|mapping| mapping.rhs.is_empty()));
+
+        assert!(parse_map_command("set number", "\\").is_none());
+        assert!(parse_map_command("nmap <buffer> x y", "\\")
+            .unwrap()
+            .is_err());
+        assert!(parse_map_command("nmap x", "\\").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_parse_let_mapleader() {
+        assert_eq!(
+            parse_let_mapleader("let mapleader = \",\"")
+                .unwrap()
+                .unwrap(),
+            ","
+        );
+        assert_eq!(
+            parse_let_mapleader("let g:mapleader=\"\\<Space>\"")
+                .unwrap()
+                .unwrap(),
+            "<Space>"
+        );
+        assert_eq!(
+            parse_let_mapleader("let mapleader = ' '").unwrap().unwrap(),
+            " "
+        );
+        assert!(parse_let_mapleader("let maplocalleader = ','").is_none());
+    }
+
+    #[gpui::test]
+    async fn test_vimrc_mappings(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.update(|cx| {
+            load_vimrc(
+                indoc! {r#"
+                    " comments and blank lines are ignored
+
+                    let mapleader = " "
+                    nnoremap <leader>d dd
+                    nmap Q <leader>d
+                    nnoremap X Q
+                    inoremap jk <Esc>
+                "#},
+                cx,
+            )
+            .unwrap();
+            bind_user_mappings(cx);
+        });
+
+        cx.set_state(
+            indoc! {"
+                ˇone
+                two
+                three"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes("space d");
+        cx.assert_state(
+            indoc! {"
+                ˇtwo
+                three"},
+            Mode::Normal,
+        );
+
+        // recursive mappings expand other mappings
+        cx.simulate_keystrokes("shift-q");
+        cx.assert_state("ˇthree", Mode::Normal);
+
+        // non-recursive mappings only use the built-in bindings, and there
+        // is no built-in binding for Q.
+        cx.simulate_keystrokes("shift-x");
+        cx.assert_state("ˇthree", Mode::Normal);
+
+        cx.simulate_keystrokes("i j k");
+        cx.assert_state("ˇthree", Mode::Normal);
+    }
+
+    #[gpui::test]
+    async fn test_map_command(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state("one ˇtwo", Mode::Normal);
+        cx.simulate_keystrokes(": n n o r e m a p space shift-y space y $ enter");
+        cx.simulate_keystrokes("shift-y $ p");
+        cx.assert_state("one twotwˇo", Mode::Normal);
+    }
+
+    #[gpui::test]
+    fn test_invalid_vimrc(cx: &mut gpui::AppContext) {
+        let error = load_vimrc("nnoremap Y y$\nset number\nnmap <C-Q>\n", cx).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid vimrc\nline 2: unsupported command\nline 3: expected a key sequence and its replacement"
+        );
+        // valid lines are still loaded
+        assert_eq!(cx.global::<VimMappings>().vimrc_mappings.len(), 1);
+    }
+}
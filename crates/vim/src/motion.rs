@@ -437,8 +437,10 @@ impl Vim {
                         });
                     }
                 }
-                Mode::Normal | Mode::Replace | Mode::Insert => {
+                Mode::Normal | Mode::Replace | Mode::Insert | Mode::HelixNormal => {
                     if self.active_operator().is_none() {
+                        let from = prior_selections.last().map(|selection| selection.end);
+                        self.push_jump(from, cx);
                         return;
                     }
                 }
@@ -457,6 +459,15 @@ impl Vim {
 
         let count = self.take_count(cx);
         let active_operator = self.active_operator();
+        if active_operator.is_none() && motion.is_jump() {
+            let from = match &motion {
+                Motion::ZedSearchResult {
+                    prior_selections, ..
+                } => prior_selections.last().map(|selection| selection.end),
+                _ => None,
+            };
+            self.push_jump(from, cx);
+        }
         let mut waiting_operator: Option<Operator> = None;
         match self.mode {
             Mode::Normal | Mode::Replace | Mode::Insert => {
@@ -471,6 +482,7 @@ impl Vim {
             Mode::Visual | Mode::VisualLine | Mode::VisualBlock => {
                 self.visual_motion(motion.clone(), count, cx)
             }
+            Mode::HelixNormal => self.helix_motion(motion.clone(), count, cx),
         }
         self.clear_operator(cx);
         if let Some(operator) = waiting_operator {
@@ -529,6 +541,52 @@ impl Motion {
         }
     }
 
+    /// Whether the motion is a jump, which is recorded in the jump list.
+    pub fn is_jump(&self) -> bool {
+        use Motion::*;
+        match self {
+            StartOfDocument
+            | EndOfDocument
+            | Matching
+            | SentenceBackward
+            | SentenceForward
+            | StartOfParagraph
+            | EndOfParagraph
+            | WindowTop
+            | WindowMiddle
+            | WindowBottom
+            | ZedSearchResult { .. }
+            | Jump { .. } => true,
+            Down { .. }
+            | Up { .. }
+            | CurrentLine
+            | EndOfLine { .. }
+            | FindForward { .. }
+            | FindBackward { .. }
+            | RepeatFind { .. }
+            | RepeatFindReversed { .. }
+            | Left
+            | Backspace
+            | Right
+            | Space
+            | StartOfLine { .. }
+            | EndOfLineDownward
+            | GoToColumn
+            | NextWordStart { .. }
+            | NextWordEnd { .. }
+            | PreviousWordStart { .. }
+            | PreviousWordEnd { .. }
+            | NextSubwordStart { .. }
+            | NextSubwordEnd { .. }
+            | PreviousSubwordStart { .. }
+            | PreviousSubwordEnd { .. }
+            | FirstNonWhitespace { .. }
+            | NextLineStart
+            | PreviousLineStart
+            | StartOfLineDownward => false,
+        }
+    }
+
     pub fn infallible(&self) -> bool {
         use Motion::*;
         match self {
@@ -17,6 +17,11 @@ use crate::{
 
 impl Vim {
     pub fn create_mark(&mut self, text: Arc<str>, tail: bool, cx: &mut ViewContext<Self>) {
+        if is_global_mark(&text) {
+            self.create_global_mark(text.to_string(), cx);
+            self.clear_operator(cx);
+            return;
+        }
         let Some(anchors) = self.update_editor(cx, |_, editor, _| {
             editor
                 .selections
@@ -69,6 +74,24 @@ impl Vim {
     pub fn jump(&mut self, text: Arc<str>, line: bool, cx: &mut ViewContext<Self>) {
         self.pop_operator(cx);
 
+        if is_global_mark(&text) {
+            let Some(mark) = self.global_mark(&text, cx) else {
+                return;
+            };
+            if self.active_operator().is_some() {
+                // Operators can only act on a global mark in the current file.
+                if let Some(anchor) = self.file_mark_anchor(&mark, cx) {
+                    self.motion(Motion::Jump { anchor, line }, cx)
+                } else {
+                    self.clear_operator(cx)
+                }
+            } else {
+                self.push_jump(None, cx);
+                self.open_file_mark(mark, line, cx);
+            }
+            return;
+        }
+
         let anchors = match &*text {
             "{" | "}" => self.update_editor(cx, |_, editor, cx| {
                 let (map, selections) = editor.selections.all_display(cx);
@@ -103,6 +126,7 @@ impl Vim {
                 )
             }
         } else {
+            self.push_jump(None, cx);
             self.update_editor(cx, |_, editor, cx| {
                 let map = editor.snapshot(cx);
                 let mut ranges: Vec<Range<Anchor>> = Vec::new();
@@ -127,6 +151,12 @@ impl Vim {
     }
 }
 
+/// Uppercase marks are global: they remember the file as well as the position.
+fn is_global_mark(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|char| char.is_ascii_uppercase()) && chars.next().is_none()
+}
+
 pub fn jump_motion(
     map: &DisplaySnapshot,
     anchor: Anchor,
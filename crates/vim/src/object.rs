@@ -107,6 +107,11 @@ impl Vim {
         match self.mode {
             Mode::Normal => self.normal_object(object, cx),
             Mode::Visual | Mode::VisualLine | Mode::VisualBlock => self.visual_object(object, cx),
+            Mode::HelixNormal => {
+                // Objects extend the selections, as in visual mode, but stay in Helix mode.
+                self.visual_object(object, cx);
+                self.switch_mode(Mode::HelixNormal, true, cx);
+            }
             Mode::Insert | Mode::Replace => {
                 // Shouldn't execute a text object in insert mode. Ignoring
             }
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use db::{define_connection, query, sqlez_macros::sql};
+use workspace::{WorkspaceDb, WorkspaceId};
+
+/// A global mark, as stored in the database: `(name, path, row, column)`.
+pub(crate) type SerializedMark = (String, PathBuf, u32, u32);
+
+/// An entry in the jump list, as stored in the database: `(path, row, column)`.
+pub(crate) type SerializedJump = (PathBuf, u32, u32);
+
+define_connection! {
+    // Current schema shape using pseudo-rust syntax:
+    // vim_global_marks(
+    //   workspace_id: usize,
+    //   mark_name: String,
+    //   path: PathBuf,
+    //   row: u32,
+    //   column: u32,
+    // )
+    // vim_jumps(
+    //   workspace_id: usize,
+    //   jump_index: usize,
+    //   path: PathBuf,
+    //   row: u32,
+    //   column: u32,
+    // )
+    pub static ref DB: VimDb<WorkspaceDb> =
+        &[sql!(
+            CREATE TABLE vim_global_marks (
+                workspace_id INTEGER NOT NULL,
+                mark_name TEXT NOT NULL,
+                path BLOB NOT NULL,
+                row INTEGER NOT NULL,
+                column INTEGER NOT NULL,
+                PRIMARY KEY(workspace_id, mark_name),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
+
+            CREATE TABLE vim_jumps (
+                workspace_id INTEGER NOT NULL,
+                jump_index INTEGER NOT NULL,
+                path BLOB NOT NULL,
+                row INTEGER NOT NULL,
+                column INTEGER NOT NULL,
+                PRIMARY KEY(workspace_id, jump_index),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
+        )];
+}
+
+impl VimDb {
+    query! {
+        pub fn get_global_marks(workspace_id: WorkspaceId) -> Result<Vec<SerializedMark>> {
+            SELECT mark_name, path, row, column
+            FROM vim_global_marks
+            WHERE workspace_id = ?
+        }
+    }
+
+    query! {
+        pub fn get_jumps(workspace_id: WorkspaceId) -> Result<Vec<SerializedJump>> {
+            SELECT path, row, column
+            FROM vim_jumps
+            WHERE workspace_id = ?
+            ORDER BY jump_index
+        }
+    }
+
+    /// Replaces the global marks and jump list stored for the workspace.
+    pub async fn save_marks_and_jumps(
+        &self,
+        workspace_id: WorkspaceId,
+        marks: Vec<SerializedMark>,
+        jumps: Vec<SerializedJump>,
+    ) -> Result<()> {
+        self.write(move |conn| {
+            conn.with_savepoint("save_marks_and_jumps", || {
+                conn.exec_bound(sql!(
+                    DELETE FROM vim_global_marks WHERE workspace_id = ?1;
+                    DELETE FROM vim_jumps WHERE workspace_id = ?1;
+                ))?(workspace_id)?;
+
+                for (name, path, row, column) in marks {
+                    conn.exec_bound(sql!(
+                        INSERT INTO vim_global_marks
+                            (workspace_id, mark_name, path, row, column)
+                        VALUES (?, ?, ?, ?, ?)
+                    ))?((workspace_id, name, path, row, column))?;
+                }
+
+                for (ix, (path, row, column)) in jumps.into_iter().enumerate() {
+                    conn.exec_bound(sql!(
+                        INSERT INTO vim_jumps
+                            (workspace_id, jump_index, path, row, column)
+                        VALUES (?, ?, ?, ?, ?)
+                    ))?((workspace_id, ix as i64, path, row, column))?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[gpui::test]
+    async fn test_save_and_get_marks_and_jumps() {
+        let workspace_id = workspace::WORKSPACE_DB.next_id().await.unwrap();
+
+        let marks = vec![
+            ("A".to_string(), PathBuf::from("/root/a.rs"), 3, 4),
+            ("B".to_string(), PathBuf::from("/root/b.rs"), 0, 0),
+        ];
+        let jumps = vec![
+            (PathBuf::from("/root/b.rs"), 10, 2),
+            (PathBuf::from("/root/a.rs"), 1, 0),
+        ];
+        DB.save_marks_and_jumps(workspace_id, marks.clone(), jumps.clone())
+            .await
+            .unwrap();
+
+        let mut have = DB.get_global_marks(workspace_id).unwrap();
+        have.sort();
+        assert_eq!(have, marks);
+        assert_eq!(DB.get_jumps(workspace_id).unwrap(), jumps);
+
+        // Saving again replaces the previous state.
+        DB.save_marks_and_jumps(workspace_id, marks[..1].to_vec(), Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(DB.get_global_marks(workspace_id).unwrap(), marks[..1]);
+        assert_eq!(DB.get_jumps(workspace_id).unwrap(), Vec::new());
+    }
+}
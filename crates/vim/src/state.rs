@@ -2,6 +2,7 @@ use std::borrow::BorrowMut;
 use std::{fmt::Display, ops::Range, sync::Arc};
 
 use crate::command::command_interceptor;
+use crate::mapping::is_mapping_action;
 use crate::normal::repeat::Replayer;
 use crate::surrounds::SurroundsType;
 use crate::{motion::Motion, object::Object};
@@ -26,6 +27,7 @@ pub enum Mode {
     Visual,
     VisualLine,
     VisualBlock,
+    HelixNormal,
 }
 
 impl Display for Mode {
@@ -37,6 +39,7 @@ impl Display for Mode {
             Mode::Visual => write!(f, "VISUAL"),
             Mode::VisualLine => write!(f, "VISUAL LINE"),
             Mode::VisualBlock => write!(f, "VISUAL BLOCK"),
+            Mode::HelixNormal => write!(f, "HELIX NORMAL"),
         }
     }
 }
@@ -44,7 +47,7 @@ impl Display for Mode {
 impl Mode {
     pub fn is_visual(&self) -> bool {
         match self {
-            Mode::Normal | Mode::Insert | Mode::Replace => false,
+            Mode::Normal | Mode::Insert | Mode::Replace | Mode::HelixNormal => false,
             Mode::Visual | Mode::VisualLine | Mode::VisualBlock => true,
         }
     }
@@ -175,6 +178,10 @@ impl VimGlobals {
             let Some(action) = event.action.as_ref().map(|action| action.boxed_clone()) else {
                 return;
             };
+            // The keystrokes replayed by a mapping are observed individually.
+            if is_mapping_action(&*action) {
+                return;
+            }
             Vim::globals(cx).observe_action(action.boxed_clone())
         })
         .detach();
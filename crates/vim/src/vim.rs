@@ -6,11 +6,15 @@ mod test;
 mod change_list;
 mod command;
 mod digraph;
+mod helix;
 mod insert;
+mod jump_list;
+mod mapping;
 mod mode_indicator;
 mod motion;
 mod normal;
 mod object;
+mod persistence;
 mod replace;
 mod state;
 mod surrounds;
@@ -27,7 +31,10 @@ use gpui::{
     Render, View, ViewContext, WeakView,
 };
 use insert::NormalBefore;
+use jump_list::FileMarks;
 use language::{CursorShape, Point, Selection, SelectionGoal, TransactionId};
+use mapping::VimMappings;
+pub use mapping::{bind_user_mappings, load_vimrc};
 pub use mode_indicator::ModeIndicator;
 use motion::Motion;
 use normal::search::SearchSubmit;
@@ -90,11 +97,15 @@ pub fn init(cx: &mut AppContext) {
     VimModeSetting::register(cx);
     VimSettings::register(cx);
     VimGlobals::register(cx);
+    cx.default_global::<VimMappings>();
+    cx.default_global::<FileMarks>();
 
     cx.observe_new_views(|editor: &mut Editor, cx| Vim::register(editor, cx))
         .detach();
 
-    cx.observe_new_views(|workspace: &mut Workspace, _| {
+    cx.observe_new_views(|workspace: &mut Workspace, cx| {
+        jump_list::register_workspace(workspace, cx);
+
         workspace.register_action(|workspace, _: &ToggleVimMode, cx| {
             let fs = workspace.app_state().fs.clone();
             let currently_enabled = Vim::enabled(cx);
@@ -132,7 +143,7 @@ pub(crate) struct VimAddon {
 
 impl editor::Addon for VimAddon {
     fn extend_key_context(&self, key_context: &mut KeyContext, cx: &AppContext) {
-        self.view.read(cx).extend_key_context(key_context)
+        self.view.read(cx).extend_key_context(key_context, cx)
     }
 
     fn to_any(&self) -> &dyn std::any::Any {
@@ -158,6 +169,10 @@ pub(crate) struct Vim {
     pub(crate) change_list: Vec<Vec<Anchor>>,
     pub(crate) change_list_position: Option<usize>,
 
+    /// Whether a vim command is being run. The commands that are jumps record themselves in
+    /// the jump list, so the jumps the editor records while they run are ignored.
+    running_command: bool,
+
     pub(crate) current_tx: Option<TransactionId>,
     pub(crate) current_anchor: Option<Selection<Anchor>>,
     pub(crate) undo_modes: HashMap<TransactionId, Mode>,
@@ -198,9 +213,15 @@ impl Vim {
             let listener = cx.listener(Vim::observe_keystrokes);
             cx.observe_keystrokes(listener).detach();
 
+            let mode = if VimSettings::get_global(cx).helix_mode {
+                Mode::HelixNormal
+            } else {
+                Mode::Normal
+            };
+
             Vim {
-                mode: Mode::Normal,
-                last_mode: Mode::Normal,
+                mode,
+                last_mode: mode,
                 pre_count: None,
                 post_count: None,
                 operator_stack: Vec::new(),
@@ -210,6 +231,7 @@ impl Vim {
                 stored_visual_mode: None,
                 change_list: Vec::new(),
                 change_list_position: None,
+                running_command: false,
                 current_tx: None,
                 current_anchor: None,
                 undo_modes: HashMap::default(),
@@ -294,6 +316,9 @@ impl Vim {
             object::register(editor, cx);
             visual::register(editor, cx);
             change_list::register(editor, cx);
+            mapping::register(editor, cx);
+            jump_list::register(editor, cx);
+            helix::register(editor, cx);
 
             cx.defer(|vim, cx| {
                 vim.focused(false, cx);
@@ -323,10 +348,25 @@ impl Vim {
         cx: &mut ViewContext<Vim>,
         f: impl Fn(&mut Vim, &A, &mut ViewContext<Vim>) + 'static,
     ) {
-        let subscription = editor.register_action(cx.listener(f));
+        let subscription =
+            editor.register_action(cx.listener(move |vim, action, cx| {
+                vim.run_command(cx, |vim, cx| f(vim, action, cx))
+            }));
         cx.on_release(|_, _, _| drop(subscription)).detach();
     }
 
+    /// Runs a vim command, ignoring the jumps the editor records while it runs.
+    pub(crate) fn run_command(
+        &mut self,
+        cx: &mut ViewContext<Self>,
+        f: impl FnOnce(&mut Self, &mut ViewContext<Self>),
+    ) {
+        self.running_command = true;
+        f(self, cx);
+        // The editor's events are handled before deferred callbacks.
+        cx.defer(|vim, _| vim.running_command = false);
+    }
+
     pub fn editor(&self) -> Option<View<Editor>> {
         self.editor.upgrade()
     }
@@ -386,6 +426,13 @@ impl Vim {
                 self.transaction_undone(transaction_id, cx)
             }
             EditorEvent::Edited { .. } => self.push_to_change_list(cx),
+            // Jumps made by other commands, such as going to a definition or to a symbol in the
+            // outline, are recorded like vim's own. Switching editors is recorded when the
+            // other editor is focused.
+            EditorEvent::PushedToNavHistory {
+                anchor,
+                is_deactivate: false,
+            } if !self.running_command => self.push_jump(Some(*anchor), cx),
             EditorEvent::FocusedIn => self.sync_vim_settings(cx),
             _ => {}
         }
@@ -425,6 +472,12 @@ impl Vim {
     }
 
     pub fn switch_mode(&mut self, mode: Mode, leave_selections: bool, cx: &mut ViewContext<Self>) {
+        // In Helix mode, everything that returns to normal mode returns to Helix's normal mode.
+        let mode = if mode == Mode::Normal && VimSettings::get_global(cx).helix_mode {
+            Mode::HelixNormal
+        } else {
+            mode
+        };
         let last_mode = self.mode;
         let prior_mode = self.last_mode;
         let prior_tx = self.current_tx;
@@ -432,7 +485,7 @@ impl Vim {
         self.mode = mode;
         self.operator_stack.clear();
         self.selected_register.take();
-        if mode == Mode::Normal || mode != last_mode {
+        if matches!(mode, Mode::Normal | Mode::HelixNormal) || mode != last_mode {
             self.current_tx.take();
             self.current_anchor.take();
         }
@@ -503,7 +556,8 @@ impl Vim {
                 }
 
                 s.move_with(|map, selection| {
-                    if last_mode.is_visual() && !mode.is_visual() {
+                    // Helix keeps the selections when leaving select mode.
+                    if last_mode.is_visual() && !mode.is_visual() && mode != Mode::HelixNormal {
                         let mut point = selection.head();
                         if !selection.reversed && !selection.is_empty() {
                             point = movement::left(map, selection.head());
@@ -546,7 +600,9 @@ impl Vim {
                 }
             }
             Mode::Replace => CursorShape::Underscore,
-            Mode::Visual | Mode::VisualLine | Mode::VisualBlock => CursorShape::Block,
+            Mode::Visual | Mode::VisualLine | Mode::VisualBlock | Mode::HelixNormal => {
+                CursorShape::Block
+            }
             Mode::Insert => CursorShape::Bar,
         }
     }
@@ -560,9 +616,12 @@ impl Vim {
                     true
                 }
             }
-            Mode::Normal | Mode::Replace | Mode::Visual | Mode::VisualLine | Mode::VisualBlock => {
-                false
-            }
+            Mode::Normal
+            | Mode::Replace
+            | Mode::Visual
+            | Mode::VisualLine
+            | Mode::VisualBlock
+            | Mode::HelixNormal => false,
         }
     }
 
@@ -572,19 +631,23 @@ impl Vim {
 
     pub fn clip_at_line_ends(&self) -> bool {
         match self.mode {
-            Mode::Insert | Mode::Visual | Mode::VisualLine | Mode::VisualBlock | Mode::Replace => {
-                false
-            }
+            Mode::Insert
+            | Mode::Visual
+            | Mode::VisualLine
+            | Mode::VisualBlock
+            | Mode::Replace
+            | Mode::HelixNormal => false,
             Mode::Normal => true,
         }
     }
 
-    pub fn extend_key_context(&self, context: &mut KeyContext) {
+    pub fn extend_key_context(&self, context: &mut KeyContext, cx: &AppContext) {
         let mut mode = match self.mode {
             Mode::Normal => "normal",
             Mode::Visual | Mode::VisualLine | Mode::VisualBlock => "visual",
             Mode::Insert => "insert",
             Mode::Replace => "replace",
+            Mode::HelixNormal => "helix_normal",
         }
         .to_string();
 
@@ -609,6 +672,12 @@ impl Vim {
         if mode != "waiting" && mode != "insert" && mode != "replace" {
             context.add("VimControl");
         }
+        if cx
+            .try_global::<VimMappings>()
+            .is_some_and(|mappings| mappings.noremap)
+        {
+            context.add("VimNoremap");
+        }
         context.set("vim_mode", mode);
         context.set("vim_operator", operator_id);
     }
@@ -665,6 +734,11 @@ impl Vim {
                 });
             }
         }
+        if let Some(old_vim) = Vim::globals(cx).focused_vim() {
+            if old_vim.entity_id() != cx.view().entity_id() {
+                old_vim.update(cx, |vim, cx| vim.push_jump_on_editor_change(cx));
+            }
+        }
         Vim::globals(cx).focused_vim = Some(cx.view().downgrade());
     }
 
@@ -887,7 +961,7 @@ impl Vim {
                 });
                 self.switch_mode(Mode::Normal, true, cx)
             }
-            Mode::Normal => {
+            Mode::Normal | Mode::HelixNormal => {
                 self.update_editor(cx, |_, editor, cx| {
                     editor.change_selections(None, cx, |s| {
                         s.move_with(|map, selection| {
@@ -1091,6 +1165,7 @@ pub enum UseSystemClipboard {
 
 #[derive(Deserialize)]
 struct VimSettings {
+    pub helix_mode: bool,
     pub toggle_relative_line_numbers: bool,
     pub use_system_clipboard: UseSystemClipboard,
     pub use_multiline_find: bool,
@@ -1100,6 +1175,7 @@ struct VimSettings {
 
 #[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
 struct VimSettingsContent {
+    pub helix_mode: Option<bool>,
     pub toggle_relative_line_numbers: Option<bool>,
     pub use_system_clipboard: Option<UseSystemClipboard>,
     pub use_multiline_find: Option<bool>,
@@ -379,7 +379,7 @@ impl Global for ProjectItemOpeners {}
 
 /// Registers a [ProjectItem] for the app. When opening a file, all the registered
 /// items will get a chance to open the file, starting from the project item that
-/// was added last.
+/// was added last. If an item fails to open the file, the next one is tried.
 pub fn register_project_item<I: ProjectItem>(cx: &mut AppContext) {
     let builders = cx.default_global::<ProjectItemOpeners>();
     builders.push(|project, project_path, cx| {
@@ -2624,14 +2624,21 @@ impl Workspace {
     ) -> Task<Result<(Option<ProjectEntryId>, WorkspaceItemBuilder)>> {
         let project = self.project().clone();
         let project_item_builders = cx.default_global::<ProjectItemOpeners>().clone();
-        let Some(open_project_item) = project_item_builders
-            .iter()
-            .rev()
-            .find_map(|open_project_item| open_project_item(&project, &path, cx))
-        else {
-            return Task::ready(Err(anyhow!("cannot open file {:?}", path.path)));
-        };
-        open_project_item
+        cx.spawn(|mut cx| async move {
+            let mut first_error = None;
+            for open_project_item in project_item_builders.iter().rev() {
+                let Some(task) = cx.update(|cx| open_project_item(&project, &path, cx))? else {
+                    continue;
+                };
+                match task.await {
+                    Ok(opened) => return Ok(opened),
+                    Err(error) => {
+                        first_error.get_or_insert(error);
+                    }
+                }
+            }
+            Err(first_error.unwrap_or_else(|| anyhow!("cannot open file {:?}", path.path)))
+        })
     }
 
     pub fn find_project_item<T>(
@@ -7396,6 +7403,64 @@ mod tests {
             }
         }
 
+        // View
+        struct TestFailingItemView {
+            focus_handle: FocusHandle,
+        }
+        // Model
+        struct TestFailingItem {}
+
+        impl project::Item for TestFailingItem {
+            fn try_open(
+                _project: &Model<Project>,
+                _path: &ProjectPath,
+                _cx: &mut AppContext,
+            ) -> Option<Task<gpui::Result<Model<Self>>>> {
+                Some(Task::ready(Err(anyhow!("failed to open"))))
+            }
+
+            fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
+                None
+            }
+
+            fn project_path(&self, _: &AppContext) -> Option<ProjectPath> {
+                None
+            }
+        }
+
+        impl Item for TestFailingItemView {
+            type Event = ();
+        }
+        impl EventEmitter<()> for TestFailingItemView {}
+        impl FocusableView for TestFailingItemView {
+            fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+                self.focus_handle.clone()
+            }
+        }
+
+        impl Render for TestFailingItemView {
+            fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+                Empty
+            }
+        }
+
+        impl ProjectItem for TestFailingItemView {
+            type Item = TestFailingItem;
+
+            fn for_project_item(
+                _project: Model<Project>,
+                _item: Model<Self::Item>,
+                cx: &mut ViewContext<Self>,
+            ) -> Self
+            where
+                Self: Sized,
+            {
+                Self {
+                    focus_handle: cx.focus_handle(),
+                }
+            }
+        }
+
         #[gpui::test]
         async fn test_register_project_item(cx: &mut TestAppContext) {
             init_test(cx);
@@ -7508,6 +7573,57 @@ mod tests {
                 .await;
             assert!(handle.is_err());
         }
+
+        #[gpui::test]
+        async fn test_register_project_item_falls_back_on_error(cx: &mut TestAppContext) {
+            init_test(cx);
+
+            cx.update(|cx| {
+                register_project_item::<TestPngItemView>(cx);
+                register_project_item::<TestFailingItemView>(cx);
+            });
+
+            let fs = FakeFs::new(cx.executor());
+            fs.insert_tree(
+                "/root1",
+                json!({
+                    "one.png": "BINARYDATAHERE",
+                    "three.txt": "editing text, sure why not?"
+                }),
+            )
+            .await;
+
+            let project = Project::test(fs, ["root1".as_ref()], cx).await;
+            let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+
+            let worktree_id = project.update(cx, |project, cx| {
+                project.worktrees(cx).next().unwrap().read(cx).id()
+            });
+
+            // The item registered last fails to open the file, so the next one is used.
+            let handle = workspace
+                .update(cx, |workspace, cx| {
+                    let project_path = (worktree_id, "one.png");
+                    workspace.open_path(project_path, None, true, cx)
+                })
+                .await
+                .unwrap();
+            assert_eq!(
+                handle.to_any().entity_type(),
+                TypeId::of::<TestPngItemView>()
+            );
+
+            // When no item can open the file, the first error is reported.
+            let error = workspace
+                .update(cx, |workspace, cx| {
+                    let project_path = (worktree_id, "three.txt");
+                    workspace.open_path(project_path, None, true, cx)
+                })
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(error.to_string(), "failed to open");
+        }
     }
 
     pub fn init_test(cx: &mut TestAppContext) {
@@ -16,7 +16,7 @@ use futures::{
     select_biased,
     stream::select,
     task::Poll,
-    FutureExt as _, Stream, StreamExt,
+    FutureExt as _, SinkExt as _, Stream, StreamExt,
 };
 use fuzzy::CharBag;
 use git::{
@@ -49,6 +49,7 @@ use std::{
     ffi::OsStr,
     fmt,
     future::Future,
+    io::{self, Read as _},
     mem,
     ops::{AddAssign, Deref, DerefMut, Sub},
     path::{Path, PathBuf},
@@ -60,7 +61,7 @@ use std::{
     time::{Duration, Instant, SystemTime},
 };
 use sum_tree::{Bias, Edit, SeekTarget, SumTree, TreeMap, TreeSet};
-use text::{LineEnding, Rope};
+use text::{ChunkDecoder, Encoding, LineEnding, Rope};
 use util::{paths::home_dir, ResultExt};
 pub use worktree_settings::WorktreeSettings;
 
@@ -97,9 +98,79 @@ pub enum CreatedEntry {
 pub struct LoadedFile {
     pub file: Arc<File>,
     pub text: String,
+    pub encoding: Encoding,
     pub diff_base: Option<String>,
 }
 
+/// A file whose text is loaded one chunk at a time.
+pub struct LoadedFileChunks {
+    pub file: Arc<File>,
+    /// The file's first chunk of text.
+    pub text: String,
+    pub encoding: Encoding,
+    /// The file's remaining chunks of text, which are read from disk as they're received.
+    pub remaining_chunks: mpsc::Receiver<Result<String>>,
+}
+
+/// Reads and decodes a file's contents one chunk at a time.
+struct TextChunks {
+    reader: Box<dyn io::Read + Send>,
+    decoder: ChunkDecoder,
+    chunk_len: usize,
+    first_chunk: Option<Vec<u8>>,
+    /// Whether the previous chunk ended in a carriage return, which is held back in case the
+    /// next chunk starts with the line feed of the same line ending.
+    carriage_return: bool,
+    done: bool,
+}
+
+impl TextChunks {
+    /// Reads the first chunk of the contents to detect their encoding.
+    fn new(mut reader: Box<dyn io::Read + Send>, chunk_len: usize) -> Result<(Self, Encoding)> {
+        let first_chunk = read_chunk(&mut reader, chunk_len)?;
+        let encoding =
+            Encoding::detect_prefix(&first_chunk).ok_or_else(|| anyhow!("not a text file"))?;
+        let chunks = Self {
+            reader,
+            decoder: encoding.chunk_decoder(),
+            chunk_len,
+            first_chunk: Some(first_chunk),
+            carriage_return: false,
+            done: false,
+        };
+        Ok((chunks, encoding))
+    }
+
+    fn next(&mut self) -> Result<Option<String>> {
+        if self.done {
+            return Ok(None);
+        }
+        let bytes = match self.first_chunk.take() {
+            Some(bytes) => bytes,
+            None => read_chunk(&mut self.reader, self.chunk_len)?,
+        };
+        self.done = bytes.len() < self.chunk_len;
+
+        let mut text = String::new();
+        if mem::take(&mut self.carriage_return) {
+            text.push('\r');
+        }
+        text.push_str(&self.decoder.decode(&bytes, self.done)?);
+        if !self.done && text.ends_with('\r') {
+            text.pop();
+            self.carriage_return = true;
+        }
+        Ok(Some(text))
+    }
+}
+
+/// Reads up to `chunk_len` bytes, only reading fewer at the end of the contents.
+fn read_chunk(reader: &mut impl io::Read, chunk_len: usize) -> io::Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(chunk_len);
+    io::Read::take(reader, chunk_len as u64).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
 pub struct LocalWorktree {
     snapshot: LocalSnapshot,
     scan_requests_tx: channel::Sender<ScanRequest>,
@@ -674,15 +745,30 @@ impl Worktree {
         }
     }
 
+    pub fn load_file_in_chunks(
+        &self,
+        path: &Path,
+        chunk_len: usize,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<LoadedFileChunks>> {
+        match self {
+            Worktree::Local(this) => this.load_file_in_chunks(path, chunk_len, cx),
+            Worktree::Remote(_) => {
+                Task::ready(Err(anyhow!("remote worktrees can't yet load files")))
+            }
+        }
+    }
+
     pub fn write_file(
         &self,
         path: &Path,
         text: Rope,
         line_ending: LineEnding,
+        encoding: Encoding,
         cx: &mut ModelContext<Worktree>,
     ) -> Task<Result<Arc<File>>> {
         match self {
-            Worktree::Local(this) => this.write_file(path, text, line_ending, cx),
+            Worktree::Local(this) => this.write_file(path, text, line_ending, encoding, cx),
             Worktree::Remote(_) => {
                 Task::ready(Err(anyhow!("remote worktree can't yet write files")))
             }
@@ -1245,7 +1331,10 @@ impl LocalWorktree {
 
         cx.spawn(|this, mut cx| async move {
             let abs_path = abs_path?;
-            let text = fs.load(&abs_path).await?;
+            let bytes = fs.load_bytes(&abs_path).await?;
+            let encoding = Encoding::detect(&bytes)
+                .ok_or_else(|| anyhow!("{abs_path:?} is not a text file"))?;
+            let text = encoding.decode(&bytes)?;
             let mut index_task = None;
             let snapshot = this.update(&mut cx, |this, _| this.as_local().unwrap().snapshot())?;
             if let Some(repo) = snapshot.repository_for_path(&path) {
@@ -1269,38 +1358,88 @@ impl LocalWorktree {
             let worktree = this
                 .upgrade()
                 .ok_or_else(|| anyhow!("worktree was dropped"))?;
-            let file = match entry.await? {
-                Some(entry) => File::for_entry(entry, worktree),
-                None => {
-                    let metadata = fs
-                        .metadata(&abs_path)
-                        .await
-                        .with_context(|| {
-                            format!("Loading metadata for excluded file {abs_path:?}")
-                        })?
-                        .with_context(|| {
-                            format!("Excluded file {abs_path:?} got removed during loading")
-                        })?;
-                    Arc::new(File {
-                        entry_id: None,
-                        worktree,
-                        path,
-                        mtime: Some(metadata.mtime),
-                        is_local: true,
-                        is_deleted: false,
-                        is_private,
-                    })
-                }
-            };
+            let file = File::for_loaded_path(
+                entry.await?,
+                worktree,
+                fs.as_ref(),
+                &abs_path,
+                path,
+                is_private,
+            )
+            .await?;
 
             Ok(LoadedFile {
                 file,
                 text,
+                encoding,
                 diff_base,
             })
         })
     }
 
+    /// Loads the file's text one chunk of `chunk_len` bytes at a time, returning once the first
+    /// chunk is read. The rest are read as they're received, so that a large file can be shown
+    /// before all of it is in memory.
+    fn load_file_in_chunks(
+        &self,
+        path: &Path,
+        chunk_len: usize,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<LoadedFileChunks>> {
+        let path = Arc::from(path);
+        let abs_path = self.absolutize(&path);
+        let fs = self.fs.clone();
+        let entry = self.refresh_entry(path.clone(), None, cx);
+        let is_private = self.is_path_private(path.as_ref());
+
+        cx.spawn(|this, cx| async move {
+            let abs_path = abs_path?;
+            let reader = fs.open_sync(&abs_path).await?;
+            let (mut chunks, encoding, text) = cx
+                .background_executor()
+                .spawn(async move {
+                    let (mut chunks, encoding) = TextChunks::new(reader, chunk_len)?;
+                    let text = chunks.next()?.unwrap_or_default();
+                    anyhow::Ok((chunks, encoding, text))
+                })
+                .await
+                .with_context(|| format!("loading {abs_path:?}"))?;
+
+            // Only one chunk is read ahead of the one being received.
+            let (mut chunks_tx, remaining_chunks) = mpsc::channel(0);
+            cx.background_executor()
+                .spawn(async move {
+                    while let Some(chunk) = chunks.next().transpose() {
+                        let is_err = chunk.is_err();
+                        if chunks_tx.send(chunk).await.is_err() || is_err {
+                            break;
+                        }
+                    }
+                })
+                .detach();
+
+            let worktree = this
+                .upgrade()
+                .ok_or_else(|| anyhow!("worktree was dropped"))?;
+            let file = File::for_loaded_path(
+                entry.await?,
+                worktree,
+                fs.as_ref(),
+                &abs_path,
+                path,
+                is_private,
+            )
+            .await?;
+
+            Ok(LoadedFileChunks {
+                file,
+                text,
+                encoding,
+                remaining_chunks,
+            })
+        })
+    }
+
     /// Find the lowest path in the worktree's datastructures that is an ancestor
     fn lowest_ancestor(&self, path: &Path) -> PathBuf {
         let mut lowest_ancestor = None;
@@ -1383,6 +1522,7 @@ impl LocalWorktree {
         path: impl Into<Arc<Path>>,
         text: Rope,
         line_ending: LineEnding,
+        encoding: Encoding,
         cx: &mut ModelContext<Worktree>,
     ) -> Task<Result<Arc<File>>> {
         let path = path.into();
@@ -1395,7 +1535,10 @@ impl LocalWorktree {
         let write = cx.background_executor().spawn({
             let fs = fs.clone();
             let abs_path = abs_path.clone();
-            async move { fs.save(&abs_path, &text, line_ending).await }
+            async move {
+                fs.save_with_encoding(&abs_path, &text, line_ending, encoding)
+                    .await
+            }
         });
 
         cx.spawn(move |this, mut cx| async move {
@@ -3142,6 +3285,14 @@ impl language::LocalFile for File {
         cx.background_executor()
             .spawn(async move { fs.load(&abs_path?).await })
     }
+
+    fn load_bytes(&self, cx: &AppContext) -> Task<Result<Vec<u8>>> {
+        let worktree = self.worktree.read(cx).as_local().unwrap();
+        let abs_path = worktree.absolutize(&self.path);
+        let fs = worktree.fs.clone();
+        cx.background_executor()
+            .spawn(async move { fs.load_bytes(&abs_path?).await })
+    }
 }
 
 impl File {
@@ -3157,6 +3308,35 @@ impl File {
         })
     }
 
+    /// Returns the file that was loaded from the given path, which only has an entry if the
+    /// path isn't excluded from the worktree.
+    async fn for_loaded_path(
+        entry: Option<Entry>,
+        worktree: Model<Worktree>,
+        fs: &dyn Fs,
+        abs_path: &Path,
+        path: Arc<Path>,
+        is_private: bool,
+    ) -> Result<Arc<Self>> {
+        if let Some(entry) = entry {
+            return Ok(Self::for_entry(entry, worktree));
+        }
+        let metadata = fs
+            .metadata(abs_path)
+            .await
+            .with_context(|| format!("Loading metadata for excluded file {abs_path:?}"))?
+            .with_context(|| format!("Excluded file {abs_path:?} got removed during loading"))?;
+        Ok(Arc::new(Self {
+            entry_id: None,
+            worktree,
+            path,
+            mtime: Some(metadata.mtime),
+            is_local: true,
+            is_deleted: false,
+            is_private,
+        }))
+    }
+
     pub fn from_proto(
         proto: rpc::proto::File,
         worktree: Model<Worktree>,
@@ -4,6 +4,7 @@ use crate::{
 };
 use anyhow::Result;
 use fs::{FakeFs, Fs, RealFs, RemoveOptions};
+use futures::StreamExt as _;
 use git::{repository::GitFileStatus, GITIGNORE};
 use gpui::{BorrowAppContext, ModelContext, Task, TestAppContext};
 use parking_lot::Mutex;
@@ -854,6 +855,7 @@ async fn test_write_file(cx: &mut TestAppContext) {
             Path::new("tracked-dir/file.txt"),
             "hello".into(),
             Default::default(),
+            Default::default(),
             cx,
         )
     })
@@ -864,6 +866,7 @@ async fn test_write_file(cx: &mut TestAppContext) {
             Path::new("ignored-dir/file.txt"),
             "world".into(),
             Default::default(),
+            Default::default(),
             cx,
         )
     })
@@ -1732,8 +1735,13 @@ fn randomly_mutate_worktree(
                 })
             } else {
                 log::info!("overwriting file {:?} ({})", entry.path, entry.id.0);
-                let task =
-                    worktree.write_file(entry.path.clone(), "".into(), Default::default(), cx);
+                let task = worktree.write_file(
+                    entry.path.clone(),
+                    "".into(),
+                    Default::default(),
+                    Default::default(),
+                    cx,
+                );
                 cx.background_executor().spawn(async move {
                     task.await?;
                     Ok(())
@@ -2616,6 +2624,40 @@ fn check_worktree_entries(
     }
 }
 
+#[gpui::test]
+async fn test_load_file_in_chunks(cx: &mut TestAppContext) {
+    init_test(cx);
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree("/root", json!({})).await;
+    // The chunks split the "é" between its two bytes, and the "\r\n" between its characters.
+    fs.insert_file("/root/a.txt", "aé\r\nb".as_bytes().to_vec())
+        .await;
+
+    let tree = Worktree::local(
+        Path::new("/root"),
+        true,
+        fs,
+        Default::default(),
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+    let mut loaded = tree
+        .update(cx, |tree, cx| {
+            tree.load_file_in_chunks("a.txt".as_ref(), 2, cx)
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(loaded.encoding, text::Encoding::Utf8);
+    assert_eq!(loaded.file.path.as_ref(), Path::new("a.txt"));
+    let mut chunks = vec![loaded.text];
+    while let Some(chunk) = loaded.remaining_chunks.next().await {
+        chunks.push(chunk.unwrap());
+    }
+    assert_eq!(chunks, ["a", "é", "\r\nb", ""]);
+}
+
 fn init_test(cx: &mut gpui::TestAppContext) {
     if std::env::var("RUST_LOG").is_ok() {
         env_logger::try_init().ok();
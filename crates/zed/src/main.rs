@@ -234,6 +234,8 @@ fn init_ui(
     crate::zed::linux_prompts::init(cx);
 
     app_state.languages.set_theme(cx.theme().clone());
+    // Registered before the editor, so it only opens the files the editor can't.
+    hex_editor::init(cx);
     editor::init(cx);
     image_viewer::init(cx);
     diagnostics::init(cx);
@@ -244,6 +246,7 @@ fn init_ui(
     recent_projects::init(cx);
     go_to_line::init(cx);
     file_finder::init(cx);
+    file_history::init(cx);
     tab_switcher::init(cx);
     dev_server_projects::init(app_state.client.clone(), cx);
     outline::init(cx);
@@ -257,6 +260,8 @@ fn init_ui(
     terminal_view::init(cx);
     journal::init(app_state.clone(), cx);
     language_selector::init(cx);
+    encoding_selector::init(cx);
+    line_ending_selector::init(cx);
     theme_selector::init(cx);
     language_tools::init(cx);
     call::init(app_state.client.clone(), app_state.user_store.clone(), cx);
@@ -399,6 +404,11 @@ fn main() {
         fs.clone(),
         paths::keymap_file().clone(),
     );
+    let user_vimrc_file_rx = watch_config_file(
+        &app.background_executor(),
+        fs.clone(),
+        paths::vimrc_file().clone(),
+    );
 
     let login_shell_env_loaded = if stdout_is_a_pty() {
         Task::ready(())
@@ -446,7 +456,12 @@ fn main() {
 
         settings::init(cx);
         handle_settings_file_changes(user_settings_file_rx, cx, handle_settings_changed);
-        handle_keymap_file_changes(user_keymap_file_rx, cx, handle_keymap_changed);
+        handle_keymap_file_changes(
+            user_keymap_file_rx,
+            user_vimrc_file_rx,
+            cx,
+            handle_keymap_changed,
+        );
 
         client::init_settings(cx);
         let client = Client::production(cx);
@@ -203,6 +203,10 @@ pub fn initialize_workspace(
             activity_indicator::ActivityIndicator::new(workspace, app_state.languages.clone(), cx);
         let active_buffer_language =
             cx.new_view(|_| language_selector::ActiveBufferLanguage::new(workspace));
+        let active_buffer_encoding =
+            cx.new_view(|_| encoding_selector::ActiveBufferEncoding::new(workspace));
+        let active_buffer_line_ending =
+            cx.new_view(|_| line_ending_selector::ActiveBufferLineEnding::new(workspace));
         let vim_mode_indicator = cx.new_view(vim::ModeIndicator::new);
         let cursor_position =
             cx.new_view(|_| go_to_line::cursor_position::CursorPosition::new(workspace));
@@ -210,6 +214,8 @@ pub fn initialize_workspace(
             status_bar.add_left_item(diagnostic_summary, cx);
             status_bar.add_left_item(activity_indicator, cx);
             status_bar.add_right_item(inline_completion_button, cx);
+            status_bar.add_right_item(active_buffer_line_ending, cx);
+            status_bar.add_right_item(active_buffer_encoding, cx);
             status_bar.add_right_item(active_buffer_language, cx);
             status_bar.add_right_item(vim_mode_indicator, cx);
             status_bar.add_right_item(cursor_position, cx);
@@ -761,6 +767,7 @@ fn open_log_file(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
 
 pub fn handle_keymap_file_changes(
     mut user_keymap_file_rx: mpsc::UnboundedReceiver<String>,
+    mut user_vimrc_file_rx: mpsc::UnboundedReceiver<String>,
     cx: &mut AppContext,
     keymap_changed: impl Fn(Option<anyhow::Error>, &mut AppContext) + 'static,
 ) {
@@ -786,23 +793,46 @@ pub fn handle_keymap_file_changes(
 
     cx.spawn(move |cx| async move {
         let mut user_keymap = KeymapFile::default();
+        // The keymap file and the vimrc share a notification, so the errors of both are
+        // kept until the file that caused them is fixed.
+        let mut keymap_error = None;
+        let mut vimrc_error = None;
         loop {
+            let mut errors_changed = false;
             select_biased! {
                 _ = base_keymap_rx.next() => {}
                 user_keymap_content = user_keymap_file_rx.next() => {
                     if let Some(user_keymap_content) = user_keymap_content {
                         match KeymapFile::parse(&user_keymap_content) {
                             Ok(keymap_content) => {
-                                cx.update(|cx| keymap_changed(None, cx)).log_err();
+                                keymap_error = None;
                                 user_keymap = keymap_content;
                             }
-                            Err(error) => {
-                                cx.update(|cx| keymap_changed(Some(error), cx)).log_err();
-                            }
+                            Err(error) => keymap_error = Some(error.to_string()),
                         }
+                        errors_changed = true;
+                    }
+                }
+                user_vimrc_content = user_vimrc_file_rx.next() => {
+                    if let Some(user_vimrc_content) = user_vimrc_content {
+                        vimrc_error = cx
+                            .update(|cx| vim::load_vimrc(&user_vimrc_content, cx))
+                            .ok()
+                            .and_then(|result| result.err())
+                            .map(|error| error.to_string());
+                        errors_changed = true;
                     }
                 }
             }
+            if errors_changed {
+                let errors = keymap_error
+                    .iter()
+                    .chain(&vimrc_error)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let error = (!errors.is_empty()).then(|| anyhow::anyhow!(errors.join("\n")));
+                cx.update(|cx| keymap_changed(error, cx)).log_err();
+            }
             cx.update(|cx| reload_keymaps(cx, &user_keymap)).ok();
         }
     })
@@ -813,6 +843,7 @@ fn reload_keymaps(cx: &mut AppContext, keymap_content: &KeymapFile) {
     cx.clear_key_bindings();
     load_default_keymap(cx);
     keymap_content.clone().add_to_cx(cx).log_err();
+    vim::bind_user_mappings(cx);
     cx.set_menus(app_menus());
     cx.set_dock_menu(vec![MenuItem::action("New Window", workspace::NewWindow)])
 }
@@ -3119,8 +3150,10 @@ mod tests {
                 app_state.fs.clone(),
                 PathBuf::from("/keymap.json"),
             );
+            let vimrc_rx =
+                watch_config_file(&executor, app_state.fs.clone(), PathBuf::from("/vimrc"));
             handle_settings_file_changes(settings_rx, cx, |_, _| {});
-            handle_keymap_file_changes(keymap_rx, cx, |_, _| {});
+            handle_keymap_file_changes(keymap_rx, vimrc_rx, cx, |_, _| {});
         });
         workspace
             .update(cx, |workspace, cx| {
@@ -3258,9 +3291,11 @@ mod tests {
                 app_state.fs.clone(),
                 PathBuf::from("/keymap.json"),
             );
+            let vimrc_rx =
+                watch_config_file(&executor, app_state.fs.clone(), PathBuf::from("/vimrc"));
 
             handle_settings_file_changes(settings_rx, cx, |_, _| {});
-            handle_keymap_file_changes(keymap_rx, cx, |_, _| {});
+            handle_keymap_file_changes(keymap_rx, vimrc_rx, cx, |_, _| {});
         });
 
         cx.background_executor.run_until_parked();